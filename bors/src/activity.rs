@@ -0,0 +1,76 @@
+//! Daily snapshots of a repository's activity, so maintainers can see how it trends over time
+//!
+//! Once an hour the number of open PRs, the length of the merge queue and the number of PRs landed
+//! so far that day are sampled into the repo's store. Each day keeps only its latest sample, so the
+//! snapshot of a past day reflects how it ended.
+
+use github::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of days of snapshots remembered, the oldest are dropped first
+pub const MAX_ACTIVITY_SNAPSHOTS: usize = 90;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Characters of increasing height which a sparkline is drawn with
+const SPARKS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The activity of a repository on one day
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ActivitySnapshot {
+    /// Days since the epoch
+    pub day: u64,
+    pub open_pulls: usize,
+    /// Number of PRs queued or being tested
+    pub queue_length: usize,
+    /// Number of PRs merged during the day
+    pub landed: usize,
+}
+
+impl ActivitySnapshot {
+    /// The day formatted as `YYYY-MM-DD`
+    pub fn date(&self) -> String {
+        DateTime::from_timestamp((self.day * SECONDS_PER_DAY) as i64)
+            .map(|date| date.to_string()[..10].to_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Days since the epoch of `time`
+pub fn day_of(time: DateTime) -> u64 {
+    time.timestamp().max(0) as u64 / SECONDS_PER_DAY
+}
+
+/// Draw `values` as a line of bars scaled to the largest of them
+pub fn sparkline(values: impl IntoIterator<Item = usize>) -> String {
+    let values = values.into_iter().collect::<Vec<_>>();
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .into_iter()
+        .map(|value| SPARKS[(value * (SPARKS.len() - 1)).checked_div(max).unwrap_or(0)])
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sparkline, ActivitySnapshot};
+
+    #[test]
+    fn sparklines() {
+        assert_eq!(sparkline(vec![]), "");
+        assert_eq!(sparkline(vec![0, 0]), "▁▁");
+        assert_eq!(sparkline(vec![0, 7, 14, 3]), "▁▄█▂");
+    }
+
+    #[test]
+    fn snapshot_date() {
+        let snapshot = ActivitySnapshot {
+            day: 100,
+            open_pulls: 3,
+            queue_length: 1,
+            landed: 2,
+        };
+        assert_eq!(snapshot.date(), "1970-04-11");
+    }
+}
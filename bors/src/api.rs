@@ -0,0 +1,401 @@
+//! Types served by bors' JSON API, for use by dashboards and other external clients
+//!
+//! These are kept separate from bors' internal state so that its representation can change without
+//! breaking clients. Within a major `VERSION` fields may be added, but existing ones are never
+//! removed, renamed or given a different meaning. Clients should ignore fields they don't know.
+
+use crate::{
+    activity::ActivitySnapshot,
+    config::RepoConfig,
+    conflicts::ConflictCluster,
+    insights::CiInsights,
+    state::{Priority, PullRequestState, Status},
+};
+use serde::{Deserialize, Serialize};
+
+/// Version of the API, which is part of the path of each of its routes, e.g. `/api/v1/repos`
+pub const VERSION: u32 = 1;
+
+/// Status of a repository and of the pull requests bors tracks for it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RepoStatusDto {
+    pub owner: String,
+    pub name: String,
+    /// Indicates if bors' API token for the repository is valid
+    pub healthy: bool,
+    /// Description of the repository's SLA breach, if it's currently in breach
+    pub sla_breach: Option<String>,
+    /// Queued PRs, in the order they'll be landed. The PR being tested, if any, is first
+    pub queue: Vec<QueueEntryDto>,
+    /// All open PRs, ordered the same way as the queue with PRs in review last
+    pub pulls: Vec<PullSummaryDto>,
+}
+
+/// Position of a PR in a repository's merge queue
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct QueueEntryDto {
+    pub number: u64,
+    /// Position in the queue, starting at 1
+    pub position: usize,
+    pub status: PullStatusDto,
+    pub priority: PriorityDto,
+}
+
+/// Summary of a pull request tracked by bors
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PullSummaryDto {
+    pub number: u64,
+    pub title: String,
+    pub author: Option<String>,
+    pub head_ref: String,
+    pub head_oid: String,
+    pub base_ref: String,
+    pub status: PullStatusDto,
+    pub priority: PriorityDto,
+    pub approved: bool,
+    pub approval_expired: bool,
+    pub mergeable: bool,
+    pub is_draft: bool,
+    /// Queue lane the PR is landed through, if any
+    pub lane: Option<String>,
+    pub labels: Vec<String>,
+    /// Other queued PRs which change some of the same files, if this PR is queued
+    pub overlaps: Vec<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum PullStatusDto {
+    InReview,
+    Queued,
+    Testing,
+    Canary,
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum PriorityDto {
+    High,
+    Normal,
+    Low,
+}
+
+/// How long the checks on a repository's merge commits take and how often they flake, to guide
+/// optimizing its CI
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[non_exhaustive]
+pub struct CiInsightsDto {
+    pub owner: String,
+    pub name: String,
+    /// Number of completed checks the insights are derived from
+    pub total_runs: usize,
+    /// Checks with the longest average duration, slowest first
+    pub slowest: Vec<CheckDurationDto>,
+    /// Checks which failed and then passed on a later attempt to land the same changes, by
+    /// decreasing flake rate
+    pub flakiest: Vec<CheckFlakinessDto>,
+    /// Daily totals over the last 30 days, oldest first. Days without any checks are omitted
+    pub trend: Vec<TrendPointDto>,
+    /// How far the most recently landed PRs were behind their base when they landed
+    pub merge_skew: MergeSkewDto,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CheckDurationDto {
+    pub name: String,
+    pub runs: usize,
+    pub average_seconds: u64,
+    pub max_seconds: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[non_exhaustive]
+pub struct CheckFlakinessDto {
+    pub name: String,
+    pub runs: usize,
+    pub flakes: usize,
+    /// Fraction of the check's runs which were flakes, between 0 and 1
+    pub flake_rate: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TrendPointDto {
+    /// Day the checks completed on, as `YYYY-MM-DD` in UTC
+    pub date: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub average_seconds: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[non_exhaustive]
+pub struct MergeSkewDto {
+    /// Number of landed PRs the skew is derived from
+    pub landed: usize,
+    /// Number of those PRs which were behind their base
+    pub behind: usize,
+    pub average_commits_behind: f64,
+    pub max_commits_behind: usize,
+    /// How long the PRs which were behind had been behind for, since the oldest commit they were
+    /// missing
+    pub average_behind_seconds: u64,
+    pub max_behind_seconds: u64,
+}
+
+/// Clusters of queued PRs which change some of the same files, so that maintainers can decide which
+/// order they should land in
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConflictsDto {
+    pub owner: String,
+    pub name: String,
+    /// Clusters in the order of their first PR in the queue
+    pub clusters: Vec<ConflictClusterDto>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConflictClusterDto {
+    /// PRs linked to each other, directly or through other PRs in the cluster, by changing the
+    /// same files, in queue order
+    pub pulls: Vec<u64>,
+    /// Files changed by more than one of the PRs
+    pub files: Vec<String>,
+}
+
+/// Daily history of a repository's open PRs, queue length and land rate
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ActivityDto {
+    pub owner: String,
+    pub name: String,
+    /// One snapshot for each day bors was running, oldest first
+    pub history: Vec<ActivitySnapshotDto>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ActivitySnapshotDto {
+    /// Day the snapshot was taken on, as `YYYY-MM-DD` in UTC
+    pub date: String,
+    pub open_pulls: usize,
+    /// Number of PRs queued or being tested
+    pub queue_length: usize,
+    /// Number of PRs merged during the day
+    pub landed: usize,
+}
+
+impl ActivityDto {
+    pub(crate) fn new(config: &RepoConfig, activity: &[ActivitySnapshot]) -> Self {
+        Self {
+            owner: config.owner().to_owned(),
+            name: config.name().to_owned(),
+            history: activity
+                .iter()
+                .map(|snapshot| ActivitySnapshotDto {
+                    date: snapshot.date(),
+                    open_pulls: snapshot.open_pulls,
+                    queue_length: snapshot.queue_length,
+                    landed: snapshot.landed,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ConflictsDto {
+    pub(crate) fn new(config: &RepoConfig, clusters: &[ConflictCluster]) -> Self {
+        Self {
+            owner: config.owner().to_owned(),
+            name: config.name().to_owned(),
+            clusters: clusters
+                .iter()
+                .map(|cluster| ConflictClusterDto {
+                    pulls: cluster.pulls.clone(),
+                    files: cluster.files.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl CiInsightsDto {
+    pub(crate) fn new(config: &RepoConfig, insights: &CiInsights) -> Self {
+        Self {
+            owner: config.owner().to_owned(),
+            name: config.name().to_owned(),
+            total_runs: insights.total_runs,
+            slowest: insights
+                .slowest
+                .iter()
+                .map(|check| CheckDurationDto {
+                    name: check.name.clone(),
+                    runs: check.runs,
+                    average_seconds: check.average.as_secs(),
+                    max_seconds: check.max.as_secs(),
+                })
+                .collect(),
+            flakiest: insights
+                .flakiest
+                .iter()
+                .map(|check| CheckFlakinessDto {
+                    name: check.name.clone(),
+                    runs: check.runs,
+                    flakes: check.flakes,
+                    flake_rate: check.rate(),
+                })
+                .collect(),
+            trend: insights
+                .trend
+                .iter()
+                .map(|point| TrendPointDto {
+                    date: point.date(),
+                    runs: point.runs,
+                    failures: point.failures,
+                    average_seconds: point.average.as_secs(),
+                })
+                .collect(),
+            merge_skew: MergeSkewDto {
+                landed: insights.merge_skew.landed,
+                behind: insights.merge_skew.behind,
+                average_commits_behind: insights.merge_skew.average_commits_behind,
+                max_commits_behind: insights.merge_skew.max_commits_behind,
+                average_behind_seconds: insights.merge_skew.average_behind.as_secs(),
+                max_behind_seconds: insights.merge_skew.max_behind.as_secs(),
+            },
+        }
+    }
+}
+
+impl RepoStatusDto {
+    /// Build the status of a repository from its PRs, which must already be in queue order
+    pub(crate) fn new(
+        config: &RepoConfig,
+        healthy: bool,
+        sla_breach: Option<String>,
+        pulls: &[PullRequestState],
+    ) -> Self {
+        let queue = pulls
+            .iter()
+            .filter(|p| p.status.is_queued() || p.status.is_testing())
+            .enumerate()
+            .map(|(i, p)| QueueEntryDto {
+                number: p.number,
+                position: i + 1,
+                status: PullStatusDto::from(&p.status),
+                priority: p.priority(config).into(),
+            })
+            .collect();
+
+        Self {
+            owner: config.owner().to_owned(),
+            name: config.name().to_owned(),
+            healthy,
+            sla_breach,
+            queue,
+            pulls: pulls
+                .iter()
+                .map(|p| {
+                    let mut summary = PullSummaryDto::new(p, config);
+                    if p.status.is_queued() {
+                        summary.overlaps = pulls
+                            .iter()
+                            .filter(|other| {
+                                other.number != p.number
+                                    && other.status.is_queued()
+                                    && p.overlaps(other)
+                            })
+                            .map(|other| other.number)
+                            .collect();
+                    }
+                    summary
+                })
+                .collect(),
+        }
+    }
+}
+
+impl PullSummaryDto {
+    pub(crate) fn new(pr: &PullRequestState, config: &RepoConfig) -> Self {
+        let mut labels = pr.labels.iter().cloned().collect::<Vec<_>>();
+        labels.sort_unstable();
+
+        Self {
+            number: pr.number,
+            title: pr.title.clone(),
+            author: pr.author.clone(),
+            head_ref: pr.head_ref_name.clone(),
+            head_oid: pr.head_ref_oid.to_string(),
+            base_ref: pr.base_ref_name.clone(),
+            status: PullStatusDto::from(&pr.status),
+            priority: pr.priority(config).into(),
+            approved: pr.approved,
+            approval_expired: pr.approval_expired(config),
+            mergeable: pr.mergeable,
+            is_draft: pr.is_draft,
+            lane: pr.lane.clone(),
+            labels,
+            overlaps: Vec::new(),
+        }
+    }
+}
+
+impl From<&Status> for PullStatusDto {
+    fn from(status: &Status) -> Self {
+        match status {
+            Status::InReview => PullStatusDto::InReview,
+            Status::Queued(_) => PullStatusDto::Queued,
+            Status::Testing { .. } => PullStatusDto::Testing,
+            Status::Canary { .. } => PullStatusDto::Canary,
+            Status::Failed { .. } => PullStatusDto::Failed,
+        }
+    }
+}
+
+impl From<Priority> for PriorityDto {
+    fn from(priority: Priority) -> Self {
+        match priority {
+            Priority::High => PriorityDto::High,
+            Priority::Normal => PriorityDto::Normal,
+            Priority::Low => PriorityDto::Low,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serialized_queue_entry() {
+        let entry = QueueEntryDto {
+            number: 7,
+            position: 1,
+            status: PullStatusDto::InReview,
+            priority: PriorityDto::High,
+        };
+
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "number": 7,
+                "position": 1,
+                "status": "in_review",
+                "priority": "high",
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<QueueEntryDto>(json).unwrap(),
+            entry
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Append-only audit log of notable actions taken by, or requested of, bors
+//!
+//! Entries are written as json lines to `state/<owner>/<name>.audit.jsonl`.
+
+use crate::{
+    config::RepoConfig,
+    graphql::GithubClient,
+    store::{relocate_file, repo_state_dir},
+    Result,
+};
+use github::{client::PaginationOptions, DateTime, IssueEventRecord};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime,
+    /// The user responsible for the action
+    pub actor: String,
+    pub action: String,
+    pub pr: Option<u64>,
+    pub details: String,
+}
+
+impl AuditEntry {
+    pub fn new<A: Into<String>, B: Into<String>, D: Into<String>>(
+        actor: A,
+        action: B,
+        pr: Option<u64>,
+        details: D,
+    ) -> Self {
+        Self {
+            timestamp: DateTime::now(),
+            actor: actor.into(),
+            action: action.into(),
+            pr,
+            details: details.into(),
+        }
+    }
+}
+
+/// Indicates if changes of `label` are recorded, as it affects when or whether PRs land, i.e. it's
+/// a priority label or one of the repo's `blocking-labels`
+pub fn is_audited_label(config: &RepoConfig, label: &str) -> bool {
+    label == config.labels().high_priority()
+        || label == config.labels().low_priority()
+        || config.blocking_labels().any(|blocking| blocking == label)
+}
+
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn open(config: &RepoConfig) -> Result<Self> {
+        let path = repo_state_dir(config)?.join(format!("{}.audit.jsonl", config.name()));
+        Ok(Self::open_path(path))
+    }
+
+    /// Append entries to the log at `path`
+    pub(crate) fn open_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Move the log to the location for the repo `config` pertains to, after the repo was renamed
+    /// or transferred
+    pub fn relocate(&mut self, config: &RepoConfig) -> Result<()> {
+        let path = repo_state_dir(config)?.join(format!("{}.audit.jsonl", config.name()));
+        relocate_file(&self.path, &path)?;
+        self.path = path;
+        Ok(())
+    }
+
+    pub fn record(&self, entry: &AuditEntry) -> Result<()> {
+        info!(
+            "audit: {} {} {:?}: {}",
+            entry.actor, entry.action, entry.pr, entry.details
+        );
+
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)?;
+
+        Ok(())
+    }
+
+    /// The entries recorded so far, oldest first
+    pub fn entries(&self) -> Result<Vec<AuditEntry>> {
+        let log = match fs::read_to_string(&self.path) {
+            Ok(log) => log,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        log.lines()
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    /// Replace the log with `entries`, e.g. those of an imported state snapshot
+    pub fn replace(&self, entries: &[AuditEntry]) -> Result<()> {
+        let mut log = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut log, entry)?;
+            log.push(b'\n');
+        }
+
+        let tmp = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp, log)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Record the audited labels added to or removed from PR `number` without bors seeing it
+    /// happen, going from `before` to `after`. Each change is attributed to the user who last made
+    /// it according to the PR's issue events
+    pub async fn record_label_changes(
+        &self,
+        config: &RepoConfig,
+        github: &GithubClient,
+        number: u64,
+        before: &HashSet<String>,
+        after: &HashSet<String>,
+    ) -> Result<()> {
+        let mut changes = after
+            .difference(before)
+            .map(|label| (label, "labeled"))
+            .chain(before.difference(after).map(|label| (label, "unlabeled")))
+            .filter(|(label, _)| is_audited_label(config, label))
+            .collect::<Vec<_>>();
+        if changes.is_empty() {
+            return Ok(());
+        }
+        changes.sort_unstable();
+
+        let events = fetch_issue_events(config, github, number).await?;
+        for (label, kind) in changes {
+            let event = events.iter().rev().find(|event| {
+                event.event == kind
+                    && event
+                        .label
+                        .as_ref()
+                        .is_some_and(|event_label| &event_label.name == label)
+            });
+            let actor = event
+                .and_then(|event| event.actor.as_ref())
+                .map_or("unknown", |actor| actor.login.as_str());
+            let action = if kind == "labeled" {
+                "label-added"
+            } else {
+                "label-removed"
+            };
+
+            let mut entry = AuditEntry::new(actor, action, Some(number), label.as_str());
+            if let Some(event) = event {
+                entry.timestamp = event.created_at;
+            }
+            self.record(&entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+async fn fetch_issue_events(
+    config: &RepoConfig,
+    github: &GithubClient,
+    number: u64,
+) -> Result<Vec<IssueEventRecord>> {
+    let mut events = Vec::new();
+    let mut page = None;
+    loop {
+        let response = github
+            .issues()
+            .list_events(
+                config.owner(),
+                config.name(),
+                number,
+                Some(PaginationOptions {
+                    page,
+                    per_page: Some(100),
+                }),
+            )
+            .await?;
+        page = response.pagination().next_page;
+        events.extend(response.into_inner());
+        if page.is_none() {
+            break;
+        }
+    }
+
+    Ok(events)
+}
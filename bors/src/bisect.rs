@@ -0,0 +1,232 @@
+//! Bisection of a branch's history to find the landed PR which broke it
+//!
+//! A bisection is driven by `git bisect` in the repository's checkout. Each candidate commit is
+//! pushed to the `bisect` branch and marked good or bad based on the results of the repository's
+//! `checks` reported for it. Once the first bad commit is found it's reported, along with the PR
+//! which landed it, on the issue the bisection was requested on.
+
+use crate::{
+    config::RepoConfig,
+    git::{BisectMark, BisectStep, GitBackend},
+    graphql::GithubClient,
+    templates, Result,
+};
+use github::{Conclusion, Oid};
+use log::{info, warn};
+use std::{collections::HashMap, time::Instant};
+
+pub const BISECT_BRANCH: &str = "bisect";
+
+#[derive(Debug)]
+pub struct Bisection {
+    /// The issue the bisection was requested on, where its result is reported
+    issue: u64,
+    requester: String,
+    /// The commit currently being tested and when it was pushed
+    candidate: Option<(Oid, Instant)>,
+    /// Results of the checks reported for the candidate
+    results: HashMap<String, Conclusion>,
+    /// Number of commits tested so far
+    steps: usize,
+}
+
+impl Bisection {
+    pub fn new(issue: u64, requester: &str) -> Self {
+        Self {
+            issue,
+            requester: requester.to_owned(),
+            candidate: None,
+            results: HashMap::new(),
+            steps: 0,
+        }
+    }
+
+    pub fn issue(&self) -> u64 {
+        self.issue
+    }
+
+    /// Start bisecting `good..bad`, returning `false` if the bisection finished immediately, e.g.
+    /// because `bad` is the only candidate
+    pub async fn start(
+        &mut self,
+        good: &Oid,
+        bad: &Oid,
+        config: &RepoConfig,
+        github: &GithubClient,
+        git: &mut dyn GitBackend,
+    ) -> Result<bool> {
+        info!("bisecting {}..{}", good, bad);
+        let step = git.bisect_start(good, bad);
+        self.advance(step, config, github, git).await
+    }
+
+    /// Record the result of a check reported for `oid`
+    pub fn add_build_result(&mut self, oid: &Oid, name: &str, conclusion: Conclusion) {
+        match &self.candidate {
+            Some((candidate, _)) if candidate == oid => {
+                self.results.insert(name.to_owned(), conclusion);
+            }
+            _ => {}
+        }
+    }
+
+    /// Determine the result of testing the candidate, if it's known yet
+    fn mark(&self, config: &RepoConfig) -> Option<BisectMark> {
+        let started = match &self.candidate {
+            Some((_, started)) => started,
+            None => return None,
+        };
+
+        let mut complete = true;
+        for check in config.checks() {
+            match self.results.get(check) {
+                Some(Conclusion::Success | Conclusion::Neutral | Conclusion::Skipped) => {}
+                Some(Conclusion::Failure | Conclusion::TimedOut) => return Some(BisectMark::Bad),
+                // Neither good nor bad, e.g. the build was cancelled
+                Some(Conclusion::Cancelled | Conclusion::ActionRequired) => {
+                    return Some(BisectMark::Skip)
+                }
+                None => complete = false,
+            }
+        }
+
+        if complete {
+            Some(BisectMark::Good)
+        } else if started.elapsed() > config.timeout() {
+            Some(BisectMark::Skip)
+        } else {
+            None
+        }
+    }
+
+    /// Move on to the next step once the candidate's result is known, returning `false` once the
+    /// bisection has finished
+    pub async fn process(
+        &mut self,
+        config: &RepoConfig,
+        github: &GithubClient,
+        git: &mut dyn GitBackend,
+    ) -> Result<bool> {
+        let mark = match self.mark(config) {
+            Some(mark) => mark,
+            None => return Ok(true),
+        };
+        let (candidate, _) = self.candidate.take().unwrap();
+        info!("bisect: marking {} as {:?}", candidate, mark);
+
+        let step = git.bisect_mark(&candidate, mark);
+        self.advance(step, config, github, git).await
+    }
+
+    async fn advance(
+        &mut self,
+        step: Result<BisectStep>,
+        config: &RepoConfig,
+        github: &GithubClient,
+        git: &mut dyn GitBackend,
+    ) -> Result<bool> {
+        let step = step.and_then(|step| {
+            if let BisectStep::Test(oid) = &step {
+                git.push_oid_to_branch(oid, BISECT_BRANCH)?;
+            }
+            Ok(step)
+        });
+
+        let msg = match step {
+            Ok(BisectStep::Test(oid)) => {
+                info!("bisect: testing {}", oid);
+                self.candidate = Some((oid, Instant::now()));
+                self.results.clear();
+                self.steps += 1;
+                return Ok(true);
+            }
+            Ok(BisectStep::Culprit(oid)) => {
+                let pr = github
+                    .repos()
+                    .list_pull_requests_associated_with_commit(
+                        config.owner(),
+                        config.name(),
+                        &oid.to_string(),
+                        Default::default(),
+                    )
+                    .await?
+                    .into_inner()
+                    .into_iter()
+                    .find(|pull| pull.merged_at.is_some())
+                    .map(|pull| pull.number);
+
+                templates::render(
+                    config,
+                    "bisect-culprit",
+                    liquid::object!({
+                        "requester": self.requester,
+                        "commit": oid.to_string(),
+                        "pr": pr,
+                        "steps": self.steps,
+                    }),
+                )?
+            }
+            Ok(BisectStep::Inconclusive(candidates)) => templates::render(
+                config,
+                "bisect-inconclusive",
+                liquid::object!({
+                    "requester": self.requester,
+                    "commits": candidates.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                }),
+            )?,
+            Err(e) => {
+                warn!("bisect failed: {:#}", e);
+                templates::render(
+                    config,
+                    "bisect-failed",
+                    liquid::object!({
+                        "requester": self.requester,
+                        "error": format!("{:#}", e),
+                    }),
+                )?
+            }
+        };
+
+        if let Err(e) = git.bisect_reset() {
+            warn!("unable to reset bisect: {:#}", e);
+        }
+
+        github
+            .issues()
+            .create_comment(config.owner(), config.name(), self.issue, &msg)
+            .await?;
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mark() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+            checks = ["ci", "lint"]
+            "#,
+        )
+        .unwrap();
+        let candidate = Oid::from_str("1".repeat(40));
+
+        let mut bisection = Bisection::new(1, "octocat");
+        assert_eq!(bisection.mark(&config), None);
+
+        bisection.candidate = Some((candidate.clone(), Instant::now()));
+        bisection.add_build_result(&Oid::from_str("2".repeat(40)), "ci", Conclusion::Failure);
+        bisection.add_build_result(&candidate, "ci", Conclusion::Success);
+        assert_eq!(bisection.mark(&config), None);
+
+        bisection.add_build_result(&candidate, "lint", Conclusion::Success);
+        assert_eq!(bisection.mark(&config), Some(BisectMark::Good));
+
+        bisection.add_build_result(&candidate, "lint", Conclusion::Failure);
+        assert_eq!(bisection.mark(&config), Some(BisectMark::Bad));
+    }
+}
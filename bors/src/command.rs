@@ -1,14 +1,23 @@
 //! Defines commands which can be asked to be performed
 
 use crate::{
-    config::RepoConfig,
+    audit::AuditEntry,
+    bisect::{Bisection, BISECT_BRANCH},
+    config::{CanaryHeadChecks, RepoConfig, ReviewExemption},
     event_processor::{ActivePullRequestContext, CommandContext},
+    git::{MergeOutcome, Squash},
     project_board::ProjectBoard,
-    state::{Priority, Status},
-    Result,
+    queue::CANARY_BRANCH,
+    state::{Priority, SquashMode, Status, TestSuiteResult},
+    store::UserPreferences,
+    templates, Result,
 };
-use github::client::NewPullRequest;
-use log::info;
+use github::{
+    client::{NewPullRequest, UpdatePullRequest},
+    Oid,
+};
+use log::{info, warn};
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,6 +27,7 @@ pub struct ParseCommandError;
 #[derive(Debug)]
 pub struct Command {
     cmd: String,
+    info: &'static CommandInfo,
     command_type: CommandType,
 }
 
@@ -27,8 +37,14 @@ enum CommandType {
     Cancel,
     Canary,
     CherryPick(CherryPick),
-    Help,
+    Help(Option<&'static CommandInfo>),
+    Ignore(IgnoreCommand),
     Priority(PriorityCommand),
+    Bisect(BisectCommand),
+    QueueMove(QueueMoveCommand),
+    RequeueFailed(RequeueFailedCommand),
+    Prefs(PrefsCommand),
+    Refresh,
 }
 
 impl CommandType {
@@ -38,13 +54,401 @@ impl CommandType {
             CommandType::Cancel => "Cancel",
             CommandType::Canary => "Canary",
             CommandType::CherryPick(_) => "CherryPick",
-            CommandType::Help => "Help",
+            CommandType::Help(_) => "Help",
+            CommandType::Ignore(i) if i.ignore => "Ignore",
+            CommandType::Ignore(_) => "Unignore",
             CommandType::Priority(_) => "Priority",
+            CommandType::Bisect(_) => "Bisect",
+            CommandType::QueueMove(_) => "QueueMove",
+            CommandType::RequeueFailed(_) => "RequeueFailed",
+            CommandType::Prefs(_) => "Prefs",
+            CommandType::Refresh => "Refresh",
         }
     }
 }
 
+/// An argument to a command, of the form `<key>` or `<key>=<value>`
+type Arg<'a> = (&'a str, Option<&'a str>);
+
+/// Metadata describing a command, used both to parse commands and to generate their help
+/// documentation so that the two never drift apart
+#[derive(Debug)]
+struct CommandInfo {
+    title: &'static str,
+
+    /// Names the command can be invoked by, the first being its canonical name
+    names: &'static [&'static str],
+
+    /// Usage of the command's positional argument, if it takes one
+    positional: Option<&'static str>,
+
+    description: &'static str,
+
+    /// Usage and description of each optional argument
+    options: &'static [(&'static str, &'static str)],
+
+    /// Construct the command from the name it was invoked by and its arguments
+    parse: fn(&str, &mut dyn Iterator<Item = Arg<'_>>) -> Result<CommandType, ParseCommandError>,
+
+    /// Describe the repository's settings which affect the command
+    settings: fn(&RepoConfig) -> Vec<String>,
+}
+
+const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        title: "Land",
+        names: &["land", "merge"],
+        positional: None,
+        description: "attempt to land or merge a PR",
+        options: &[
+            (
+                "priority=<level>",
+                "set the priority level for the PR (`high`, `normal`, `low`), may be shortened to `p=<level>`",
+            ),
+            ("squash+", "squash the PR down to a single commit before landing"),
+            (
+                "squash=<mode>",
+                "squash the PR, keeping the message of its first commit (`keep-first`), concatenating the messages of all of its commits (`concat`) or using its title and description (`template`)",
+            ),
+            ("squash-", "land the PR without squashing it"),
+            ("rebase+", "land the PR by rebasing it onto its base branch"),
+            ("rebase-", "land the PR by creating a merge commit, preserving its original commits"),
+            ("rollup+", "allow the PR to be tested in a batch along with other PRs"),
+            ("rollup-", "always test the PR on its own, e.g. if it's likely to break the build"),
+            (
+                "notify=<users>",
+                "mention the comma-separated users or teams, e.g. `@octocat,@org/team`, once the land completes. Teams are expanded to their members",
+            ),
+            (
+                "resume",
+                "re-test the exact merge commit from the PR's previous land, e.g. after an infrastructure outage, without re-checking approvals",
+            ),
+            (
+                "when-green",
+                "bypass the merge queue, merging the PR directly once the checks on its own head commit pass",
+            ),
+            ("when-green-", "land the PR through the merge queue"),
+            (
+                "hotfix",
+                "land the PR ahead of every other PR, interrupting the PR being tested (admins only)",
+            ),
+            (
+                "base=<branch>",
+                "retarget the PR onto a different base branch, e.g. `base=release-1.x`, before landing it",
+            ),
+        ],
+        parse: |_, args| Ok(CommandType::Land(Land::with_args(args)?)),
+        settings: |config| {
+            let labels = config.labels();
+            let mut settings = vec![
+                if config.require_review() {
+                    "PRs must be approved before they can be queued for landing".to_owned()
+                } else {
+                    "PRs can be queued for landing without being approved".to_owned()
+                },
+                if config.require_checklist() {
+                    "Every item of the checklist in the PR's description must be checked"
+                        .to_owned()
+                } else {
+                    "The checklist in the PR's description isn't enforced".to_owned()
+                },
+                if config.require_linked_issue() {
+                    "The PR's description must link an open issue it closes, e.g. `Fixes #12`"
+                        .to_owned()
+                } else {
+                    "PRs don't need to link an issue".to_owned()
+                },
+                if config.merge_commits() {
+                    "PRs are landed by creating a merge commit, unless landed with `rebase+`"
+                        .to_owned()
+                } else {
+                    format!(
+                        "PRs are landed by rebasing them, unless labeled with `{}`",
+                        labels.merge_commit()
+                    )
+                },
+                format!("PRs labeled with `{}` are squashed", labels.squash()),
+                format!(
+                    "Priority is set with the `{}` and `{}` labels",
+                    labels.high_priority(),
+                    labels.low_priority()
+                ),
+            ];
+            if config.strict_changes_requested() {
+                settings.push(
+                    "Any outstanding \"changes requested\" review blocks PRs from being queued"
+                        .to_owned(),
+                );
+            }
+            if config.block_on_review_requests() {
+                settings.push(
+                    "Any outstanding review request blocks PRs from being queued".to_owned(),
+                );
+            }
+            if let Some(max_age) = config.approval_max_age() {
+                settings.push(format!(
+                    "Approvals expire after {} days",
+                    max_age.as_secs() / (24 * 60 * 60)
+                ));
+            }
+            if config.require_review() {
+                settings.extend(config.review_exemptions().iter().map(ReviewExemption::describe));
+            }
+            settings.push(checks_setting(config.required_checks()));
+            if config.land_when_green() {
+                settings.push(
+                    "`/land` merges PRs directly once their own checks pass, use `/land when-green-` \
+                    to use the merge queue"
+                        .to_owned(),
+                );
+            }
+            settings.push(format!(
+                "Tests run on the {} branch(es) and time out after {} minutes",
+                config
+                    .test_branches()
+                    .map(|b| format!("`{}`", b.name()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                config.timeout().as_secs() / 60
+            ));
+            settings
+        },
+    },
+    CommandInfo {
+        title: "Canary",
+        names: &["canary", "try"],
+        positional: None,
+        description: "canary a PR by performing all checks without merging",
+        options: &[],
+        parse: |_, _| Ok(CommandType::Canary),
+        settings: |config| {
+            vec![
+                format!(
+                    "A canary can also be requested with the `{}` label",
+                    config.labels().canary()
+                ),
+                checks_setting(config.checks()),
+            ]
+        },
+    },
+    CommandInfo {
+        title: "Cancel",
+        names: &["cancel", "stop"],
+        positional: None,
+        description: "stop an in-progress land",
+        options: &[],
+        parse: |_, _| Ok(CommandType::Cancel),
+        settings: |_| Vec::new(),
+    },
+    CommandInfo {
+        title: "Cherry Pick",
+        names: &["cherry-pick", "cherry"],
+        positional: Some("<target>"),
+        description: "cherry-pick a PR into `<target>` branch",
+        options: &[],
+        parse: |_, args| Ok(CommandType::CherryPick(CherryPick::with_args(args)?)),
+        settings: |_| Vec::new(),
+    },
+    CommandInfo {
+        title: "Priority",
+        names: &["priority"],
+        positional: Some("<level>"),
+        description: "set the priority level for a PR (`high`, `normal`, `low`)",
+        options: &[],
+        parse: |_, args| Ok(CommandType::Priority(PriorityCommand::with_args(args)?)),
+        settings: |config| {
+            vec![format!(
+                "Priority is recorded with the `{}` and `{}` labels",
+                config.labels().high_priority(),
+                config.labels().low_priority()
+            )]
+        },
+    },
+    CommandInfo {
+        title: "Ignore",
+        names: &["ignore", "unignore"],
+        positional: Some("@<user>"),
+        description: "(admin only) ignore, or stop ignoring, commands from `<user>`",
+        options: &[],
+        parse: |name, args| {
+            Ok(CommandType::Ignore(IgnoreCommand::with_args(
+                args,
+                name == "ignore",
+            )?))
+        },
+        settings: |config| {
+            let banned = config
+                .banned_users()
+                .map(|u| format!("@{}", u))
+                .collect::<Vec<_>>();
+            if banned.is_empty() {
+                Vec::new()
+            } else {
+                vec![format!(
+                    "Commands from {} are always ignored",
+                    banned.join(", ")
+                )]
+            }
+        },
+    },
+    CommandInfo {
+        title: "Bisect",
+        names: &["bisect"],
+        positional: None,
+        description: "find the commit which broke a branch, by testing the commits landed between \
+            a known good and a known bad commit. Can be used on issues as well as PRs",
+        options: &[
+            ("good=<sha>", "a commit where the checks pass"),
+            ("bad=<sha>", "a later commit on the same branch where the checks fail"),
+        ],
+        parse: |_, args| Ok(CommandType::Bisect(BisectCommand::with_args(args)?)),
+        settings: |config| {
+            vec![
+                format!(
+                    "Candidate commits are pushed to the `{}` branch and time out after {} minutes",
+                    BISECT_BRANCH,
+                    config.timeout().as_secs() / 60
+                ),
+                checks_setting(config.checks()),
+            ]
+        },
+    },
+    CommandInfo {
+        title: "Queue",
+        names: &["queue"],
+        positional: Some("move #<pr> top|bottom|after #<pr>"),
+        description: "move a queued PR to the top or bottom of the merge queue, or right after \
+            another queued PR, regardless of priority. Can be used on issues as well as PRs",
+        options: &[],
+        parse: |_, args| Ok(CommandType::QueueMove(QueueMoveCommand::with_args(args)?)),
+        settings: |_| {
+            vec![
+                "PRs queued after the queue was reordered land after the reordered PRs, in order \
+                of priority"
+                    .to_owned(),
+            ]
+        },
+    },
+    CommandInfo {
+        title: "Requeue failed",
+        names: &["requeue-failed"],
+        positional: None,
+        description: "(admin only) re-queue every PR whose land failed, e.g. after an outage of \
+            CI. Can be used on issues as well as PRs",
+        options: &[(
+            "since=<duration>",
+            "only re-queue PRs which failed within the last `<duration>`, e.g. `30m`, `2h` or `1d`",
+        )],
+        parse: |_, args| Ok(CommandType::RequeueFailed(RequeueFailedCommand::with_args(args)?)),
+        settings: |config| {
+            vec![format!(
+                "PRs are re-queued in the order they failed, keeping their priority, with at most \
+                {} of them waiting in the queue at a time",
+                config.requeue_batch_size()
+            )]
+        },
+    },
+    CommandInfo {
+        title: "Preferences",
+        names: &["prefs", "preferences"],
+        positional: None,
+        description: "set your preferences for this repository, or show them if no options are \
+            given. Can be used on issues as well as PRs",
+        options: &[
+            (
+                "squash=<on|off|default>",
+                "squash, or don't squash, the PRs you land without `squash+` or `squash-`",
+            ),
+            (
+                "notifications=<on|off|default>",
+                "allow, or stop, others mentioning you via `land notify=`",
+            ),
+        ],
+        parse: |_, args| Ok(CommandType::Prefs(PrefsCommand::with_args(args)?)),
+        settings: |_| Vec::new(),
+    },
+    CommandInfo {
+        title: "Refresh",
+        names: &["refresh", "sync"],
+        positional: None,
+        description: "refresh the PR's labels, reviews, head commit and checks from Github, e.g. \
+            if bors missed an update",
+        options: &[],
+        parse: |_, _| Ok(CommandType::Refresh),
+        settings: |_| Vec::new(),
+    },
+    CommandInfo {
+        title: "Help",
+        names: &["help", "h"],
+        positional: Some("[<command>]"),
+        description: "show this help message, or the documentation for `<command>`",
+        options: &[],
+        parse: |_, args| match args.next() {
+            None => Ok(CommandType::Help(None)),
+            Some((name, None)) => CommandInfo::find(name.trim_start_matches('/'))
+                .map(|info| CommandType::Help(Some(info)))
+                .ok_or(ParseCommandError),
+            Some(_) => Err(ParseCommandError),
+        },
+        settings: |_| Vec::new(),
+    },
+];
+
+fn checks_setting<'a>(checks: impl Iterator<Item = &'a str>) -> String {
+    let checks = checks.map(|c| format!("`{}`", c)).collect::<Vec<_>>();
+    if checks.is_empty() {
+        "No checks are required to pass".to_owned()
+    } else {
+        format!("Required checks: {}", checks.join(", "))
+    }
+}
+
+impl CommandInfo {
+    fn find(name: &str) -> Option<&'static Self> {
+        COMMANDS.iter().find(|info| info.names.contains(&name))
+    }
+
+    /// The ways in which the command can be invoked, formatted for markdown
+    fn usage(&self) -> String {
+        self.names
+            .iter()
+            .map(|name| match self.positional {
+                Some(positional) => format!("`{} {}`", name, positional),
+                None => format!("`{}`", name),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
 impl Command {
+    /// A stable hash of the command, used to avoid executing the same command twice
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a, since the fingerprint is persisted it must not change between releases
+        self.cmd
+            .trim()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+                (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+            })
+    }
+
+    /// The canonical name of the command, regardless of the alias it was invoked by
+    pub fn name(&self) -> &'static str {
+        self.info.names[0]
+    }
+
+    /// Indicates if the command can be used on issues, rather than only on PRs
+    pub fn applies_to_issues(&self) -> bool {
+        matches!(
+            self.command_type,
+            CommandType::Bisect(_)
+                | CommandType::QueueMove(_)
+                | CommandType::RequeueFailed(_)
+                | CommandType::Prefs(_)
+        )
+    }
+
     pub fn from_comment(c: &str) -> Option<Result<Self, ParseCommandError>> {
         c.lines()
             .find(|line| line.starts_with('/'))
@@ -66,10 +470,11 @@ impl Command {
             return Err(ParseCommandError);
         }
 
-        let command_type = Self::from_iter(s.split_whitespace().skip(1))?;
+        let (info, command_type) = Self::from_iter(s.split_whitespace().skip(1))?;
 
         Ok(Command {
             cmd: s.to_owned(),
+            info,
             command_type,
         })
     }
@@ -89,28 +494,30 @@ impl Command {
             return Err(ParseCommandError);
         }
 
-        let command_type = Self::from_iter(s[1..].split_whitespace())?;
+        let (info, command_type) = Self::from_iter(s[1..].split_whitespace())?;
 
         Ok(Command {
             cmd: s.to_owned(),
+            info,
             command_type,
         })
     }
 
-    fn from_iter<'a, I>(iter: I) -> Result<CommandType, ParseCommandError>
+    fn from_iter<'a, I>(iter: I) -> Result<(&'static CommandInfo, CommandType), ParseCommandError>
     where
         I: IntoIterator<Item = &'a str>,
     {
         let mut iter = iter.into_iter();
 
-        let command_name = if let Some(name) = iter.next() {
-            name
-        } else {
-            return Err(ParseCommandError);
+        let command_name = match iter.next() {
+            // Allow commands to be namespaced, e.g. `/bors ignore @user`
+            Some("bors") => iter.next().ok_or(ParseCommandError)?,
+            Some(name) => name,
+            None => return Err(ParseCommandError),
         };
 
         // Arguments take the form of `<key>=<value>`
-        let args = iter.map(|arg| {
+        let mut args = iter.map(|arg| {
             if let Some(idx) = arg.find('=') {
                 (&arg[..idx], Some(&arg[idx + 1..]))
             } else {
@@ -118,18 +525,9 @@ impl Command {
             }
         });
 
-        let command_type = match command_name {
-            "land" | "merge" => CommandType::Land(Land::with_args(args)?),
-            "cancel" | "stop" => CommandType::Cancel,
-            "canary" | "try" => CommandType::Canary,
-            "cherry" | "cherry-pick" => CommandType::CherryPick(CherryPick::with_args(args)?),
-            "help" | "h" => CommandType::Help,
-            "priority" => CommandType::Priority(PriorityCommand::with_args(args)?),
-
-            _ => return Err(ParseCommandError),
-        };
-
-        Ok(command_type)
+        let info = CommandInfo::find(command_name).ok_or(ParseCommandError)?;
+        let command_type = (info.parse)(command_name, &mut args)?;
+        Ok((info, command_type))
     }
 
     /// Display help information for Commands, formatted for use in Github comments
@@ -157,15 +555,40 @@ impl Command {
             reason = Some("Not Collaborator");
         }
 
+        // Ignoring users, landing hotfixes and re-queuing failed PRs in bulk is restricted to
+        // repository admins
+        let requires_admin = match &self.command_type {
+            CommandType::Ignore(_) | CommandType::RequeueFailed(_) => true,
+            CommandType::Land(land) => land.hotfix,
+            _ => false,
+        };
+        if is_authorized && requires_admin {
+            let permission = ctx
+                .github()
+                .repos()
+                .get_collaborator_permission_level(
+                    ctx.config().owner(),
+                    ctx.config().name(),
+                    ctx.sender(),
+                )
+                .await?
+                .into_inner();
+
+            if permission != "admin" {
+                is_authorized = false;
+                reason = Some("Not Admin");
+            }
+        }
+
         // Post a comment to Github if there was a reason why the user wasn't authorized
         if !is_authorized {
             if let Some(reason) = reason {
-                ctx.create_pr_comment(&format!(
-                    "@{}: :key: Insufficient privileges: {}",
-                    ctx.sender(),
-                    reason
-                ))
-                .await?;
+                let msg = templates::render(
+                    ctx.config(),
+                    "insufficient-privileges",
+                    liquid::object!({ "sender": ctx.sender(), "reason": reason }),
+                )?;
+                ctx.create_pr_comment(&msg).await?;
             }
         }
 
@@ -176,39 +599,300 @@ impl Command {
         info!("Executing command '{}'", self.command_type.name());
 
         match &self.command_type {
-            CommandType::Land(l) => Self::execute_land(ctx, l.priority(), l.squash).await?,
+            CommandType::Land(l) => Self::execute_land(ctx, l).await?,
             CommandType::Cancel => Self::cancel_land(ctx).await?,
             CommandType::Canary => Self::canary_land(ctx).await?,
             CommandType::CherryPick(c) => Self::cherry_pick(ctx, c.target()).await?,
-            CommandType::Help => {
-                ctx.create_pr_comment(&Help::new(ctx.config(), ctx.project_board()).to_string())
-                    .await?
+            CommandType::Help(topic) => {
+                let help = match topic {
+                    Some(info) => CommandHelp::new(info, ctx.config()).to_string(),
+                    None => Help::new(ctx.config(), ctx.project_board()).to_string(),
+                };
+                let msg =
+                    templates::render(ctx.config(), "help", liquid::object!({ "help": help }))?;
+                ctx.create_pr_comment(&msg).await?
             }
+            CommandType::Ignore(i) => Self::execute_ignore(ctx, i).await?,
             CommandType::Priority(p) => Self::execute_priority(ctx, p.priority()).await?,
+            CommandType::Bisect(b) => Self::execute_bisect(ctx, b).await?,
+            CommandType::QueueMove(m) => Self::execute_queue_move(ctx, m).await?,
+            CommandType::RequeueFailed(r) => Self::execute_requeue_failed(ctx, r).await?,
+            CommandType::Prefs(p) => Self::execute_prefs(ctx, p).await?,
+            CommandType::Refresh => Self::execute_refresh(ctx).await?,
         }
 
         Ok(())
     }
 
-    async fn execute_land(
-        ctx: &mut CommandContext<'_>,
-        priority: Option<Priority>,
-        squash: Option<bool>,
-    ) -> Result<()> {
+    async fn execute_land(ctx: &mut CommandContext<'_>, land: &Land) -> Result<()> {
+        let sender = ctx.sender().to_owned();
+        let preferences = ctx.store().data().preferences(&sender);
+        let mut notify = ctx.expand_teams(&land.notify).await;
+        let data = ctx.store().data();
+        notify.retain(|user| {
+            let wants_notifications = data.preferences(user).wants_notifications();
+            if !wants_notifications {
+                info!("not notifying '{}', who opted out of notifications", user);
+            }
+            wants_notifications
+        });
+
         let mut ctx = if let Some(ctx) = ctx.active_pull_request_context().await {
             ctx
         } else {
             return Ok(());
         };
 
-        if let Some(priority) = priority {
+        let base = land
+            .base
+            .as_deref()
+            .filter(|base| *base != ctx.pr().base_ref_name);
+        // Changing the base of a PR that's being tested would leave the queue testing it against
+        // the old base
+        if let Some(base) = base.filter(|_| !ctx.pr().status.is_in_review()) {
+            let msg = templates::render(
+                ctx.config(),
+                "retarget-while-queued",
+                liquid::object!({ "sender": ctx.sender(), "base": base }),
+            )?;
+            return ctx.create_pr_comment(&msg).await;
+        }
+
+        if let Some(priority) = land.priority() {
             Self::set_priority(&mut ctx, priority).await?;
         }
-        if let Some(squash) = squash {
+        if let Some(squash) = land.squash.or(preferences.squash) {
             Self::set_squash(&mut ctx, squash).await?;
         }
+        if let Some(mode) = land.squash_mode {
+            info!("#{}: set squash mode to {:?}", ctx.pr().number, mode);
+            ctx.pr_mut().squash_mode = mode;
+        }
+        if let Some(rebase) = land.rebase {
+            Self::set_rebase(&mut ctx, rebase).await?;
+        }
+        if let Some(rollup) = land.rollup {
+            Self::set_rollup(&mut ctx, rollup).await?;
+        }
+        if !notify.is_empty() {
+            info!("#{}: notify {:?} on completion", ctx.pr().number, notify);
+            let pr_notify = &mut ctx.pr_mut().notify;
+            for user in notify {
+                if !pr_notify.contains(&user) {
+                    pr_notify.push(user);
+                }
+            }
+        }
+
+        if land.hotfix {
+            info!("pr #{} is a hotfix", ctx.pr().number);
+            ctx.pr_mut().hotfix = true;
+            // The queue picks the hotfix up the next time it's processed
+            if ctx.pr().status.is_queued() {
+                return Ok(());
+            }
+        }
+
+        if land.resume {
+            if let Some(base) = base.filter(|_| ctx.pr().can_resume_land()) {
+                if !Self::retarget(&mut ctx, base).await? {
+                    return Ok(());
+                }
+            }
+            Self::resume_land(&mut ctx).await
+        } else if land.hotfix {
+            Self::mark_pr_ready_to_land(&mut ctx, base, false).await?;
+            // e.g. the PR is missing approvals
+            if !ctx.pr().status.is_queued() {
+                ctx.pr_mut().hotfix = false;
+            }
+            Ok(())
+        } else {
+            let when_green = land
+                .when_green
+                .unwrap_or_else(|| ctx.config().land_when_green());
+            Self::mark_pr_ready_to_land(&mut ctx, base, when_green).await
+        }
+    }
+
+    /// Change the base branch of the PR to `base` so that it can then be landed onto it, returning
+    /// `false` if the PR can't be retargeted
+    async fn retarget(ctx: &mut ActivePullRequestContext<'_>, base: &str) -> Result<bool> {
+        let number = ctx.pr().number;
+        let head_oid = ctx.pr().head_ref_oid.clone();
+        let title = ctx.pr().title.clone();
+        let old_base = ctx.pr().base_ref_name.clone();
+        let merge_commit = ctx.pr().lands_with_merge_commit(ctx.config());
+
+        if ctx.git_repository().fetch_ref(base).is_err() {
+            info!("invalid land base: '{}'", base);
+            let msg = templates::render(
+                ctx.config(),
+                "invalid-land-base",
+                liquid::object!({ "sender": ctx.sender(), "base": base }),
+            )?;
+            ctx.create_pr_comment(&msg).await?;
+            return Ok(false);
+        }
+
+        // Check that the PR applies to the new base the same way the merge queue will apply it
+        let branch = format!("retarget/{}", number);
+        let outcome = if merge_commit {
+            ctx.git_repository()
+                .fetch_and_merge(base, &head_oid, &branch, number, &title)
+        } else {
+            ctx.git_repository()
+                .fetch_and_rebase(base, &head_oid, &branch, number, &Squash::None)
+        };
+        // The branch was only needed for the check
+        ctx.git_repository().delete_branch(&branch)?;
+        let files = match outcome? {
+            MergeOutcome::Merged(_) => None,
+            MergeOutcome::Conflict { files } => Some(files),
+            MergeOutcome::Empty => Some(Vec::new()),
+        };
+        if let Some(files) = files {
+            info!("pr #{} doesn't apply to '{}'", number, base);
+            let msg = templates::render(
+                ctx.config(),
+                "retarget-failed",
+                liquid::object!({
+                    "sender": ctx.sender(),
+                    "base": base,
+                    "files": files,
+                }),
+            )?;
+            ctx.create_pr_comment(&msg).await?;
+            return Ok(false);
+        }
+
+        info!(
+            "retargeting pr #{} from '{}' to '{}'",
+            number, old_base, base
+        );
+        let pull = ctx
+            .github()
+            .pulls()
+            .update(
+                ctx.config().owner(),
+                ctx.config().name(),
+                number,
+                UpdatePullRequest {
+                    base: Some(base.to_owned()),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_inner();
+
+        ctx.audit_log().record(&AuditEntry::new(
+            ctx.sender(),
+            "retarget",
+            Some(number),
+            format!("{} -> {}", old_base, base),
+        ))?;
+
+        // Update the state now so that the `edited` webhook for the new base isn't treated as an
+        // unexpected change
+        let pr = ctx.pr_mut();
+        pr.base_ref_name = pull.base.git_ref;
+        pr.base_ref_oid = pull.base.sha;
+
+        Ok(true)
+    }
+
+    async fn resume_land(ctx: &mut ActivePullRequestContext<'_>) -> Result<()> {
+        info!("attempting to resume land of pr #{}", ctx.pr().number);
+
+        if !ctx.pr().can_resume_land() {
+            let msg = templates::render(
+                ctx.config(),
+                "nothing-to-resume",
+                liquid::object!({ "sender": ctx.sender() }),
+            )?;
+            return ctx.create_pr_comment(&msg).await;
+        }
+
+        // The merge commit already passed the review gates when it was first queued and is
+        // discarded if the PR is updated, so they aren't checked again
+        ctx.audit_log().record(&AuditEntry::new(
+            ctx.sender(),
+            "resume-land",
+            Some(ctx.pr().number),
+            ctx.pr().last_merge_oid.as_ref().unwrap().to_string(),
+        ))?;
+        ctx.pr_mut().resume = true;
+        ctx.update_pr_status(Status::queued()).await?;
+        info!("pr #{} queued to resume landing", ctx.pr().number);
+
+        Ok(())
+    }
+
+    async fn execute_ignore(ctx: &mut CommandContext<'_>, command: &IgnoreCommand) -> Result<()> {
+        let user = command.user().to_owned();
+        info!("set ignore of user '{}' to {}", user, command.ignore);
 
-        Self::mark_pr_ready_to_land(&mut ctx).await
+        ctx.store().update(|data| {
+            if command.ignore {
+                data.ignored_users.insert(user.clone());
+            } else {
+                data.ignored_users.remove(&user);
+            }
+        })?;
+
+        ctx.audit_log().record(&AuditEntry::new(
+            ctx.sender(),
+            if command.ignore {
+                "ignore-user"
+            } else {
+                "unignore-user"
+            },
+            Some(ctx.number()),
+            &user,
+        ))?;
+
+        let msg = templates::render(
+            ctx.config(),
+            if command.ignore {
+                "ignore-user"
+            } else {
+                "unignore-user"
+            },
+            liquid::object!({ "sender": ctx.sender(), "user": user }),
+        )?;
+        ctx.create_pr_comment(&msg).await
+    }
+
+    async fn execute_prefs(ctx: &mut CommandContext<'_>, command: &PrefsCommand) -> Result<()> {
+        let sender = ctx.sender().to_owned();
+
+        if command.squash.is_some() || command.notifications.is_some() {
+            info!("set preferences of user '{}': {:?}", sender, command);
+            ctx.store().update(|data| {
+                let preferences = data.user_preferences.entry(sender.clone()).or_default();
+                if let Some(squash) = command.squash {
+                    preferences.squash = squash;
+                }
+                if let Some(notifications) = command.notifications {
+                    preferences.notifications = notifications;
+                }
+                if *preferences == UserPreferences::default() {
+                    data.user_preferences.remove(&sender);
+                }
+            })?;
+        }
+
+        let preferences = ctx.store().data().preferences(&sender);
+        let msg = templates::render(
+            ctx.config(),
+            "user-preferences",
+            liquid::object!({
+                "sender": sender,
+                "squash": PrefsCommand::describe(preferences.squash),
+                "notifications": PrefsCommand::describe(preferences.notifications),
+            }),
+        )?;
+        ctx.create_pr_comment(&msg).await
     }
 
     async fn execute_priority(ctx: &mut CommandContext<'_>, priority: Priority) -> Result<()> {
@@ -261,23 +945,128 @@ impl Command {
         Ok(())
     }
 
-    async fn mark_pr_ready_to_land(ctx: &mut ActivePullRequestContext<'_>) -> Result<()> {
+    async fn set_rebase(ctx: &mut ActivePullRequestContext<'_>, rebase: bool) -> Result<()> {
+        info!("#{}: set rebase to {}", ctx.pr().number, rebase);
+
+        // Kept on the PR as well as through the label, so that `rebase+` overrides `merge-commits`
+        ctx.pr_mut().rebase = Some(rebase);
+        let label = ctx.config().labels().merge_commit().to_owned();
+
+        if rebase {
+            ctx.remove_label(&label).await?;
+        } else {
+            ctx.set_label(&label).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_rollup(ctx: &mut ActivePullRequestContext<'_>, rollup: bool) -> Result<()> {
+        info!("#{}: set rollup to {}", ctx.pr().number, rollup);
+
+        let label = ctx.config().labels().no_rollup().to_owned();
+
+        if rollup {
+            ctx.remove_label(&label).await?;
+        } else {
+            ctx.set_label(&label).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Queue the PR for landing, or merge it once it's green if `when_green`, retargeting it onto
+    /// `base` first once it's known to be allowed to land
+    async fn mark_pr_ready_to_land(
+        ctx: &mut ActivePullRequestContext<'_>,
+        base: Option<&str>,
+        when_green: bool,
+    ) -> Result<()> {
         info!("attempting to mark pr #{} ReadyToLand", ctx.pr().number);
 
         // Skip marking for land on draft PRs
         if ctx.pr().is_draft() {
-            ctx.create_pr_comment(
-                ":clipboard: Looks like this PR is still in progress, unable to queue for landing",
-            )
-            .await?;
+            let msg = templates::render(
+                ctx.config(),
+                "draft-pr",
+                liquid::object!({ "sender": ctx.sender() }),
+            )?;
+            ctx.create_pr_comment(&msg).await?;
             return Ok(());
         }
 
+        if ctx.config().require_checklist() && !ctx.pr().status.is_queued() {
+            let unchecked = ctx.pr().unchecked_checklist_items();
+            if !unchecked.is_empty() {
+                info!(
+                    "pr #{} has unchecked checklist items, unable to queue for landing",
+                    ctx.pr().number
+                );
+
+                let msg = templates::render(
+                    ctx.config(),
+                    "unchecked-checklist",
+                    liquid::object!({ "sender": ctx.sender(), "items": unchecked }),
+                )?;
+                ctx.create_pr_comment(&msg).await?;
+                return Ok(());
+            }
+        }
+
+        if ctx.config().require_linked_issue() && !ctx.pr().status.is_queued() {
+            let linked = ctx.pr().linked_issues();
+            let mut open = false;
+            let mut invalid = Vec::new();
+            for number in linked {
+                match ctx
+                    .github()
+                    .issues()
+                    .get(ctx.config().owner(), ctx.config().name(), number)
+                    .await
+                {
+                    Ok(issue) => {
+                        let issue = issue.into_inner();
+                        if matches!(issue.state, github::State::Open) && !issue.is_pull_request() {
+                            open = true;
+                        } else {
+                            invalid.push(number);
+                        }
+                    }
+                    // The issue doesn't exist or was deleted
+                    Err(github::client::Error::GithubClientError(status, _))
+                        if status.as_u16() == 404 || status.as_u16() == 410 =>
+                    {
+                        invalid.push(number);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+
+            if !open {
+                info!(
+                    "pr #{} doesn't link an open issue, unable to queue for landing",
+                    ctx.pr().number
+                );
+
+                let msg = templates::render(
+                    ctx.config(),
+                    "missing-linked-issue",
+                    liquid::object!({ "sender": ctx.sender(), "invalid": invalid }),
+                )?;
+                ctx.create_pr_comment(&msg).await?;
+                return Ok(());
+            }
+        }
+
         match ctx.pr().status {
-            Status::InReview => {
+            Status::InReview | Status::Failed { .. } => {
                 // double check the approval on the PR
-                if ctx.config().require_review() && !ctx.pr().approved {
-                    let approved = ctx
+                let blocked = ctx.config().strict_changes_requested()
+                    && !ctx.pr().changes_requested_by().is_empty();
+                if (ctx.config().require_review() && !ctx.pr().has_valid_approval(ctx.config()))
+                    || blocked
+                {
+                    let decision = ctx
                         .github()
                         .get_review_decision(
                             ctx.config().repo().owner(),
@@ -286,40 +1075,150 @@ impl Command {
                         )
                         .await?;
 
-                    ctx.pr_mut().approved = approved;
+                    ctx.pr_mut().approved = decision.approved;
+                    ctx.pr_mut().approved_at = decision.approved_at;
+                    ctx.pr_mut().reviews = decision.reviews;
+                }
+
+                let changes_requested_by = ctx
+                    .pr()
+                    .changes_requested_by()
+                    .into_iter()
+                    .map(ToOwned::to_owned)
+                    .collect::<Vec<_>>();
+                if ctx.config().strict_changes_requested() && !changes_requested_by.is_empty() {
+                    info!(
+                        "pr #{} has outstanding change requests, unable to queue for landing",
+                        ctx.pr().number
+                    );
+
+                    let msg = templates::render(
+                        ctx.config(),
+                        "changes-requested",
+                        liquid::object!({
+                            "sender": ctx.sender(),
+                            "reviewers": changes_requested_by,
+                        }),
+                    )?;
+                    return ctx.create_pr_comment(&msg).await;
+                }
+
+                let review_requests = ctx.pr().review_requests(ctx.config());
+                if ctx.config().block_on_review_requests() && !review_requests.is_empty() {
+                    info!(
+                        "pr #{} has outstanding review requests, unable to queue for landing",
+                        ctx.pr().number
+                    );
+
+                    let msg = templates::render(
+                        ctx.config(),
+                        "review-requested",
+                        liquid::object!({
+                            "sender": ctx.sender(),
+                            "reviewers": review_requests,
+                        }),
+                    )?;
+                    return ctx.create_pr_comment(&msg).await;
                 }
 
-                if ctx.pr().approved || !ctx.config().require_review() {
+                let mut approved =
+                    !ctx.config().require_review() || ctx.pr().has_valid_approval(ctx.config());
+                if !approved {
+                    if let Some(exemption) = Self::review_exemption(ctx).await? {
+                        info!(
+                            "pr #{} is exempted from review by the '{}' policy",
+                            ctx.pr().number,
+                            exemption.name()
+                        );
+                        ctx.audit_log().record(&AuditEntry::new(
+                            ctx.sender(),
+                            "review-exemption",
+                            Some(ctx.pr().number),
+                            exemption.name(),
+                        ))?;
+
+                        let msg = templates::render(
+                            ctx.config(),
+                            "review-exempted",
+                            liquid::object!({
+                                "sender": ctx.sender(),
+                                "policy": exemption.name(),
+                            }),
+                        )?;
+                        ctx.create_pr_comment(&msg).await?;
+                        approved = true;
+                    }
+                }
+                if let Some(base) = base.filter(|_| approved) {
+                    if !Self::retarget(ctx, base).await? {
+                        return Ok(());
+                    }
+                }
+                if approved && when_green {
+                    Self::merge_when_green(ctx).await?;
+                } else if approved {
+                    // The PR lands through the queue instead of being merged directly
+                    ctx.pr_mut().stop_merge_when_green();
                     ctx.update_pr_status(Status::queued()).await?;
                     info!("pr #{} queued for landing", ctx.pr().number);
+
+                    let remaining = ctx.pr().approval_remaining(ctx.config());
+                    if let Some(remaining) = remaining.filter(|_| ctx.config().require_review()) {
+                        let msg = templates::render(
+                            ctx.config(),
+                            "queued",
+                            liquid::object!({
+                                "approval_hours_remaining": remaining.as_secs() / (60 * 60),
+                            }),
+                        )?;
+                        ctx.create_pr_comment(&msg).await?;
+                    }
+                } else if ctx.pr().approval_expired(ctx.config()) {
+                    info!(
+                        "pr #{}'s approval has expired, unable to queue for landing",
+                        ctx.pr().number
+                    );
+
+                    let msg = templates::render(
+                        ctx.config(),
+                        "approval-expired",
+                        liquid::object!({
+                            "sender": ctx.sender(),
+                            "days": ctx.config().approval_max_age().unwrap().as_secs() / (24 * 60 * 60),
+                        }),
+                    )?;
+                    ctx.create_pr_comment(&msg).await?;
                 } else {
                     info!(
                         "pr #{} is missing approvals, unable to queue for landing",
                         ctx.pr().number
                     );
 
-                    let msg = format!(
-                        "@{} :exclamation: This PR is still missing approvals, unable to queue for landing",
-                        ctx.sender(),
-                    );
+                    let msg = templates::render(
+                        ctx.config(),
+                        "missing-approvals",
+                        liquid::object!({ "sender": ctx.sender() }),
+                    )?;
                     ctx.create_pr_comment(&msg).await?;
                 }
             }
             Status::Queued(_) | Status::Testing { .. } => {
                 info!("pr #{} already queued for landing", ctx.pr().number);
 
-                let msg = format!(
-                    "@{} :bulb: This PR is already queued for landing",
-                    ctx.sender(),
-                );
+                let msg = templates::render(
+                    ctx.config(),
+                    "already-queued",
+                    liquid::object!({ "sender": ctx.sender() }),
+                )?;
 
                 ctx.create_pr_comment(&msg).await?;
             }
             Status::Canary { .. } => {
-                let msg = format!(
-                    "@{} :bulb: This PR is currently being canaried, cancel the canary before queuing for landing",
-                    ctx.sender(),
-                );
+                let msg = templates::render(
+                    ctx.config(),
+                    "land-during-canary",
+                    liquid::object!({ "sender": ctx.sender() }),
+                )?;
 
                 ctx.create_pr_comment(&msg).await?;
             }
@@ -328,6 +1227,82 @@ impl Command {
         Ok(())
     }
 
+    /// The first review exemption policy covering the PR's author and changes, if any
+    async fn review_exemption<'a>(
+        ctx: &ActivePullRequestContext<'a>,
+    ) -> Result<Option<&'a ReviewExemption>> {
+        let exemptions = ctx.config().review_exemptions();
+        let author = match &ctx.pr().author {
+            Some(author) if !exemptions.is_empty() => author,
+            _ => return Ok(None),
+        };
+
+        let files = ctx.pr().fetch_files(ctx.config(), ctx.github()).await?;
+        let changed_lines = files.iter().map(|file| file.changes).sum();
+        let mut paths = Vec::new();
+        for file in &files {
+            paths.push(file.filename.as_str());
+            paths.extend(file.previous_filename.as_deref());
+        }
+
+        let mut is_member = None;
+        for exemption in exemptions {
+            if !exemption.covers_change(paths.iter().copied(), changed_lines) {
+                continue;
+            }
+            if exemption.is_author(author) {
+                return Ok(Some(exemption));
+            }
+            if exemption.org_members() {
+                // Membership is checked at most once, and only if a policy needs it
+                if is_member.is_none() {
+                    let response = ctx
+                        .github()
+                        .orgs()
+                        .is_member(ctx.config().owner(), author)
+                        .await?;
+                    is_member = Some(response.into_inner());
+                }
+                if is_member == Some(true) {
+                    return Ok(Some(exemption));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Wait for the checks on the PR's head commit to pass and then merge it directly, without
+    /// going through the merge queue
+    async fn merge_when_green(ctx: &mut ActivePullRequestContext<'_>) -> Result<()> {
+        if ctx.pr().merge_when_green {
+            info!("pr #{} is already waiting for its checks", ctx.pr().number);
+            return Ok(());
+        }
+
+        // Checks may have completed before the land was requested, so seed the results with
+        // those reported so far. The PR only starts waiting once they've been fetched so that a
+        // failure leaves it as it was
+        let results = ctx
+            .pr()
+            .fetch_head_test_results(ctx.config(), ctx.github())
+            .await?;
+
+        info!(
+            "pr #{} will be merged once its checks pass",
+            ctx.pr().number
+        );
+        ctx.pr_mut().merge_when_green = true;
+        ctx.pr_mut().head_test_results.extend(results);
+
+        let msg = templates::render(
+            ctx.config(),
+            "merge-when-green",
+            liquid::object!({ "sender": ctx.sender() }),
+        )?;
+        ctx.create_pr_comment(&msg).await
+    }
+
     async fn cancel_land(ctx: &mut CommandContext<'_>) -> Result<()> {
         let mut ctx = if let Some(ctx) = ctx.active_pull_request_context().await {
             ctx
@@ -337,6 +1312,62 @@ impl Command {
 
         info!("Canceling land of pr #{}", ctx.pr().number);
 
+        // Clean up after tests which were already started so that nothing is left looking like
+        // it's still in progress. The PR is only returned to review once that's done, so a failed
+        // cleanup can be retried by canceling again
+        let (branches, description) = match &ctx.pr().status {
+            Status::Testing { .. } => {
+                let branches = match ctx.pr().lane.as_deref().and_then(|l| ctx.config().lane(l)) {
+                    Some(lane) => vec![lane.test_branch()],
+                    None => ctx
+                        .config()
+                        .test_branches()
+                        .map(|b| b.name().to_owned())
+                        .collect(),
+                };
+                (branches, "Land canceled")
+            }
+            Status::Canary { .. } => (vec![CANARY_BRANCH.to_owned()], "Canary canceled"),
+            Status::InReview | Status::Failed { .. } | Status::Queued(_) => {
+                return ctx.update_pr_status(Status::InReview).await;
+            }
+        };
+
+        let (owner, name) = (ctx.config().owner(), ctx.config().name());
+        ctx.github()
+            .repos()
+            .create_status(
+                owner,
+                name,
+                &ctx.pr().head_ref_oid.to_string(),
+                &github::client::CreateStatusRequest {
+                    state: github::StatusEventState::Error,
+                    target_url: None,
+                    description: Some(description),
+                    context: "bors",
+                },
+            )
+            .await?;
+
+        // The queue moves on to the next PR, if there is one, by pushing over the reset branch
+        for branch in &branches {
+            info!(
+                "Resetting test branch '{}' to {}",
+                branch,
+                ctx.pr().base_ref_oid
+            );
+            ctx.github()
+                .git()
+                .update_ref(
+                    owner,
+                    name,
+                    &format!("heads/{}", branch),
+                    &ctx.pr().base_ref_oid,
+                    true,
+                )
+                .await?;
+        }
+
         ctx.update_pr_status(Status::InReview).await
     }
 
@@ -350,45 +1381,256 @@ impl Command {
         info!("Canarying land of pr #{}", ctx.pr().number);
 
         match ctx.pr().status {
-            Status::InReview => ctx.pr_mut().canary_requested = true,
+            Status::InReview | Status::Failed { .. } => {
+                let (sender, config, github) =
+                    (ctx.sender().to_owned(), ctx.config(), ctx.github());
+                // The canary is landed, so the PR is no longer merged directly
+                ctx.pr_mut().stop_merge_when_green();
+                ctx.pr_mut().request_canary(&sender, config, github).await?;
+
+                let head = TestSuiteResult::head(&ctx.pr().head_test_results, config);
+                if config.canary_head_checks() == CanaryHeadChecks::Passed
+                    && matches!(head, TestSuiteResult::Pending)
+                {
+                    let msg = templates::render(
+                        config,
+                        "canary-waiting-for-checks",
+                        liquid::object!({ "sender": sender }),
+                    )?;
+                    ctx.create_pr_comment(&msg).await?;
+                }
+            }
             Status::Queued(_) | Status::Testing { .. } => {
-                let msg = format!(
-                    "@{} :bulb: This PR is currently queued for landing, cancel first if you want to canary the landing",
-                    ctx.sender(),
-                );
+                let msg = templates::render(
+                    ctx.config(),
+                    "canary-while-queued",
+                    liquid::object!({ "sender": ctx.sender() }),
+                )?;
 
                 ctx.create_pr_comment(&msg).await?;
             }
             Status::Canary { .. } => {
-                ctx.create_pr_comment("This PR is already being canaried")
-                    .await?;
+                let msg = templates::render(
+                    ctx.config(),
+                    "already-canary",
+                    liquid::object!({ "sender": ctx.sender() }),
+                )?;
+                ctx.create_pr_comment(&msg).await?;
             }
         }
 
         Ok(())
     }
 
-    async fn cherry_pick(ctx: &mut CommandContext<'_>, target: &str) -> Result<()> {
-        // Check if target is a valid branch
-        if ctx.git_repository().fetch_ref(target).is_err() {
-            info!("invalid cherry-pick target: '{}'", target);
-            let msg = format!(
-                "@{} :exclamation: '{}' is an invalid branch target for cherry-picking",
-                ctx.sender(),
-                target,
-            );
-            ctx.create_pr_comment(&msg).await?;
-            return Ok(());
+    async fn execute_bisect(ctx: &mut CommandContext<'_>, command: &BisectCommand) -> Result<()> {
+        let reason = if let Some(bisection) = ctx.bisection() {
+            Some(format!(
+                "a bisection is already in progress on #{}",
+                bisection.issue()
+            ))
+        } else if ctx.config().checks().next().is_none() {
+            Some("no checks are configured to test commits with".to_owned())
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            let msg = templates::render(
+                ctx.config(),
+                "invalid-bisect",
+                liquid::object!({ "sender": ctx.sender(), "reason": reason }),
+            )?;
+            return ctx.create_pr_comment(&msg).await;
         }
 
-        // Get Commit range from PR
-        let pull = ctx
-            .github()
-            .pulls()
-            .get(ctx.config().owner(), ctx.config().name(), ctx.number())
-            .await?
-            .into_inner();
-        let base_oid = pull.base.sha;
+        let msg = templates::render(
+            ctx.config(),
+            "bisect-started",
+            liquid::object!({
+                "sender": ctx.sender(),
+                "good": command.good.to_string(),
+                "bad": command.bad.to_string(),
+                "branch": BISECT_BRANCH,
+            }),
+        )?;
+        ctx.create_pr_comment(&msg).await?;
+
+        let (config, github) = (ctx.config(), ctx.github());
+        let mut bisection = Bisection::new(ctx.number(), ctx.sender());
+        if bisection
+            .start(
+                &command.good,
+                &command.bad,
+                config,
+                github,
+                ctx.git_repository(),
+            )
+            .await?
+        {
+            ctx.set_bisection(bisection);
+        }
+
+        Ok(())
+    }
+
+    async fn execute_queue_move(
+        ctx: &mut CommandContext<'_>,
+        command: &QueueMoveCommand,
+    ) -> Result<()> {
+        let mut order = ctx.queued().to_vec();
+        let number = command.number;
+
+        let reason = match command.position {
+            _ if !order.contains(&number) => Some(format!("#{} isn't queued", number)),
+            QueuePosition::After(other) if other == number => {
+                Some("a PR can't be moved after itself".to_owned())
+            }
+            QueuePosition::After(other) if !order.contains(&other) => {
+                Some(format!("#{} isn't queued", other))
+            }
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            let msg = templates::render(
+                ctx.config(),
+                "invalid-queue-move",
+                liquid::object!({ "sender": ctx.sender(), "reason": reason }),
+            )?;
+            return ctx.create_pr_comment(&msg).await;
+        }
+
+        order.retain(|n| *n != number);
+        let index = match command.position {
+            QueuePosition::Top => 0,
+            QueuePosition::Bottom => order.len(),
+            QueuePosition::After(other) => order.iter().position(|n| *n == other).unwrap() + 1,
+        };
+        order.insert(index, number);
+        info!("moving pr #{} to {} in the queue", number, index + 1);
+
+        let total = order.len();
+        ctx.store().update(|data| data.queue_order = order)?;
+        ctx.audit_log().record(&AuditEntry::new(
+            ctx.sender(),
+            "queue-move",
+            Some(number),
+            command.position.to_string(),
+        ))?;
+
+        let msg = templates::render(
+            ctx.config(),
+            "queue-moved",
+            liquid::object!({
+                "sender": ctx.sender(),
+                "number": number,
+                "position": index + 1,
+                "total": total,
+            }),
+        )?;
+        ctx.create_pr_comment(&msg).await
+    }
+
+    async fn execute_requeue_failed(
+        ctx: &mut CommandContext<'_>,
+        command: &RequeueFailedCommand,
+    ) -> Result<()> {
+        let pending = ctx.store().data().requeue_pending.clone();
+        let numbers = ctx
+            .failed()
+            .iter()
+            .filter(|(_, failed_at)| command.since.is_none_or(|s| failed_at.elapsed() <= s))
+            .map(|(number, _)| *number)
+            .filter(|number| !pending.contains(number))
+            .collect::<Vec<_>>();
+
+        if numbers.is_empty() {
+            let msg = templates::render(
+                ctx.config(),
+                "nothing-to-requeue",
+                liquid::object!({ "sender": ctx.sender() }),
+            )?;
+            return ctx.create_pr_comment(&msg).await;
+        }
+
+        info!("re-queuing failed prs {:?}", numbers);
+        ctx.store()
+            .update(|data| data.requeue_pending.extend(&numbers))?;
+        ctx.audit_log().record(&AuditEntry::new(
+            ctx.sender(),
+            "requeue-failed",
+            None,
+            command.to_string(),
+        ))?;
+
+        let msg = templates::render(
+            ctx.config(),
+            "requeue-failed",
+            liquid::object!({
+                "sender": ctx.sender(),
+                "numbers": numbers,
+                "batch_size": ctx.config().requeue_batch_size(),
+            }),
+        )?;
+        ctx.create_pr_comment(&msg).await
+    }
+
+    async fn execute_refresh(ctx: &mut CommandContext<'_>) -> Result<()> {
+        let mut ctx = if let Some(ctx) = ctx.active_pull_request_context().await {
+            ctx
+        } else {
+            return Ok(());
+        };
+
+        info!("refreshing pr #{}", ctx.pr().number);
+        let (config, github, project_board) = (ctx.config(), ctx.github(), ctx.project_board());
+        let labels = ctx.pr().labels.clone();
+        ctx.pr_mut().refresh(config, github, project_board).await?;
+        if let Err(e) = ctx
+            .audit_log()
+            .record_label_changes(
+                ctx.config(),
+                ctx.github(),
+                ctx.pr().number,
+                &labels,
+                &ctx.pr().labels,
+            )
+            .await
+        {
+            warn!(
+                "pr #{}: unable to record label changes: {:#}",
+                ctx.pr().number,
+                e
+            );
+        }
+
+        let msg = templates::render(
+            ctx.config(),
+            "pr-refreshed",
+            liquid::object!({ "sender": ctx.sender() }),
+        )?;
+        ctx.create_pr_comment(&msg).await
+    }
+
+    async fn cherry_pick(ctx: &mut CommandContext<'_>, target: &str) -> Result<()> {
+        // Check if target is a valid branch
+        if ctx.git_repository().fetch_ref(target).is_err() {
+            info!("invalid cherry-pick target: '{}'", target);
+            let msg = templates::render(
+                ctx.config(),
+                "invalid-cherry-pick-target",
+                liquid::object!({ "sender": ctx.sender(), "target": target }),
+            )?;
+            ctx.create_pr_comment(&msg).await?;
+            return Ok(());
+        }
+
+        // Get Commit range from PR
+        let pull = ctx
+            .github()
+            .pulls()
+            .get(ctx.config().owner(), ctx.config().name(), ctx.number())
+            .await?
+            .into_inner();
+        let base_oid = pull.base.sha;
         let head_oid = pull.head.sha;
 
         let branch = format!("pick/{}/{}", ctx.number(), target);
@@ -398,27 +1640,26 @@ impl Command {
             .fetch_and_cherry_pick(target, &branch, &base_oid, &head_oid)?
             .is_none()
         {
-            let msg = format!(
-                "@{} :exclamation: cherry-pick failed, possibly due to conflicts. \
-                You can perform the cherry-pick yourself by running the following commands:\n\
-                ```\n\
-                git fetch {url} {target} {head_oid}\n\
-                git checkout {target}\n\
-                git cherry-pick {base_oid}..{head_oid}\n\
-                ```\n\
-                ",
-                ctx.sender(),
-                url = ctx.config().repo().to_github_https_url(),
-                target = target,
-                head_oid = head_oid,
-                base_oid = base_oid,
-            );
+            let msg = templates::render(
+                ctx.config(),
+                "cherry-pick-failed",
+                liquid::object!({
+                    "sender": ctx.sender(),
+                    "url": ctx.config().repo().to_github_https_url(),
+                    "target": target,
+                    "head_oid": head_oid.to_string(),
+                    "base_oid": base_oid.to_string(),
+                }),
+            )?;
             ctx.create_pr_comment(&msg).await?;
 
             return Ok(());
         }
 
         // Push branch and open pull request
+        if ctx.github().is_read_only() {
+            return Err(github::client::Error::ReadOnly.into());
+        }
         ctx.git_repository().push_branch(&branch)?;
         info!("pushed '{}' branch", branch);
 
@@ -467,12 +1708,15 @@ impl Command {
             .await?
             .into_inner();
 
-        let msg = format!(
-            "@{} :cherries: Opened PR #{} to cherry-pick these changes into {}",
-            ctx.sender(),
-            new_pull.number,
-            target
-        );
+        let msg = templates::render(
+            ctx.config(),
+            "cherry-pick-opened",
+            liquid::object!({
+                "sender": ctx.sender(),
+                "number": new_pull.number,
+                "target": target,
+            }),
+        )?;
         ctx.create_pr_comment(&msg).await?;
 
         Ok(())
@@ -544,6 +1788,22 @@ impl std::fmt::Display for Help<'_> {
             )?;
         }
 
+        if self.config.require_checklist() {
+            writeln!(
+                f,
+                "- Every item of the checklist in a PR's description must be checked before it \
+                can be queued for merging.",
+            )?;
+        }
+
+        if self.config.require_linked_issue() {
+            writeln!(
+                f,
+                "- A PR's description must link an open issue it closes, e.g. with `Fixes #12`, \
+                before it can be queued for merging.",
+            )?;
+        }
+
         if self.config.maintainer_mode() {
             writeln!(
                 f,
@@ -566,27 +1826,15 @@ impl std::fmt::Display for Help<'_> {
         )?;
         writeln!(f, "| Command | Action | Description |")?;
         writeln!(f, "| --- | --- | --- |")?;
-        writeln!(
-            f,
-            "| __Land__ | `land`, `merge` | attempt to land or merge a PR |"
-        )?;
-        writeln!(
-            f,
-            "| __Canary__ | `canary`, `try` | canary a PR by performing all checks without merging |"
-        )?;
-        writeln!(
-            f,
-            "| __Cancel__ | `cancel`, `stop` | stop an in-progress land |"
-        )?;
-        writeln!(
-            f,
-            "| __Cherry Pick__ | `cherry-pick <target>` | cherry-pick a PR into `<target>` branch |"
-        )?;
-        writeln!(
-            f,
-            "| __Priority__ | `priority` | set the priority level for a PR (`high`, `normal`, `low`) |"
-        )?;
-        writeln!(f, "| __Help__ | `help`, `h` | show this help message |")?;
+        for info in COMMANDS {
+            writeln!(
+                f,
+                "| __{}__ | {} | {} |",
+                info.title,
+                info.usage(),
+                info.description
+            )?;
+        }
         writeln!(f)?;
 
         //
@@ -623,7 +1871,29 @@ impl std::fmt::Display for Help<'_> {
             "| ![label: {name}](https://img.shields.io/static/v1?label=&message={name}&color=lightgrey) | {desc} |",
             name = self.config.labels().squash(),
             desc = "Before merging the PR will be squashed down to a single commit, \
-            only retaining the commit message of the first commit in the PR.",
+            only retaining the commit message of the first commit in the PR unless another \
+            message is chosen with `/land squash=<mode>`.",
+        )?;
+        writeln!(
+            f,
+            "| ![label: {name}](https://img.shields.io/static/v1?label=&message={name}&color=lightgrey) | {desc} |",
+            name = self.config.labels().canary(),
+            desc = "Canary the PR, as if `/canary` had been commented. \
+            The label is removed once the canary completes.",
+        )?;
+        writeln!(
+            f,
+            "| ![label: {name}](https://img.shields.io/static/v1?label=&message={name}&color=lightgrey) | {desc} |",
+            name = self.config.labels().merge_commit(),
+            desc = "Instead of rebasing, the PR will be landed by creating a merge commit, \
+            preserving the PR's original commits. Set with `/land rebase-`.",
+        )?;
+        writeln!(
+            f,
+            "| ![label: {name}](https://img.shields.io/static/v1?label=&message={name}&color=lightgrey) | {desc} |",
+            name = self.config.labels().no_rollup(),
+            desc = "The PR is always tested on its own, never in a batch with other PRs \
+            (see `batch-size`). Set with `/land rollup-`.",
         )?;
 
         writeln!(f)?;
@@ -631,10 +1901,65 @@ impl std::fmt::Display for Help<'_> {
     }
 }
 
+/// Focused documentation for a single command, including the repository's settings which
+/// affect it
+struct CommandHelp<'a> {
+    info: &'static CommandInfo,
+    config: &'a RepoConfig,
+}
+
+impl<'a> CommandHelp<'a> {
+    fn new(info: &'static CommandInfo, config: &'a RepoConfig) -> Self {
+        Self { info, config }
+    }
+}
+
+impl std::fmt::Display for CommandHelp<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "### {}", self.info.title)?;
+        writeln!(f, "{}: {}", self.info.usage(), self.info.description)?;
+
+        if !self.info.options.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "| Argument | Description |")?;
+            writeln!(f, "| --- | --- |")?;
+            for (usage, description) in self.info.options {
+                writeln!(f, "| `{}` | {} |", usage, description)?;
+            }
+        }
+
+        let settings = (self.info.settings)(self.config);
+        if !settings.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "#### Settings for this repository")?;
+            for setting in settings {
+                writeln!(f, "- {}", setting)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 struct Land {
     priority: Option<PriorityCommand>,
     squash: Option<bool>,
+    /// How the PR's commits are combined when it's squashed
+    squash_mode: Option<SquashMode>,
+    rebase: Option<bool>,
+    /// Whether the PR may be tested in a batch with other PRs
+    rollup: Option<bool>,
+    /// Users and teams to mention once the land completes
+    notify: Vec<String>,
+    /// Re-test the merge commit from the PR's previous land
+    resume: bool,
+    /// Merge the PR directly once its own checks pass instead of using the merge queue
+    when_green: Option<bool>,
+    /// Land the PR ahead of the rest of the queue, preempting the PR being tested
+    hotfix: bool,
+    /// Retarget the PR onto this base branch before landing it
+    base: Option<String>,
 }
 
 impl Land {
@@ -644,6 +1969,14 @@ impl Land {
     {
         let mut priority = None;
         let mut squash = None;
+        let mut squash_mode = None;
+        let mut rebase = None;
+        let mut rollup = None;
+        let mut notify = Vec::new();
+        let mut resume = false;
+        let mut when_green = None;
+        let mut hotfix = false;
+        let mut base = None;
 
         for (key, value) in iter {
             match key {
@@ -653,16 +1986,68 @@ impl Land {
                 "squash+" => {
                     squash = Some(true);
                 }
+                "squash" => {
+                    let mode = value.ok_or(ParseCommandError)?;
+                    squash = Some(true);
+                    squash_mode = Some(mode.parse().map_err(|_| ParseCommandError)?);
+                }
                 "squash-" => {
                     squash = Some(false);
                 }
+                "rebase+" => {
+                    rebase = Some(true);
+                }
+                "rebase-" => {
+                    rebase = Some(false);
+                }
+                "rollup+" => {
+                    rollup = Some(true);
+                }
+                "rollup-" => {
+                    rollup = Some(false);
+                }
+                "notify" => {
+                    for user in value.ok_or(ParseCommandError)?.split(',') {
+                        let user = user.trim().trim_start_matches('@');
+                        if user.is_empty() {
+                            return Err(ParseCommandError);
+                        }
+                        notify.push(user.to_owned());
+                    }
+                }
+                "resume" => {
+                    resume = true;
+                }
+                "when-green" | "when-green+" => {
+                    when_green = Some(true);
+                }
+                "when-green-" => {
+                    when_green = Some(false);
+                }
+                "hotfix" => {
+                    hotfix = true;
+                }
+                "base" => {
+                    base = Some(value.ok_or(ParseCommandError)?.to_owned());
+                }
 
                 // First key we hit that we don't understand we should just bail
                 _ => break,
             }
         }
 
-        Ok(Self { priority, squash })
+        Ok(Self {
+            priority,
+            squash,
+            squash_mode,
+            rebase,
+            rollup,
+            notify,
+            resume,
+            when_green,
+            hotfix,
+            base,
+        })
     }
 
     fn priority(&self) -> Option<Priority> {
@@ -706,6 +2091,218 @@ impl PriorityCommand {
     }
 }
 
+#[derive(Debug)]
+struct IgnoreCommand {
+    user: String,
+    ignore: bool,
+}
+
+impl IgnoreCommand {
+    fn with_args<'a, I>(iter: I, ignore: bool) -> Result<Self, ParseCommandError>
+    where
+        I: IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    {
+        let mut iter = iter.into_iter();
+
+        let user = match iter.next() {
+            Some((user, None)) => user.trim_start_matches('@'),
+            _ => return Err(ParseCommandError),
+        };
+
+        if user.is_empty() {
+            return Err(ParseCommandError);
+        }
+
+        Ok(Self {
+            user: user.to_owned(),
+            ignore,
+        })
+    }
+
+    fn user(&self) -> &str {
+        &self.user
+    }
+}
+
+#[derive(Debug)]
+struct BisectCommand {
+    good: Oid,
+    bad: Oid,
+}
+
+impl BisectCommand {
+    fn with_args<'a, I>(iter: I) -> Result<Self, ParseCommandError>
+    where
+        I: IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    {
+        let mut good = None;
+        let mut bad = None;
+
+        for (key, value) in iter {
+            let value = value.filter(|v| !v.is_empty()).ok_or(ParseCommandError)?;
+            match key {
+                "good" => good = Some(Oid::from_str(value)),
+                "bad" => bad = Some(Oid::from_str(value)),
+                _ => return Err(ParseCommandError),
+            }
+        }
+
+        match (good, bad) {
+            (Some(good), Some(bad)) => Ok(Self { good, bad }),
+            _ => Err(ParseCommandError),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum QueuePosition {
+    Top,
+    Bottom,
+    After(u64),
+}
+
+impl std::fmt::Display for QueuePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueuePosition::Top => f.write_str("top"),
+            QueuePosition::Bottom => f.write_str("bottom"),
+            QueuePosition::After(number) => write!(f, "after #{}", number),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct QueueMoveCommand {
+    number: u64,
+    position: QueuePosition,
+}
+
+impl QueueMoveCommand {
+    fn with_args<'a, I>(iter: I) -> Result<Self, ParseCommandError>
+    where
+        I: IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    {
+        let mut iter = iter.into_iter();
+        let mut next = || match iter.next() {
+            Some((arg, None)) => Ok(arg),
+            _ => Err(ParseCommandError),
+        };
+        let pr_number = |arg: &str| {
+            arg.strip_prefix('#')
+                .and_then(|n| n.parse().ok())
+                .ok_or(ParseCommandError)
+        };
+
+        if next()? != "move" {
+            return Err(ParseCommandError);
+        }
+        let number = pr_number(next()?)?;
+        let position = match next()? {
+            "top" => QueuePosition::Top,
+            "bottom" => QueuePosition::Bottom,
+            "after" => QueuePosition::After(pr_number(next()?)?),
+            _ => return Err(ParseCommandError),
+        };
+        if next().is_ok() {
+            return Err(ParseCommandError);
+        }
+
+        Ok(Self { number, position })
+    }
+}
+
+#[derive(Debug)]
+struct RequeueFailedCommand {
+    /// Only PRs which failed within this long are re-queued, or every failed PR if `None`
+    since: Option<Duration>,
+}
+
+impl RequeueFailedCommand {
+    fn with_args<'a, I>(iter: I) -> Result<Self, ParseCommandError>
+    where
+        I: IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    {
+        let mut since = None;
+
+        for (key, value) in iter {
+            match (key, value) {
+                ("since", Some(value)) => since = Some(Self::parse_duration(value)?),
+                _ => return Err(ParseCommandError),
+            }
+        }
+
+        Ok(Self { since })
+    }
+
+    /// Parse a duration of the form `<number><unit>`, where the unit is `m`, `h` or `d`
+    fn parse_duration(s: &str) -> Result<Duration, ParseCommandError> {
+        let unit = match s.chars().last() {
+            Some('m') => 60,
+            Some('h') => 60 * 60,
+            Some('d') => 24 * 60 * 60,
+            _ => return Err(ParseCommandError),
+        };
+        let count = s[..s.len() - 1]
+            .parse::<u64>()
+            .map_err(|_| ParseCommandError)?;
+        let secs = count.checked_mul(unit).ok_or(ParseCommandError)?;
+        Ok(Duration::from_secs(secs))
+    }
+}
+
+impl std::fmt::Display for RequeueFailedCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.since {
+            Some(since) => write!(f, "failed within the last {} minutes", since.as_secs() / 60),
+            None => f.write_str("all failed"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PrefsCommand {
+    /// New value of each preference, where `Some(None)` restores its default
+    squash: Option<Option<bool>>,
+    notifications: Option<Option<bool>>,
+}
+
+impl PrefsCommand {
+    fn with_args<'a, I>(iter: I) -> Result<Self, ParseCommandError>
+    where
+        I: IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    {
+        let mut squash = None;
+        let mut notifications = None;
+
+        for (key, value) in iter {
+            let value = match value.ok_or(ParseCommandError)? {
+                "on" => Some(true),
+                "off" => Some(false),
+                "default" => None,
+                _ => return Err(ParseCommandError),
+            };
+            match key {
+                "squash" => squash = Some(value),
+                "notifications" => notifications = Some(value),
+                _ => return Err(ParseCommandError),
+            }
+        }
+
+        Ok(Self {
+            squash,
+            notifications,
+        })
+    }
+
+    fn describe(preference: Option<bool>) -> &'static str {
+        match preference {
+            Some(true) => "on",
+            Some(false) => "off",
+            None => "default",
+        }
+    }
+}
+
 #[derive(Debug)]
 struct CherryPick {
     target: String,
@@ -741,3 +2338,655 @@ impl CherryPick {
         &self.target
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Command;
+    use crate::{
+        event_processor::EventProcessor,
+        git::{
+            mock::{GitOperation, MockGitBackend},
+            MergeOutcome,
+        },
+        graphql::{fake::FakeGithub, is_read_only, GithubClient},
+        queue::CANARY_BRANCH,
+        state::{PullRequestState, Status},
+    };
+    use github::{
+        client::ReadOnlySwitch,
+        test_fixtures::{EventPayload, PullRequestEventBuilder},
+        DateTime, Oid,
+    };
+    use hyper::{Method, StatusCode};
+    use serde_json::json;
+    use std::time::{Duration, SystemTime};
+
+    /// An `EventProcessor` for `bors-rs/bors` with PR #1 open
+    fn processor(test: &str, github: &FakeGithub, git: &MockGitBackend) -> EventProcessor {
+        processor_with_config(test, "", github, git)
+    }
+
+    /// An `EventProcessor` for `bors-rs/bors`, with the additional configuration `extra`, with PR
+    /// #1 open
+    fn processor_with_config(
+        test: &str,
+        extra: &str,
+        github: &FakeGithub,
+        git: &MockGitBackend,
+    ) -> EventProcessor {
+        processor_with_client(test, extra, github.client(), github, git)
+    }
+
+    /// An `EventProcessor` like `processor_with_config` which talks to `github` through `client`
+    fn processor_with_client(
+        test: &str,
+        extra: &str,
+        client: GithubClient,
+        github: &FakeGithub,
+        git: &MockGitBackend,
+    ) -> EventProcessor {
+        let config =
+            toml::from_str(&format!("owner = \"bors-rs\"\nname = \"bors\"\n{}", extra)).unwrap();
+        let dir =
+            std::env::temp_dir().join(format!("bors-command-test-{}-{}", test, std::process::id()));
+        let mut processor = EventProcessor::for_test(config, client, git.clone(), &dir);
+
+        let pull = PullRequestEventBuilder::new("opened").number(1).build();
+        processor
+            .pulls_mut()
+            .insert(PullRequestState::from_pull_request(&pull.pull_request));
+        github.respond(
+            Method::GET,
+            "repos/bors-rs/bors/pulls/1",
+            StatusCode::OK,
+            PullRequestEventBuilder::new("opened").number(1).payload()["pull_request"].clone(),
+        );
+
+        processor
+    }
+
+    async fn try_execute(processor: &mut EventProcessor, comment: &str) -> crate::Result<()> {
+        let command = Command::from_comment(comment).unwrap().unwrap();
+        let mut ctx = processor.command_context("alice", 1);
+        command.execute(&mut ctx).await
+    }
+
+    async fn execute(processor: &mut EventProcessor, comment: &str) {
+        try_execute(processor, comment).await.unwrap();
+    }
+
+    /// Respond to requests for the checks on PR #1's head with `check_runs`
+    fn respond_head_checks(github: &FakeGithub, check_runs: serde_json::Value) {
+        let head = PullRequestEventBuilder::new("opened").payload()["pull_request"]["head"]["sha"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        let total_count = check_runs.as_array().unwrap().len();
+        github.respond(
+            Method::GET,
+            &format!("repos/bors-rs/bors/commits/{}/check-runs", head),
+            StatusCode::OK,
+            json!({ "total_count": total_count, "check_runs": check_runs }),
+        );
+        github.respond(
+            Method::GET,
+            &format!("repos/bors-rs/bors/commits/{}/status", head),
+            StatusCode::OK,
+            json!({
+                "state": "pending",
+                "name": "bors",
+                "sha": head,
+                "total_count": 0,
+                "statuses": [],
+                "commit_url": "",
+                "repository_url": "",
+            }),
+        );
+    }
+
+    fn is_push(operation: &GitOperation) -> bool {
+        matches!(
+            operation,
+            GitOperation::PushBranch(_)
+                | GitOperation::PushOid { .. }
+                | GitOperation::PushToRemote { .. }
+        )
+    }
+
+    #[tokio::test]
+    async fn cherry_pick() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("cherry-pick", &github, &git);
+        git.set_ref("release", Oid::from_str("e".repeat(40)));
+        github.respond(
+            Method::POST,
+            "repos/bors-rs/bors/pulls",
+            StatusCode::CREATED,
+            PullRequestEventBuilder::new("opened").number(3).payload()["pull_request"].clone(),
+        );
+
+        execute(&mut processor, "/cherry-pick release").await;
+
+        let pull = &processor.pulls_mut()[&1];
+        let (base_oid, head_oid) = (pull.base_ref_oid.clone(), pull.head_ref_oid.clone());
+        assert_eq!(
+            git.operations(),
+            [
+                GitOperation::FetchRef("release".into()),
+                GitOperation::CherryPick {
+                    target_ref: "release".into(),
+                    branch: "pick/1/release".into(),
+                    base_oid,
+                    head_oid,
+                },
+                GitOperation::PushBranch("pick/1/release".into()),
+            ]
+        );
+        let opened = github.bodies(Method::POST, "repos/bors-rs/bors/pulls");
+        assert_eq!(opened.len(), 1);
+        assert_eq!(opened[0]["head"], "pick/1/release");
+        assert_eq!(opened[0]["base"], "release");
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0].contains("Opened PR #3"), "{}", comments[0]);
+    }
+
+    #[tokio::test]
+    async fn cherry_pick_failures() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("cherry-pick-failures", &github, &git);
+
+        // The target branch doesn't exist
+        execute(&mut processor, "/cherry-pick release").await;
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert!(
+            comments[0].contains("invalid branch target"),
+            "{}",
+            comments[0]
+        );
+
+        // The PR doesn't apply to the target branch
+        git.set_ref("release", Oid::from_str("e".repeat(40)));
+        git.push_outcome(MergeOutcome::Conflict { files: Vec::new() });
+        execute(&mut processor, "/cherry-pick release").await;
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert!(
+            comments[1].contains("cherry-pick failed"),
+            "{}",
+            comments[1]
+        );
+
+        assert!(!git.operations().iter().any(is_push));
+        assert!(github
+            .bodies(Method::POST, "repos/bors-rs/bors/pulls")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn land_when_green() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("land-when-green", &github, &git);
+
+        // The PR only waits for its checks once they could be fetched
+        assert!(try_execute(&mut processor, "/land when-green")
+            .await
+            .is_err());
+        assert!(!processor.pulls_mut()[&1].merge_when_green);
+        assert!(github.comments("bors-rs", "bors", 1).is_empty());
+
+        respond_head_checks(&github, json!([]));
+        execute(&mut processor, "/land when-green").await;
+        assert!(processor.pulls_mut()[&1].merge_when_green);
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(
+            comments[0].contains("merged once the checks"),
+            "{}",
+            comments[0]
+        );
+
+        // Landing through the queue stops waiting to merge the PR directly
+        execute(&mut processor, "/land").await;
+        let pull = &processor.pulls_mut()[&1];
+        assert!(!pull.merge_when_green);
+        assert!(pull.status.is_queued());
+    }
+
+    #[tokio::test]
+    async fn canary_stops_land_when_green() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("canary-when-green", &github, &git);
+        respond_head_checks(&github, json!([]));
+
+        execute(&mut processor, "/land when-green").await;
+        execute(&mut processor, "/canary").await;
+        let pull = &processor.pulls_mut()[&1];
+        assert!(!pull.merge_when_green);
+        assert!(pull.canary_requested);
+    }
+
+    /// Approve PR #1 `days_ago`, both in bors' state and on Github
+    fn approve(processor: &mut EventProcessor, github: &FakeGithub, days_ago: u64) {
+        let approved_at =
+            DateTime::from(SystemTime::now() - Duration::from_secs(days_ago * 24 * 60 * 60));
+        let mut pull = processor.pulls_mut().get_mut(&1).unwrap();
+        pull.approved = true;
+        pull.approved_at = Some(approved_at);
+        drop(pull);
+        github.respond_reviews("APPROVED", &[("bob", "APPROVED", &approved_at.to_string())]);
+    }
+
+    #[tokio::test]
+    async fn land_reports_remaining_approval() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor_with_config(
+            "land-approval-remaining",
+            "require-review = true\napproval-max-age-days = 2",
+            &github,
+            &git,
+        );
+        approve(&mut processor, &github, 1);
+
+        execute(&mut processor, "/land").await;
+        assert!(processor.pulls_mut()[&1].status.is_queued());
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0].contains("another 23 hours"), "{}", comments[0]);
+    }
+
+    #[tokio::test]
+    async fn land_refuses_expired_approval() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor_with_config(
+            "land-approval-expired",
+            "require-review = true\napproval-max-age-days = 2",
+            &github,
+            &git,
+        );
+        approve(&mut processor, &github, 3);
+
+        execute(&mut processor, "/land").await;
+        assert!(matches!(processor.pulls_mut()[&1].status, Status::InReview));
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(
+            comments[0].starts_with("@alice :hourglass:"),
+            "{}",
+            comments[0]
+        );
+        assert!(
+            comments[0].contains("more than 2 days old"),
+            "{}",
+            comments[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn later_approval_doesnt_lift_change_request() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor_with_config(
+            "strict-changes-requested",
+            "require-review = true\nstrict-changes-requested = true",
+            &github,
+            &git,
+        );
+        github.respond_reviews(
+            "APPROVED",
+            &[
+                ("bob", "CHANGES_REQUESTED", "2020-10-16T12:00:00Z"),
+                ("carol", "APPROVED", "2020-10-16T13:00:00Z"),
+            ],
+        );
+
+        execute(&mut processor, "/land").await;
+        let pull = &processor.pulls_mut()[&1];
+        assert!(pull.approved);
+        assert!(matches!(pull.status, Status::InReview));
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(
+            comments[0].contains("Changes were requested by @bob"),
+            "{}",
+            comments[0]
+        );
+    }
+
+    /// Add the open PR `number`, whose land failed `minutes_ago`
+    fn add_failed(processor: &mut EventProcessor, number: u64, minutes_ago: u64) {
+        let event = PullRequestEventBuilder::new("opened")
+            .number(number)
+            .head(&format!("pr-{}", number), &format!("a{:039x}", number))
+            .build();
+        let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+        pull.status = Status::Failed {
+            reason: "check 'ci' failed".to_owned(),
+            check: Some("ci".to_owned()),
+            failed_at: DateTime::from(SystemTime::now() - Duration::from_secs(minutes_ago * 60)),
+        };
+        processor.pulls_mut().insert(pull);
+    }
+
+    #[tokio::test]
+    async fn requeue_failed_requires_admin() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("requeue-failed-admin", &github, &git);
+        github.respond(
+            Method::GET,
+            "repos/bors-rs/bors/collaborators/alice",
+            StatusCode::NO_CONTENT,
+            serde_json::Value::Null,
+        );
+        let command = Command::from_comment("/requeue-failed").unwrap().unwrap();
+
+        github.respond(
+            Method::GET,
+            "repos/bors-rs/bors/collaborators/alice/permission",
+            StatusCode::OK,
+            json!({ "permission": "write" }),
+        );
+        assert!(!command
+            .is_authorized(&processor.command_context("alice", 1))
+            .await
+            .unwrap());
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0].contains("Not Admin"), "{}", comments[0]);
+
+        github.respond(
+            Method::GET,
+            "repos/bors-rs/bors/collaborators/alice/permission",
+            StatusCode::OK,
+            json!({ "permission": "admin" }),
+        );
+        assert!(command
+            .is_authorized(&processor.command_context("alice", 1))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn requeue_failed_in_order_of_failure() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("requeue-failed-window", &github, &git);
+        add_failed(&mut processor, 2, 20);
+        add_failed(&mut processor, 3, 40);
+        add_failed(&mut processor, 4, 180);
+        let requeue_pending = |processor: &mut EventProcessor| {
+            processor
+                .command_context("alice", 1)
+                .store()
+                .data()
+                .requeue_pending
+                .iter()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        // Only the PRs which failed within the window are staged, oldest failure first
+        execute(&mut processor, "/requeue-failed since=1h").await;
+        assert_eq!(requeue_pending(&mut processor), [3, 2]);
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert!(comments[0].contains("#3, #2"), "{}", comments[0]);
+
+        // PRs which are already staged aren't staged twice
+        execute(&mut processor, "/requeue-failed").await;
+        assert_eq!(requeue_pending(&mut processor), [3, 2, 4]);
+        execute(&mut processor, "/requeue-failed").await;
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert!(
+            comments[2].contains("no failed PRs to re-queue"),
+            "{}",
+            comments[2]
+        );
+        assert!(processor.pulls_mut()[&2].status.is_failed());
+    }
+
+    #[test]
+    fn requeue_failed_window_mustnt_overflow() {
+        assert!(Command::from_comment("/requeue-failed since=2d")
+            .unwrap()
+            .is_ok());
+        assert!(
+            Command::from_comment("/requeue-failed since=999999999999999999d")
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn land_retargets_base() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor =
+            processor_with_config("land-retarget", "require-review = true", &github, &git);
+        git.set_ref("release", Oid::from_str("e".repeat(40)));
+        github.respond(
+            Method::PATCH,
+            "repos/bors-rs/bors/pulls/1",
+            StatusCode::OK,
+            PullRequestEventBuilder::new("opened")
+                .number(1)
+                .base("release", &"e".repeat(40))
+                .payload()["pull_request"]
+                .clone(),
+        );
+
+        // A PR which isn't allowed to land isn't retargeted
+        github.respond_reviews("REVIEW_REQUIRED", &[]);
+        execute(&mut processor, "/land base=release").await;
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert!(comments[0].contains("missing approvals"), "{}", comments[0]);
+        assert!(git.operations().is_empty());
+
+        // The PR doesn't apply to the new base
+        approve(&mut processor, &github, 0);
+        git.push_outcome(MergeOutcome::Conflict {
+            files: vec!["README.md".into()],
+        });
+        execute(&mut processor, "/land base=release").await;
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert!(comments[1].contains("doesn't apply"), "{}", comments[1]);
+        assert!(comments[1].contains("`README.md`"), "{}", comments[1]);
+        assert_eq!(
+            git.operations().last(),
+            Some(&GitOperation::DeleteBranch("retarget/1".into()))
+        );
+        assert!(github
+            .bodies(Method::PATCH, "repos/bors-rs/bors/pulls/1")
+            .is_empty());
+        assert!(matches!(processor.pulls_mut()[&1].status, Status::InReview));
+
+        execute(&mut processor, "/land base=release").await;
+        let retargeted = github.bodies(Method::PATCH, "repos/bors-rs/bors/pulls/1");
+        assert_eq!(retargeted.len(), 1);
+        assert_eq!(retargeted[0]["base"], "release");
+        let pull = &processor.pulls_mut()[&1];
+        assert_eq!(pull.base_ref_name, "release");
+        assert!(pull.status.is_queued());
+        assert_eq!(
+            git.operations().last(),
+            Some(&GitOperation::DeleteBranch("retarget/1".into()))
+        );
+        assert!(!git.operations().iter().any(is_push));
+    }
+
+    /// Put PR #1 in `status`, optionally in `lane`, and cancel its land
+    async fn cancel(processor: &mut EventProcessor, status: Status, lane: Option<&str>) {
+        let mut pull = processor.pulls_mut().get_mut(&1).unwrap();
+        pull.status = status;
+        pull.lane = lane.map(ToOwned::to_owned);
+        drop(pull);
+        execute(processor, "/cancel").await;
+        assert!(matches!(processor.pulls_mut()[&1].status, Status::InReview));
+    }
+
+    #[tokio::test]
+    async fn cancel_resets_test_branches() {
+        let merge_oid = Oid::from_str("c".repeat(40));
+        let cases = [
+            (
+                Status::testing(merge_oid.clone()),
+                None,
+                "auto",
+                "Land canceled",
+            ),
+            (
+                Status::testing(merge_oid.clone()),
+                Some("docs"),
+                "auto-docs",
+                "Land canceled",
+            ),
+            (
+                Status::canary(merge_oid.clone()),
+                None,
+                CANARY_BRANCH,
+                "Canary canceled",
+            ),
+        ];
+        for (status, lane, branch, description) in cases.iter() {
+            let github = FakeGithub::start();
+            let git = MockGitBackend::new("bors");
+            let mut processor = processor_with_config(
+                "cancel",
+                "[[lanes]]\nname = \"docs\"\npaths = [\"docs/*\"]",
+                &github,
+                &git,
+            );
+            let pull = &processor.pulls_mut()[&1];
+            let (head, base) = (pull.head_ref_oid.to_string(), pull.base_ref_oid.to_string());
+
+            cancel(&mut processor, status.clone(), *lane).await;
+            let statuses = github.bodies(
+                Method::POST,
+                &format!("repos/bors-rs/bors/statuses/{}", head),
+            );
+            assert_eq!(statuses.len(), 1);
+            assert_eq!(statuses[0]["state"], "error");
+            assert_eq!(statuses[0]["description"], *description);
+            assert_eq!(
+                github.bodies(
+                    Method::PATCH,
+                    &format!("repos/bors-rs/bors/git/refs/heads/{}", branch)
+                ),
+                [json!({ "sha": base, "force": true })]
+            );
+            assert!(git.operations().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn cancel_returns_queued_pr_to_review() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("cancel-queued", &github, &git);
+
+        cancel(&mut processor, Status::queued(), None).await;
+        let head = processor.pulls_mut()[&1].head_ref_oid.to_string();
+        assert!(github.statuses("bors-rs", "bors", &head).is_empty());
+        assert!(github
+            .bodies(Method::PATCH, "repos/bors-rs/bors/git/refs/heads/auto")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancel_while_read_only_can_be_retried() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let read_only = ReadOnlySwitch::new(true);
+        let client = github.configured_client(|builder| builder.read_only(read_only.clone()));
+        let mut processor = processor_with_client("cancel-read-only", "", client, &github, &git);
+        let merge_oid = Oid::from_str("c".repeat(40));
+        processor.pulls_mut().get_mut(&1).unwrap().status = Status::testing(merge_oid.clone());
+
+        // Nothing is written to Github, and the land isn't canceled until the cleanup succeeds
+        let error = try_execute(&mut processor, "/cancel").await.unwrap_err();
+        assert!(is_read_only(&error), "{:#}", error);
+        assert!(github
+            .received()
+            .iter()
+            .all(|request| request.method == Method::GET));
+        assert_eq!(
+            processor.pulls_mut()[&1].status.merge_oid(),
+            Some(&merge_oid)
+        );
+
+        read_only.set(false);
+        execute(&mut processor, "/cancel").await;
+        assert!(matches!(processor.pulls_mut()[&1].status, Status::InReview));
+        assert_eq!(
+            github
+                .bodies(Method::PATCH, "repos/bors-rs/bors/git/refs/heads/auto")
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn refresh_updates_only_its_pr() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("refresh", &github, &git);
+        let merge_oid = Oid::from_str("c".repeat(40));
+        for (number, status) in [
+            (2, Status::testing(merge_oid.clone())),
+            (3, Status::queued()),
+        ] {
+            let event = PullRequestEventBuilder::new("opened")
+                .number(number)
+                .build();
+            let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+            pull.status = status;
+            processor.pulls_mut().insert(pull);
+        }
+
+        // A push, a label and an approval whose webhooks were missed
+        let head = "b".repeat(40);
+        github.respond(
+            Method::GET,
+            "repos/bors-rs/bors/pulls/1",
+            StatusCode::OK,
+            PullRequestEventBuilder::new("opened")
+                .number(1)
+                .head("pr-1", &head)
+                .labels(&["documentation"])
+                .payload()["pull_request"]
+                .clone(),
+        );
+        github.respond_reviews("APPROVED", &[("bob", "APPROVED", "2020-10-16T12:00:00Z")]);
+
+        execute(&mut processor, "/refresh").await;
+        let pull = &processor.pulls_mut()[&1];
+        assert_eq!(pull.head_ref_oid, Oid::from_str(head));
+        assert!(pull.has_label("documentation"));
+        assert!(pull.approved);
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(
+            comments[0].contains("Refreshed this PR's state"),
+            "{}",
+            comments[0]
+        );
+
+        assert!(matches!(
+            &processor.pulls_mut()[&2].status,
+            Status::Testing { merge_oid: oid, .. } if oid == &merge_oid
+        ));
+        assert!(processor.pulls_mut()[&3].status.is_queued());
+        assert!(!processor.pulls_mut()[&2].approved);
+        assert!(github.received().iter().all(|r| {
+            ["pulls/2", "issues/2", "pulls/3", "issues/3"]
+                .iter()
+                .all(|other| !r.path.contains(other))
+        }));
+        assert!(git.operations().is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Clusters of queued PRs which are known to conflict with each other
+//!
+//! Queued PRs which change some of the same files may conflict, or break each other once one of
+//! them lands. Grouping them lets maintainers decide the order they should land in up front,
+//! rather than finding out once the queue reaches them.
+
+use crate::state::PullRequestState;
+use std::collections::BTreeMap;
+
+/// Queued PRs linked to each other, directly or through other PRs, by changing the same files
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConflictCluster {
+    /// The PRs in the cluster, in queue order
+    pub pulls: Vec<u64>,
+    /// The files changed by more than one PR in the cluster, sorted
+    pub files: Vec<String>,
+}
+
+/// The clusters of PRs in the queue, i.e. queued or being tested, among `pulls`, which must
+/// already be in queue order. PRs which don't overlap with any other aren't part of a cluster
+pub fn conflict_clusters(pulls: &[PullRequestState]) -> Vec<ConflictCluster> {
+    let queued = pulls
+        .iter()
+        .filter(|p| p.status.is_queued() || p.status.is_testing())
+        .collect::<Vec<_>>();
+
+    let mut clustered = vec![false; queued.len()];
+    let mut clusters = Vec::new();
+    for start in 0..queued.len() {
+        if clustered[start] {
+            continue;
+        }
+        clustered[start] = true;
+
+        let mut members = vec![start];
+        let mut next = 0;
+        while next < members.len() {
+            let pull = queued[members[next]];
+            for (i, other) in queued.iter().enumerate() {
+                if !clustered[i] && pull.overlaps(other) {
+                    clustered[i] = true;
+                    members.push(i);
+                }
+            }
+            next += 1;
+        }
+
+        if members.len() < 2 {
+            continue;
+        }
+        members.sort_unstable();
+
+        let mut changed_by = BTreeMap::<&str, usize>::new();
+        for &i in &members {
+            for path in &queued[i].changed_files {
+                *changed_by.entry(path).or_default() += 1;
+            }
+        }
+
+        clusters.push(ConflictCluster {
+            pulls: members.iter().map(|&i| queued[i].number).collect(),
+            files: changed_by
+                .into_iter()
+                .filter(|(_, count)| *count > 1)
+                .map(|(path, _)| path.to_owned())
+                .collect(),
+        });
+    }
+
+    clusters
+}
+
+#[cfg(test)]
+mod test {
+    use super::{conflict_clusters, ConflictCluster};
+    use crate::state::{PullRequestState, Status};
+    use github::test_fixtures::{EventPayload, PullRequestEventBuilder};
+
+    fn pull(number: u64, status: Status, files: &[&str]) -> PullRequestState {
+        let event = PullRequestEventBuilder::new("opened")
+            .number(number)
+            .build();
+        let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+        pull.status = status;
+        pull.changed_files = files.iter().map(|&f| f.to_owned()).collect();
+        pull
+    }
+
+    #[test]
+    fn overlapping_pulls_are_clustered_transitively() {
+        let pulls = [
+            pull(1, Status::queued(), &["a.rs", "b.rs"]),
+            pull(2, Status::queued(), &["c.rs"]),
+            pull(3, Status::queued(), &["b.rs", "d.rs"]),
+            pull(4, Status::InReview, &["c.rs"]),
+            pull(5, Status::queued(), &["d.rs"]),
+            pull(6, Status::queued(), &["e.rs"]),
+        ];
+
+        assert_eq!(
+            conflict_clusters(&pulls),
+            [ConflictCluster {
+                pulls: vec![1, 3, 5],
+                files: vec!["b.rs".to_owned(), "d.rs".to_owned()],
+            }]
+        );
+    }
+}
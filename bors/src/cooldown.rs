@@ -0,0 +1,52 @@
+//! Per-user command cooldowns, preventing a single user from flooding bors with commands
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Default)]
+pub struct CommandCooldowns {
+    /// When each (user, command) pair may next be used
+    until: HashMap<(String, &'static str), Instant>,
+}
+
+impl CommandCooldowns {
+    /// Check if `user` may use `command`, starting its cooldown if so
+    pub fn try_use(&mut self, user: &str, command: &'static str, cooldown: Duration) -> bool {
+        let now = Instant::now();
+        self.until.retain(|_, until| *until > now);
+
+        let key = (user.to_owned(), command);
+        if self.until.contains_key(&key) {
+            return false;
+        }
+
+        if !cooldown.is_zero() {
+            self.until.insert(key, now + cooldown);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CommandCooldowns;
+    use std::time::Duration;
+
+    #[test]
+    fn cooldowns() {
+        let mut cooldowns = CommandCooldowns::default();
+        let minute = Duration::from_secs(60);
+
+        assert!(cooldowns.try_use("octocat", "land", minute));
+        assert!(!cooldowns.try_use("octocat", "land", minute));
+        // Cooldowns are per user and per command
+        assert!(cooldowns.try_use("octocat", "cancel", minute));
+        assert!(cooldowns.try_use("hubot", "land", minute));
+
+        // Commands without a cooldown are never limited
+        assert!(cooldowns.try_use("octocat", "help", Duration::ZERO));
+        assert!(cooldowns.try_use("octocat", "help", Duration::ZERO));
+    }
+}
@@ -1,11 +1,29 @@
 use crate::{
-    command::Command,
+    activity::{self, ActivitySnapshot},
+    audit::{self, AuditEntry, AuditLog},
+    bisect::Bisection,
+    command::{Command, ParseCommandError},
     config::{GitConfig, GithubConfig, RepoConfig},
-    git::GitRepository,
-    graphql::GithubClient,
+    cooldown::CommandCooldowns,
+    git::{BackgroundGit, GitBackend, GitRepository},
+    graphql::{
+        is_auth_expired, is_read_only, AuthHealth, GithubClient, GraphqlHealth, ReactionSubject,
+        ReviewDecisionCacheStats,
+    },
+    insights::{CheckRunRecord, MergeSkewRecord},
+    labeler,
     project_board::ProjectBoard,
-    queue::MergeQueue,
-    state::{PullRequestState, Status},
+    protection::{self, BranchRole, ProtectionConflict},
+    queue::{MergeQueue, PullRequestMut, PullRequests, CANARY_BRANCH},
+    queue_hooks::{QueueHookEvent, QueueHookPayload, QueueHooks},
+    server::delivery::RecentDeliveries,
+    sla::{self, SlaStatus},
+    snapshot::StateSnapshot,
+    state::{BuildResultSource, PullRequestState, Repo, Status, TestSuiteResult},
+    store::{QueueData, QueueStore, Store},
+    summary::SummaryStatuses,
+    teams::TeamMembers,
+    templates::{self, CommentMarker},
     Result,
 };
 use futures::{
@@ -13,16 +31,57 @@ use futures::{
     sink::SinkExt,
     stream::StreamExt,
 };
-use github::{Event, NodeId, PullRequestReviewEvent};
+use github::{
+    client::ReadOnlySwitch, DateTime, Event, EventType, Oid, PullRequestReviewEvent,
+    RepositoryEvent, User, UserType,
+};
 use log::{error, info, warn};
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::task::JoinHandle;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Context of the status, or name of the check, bors reports on the PRs it lands
+pub(crate) const BORS_CONTEXT: &str = "bors";
+
+/// Webhook events which are handled by the `EventProcessor`
+pub(crate) const REQUIRED_WEBHOOK_EVENTS: &[EventType] = &[
+    EventType::CheckRun,
+    EventType::IssueComment,
+    EventType::PullRequest,
+    EventType::PullRequestReview,
+    EventType::PullRequestReviewComment,
+    EventType::Push,
+    EventType::Repository,
+    EventType::Status,
+    EventType::WorkflowRun,
+];
 
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum Request {
     Webhook { event: Event, delivery_id: String },
-    GetState(oneshot::Sender<(MergeQueue, HashMap<u64, PullRequestState>)>),
+    GetState(oneshot::Sender<(MergeQueue, PullRequests)>),
+    ExportState(oneshot::Sender<StateSnapshot>),
+    GetCheckRuns(oneshot::Sender<Vec<CheckRunRecord>>),
+    GetMergeSkews(oneshot::Sender<Vec<MergeSkewRecord>>),
+    GetActivity(oneshot::Sender<Vec<ActivitySnapshot>>),
+    GetProtectionConflicts(oneshot::Sender<Vec<ProtectionConflict>>),
+    GetReviewDecisionCacheStats(oneshot::Sender<ReviewDecisionCacheStats>),
+    ImportState(StateSnapshot),
     Synchronize,
+    GitMaintenance,
+    Prefetch,
+    CheckSla,
+    AutoLand,
+    SnapshotActivity,
+    ProcessQueue,
+    ResumeLand(u64, oneshot::Sender<bool>),
+    SyncPull(u64, oneshot::Sender<bool>),
 }
 
 #[derive(Clone, Debug)]
@@ -42,143 +101,1359 @@ impl EventProcessorSender {
             .await
     }
 
-    pub async fn get_state(
-        &self,
-    ) -> Result<(MergeQueue, HashMap<u64, PullRequestState>), mpsc::SendError> {
+    pub async fn get_state(&self) -> Result<(MergeQueue, PullRequests), mpsc::SendError> {
         let (tx, rx) = oneshot::channel();
         self.inner.clone().send(Request::GetState(tx)).await?;
         Ok(rx.await.unwrap())
     }
 
+    pub async fn export_state(&self) -> Result<StateSnapshot, mpsc::SendError> {
+        let (tx, rx) = oneshot::channel();
+        self.inner.clone().send(Request::ExportState(tx)).await?;
+        Ok(rx.await.unwrap())
+    }
+
+    /// The completed checks on merge commits recorded for CI insights, oldest first
+    pub async fn check_runs(&self) -> Result<Vec<CheckRunRecord>, mpsc::SendError> {
+        let (tx, rx) = oneshot::channel();
+        self.inner.clone().send(Request::GetCheckRuns(tx)).await?;
+        Ok(rx.await.unwrap())
+    }
+
+    /// How far the recently landed PRs were behind their base, oldest first
+    pub async fn merge_skews(&self) -> Result<Vec<MergeSkewRecord>, mpsc::SendError> {
+        let (tx, rx) = oneshot::channel();
+        self.inner.clone().send(Request::GetMergeSkews(tx)).await?;
+        Ok(rx.await.unwrap())
+    }
+
+    /// The daily snapshots of the repo's activity, oldest first
+    pub async fn activity(&self) -> Result<Vec<ActivitySnapshot>, mpsc::SendError> {
+        let (tx, rx) = oneshot::channel();
+        self.inner.clone().send(Request::GetActivity(tx)).await?;
+        Ok(rx.await.unwrap())
+    }
+
+    /// The branch protection settings found to conflict with bors at the last synchronization
+    pub async fn protection_conflicts(&self) -> Result<Vec<ProtectionConflict>, mpsc::SendError> {
+        let (tx, rx) = oneshot::channel();
+        self.inner
+            .clone()
+            .send(Request::GetProtectionConflicts(tx))
+            .await?;
+        Ok(rx.await.unwrap())
+    }
+
+    /// How well the cache of the PRs' review decisions is doing
+    pub async fn review_decision_cache_stats(
+        &self,
+    ) -> Result<ReviewDecisionCacheStats, mpsc::SendError> {
+        let (tx, rx) = oneshot::channel();
+        self.inner
+            .clone()
+            .send(Request::GetReviewDecisionCacheStats(tx))
+            .await?;
+        Ok(rx.await.unwrap())
+    }
+
+    pub async fn import_state(&self, snapshot: StateSnapshot) -> Result<(), mpsc::SendError> {
+        self.inner
+            .clone()
+            .send(Request::ImportState(snapshot))
+            .await
+    }
+
+    /// Indicates if the event processor has stopped and is no longer handling requests
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
     pub async fn sync(&self) -> Result<(), mpsc::SendError> {
         self.inner.clone().send(Request::Synchronize).await
     }
+
+    pub async fn git_maintenance(&self) -> Result<(), mpsc::SendError> {
+        self.inner.clone().send(Request::GitMaintenance).await
+    }
+
+    pub async fn prefetch(&self) -> Result<(), mpsc::SendError> {
+        self.inner.clone().send(Request::Prefetch).await
+    }
+
+    pub async fn check_sla(&self) -> Result<(), mpsc::SendError> {
+        self.inner.clone().send(Request::CheckSla).await
+    }
+
+    pub async fn auto_land(&self) -> Result<(), mpsc::SendError> {
+        self.inner.clone().send(Request::AutoLand).await
+    }
+
+    pub async fn snapshot_activity(&self) -> Result<(), mpsc::SendError> {
+        self.inner.clone().send(Request::SnapshotActivity).await
+    }
+
+    /// Pick up processing the merge queue, e.g. once writes to Github have been enabled again
+    pub async fn process_queue(&self) -> Result<(), mpsc::SendError> {
+        self.inner.clone().send(Request::ProcessQueue).await
+    }
+
+    /// Re-test the merge commit from a PR's previous land, returning false if there was no land
+    /// to resume
+    pub async fn resume_land(&self, number: u64) -> Result<bool, mpsc::SendError> {
+        let (tx, rx) = oneshot::channel();
+        self.inner
+            .clone()
+            .send(Request::ResumeLand(number, tx))
+            .await?;
+        Ok(rx.await.unwrap())
+    }
+
+    /// Refresh a single PR's state from Github, returning false if it isn't open or couldn't be
+    /// fetched
+    pub async fn sync_pull(&self, number: u64) -> Result<bool, mpsc::SendError> {
+        let (tx, rx) = oneshot::channel();
+        self.inner
+            .clone()
+            .send(Request::SyncPull(number, tx))
+            .await?;
+        Ok(rx.await.unwrap())
+    }
+}
+
+/// Tracks when the event processor last synchronized its state with Github, and whether it has
+/// failed to since
+#[derive(Clone, Debug, Default)]
+pub struct SyncStatus {
+    inner: Arc<Mutex<SyncStatusInner>>,
+}
+
+#[derive(Debug, Default)]
+struct SyncStatusInner {
+    last_synced: Option<SystemTime>,
+    /// The error the last attempt to synchronize failed with, if it failed
+    last_error: Option<String>,
+}
+
+impl SyncStatus {
+    pub fn last_synced(&self) -> Option<SystemTime> {
+        self.inner.lock().unwrap().last_synced
+    }
+
+    /// The error the last attempt to synchronize failed with, leaving the state last synchronized
+    /// stale
+    pub fn last_error(&self) -> Option<String> {
+        self.inner.lock().unwrap().last_error.clone()
+    }
+
+    pub(crate) fn record_synced(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.last_synced = Some(SystemTime::now());
+        inner.last_error = None;
+    }
+
+    pub(crate) fn record_failed(&self, error: &anyhow::Error) {
+        self.inner.lock().unwrap().last_error = Some(format!("{:#}", error));
+    }
+}
+
+/// What bors tests on one of its test branches
+#[derive(Debug)]
+enum TestBranchTester {
+    /// The PRs landed by the main queue, or by the named lane
+    Queue(Option<String>),
+    Canary,
 }
 
 #[derive(Debug)]
 pub struct EventProcessor {
     config: RepoConfig,
+    github_config: GithubConfig,
     github: GithubClient,
-    git_repository: GitRepository,
+    git_repository: Box<dyn GitBackend>,
+    /// Runs the git work which doesn't need to hold up processing events
+    background_git: BackgroundGit,
     merge_queue: MergeQueue,
     project_board: Option<ProjectBoard>,
-    pulls: HashMap<u64, PullRequestState>,
+    pulls: PullRequests,
+    store: Store,
+    queue_store: QueueStore,
+    /// The queue state saved before bors restarted, until it's restored by the first successful
+    /// synchronization
+    saved_queue: Option<QueueData>,
+    audit_log: AuditLog,
+    sync_status: SyncStatus,
+    sla_status: SlaStatus,
+    command_cooldowns: CommandCooldowns,
+    summary_statuses: SummaryStatuses,
+    team_members: TeamMembers,
+    /// Branch protection settings found to conflict with bors when it last synchronized
+    protection_conflicts: Vec<ProtectionConflict>,
+    /// Delivers the queue events of the repo's PRs to its queue hooks
+    queue_hooks: QueueHooks,
+    /// The bisection in progress, if any
+    bisection: Option<Bisection>,
+    /// Switch disabling writes to Github for just this repo
+    read_only: ReadOnlySwitch,
+    /// Head commits of the PRs auto-land already passed over, so they're only reconsidered once
+    /// updated
+    auto_land_skipped: HashMap<u64, Oid>,
+    /// The webhook deliveries handled for the repo, carried over by its state snapshots
+    recent_deliveries: RecentDeliveries,
     requests_rx: mpsc::Receiver<Request>,
 }
 
 impl EventProcessor {
+    /// Create an `EventProcessor` which doesn't write to Github while either the `read_only`
+    /// switch shared by every repo or its own switch is on
     pub fn new(
         config: RepoConfig,
         github_config: &GithubConfig,
         git_config: &GitConfig,
+        read_only: ReadOnlySwitch,
+    ) -> Result<(EventProcessorSender, Self)> {
+        let git_repository = GitRepository::from_config(git_config, &config)?;
+        Self::with_git_backend(config, github_config, Box::new(git_repository), read_only)
+    }
+
+    /// Create an `EventProcessor` which performs git operations using the provided backend
+    pub fn with_git_backend(
+        config: RepoConfig,
+        github_config: &GithubConfig,
+        git_repository: Box<dyn GitBackend>,
+        read_only: ReadOnlySwitch,
     ) -> Result<(EventProcessorSender, Self)> {
         let (tx, rx) = mpsc::channel(1024);
-        let github = GithubClient::new(&github_config.github_api_token);
-        let git_repository = GitRepository::from_config(git_config, config.repo())?;
+        let repo_read_only = ReadOnlySwitch::new(config.read_only());
+        let github = GithubClient::for_repo(
+            github_config,
+            config.repo(),
+            &[read_only, repo_read_only.clone()],
+        )?;
+        let store = Store::open(&config)?;
+        let queue_store = QueueStore::open(&config)?;
+        let audit_log = AuditLog::open(&config)?;
+        let pulls = PullRequests::new(config.labels());
 
         Ok((
             EventProcessorSender::new(tx),
             Self {
                 config,
+                github_config: github_config.clone(),
                 github,
                 git_repository,
+                background_git: BackgroundGit::default(),
                 merge_queue: MergeQueue::new(),
                 project_board: None,
-                pulls: HashMap::new(),
+                pulls,
+                store,
+                queue_store,
+                saved_queue: None,
+                audit_log,
+                sync_status: SyncStatus::default(),
+                sla_status: SlaStatus::default(),
+                command_cooldowns: CommandCooldowns::default(),
+                summary_statuses: SummaryStatuses::default(),
+                team_members: TeamMembers::default(),
+                protection_conflicts: Vec::new(),
+                queue_hooks: QueueHooks::default(),
+                bisection: None,
+                read_only: repo_read_only,
+                auto_land_skipped: HashMap::new(),
+                recent_deliveries: RecentDeliveries::default(),
                 requests_rx: rx,
             },
         ))
     }
 
-    pub async fn start(mut self) {
-        self.synchronize()
-            .await
-            .expect("unable to synchronize initial state");
+    /// Create an `EventProcessor` for tests, talking to `github` and performing git operations
+    /// against `git`, which keeps its state in `dir` rather than in the working directory
+    #[cfg(test)]
+    pub(crate) fn for_test(
+        config: RepoConfig,
+        github: GithubClient,
+        git: crate::git::mock::MockGitBackend,
+        dir: &std::path::Path,
+    ) -> Self {
+        std::fs::create_dir_all(dir).unwrap();
+        let (_tx, rx) = mpsc::channel(1);
+        let read_only = ReadOnlySwitch::new(config.read_only());
+        let pulls = PullRequests::new(config.labels());
+
+        Self {
+            github_config: toml::from_str(r#"github-api-token = "token""#).unwrap(),
+            github,
+            git_repository: Box::new(git),
+            background_git: BackgroundGit::default(),
+            merge_queue: MergeQueue::new(),
+            project_board: None,
+            pulls,
+            store: Store::open_path(dir.join("store.json")).unwrap(),
+            queue_store: QueueStore::open_path(dir.join("queue.json")),
+            saved_queue: None,
+            audit_log: AuditLog::open_path(dir.join("audit.jsonl")),
+            sync_status: SyncStatus::default(),
+            sla_status: SlaStatus::default(),
+            command_cooldowns: CommandCooldowns::default(),
+            summary_statuses: SummaryStatuses::default(),
+            team_members: TeamMembers::default(),
+            protection_conflicts: Vec::new(),
+            queue_hooks: QueueHooks::default(),
+            bisection: None,
+            read_only,
+            auto_land_skipped: HashMap::new(),
+            recent_deliveries: RecentDeliveries::default(),
+            requests_rx: rx,
+            config,
+        }
+    }
+
+    /// The open PRs, for tests to set up the state they exercise
+    #[cfg(test)]
+    pub(crate) fn pulls_mut(&mut self) -> &mut PullRequests {
+        &mut self.pulls
+    }
+
+    pub fn graphql_health(&self) -> GraphqlHealth {
+        self.github.graphql_health().clone()
+    }
+
+    pub fn auth_health(&self) -> AuthHealth {
+        self.github.auth_health().clone()
+    }
+
+    pub fn sync_status(&self) -> SyncStatus {
+        self.sync_status.clone()
+    }
+
+    pub fn sla_status(&self) -> SlaStatus {
+        self.sla_status.clone()
+    }
+
+    pub fn read_only(&self) -> ReadOnlySwitch {
+        self.read_only.clone()
+    }
+
+    pub async fn start(mut self) {
+        match QueueHooks::spawn(&self.config, &self.github_config) {
+            Ok(hooks) => self.queue_hooks = hooks,
+            Err(e) => warn!(
+                "{}/{} - Unable to start delivering queue hooks: {:#}",
+                self.config.owner(),
+                self.config.name(),
+                e
+            ),
+        }
+
+        if let Err(e) = self.validate_webhook().await {
+            warn!(
+                "{}/{} - Unable to validate webhook configuration: {:#}",
+                self.config.owner(),
+                self.config.name(),
+                e
+            );
+        }
+
+        match self.queue_store.load() {
+            Ok(saved) => self.saved_queue = saved,
+            Err(e) => warn!(
+                "{}/{} - Unable to load the saved queue state: {:#}",
+                self.config.owner(),
+                self.config.name(),
+                e
+            ),
+        }
+
+        if let Err(e) = self.synchronize().await {
+            self.sync_status.record_failed(&e);
+            // Keep running with an expired token so that the problem is visible on the status
+            // page, the state will be synchronized once the token has been replaced
+            if is_auth_expired(&e) {
+                self.record_auth_expired();
+            } else {
+                panic!("unable to synchronize initial state: {:?}", e);
+            }
+        }
+        self.save_queue();
+
+        while let Some(request) = self.requests_rx.next().await {
+            if let Err(e) = self.handle_request(request).await {
+                if is_auth_expired(&e) {
+                    self.record_auth_expired();
+                } else if is_read_only(&e) {
+                    info!(
+                        "{}/{} - Request not completed, bors is read-only: {:#}",
+                        self.config.owner(),
+                        self.config.name(),
+                        e
+                    );
+                } else {
+                    error!("Error while handling request: {:?}", e);
+                }
+            }
+
+            self.emit_queue_events();
+            self.save_queue();
+        }
+    }
+
+    /// Save the state of the merge queue and the open PRs, unless the state saved before bors
+    /// restarted is yet to be restored
+    fn save_queue(&self) {
+        if self.saved_queue.is_some() {
+            return;
+        }
+
+        if let Err(e) = self
+            .queue_store
+            .save(&self.merge_queue, self.pulls.values())
+        {
+            warn!(
+                "{}/{} - Unable to save the queue state: {:#}",
+                self.config.owner(),
+                self.config.name(),
+                e
+            );
+        }
+    }
+
+    /// Hand the queue events of the PRs recorded while handling a request to the queue hooks, in
+    /// the order they happened
+    fn emit_queue_events(&mut self) {
+        let mut payloads = self
+            .pulls
+            .values_mut()
+            .flat_map(|mut pull| std::mem::take(&mut pull.queue_events))
+            .collect::<Vec<_>>();
+        payloads.sort_by_key(|payload| payload.timestamp);
+
+        for payload in payloads {
+            self.queue_hooks.emit(payload);
+        }
+    }
+
+    fn record_auth_expired(&self) {
+        error!(
+            "{}/{} - Github rejected the API token, it has likely expired or been revoked",
+            self.config.owner(),
+            self.config.name(),
+        );
+        self.github.auth_health().record_expired();
+    }
+
+    async fn handle_request(&mut self, request: Request) -> Result<()> {
+        use Request::*;
+        match request {
+            Webhook { event, delivery_id } => self.handle_webhook(event, delivery_id).await?,
+
+            Request::GetState(oneshot) => {
+                self.pulls.set_order(&self.store.data().queue_order);
+                if oneshot
+                    .send((self.merge_queue.clone(), self.pulls.clone()))
+                    .is_err()
+                {
+                    warn!("Unable to deliver current state, receiver dropped");
+                }
+            }
+
+            ExportState(oneshot) => {
+                let snapshot = StateSnapshot::new(
+                    self.config.repo().to_owned(),
+                    self.merge_queue.clone(),
+                    self.pulls.values().cloned().collect(),
+                    self.store.data().clone(),
+                    self.audit_log.entries()?,
+                    self.recent_deliveries
+                        .ids()
+                        .map(ToOwned::to_owned)
+                        .collect(),
+                );
+                if oneshot.send(snapshot).is_err() {
+                    warn!("Unable to deliver state snapshot, receiver dropped");
+                }
+            }
+
+            GetCheckRuns(oneshot) => {
+                let runs = self.store.data().check_runs.iter().cloned().collect();
+                if oneshot.send(runs).is_err() {
+                    warn!("Unable to deliver check runs, receiver dropped");
+                }
+            }
+
+            GetMergeSkews(oneshot) => {
+                let skews = self.store.data().merge_skews.iter().cloned().collect();
+                if oneshot.send(skews).is_err() {
+                    warn!("Unable to deliver merge skews, receiver dropped");
+                }
+            }
+
+            GetActivity(oneshot) => {
+                let activity = self.store.data().activity.iter().cloned().collect();
+                if oneshot.send(activity).is_err() {
+                    warn!("Unable to deliver activity, receiver dropped");
+                }
+            }
+
+            GetProtectionConflicts(oneshot) => {
+                if oneshot.send(self.protection_conflicts.clone()).is_err() {
+                    warn!("Unable to deliver protection conflicts, receiver dropped");
+                }
+            }
+
+            GetReviewDecisionCacheStats(oneshot) => {
+                if oneshot
+                    .send(self.github.review_decision_cache_stats())
+                    .is_err()
+                {
+                    warn!("Unable to deliver review decision cache stats, receiver dropped");
+                }
+            }
+
+            ImportState(snapshot) => self.import_state(snapshot)?,
+
+            Synchronize => {
+                if let Err(e) = self.synchronize().await {
+                    self.sync_status.record_failed(&e);
+                    return Err(e);
+                }
+                self.github.auth_health().record_valid();
+            }
+
+            GitMaintenance => {
+                let task = self.git_repository.maintenance();
+                self.background_git.spawn("git maintenance", task);
+            }
+
+            Prefetch => {
+                self.prefetch();
+            }
+
+            CheckSla => self.check_sla(),
+
+            AutoLand => {
+                self.process_auto_land().await?;
+                self.process_merge_queue().await?;
+            }
+
+            SnapshotActivity => self.snapshot_activity()?,
+
+            ProcessQueue => {
+                self.process_merge_queue().await?;
+                self.process_bisection().await?;
+            }
+
+            ResumeLand(number, oneshot) => {
+                let resumed = self.resume_land(number).await?;
+                if oneshot.send(resumed).is_err() {
+                    warn!("Unable to deliver result of resuming land, receiver dropped");
+                }
+                if resumed {
+                    self.process_merge_queue().await?;
+                }
+            }
+
+            SyncPull(number, oneshot) => {
+                let synced = self.sync_pull(number).await;
+                if oneshot.send(matches!(synced, Ok(true))).is_err() {
+                    warn!("Unable to deliver result of synchronizing pr, receiver dropped");
+                }
+                if synced? {
+                    self.process_merge_queue().await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the base branches of open PRs and the heads of queued PRs into the checkout ahead of
+    /// them being landed, in the background
+    fn prefetch(&mut self) -> Option<JoinHandle<()>> {
+        let base_refs = self
+            .pulls
+            .values()
+            .map(|p| p.base_ref_name.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let heads = self
+            .pulls
+            .values()
+            .filter(|p| p.status.is_queued())
+            .map(|p| p.head_ref_oid.clone())
+            .collect::<Vec<_>>();
+
+        let task = self.git_repository.prefetch(base_refs, heads);
+        self.background_git.spawn("prefetch", task)
+    }
+
+    /// Refresh the state of a single PR from Github, without disturbing any other PR or the
+    /// queue, returning false if the PR isn't open
+    async fn sync_pull(&mut self, number: u64) -> Result<bool> {
+        info!("Synchronizing pr #{}", number);
+
+        if self.pulls.contains_key(&number) {
+            let mut pull = self.pulls.get_mut(&number).expect("PR should exist");
+            let labels = pull.labels.clone();
+            pull.refresh(&self.config, &self.github, self.project_board.as_ref())
+                .await?;
+            if let Err(e) = self
+                .audit_log
+                .record_label_changes(&self.config, &self.github, number, &labels, &pull.labels)
+                .await
+            {
+                warn!("pr #{}: unable to record label changes: {:#}", number, e);
+            }
+        } else {
+            let mut pull = self
+                .github
+                .pull(self.config.owner(), self.config.name(), number)
+                .await?;
+            if !matches!(pull.state, github::PullRequestState::Open) {
+                return Ok(false);
+            }
+            if let Some(board) = &self.project_board {
+                board.create_card(&self.github, &mut pull).await?;
+            }
+            self.pulls.insert(pull);
+        }
+
+        // The PR was closed without bors hearing about it
+        if !matches!(self.pulls[&number].state, github::PullRequestState::Open) {
+            if let Some(mut pull) = self.pulls.remove(&number) {
+                if let Some(board) = &self.project_board {
+                    board.delete_card(&self.github, &mut pull).await?;
+                }
+            }
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    async fn resume_land(&mut self, number: u64) -> Result<bool> {
+        let mut pull = match self.pulls.get_mut(&number) {
+            Some(pull) if pull.can_resume_land() => pull,
+            _ => return Ok(false),
+        };
+
+        info!("Resuming land of pr #{} through the API", number);
+        self.audit_log.record(&AuditEntry::new(
+            "api",
+            "resume-land",
+            Some(number),
+            pull.last_merge_oid.as_ref().unwrap().to_string(),
+        ))?;
+        pull.resume = true;
+        pull.update_status(
+            Status::queued(),
+            &self.config,
+            &self.github,
+            self.project_board.as_ref(),
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Record today's open PRs, queue length and land rate in the store
+    fn snapshot_activity(&mut self) -> Result<()> {
+        let day = activity::day_of(DateTime::now());
+        let snapshot = ActivitySnapshot {
+            day,
+            open_pulls: self.pulls.len(),
+            queue_length: self
+                .pulls
+                .values()
+                .filter(|p| p.status.is_queued() || p.status.is_testing())
+                .count(),
+            landed: self.store.data().landed_on(day),
+        };
+        self.store.update(|data| data.record_activity(snapshot))
+    }
+
+    fn check_sla(&self) {
+        let breach = sla::check(
+            &self.config,
+            &self.merge_queue,
+            &self.pulls,
+            self.github.auth_health().is_healthy(),
+        );
+
+        match (&breach, self.sla_status.record(breach.clone())) {
+            // Only alert when a breach starts or its suspected cause changes
+            (Some(breach), Some(previous)) if breach.cause == previous.cause => {}
+            (Some(breach), _) => error!(
+                "{}/{} - Queue SLA breached: {}",
+                self.config.owner(),
+                self.config.name(),
+                breach
+            ),
+            (None, Some(_)) => info!(
+                "{}/{} - Queue is back within its SLA",
+                self.config.owner(),
+                self.config.name()
+            ),
+            (None, None) => {}
+        }
+    }
+
+    /// Verify that the repository's webhook is subscribed to every event that bors needs,
+    /// optionally adding any missing subscriptions
+    async fn validate_webhook(&self) -> Result<()> {
+        let hooks = self
+            .github
+            .repos()
+            .list_hooks(self.config.owner(), self.config.name(), Default::default())
+            .await?
+            .into_inner();
+
+        // If the webhook's URL is configured only validate that hook, otherwise consider the
+        // union of all active hooks which deliver json payloads
+        let hooks = hooks
+            .into_iter()
+            .filter(|hook| match self.github_config.webhook_url() {
+                Some(url) => hook.config.url == url,
+                None => hook.active && hook.config.content_type == "json",
+            })
+            .collect::<Vec<_>>();
+
+        if hooks.is_empty() {
+            warn!(
+                "{}/{} - No webhook found delivering events to bors",
+                self.config.owner(),
+                self.config.name()
+            );
+            return Ok(());
+        }
+
+        let subscribed = |event: &EventType| {
+            hooks
+                .iter()
+                .flat_map(|hook| hook.events.iter())
+                .any(|e| e == event || *e == EventType::Wildcard)
+        };
+        let missing = REQUIRED_WEBHOOK_EVENTS
+            .iter()
+            .filter(|event| !subscribed(event))
+            .copied()
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let missing_list = missing
+            .iter()
+            .map(EventType::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match (
+            self.github_config.fix_webhook_events,
+            self.github_config.webhook_url(),
+            hooks.as_slice(),
+        ) {
+            (true, Some(_), [hook]) => {
+                info!(
+                    "{}/{} - Adding missing webhook events: {}",
+                    self.config.owner(),
+                    self.config.name(),
+                    missing_list
+                );
+                self.github
+                    .repos()
+                    .edit_hook(
+                        self.config.owner(),
+                        self.config.name(),
+                        hook.id,
+                        &github::client::EditHookRequest {
+                            add_events: Some(&missing),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+            }
+            _ => {
+                error!(
+                    "{}/{} - Webhook is missing subscriptions for events required by bors: {}",
+                    self.config.owner(),
+                    self.config.name(),
+                    missing_list
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn import_state(&mut self, snapshot: StateSnapshot) -> Result<()> {
+        info!(
+            "{}/{} - Importing state for {} pull requests",
+            self.config.owner(),
+            self.config.name(),
+            snapshot.pulls.len()
+        );
+
+        self.merge_queue = snapshot.merge_queue;
+        self.pulls.clear();
+        self.pulls.extend(snapshot.pulls);
+        self.recent_deliveries = RecentDeliveries::default();
+        for delivery_id in &snapshot.deliveries {
+            self.recent_deliveries.insert(delivery_id);
+        }
+        self.audit_log.replace(&snapshot.audit_log)?;
+        self.store.replace(snapshot.data)
+    }
+
+    async fn handle_webhook(&mut self, event: Event, delivery_id: String) -> Result<()> {
+        self.recent_deliveries.insert(&delivery_id);
+
+        if event.repository().is_none() {
+            if let Some(org) = event.organization() {
+                if org.login == self.config.owner() {
+                    return self.handle_org_event(&event);
+                }
+            }
+        }
+
+        // Events for a renamed or transferred repo arrive under its new owner and name
+        if let Event::Repository(e) = &event {
+            if (e.is_renamed() || e.is_transferred())
+                && e.previous_owner() == self.config.owner()
+                && e.previous_name() == self.config.name()
+            {
+                return self.handle_repository_moved(e);
+            }
+        }
+
+        // Verify that the event is from our configured repository
+        if !event
+            .repository()
+            .map(|r| r.owner.login == self.config.owner() && r.name == self.config.name())
+            .unwrap_or(false)
+        {
+            warn!("Recieved webhook intended for another repository");
+            return Ok(());
+        }
+
+        info!(
+            "{}/{} - Handling Webhook: event = '{:?}', id = {}",
+            self.config.owner(),
+            self.config.name(),
+            event.event_type(),
+            delivery_id
+        );
+
+        match &event {
+            Event::PullRequest(e) => self.handle_pull_request_event(e).await?,
+            Event::CheckRun(e) if e.action.is_rerequested() && e.check_run.name == BORS_CONTEXT => {
+                self.handle_rerequested_check(&e.check_run.head_sha).await?
+            }
+            Event::CheckRun(e) => self.handle_check_run_event(e),
+            Event::CheckSuite(e) if e.action.is_requested() => {
+                self.handle_requested_check_suite(e).await
+            }
+            // "Re-run all checks" rerequests every suite on the commit, each of which is only
+            // bors' to act on if it holds the bors check run
+            Event::CheckSuite(e)
+                if e.action.is_rerequested() && self.is_own_check_suite(&e.check_suite) =>
+            {
+                self.handle_rerequested_check(&e.check_suite.head_sha).await?
+            }
+            Event::Status(e) => self.handle_status_event(e),
+            Event::Push(e) => self.handle_push_event(e).await?,
+            Event::IssueComment(e)
+                // Only process commands from newly created comments
+                if e.action.is_created() && e.issue.is_pull_request() => {
+                    self.process_comment(
+                        &e.sender,
+                        e.issue.number,
+                        e.comment.body(),
+                        ReactionSubject::IssueComment {
+                            node_id: &e.comment.node_id,
+                            id: e.comment.id,
+                        },
+                    )
+                    .await?
+                }
+            Event::IssueComment(e)
+                // Only a few commands, e.g. `/bisect`, can be used on issues
+                if e.action.is_created() && self.is_issue_command(e.comment.body()) => {
+                    self.process_comment(
+                        &e.sender,
+                        e.issue.number,
+                        e.comment.body(),
+                        ReactionSubject::IssueComment {
+                            node_id: &e.comment.node_id,
+                            id: e.comment.id,
+                        },
+                    )
+                    .await?
+                }
+            Event::IssueComment(e)
+                if e.action.is_edited() && e.issue.is_pull_request() => {
+                    self.process_edited_comment(
+                        &e.sender,
+                        e.issue.number,
+                        e.comment.body(),
+                        e.changes.as_ref().and_then(|c| c.body.as_ref()).map(|b| b.from.as_str()),
+                        ReactionSubject::IssueComment {
+                            node_id: &e.comment.node_id,
+                            id: e.comment.id,
+                        },
+                    )
+                    .await?
+                }
+            Event::PullRequestReview(e) => self.handle_pull_request_review_event(e).await?,
+            Event::PullRequestReviewComment(e)
+                if e.action.is_created() => {
+                    self.process_comment(
+                        &e.sender,
+                        e.pull_request.number,
+                        e.comment.body(),
+                        ReactionSubject::ReviewComment {
+                            node_id: &e.comment.node_id,
+                            id: e.comment.id,
+                        },
+                    )
+                    .await?
+                }
+            Event::PullRequestReviewComment(e)
+                if e.action.is_edited() => {
+                    self.process_edited_comment(
+                        &e.sender,
+                        e.pull_request.number,
+                        e.comment.body(),
+                        e.changes.as_ref().and_then(|c| c.body.as_ref()).map(|b| b.from.as_str()),
+                        ReactionSubject::ReviewComment {
+                            node_id: &e.comment.node_id,
+                            id: e.comment.id,
+                        },
+                    )
+                    .await?
+                }
+            Event::WorkflowRun(e) => self.handle_workflow_run_event(e),
+            // Unsupported Event
+            _ => {}
+        }
+
+        self.process_merge_queue().await?;
+        self.process_merge_when_green().await?;
+        self.process_bisection().await?;
+
+        Ok(())
+    }
+
+    /// Follow the repository to its new owner and name, moving its on-disk state and checkout
+    fn handle_repository_moved(&mut self, event: &RepositoryEvent) -> Result<()> {
+        let old_repo = self.config.repo().clone();
+        let new_repo = Repo::from_repository(&event.repository);
+        warn!(
+            "{}/{} - Repository moved to {}/{}, update the configuration to match",
+            old_repo.owner(),
+            old_repo.name(),
+            new_repo.owner(),
+            new_repo.name()
+        );
+
+        self.config.rename(new_repo.owner(), new_repo.name());
+        self.store.relocate(&self.config)?;
+        self.queue_store.relocate(&self.config)?;
+        self.audit_log.relocate(&self.config)?;
+        self.git_repository.relocate(&self.config)?;
+        // Github App tokens are scoped to the repo by name
+        self.github = GithubClient::for_repo(
+            &self.github_config,
+            self.config.repo(),
+            self.github.read_only_switches(),
+        )?;
+
+        // PRs opened from a branch of the repo itself are pushed to under its new name
+        for mut pull in self.pulls.values_mut() {
+            if pull.head_repo.as_ref() == Some(&old_repo) {
+                pull.head_repo = Some(new_repo.clone());
+            }
+        }
+
+        self.audit_log.record(&AuditEntry::new(
+            &event.sender.login,
+            if event.is_renamed() {
+                "repository-renamed"
+            } else {
+                "repository-transferred"
+            },
+            None,
+            format!(
+                "{}/{} -> {}/{}",
+                old_repo.owner(),
+                old_repo.name(),
+                new_repo.owner(),
+                new_repo.name()
+            ),
+        ))
+    }
+
+    /// Handle an event delivered by the webhook of the organization owning the repository
+    fn handle_org_event(&mut self, event: &Event) -> Result<()> {
+        info!(
+            "{}/{} - Handling organization webhook: event = '{:?}'",
+            self.config.owner(),
+            self.config.name(),
+            event.event_type(),
+        );
+
+        // Users blocked by the organization can no longer interact with its repositories, so
+        // stop acting on any commands they left behind until they're unblocked. Users who were
+        // already ignored through `/ignore` stay ignored once unblocked
+        if let Event::OrgBlock(e) = event {
+            let user = e.blocked_user.login.clone();
+            let action = match e.action.as_str() {
+                "blocked" => {
+                    self.store.update(|data| {
+                        if data.ignored_users.insert(user.clone()) {
+                            data.org_blocked_users.insert(user.clone());
+                        }
+                    })?;
+                    "org-block"
+                }
+                "unblocked" => {
+                    self.store.update(|data| {
+                        if data.org_blocked_users.remove(&user) {
+                            data.ignored_users.remove(&user);
+                        }
+                    })?;
+                    "org-unblock"
+                }
+                _ => return Ok(()),
+            };
+            self.audit_log
+                .record(&AuditEntry::new(&e.sender.login, action, None, &user))?;
+        }
+
+        Ok(())
+    }
+
+    /// Merge the PRs waiting on the checks of their head commit once those checks have passed
+    async fn process_merge_when_green(&mut self) -> Result<()> {
+        let waiting = self
+            .pulls
+            .values()
+            .filter(|pr| pr.merge_when_green)
+            .map(|pr| pr.number)
+            .collect::<Vec<_>>();
+
+        for number in waiting {
+            let pull = &self.pulls[&number];
+            let result = TestSuiteResult::merge_when_green(&pull.head_test_results, &self.config);
+            let comment = match result {
+                TestSuiteResult::Pending | TestSuiteResult::TimedOut => continue,
+                TestSuiteResult::Failed { name, result } => {
+                    info!("pr #{} failed check '{}', not merging", number, name);
+                    Some(templates::render(
+                        &self.config,
+                        "test-failed",
+                        liquid::object!({
+                            "name": name,
+                            "url": result.details_url,
+                            "source": result.source.to_string(),
+                            "notify": pull.notify,
+                        }),
+                    )?)
+                }
+                TestSuiteResult::Passed => {
+                    info!("pr #{} passed its checks, merging", number);
+                    match self.merge_pull_directly(pull).await {
+                        // Github reports the merge on the PR itself
+                        Ok(()) => None,
+                        Err(e) => Some(templates::render(
+                            &self.config,
+                            "merge-failed",
+                            liquid::object!({
+                                "base": pull.base_ref_name,
+                                "error": format!("{:#}", e),
+                                "notify": pull.notify,
+                            }),
+                        )?),
+                    }
+                }
+            };
+
+            let mut pull = self.pulls.get_mut(&number).unwrap();
+            pull.stop_merge_when_green();
+            pull.notify.clear();
+            if let Some(comment) = comment {
+                self.github
+                    .issues()
+                    .create_comment(self.config.owner(), self.config.name(), number, &comment)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Merge a PR through Github's API, pinned to the head commit whose checks passed
+    async fn merge_pull_directly(&self, pull: &PullRequestState) -> Result<()> {
+        use github::client::{MergeMethod, MergePullRequest};
+
+        let merge_method = if pull.has_label(self.config.labels().squash()) {
+            MergeMethod::Squash
+        } else if pull.lands_with_merge_commit(&self.config) {
+            MergeMethod::Merge
+        } else {
+            MergeMethod::Rebase
+        };
+
+        self.github
+            .pulls()
+            .merge(
+                self.config.owner(),
+                self.config.name(),
+                pull.number,
+                MergePullRequest {
+                    commit_title: format!("{} (#{})", pull.title, pull.number),
+                    commit_message: pull.body.clone(),
+                    merge_method,
+                    sha: pull.head_ref_oid.to_string(),
+                },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Queue the PRs opened by the bots configured for auto-land once their checks have passed, as
+    /// long as they only change allowed paths and the day's limit hasn't been reached
+    async fn process_auto_land(&mut self) -> Result<()> {
+        let auto_land = self.config.auto_land();
+        if !auto_land.is_enabled() || self.github.is_read_only() {
+            return Ok(());
+        }
 
-        while let Some(request) = self.requests_rx.next().await {
-            if let Err(e) = self.handle_request(request).await {
-                error!("Error while handling request: {:?}", e);
-            }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let day = now / SECONDS_PER_DAY;
+        if !auto_land.is_window_open((now % SECONDS_PER_DAY / (60 * 60)) as u8) {
+            return Ok(());
         }
-    }
 
-    async fn handle_request(&mut self, request: Request) -> Result<()> {
-        use Request::*;
-        match request {
-            Webhook { event, delivery_id } => self.handle_webhook(event, delivery_id).await?,
+        // PRs that were closed or updated are reconsidered
+        let pulls = &self.pulls;
+        self.auto_land_skipped
+            .retain(|number, oid| pulls.get(number).is_some_and(|p| &p.head_ref_oid == oid));
+
+        let mut candidates = self
+            .pulls
+            .values()
+            .filter(|p| {
+                matches!(p.status, Status::InReview)
+                    && !p.is_draft
+                    && !p.merge_when_green
+                    && !self.auto_land_skipped.contains_key(&p.number)
+                    && p.author.as_deref().is_some_and(|a| auto_land.is_author(a))
+            })
+            .map(|p| p.number)
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+
+        for number in candidates {
+            if let Some(max) = auto_land.max_per_day() {
+                if self.store.data().auto_landed_on(day) >= max {
+                    info!("auto-land limit of {} PRs a day reached", max);
+                    break;
+                }
+            }
 
-            Request::GetState(oneshot) => {
-                if oneshot
-                    .send((self.merge_queue.clone(), self.pulls.clone()))
-                    .is_err()
-                {
-                    warn!("Unable to deliver current state, receiver dropped");
+            let pull = &self.pulls[&number];
+            let results = pull
+                .fetch_head_test_results(&self.config, &self.github)
+                .await?;
+            let skipped = match TestSuiteResult::head(&results, &self.config) {
+                TestSuiteResult::Pending => continue,
+                TestSuiteResult::Passed => {
+                    let files = pull.fetch_changed_files(&self.config, &self.github).await?;
+                    let disallowed = files
+                        .iter()
+                        .filter(|path| !auto_land.is_allowed_path(path))
+                        .map(String::as_str)
+                        .collect::<Vec<_>>();
+                    if disallowed.is_empty() {
+                        None
+                    } else {
+                        Some(format!("changes {}", disallowed.join(", ")))
+                    }
                 }
+                TestSuiteResult::TimedOut => Some("checks timed out".to_owned()),
+                TestSuiteResult::Failed { name, .. } => Some(format!("check '{}' failed", name)),
+            };
+
+            if let Some(reason) = skipped {
+                self.audit_log.record(&AuditEntry::new(
+                    "auto-land",
+                    "auto-land-skipped",
+                    Some(number),
+                    format!("{}: {}", pull.head_ref_oid, reason),
+                ))?;
+                self.auto_land_skipped
+                    .insert(number, pull.head_ref_oid.clone());
+                continue;
             }
 
-            Synchronize => self.synchronize().await?,
+            self.audit_log.record(&AuditEntry::new(
+                "auto-land",
+                "auto-land",
+                Some(number),
+                pull.head_ref_oid.to_string(),
+            ))?;
+            self.pulls
+                .get_mut(&number)
+                .unwrap()
+                .update_status(
+                    Status::queued(),
+                    &self.config,
+                    &self.github,
+                    self.project_board.as_ref(),
+                )
+                .await?;
+            self.store.update(|data| data.record_auto_landed(day))?;
+            info!("pr #{} queued by auto-land", number);
         }
 
         Ok(())
     }
 
-    async fn handle_webhook(&mut self, event: Event, delivery_id: String) -> Result<()> {
-        // Verify that the event is from our configured repository
-        if !event
-            .repository()
-            .map(|r| r.owner.login == self.config.owner() && r.name == self.config.name())
-            .unwrap_or(false)
-        {
-            warn!("Recieved webhook intended for another repository");
+    /// Detect pushes to the test branches which weren't made by bors, which could be used to have
+    /// CI test, and potentially pass, a commit other than the one bors intends to land
+    async fn handle_push_event(&mut self, event: &github::PushEvent) -> Result<()> {
+        let branch = match event.branch() {
+            Some(branch) => branch,
+            None => return Ok(()),
+        };
+        let tester = match self.test_branch_tester(branch) {
+            Some(tester) => tester,
+            None => return Ok(()),
+        };
+
+        // Every merge commit bors creates is recorded on the PR it was created for
+        let (expected_oid, created_by_bors) = self.expected_test_branch_oid(&tester, &event.after);
+        if created_by_bors && !event.deleted {
             return Ok(());
         }
 
-        info!(
-            "{}/{} - Handling Webhook: event = '{:?}', id = {}",
+        warn!(
+            "{}/{} - SECURITY: unexpected push to test branch '{}' by '{}': {} -> {}",
             self.config.owner(),
             self.config.name(),
-            event.event_type(),
-            delivery_id
+            branch,
+            event.sender.login,
+            event.before,
+            event.after
         );
+        self.audit_log.record(&AuditEntry::new(
+            &event.sender.login,
+            "unexpected-push",
+            None,
+            format!("{}: {} -> {}", branch, event.before, event.after),
+        ))?;
+
+        if !self.config.reset_test_branches() {
+            return Ok(());
+        }
 
-        match &event {
-            Event::PullRequest(e) => self.handle_pull_request_event(e).await?,
-            Event::CheckRun(e) => self.handle_check_run_event(e),
-            Event::Status(e) => self.handle_status_event(e),
-            Event::IssueComment(e) => {
-                // Only process commands from newly created comments
-                if e.action.is_created() && e.issue.is_pull_request() {
-                    self.process_comment(
-                        &e.sender.login,
-                        e.issue.number,
-                        e.comment.body(),
-                        &e.comment.node_id,
-                    )
-                    .await?
-                }
+        match expected_oid {
+            // A deleted branch can't be updated, it's pushed again from bors' own clone, which has
+            // the merge commit it created
+            Some(oid) if event.deleted => {
+                info!("Recreating test branch '{}' at {}", branch, oid);
+                self.git_repository.push_oid_to_branch(&oid, branch)?;
             }
-            Event::PullRequestReview(e) => self.handle_pull_request_review_event(e).await?,
-            Event::PullRequestReviewComment(e) => {
-                if e.action.is_created() {
-                    self.process_comment(
-                        &e.sender.login,
-                        e.pull_request.number,
-                        e.comment.body(),
-                        &e.comment.node_id,
+            Some(oid) => {
+                info!("Resetting test branch '{}' to {}", branch, oid);
+                self.github
+                    .git()
+                    .update_ref(
+                        self.config.owner(),
+                        self.config.name(),
+                        &format!("heads/{}", branch),
+                        &oid,
+                        true,
                     )
-                    .await?
-                }
+                    .await?;
             }
-            Event::WorkflowRun(e) => self.handle_workflow_run_event(e),
-            // Unsupported Event
-            _ => {}
+            None => warn!(
+                "Unable to reset test branch '{}', bors isn't currently testing a commit on it",
+                branch
+            ),
         }
 
-        self.process_merge_queue().await?;
-
         Ok(())
     }
 
+    /// What bors tests on `branch`, if it's one of its test branches
+    fn test_branch_tester(&self, branch: &str) -> Option<TestBranchTester> {
+        if branch == CANARY_BRANCH {
+            Some(TestBranchTester::Canary)
+        } else if self.config.test_branches().any(|b| b.name() == branch) {
+            Some(TestBranchTester::Queue(None))
+        } else {
+            self.config
+                .lanes()
+                .find(|lane| lane.test_branch() == branch)
+                .map(|lane| TestBranchTester::Queue(Some(lane.name().to_owned())))
+        }
+    }
+
+    /// Returns the commit bors is currently testing on the test branch of `tester`, along with
+    /// whether `oid` is a merge commit created by bors
+    fn expected_test_branch_oid(
+        &self,
+        tester: &TestBranchTester,
+        oid: &Oid,
+    ) -> (Option<Oid>, bool) {
+        // A queue or lane tests one PR, or batch, at a time, whose merge commit is the one pushed.
+        // For a batch that's the tip of the batch, which is only recorded on the PR being tested
+        let expected = self
+            .pulls
+            .values()
+            .filter_map(|pull| match (&pull.status, tester) {
+                (Status::Testing { merge_oid, .. }, TestBranchTester::Queue(lane))
+                    if &pull.lane == lane =>
+                {
+                    Some(merge_oid)
+                }
+                (Status::Canary { merge_oid, .. }, TestBranchTester::Canary) => Some(merge_oid),
+                _ => None,
+            })
+            .next()
+            .cloned();
+
+        // Test branches are also reset to the base of a PR whose land is canceled, which can't get
+        // anything untested landed
+        let created_by_bors = expected.as_ref() == Some(oid)
+            || self
+                .pulls
+                .values()
+                .any(|pull| pull.last_merge_oid.as_ref() == Some(oid) || &pull.base_ref_oid == oid);
+
+        (expected, created_by_bors)
+    }
+
+    /// Labels are a convenience, so failing to apply them mustn't hold up handling the PR's event
+    async fn label_by_paths(
+        config: &RepoConfig,
+        github: &GithubClient,
+        pull: &mut PullRequestState,
+    ) {
+        if let Err(e) = labeler::label_by_paths(config, github, pull).await {
+            warn!(
+                "pr #{}: unable to label by changed paths: {:#}",
+                pull.number, e
+            );
+        }
+    }
+
     async fn handle_pull_request_event(&mut self, event: &github::PullRequestEvent) -> Result<()> {
         use github::PullRequestEventAction;
 
@@ -187,9 +1462,21 @@ impl EventProcessor {
             event.action, event.pull_request.number
         );
 
+        // New commits may dismiss stale reviews, and a closed PR's decision is of no further use
+        if matches!(
+            event.action,
+            PullRequestEventAction::Synchronize | PullRequestEventAction::Closed
+        ) {
+            self.github.invalidate_review_decision(
+                self.config.owner(),
+                self.config.name(),
+                event.pull_request.number,
+            );
+        }
+
         match event.action {
             PullRequestEventAction::Synchronize => {
-                if let Some(pr) = self.pulls.get_mut(&event.pull_request.number) {
+                if let Some(mut pr) = self.pulls.get_mut(&event.pull_request.number) {
                     pr.update_head(
                         event.pull_request.head.sha.clone(),
                         &self.config,
@@ -197,6 +1484,7 @@ impl EventProcessor {
                         self.project_board.as_ref(),
                     )
                     .await?;
+                    Self::label_by_paths(&self.config, &self.github, &mut pr).await;
                 }
             }
             PullRequestEventAction::Opened | PullRequestEventAction::Reopened => {
@@ -223,11 +1511,11 @@ impl EventProcessor {
                             self.config.repo().owner(),
                             self.config.repo().name(),
                             state.number,
-                            ":exclamation: before this PR can be merged please make sure that you enable \
-                            [\"Allow edits from maintainers\"]\
-                            (https://help.github.com/en/github/collaborating-with-issues-and-pull-requests/allowing-changes-to-a-pull-request-branch-created-from-a-fork).\n\n\
-                            This is needed for tooling to be able to update this PR in-place so that Github can \
-                            properly recognize and mark it as merged once its merged into the upstream branch",
+                            &templates::render(
+                                &self.config,
+                                "enable-maintainer-edits",
+                                liquid::object!({}),
+                            )?,
                         )
                         .await?;
                 }
@@ -236,7 +1524,9 @@ impl EventProcessor {
                     board.create_card(&self.github, &mut state).await?;
                 }
 
-                if self.pulls.insert(state.number, state).is_some() {
+                Self::label_by_paths(&self.config, &self.github, &mut state).await;
+
+                if self.pulls.insert(state).is_some() {
                     warn!("Opened/Reopened event replaced an existing PullRequestState");
                 }
             }
@@ -249,11 +1539,29 @@ impl EventProcessor {
 
                 if merged {
                     info!("pr #{} successfully Merged!", event.pull_request.number);
+                    let day = activity::day_of(DateTime::now());
+                    self.store
+                        .update(|data| data.record_landed(day, event.pull_request.number))?;
                 }
 
                 // XXX Do we need to call into the MergeQueue to notify it that a PR was merged or
                 // closed?
                 if let Some(mut pull) = self.pulls.remove(&event.pull_request.number) {
+                    for payload in pull.queue_events.drain(..) {
+                        self.queue_hooks.emit(payload);
+                    }
+                    if pull.status.is_queued() || pull.status.is_testing() {
+                        let reason = if merged {
+                            "merged outside of bors"
+                        } else {
+                            "closed"
+                        };
+                        self.queue_hooks.emit(
+                            QueueHookPayload::new(QueueHookEvent::Cancelled, &self.config, &pull)
+                                .with_reason(reason),
+                        );
+                    }
+
                     if let Some(board) = &self.project_board {
                         board.delete_card(&self.github, &mut pull).await?;
                     }
@@ -261,38 +1569,130 @@ impl EventProcessor {
             }
             PullRequestEventAction::Labeled => {
                 if let Some(label) = &event.label {
-                    if let Some(pull) = self.pulls.get_mut(&event.pull_request.number) {
+                    if let Some(mut pull) = self.pulls.get_mut(&event.pull_request.number) {
                         pull.labels.insert(label.name.clone());
+                        if audit::is_audited_label(&self.config, &label.name) {
+                            self.audit_log.record(&AuditEntry::new(
+                                &event.sender.login,
+                                "label-added",
+                                Some(pull.number),
+                                &label.name,
+                            ))?;
+                        }
+
+                        // Applying the canary label acts as if `/canary` had been commented
+                        if label.name == self.config.labels().canary() {
+                            if pull.status.is_in_review() || pull.status.is_failed() {
+                                info!("Canary requested via label for pr #{}", pull.number);
+                                pull.request_canary(
+                                    &event.sender.login,
+                                    &self.config,
+                                    &self.github,
+                                )
+                                .await?;
+                            } else if pull.status.is_queued() || pull.status.is_testing() {
+                                // Nothing would ever remove the label from a queued PR, so it's
+                                // refused right away like `/canary` would be. A running canary
+                                // removes it once it finishes
+                                let msg = templates::render(
+                                    &self.config,
+                                    "canary-while-queued",
+                                    liquid::object!({ "sender": event.sender.login }),
+                                )?;
+                                pull.remove_label(&self.config, &self.github, &label.name)
+                                    .await?;
+                                pull.post_comment(&self.config, &self.github, &msg).await?;
+                            }
+                        }
                     }
                 }
             }
             PullRequestEventAction::Unlabeled => {
                 if let Some(label) = &event.label {
-                    if let Some(pull) = self.pulls.get_mut(&event.pull_request.number) {
+                    if let Some(mut pull) = self.pulls.get_mut(&event.pull_request.number) {
                         pull.labels.remove(&label.name);
+                        if audit::is_audited_label(&self.config, &label.name) {
+                            self.audit_log.record(&AuditEntry::new(
+                                &event.sender.login,
+                                "label-removed",
+                                Some(pull.number),
+                                &label.name,
+                            ))?;
+                        }
+                    }
+                }
+            }
+            PullRequestEventAction::ReviewRequested
+            | PullRequestEventAction::ReviewRequestRemoved => {
+                if let Some(mut pull) = self.pulls.get_mut(&event.pull_request.number) {
+                    pull.update_review_requests(&event.pull_request);
+                }
+            }
+            PullRequestEventAction::Locked | PullRequestEventAction::Unlocked => {
+                if let Some(mut pull) = self.pulls.get_mut(&event.pull_request.number) {
+                    pull.locked = event.pull_request.locked;
+                    if pull.locked {
+                        warn!(
+                            "pr #{} conversation was locked, comments are shown in its bors check \
+                            run instead",
+                            pull.number
+                        );
                     }
                 }
             }
             PullRequestEventAction::ConvertedToDraft => {
-                if let Some(pull) = self.pulls.get_mut(&event.pull_request.number) {
+                if let Some(mut pull) = self.pulls.get_mut(&event.pull_request.number) {
                     pull.is_draft = true;
                 }
             }
             PullRequestEventAction::ReadyForReview => {
-                if let Some(pull) = self.pulls.get_mut(&event.pull_request.number) {
+                if let Some(mut pull) = self.pulls.get_mut(&event.pull_request.number) {
                     pull.is_draft = false;
                 }
             }
             PullRequestEventAction::Edited => {
                 // TODO maybe factor this out and run it on every PullRequestEvent type
                 // Update PR state from Webhook
-                if let Some(pull) = self.pulls.get_mut(&event.pull_request.number) {
+                if let Some(mut pull) = self.pulls.get_mut(&event.pull_request.number) {
                     if event.pull_request.title != pull.title {
                         pull.title = event.pull_request.title.clone();
                     }
                     let body = event.pull_request.body.as_deref().unwrap_or("");
                     if body != pull.body {
                         pull.body = body.to_owned();
+
+                        // Kick the PR out of the queue if its checklist is no longer complete
+                        let unchecked = pull.unchecked_checklist_items();
+                        if self.config.require_checklist()
+                            && !unchecked.is_empty()
+                            && matches!(pull.status, Status::Queued(_) | Status::Testing { .. })
+                        {
+                            info!(
+                                "pr #{} checklist is incomplete, removing from the queue",
+                                pull.number
+                            );
+                            let comment = templates::render(
+                                &self.config,
+                                "checklist-dequeued",
+                                liquid::object!({ "items": unchecked }),
+                            )?;
+                            pull.update_status(
+                                Status::InReview,
+                                &self.config,
+                                &self.github,
+                                self.project_board.as_ref(),
+                            )
+                            .await?;
+                            self.github
+                                .issues()
+                                .create_comment(
+                                    self.config.owner(),
+                                    self.config.name(),
+                                    pull.number,
+                                    &comment,
+                                )
+                                .await?;
+                        }
                     }
 
                     pull.update_base_ref(
@@ -317,16 +1717,70 @@ impl EventProcessor {
         Ok(())
     }
 
-    fn pull_from_merge_oid(&mut self, oid: &github::Oid) -> Option<&mut PullRequestState> {
-        self.pulls
-            .iter_mut()
-            .find(|(_n, pr)| match &pr.status {
-                Status::Testing { merge_oid, .. } | Status::Canary { merge_oid, .. } => {
-                    merge_oid == oid
-                }
-                Status::InReview | Status::Queued(_) => false,
+    /// Record the result of a completed build. `timing` is when the build started and finished, or
+    /// `None` if it's unknown, in which case the build is taken to have started along with the
+    /// tests of the merge commit
+    fn record_build_result(
+        &mut self,
+        merge_oid: &github::Oid,
+        build_name: &str,
+        details_url: &str,
+        conclusion: github::Conclusion,
+        source: BuildResultSource,
+        timing: Option<(DateTime, DateTime)>,
+    ) {
+        if !self.config.is_trusted_build_result(build_name, &source) {
+            info!("Ignoring build result '{}' from {}", build_name, source);
+            return;
+        }
+
+        if let Some(bisection) = &mut self.bisection {
+            bisection.add_build_result(merge_oid, build_name, conclusion);
+        }
+
+        let run = if let Some(number) = self.pulls.number_from_merge_oid(merge_oid) {
+            let mut pr = self.pulls.get_mut(&number).expect("PR should exist");
+            pr.add_build_result(build_name, details_url, conclusion, source);
+
+            let (started_at, finished_at) = timing.unwrap_or_else(|| {
+                let now = DateTime::now();
+                let started_at = match &pr.status {
+                    Status::Testing {
+                        tests_started_at, ..
+                    }
+                    | Status::Canary {
+                        tests_started_at, ..
+                    } => now.checked_sub(tests_started_at.elapsed()),
+                    Status::InReview | Status::Failed { .. } | Status::Queued(_) => None,
+                };
+                (started_at.unwrap_or(now), now)
+            });
+            Some(CheckRunRecord {
+                name: build_name.to_owned(),
+                pr: pr.number,
+                head_oid: pr.head_ref_oid.clone(),
+                merge_oid: merge_oid.clone(),
+                started_at,
+                finished_at,
+                passed: matches!(conclusion, github::Conclusion::Success),
             })
-            .map(|(_n, pr)| pr)
+        } else {
+            let config = &self.config;
+            if let Some(mut pr) = self
+                .pulls
+                .values_mut()
+                .find(|pr| pr.tracks_head_checks(config) && &pr.head_ref_oid == merge_oid)
+            {
+                pr.add_head_build_result(build_name, details_url, conclusion, source, config);
+            }
+            None
+        };
+
+        if let Some(run) = run {
+            if let Err(e) = self.store.update(|data| data.record_check_run(run)) {
+                warn!("Unable to record check run: {:#}", e);
+            }
+        }
     }
 
     fn handle_check_run_event(&mut self, event: &github::CheckRunEvent) {
@@ -346,12 +1800,147 @@ impl EventProcessor {
             _ => return,
         };
 
-        if let Some(pr) = self.pull_from_merge_oid(&event.check_run.head_sha) {
-            pr.add_build_result(
-                &event.check_run.name,
-                &event.check_run.details_url,
-                conclusion,
-            );
+        self.record_build_result(
+            &event.check_run.head_sha,
+            &event.check_run.name,
+            &event.check_run.details_url,
+            conclusion,
+            BuildResultSource::CheckRun {
+                suite: event.check_run.check_suite.id,
+                app: event.check_run.app.slug.clone(),
+            },
+            Some((
+                event.check_run.started_at,
+                event.check_run.completed_at.unwrap_or_else(DateTime::now),
+            )),
+        );
+    }
+
+    /// Whether `suite` is the one holding the bors check run of a PR: it belongs to the app bors
+    /// authenticates as, on the head commit bors created its check run for
+    fn is_own_check_suite(&self, suite: &github::CheckSuite) -> bool {
+        self.github_config
+            .app
+            .as_ref()
+            .is_some_and(|app| app.id == suite.app.id)
+            && self.pulls.values().any(|pull| {
+                pull.check_run
+                    .as_ref()
+                    .is_some_and(|(head, _)| *head == suite.head_sha)
+            })
+    }
+
+    /// Re-evaluate the PRs whose head is `head_sha` after a re-run of the bors check was requested:
+    /// their approval is checked again and their status updated. A land which was interrupted is
+    /// only resumed by `/land resume`, since re-running the check isn't authorized like a command
+    async fn handle_rerequested_check(&mut self, head_sha: &Oid) -> Result<()> {
+        let numbers = self
+            .pulls
+            .values()
+            .filter(|p| &p.head_ref_oid == head_sha)
+            .map(|p| p.number)
+            .collect::<Vec<_>>();
+
+        // Re-running the check is the way to have bors look at the approval afresh
+        for &number in &numbers {
+            self.github
+                .invalidate_review_decision(self.config.owner(), self.config.name(), number);
+        }
+        let mut decisions = self
+            .github
+            .get_review_decisions(self.config.owner(), self.config.name(), &numbers)
+            .await?;
+
+        for number in numbers {
+            info!("Re-run of the bors check requested for pr #{}", number);
+            let decision = decisions
+                .remove(&number)
+                .expect("decision should be fetched");
+            let mut pull = self.pulls.get_mut(&number).expect("PR should exist");
+            pull.approved = decision.approved;
+            pull.approved_at = decision.approved_at;
+            pull.reviews = decision.reviews;
+
+            let blocked = (self.config.require_review() && !pull.has_valid_approval(&self.config))
+                || (self.config.strict_changes_requested()
+                    && !pull.changes_requested_by().is_empty());
+
+            let (state, description) = match &pull.status {
+                Status::Queued(_) | Status::Testing { .. } | Status::Canary { .. } => {
+                    (github::StatusEventState::Pending, None)
+                }
+                Status::InReview | Status::Failed { .. } if pull.merge_when_green => {
+                    // Re-run the gates on the PR's head, picking up checks re-run since
+                    let results = pull
+                        .fetch_head_test_results(&self.config, &self.github)
+                        .await?;
+                    pull.head_test_results = results;
+                    (github::StatusEventState::Pending, None)
+                }
+                Status::InReview | Status::Failed { .. } if blocked => (
+                    github::StatusEventState::Error,
+                    Some("Awaiting an approving review"),
+                ),
+                Status::InReview | Status::Failed { .. } => (
+                    github::StatusEventState::Error,
+                    Some("Not queued for landing"),
+                ),
+            };
+
+            self.github
+                .repos()
+                .create_status(
+                    self.config.owner(),
+                    self.config.name(),
+                    &head_sha.to_string(),
+                    &github::client::CreateStatusRequest {
+                        state,
+                        target_url: None,
+                        description,
+                        context: BORS_CONTEXT,
+                    },
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Github requests a check suite from bors when a commit is pushed. Each open PR the commit is
+    /// the head of is given a `bors` check run right away, so that the checks UI shows it's
+    /// waiting to be landed
+    async fn handle_requested_check_suite(&mut self, event: &github::CheckSuiteEvent) {
+        if !self.config.check_run() || self.github.is_read_only() {
+            return;
+        }
+
+        let head_sha = &event.check_suite.head_sha;
+        // The suite may be requested before the PR is updated with its new head
+        let numbers = self
+            .pulls
+            .values()
+            .filter(|pull| {
+                pull.head_ref_oid == *head_sha
+                    || event
+                        .check_suite
+                        .pull_requests
+                        .iter()
+                        .any(|pr| pr.number == pull.number)
+            })
+            .map(|pull| pull.number)
+            .collect::<Vec<_>>();
+
+        for number in numbers {
+            let mut pull = self.pulls.get_mut(&number).unwrap();
+            if let Err(e) = pull
+                .create_check_run(&self.config, &self.github, head_sha)
+                .await
+            {
+                warn!(
+                    "unable to create the bors check run of pr #{}: {}",
+                    number, e
+                );
+            }
         }
     }
 
@@ -370,17 +1959,20 @@ impl EventProcessor {
             _ => return,
         };
 
-        if let Some(pr) = self.pull_from_merge_oid(&event.workflow_run.head_sha) {
-            pr.add_build_result(
-                &event.workflow_run.name,
-                &event.workflow_run.html_url,
-                conclusion,
-            );
-        }
+        // Workflow runs are backed by a check suite created by the GitHub Actions app
+        self.record_build_result(
+            &event.workflow_run.head_sha,
+            &event.workflow_run.name,
+            &event.workflow_run.html_url,
+            conclusion,
+            BuildResultSource::CheckRun {
+                suite: event.workflow_run.check_suite_id,
+                app: "github-actions".to_owned(),
+            },
+            Some((event.workflow_run.created_at, event.workflow_run.updated_at)),
+        );
     }
 
-    // XXX This currently shoehorns github's statuses to fit into the new checks api. We should
-    // probably introduce a few types to distinguish between the two
     fn handle_status_event(&mut self, event: &github::StatusEvent) {
         // Skip the event if it hasn't completed
         let conclusion = match event.state {
@@ -390,60 +1982,373 @@ impl EventProcessor {
             github::StatusEventState::Error => github::Conclusion::Failure,
         };
 
-        if let Some(pr) = self.pull_from_merge_oid(&event.sha) {
-            pr.add_build_result(
-                &event.context,
-                &event.target_url.as_deref().unwrap_or(""),
-                conclusion,
-            );
-        }
+        self.record_build_result(
+            &event.sha,
+            &event.context,
+            event.target_url.as_deref().unwrap_or(""),
+            conclusion,
+            BuildResultSource::CommitStatus {
+                context: event.context.clone(),
+            },
+            None,
+        );
     }
 
     async fn process_merge_queue(&mut self) -> Result<()> {
+        // Landing requires pushing, so the queue is paused until writes are enabled again
+        if self.github.is_read_only() {
+            return Ok(());
+        }
+
+        self.sync_queue_order()?;
+        self.requeue_failed().await?;
+        self.expire_failures().await?;
+        self.expire_blocked().await?;
+        self.merge_queue.set_hooks(&self.queue_hooks);
+
         self.merge_queue
             .process_queue(
                 &self.config,
                 &self.github,
-                &mut self.git_repository,
+                self.git_repository.as_mut(),
                 self.project_board.as_ref(),
                 &mut self.pulls,
             )
-            .await
+            .await?;
+
+        for skew in self.merge_queue.take_merge_skews() {
+            if let Err(e) = self.store.update(|data| data.record_merge_skew(skew)) {
+                warn!("Unable to record merge skew: {:#}", e);
+            }
+        }
+
+        let queued = self.pulls.queued().map(|p| p.number).collect::<Vec<_>>();
+        self.summary_statuses
+            .update(&self.config, &self.github, &self.pulls, &queued)
+            .await;
+
+        Ok(())
+    }
+
+    /// Re-queue the PRs `/requeue-failed` is waiting to re-queue, in order, while fewer than
+    /// `requeue-batch-size` PRs are waiting in the queue. PRs which were updated, closed or
+    /// re-queued since, or are now blocked from landing, are skipped
+    async fn requeue_failed(&mut self) -> Result<()> {
+        if self.store.data().requeue_pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut pending = self.store.data().requeue_pending.clone();
+        let mut waiting = self.pulls.queued().count();
+        while waiting < self.config.requeue_batch_size() {
+            let number = match pending.pop_front() {
+                Some(number) => number,
+                None => break,
+            };
+            let mut pull = match self.pulls.get_mut(&number) {
+                Some(pull) if pull.status.is_failed() => pull,
+                _ => continue,
+            };
+            if let Some(reason) = pull.blocked_reason(&self.config) {
+                info!("not re-queuing pr #{} since {}", number, reason);
+                continue;
+            }
+
+            info!("re-queuing pr #{} after its land failed", number);
+            pull.update_status(
+                Status::queued(),
+                &self.config,
+                &self.github,
+                self.project_board.as_ref(),
+            )
+            .await?;
+            waiting += 1;
+        }
+
+        if pending.len() != self.store.data().requeue_pending.len() {
+            self.store.update(|data| data.requeue_pending = pending)?;
+        }
+        Ok(())
+    }
+
+    /// Return the PRs whose land failed more than `failed-status-hours` ago to review
+    async fn expire_failures(&mut self) -> Result<()> {
+        let max_age = self.config.failed_status_duration();
+        for mut pull in self.pulls.values_mut() {
+            if let Status::Failed { failed_at, .. } = &pull.status {
+                if failed_at.elapsed() >= max_age {
+                    info!(
+                        "pr #{}'s failure has expired, returning it to review",
+                        pull.number
+                    );
+                    pull.update_status(
+                        Status::InReview,
+                        &self.config,
+                        &self.github,
+                        self.project_board.as_ref(),
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return the queued PRs which have been blocked from landing for more than
+    /// `blocked-queue-minutes` to review, so that the queue and its ETAs only reflect PRs which
+    /// can land
+    async fn expire_blocked(&mut self) -> Result<()> {
+        let max_age = self.config.blocked_queue_duration();
+        for mut pull in self.pulls.values_mut() {
+            if !pull.status.is_queued() {
+                continue;
+            }
+
+            let reason = match pull.blocked_reason(&self.config) {
+                Some(reason) => reason,
+                None => {
+                    pull.blocked_since = None;
+                    continue;
+                }
+            };
+            let blocked_since = pull.blocked_since.get_or_insert_with(DateTime::now);
+            let max_age = match max_age {
+                Some(max_age) if blocked_since.elapsed() >= max_age => max_age,
+                _ => continue,
+            };
+
+            info!(
+                "pr #{} has been blocked while queued for too long ({}), returning it to review",
+                pull.number, reason
+            );
+            let comment = templates::render(
+                &self.config,
+                "blocked-dequeued",
+                liquid::object!({ "reason": reason, "minutes": max_age.as_secs() / 60 }),
+            )?;
+            pull.update_status(
+                Status::InReview,
+                &self.config,
+                &self.github,
+                self.project_board.as_ref(),
+            )
+            .await?;
+            pull.notify.clear();
+            self.github
+                .issues()
+                .create_comment(
+                    self.config.owner(),
+                    self.config.name(),
+                    pull.number,
+                    &comment,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn process_bisection(&mut self) -> Result<()> {
+        if self.github.is_read_only() {
+            return Ok(());
+        }
+
+        if let Some(bisection) = &mut self.bisection {
+            if !bisection
+                .process(&self.config, &self.github, self.git_repository.as_mut())
+                .await?
+            {
+                self.bisection = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forget the explicit position of PRs which have left the queue, so that they are ordered by
+    /// priority again if they are re-queued, and hand the order to the index of open PRs
+    fn sync_queue_order(&mut self) -> Result<()> {
+        let pulls = &self.pulls;
+        let in_queue = |number: &u64| {
+            pulls
+                .get(number)
+                .is_some_and(|p| p.status.is_queued() || p.status.is_testing())
+        };
+        if !self.store.data().queue_order.iter().all(in_queue) {
+            self.store
+                .update(|data| data.queue_order.retain(|number| in_queue(number)))?;
+        }
+        self.pulls.set_order(&self.store.data().queue_order);
+        Ok(())
+    }
+
+    pub(crate) fn command_context<'a>(
+        &'a mut self,
+        sender: &'a str,
+        pr_number: u64,
+    ) -> CommandContext<'a> {
+        self.pulls.set_order(&self.store.data().queue_order);
+        let mut failed = self
+            .pulls
+            .values()
+            .filter_map(|p| match &p.status {
+                Status::Failed { failed_at, .. } => Some((p.number, *failed_at)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        failed.sort_unstable_by_key(|(number, failed_at)| (*failed_at, *number));
+
+        CommandContext {
+            queued: self.pulls.queued().map(|p| p.number).collect(),
+            failed,
+            number: pr_number,
+            pull_request: self.pulls.get_mut(&pr_number),
+            repo: self.git_repository.as_mut(),
+            github: &self.github,
+            config: &self.config,
+            project_board: self.project_board.as_ref(),
+            store: &mut self.store,
+            audit_log: &self.audit_log,
+            bisection: &mut self.bisection,
+            team_members: &mut self.team_members,
+            sender,
+        }
+    }
+
+    /// Indicates if commands from `user` should be ignored, either because they are banned in the
+    /// config or were ignored via the `/ignore` command
+    fn is_ignored_user(&self, user: &str) -> bool {
+        self.config
+            .banned_users()
+            .any(|u| u.eq_ignore_ascii_case(user))
+            || self
+                .store
+                .data()
+                .ignored_users
+                .iter()
+                .any(|u| u.eq_ignore_ascii_case(user))
+    }
+
+    fn parse_command(&self, comment: &str) -> Option<Result<Command, ParseCommandError>> {
+        Command::from_comment(comment)
+            .or_else(|| Command::from_comment_with_username(comment, self.git_repository.user()))
+    }
+
+    fn is_issue_command(&self, comment: Option<&str>) -> bool {
+        matches!(
+            comment.and_then(|c| self.parse_command(c)),
+            Some(Ok(command)) if command.applies_to_issues()
+        )
     }
 
-    fn command_context<'a>(&'a mut self, sender: &'a str, pr_number: u64) -> CommandContext<'a> {
-        CommandContext {
-            number: pr_number,
-            pull_request: self.pulls.get_mut(&pr_number),
-            repo: &mut self.git_repository,
-            github: &self.github,
-            config: &self.config,
-            project_board: self.project_board.as_ref(),
-            sender,
+    /// Process a comment which was edited, provided its previous body didn't contain a valid
+    /// command, e.g. to allow fixing a typo in a command
+    async fn process_edited_comment(
+        &mut self,
+        user: &User,
+        pr_number: u64,
+        comment: Option<&str>,
+        previous: Option<&str>,
+        reaction_subject: ReactionSubject<'_>,
+    ) -> Result<()> {
+        // Only edits which changed the body are of interest
+        let previous = match previous {
+            Some(previous) if self.config.process_edited_comments() => previous,
+            _ => return Ok(()),
+        };
+
+        if let Some(Ok(_)) = self.parse_command(previous) {
+            info!("comment previously contained a valid command, ignoring edit");
+            return Ok(());
         }
+
+        self.process_comment(user, pr_number, comment, reaction_subject)
+            .await
     }
 
     async fn process_comment(
         &mut self,
-        user: &str,
+        user: &User,
         pr_number: u64,
         comment: Option<&str>,
-        node_id: &NodeId,
+        reaction_subject: ReactionSubject<'_>,
     ) -> Result<()> {
         info!("comment: {:#?}", comment);
 
-        match comment.and_then(|c| {
-            if let Some(cmd) = Command::from_comment(c) {
-                Some(cmd)
-            } else {
-                Command::from_comment_with_username(c, self.git_repository.user())
+        // bors' own comments may quote commands, e.g. in the help, which mustn't be executed
+        if comment.and_then(CommentMarker::parse).is_some() {
+            info!("Ignoring comment posted by bors");
+            return Ok(());
+        }
+
+        // Bots may echo commands back, which could otherwise lead to loops
+        if user.login.eq_ignore_ascii_case(self.git_repository.user())
+            || self
+                .config
+                .is_ignored_bot(&user.login, user.user_type == UserType::Bot)
+        {
+            info!("Ignoring comment posted by bot '{}'", user.login);
+            return Ok(());
+        }
+        let user = user.login.as_str();
+
+        match comment.and_then(|c| self.parse_command(c)) {
+            Some(Ok(_)) | Some(Err(_)) if self.is_ignored_user(user) => {
+                // Silently drop commands from ignored users, only leaving a record in the audit log
+                self.audit_log.record(&AuditEntry::new(
+                    user,
+                    "ignored-command",
+                    Some(pr_number),
+                    comment.unwrap_or_default(),
+                ))?;
+            }
+            Some(Ok(command))
+                if self.store.data().is_command_processed(
+                    reaction_subject.node_id().id(),
+                    command.fingerprint(),
+                ) =>
+            {
+                info!("Command has already been executed for this comment");
+            }
+            Some(Ok(command))
+                if !self.command_cooldowns.try_use(
+                    user,
+                    command.name(),
+                    self.config.command_cooldown(command.name()),
+                ) =>
+            {
+                info!(
+                    "Dropping '{}' command from '{}', it is on cooldown",
+                    command.name(),
+                    user
+                );
+                self.github
+                    .add_reaction(
+                        self.config.owner(),
+                        self.config.name(),
+                        reaction_subject,
+                        github::ReactionType::Confused,
+                    )
+                    .await?;
             }
-        }) {
             Some(Ok(command)) => {
                 info!("Valid Command");
 
+                self.store.update(|data| {
+                    data.record_processed_command(
+                        reaction_subject.node_id().id(),
+                        command.fingerprint(),
+                    )
+                })?;
+
                 self.github
-                    .add_reaction(node_id, github::ReactionType::Rocket)
+                    .add_reaction(
+                        self.config.owner(),
+                        self.config.name(),
+                        reaction_subject,
+                        github::ReactionType::Rocket,
+                    )
                     .await?;
 
                 let mut ctx = self.command_context(user, pr_number);
@@ -460,10 +2365,14 @@ impl EventProcessor {
                         self.config.repo().owner(),
                         self.config.repo().name(),
                         pr_number,
-                        &format!(
-                            ":exclamation: Invalid command\n\n{}",
-                            Command::help(&self.config, self.project_board.as_ref())
-                        ),
+                        &templates::render(
+                            &self.config,
+                            "invalid-command",
+                            liquid::object!({
+                                "help": Command::help(&self.config, self.project_board.as_ref())
+                                    .to_string(),
+                            }),
+                        )?,
                     )
                     .await?;
             }
@@ -479,8 +2388,10 @@ impl EventProcessor {
         use github::ReviewState;
 
         let pr_number = e.pull_request.number;
-        if let Some(pr) = self.pulls.get_mut(&pr_number) {
-            let mut approved = self
+        self.github
+            .invalidate_review_decision(self.config.owner(), self.config.name(), pr_number);
+        if let Some(mut pr) = self.pulls.get_mut(&pr_number) {
+            let mut decision = self
                 .github
                 .get_review_decision(
                     self.config.repo().owner(),
@@ -494,17 +2405,22 @@ impl EventProcessor {
             // querying Github directly reflects the old state, not the new state based on the
             // current webhook that is being processed. This checks for this potential scenario and
             // re-queries Github, hoping to get the new state
-            match (pr.approved, approved, e.review.state) {
+            match (pr.approved, decision.approved, e.review.state) {
                 (true, true, ReviewState::Dismissed)
                 | (true, true, ReviewState::ChangesRequested)
                 | (false, false, ReviewState::Approved) => {
                     info!("Re-Querying for Review status due to potential race condition");
                     info!(
                         "Before PR: {} Query: {} Review State: {:?}",
-                        pr.approved, approved, e.review.state
+                        pr.approved, decision.approved, e.review.state
                     );
                     tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-                    approved = self
+                    self.github.invalidate_review_decision(
+                        self.config.repo().owner(),
+                        self.config.repo().name(),
+                        pr_number,
+                    );
+                    decision = self
                         .github
                         .get_review_decision(
                             self.config.repo().owner(),
@@ -514,21 +2430,35 @@ impl EventProcessor {
                         .await?;
                     info!(
                         "After PR: {} Query: {} Review State: {:?}",
-                        pr.approved, approved, e.review.state
+                        pr.approved, decision.approved, e.review.state
+                    );
+                    // Github may still be behind, so the next lookup shouldn't trust this answer
+                    self.github.invalidate_review_decision(
+                        self.config.repo().owner(),
+                        self.config.repo().name(),
+                        pr_number,
                     );
                 }
                 _ => {}
             }
 
-            pr.approved = approved;
+            pr.approved = decision.approved;
+            pr.approved_at = decision.approved_at;
+            pr.reviews = decision.reviews;
+            // Github may not reflect the review being processed yet
+            pr.record_review(&e.review.user.login, e.review.state);
+            // Reviewing a PR fulfills the request for the reviewer's review
+            pr.update_review_requests(&e.pull_request);
         }
 
         if e.action.is_submitted() {
             self.process_comment(
-                &e.sender.login,
+                &e.sender,
                 e.pull_request.number,
                 e.review.body(),
-                &e.review.node_id,
+                ReactionSubject::Review {
+                    node_id: &e.review.node_id,
+                },
             )
             .await?
         }
@@ -536,8 +2466,118 @@ impl EventProcessor {
         Ok(())
     }
 
+    /// Look for protection settings of the branches bors pushes to which would make its pushes
+    /// fail. Failing to fetch them, e.g. because the token can't read them, isn't fatal
+    async fn check_branch_protection(&mut self) {
+        let (owner, name) = (self.config.owner(), self.config.name());
+        let user = self.git_repository.user().to_owned();
+        let is_admin = match self
+            .github
+            .repos()
+            .get_collaborator_permission_level(owner, name, &user)
+            .await
+        {
+            Ok(permission) => permission.into_inner() == "admin",
+            Err(e) => {
+                warn!("unable to check the permission of '{}': {}", user, e);
+                false
+            }
+        };
+
+        let bases = self
+            .pulls
+            .values()
+            .map(|pull| pull.base_ref_name.clone())
+            .collect::<BTreeSet<_>>();
+        let test_branches = self
+            .config
+            .test_branches()
+            .map(|branch| branch.name().to_owned())
+            .chain(self.config.lanes().map(|lane| lane.test_branch()))
+            .chain(std::iter::once(CANARY_BRANCH.to_owned()))
+            .collect::<BTreeSet<_>>();
+        let branches = bases
+            .into_iter()
+            .map(|branch| (branch, BranchRole::Base))
+            .chain(test_branches.into_iter().map(|b| (b, BranchRole::Test)));
+
+        let mut conflicts = Vec::new();
+        for (branch, role) in branches {
+            let protection = match self
+                .github
+                .repos()
+                .get_branch_protection(owner, name, &branch)
+                .await
+            {
+                Ok(protection) => protection.into_inner(),
+                // The branch isn't protected
+                Err(e) if e.is_not_found() => continue,
+                Err(e) => {
+                    warn!("unable to fetch the protection of '{}': {}", branch, e);
+                    continue;
+                }
+            };
+            conflicts.extend(protection::conflicts(
+                &self.config,
+                &branch,
+                role,
+                &protection,
+                &user,
+                is_admin,
+            ));
+        }
+
+        for conflict in &conflicts {
+            warn!("branch protection conflicts with bors: {}", conflict);
+        }
+        self.protection_conflicts = conflicts;
+    }
+
+    /// Carry the queue state saved before bors restarted over to the PRs which weren't updated in
+    /// the meantime, and resume the lands that were in progress
+    fn restore_queue(&mut self, saved: QueueData) {
+        let mut restored = 0;
+        for saved_pull in saved.pulls {
+            if let Some(mut pull) = self.pulls.get_mut(&saved_pull.number) {
+                if pull.restore_queue_state(saved_pull) {
+                    restored += 1;
+                }
+            }
+        }
+        self.merge_queue.restore(saved.merge_queue, &self.pulls);
+
+        info!(
+            "{}/{} - Restored the queue state of {} pull requests",
+            self.config.owner(),
+            self.config.name(),
+            restored
+        );
+    }
+
+    /// Refresh the PRs being tested or canaried, picking up the results of checks which completed
+    /// without bors receiving their webhooks
+    async fn refresh_tested_pulls(&mut self) {
+        let tested = self
+            .pulls
+            .values()
+            .filter(|p| p.status.is_testing() || p.status.is_canary())
+            .map(|p| p.number)
+            .collect::<Vec<_>>();
+        for number in tested {
+            if let Some(mut pull) = self.pulls.get_mut(&number) {
+                if let Err(e) = pull
+                    .refresh(&self.config, &self.github, self.project_board.as_ref())
+                    .await
+                {
+                    warn!("pr #{}: unable to refresh: {:#}", number, e);
+                }
+            }
+        }
+    }
+
     async fn synchronize(&mut self) -> Result<()> {
         info!("Synchronizing");
+        self.github.clear_review_decisions();
 
         let pulls = self
             .github
@@ -547,10 +2587,26 @@ impl EventProcessor {
 
         // TODO: Scrape the comments/Reviews of each PR to pull out reviewer/approval data
 
+        // The files changed by queued PRs are only fetched again for those pushed to since
+        let pulls = pulls
+            .into_iter()
+            .map(|mut pull| {
+                if let Some(previous) = self.pulls.remove(&pull.number) {
+                    pull.keep_changed_files(previous);
+                }
+                pull
+            })
+            .collect::<Vec<_>>();
         self.pulls.clear();
-        self.pulls
-            .extend(pulls.into_iter().map(|pr| (pr.number, pr)));
+        self.pulls.extend(pulls);
         self.merge_queue.reset();
+        let restored = match self.saved_queue.take() {
+            Some(saved) => {
+                self.restore_queue(saved);
+                true
+            }
+            None => false,
+        };
 
         // Sync and reset project board
         let board = crate::project_board::ProjectBoard::synchronize_or_init(
@@ -580,6 +2636,14 @@ impl EventProcessor {
 
         self.project_board = Some(board);
 
+        // Checks which completed on the restored merge commits while bors was down were missed
+        if restored {
+            self.refresh_tested_pulls().await;
+        }
+
+        self.check_branch_protection().await;
+        self.sync_status.record_synced();
+
         info!("Done Synchronizing");
         Ok(())
     }
@@ -589,25 +2653,35 @@ pub struct ActivePullRequestContext<'a> {
     pull_request: &'a mut PullRequestState,
     github: &'a GithubClient,
     config: &'a RepoConfig,
+    repo: &'a mut dyn GitBackend,
     project_board: Option<&'a ProjectBoard>,
+    audit_log: &'a AuditLog,
     sender: &'a str,
 }
 
 impl<'a> ActivePullRequestContext<'a> {
+    pub fn audit_log(&self) -> &AuditLog {
+        self.audit_log
+    }
+
     pub fn pr(&self) -> &PullRequestState {
-        &self.pull_request
+        self.pull_request
     }
 
     pub fn pr_mut(&mut self) -> &mut PullRequestState {
-        &mut self.pull_request
+        self.pull_request
+    }
+
+    pub fn github(&self) -> &'a GithubClient {
+        self.github
     }
 
-    pub fn github(&self) -> &GithubClient {
-        &self.github
+    pub fn git_repository(&mut self) -> &mut dyn GitBackend {
+        self.repo
     }
 
-    pub fn config(&self) -> &RepoConfig {
-        &self.config
+    pub fn config(&self) -> &'a RepoConfig {
+        self.config
     }
 
     #[allow(dead_code)]
@@ -616,20 +2690,13 @@ impl<'a> ActivePullRequestContext<'a> {
     }
 
     pub fn sender(&self) -> &str {
-        &self.sender
+        self.sender
     }
 
     pub async fn create_pr_comment(&self, body: &str) -> Result<()> {
-        self.github()
-            .issues()
-            .create_comment(
-                self.config().owner(),
-                self.config().name(),
-                self.pr().number,
-                body,
-            )
-            .await?;
-        Ok(())
+        self.pr()
+            .post_comment(self.config(), self.github(), body)
+            .await
     }
 
     pub async fn update_pr_status(&mut self, status: Status) -> Result<()> {
@@ -654,24 +2721,34 @@ impl<'a> ActivePullRequestContext<'a> {
 }
 
 pub struct CommandContext<'a> {
+    /// Numbers of the queued PRs, in the order they are landed
+    queued: Vec<u64>,
+    /// Numbers of the PRs whose land failed and when, in the order they failed
+    failed: Vec<(u64, DateTime)>,
     number: u64,
-    pull_request: Option<&'a mut PullRequestState>,
+    pull_request: Option<PullRequestMut<'a>>,
     github: &'a GithubClient,
     config: &'a RepoConfig,
-    repo: &'a mut GitRepository,
+    repo: &'a mut dyn GitBackend,
     project_board: Option<&'a ProjectBoard>,
+    store: &'a mut Store,
+    audit_log: &'a AuditLog,
+    bisection: &'a mut Option<Bisection>,
+    team_members: &'a mut TeamMembers,
     sender: &'a str,
 }
 
 impl<'a> CommandContext<'a> {
     pub async fn active_pull_request_context(&mut self) -> Option<ActivePullRequestContext<'_>> {
         if self.pull_request.is_none() {
-            let msg = format!(
-                "@{} :exclamation: Unable to run the provided command on a closed PR",
-                self.sender(),
-            );
             // Ignore the result from posting the comment
-            let _ = self.create_pr_comment(&msg).await;
+            if let Ok(msg) = templates::render(
+                self.config,
+                "closed-pr",
+                liquid::object!({ "sender": self.sender() }),
+            ) {
+                let _ = self.create_pr_comment(&msg).await;
+            }
         }
 
         if let Some(pull_request) = &mut self.pull_request {
@@ -679,7 +2756,9 @@ impl<'a> CommandContext<'a> {
                 pull_request,
                 github: self.github,
                 config: self.config,
+                repo: &mut *self.repo,
                 project_board: self.project_board,
+                audit_log: self.audit_log,
                 sender: self.sender,
             })
         } else {
@@ -701,16 +2780,24 @@ impl<'a> CommandContext<'a> {
         self.pull_request.as_deref_mut()
     }
 
-    pub fn git_repository(&mut self) -> &mut GitRepository {
-        &mut self.repo
+    pub fn git_repository(&mut self) -> &mut dyn GitBackend {
+        self.repo
     }
 
-    pub fn github(&self) -> &GithubClient {
-        &self.github
+    pub fn github(&self) -> &'a GithubClient {
+        self.github
     }
 
-    pub fn config(&self) -> &RepoConfig {
-        &self.config
+    pub fn config(&self) -> &'a RepoConfig {
+        self.config
+    }
+
+    pub fn bisection(&self) -> Option<&Bisection> {
+        self.bisection.as_ref()
+    }
+
+    pub fn set_bisection(&mut self, bisection: Bisection) {
+        *self.bisection = Some(bisection);
     }
 
     pub fn project_board(&self) -> Option<&'a ProjectBoard> {
@@ -718,10 +2805,35 @@ impl<'a> CommandContext<'a> {
     }
 
     pub fn sender(&self) -> &str {
-        &self.sender
+        self.sender
+    }
+
+    pub fn queued(&self) -> &[u64] {
+        &self.queued
+    }
+
+    pub fn failed(&self) -> &[(u64, DateTime)] {
+        &self.failed
+    }
+
+    pub fn store(&mut self) -> &mut Store {
+        self.store
+    }
+
+    pub fn audit_log(&self) -> &AuditLog {
+        self.audit_log
+    }
+
+    /// Replace the `@org/team` mentions among `mentions` by the members of each team
+    pub async fn expand_teams(&mut self, mentions: &[String]) -> Vec<String> {
+        self.team_members.expand(self.github, mentions).await
     }
 
     pub async fn create_pr_comment(&self, body: &str) -> Result<()> {
+        if let Some(pr) = self.pr() {
+            return pr.post_comment(self.config(), self.github(), body).await;
+        }
+
         self.github()
             .issues()
             .create_comment(
@@ -734,3 +2846,769 @@ impl<'a> CommandContext<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{EventProcessor, Request};
+    use crate::{
+        audit::AuditEntry,
+        command::Command,
+        git::mock::{GitOperation, MockGitBackend},
+        graphql::fake::FakeGithub,
+        state::{PullRequestState, Status},
+    };
+    use futures::channel::oneshot;
+    use github::{
+        test_fixtures::{EventPayload, IssueCommentEventBuilder, PullRequestEventBuilder},
+        Event, EventType, Oid,
+    };
+    use hyper::{Method, StatusCode};
+
+    fn processor(test: &str, github: &FakeGithub, git: &MockGitBackend) -> EventProcessor {
+        processor_with_config(test, "", github, git)
+    }
+
+    /// An `EventProcessor` for `bors-rs/bors` with the additional configuration `extra`
+    fn processor_with_config(
+        test: &str,
+        extra: &str,
+        github: &FakeGithub,
+        git: &MockGitBackend,
+    ) -> EventProcessor {
+        let config =
+            toml::from_str(&format!("owner = \"bors-rs\"\nname = \"bors\"\n{}", extra)).unwrap();
+        let dir = std::env::temp_dir().join(format!(
+            "bors-event-processor-test-{}-{}",
+            test,
+            std::process::id()
+        ));
+        EventProcessor::for_test(config, github.client(), git.clone(), &dir)
+    }
+
+    /// Add the open PR `number` to the processor's state, with its status set to `status`
+    fn add_pull(processor: &mut EventProcessor, number: u64, status: Status) {
+        let event = PullRequestEventBuilder::new("opened")
+            .number(number)
+            .head(&format!("pr-{}", number), &format!("a{:039x}", number))
+            .build();
+        let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+        pull.status = status;
+        processor.pulls_mut().insert(pull);
+    }
+
+    fn org_block(action: &str) -> Event {
+        let mut payload: serde_json::Value =
+            serde_json::from_str(include_str!("../../github/test-input/org-block-event.json"))
+                .unwrap();
+        payload["action"] = action.into();
+        Event::from_json(EventType::OrgBlock, payload.to_string().as_bytes()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn org_block_ignores_user_until_unblocked() {
+        let github = FakeGithub::start();
+        let mut processor = processor("org-block", &github, &MockGitBackend::new("bors"));
+
+        processor
+            .handle_webhook(org_block("blocked"), "1".to_owned())
+            .await
+            .unwrap();
+        assert!(processor.store.data().ignored_users.contains("spammer"));
+
+        processor
+            .handle_webhook(org_block("unblocked"), "2".to_owned())
+            .await
+            .unwrap();
+        assert!(processor.store.data().ignored_users.is_empty());
+
+        // A user ignored through `/ignore` stays ignored once their org unblocks them
+        processor
+            .store
+            .update(|data| {
+                data.ignored_users.insert("spammer".to_owned());
+            })
+            .unwrap();
+        processor
+            .handle_webhook(org_block("blocked"), "3".to_owned())
+            .await
+            .unwrap();
+        processor
+            .handle_webhook(org_block("unblocked"), "4".to_owned())
+            .await
+            .unwrap();
+        assert!(processor.store.data().ignored_users.contains("spammer"));
+    }
+
+    /// A re-run of every check in the suite of `app_id` on the head of PR `number`
+    fn check_suite_rerequested(number: u64, app_id: u64) -> Event {
+        let mut payload: serde_json::Value = serde_json::from_str(include_str!(
+            "../../github/test-input/check-suite-event.json"
+        ))
+        .unwrap();
+        payload["action"] = "rerequested".into();
+        payload["check_suite"]["head_sha"] = format!("a{:039x}", number).into();
+        payload["check_suite"]["app"]["id"] = app_id.into();
+        payload["repository"]["name"] = "bors".into();
+        payload["repository"]["owner"]["login"] = "bors-rs".into();
+        Event::from_json(EventType::CheckSuite, payload.to_string().as_bytes()).unwrap()
+    }
+
+    /// Authenticate `processor` as the app `id`, and give PR `number` an interrupted land and a
+    /// bors check run
+    fn resumable_land(processor: &mut EventProcessor, id: u64, number: u64, status: Status) {
+        processor.github_config = toml::from_str(&format!(
+            "[app]\nid = {}\ninstallation-id = 1\nprivate-key = \"key\"",
+            id
+        ))
+        .unwrap();
+        add_pull(processor, number, status);
+        let mut pull = processor.pulls_mut().remove(&number).unwrap();
+        pull.last_merge_oid = Some(Oid::from_str(format!("b{:039x}", number)));
+        pull.check_run = Some((pull.head_ref_oid.clone(), 1));
+        processor.pulls_mut().insert(pull);
+    }
+
+    #[tokio::test]
+    async fn state_snapshot_carries_audit_log_and_deliveries() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut exported = processor("snapshot-export", &github, &git);
+        exported
+            .handle_webhook(org_block("blocked"), "1".to_owned())
+            .await
+            .unwrap();
+        exported
+            .audit_log
+            .record(&AuditEntry::new("alice", "land", Some(1), ""))
+            .unwrap();
+
+        let (tx, rx) = oneshot::channel();
+        exported
+            .handle_request(Request::ExportState(tx))
+            .await
+            .unwrap();
+        let snapshot = rx.await.unwrap();
+        assert_eq!(snapshot.deliveries, ["1"]);
+        let actions = snapshot
+            .audit_log
+            .iter()
+            .map(|entry| entry.action.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(actions.last(), Some(&"land"));
+
+        let mut imported = processor("snapshot-import", &github, &git);
+        imported
+            .audit_log
+            .record(&AuditEntry::new("bob", "cancel", Some(2), ""))
+            .unwrap();
+        imported.import_state(snapshot).unwrap();
+        let imported_actions = imported
+            .audit_log
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect::<Vec<_>>();
+        assert_eq!(imported_actions, actions);
+        assert_eq!(imported.recent_deliveries.ids().collect::<Vec<_>>(), ["1"]);
+    }
+
+    #[tokio::test]
+    async fn own_check_suite_rerun_doesnt_resume_land() {
+        let github = FakeGithub::start();
+        let mut processor = processor("suite-rerun", &github, &MockGitBackend::new("bors"));
+        resumable_land(&mut processor, 15368, 1, Status::InReview);
+        github.respond_reviews("REVIEW_REQUIRED", &[]);
+
+        processor
+            .handle_webhook(check_suite_rerequested(1, 15368), "1".to_owned())
+            .await
+            .unwrap();
+        assert!(matches!(processor.pulls_mut()[&1].status, Status::InReview));
+        assert_eq!(
+            github.statuses("bors-rs", "bors", &format!("a{:039x}", 1)),
+            ["error"]
+        );
+        assert!(github
+            .bodies(Method::PATCH, "repos/bors-rs/bors/git/refs/heads/master")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn suite_rerun_doesnt_undo_cancel() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("suite-rerun-cancel", &github, &git);
+        let merge_oid = Oid::from_str(format!("b{:039x}", 1));
+        resumable_land(&mut processor, 15368, 1, Status::testing(merge_oid));
+        github.respond_reviews("REVIEW_REQUIRED", &[]);
+
+        let command = Command::from_comment("/cancel").unwrap().unwrap();
+        command
+            .execute(&mut processor.command_context("alice", 1))
+            .await
+            .unwrap();
+        assert!(matches!(processor.pulls_mut()[&1].status, Status::InReview));
+
+        processor
+            .handle_webhook(check_suite_rerequested(1, 15368), "1".to_owned())
+            .await
+            .unwrap();
+        let pull = &processor.pulls_mut()[&1];
+        assert!(matches!(pull.status, Status::InReview));
+        assert!(!pull.resume);
+        assert!(git.operations().is_empty());
+    }
+
+    #[tokio::test]
+    async fn foreign_check_suite_rerun_is_ignored() {
+        let github = FakeGithub::start();
+        let mut processor = processor("foreign-suite", &github, &MockGitBackend::new("bors"));
+        resumable_land(&mut processor, 1, 1, Status::InReview);
+
+        processor
+            .handle_webhook(check_suite_rerequested(1, 15368), "1".to_owned())
+            .await
+            .unwrap();
+        assert!(matches!(processor.pulls_mut()[&1].status, Status::InReview));
+        assert!(github.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_land_is_not_resumed_by_suite_rerun() {
+        let github = FakeGithub::start();
+        let mut processor = processor("failed-suite", &github, &MockGitBackend::new("bors"));
+        resumable_land(&mut processor, 15368, 1, Status::failed("ci failed", None));
+        github.respond_reviews("REVIEW_REQUIRED", &[]);
+
+        processor
+            .handle_webhook(check_suite_rerequested(1, 15368), "1".to_owned())
+            .await
+            .unwrap();
+        assert!(processor.pulls_mut()[&1].status.is_failed());
+        assert_eq!(
+            github.statuses("bors-rs", "bors", &format!("a{:039x}", 1)),
+            ["error"]
+        );
+    }
+
+    /// Stage PRs `numbers` to be re-queued, as `/requeue-failed` does
+    fn stage_requeue(processor: &mut EventProcessor, numbers: &[u64]) {
+        processor
+            .store
+            .update(|data| data.requeue_pending = numbers.iter().copied().collect())
+            .unwrap();
+    }
+
+    fn requeue_pending(processor: &EventProcessor) -> Vec<u64> {
+        processor
+            .store
+            .data()
+            .requeue_pending
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn failed_prs_are_requeued_in_stages() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor =
+            processor_with_config("requeue-stages", "requeue-batch-size = 2", &github, &git);
+        for number in 1..=4 {
+            add_pull(&mut processor, number, Status::failed("ci failed", None));
+        }
+        stage_requeue(&mut processor, &[3, 1, 4, 2]);
+
+        processor.requeue_failed().await.unwrap();
+        assert!(processor.pulls_mut()[&3].status.is_queued());
+        assert!(processor.pulls_mut()[&1].status.is_queued());
+        assert_eq!(requeue_pending(&processor), [4, 2]);
+
+        // Nothing more is re-queued while the queue is full
+        processor.requeue_failed().await.unwrap();
+        assert!(processor.pulls_mut()[&4].status.is_failed());
+        assert_eq!(requeue_pending(&processor), [4, 2]);
+
+        // Once a PR has landed, the next one takes its place
+        processor.pulls_mut().remove(&3);
+        processor.requeue_failed().await.unwrap();
+        assert!(processor.pulls_mut()[&4].status.is_queued());
+        assert!(processor.pulls_mut()[&2].status.is_failed());
+        assert_eq!(requeue_pending(&processor), [2]);
+    }
+
+    #[tokio::test]
+    async fn requeue_skips_prs_changed_since_failing() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor_with_config(
+            "requeue-skips",
+            "blocking-labels = [\"do-not-merge\"]",
+            &github,
+            &git,
+        );
+        for number in 1..=4 {
+            add_pull(&mut processor, number, Status::failed("ci failed", None));
+        }
+        let pushed = PullRequestEventBuilder::new("synchronize")
+            .number(2)
+            .head("pr-2", &"c".repeat(40))
+            .repository("bors-rs", "bors")
+            .build_event();
+        processor
+            .handle_webhook(pushed, "1".to_owned())
+            .await
+            .unwrap();
+        let closed = PullRequestEventBuilder::new("closed")
+            .number(3)
+            .repository("bors-rs", "bors")
+            .build_event();
+        processor
+            .handle_webhook(closed, "2".to_owned())
+            .await
+            .unwrap();
+        processor
+            .pulls_mut()
+            .get_mut(&4)
+            .unwrap()
+            .labels
+            .insert("do-not-merge".to_owned());
+        stage_requeue(&mut processor, &[1, 2, 3, 4]);
+
+        processor.requeue_failed().await.unwrap();
+        assert!(processor.pulls_mut()[&1].status.is_queued());
+        assert!(matches!(processor.pulls_mut()[&2].status, Status::InReview));
+        assert!(!processor.pulls_mut().contains_key(&3));
+        assert!(processor.pulls_mut()[&4].status.is_failed());
+        assert!(requeue_pending(&processor).is_empty());
+    }
+
+    #[tokio::test]
+    async fn prefetch_runs_in_background() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("prefetch", &github, &git);
+        add_pull(&mut processor, 1, Status::queued());
+        add_pull(&mut processor, 2, Status::InReview);
+        let base = processor.pulls_mut()[&1].base_ref_name.clone();
+
+        processor.prefetch().unwrap().await.unwrap();
+        // Only the heads of queued PRs are fetched
+        assert_eq!(
+            git.operations(),
+            [GitOperation::Prefetch {
+                base_refs: vec![base],
+                oids: vec![Oid::from_str(format!("a{:039x}", 1))],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn saved_queue_is_kept_until_restored() {
+        let github = FakeGithub::start();
+        let mut processor = processor("saved-queue", &github, &MockGitBackend::new("bors"));
+        let merge_oid = Oid::from_str(format!("c{:039x}", 1));
+        add_pull(&mut processor, 1, Status::testing(merge_oid));
+        add_pull(&mut processor, 2, Status::queued());
+        processor.save_queue();
+
+        // bors restarted, and #2 was pushed to while it was down
+        processor.saved_queue = processor.queue_store.load().unwrap();
+        processor.pulls_mut().clear();
+        add_pull(&mut processor, 1, Status::InReview);
+        add_pull(&mut processor, 2, Status::InReview);
+        processor.pulls_mut().get_mut(&2).unwrap().head_ref_oid =
+            Oid::from_str(format!("b{:039x}", 2));
+
+        // The saved state isn't overwritten before it has been restored
+        processor.save_queue();
+        let saved = processor.queue_store.load().unwrap().unwrap();
+        assert!(saved.pulls.iter().all(|p| !p.status.is_in_review()));
+
+        let saved = processor.saved_queue.take().unwrap();
+        processor.restore_queue(saved);
+        assert!(processor.pulls_mut()[&1].status.is_testing());
+        assert!(processor.pulls_mut()[&2].status.is_in_review());
+
+        processor.save_queue();
+        let saved = processor.queue_store.load().unwrap().unwrap();
+        assert!(saved.pulls[0].status.is_testing());
+        assert!(saved.pulls[1].status.is_in_review());
+    }
+
+    /// A push which moved `branch` of the repo to `after`
+    fn push_event(branch: &str, after: &Oid) -> github::PushEvent {
+        let mut payload: serde_json::Value =
+            serde_json::from_str(include_str!("../../github/test-input/push-event.json")).unwrap();
+        payload["ref"] = format!("refs/heads/{}", branch).into();
+        payload["after"] = after.to_string().into();
+        serde_json::from_value(payload).unwrap()
+    }
+
+    #[tokio::test]
+    async fn foreign_pushes_to_test_branches_are_reset() {
+        let github = FakeGithub::start();
+        let mut processor = processor("test-branch-push", &github, &MockGitBackend::new("bors"));
+        processor.config = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+            reset-test-branches = true
+
+            [[lanes]]
+            name = "docs"
+            paths = ["docs/*"]
+            "#,
+        )
+        .unwrap();
+        // #1 is tested by the main queue as the tip of a batch, which isn't remembered for
+        // resuming, and #2 by the docs lane
+        let batch_tip = Oid::from_str(format!("c{:039x}", 1));
+        let lane_merge = Oid::from_str(format!("c{:039x}", 2));
+        add_pull(&mut processor, 1, Status::testing(batch_tip.clone()));
+        add_pull(&mut processor, 2, Status::testing(lane_merge.clone()));
+        processor.pulls_mut().get_mut(&2).unwrap().lane = Some("docs".to_owned());
+
+        // bors' own pushes, and pushes to other branches, are left alone
+        let foreign = Oid::from_str(format!("f{:039x}", 1));
+        for (branch, oid) in [
+            ("auto", &batch_tip),
+            ("auto-docs", &lane_merge),
+            ("master", &foreign),
+        ] {
+            processor
+                .handle_push_event(&push_event(branch, oid))
+                .await
+                .unwrap();
+        }
+        assert!(processor.audit_log.entries().unwrap().is_empty());
+        assert!(github.received().is_empty());
+
+        // Each branch is reset to the commit of its own queue
+        for branch in ["auto", "auto-docs"] {
+            processor
+                .handle_push_event(&push_event(branch, &foreign))
+                .await
+                .unwrap();
+        }
+        let actions = processor
+            .audit_log
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect::<Vec<_>>();
+        assert_eq!(actions, ["unexpected-push", "unexpected-push"]);
+        for (branch, oid) in [("auto", &batch_tip), ("auto-docs", &lane_merge)] {
+            assert_eq!(
+                github.bodies(
+                    Method::PATCH,
+                    &format!("repos/bors-rs/bors/git/refs/heads/{}", branch)
+                ),
+                [serde_json::json!({ "sha": oid.to_string(), "force": true })]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn deleted_test_branches_are_recreated() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor_with_config(
+            "test-branch-delete",
+            "reset-test-branches = true",
+            &github,
+            &git,
+        );
+        let merge_oid = Oid::from_str(format!("c{:039x}", 1));
+        add_pull(&mut processor, 1, Status::testing(merge_oid.clone()));
+
+        let mut deleted = push_event("auto", &Oid::from_str("0".repeat(40)));
+        deleted.deleted = true;
+        processor.handle_push_event(&deleted).await.unwrap();
+
+        let entries = processor.audit_log.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "unexpected-push");
+        assert_eq!(
+            git.operations(),
+            [GitOperation::PushOid {
+                oid: merge_oid,
+                branch: "auto".to_owned(),
+            }]
+        );
+        assert!(github
+            .bodies(Method::PATCH, "repos/bors-rs/bors/git/refs/heads/auto")
+            .is_empty());
+    }
+
+    /// An edit of comment on PR #1 by `alice`, from `previous` to `body`
+    fn comment_edit(previous: &str, body: &str) -> Event {
+        IssueCommentEventBuilder::new("edited")
+            .number(1)
+            .body(body)
+            .previous_body(previous)
+            .sender("alice")
+            .on_pull_request(true)
+            .repository("bors-rs", "bors")
+            .build_event()
+    }
+
+    /// A processor with `extra` configuration, on whose repository `alice` may run commands
+    fn edit_processor(test: &str, extra: &str, github: &FakeGithub) -> EventProcessor {
+        github.respond(
+            Method::GET,
+            "repos/bors-rs/bors/collaborators/alice",
+            StatusCode::NO_CONTENT,
+            serde_json::Value::Null,
+        );
+        github.respond_reviews("REVIEW_REQUIRED", &[]);
+        processor_with_config(test, extra, github, &MockGitBackend::new("bors"))
+    }
+
+    #[tokio::test]
+    async fn edit_fixing_invalid_command_executes_it_once() {
+        let github = FakeGithub::start();
+        let mut processor = edit_processor(
+            "edit-fixes-command",
+            "process-edited-comments = true",
+            &github,
+        );
+
+        processor
+            .handle_webhook(comment_edit("/hepl", "/help"), "1".to_owned())
+            .await
+            .unwrap();
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(
+            comments[0].contains("Bors help and documentation"),
+            "{}",
+            comments[0]
+        );
+
+        // Neither a redelivery of the edit nor another edit to the same command runs it again
+        processor
+            .handle_webhook(comment_edit("/hepl", "/help"), "2".to_owned())
+            .await
+            .unwrap();
+        processor
+            .handle_webhook(comment_edit("/hlep", "/help"), "3".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(github.comments("bors-rs", "bors", 1).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn edit_of_valid_command_is_ignored() {
+        let github = FakeGithub::start();
+        let mut processor = edit_processor(
+            "edit-valid-command",
+            "process-edited-comments = true",
+            &github,
+        );
+
+        processor
+            .handle_webhook(comment_edit("/help", "/help squash"), "1".to_owned())
+            .await
+            .unwrap();
+        assert!(github.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn edited_comments_are_ignored_by_default() {
+        let github = FakeGithub::start();
+        let mut processor = edit_processor("edit-disabled", "", &github);
+
+        processor
+            .handle_webhook(comment_edit("/hepl", "/help"), "1".to_owned())
+            .await
+            .unwrap();
+        assert!(github.received().is_empty());
+    }
+
+    /// The canary label being added to PR #1
+    fn canary_labeled() -> Event {
+        PullRequestEventBuilder::new("labeled")
+            .number(1)
+            .label("bors-canary")
+            .repository("bors-rs", "bors")
+            .build_event()
+    }
+
+    #[tokio::test]
+    async fn canary_label_starts_canary() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("canary-label", &github, &git);
+        add_pull(&mut processor, 1, Status::InReview);
+
+        processor
+            .handle_webhook(canary_labeled(), "1".to_owned())
+            .await
+            .unwrap();
+        let pull = &processor.pulls_mut()[&1];
+        assert!(pull.status.is_canary());
+        assert!(pull.canary_requester.is_some());
+        assert!(pull.labels.contains("bors-canary"));
+        assert!(git
+            .operations()
+            .contains(&GitOperation::PushBranch("canary".into())));
+    }
+
+    #[tokio::test]
+    async fn canary_label_is_refused_on_queued_pr() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor("canary-label-queued", &github, &git);
+        add_pull(&mut processor, 1, Status::queued());
+
+        processor
+            .handle_webhook(canary_labeled(), "1".to_owned())
+            .await
+            .unwrap();
+        assert!(!processor
+            .pulls_mut()
+            .get(&1)
+            .is_some_and(|pull| pull.canary_requested || pull.status.is_canary()));
+        assert!(!git
+            .operations()
+            .contains(&GitOperation::PushBranch("canary".into())));
+        assert_eq!(
+            github
+                .received()
+                .iter()
+                .filter(|request| request.method == Method::DELETE
+                    && request.path == "repos/bors-rs/bors/issues/1/labels/bors-canary")
+                .count(),
+            1
+        );
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert!(
+            comments
+                .iter()
+                .any(|comment| comment.contains("currently queued for landing")),
+            "{:?}",
+            comments
+        );
+    }
+
+    /// `reviewer` submitting a review of PR #1 in `state`, e.g. `changes_requested`
+    fn review_submitted(reviewer: &str, state: &str) -> Event {
+        let mut payload: serde_json::Value = serde_json::from_str(include_str!(
+            "../../github/test-input/pull-request-review-event.json"
+        ))
+        .unwrap();
+        payload["action"] = "submitted".into();
+        payload["review"]["state"] = state.into();
+        payload["review"]["user"]["login"] = reviewer.into();
+        payload["review"]["body"] = serde_json::Value::Null;
+        payload["pull_request"]["number"] = 1.into();
+        payload["repository"]["owner"]["login"] = "bors-rs".into();
+        payload["repository"]["name"] = "bors".into();
+        Event::from_json(EventType::PullRequestReview, payload.to_string().as_bytes()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn change_request_blocks_queued_pr() {
+        let github = FakeGithub::start();
+        let git = MockGitBackend::new("bors");
+        let mut processor = processor_with_config(
+            "strict-changes-requested",
+            "strict-changes-requested = true\nblocked-queue-minutes = 0",
+            &github,
+            &git,
+        );
+        add_pull(&mut processor, 1, Status::queued());
+        processor.pulls_mut().get_mut(&1).unwrap().approved = true;
+        github.respond_reviews(
+            "CHANGES_REQUESTED",
+            &[
+                ("alice", "APPROVED", "2020-10-16T12:00:00Z"),
+                ("bob", "CHANGES_REQUESTED", "2020-10-16T13:00:00Z"),
+            ],
+        );
+
+        processor
+            .handle_webhook(review_submitted("bob", "changes_requested"), "1".to_owned())
+            .await
+            .unwrap();
+        assert!(matches!(processor.pulls_mut()[&1].status, Status::InReview));
+        assert!(git.operations().is_empty());
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(
+            comments[0].contains("changes were requested by @bob"),
+            "{}",
+            comments[0]
+        );
+    }
+
+    /// A change of the reviewers requested on PR #1 to `reviewers`
+    fn review_requests_changed(action: &str, reviewers: &[&str]) -> Event {
+        PullRequestEventBuilder::new(action)
+            .number(1)
+            .requested_reviewers(reviewers)
+            .repository("bors-rs", "bors")
+            .build_event()
+    }
+
+    /// `alice` commenting `body` on PR #1
+    fn comment(body: &str) -> Event {
+        IssueCommentEventBuilder::new("created")
+            .number(1)
+            .body(body)
+            .sender("alice")
+            .on_pull_request(true)
+            .repository("bors-rs", "bors")
+            .build_event()
+    }
+
+    #[tokio::test]
+    async fn review_request_blocks_land() {
+        let github = FakeGithub::start();
+        let mut processor = edit_processor(
+            "block-on-review-requests",
+            "block-on-review-requests = true",
+            &github,
+        );
+        add_pull(&mut processor, 1, Status::InReview);
+
+        processor
+            .handle_webhook(
+                review_requests_changed("review_requested", &["bob"]),
+                "1".to_owned(),
+            )
+            .await
+            .unwrap();
+        processor
+            .handle_webhook(comment("/land"), "2".to_owned())
+            .await
+            .unwrap();
+        assert!(matches!(processor.pulls_mut()[&1].status, Status::InReview));
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(
+            comments[0].contains("Reviews are still requested from @bob"),
+            "{}",
+            comments[0]
+        );
+
+        processor
+            .handle_webhook(
+                review_requests_changed("review_request_removed", &[]),
+                "3".to_owned(),
+            )
+            .await
+            .unwrap();
+        processor
+            .handle_webhook(comment("/land"), "4".to_owned())
+            .await
+            .unwrap();
+        let status = &processor.pulls_mut()[&1].status;
+        assert!(status.is_queued() || status.is_testing(), "{:?}", status);
+        assert_eq!(github.comments("bors-rs", "bors", 1).len(), 1);
+    }
+}
@@ -0,0 +1,436 @@
+//! An in-memory [`GitBackend`] for exercising bors without a real git checkout
+
+use super::{BackgroundTask, BisectMark, BisectStep, GitBackend, MergeOutcome, MergeSkew, Squash};
+use crate::{config::RepoConfig, state::Repo, Result};
+use github::Oid;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// An operation performed against a [`MockGitBackend`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum GitOperation {
+    FetchRef(String),
+    Rebase {
+        base_ref: String,
+        head_oid: Oid,
+        branch: String,
+        squash: Squash,
+    },
+    RebaseOnto {
+        base_ref: String,
+        onto: Oid,
+        head_oid: Oid,
+        branch: String,
+        squash: Squash,
+    },
+    CommitMessages {
+        base_ref: String,
+        head_oid: Oid,
+    },
+    Merge {
+        base_ref: String,
+        head_oid: Oid,
+        branch: String,
+    },
+    CheckUpToDate {
+        base_ref: String,
+        head_oid: Oid,
+    },
+    MergeSkew {
+        base_ref: String,
+        head_oid: Oid,
+    },
+    CherryPick {
+        target_ref: String,
+        branch: String,
+        base_oid: Oid,
+        head_oid: Oid,
+    },
+    PushBranch(String),
+    PushOid {
+        oid: Oid,
+        branch: String,
+    },
+    PushToRemote {
+        repo: Repo,
+        branch: String,
+        new_oid: Oid,
+    },
+    DeleteBranch(String),
+    BisectStart {
+        good: Oid,
+        bad: Oid,
+    },
+    BisectMark {
+        oid: Oid,
+        mark: BisectMark,
+    },
+    BisectReset,
+    Prefetch {
+        base_refs: Vec<String>,
+        oids: Vec<Oid>,
+    },
+    Maintenance,
+    Relocate(Repo),
+}
+
+/// A [`GitBackend`] which records every operation performed against it
+///
+/// Rebases and merges succeed with a freshly generated commit unless an outcome has been queued
+/// with [`MockGitBackend::push_outcome`]. Fetching a ref returns the commit registered with
+/// [`MockGitBackend::set_ref`], failing if there isn't one. Each step of a bisection returns the
+/// step queued with [`MockGitBackend::push_bisect_step`], failing if there isn't one. A PR's head
+/// is only up to date with its base if it was registered with [`MockGitBackend::set_up_to_date`],
+/// and has the commit messages registered with [`MockGitBackend::set_commit_messages`], if any.
+/// It's behind its base by the skew registered with [`MockGitBackend::set_merge_skew`], if any.
+///
+/// Clones share their state, so a test can hand a clone to what it exercises and keep the other to
+/// set up outcomes and check the operations performed.
+#[derive(Clone, Debug, Default)]
+pub struct MockGitBackend {
+    user: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    refs: HashMap<String, Oid>,
+    outcomes: VecDeque<MergeOutcome>,
+    bisect_steps: VecDeque<BisectStep>,
+    up_to_date: Vec<Oid>,
+    commit_messages: HashMap<Oid, Vec<String>>,
+    merge_skews: HashMap<Oid, MergeSkew>,
+    operations: Vec<GitOperation>,
+    next_commit: u64,
+}
+
+impl MockGitBackend {
+    pub fn new(user: &str) -> Self {
+        Self {
+            user: user.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Make fetching `r` return `oid`
+    pub fn set_ref(&self, r: &str, oid: Oid) {
+        self.state().refs.insert(r.to_owned(), oid);
+    }
+
+    /// Queue the outcome of the next rebase or merge
+    pub fn push_outcome(&self, outcome: MergeOutcome) {
+        self.state().outcomes.push_back(outcome);
+    }
+
+    /// Make `head_oid` appear to be built directly on top of its base
+    pub fn set_up_to_date(&self, head_oid: Oid) {
+        self.state().up_to_date.push(head_oid);
+    }
+
+    /// Make the PR whose head is `head_oid` appear to consist of commits with `messages`
+    pub fn set_commit_messages(&self, head_oid: Oid, messages: Vec<String>) {
+        self.state().commit_messages.insert(head_oid, messages);
+    }
+
+    /// Make the PR whose head is `head_oid` appear to be behind its base by `skew`
+    pub fn set_merge_skew(&self, head_oid: Oid, skew: MergeSkew) {
+        self.state().merge_skews.insert(head_oid, skew);
+    }
+
+    /// Queue the next step of a bisection
+    pub fn push_bisect_step(&self, step: BisectStep) {
+        self.state().bisect_steps.push_back(step);
+    }
+
+    /// The operations performed so far, in order
+    pub fn operations(&self) -> Vec<GitOperation> {
+        self.state().operations.clone()
+    }
+
+    fn record(&self, operation: GitOperation) {
+        self.state().operations.push(operation);
+    }
+
+    fn next_bisect_step(&self) -> Result<BisectStep> {
+        self.state()
+            .bisect_steps
+            .pop_front()
+            .ok_or_else(|| anyhow::anyhow!("no bisect step queued"))
+    }
+
+    fn next_outcome(&self) -> MergeOutcome {
+        let mut state = self.state();
+        match state.outcomes.pop_front() {
+            Some(outcome) => outcome,
+            None => {
+                state.next_commit += 1;
+                MergeOutcome::Merged(Oid::from_str(format!("{:040x}", state.next_commit)))
+            }
+        }
+    }
+}
+
+impl GitBackend for MockGitBackend {
+    fn user(&self) -> &str {
+        &self.user
+    }
+
+    fn fetch_ref(&mut self, r: &str) -> Result<Oid> {
+        self.record(GitOperation::FetchRef(r.to_owned()));
+        self.state()
+            .refs
+            .get(r)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("couldn't find remote ref {}", r))
+    }
+
+    fn fetch_and_rebase(
+        &mut self,
+        base_ref: &str,
+        head_oid: &Oid,
+        branch: &str,
+        _pr_number: u64,
+        squash: &Squash,
+    ) -> Result<MergeOutcome> {
+        self.record(GitOperation::Rebase {
+            base_ref: base_ref.to_owned(),
+            head_oid: head_oid.clone(),
+            branch: branch.to_owned(),
+            squash: squash.clone(),
+        });
+        Ok(self.next_outcome())
+    }
+
+    fn fetch_and_rebase_onto(
+        &mut self,
+        base_ref: &str,
+        onto: &Oid,
+        head_oid: &Oid,
+        branch: &str,
+        _pr_number: u64,
+        squash: &Squash,
+    ) -> Result<MergeOutcome> {
+        self.record(GitOperation::RebaseOnto {
+            base_ref: base_ref.to_owned(),
+            onto: onto.clone(),
+            head_oid: head_oid.clone(),
+            branch: branch.to_owned(),
+            squash: squash.clone(),
+        });
+        Ok(self.next_outcome())
+    }
+
+    fn fetch_commit_messages(&mut self, base_ref: &str, head_oid: &Oid) -> Result<Vec<String>> {
+        self.record(GitOperation::CommitMessages {
+            base_ref: base_ref.to_owned(),
+            head_oid: head_oid.clone(),
+        });
+        Ok(self
+            .state()
+            .commit_messages
+            .get(head_oid)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn fetch_and_merge(
+        &mut self,
+        base_ref: &str,
+        head_oid: &Oid,
+        branch: &str,
+        _pr_number: u64,
+        _title: &str,
+    ) -> Result<MergeOutcome> {
+        self.record(GitOperation::Merge {
+            base_ref: base_ref.to_owned(),
+            head_oid: head_oid.clone(),
+            branch: branch.to_owned(),
+        });
+        Ok(self.next_outcome())
+    }
+
+    fn fetch_and_check_up_to_date(&mut self, base_ref: &str, head_oid: &Oid) -> Result<bool> {
+        self.record(GitOperation::CheckUpToDate {
+            base_ref: base_ref.to_owned(),
+            head_oid: head_oid.clone(),
+        });
+        Ok(self.state().up_to_date.contains(head_oid))
+    }
+
+    fn fetch_merge_skew(&mut self, base_ref: &str, head_oid: &Oid) -> Result<MergeSkew> {
+        self.record(GitOperation::MergeSkew {
+            base_ref: base_ref.to_owned(),
+            head_oid: head_oid.clone(),
+        });
+        Ok(self
+            .state()
+            .merge_skews
+            .get(head_oid)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn fetch_and_cherry_pick(
+        &mut self,
+        target_ref: &str,
+        branch: &str,
+        base_oid: &Oid,
+        head_oid: &Oid,
+    ) -> Result<Option<Oid>> {
+        self.record(GitOperation::CherryPick {
+            target_ref: target_ref.to_owned(),
+            branch: branch.to_owned(),
+            base_oid: base_oid.clone(),
+            head_oid: head_oid.clone(),
+        });
+        match self.next_outcome() {
+            MergeOutcome::Merged(oid) => Ok(Some(oid)),
+            MergeOutcome::Conflict { .. } | MergeOutcome::Empty => Ok(None),
+        }
+    }
+
+    fn push_branch(&mut self, branch: &str) -> Result<()> {
+        self.record(GitOperation::PushBranch(branch.to_owned()));
+        Ok(())
+    }
+
+    fn push_oid_to_branch(&mut self, oid: &Oid, branch: &str) -> Result<()> {
+        self.record(GitOperation::PushOid {
+            oid: oid.clone(),
+            branch: branch.to_owned(),
+        });
+        Ok(())
+    }
+
+    fn push_to_remote(
+        &mut self,
+        repo: &Repo,
+        branch: &str,
+        _old_oid: &Oid,
+        new_oid: &Oid,
+    ) -> Result<()> {
+        self.record(GitOperation::PushToRemote {
+            repo: repo.clone(),
+            branch: branch.to_owned(),
+            new_oid: new_oid.clone(),
+        });
+        Ok(())
+    }
+
+    fn delete_branch(&mut self, branch: &str) -> Result<()> {
+        self.record(GitOperation::DeleteBranch(branch.to_owned()));
+        Ok(())
+    }
+
+    fn bisect_start(&mut self, good: &Oid, bad: &Oid) -> Result<BisectStep> {
+        self.record(GitOperation::BisectStart {
+            good: good.clone(),
+            bad: bad.clone(),
+        });
+        self.next_bisect_step()
+    }
+
+    fn bisect_mark(&mut self, oid: &Oid, mark: BisectMark) -> Result<BisectStep> {
+        self.record(GitOperation::BisectMark {
+            oid: oid.clone(),
+            mark,
+        });
+        self.next_bisect_step()
+    }
+
+    fn bisect_reset(&mut self) -> Result<()> {
+        self.record(GitOperation::BisectReset);
+        Ok(())
+    }
+
+    fn prefetch(&mut self, base_refs: Vec<String>, oids: Vec<Oid>) -> BackgroundTask {
+        let git = self.clone();
+        Box::new(move || {
+            git.record(GitOperation::Prefetch { base_refs, oids });
+            Ok(())
+        })
+    }
+
+    fn maintenance(&mut self) -> BackgroundTask {
+        let git = self.clone();
+        Box::new(move || {
+            git.record(GitOperation::Maintenance);
+            Ok(())
+        })
+    }
+
+    fn relocate(&mut self, config: &RepoConfig) -> Result<()> {
+        self.record(GitOperation::Relocate(config.repo().clone()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GitOperation, MockGitBackend};
+    use crate::git::{GitBackend, MergeOutcome, Squash};
+    use github::Oid;
+
+    #[test]
+    fn records_operations() {
+        let mut git = MockGitBackend::new("bors");
+        let head = Oid::from_str("1".repeat(40));
+
+        git.push_outcome(MergeOutcome::Conflict {
+            files: vec!["README.md".into()],
+        });
+        assert!(matches!(
+            git.fetch_and_rebase("main", &head, "auto", 1, &Squash::None)
+                .unwrap(),
+            MergeOutcome::Conflict { .. }
+        ));
+
+        let merged = match git
+            .fetch_and_rebase("main", &head, "auto", 1, &Squash::KeepFirst)
+            .unwrap()
+        {
+            MergeOutcome::Merged(oid) => oid,
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        };
+        git.push_oid_to_branch(&merged, "auto").unwrap();
+
+        assert!(git.fetch_ref("missing").is_err());
+        assert_eq!(
+            git.operations().last(),
+            Some(&GitOperation::FetchRef("missing".into()))
+        );
+        assert_eq!(git.operations().len(), 4);
+    }
+
+    #[test]
+    fn commit_messages() {
+        let mut git = MockGitBackend::new("bors");
+        let head = Oid::from_str("1".repeat(40));
+        assert!(git.fetch_commit_messages("main", &head).unwrap().is_empty());
+
+        git.set_commit_messages(head.clone(), vec!["First".into(), "Second".into()]);
+        assert_eq!(
+            git.fetch_commit_messages("main", &head).unwrap(),
+            vec!["First".to_owned(), "Second".to_owned()]
+        );
+
+        let squash = Squash::Message("First\n\nSecond".into());
+        git.fetch_and_rebase("main", &head, "auto", 1, &squash)
+            .unwrap();
+        assert_eq!(
+            git.operations().last(),
+            Some(&GitOperation::Rebase {
+                base_ref: "main".into(),
+                head_oid: head,
+                branch: "auto".into(),
+                squash,
+            })
+        );
+    }
+}
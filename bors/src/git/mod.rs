@@ -0,0 +1,1161 @@
+//! Git operations performed against a checkout of a GitHub repository
+
+use crate::{
+    config::{GitConfig, RepoConfig, TENANTS_DIR},
+    state::Repo,
+    Result,
+};
+use anyhow::{anyhow, Context};
+use github::{DateTime, Oid};
+use log::{debug, info, warn};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+use tokio::task::JoinHandle;
+
+pub mod mock;
+
+const REPOS_DIR: &str = "repos";
+
+/// Printed by `git bisect` when only skipped commits are left to test
+const BISECT_EXHAUSTED: &str = "We cannot bisect more";
+
+/// Number of times a shallow repository's history is deepened looking for a merge base before
+/// falling back to fetching its full history
+const MAX_DEEPEN_ATTEMPTS: u32 = 4;
+
+/// The local ref [`GitBackend::fetch_ref`] fetches into, so that what it resolves can't be
+/// replaced by another fetch's `FETCH_HEAD`
+const FETCHED_REF: &str = "refs/bors/fetched";
+
+/// Git work, e.g. housekeeping, which is run on a blocking thread by [`BackgroundGit`] while the
+/// event processor carries on processing events. A task takes the checkout's lock while it uses
+/// the checkout, so foreground git operations wait for it rather than racing it
+pub type BackgroundTask = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// Runs [`BackgroundTask`]s, one at a time
+#[derive(Clone, Debug, Default)]
+pub struct BackgroundGit {
+    busy: Arc<AtomicBool>,
+}
+
+impl BackgroundGit {
+    /// Run `task` on a blocking thread, unless the previous task is still running in which case
+    /// `what` is skipped until it's next requested
+    pub fn spawn(&self, what: &'static str, task: BackgroundTask) -> Option<JoinHandle<()>> {
+        if self.busy.swap(true, Ordering::AcqRel) {
+            info!(
+                "skipping {}, the previous background git task is still running",
+                what
+            );
+            return None;
+        }
+
+        let busy = self.busy.clone();
+        Some(tokio::task::spawn_blocking(move || {
+            if let Err(e) = task() {
+                warn!("{} failed: {:#}", what, e);
+            }
+            busy.store(false, Ordering::Release);
+        }))
+    }
+}
+
+/// The outcome of attempting to combine a PR with its base branch
+#[derive(Debug)]
+pub enum MergeOutcome {
+    /// The PR was successfully rebased or merged, resulting in the provided commit
+    Merged(Oid),
+    /// The PR conflicts with its base branch in the listed files
+    Conflict { files: Vec<String> },
+    /// The PR's changes are already present in its base branch
+    Empty,
+}
+
+/// How the commits of a PR are combined when it's rebased onto its base branch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Squash {
+    /// Keep each of the PR's commits
+    None,
+    /// Squash the PR into its first commit, keeping that commit's message
+    KeepFirst,
+    /// Squash the PR into a single commit with the provided message
+    Message(String),
+}
+
+/// How far a PR's head is behind the tip of its base branch
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeSkew {
+    /// Number of commits on the base branch which the PR's head doesn't contain
+    pub commits_behind: usize,
+    /// When the oldest of those commits was committed, `None` if the PR is up to date
+    pub behind_since: Option<DateTime>,
+}
+
+/// The result of testing a commit during a bisection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BisectMark {
+    Good,
+    Bad,
+    /// The commit couldn't be tested, e.g. because CI didn't report a result in time
+    Skip,
+}
+
+impl BisectMark {
+    fn as_str(self) -> &'static str {
+        match self {
+            BisectMark::Good => "good",
+            BisectMark::Bad => "bad",
+            BisectMark::Skip => "skip",
+        }
+    }
+}
+
+/// The next step of a bisection
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BisectStep {
+    /// The provided commit needs to be tested
+    Test(Oid),
+    /// The provided commit is the first bad commit
+    Culprit(Oid),
+    /// Commits which were skipped leave any of these as the first bad commit
+    Inconclusive(Vec<Oid>),
+}
+
+/// The git operations bors needs to perform in order to test and land PRs
+///
+/// [`GitRepository`] implements this by shelling out to `git` in an on-disk checkout while
+/// [`mock::MockGitBackend`] records the operations performed so that the queue and command logic
+/// can be exercised without a real checkout.
+pub trait GitBackend: std::fmt::Debug + Send + Sync {
+    /// The name of the user commits are authored as
+    fn user(&self) -> &str;
+
+    /// Fetch `r` from the remote, returning the commit it points to
+    fn fetch_ref(&mut self, r: &str) -> Result<Oid>;
+
+    /// Rebase `head_oid` onto `base_ref` on `branch`, squashing its commits as requested
+    fn fetch_and_rebase(
+        &mut self,
+        base_ref: &str,
+        head_oid: &Oid,
+        branch: &str,
+        pr_number: u64,
+        squash: &Squash,
+    ) -> Result<MergeOutcome>;
+
+    /// Rebase the commits `head_oid` adds on top of `base_ref` onto `onto` on `branch`, where
+    /// `onto` is built on `base_ref`, e.g. the merge commit of another PR tested in the same batch
+    fn fetch_and_rebase_onto(
+        &mut self,
+        base_ref: &str,
+        onto: &Oid,
+        head_oid: &Oid,
+        branch: &str,
+        pr_number: u64,
+        squash: &Squash,
+    ) -> Result<MergeOutcome>;
+
+    /// The messages of the commits `head_oid` adds on top of `base_ref`, oldest first
+    fn fetch_commit_messages(&mut self, base_ref: &str, head_oid: &Oid) -> Result<Vec<String>>;
+
+    /// Create a merge commit of `head_oid` into `base_ref` on `branch`, preserving the PR's
+    /// original commits
+    fn fetch_and_merge(
+        &mut self,
+        base_ref: &str,
+        head_oid: &Oid,
+        branch: &str,
+        pr_number: u64,
+        title: &str,
+    ) -> Result<MergeOutcome>;
+
+    /// Fetch `base_ref` and `head_oid`, indicating if `head_oid` is built directly on top of the
+    /// tip of `base_ref`, in which case `base_ref` can be fast-forwarded to it
+    fn fetch_and_check_up_to_date(&mut self, base_ref: &str, head_oid: &Oid) -> Result<bool>;
+
+    /// Fetch `base_ref` and `head_oid`, measuring how far `head_oid` is behind the tip of
+    /// `base_ref`
+    fn fetch_merge_skew(&mut self, base_ref: &str, head_oid: &Oid) -> Result<MergeSkew>;
+
+    /// Cherry-pick the commits in `base_oid..head_oid` onto `target_ref` on `branch`, returning
+    /// `None` if they don't apply cleanly
+    fn fetch_and_cherry_pick(
+        &mut self,
+        target_ref: &str,
+        branch: &str,
+        base_oid: &Oid,
+        head_oid: &Oid,
+    ) -> Result<Option<Oid>>;
+
+    /// Force push the local `branch` to the remote
+    fn push_branch(&mut self, branch: &str) -> Result<()>;
+
+    /// Force push `oid` to `branch` on the remote
+    fn push_oid_to_branch(&mut self, oid: &Oid, branch: &str) -> Result<()>;
+
+    /// Push `new_oid` to `branch` in `repo`, provided it still points to `old_oid`
+    fn push_to_remote(
+        &mut self,
+        repo: &Repo,
+        branch: &str,
+        old_oid: &Oid,
+        new_oid: &Oid,
+    ) -> Result<()>;
+
+    /// Delete the local `branch`, e.g. one a PR was only tried out on
+    fn delete_branch(&mut self, branch: &str) -> Result<()>;
+
+    /// Start bisecting the first-parent history between `good` and `bad`, abandoning any
+    /// bisection already in progress
+    fn bisect_start(&mut self, good: &Oid, bad: &Oid) -> Result<BisectStep>;
+
+    /// Record the result of testing `oid` in the bisection in progress
+    fn bisect_mark(&mut self, oid: &Oid, mark: BisectMark) -> Result<BisectStep>;
+
+    /// Abandon the bisection in progress
+    fn bisect_reset(&mut self) -> Result<()>;
+
+    /// Fetch `base_refs` and the commits `oids` ahead of time so that their objects are already
+    /// present locally when they're needed, to be run in the background
+    fn prefetch(&mut self, base_refs: Vec<String>, oids: Vec<Oid>) -> BackgroundTask;
+
+    /// Periodic housekeeping, e.g. garbage collecting unreachable objects, to be run in the
+    /// background
+    fn maintenance(&mut self) -> BackgroundTask;
+
+    /// Move the checkout to the location for the repo `config` pertains to and point its remote
+    /// at the repo, after the repo was renamed or transferred
+    fn relocate(&mut self, config: &RepoConfig) -> Result<()>;
+}
+
+/// The directory the repo `config` pertains to is checked out in
+fn checkout_directory(config: &RepoConfig) -> Result<PathBuf> {
+    let mut directory = config.tenant_directory()?;
+    directory.push(REPOS_DIR);
+    directory.push(config.owner());
+    directory.push(config.name());
+    Ok(directory)
+}
+
+/// Check that the on-disk checkout of the repo `config` pertains to exists and is a usable git
+/// repository
+pub fn check_checkout(config: &RepoConfig) -> Result<()> {
+    let directory = checkout_directory(config)?;
+    if Git::new().current_dir(&directory).is_git_repo()? {
+        Ok(())
+    } else {
+        Err(anyhow!("{} is not a git repository", directory.display()))
+    }
+}
+
+/// Serializes every use of a checkout, foreground and background, since concurrent git commands
+/// race on ref locks and `FETCH_HEAD`
+#[derive(Clone, Debug, Default)]
+struct CheckoutLock(Arc<Mutex<()>>);
+
+impl CheckoutLock {
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        // The lock guards no data, so a panic while holding it leaves nothing inconsistent
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug)]
+pub struct GitRepository {
+    directory: PathBuf,
+    checkout: CheckoutLock,
+    #[allow(dead_code)]
+    github_repo: Repo,
+    git_config: GitConfig,
+}
+
+impl GitRepository {
+    pub fn from_config(git_config: &GitConfig, config: &RepoConfig) -> Result<Self> {
+        let github_repo = config.repo().clone();
+        let git_config = git_config.clone();
+        let directory = checkout_directory(config)?;
+
+        if !Git::new().current_dir(&directory).is_git_repo()? {
+            info!(
+                "cloning '{}' to '{}'",
+                github_repo.to_github_ssh_url(),
+                directory.display()
+            );
+            Git::new().with_ssh(&git_config).clone(
+                &directory,
+                &github_repo,
+                git_config.clone_filter.as_deref(),
+                git_config.shallow_depth,
+            )?;
+        } else {
+            info!("using existing on-disk repo at {}", directory.display());
+        }
+
+        if !Git::new()
+            .current_dir(&directory)
+            .remote_matches_github_repo(&github_repo)?
+        {
+            return Err(anyhow!(
+                "on-disk repo's 'origin' remote doesn't match config"
+            ));
+        }
+
+        Ok(Self {
+            directory,
+            checkout: CheckoutLock::default(),
+            github_repo,
+            git_config,
+        })
+    }
+
+    fn fetch(&mut self, base_ref: &str, oid: &Oid) -> Result<()> {
+        self.git().fetch([base_ref, &oid.to_string()])
+    }
+
+    /// Make sure enough history is present to find the merge base of `base_oid` and `head_oid`,
+    /// deepening a shallow repository as needed
+    fn ensure_merge_base(&mut self, base_ref: &str, base_oid: &Oid, head_oid: &Oid) -> Result<()> {
+        let mut depth = match self.git_config.shallow_depth {
+            Some(depth) => depth,
+            None => return Ok(()),
+        };
+
+        for _ in 0..MAX_DEEPEN_ATTEMPTS {
+            if self.git().merge_base(base_oid, head_oid).is_ok() {
+                return Ok(());
+            }
+
+            info!("deepening history by {} commits to find merge base", depth);
+            self.git()
+                .deepen(&[base_ref, &head_oid.to_string()], Some(depth))?;
+            depth = depth.saturating_mul(2);
+        }
+
+        if self.git().merge_base(base_oid, head_oid).is_err() {
+            info!("unable to find merge base in shallow history, fetching full history");
+            self.git()
+                .deepen(&[base_ref, &head_oid.to_string()], None)?;
+        }
+
+        Ok(())
+    }
+
+    fn rebase(
+        &mut self,
+        base_oid: &Oid,
+        head_oid: &Oid,
+        branch: &str,
+        pr_number: u64,
+        squash: &Squash,
+    ) -> Result<MergeOutcome> {
+        // First create the branch to work on for the rebase
+        self.git().create_branch(branch, head_oid)?;
+
+        match squash {
+            Squash::None => {}
+            Squash::KeepFirst => {
+                if self.git().number_of_commits(base_oid, head_oid)? > 1 {
+                    // Get the first commit in the PR
+                    let oid = self.git().get_first_commit(base_oid, head_oid)?;
+
+                    // squash all commits
+                    self.git()
+                        .rebase(
+                            &oid,
+                            false,
+                            Some(format!("git commit --amend --fixup={}", oid)),
+                        )
+                        .or_else(|e| self.git().rebase_abort().map_err(|err| err.context(e)))?;
+                }
+            }
+            Squash::Message(message) => {
+                // Replace all of the PR's commits by a single one with their combined changes
+                let oid = self.git().get_first_commit(base_oid, head_oid)?;
+                self.git().reset_soft(&format!("{}^", oid))?;
+                self.git().commit(message)?;
+            }
+        }
+
+        // Attempt to perform the rebase
+        if let Err(e) = self.git().rebase(base_oid, true, None) {
+            info!("Rebase failed: {}", e);
+
+            // the rebase failed, probably due to a merge conflict so we need to record which files
+            // conflicted and then reset the state of the tree and abort the rebase
+            let files = self.git().conflicted_files().unwrap_or_default();
+            self.git().rebase_abort()?;
+            Ok(MergeOutcome::Conflict { files })
+        } else {
+            let head_oid = self.git().head_oid()?;
+
+            // If the head_oid and base_oid's match after the rebase then it means that the rebased
+            // commits resulted in no-ops
+            if head_oid == *base_oid {
+                Ok(MergeOutcome::Empty)
+            } else {
+                // Amend the tip commit to annotate that it closes the PR
+                let editor = format!(
+                    "git interpret-trailers --trailer \"Closes: #{}\" --in-place",
+                    pr_number
+                );
+                self.git().amend(&editor)?;
+                let head_oid = self.git().head_oid()?;
+
+                Ok(MergeOutcome::Merged(head_oid))
+            }
+        }
+    }
+
+    /// Determine the next step of a bisection from the output of `git bisect`
+    fn bisect_step(&self, output: &str) -> Result<BisectStep> {
+        if let Some(line) = output
+            .lines()
+            .find(|line| line.ends_with("is the first bad commit"))
+        {
+            let oid = line.split_whitespace().next().unwrap_or_default();
+            return Ok(BisectStep::Culprit(Oid::from_str(oid)));
+        }
+
+        if output.contains(BISECT_EXHAUSTED) {
+            let candidates = output
+                .lines()
+                .map(str::trim)
+                .filter(|line| line.len() == 40 && line.chars().all(|c| c.is_ascii_hexdigit()))
+                .map(Oid::from_str)
+                .collect();
+            return Ok(BisectStep::Inconclusive(candidates));
+        }
+
+        // Bisecting with `--no-checkout` leaves the commit to test in `BISECT_HEAD`
+        Ok(BisectStep::Test(self.git().ref_to_oid("BISECT_HEAD")?))
+    }
+
+    fn git(&self) -> Git {
+        Git::new()
+            .current_dir(&self.directory)
+            .with_user(&self.git_config.user)
+            .with_email(&self.git_config.email)
+            .with_ssh(&self.git_config)
+    }
+}
+
+impl GitBackend for GitRepository {
+    fn user(&self) -> &str {
+        &self.git_config.user
+    }
+
+    fn push_branch(&mut self, branch: &str) -> Result<()> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.git().push_branch(branch, true)
+    }
+
+    fn push_oid_to_branch(&mut self, oid: &Oid, branch: &str) -> Result<()> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.git().push_oid(oid, branch, true)
+    }
+
+    fn push_to_remote(
+        &mut self,
+        repo: &Repo,
+        branch: &str,
+        old_oid: &Oid,
+        new_oid: &Oid,
+    ) -> Result<()> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.git().push_to_remote(repo, branch, old_oid, new_oid)
+    }
+
+    fn fetch_ref(&mut self, r: &str) -> Result<Oid> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.git().fetch([format!("+{}:{}", r, FETCHED_REF)])?;
+        self.git().ref_to_oid(FETCHED_REF)
+    }
+
+    fn fetch_and_rebase(
+        &mut self,
+        base_ref: &str,
+        head_oid: &Oid,
+        branch: &str,
+        pr_number: u64,
+        squash: &Squash,
+    ) -> Result<MergeOutcome> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        // Fetch base ref and head_oid
+        self.fetch(base_ref, head_oid)?;
+        let base_oid = self.git().ref_to_oid(&format!("origin/{}", base_ref))?;
+        self.ensure_merge_base(base_ref, &base_oid, head_oid)?;
+        self.rebase(&base_oid, head_oid, branch, pr_number, squash)
+    }
+
+    fn fetch_and_rebase_onto(
+        &mut self,
+        base_ref: &str,
+        onto: &Oid,
+        head_oid: &Oid,
+        branch: &str,
+        pr_number: u64,
+        squash: &Squash,
+    ) -> Result<MergeOutcome> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.fetch(base_ref, head_oid)?;
+        let base_oid = self.git().ref_to_oid(&format!("origin/{}", base_ref))?;
+        self.ensure_merge_base(base_ref, &base_oid, head_oid)?;
+        // `onto` contains the tip of `base_ref`, so only the PR's own commits are replayed
+        self.rebase(onto, head_oid, branch, pr_number, squash)
+    }
+
+    fn fetch_commit_messages(&mut self, base_ref: &str, head_oid: &Oid) -> Result<Vec<String>> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.fetch(base_ref, head_oid)?;
+        let base_oid = self.git().ref_to_oid(&format!("origin/{}", base_ref))?;
+        self.ensure_merge_base(base_ref, &base_oid, head_oid)?;
+        self.git().commit_messages(&base_oid, head_oid)
+    }
+
+    fn fetch_and_merge(
+        &mut self,
+        base_ref: &str,
+        head_oid: &Oid,
+        branch: &str,
+        pr_number: u64,
+        title: &str,
+    ) -> Result<MergeOutcome> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.fetch(base_ref, head_oid)?;
+        let base_oid = self.git().ref_to_oid(&format!("origin/{}", base_ref))?;
+        self.ensure_merge_base(base_ref, &base_oid, head_oid)?;
+        self.git().create_branch(branch, &base_oid)?;
+
+        let message = format!("Merge #{}: {}\n\nCloses: #{}", pr_number, title, pr_number);
+
+        if let Err(e) = self.git().merge(head_oid, &message) {
+            info!("Merge failed: {}", e);
+
+            // the merge failed, probably due to a merge conflict so we need to record which files
+            // conflicted and then reset the state of the tree and abort the merge
+            let files = self.git().conflicted_files().unwrap_or_default();
+            self.git().merge_abort()?;
+            Ok(MergeOutcome::Conflict { files })
+        } else {
+            let head_oid = self.git().head_oid()?;
+
+            // If nothing was merged then the PR's commits are already part of `base_ref`
+            if head_oid == base_oid {
+                Ok(MergeOutcome::Empty)
+            } else {
+                Ok(MergeOutcome::Merged(head_oid))
+            }
+        }
+    }
+
+    fn fetch_and_check_up_to_date(&mut self, base_ref: &str, head_oid: &Oid) -> Result<bool> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.fetch(base_ref, head_oid)?;
+        let base_oid = self.git().ref_to_oid(&format!("origin/{}", base_ref))?;
+        if base_oid == *head_oid {
+            return Ok(false);
+        }
+        self.ensure_merge_base(base_ref, &base_oid, head_oid)?;
+        Ok(self.git().merge_base(&base_oid, head_oid)? == base_oid)
+    }
+
+    fn fetch_merge_skew(&mut self, base_ref: &str, head_oid: &Oid) -> Result<MergeSkew> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.fetch(base_ref, head_oid)?;
+        let base_oid = self.git().ref_to_oid(&format!("origin/{}", base_ref))?;
+        self.ensure_merge_base(base_ref, &base_oid, head_oid)?;
+        let timestamps = self.git().commit_timestamps(head_oid, &base_oid)?;
+        Ok(MergeSkew {
+            commits_behind: timestamps.len(),
+            behind_since: timestamps
+                .into_iter()
+                .min()
+                .and_then(DateTime::from_timestamp),
+        })
+    }
+
+    fn fetch_and_cherry_pick(
+        &mut self,
+        target_ref: &str,
+        branch: &str,
+        base_oid: &Oid,
+        head_oid: &Oid,
+    ) -> Result<Option<Oid>> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.fetch(target_ref, head_oid)?;
+        let target_oid = self.git().ref_to_oid(&format!("origin/{}", target_ref))?;
+        // The PR's commits, `base_oid..head_oid`, must be present in order to cherry-pick them
+        self.ensure_merge_base(target_ref, base_oid, head_oid)?;
+        // Create branch to work on for the cherry-pick
+        self.git().create_branch(branch, &target_oid)?;
+
+        // Attempt the cherry-pick
+        if let Err(e) = self.git().cherry_pick(base_oid, head_oid) {
+            info!("chery-pick failed: {}", e);
+
+            self.git().cherry_pick_abort()?;
+            Ok(None)
+        } else {
+            let head_oid = self.git().head_oid()?;
+            Ok(Some(head_oid))
+        }
+    }
+
+    fn delete_branch(&mut self, branch: &str) -> Result<()> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        // The branch can't be deleted while it's checked out
+        self.git().detach()?;
+        self.git().delete_branch(branch)
+    }
+
+    fn bisect_start(&mut self, good: &Oid, bad: &Oid) -> Result<BisectStep> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.git().fetch([good.to_string(), bad.to_string()])?;
+        self.ensure_merge_base(&good.to_string(), good, bad)?;
+
+        self.git().bisect(&["reset"])?;
+        // Only consider the commits which landed on the branch, not those of merged PRs
+        let output = self.git().bisect(&[
+            "start",
+            "--no-checkout",
+            "--first-parent",
+            &bad.to_string(),
+            &good.to_string(),
+        ])?;
+        self.bisect_step(&output)
+    }
+
+    fn bisect_mark(&mut self, oid: &Oid, mark: BisectMark) -> Result<BisectStep> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        let output = self.git().bisect(&[mark.as_str(), &oid.to_string()])?;
+        self.bisect_step(&output)
+    }
+
+    fn bisect_reset(&mut self) -> Result<()> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        self.git().bisect(&["reset"])?;
+        Ok(())
+    }
+
+    fn prefetch(&mut self, base_refs: Vec<String>, oids: Vec<Oid>) -> BackgroundTask {
+        let directory = self.directory.clone();
+        let git_config = self.git_config.clone();
+        let checkout = self.checkout.clone();
+        Box::new(move || {
+            if base_refs.is_empty() && oids.is_empty() {
+                return Ok(());
+            }
+
+            let _checkout = checkout.lock();
+            debug!(
+                "prefetching {} refs and {} commits",
+                base_refs.len(),
+                oids.len()
+            );
+            let git = || Git::new().current_dir(&directory).with_ssh(&git_config);
+            let oids = oids.iter().map(ToString::to_string).collect::<Vec<_>>();
+            if let Err(e) = git().fetch(base_refs.iter().chain(&oids)) {
+                // A PR may have been force-pushed since its head was recorded, leaving a commit
+                // which can no longer be fetched, so at least keep the base refs fresh
+                warn!("unable to prefetch PR heads: {:#}", e);
+                if !base_refs.is_empty() {
+                    git().fetch(&base_refs)?;
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    fn maintenance(&mut self) -> BackgroundTask {
+        let directory = self.directory.clone();
+        let git_config = self.git_config.clone();
+        let checkout = self.checkout.clone();
+        Box::new(move || {
+            // gc repacks and prunes objects, which mustn't happen while a rebase is writing them
+            let _checkout = checkout.lock();
+            info!("running git maintenance in {}", directory.display());
+            let git = || Git::new().current_dir(&directory).with_ssh(&git_config);
+            git().remote_prune()?;
+            git().gc()
+        })
+    }
+
+    fn relocate(&mut self, config: &RepoConfig) -> Result<()> {
+        let checkout = self.checkout.clone();
+        let _checkout = checkout.lock();
+        let directory = checkout_directory(config)?;
+        if directory != self.directory {
+            info!(
+                "moving checkout from '{}' to '{}'",
+                self.directory.display(),
+                directory.display()
+            );
+            if let Some(parent) = directory.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&self.directory, &directory).with_context(|| {
+                format!(
+                    "moving {} to {}",
+                    self.directory.display(),
+                    directory.display()
+                )
+            })?;
+            self.directory = directory;
+        }
+
+        self.github_repo = config.repo().clone();
+        self.git().set_remote_url(&self.github_repo)
+    }
+}
+
+struct Git {
+    inner: Command,
+}
+
+impl Git {
+    pub fn new() -> Self {
+        let mut inner = Command::new("git");
+
+        inner
+            // Stop git looking at directories above the `REPOS_DIR` or `TENANTS_DIR`
+            .env(
+                "GIT_CEILING_DIRECTORIES",
+                std::env::join_paths(
+                    [REPOS_DIR, TENANTS_DIR].map(|dir| std::env::current_dir().unwrap().join(dir)),
+                )
+                .unwrap(),
+            )
+            // Don't try and open an editor for things like `rebase -i`
+            .env("GIT_EDITOR", "cat");
+
+        Self { inner }
+    }
+
+    // Use `-C <path>` instead of `Command::current_dir` so that attempting to run a command in a
+    // didirectory that doesn't exist doesn't fail to run the command but instead will succeed with
+    // a non-zero exit code
+    pub fn current_dir(mut self, path: &Path) -> Self {
+        // self.inner.current_dir(path);
+        self.inner.arg("-C").arg(path);
+        self
+    }
+
+    pub fn with_ssh(mut self, git_config: &GitConfig) -> Self {
+        let ssh_key_file = &git_config.ssh_key_file;
+        let path = if ssh_key_file.is_absolute() {
+            ssh_key_file.to_path_buf()
+        } else {
+            std::env::current_dir().unwrap().join(ssh_key_file)
+        };
+        if !path.is_file() {
+            panic!("SSH Key File is not a file")
+        };
+        let mut command = format!("ssh -i {} -S none -o 'IdentitiesOnly true'", path.display());
+        if let Some(proxy_command) = &git_config.ssh_proxy_command {
+            // The command is run by a shell, so quote the proxy command as a single argument
+            command.push_str(&format!(
+                " -o 'ProxyCommand {}'",
+                proxy_command.replace('\'', r"'\''")
+            ));
+        }
+        self.inner.env("GIT_SSH_COMMAND", command);
+        self
+    }
+
+    pub fn with_user(mut self, user: &str) -> Self {
+        self.inner.env("GIT_AUTHOR_NAME", user);
+        self.inner.env("GIT_COMMITTER_NAME", user);
+        self
+    }
+
+    pub fn with_email(mut self, email: &str) -> Self {
+        self.inner.env("GIT_AUTHOR_EMAIL", email);
+        self.inner.env("GIT_COMMITTER_EMAIL", email);
+        self
+    }
+
+    pub fn with_editor(mut self, editor: &str) -> Self {
+        self.inner.env("GIT_EDITOR", editor);
+        self
+    }
+
+    fn run(mut self) -> Result<String> {
+        let output = self.inner.output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            debug!("Git command failed:\n$ {:?}\n{}", self.inner, stderr);
+            return Err(anyhow!("failed to run git command:\n{}", stderr));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        debug!(
+            "Git command run successfully:\n$ {:?}\n{}",
+            self.inner, stdout
+        );
+
+        Ok(stdout.into())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub fn is_git_repo(mut self) -> Result<bool> {
+        let output = self
+            .inner
+            .args(["rev-parse", "--git-dir"])
+            .output()
+            .context("checking if a directory is a git repo")?;
+
+        Ok(output.status.success())
+    }
+
+    pub fn remote_matches_github_repo(mut self, github_repo: &Repo) -> Result<bool> {
+        self.inner.args(["remote", "get-url", "origin"]);
+        let output = self.run()?;
+
+        Ok(output.trim() == github_repo.to_github_ssh_url())
+    }
+
+    pub fn clone(
+        mut self,
+        path: &Path,
+        github_repo: &Repo,
+        filter: Option<&str>,
+        depth: Option<u32>,
+    ) -> Result<()> {
+        self.inner.arg("clone");
+        if let Some(filter) = filter {
+            self.inner.arg(format!("--filter={}", filter));
+        }
+        if let Some(depth) = depth {
+            // Fetch history for every branch, not just the default branch
+            self.inner
+                .arg(format!("--depth={}", depth))
+                .arg("--no-single-branch");
+        }
+        self.inner.arg(github_repo.to_github_ssh_url()).arg(path);
+        self.run()
+            .with_context(|| format!("cloning {}", github_repo.to_github_ssh_url()))?;
+        Ok(())
+    }
+
+    pub fn fetch<I, S>(mut self, refspec: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        self.inner.arg("fetch").arg("origin").args(refspec);
+        self.run()?;
+        Ok(())
+    }
+
+    /// Fetch `depth` more commits of history for `refspec`, or all of the history if `None`
+    pub fn deepen(mut self, refspec: &[&str], depth: Option<u32>) -> Result<()> {
+        self.inner.arg("fetch");
+        match depth {
+            Some(depth) => self.inner.arg(format!("--deepen={}", depth)),
+            None => self.inner.arg("--unshallow"),
+        };
+        self.inner.arg("origin").args(refspec);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn merge_base(mut self, a: &Oid, b: &Oid) -> Result<Oid> {
+        self.inner
+            .arg("merge-base")
+            .arg(a.to_string())
+            .arg(b.to_string());
+        let output = self.run()?;
+        Ok(Oid::from_str(output.trim()))
+    }
+
+    pub fn set_remote_url(mut self, github_repo: &Repo) -> Result<()> {
+        self.inner.args(["remote", "set-url", "origin"]);
+        self.inner.arg(github_repo.to_github_ssh_url());
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn remote_prune(mut self) -> Result<()> {
+        self.inner.args(["remote", "prune", "origin"]);
+        self.run()?;
+        Ok(())
+    }
+
+    /// Garbage collect the repository, only pruning objects which have been unreachable for git's
+    /// default of two weeks
+    pub fn gc(mut self) -> Result<()> {
+        self.inner.args(["gc", "--quiet"]);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn create_branch(mut self, branch_name: &str, oid: &Oid) -> Result<()> {
+        self.inner
+            .args(["checkout", "-B", branch_name])
+            .arg(oid.to_string());
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn detach(mut self) -> Result<()> {
+        self.inner.args(["checkout", "--detach"]);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn delete_branch(mut self, branch_name: &str) -> Result<()> {
+        self.inner.args(["branch", "-D", branch_name]);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn amend(mut self, editor: &str) -> Result<()> {
+        self.inner.args(["commit", "--amend"]);
+        self.with_editor(editor).run()?;
+        Ok(())
+    }
+
+    pub fn commit(mut self, message: &str) -> Result<()> {
+        self.inner.args(["commit", "-m", message]);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn reset_soft(mut self, r: &str) -> Result<()> {
+        self.inner.args(["reset", "--soft", r]);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn rebase_abort(mut self) -> Result<()> {
+        self.inner.args(["rebase", "--abort"]);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn rebase(mut self, base_oid: &Oid, autosquash: bool, exec: Option<String>) -> Result<()> {
+        self.inner.args(["rebase", "-i", "--force-rebase"]);
+        self.inner.arg(base_oid.to_string());
+
+        if autosquash {
+            self.inner.arg("--autosquash");
+        }
+
+        if let Some(exec) = exec {
+            self.inner.arg("--exec");
+            self.inner.arg(exec);
+        }
+
+        self.run()?;
+        Ok(())
+    }
+
+    /// List the files with unresolved conflicts in an in-progress rebase, merge, or cherry-pick
+    pub fn conflicted_files(mut self) -> Result<Vec<String>> {
+        self.inner.args(["diff", "--name-only", "--diff-filter=U"]);
+        let output = self.run()?;
+        Ok(output.lines().map(ToOwned::to_owned).collect())
+    }
+
+    pub fn merge_abort(mut self) -> Result<()> {
+        self.inner.args(["merge", "--abort"]);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn merge(mut self, oid: &Oid, message: &str) -> Result<()> {
+        self.inner.args(["merge", "--no-ff", "-m", message]);
+        self.inner.arg(oid.to_string());
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn cherry_pick_abort(mut self) -> Result<()> {
+        self.inner.args(["cherry-pick", "--abort"]);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn cherry_pick(mut self, base_oid: &Oid, head_oid: &Oid) -> Result<()> {
+        self.inner.args(["cherry-pick"]);
+        self.inner.arg(format!("{}..{}", base_oid, head_oid));
+
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn get_first_commit(mut self, base_oid: &Oid, head_oid: &Oid) -> Result<Oid> {
+        self.inner
+            .arg("rev-list")
+            .arg(format!("{}..{}", base_oid, head_oid));
+        let output = self.run()?;
+        let first = output
+            .lines()
+            .last()
+            .ok_or_else(|| anyhow!("unable to query first commit in series"))?;
+        Ok(Oid::from_str(first.trim()))
+    }
+
+    /// The full messages of the commits in `base_oid..head_oid`, oldest first
+    pub fn commit_messages(mut self, base_oid: &Oid, head_oid: &Oid) -> Result<Vec<String>> {
+        self.inner
+            .args(["log", "--reverse", "--format=%B%x00"])
+            .arg(format!("{}..{}", base_oid, head_oid));
+        let output = self.run()?;
+        Ok(output
+            .split('\0')
+            .map(str::trim)
+            .filter(|message| !message.is_empty())
+            .map(ToOwned::to_owned)
+            .collect())
+    }
+
+    /// The commit timestamps, in seconds since the epoch, of the commits in `base_oid..head_oid`
+    pub fn commit_timestamps(mut self, base_oid: &Oid, head_oid: &Oid) -> Result<Vec<i64>> {
+        self.inner
+            .args(["log", "--format=%ct"])
+            .arg(format!("{}..{}", base_oid, head_oid));
+        let output = self.run()?;
+        output
+            .lines()
+            .map(|line| {
+                line.trim()
+                    .parse()
+                    .with_context(|| format!("invalid commit timestamp '{}'", line))
+            })
+            .collect()
+    }
+
+    pub fn number_of_commits(mut self, base_oid: &Oid, head_oid: &Oid) -> Result<usize> {
+        self.inner
+            .arg("rev-list")
+            .arg(format!("{}..{}", base_oid, head_oid));
+        let output = self.run()?;
+        Ok(output.lines().count())
+    }
+
+    pub fn head_oid(self) -> Result<Oid> {
+        self.ref_to_oid("HEAD")
+    }
+
+    pub fn ref_to_oid(mut self, r: &str) -> Result<Oid> {
+        self.inner.args(["rev-parse", r]);
+        let output = self.run()?;
+        Ok(Oid::from_str(output.trim()))
+    }
+
+    /// Run `git bisect`, returning its output. Running out of commits to test, which `git bisect`
+    /// reports as a failure, isn't treated as an error
+    pub fn bisect(mut self, args: &[&str]) -> Result<String> {
+        self.inner.arg("bisect").args(args);
+        let output = self.inner.output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+
+        if !output.status.success() && !stdout.contains(BISECT_EXHAUSTED) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            debug!("Git command failed:\n$ {:?}\n{}", self.inner, stderr);
+            return Err(anyhow!("failed to run git command:\n{}", stderr));
+        }
+
+        debug!(
+            "Git command run successfully:\n$ {:?}\n{}",
+            self.inner, stdout
+        );
+        Ok(stdout)
+    }
+
+    pub fn push_branch(mut self, branch: &str, force: bool) -> Result<()> {
+        self.inner.args(["push", "origin"]);
+        if force {
+            self.inner.arg("--force");
+        }
+        self.inner.arg(branch);
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn push_oid(mut self, oid: &Oid, branch: &str, force: bool) -> Result<()> {
+        self.inner.args(["push", "origin"]);
+        if force {
+            self.inner.arg("--force");
+        }
+        self.inner.arg(format!("{}:refs/heads/{}", oid, branch));
+        self.run()?;
+        Ok(())
+    }
+
+    pub fn push_to_remote(
+        mut self,
+        repo: &Repo,
+        branch: &str,
+        old_oid: &Oid,
+        new_oid: &Oid,
+    ) -> Result<()> {
+        self.inner
+            .arg("push")
+            .arg(format!("--force-with-lease={}:{}", branch, old_oid))
+            .arg(repo.to_github_ssh_url())
+            .arg(format!("{}:{}", new_oid, branch));
+        self.run()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BackgroundGit;
+    use std::sync::mpsc;
+
+    #[tokio::test]
+    async fn background_tasks_run_one_at_a_time() {
+        let background = BackgroundGit::default();
+        let (tx, rx) = mpsc::channel::<()>();
+
+        // Runs until `tx` is dropped
+        let first = background
+            .spawn(
+                "first",
+                Box::new(move || {
+                    let _ = rx.recv();
+                    Ok(())
+                }),
+            )
+            .unwrap();
+        assert!(background.spawn("second", Box::new(|| Ok(()))).is_none());
+
+        drop(tx);
+        first.await.unwrap();
+        background
+            .spawn("third", Box::new(|| Err(anyhow::anyhow!("failed"))))
+            .unwrap()
+            .await
+            .unwrap();
+        assert!(background.spawn("fourth", Box::new(|| Ok(()))).is_some());
+    }
+}
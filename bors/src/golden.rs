@@ -0,0 +1,28 @@
+//! Golden-file tests of serialized output, which catch unintended changes to the shape of state
+//! snapshots and of the debug endpoint. Run the tests with `UPDATE_GOLDEN=1` to regenerate the
+//! files after an intended change
+
+use serde::Serialize;
+use std::{env, fs, path::Path};
+
+pub fn assert_golden<T: Serialize>(name: &str, value: &T) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("test-input")
+        .join(name);
+    let actual = serde_json::to_value(value).unwrap();
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        let mut json = serde_json::to_string_pretty(&actual).unwrap();
+        json.push('\n');
+        fs::write(&path, json).unwrap();
+        return;
+    }
+
+    let expected: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        actual, expected,
+        "{} doesn't match, rerun with UPDATE_GOLDEN=1 if the change is intended",
+        name
+    );
+}
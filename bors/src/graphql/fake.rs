@@ -0,0 +1,251 @@
+//! A fake Github API for tests of the logic which talks to Github
+//!
+//! [`FakeGithub`] serves the API on a local port and records every request it receives so that
+//! tests can check what bors did. The REST endpoints bors writes to answer with a plausible
+//! response by default, any other request gets the response set with [`FakeGithub::respond`] or
+//! else a `404`.
+
+use super::{AuthHealth, GithubClient, GraphqlHealth, ReviewDecisionCache, USER_AGENT};
+use github::{client::ClientBuilder, test_fixtures, Client};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+/// A request received by a [`FakeGithub`]
+#[derive(Clone, Debug)]
+pub struct Received {
+    pub method: Method,
+    /// Path of the request without its leading slash or query, e.g. `repos/o/r/pulls/1`
+    pub path: String,
+    /// The request's json body, `Null` if it had none
+    pub body: Value,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    received: Vec<Received>,
+    responses: HashMap<(Method, String), (StatusCode, Value)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FakeGithub {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeGithub {
+    /// Start serving the fake API, must be called from within a tokio runtime
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let shared = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = shared.clone();
+            let service = service_fn(move |request| handle(state.clone(), request));
+            async move { Ok::<_, Infallible>(service) }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let url = format!("http://{}/", server.local_addr());
+        tokio::spawn(server);
+
+        Self { url, state }
+    }
+
+    /// A client talking to the fake API
+    pub fn client(&self) -> GithubClient {
+        self.configured_client(|builder| builder)
+    }
+
+    /// A client talking to the fake API, with additional options set by `configure`
+    pub fn configured_client<F>(&self, configure: F) -> GithubClient
+    where
+        F: FnOnce(ClientBuilder) -> ClientBuilder,
+    {
+        let builder = Client::builder()
+            .base_url(&self.url)
+            .user_agent(USER_AGENT)
+            .github_api_token("token");
+        GithubClient {
+            client: configure(builder).build().unwrap(),
+            graphql_health: GraphqlHealth::default(),
+            auth_health: AuthHealth::default(),
+            review_decisions: ReviewDecisionCache::default(),
+        }
+    }
+
+    /// Answer `method` requests to `path` with `status` and the json `body` from now on
+    pub fn respond(&self, method: Method, path: &str, status: StatusCode, body: Value) {
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .insert((method, path.to_owned()), (status, body));
+    }
+
+    /// Answer queries for the review decision of a PR with `decision`, e.g. `APPROVED`, and
+    /// `reviews`, as (reviewer, state, submitted at), oldest first
+    pub fn respond_reviews(&self, decision: &str, reviews: &[(&str, &str, &str)]) {
+        let nodes = reviews
+            .iter()
+            .map(|(login, state, submitted_at)| {
+                json!({
+                    "author": { "__typename": "User", "login": login },
+                    "state": state,
+                    "submittedAt": submitted_at,
+                })
+            })
+            .collect::<Vec<_>>();
+        self.respond(
+            Method::POST,
+            "graphql",
+            StatusCode::OK,
+            json!({
+                "data": {
+                    "repository": {
+                        "pullRequest": {
+                            "reviewDecision": decision,
+                            "reviews": { "nodes": nodes },
+                        },
+                    },
+                },
+            }),
+        );
+    }
+
+    /// The requests received so far, in order
+    pub fn received(&self) -> Vec<Received> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    /// The bodies of the requests received so far for `method` and `path`, in order
+    pub fn bodies(&self, method: Method, path: &str) -> Vec<Value> {
+        self.received()
+            .into_iter()
+            .filter(|r| r.method == method && r.path == path)
+            .map(|r| r.body)
+            .collect()
+    }
+
+    /// The comments posted so far on issue or PR `number` of `owner/name`
+    pub fn comments(&self, owner: &str, name: &str, number: u64) -> Vec<String> {
+        let path = format!("repos/{}/{}/issues/{}/comments", owner, name, number);
+        self.bodies(Method::POST, &path)
+            .into_iter()
+            .map(|body| body["body"].as_str().unwrap_or_default().to_owned())
+            .collect()
+    }
+
+    /// The states of the `bors` statuses created so far on `sha` of `owner/name`, in order
+    pub fn statuses(&self, owner: &str, name: &str, sha: &str) -> Vec<String> {
+        let path = format!("repos/{}/{}/statuses/{}", owner, name, sha);
+        self.bodies(Method::POST, &path)
+            .into_iter()
+            .filter(|body| body["context"] == "bors")
+            .map(|body| body["state"].as_str().unwrap_or_default().to_owned())
+            .collect()
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, hyper::Error> {
+    let method = request.method().clone();
+    let path = request.uri().path().trim_start_matches('/').to_owned();
+    let body = hyper::body::to_bytes(request.into_body()).await?;
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+    let (status, response) = {
+        let mut state = state.lock().unwrap();
+        let response = state
+            .responses
+            .get(&(method.clone(), path.clone()))
+            .cloned()
+            .unwrap_or_else(|| default_response(&method, &path, &body));
+        state.received.push(Received { method, path, body });
+        response
+    };
+
+    Ok(Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(response.to_string()))
+        .unwrap())
+}
+
+/// The response to a request to the REST endpoints bors writes to, built from the request
+fn default_response(method: &Method, path: &str, body: &Value) -> (StatusCode, Value) {
+    let segments = path.split('/').collect::<Vec<_>>();
+    match (method, segments.as_slice()) {
+        (&Method::POST, ["repos", _, _, "statuses", _]) => (
+            StatusCode::CREATED,
+            json!({
+                "id": 1,
+                "node_id": "MDY6U3RhdHVzMQ==",
+                "url": "",
+                "state": body["state"],
+                "target_url": body["target_url"],
+                "description": body["description"],
+                "context": body["context"],
+                "creator": test_fixtures::user("bors"),
+                "created_at": "2020-10-16T12:00:00Z",
+                "updated_at": "2020-10-16T12:00:00Z",
+            }),
+        ),
+        (&Method::POST, ["repos", _, _, "issues", _, "comments"]) => (
+            StatusCode::CREATED,
+            json!({
+                "id": 1,
+                "node_id": "MDEyOklzc3VlQ29tbWVudDE=",
+                "url": "",
+                "html_url": "",
+                "body": body["body"],
+                "user": test_fixtures::user("bors"),
+                "created_at": "2020-10-16T12:00:00Z",
+                "updated_at": "2020-10-16T12:00:00Z",
+            }),
+        ),
+        (&Method::POST, ["repos", _, _, "issues", _, "labels"]) => (
+            StatusCode::OK,
+            Value::Array(
+                body["labels"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(test_fixtures::label)
+                    .collect(),
+            ),
+        ),
+        (&Method::DELETE, ["repos", _, _, "issues", _, "labels", _]) => (StatusCode::OK, json!([])),
+        (&Method::POST, ["repos", _, _, "check-runs"]) => (
+            StatusCode::CREATED,
+            check_run(1, body["head_sha"].as_str().unwrap_or_default(), body),
+        ),
+        (&Method::PATCH, ["repos", _, _, "check-runs", id]) => (
+            StatusCode::OK,
+            check_run(id.parse().unwrap_or_default(), &"0".repeat(40), body),
+        ),
+        (&Method::PATCH, ["repos", _, _, "git", "refs", ..]) => (StatusCode::OK, json!({})),
+        (&Method::GET, ["repos", _, _, "pulls", _, "files"]) => (StatusCode::OK, json!([])),
+        _ => (StatusCode::NOT_FOUND, json!({ "message": "Not Found" })),
+    }
+}
+
+fn check_run(id: u64, head_sha: &str, body: &Value) -> Value {
+    json!({
+        "id": id,
+        "head_sha": head_sha,
+        "name": "bors",
+        "html_url": null,
+        "status": body.get("status").cloned().unwrap_or_else(|| json!("queued")),
+        "conclusion": body.get("conclusion").cloned().unwrap_or(Value::Null),
+    })
+}
@@ -6,30 +6,296 @@
 //! [Github's v4 API Explorer](https://developer.github.com/v4/explorer/)
 //! [Github's v4 API Docs](https://developer.github.com/v4/)
 
-use crate::{state::PullRequestState, Result};
-use github::{client::Response, Client, NodeId, ReactionType};
-use graphql_client::GraphQLQuery;
-use log::debug;
-use std::ops::Deref;
+use crate::{
+    config::GithubConfig,
+    state::{PullRequestState, Repo},
+    Result,
+};
+use github::{
+    client::{ListPullsOptions, PaginationOptions, ReadOnlySwitch, Response, StateFilter},
+    Client, DateTime, NodeId, ReactionType, ReviewState,
+};
+use graphql_client::{GraphQLQuery, QueryBody};
+use log::{debug, warn};
+use std::{
+    collections::HashMap,
+    future::Future,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
+#[cfg(test)]
+pub mod fake;
 mod query;
+mod review_cache;
+
+use review_cache::ReviewDecisionCache;
+pub use review_cache::ReviewDecisionCacheStats;
 
 const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
+/// Number of consecutive GraphQL failures after which requests go straight to the REST API
+const GRAPHQL_FAILURE_THRESHOLD: usize = 3;
+
+/// How long to wait before trying the GraphQL API again once it has been marked unhealthy
+const GRAPHQL_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Tracks the health of Github's GraphQL API, which occasionally degrades independently of the
+/// REST API
+#[derive(Clone, Debug, Default)]
+pub struct GraphqlHealth {
+    inner: Arc<GraphqlHealthInner>,
+}
+
+#[derive(Debug, Default)]
+struct GraphqlHealthInner {
+    consecutive_failures: AtomicUsize,
+    last_failure: Mutex<Option<Instant>>,
+}
+
+impl GraphqlHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.inner.consecutive_failures.load(Ordering::Relaxed) < GRAPHQL_FAILURE_THRESHOLD
+    }
+
+    pub fn consecutive_failures(&self) -> usize {
+        self.inner.consecutive_failures.load(Ordering::Relaxed)
+    }
+
+    /// Indicates if the GraphQL API should be tried, either because it is healthy or because
+    /// enough time has passed since it last failed
+    fn should_try(&self) -> bool {
+        self.is_healthy()
+            || self
+                .inner
+                .last_failure
+                .lock()
+                .unwrap()
+                .map(|t| t.elapsed() >= GRAPHQL_RETRY_INTERVAL)
+                .unwrap_or(true)
+    }
+
+    fn record_success(&self) {
+        self.inner.consecutive_failures.store(0, Ordering::Relaxed);
+    }
+
+    fn record_failure(&self) {
+        self.inner
+            .consecutive_failures
+            .fetch_add(1, Ordering::Relaxed);
+        *self.inner.last_failure.lock().unwrap() = Some(Instant::now());
+    }
+}
+
+/// Tracks whether Github is still accepting the configured API token
+#[derive(Clone, Debug, Default)]
+pub struct AuthHealth {
+    expired: Arc<AtomicBool>,
+}
+
+impl AuthHealth {
+    pub fn is_healthy(&self) -> bool {
+        !self.expired.load(Ordering::Relaxed)
+    }
+
+    pub fn record_expired(&self) {
+        self.expired.store(true, Ordering::Relaxed);
+    }
+
+    pub fn record_valid(&self) {
+        self.expired.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Indicates if `error` was caused by Github rejecting the API token
+pub fn is_auth_expired(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| {
+        e.downcast_ref::<github::client::Error>()
+            .map(github::client::Error::is_auth_expired)
+            .unwrap_or(false)
+    })
+}
+
+/// Indicates if `error` was caused by a write being refused because bors is read-only
+pub fn is_read_only(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| {
+        e.downcast_ref::<github::client::Error>()
+            .map(github::client::Error::is_read_only)
+            .unwrap_or(false)
+    })
+}
+
+/// Indicates if `error` is a failure of the GraphQL API itself, i.e. errors reported in its
+/// response, an unparsable response or the API being unavailable, which the REST API may not share
+fn is_graphql_failure(error: &anyhow::Error) -> bool {
+    use github::client::Error;
+
+    error
+        .chain()
+        .filter_map(|e| e.downcast_ref::<Error>())
+        .any(|e| match e {
+            Error::GraphqlError(_) | Error::Graphql(_) | Error::Json(_) => true,
+            Error::GithubClientError(status, _) => status.is_server_error(),
+            Error::Reqwest(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.status().is_some_and(|status| status.is_server_error())
+            }
+            _ => false,
+        })
+}
+
+/// The subject of a reaction, carrying the ids needed by both the GraphQL and REST APIs
+#[derive(Clone, Copy, Debug)]
+pub enum ReactionSubject<'a> {
+    IssueComment {
+        node_id: &'a NodeId,
+        id: u64,
+    },
+    ReviewComment {
+        node_id: &'a NodeId,
+        id: u64,
+    },
+    /// Reviews can only be reacted to via the GraphQL API
+    Review {
+        node_id: &'a NodeId,
+    },
+}
+
+impl ReactionSubject<'_> {
+    pub fn node_id(&self) -> &NodeId {
+        match self {
+            ReactionSubject::IssueComment { node_id, .. }
+            | ReactionSubject::ReviewComment { node_id, .. }
+            | ReactionSubject::Review { node_id } => node_id,
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct GithubClient(Client);
+pub struct GithubClient {
+    client: Client,
+    graphql_health: GraphqlHealth,
+    auth_health: AuthHealth,
+    review_decisions: ReviewDecisionCache,
+}
 
 impl GithubClient {
-    pub fn new(github_api_token: &str) -> Self {
-        let client = Client::builder()
-            .github_api_token(github_api_token)
+    /// Create a client which refuses to write to Github while any of the `read_only` switches
+    /// is on
+    pub fn new(config: &GithubConfig, read_only: &[ReadOnlySwitch]) -> Result<Self> {
+        Self::build(config, None, read_only)
+    }
+
+    /// Create a client for operating on `repo`, which authenticates as a Github App with tokens
+    /// scoped to just `repo`
+    pub fn for_repo(
+        config: &GithubConfig,
+        repo: &Repo,
+        read_only: &[ReadOnlySwitch],
+    ) -> Result<Self> {
+        Self::build(config, Some(repo), read_only)
+    }
+
+    fn build(
+        config: &GithubConfig,
+        repo: Option<&Repo>,
+        read_only: &[ReadOnlySwitch],
+    ) -> Result<Self> {
+        let mut builder = read_only
+            .iter()
+            .fold(Client::builder(), |builder, switch| {
+                builder.read_only(switch.clone())
+            })
             .user_agent(USER_AGENT)
-            .build()
-            .unwrap();
-        Self(client)
+            .graphql_batching(config.graphql_batching)
+            .graphql_persisted_queries(config.graphql_persisted_queries)
+            .danger_accept_invalid_certs(config.danger_accept_invalid_certs);
+        if let Some(token) = &config.github_api_token {
+            builder = builder.github_api_token(token.expose());
+        }
+        if let Some(app) = &config.app {
+            builder = builder.github_app(app.id, app.private_key.expose(), app.installation_id);
+            if let Some(repo) = repo {
+                builder = builder.installation_repositories([repo.name()]);
+            }
+        }
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(no_proxy) = &config.no_proxy {
+            builder = builder.no_proxy(no_proxy);
+        }
+        if let Some(pem) = config.ca_bundle()? {
+            builder = builder.root_certificates_pem(&pem);
+        }
+        let client = builder.build()?;
+        Ok(Self {
+            client,
+            graphql_health: GraphqlHealth::default(),
+            auth_health: AuthHealth::default(),
+            review_decisions: ReviewDecisionCache::default(),
+        })
+    }
+
+    pub fn graphql_health(&self) -> &GraphqlHealth {
+        &self.graphql_health
+    }
+
+    pub fn auth_health(&self) -> &AuthHealth {
+        &self.auth_health
+    }
+
+    /// Run `graphql`, falling back to `rest` if the GraphQL API fails or is unhealthy. Other
+    /// errors, e.g. an expired token or a missing PR, would fail the same way through the REST API
+    /// and are returned as is
+    async fn with_fallback<T, G, R>(&self, what: &str, graphql: G, rest: R) -> Result<T>
+    where
+        G: Future<Output = Result<T>>,
+        R: Future<Output = Result<T>>,
+    {
+        if self.graphql_health.should_try() {
+            match graphql.await {
+                Ok(t) => {
+                    self.graphql_health.record_success();
+                    return Ok(t);
+                }
+                Err(e) => {
+                    if !is_graphql_failure(&e) {
+                        return Err(e);
+                    }
+                    self.graphql_health.record_failure();
+                    warn!(
+                        "GraphQL request '{}' failed, falling back to REST: {:#}",
+                        what, e
+                    );
+                }
+            }
+        }
+
+        rest.await
+    }
+
+    pub async fn add_reaction(
+        &self,
+        owner: &str,
+        name: &str,
+        subject: ReactionSubject<'_>,
+        reaction: ReactionType,
+    ) -> Result<()> {
+        self.with_fallback(
+            "add_reaction",
+            self.add_reaction_graphql(subject.node_id(), reaction),
+            self.add_reaction_rest(owner, name, subject, reaction),
+        )
+        .await
     }
 
-    pub async fn add_reaction(&self, id: &NodeId, reaction: ReactionType) -> Result<()> {
+    async fn add_reaction_graphql(&self, id: &NodeId, reaction: ReactionType) -> Result<()> {
         use query::{
             add_reaction::{ResponseData, Variables},
             AddReaction,
@@ -40,12 +306,93 @@ impl GithubClient {
             reaction: reaction.into(),
         });
 
-        let _: Response<ResponseData> = self.0.graphql().query(&q).await?;
+        let _: Response<ResponseData> = self.client.graphql().query(&q).await?;
+
+        Ok(())
+    }
+
+    async fn add_reaction_rest(
+        &self,
+        owner: &str,
+        name: &str,
+        subject: ReactionSubject<'_>,
+        reaction: ReactionType,
+    ) -> Result<()> {
+        match subject {
+            ReactionSubject::IssueComment { id, .. } => {
+                self.reactions()
+                    .create_for_issue_comment(owner, name, id, reaction)
+                    .await?;
+            }
+            ReactionSubject::ReviewComment { id, .. } => {
+                self.reactions()
+                    .create_for_pull_request_review_comment(owner, name, id, reaction)
+                    .await?;
+            }
+            ReactionSubject::Review { .. } => {
+                debug!("unable to react to a review via the REST API");
+            }
+        }
 
         Ok(())
     }
 
     pub async fn open_pulls(&self, owner: &str, name: &str) -> Result<Vec<PullRequestState>> {
+        self.with_fallback(
+            "open_pulls",
+            self.open_pulls_graphql(owner, name),
+            self.open_pulls_rest(owner, name),
+        )
+        .await
+    }
+
+    async fn open_pulls_rest(&self, owner: &str, name: &str) -> Result<Vec<PullRequestState>> {
+        let mut ret = Vec::new();
+        let mut page = None;
+
+        loop {
+            let options = ListPullsOptions {
+                state: Some(StateFilter::Open),
+                pagination_options: PaginationOptions {
+                    page,
+                    per_page: Some(100),
+                },
+                ..Default::default()
+            };
+            let response = self.pulls().list(owner, name, Some(options)).await?;
+            let (pagination, rate, pulls) = response.as_parts();
+            page = pagination.next_page;
+            debug!(
+                "{}/{} - listed {} open PRs, {}/{} API requests remaining",
+                owner,
+                name,
+                pulls.len(),
+                rate.remaining,
+                rate.limit
+            );
+
+            ret.extend(pulls.iter().map(PullRequestState::from_pull_request));
+
+            if page.is_none() {
+                break;
+            }
+        }
+
+        // Fetched together rather than with a request per PR
+        let numbers = ret.iter().map(|state| state.number).collect::<Vec<_>>();
+        let mut decisions = self.get_review_decisions(owner, name, &numbers).await?;
+        for state in &mut ret {
+            if let Some(decision) = decisions.remove(&state.number) {
+                state.approved = decision.approved;
+                state.approved_at = decision.approved_at;
+                state.reviews = decision.reviews;
+            }
+        }
+
+        Ok(ret)
+    }
+
+    async fn open_pulls_graphql(&self, owner: &str, name: &str) -> Result<Vec<PullRequestState>> {
         use query::{
             list_pulls::{ResponseData, Variables},
             ListPulls,
@@ -62,7 +409,12 @@ impl GithubClient {
                 cursor: cursor.clone(),
             });
 
-            let response: ResponseData = self.0.graphql().query(&q).await?.into_inner();
+            let response = self
+                .client
+                .graphql()
+                .query::<_, ResponseData>(&q)
+                .await?
+                .into_inner();
 
             let pull_requests = if let Some(repo) = response.repository {
                 repo.pull_requests
@@ -84,40 +436,520 @@ impl GithubClient {
         Ok(ret)
     }
 
-    pub async fn get_review_decision(&self, owner: &str, name: &str, number: u64) -> Result<bool> {
-        use query::{
-            get_review_decision::{PullRequestReviewDecision, ResponseData, Variables},
-            GetReviewDecision,
-        };
+    /// Fetch the current state of a single PR, including its review decision
+    pub async fn pull(&self, owner: &str, name: &str, number: u64) -> Result<PullRequestState> {
+        let pull = self.pulls().get(owner, name, number).await?.into_inner();
+        let mut state = PullRequestState::from_pull_request(&pull);
+        self.review_decisions.invalidate(owner, name, number);
+        let decision = self.get_review_decision(owner, name, number).await?;
+        state.approved = decision.approved;
+        state.approved_at = decision.approved_at;
+        state.reviews = decision.reviews;
+        Ok(state)
+    }
 
-        let q = GetReviewDecision::build_query(Variables {
-            owner: owner.to_owned(),
-            name: name.to_owned(),
-            number: number as i64,
-        });
+    /// The PR's review decision, from the cache unless it was invalidated since it was fetched
+    pub async fn get_review_decision(
+        &self,
+        owner: &str,
+        name: &str,
+        number: u64,
+    ) -> Result<ReviewDecision> {
+        if let Some(decision) = self.review_decisions.get(owner, name, number) {
+            return Ok(decision);
+        }
 
-        let response: ResponseData = self.0.graphql().query(&q).await?.into_inner();
+        let decision = self
+            .with_fallback(
+                "get_review_decision",
+                self.get_review_decision_graphql(owner, name, number),
+                self.get_review_decision_rest(owner, name, number),
+            )
+            .await?;
+        self.review_decisions
+            .insert(owner, name, number, decision.clone());
+        Ok(decision)
+    }
+
+    /// Drop the cached review decision of a PR, after an event which may have changed it
+    pub fn invalidate_review_decision(&self, owner: &str, name: &str, number: u64) {
+        self.review_decisions.invalidate(owner, name, number);
+    }
+
+    /// Drop every cached review decision, e.g. when re-synchronizing with Github
+    pub fn clear_review_decisions(&self) {
+        self.review_decisions.clear();
+    }
+
+    pub fn review_decision_cache_stats(&self) -> ReviewDecisionCacheStats {
+        self.review_decisions.stats()
+    }
+
+    /// Approximates Github's review decision from the list of reviews: a PR is approved if it has
+    /// at least one approval and no outstanding requests for changes
+    async fn get_review_decision_rest(
+        &self,
+        owner: &str,
+        name: &str,
+        number: u64,
+    ) -> Result<ReviewDecision> {
+        let mut reviews = Vec::new();
+        let mut page = None;
+        loop {
+            let options = PaginationOptions {
+                page,
+                per_page: Some(100),
+            };
+            let response = self
+                .pulls()
+                .list_reviews(owner, name, number, Some(options))
+                .await?;
+            let (pagination, _, page_reviews) = response.into_parts();
+            reviews.extend(page_reviews);
+
+            page = pagination.next_page;
+            if page.is_none() {
+                break;
+            }
+        }
+
+        let (reviews, approved_at) = summarize_reviews(
+            reviews
+                .into_iter()
+                .map(|review| (review.user.login, review.state, Some(review.submitted_at))),
+        );
+
+        let approved = reviews.values().any(|s| matches!(s, ReviewState::Approved))
+            && !reviews
+                .values()
+                .any(|s| matches!(s, ReviewState::ChangesRequested));
+
+        Ok(ReviewDecision {
+            approved,
+            approved_at,
+            reviews,
+        })
+    }
+
+    async fn get_review_decision_graphql(
+        &self,
+        owner: &str,
+        name: &str,
+        number: u64,
+    ) -> Result<ReviewDecision> {
+        use query::get_review_decision::ResponseData;
+
+        let response = self
+            .client
+            .graphql()
+            .query::<_, ResponseData>(&review_decision_query(owner, name, number))
+            .await?
+            .into_inner();
 
         debug!("get_review_decision #{}: {:#?}", number, response);
+        Ok(review_decision(response))
+    }
 
-        let d = response
-            .repository
-            .and_then(|r| r.pull_request)
-            .and_then(|p| p.review_decision)
-            .map(|d| match d {
-                PullRequestReviewDecision::APPROVED => true,
-                _ => false,
-            })
-            .unwrap_or(false);
+    /// The review decisions of several PRs, from the cache where possible. The others are fetched
+    /// together in a single request if `graphql-batching` is enabled
+    pub async fn get_review_decisions(
+        &self,
+        owner: &str,
+        name: &str,
+        numbers: &[u64],
+    ) -> Result<HashMap<u64, ReviewDecision>> {
+        let mut decisions = HashMap::new();
+        let mut missing = Vec::new();
+        for &number in numbers {
+            match self.review_decisions.get(owner, name, number) {
+                Some(decision) => {
+                    decisions.insert(number, decision);
+                }
+                None => missing.push(number),
+            }
+        }
+        if missing.is_empty() {
+            return Ok(decisions);
+        }
+
+        let rest = async {
+            let mut decisions = Vec::new();
+            for &number in &missing {
+                decisions.push(self.get_review_decision_rest(owner, name, number).await?);
+            }
+            Ok(decisions)
+        };
+        let fetched = self
+            .with_fallback(
+                "get_review_decisions",
+                self.get_review_decisions_graphql(owner, name, &missing),
+                rest,
+            )
+            .await?;
+        for (number, decision) in missing.into_iter().zip(fetched) {
+            self.review_decisions
+                .insert(owner, name, number, decision.clone());
+            decisions.insert(number, decision);
+        }
+
+        Ok(decisions)
+    }
+
+    async fn get_review_decisions_graphql(
+        &self,
+        owner: &str,
+        name: &str,
+        numbers: &[u64],
+    ) -> Result<Vec<ReviewDecision>> {
+        use query::get_review_decision::ResponseData;
+
+        let queries = numbers
+            .iter()
+            .map(|&number| review_decision_query(owner, name, number))
+            .collect::<Vec<_>>();
+        self.client
+            .graphql()
+            .batch::<_, ResponseData>(&queries)
+            .await
+            .into_iter()
+            .map(|response| Ok(review_decision(response?.into_inner())))
+            .collect()
+    }
+}
+
+fn review_decision_query(
+    owner: &str,
+    name: &str,
+    number: u64,
+) -> QueryBody<query::get_review_decision::Variables> {
+    use query::{get_review_decision::Variables, GetReviewDecision};
+
+    GetReviewDecision::build_query(Variables {
+        owner: owner.to_owned(),
+        name: name.to_owned(),
+        number: number as i64,
+    })
+}
+
+fn review_decision(response: query::get_review_decision::ResponseData) -> ReviewDecision {
+    use query::get_review_decision::PullRequestReviewDecision;
 
-        Ok(d)
+    let pull_request = response.repository.and_then(|r| r.pull_request);
+    let approved = pull_request
+        .as_ref()
+        .and_then(|p| p.review_decision.as_ref())
+        .map(|d| matches!(d, PullRequestReviewDecision::APPROVED))
+        .unwrap_or(false);
+    let (reviews, approved_at) = summarize_reviews(
+        pull_request
+            .and_then(|p| p.reviews)
+            .and_then(|reviews| reviews.nodes)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|review| {
+                let login = review.author?.login;
+                Some((login, review.state.into(), review.submitted_at))
+            }),
+    );
+
+    ReviewDecision {
+        approved,
+        approved_at,
+        reviews,
     }
 }
 
+/// Whether a PR is approved, per Github's review decision
+#[derive(Clone, Debug)]
+pub struct ReviewDecision {
+    pub approved: bool,
+    /// When the PR's most recent approving review was submitted
+    pub approved_at: Option<DateTime>,
+    /// The latest approving or blocking review of each reviewer
+    pub reviews: HashMap<String, ReviewState>,
+}
+
+/// Reduce a PR's reviews, oldest first, to the latest approving or blocking review of each
+/// reviewer, along with when the most recent approval was submitted
+fn summarize_reviews<I>(reviews: I) -> (HashMap<String, ReviewState>, Option<DateTime>)
+where
+    I: IntoIterator<Item = (String, ReviewState, Option<DateTime>)>,
+{
+    let mut latest = HashMap::new();
+    let mut approved_at = None;
+    for (login, state, submitted_at) in reviews {
+        match state {
+            ReviewState::Approved => {
+                approved_at = submitted_at.or(approved_at);
+                latest.insert(login, state);
+            }
+            ReviewState::ChangesRequested => {
+                latest.insert(login, state);
+            }
+            // A dismissed review no longer counts either way
+            ReviewState::Dismissed => {
+                latest.remove(&login);
+            }
+            ReviewState::Commented => {}
+        }
+    }
+
+    (latest, approved_at)
+}
+
 impl Deref for GithubClient {
     type Target = Client;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.client
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fake::FakeGithub, is_graphql_failure};
+    use anyhow::{anyhow, Context};
+    use github::{
+        client::Error,
+        test_fixtures::{EventPayload, PullRequestEventBuilder},
+    };
+    use hyper::{Method, StatusCode};
+    use serde_json::json;
+
+    #[test]
+    fn only_graphql_failures_fall_back() {
+        assert!(is_graphql_failure(&Error::GraphqlError(Vec::new()).into()));
+        let wrapped = Err::<(), _>(Error::GraphqlError(Vec::new()))
+            .context("listing open PRs")
+            .unwrap_err();
+        assert!(is_graphql_failure(&wrapped));
+
+        // These would fail the same way through the REST API
+        assert!(!is_graphql_failure(&Error::AuthExpired.into()));
+        assert!(!is_graphql_failure(&Error::RateLimit.into()));
+        assert!(!is_graphql_failure(&Error::ReadOnly.into()));
+        assert!(!is_graphql_failure(&anyhow!("no such PR")));
+    }
+
+    #[tokio::test]
+    async fn auth_errors_leave_graphql_healthy() {
+        let client = FakeGithub::start().client();
+
+        let result = client
+            .with_fallback(
+                "test",
+                async { Err::<(), _>(Error::AuthExpired.into()) },
+                async { Ok(()) },
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(client.graphql_health().consecutive_failures(), 0);
+
+        let result = client
+            .with_fallback(
+                "test",
+                async { Err(Error::GraphqlError(Vec::new()).into()) },
+                async { Ok(()) },
+            )
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(client.graphql_health().consecutive_failures(), 1);
+    }
+
+    fn review_decision_response(decision: &str) -> serde_json::Value {
+        json!({
+            "data": {
+                "repository": {
+                    "pullRequest": { "reviewDecision": decision, "reviews": { "nodes": [] } },
+                },
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn bot_authors_are_listed_with_their_suffix() {
+        let github = FakeGithub::start();
+        let pull = |number: u64, login: &str, typename: &str| {
+            json!({
+                "number": number,
+                "id": format!("PR_{}", number),
+                "databaseId": number,
+                "author": { "login": login, "__typename": typename },
+                "title": "Update deps",
+                "body": "",
+                "state": "OPEN",
+                "isDraft": false,
+                "locked": false,
+                "reviewDecision": null,
+                "reviews": { "nodes": [] },
+                "reviewRequests": { "nodes": [] },
+                "maintainerCanModify": false,
+                "mergeable": "MERGEABLE",
+                "labels": { "nodes": [] },
+                "headRefName": format!("pr-{}", number),
+                "headRefOid": format!("a{:039x}", number),
+                "headRepository": { "nameWithOwner": "bors-rs/bors" },
+                "baseRefName": "master",
+                "baseRefOid": format!("b{:039x}", number),
+            })
+        };
+        github.respond(
+            Method::POST,
+            "graphql",
+            StatusCode::OK,
+            json!({
+                "data": {
+                    "repository": {
+                        "pullRequests": {
+                            "nodes": [pull(1, "dependabot", "Bot"), pull(2, "bors", "User")],
+                            "pageInfo": { "hasNextPage": false, "endCursor": null },
+                            "totalCount": 2,
+                        },
+                    },
+                },
+            }),
+        );
+
+        let pulls = github.client().open_pulls("bors-rs", "bors").await.unwrap();
+        let authors = pulls
+            .iter()
+            .map(|pull| pull.author.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(authors, ["dependabot[bot]", "bors"]);
+    }
+
+    #[tokio::test]
+    async fn review_decisions_are_batched() {
+        let github = FakeGithub::start();
+        let client = github.configured_client(|builder| builder.graphql_batching(true));
+        github.respond(
+            Method::POST,
+            "graphql",
+            StatusCode::OK,
+            json!([
+                review_decision_response("APPROVED"),
+                review_decision_response("CHANGES_REQUESTED"),
+            ]),
+        );
+
+        let decisions = client
+            .get_review_decisions("bors-rs", "bors", &[1, 2])
+            .await
+            .unwrap();
+        assert!(decisions[&1].approved);
+        assert!(!decisions[&2].approved);
+        let requests = github.bodies(Method::POST, "graphql");
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].as_array().unwrap().len(), 2);
+
+        // Cached decisions aren't fetched again
+        client
+            .get_review_decisions("bors-rs", "bors", &[1, 2])
+            .await
+            .unwrap();
+        assert_eq!(github.bodies(Method::POST, "graphql").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rest_pull_listing_batches_review_decisions() {
+        let github = FakeGithub::start();
+        let client = github.configured_client(|builder| builder.graphql_batching(true));
+        let pull = |number: u64| {
+            PullRequestEventBuilder::new("opened")
+                .number(number)
+                .payload()["pull_request"]
+                .clone()
+        };
+        github.respond(
+            Method::GET,
+            "repos/bors-rs/bors/pulls",
+            StatusCode::OK,
+            json!([pull(1), pull(2)]),
+        );
+        github.respond(
+            Method::POST,
+            "graphql",
+            StatusCode::OK,
+            json!([
+                review_decision_response("APPROVED"),
+                review_decision_response("CHANGES_REQUESTED"),
+            ]),
+        );
+
+        let pulls = client.open_pulls_rest("bors-rs", "bors").await.unwrap();
+        let approved = pulls
+            .iter()
+            .map(|pull| (pull.number, pull.approved))
+            .collect::<Vec<_>>();
+        assert_eq!(approved, [(1, true), (2, false)]);
+        assert_eq!(github.bodies(Method::POST, "graphql").len(), 1);
+        assert!(!github
+            .received()
+            .iter()
+            .any(|request| request.path.ends_with("/reviews")));
+    }
+
+    #[tokio::test]
+    async fn persisted_queries_survive_auth_errors() {
+        let github = FakeGithub::start();
+        let client = github.configured_client(|builder| builder.graphql_persisted_queries(true));
+        let is_persisted = |body: &serde_json::Value| body.get("query").is_none();
+
+        github.respond(Method::POST, "graphql", StatusCode::UNAUTHORIZED, json!({}));
+        assert!(client
+            .get_review_decision("bors-rs", "bors", 1)
+            .await
+            .is_err());
+        github.respond(
+            Method::POST,
+            "graphql",
+            StatusCode::OK,
+            review_decision_response("APPROVED"),
+        );
+        assert!(
+            client
+                .get_review_decision("bors-rs", "bors", 1)
+                .await
+                .unwrap()
+                .approved
+        );
+        let requests = github.bodies(Method::POST, "graphql");
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(is_persisted));
+    }
+
+    #[tokio::test]
+    async fn persisted_queries_are_disabled_when_not_found() {
+        let github = FakeGithub::start();
+        let client = github.configured_client(|builder| builder.graphql_persisted_queries(true));
+        let is_persisted = |body: &serde_json::Value| body.get("query").is_none();
+
+        github.respond(Method::POST, "graphql", StatusCode::NOT_FOUND, json!({}));
+        assert!(client
+            .get_review_decision("bors-rs", "bors", 1)
+            .await
+            .is_err());
+        github.respond(
+            Method::POST,
+            "graphql",
+            StatusCode::OK,
+            review_decision_response("APPROVED"),
+        );
+        client.invalidate_review_decision("bors-rs", "bors", 1);
+        assert!(
+            client
+                .get_review_decision("bors-rs", "bors", 1)
+                .await
+                .unwrap()
+                .approved
+        );
+
+        // The persisted query was retried in full, and every later query is sent in full
+        let requests = github.bodies(Method::POST, "graphql");
+        assert_eq!(requests.len(), 3);
+        assert!(is_persisted(&requests[0]));
+        assert!(!is_persisted(&requests[1]));
+        assert!(!is_persisted(&requests[2]));
     }
 }
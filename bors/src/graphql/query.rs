@@ -27,6 +27,7 @@ impl From<github::ReactionType> for add_reaction::ReactionContent {
 }
 
 type GitObjectID = github::Oid;
+type DateTime = github::DateTime;
 
 #[derive(GraphQLQuery)]
 #[graphql(
@@ -49,6 +50,48 @@ impl From<list_pulls::PullRequestState> for github::PullRequestState {
     }
 }
 
+impl From<list_pulls::PullRequestReviewState> for github::ReviewState {
+    fn from(state: list_pulls::PullRequestReviewState) -> Self {
+        use list_pulls::PullRequestReviewState;
+
+        match state {
+            PullRequestReviewState::APPROVED => github::ReviewState::Approved,
+            PullRequestReviewState::CHANGES_REQUESTED => github::ReviewState::ChangesRequested,
+            PullRequestReviewState::DISMISSED => github::ReviewState::Dismissed,
+            // Pending reviews aren't visible to bors and don't affect whether a PR can land
+            PullRequestReviewState::COMMENTED
+            | PullRequestReviewState::PENDING
+            | PullRequestReviewState::Other(_) => github::ReviewState::Commented,
+        }
+    }
+}
+
+impl From<get_review_decision::PullRequestReviewState> for github::ReviewState {
+    fn from(state: get_review_decision::PullRequestReviewState) -> Self {
+        use get_review_decision::PullRequestReviewState;
+
+        match state {
+            PullRequestReviewState::APPROVED => github::ReviewState::Approved,
+            PullRequestReviewState::CHANGES_REQUESTED => github::ReviewState::ChangesRequested,
+            PullRequestReviewState::DISMISSED => github::ReviewState::Dismissed,
+            PullRequestReviewState::COMMENTED
+            | PullRequestReviewState::PENDING
+            | PullRequestReviewState::Other(_) => github::ReviewState::Commented,
+        }
+    }
+}
+
+/// The login of a PR's author as webhooks and the REST API give it, where a bot's login has a
+/// `[bot]` suffix which GraphQL leaves out
+fn author_login(author: list_pulls::ListPullsRepositoryPullRequestsNodesAuthor) -> String {
+    match author.on {
+        list_pulls::ListPullsRepositoryPullRequestsNodesAuthorOn::Bot => {
+            format!("{}[bot]", author.login)
+        }
+        _ => author.login,
+    }
+}
+
 impl From<list_pulls::ListPullsRepositoryPullRequestsNodes> for crate::state::PullRequestState {
     fn from(pull: list_pulls::ListPullsRepositoryPullRequestsNodes) -> Self {
         let list_pulls::ListPullsRepositoryPullRequestsNodes {
@@ -56,6 +99,7 @@ impl From<list_pulls::ListPullsRepositoryPullRequestsNodes> for crate::state::Pu
             database_id,
             author,
             is_draft,
+            locked,
             review_decision,
             maintainer_can_modify,
             mergeable,
@@ -68,9 +112,46 @@ impl From<list_pulls::ListPullsRepositoryPullRequestsNodes> for crate::state::Pu
             title,
             state,
             head_repository,
+            reviews,
+            review_requests,
             ..
         } = pull;
 
+        let (reviews, approved_at) = super::summarize_reviews(
+            reviews
+                .and_then(|reviews| reviews.nodes)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter_map(|review| {
+                    let login = review.author?.login;
+                    Some((login, review.state.into(), review.submitted_at))
+                }),
+        );
+
+        let mut requested_reviewers = std::collections::BTreeSet::new();
+        let mut requested_teams = std::collections::BTreeSet::new();
+        for requested in review_requests
+            .and_then(|requests| requests.nodes)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|request| request.requested_reviewer)
+        {
+            use list_pulls::ListPullsRepositoryPullRequestsNodesReviewRequestsNodesRequestedReviewer as RequestedReviewer;
+
+            match requested {
+                RequestedReviewer::User(user) => {
+                    requested_reviewers.insert(user.login);
+                }
+                RequestedReviewer::Team(team) => {
+                    requested_teams.insert(team.slug);
+                }
+                // Mannequins are placeholders for users of imported repositories and can't review
+                RequestedReviewer::Mannequin => {}
+            }
+        }
+
         let labels = labels
             .into_iter()
             .flat_map(|nodes| {
@@ -91,15 +172,15 @@ impl From<list_pulls::ListPullsRepositoryPullRequestsNodes> for crate::state::Pu
             None
         };
 
-        let approved = match review_decision {
-            Some(list_pulls::PullRequestReviewDecision::APPROVED) => true,
-            _ => false,
-        };
+        let approved = matches!(
+            review_decision,
+            Some(list_pulls::PullRequestReviewDecision::APPROVED)
+        );
 
         Self {
             number: number as u64,
             id: database_id.unwrap() as u64, // XXX ensure this is always populated
-            author: author.map(|a| a.login),
+            author: author.map(author_login),
             title,
             body,
 
@@ -111,6 +192,7 @@ impl From<list_pulls::ListPullsRepositoryPullRequestsNodes> for crate::state::Pu
             base_ref_oid,
 
             is_draft,
+            locked,
             maintainer_can_modify,
             mergeable: matches!(mergeable, list_pulls::MergeableState::MERGEABLE),
             labels,
@@ -118,10 +200,29 @@ impl From<list_pulls::ListPullsRepositoryPullRequestsNodes> for crate::state::Pu
 
             approved_by: std::collections::HashSet::new(),
             approved,
+            approved_at,
+            reviews,
+            requested_reviewers,
+            requested_teams,
             status: crate::state::Status::InReview,
             project_card_id: None,
 
             canary_requested: false,
+            canary_requester: None,
+            notify: Vec::new(),
+            last_merge_oid: None,
+            resume: false,
+            merge_when_green: false,
+            hotfix: false,
+            lane: None,
+            lane_head: None,
+            changed_files: Vec::new(),
+            blocked_since: None,
+            check_run: None,
+            queue_events: Vec::new(),
+            head_test_results: std::collections::HashMap::new(),
+            squash_mode: Default::default(),
+            rebase: None,
         }
     }
 }
@@ -0,0 +1,145 @@
+//! Caching of the review decisions of PRs
+//!
+//! A PR's review decision is checked on several hot paths, e.g. before every `/land`, but only
+//! changes when it's reviewed or pushed to. Decisions are kept until the event processor receives
+//! an event which may change them, and are fetched again once older than `REVIEW_DECISION_TTL` in
+//! case such an event was missed.
+
+use super::ReviewDecision;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long a cached review decision is used before it's fetched again
+const REVIEW_DECISION_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Counters of how well the cache of review decisions is doing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ReviewDecisionCacheStats {
+    pub hits: u64,
+    /// Lookups of PRs without a cached decision
+    pub misses: u64,
+    /// Lookups of decisions older than the TTL, which are fetched again
+    pub expired: u64,
+    /// Decisions dropped because of an event which may have changed them
+    pub invalidations: u64,
+    /// Number of decisions currently cached
+    pub entries: usize,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ReviewDecisionCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Decisions of each PR, keyed by `(owner, name, number)`, along with when they were fetched
+    entries: HashMap<(String, String, u64), (Instant, ReviewDecision)>,
+    stats: ReviewDecisionCacheStats,
+}
+
+impl ReviewDecisionCache {
+    pub(super) fn get(&self, owner: &str, name: &str, number: u64) -> Option<ReviewDecision> {
+        let mut inner = self.inner.lock().unwrap();
+        let key = (owner.to_owned(), name.to_owned(), number);
+        match inner.entries.get(&key) {
+            Some((fetched_at, decision)) if fetched_at.elapsed() < REVIEW_DECISION_TTL => {
+                let decision = decision.clone();
+                inner.stats.hits += 1;
+                Some(decision)
+            }
+            Some(_) => {
+                inner.entries.remove(&key);
+                inner.stats.expired += 1;
+                None
+            }
+            None => {
+                inner.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub(super) fn insert(&self, owner: &str, name: &str, number: u64, decision: ReviewDecision) {
+        self.inner.lock().unwrap().entries.insert(
+            (owner.to_owned(), name.to_owned(), number),
+            (Instant::now(), decision),
+        );
+    }
+
+    pub(super) fn invalidate(&self, owner: &str, name: &str, number: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner
+            .entries
+            .remove(&(owner.to_owned(), name.to_owned(), number))
+            .is_some()
+        {
+            inner.stats.invalidations += 1;
+        }
+    }
+
+    pub(super) fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.stats.invalidations += inner.entries.len() as u64;
+        inner.entries.clear();
+    }
+
+    pub(super) fn stats(&self) -> ReviewDecisionCacheStats {
+        let inner = self.inner.lock().unwrap();
+        ReviewDecisionCacheStats {
+            entries: inner.entries.len(),
+            ..inner.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ReviewDecisionCache, ReviewDecisionCacheStats};
+    use crate::graphql::ReviewDecision;
+    use std::collections::HashMap;
+
+    fn approved() -> ReviewDecision {
+        ReviewDecision {
+            approved: true,
+            approved_at: None,
+            reviews: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn invalidation() {
+        let cache = ReviewDecisionCache::default();
+        assert!(cache.get("bors-rs", "bors", 1).is_none());
+
+        cache.insert("bors-rs", "bors", 1, approved());
+        cache.insert("bors-rs", "bors", 2, approved());
+        assert!(cache.get("bors-rs", "bors", 1).unwrap().approved);
+        // PRs of other repos aren't mixed up
+        assert!(cache.get("bors-rs", "other", 1).is_none());
+
+        cache.invalidate("bors-rs", "bors", 1);
+        assert!(cache.get("bors-rs", "bors", 1).is_none());
+        // Nothing to invalidate
+        cache.invalidate("bors-rs", "bors", 1);
+
+        assert_eq!(
+            cache.stats(),
+            ReviewDecisionCacheStats {
+                hits: 1,
+                misses: 3,
+                expired: 0,
+                invalidations: 1,
+                entries: 1,
+            }
+        );
+
+        cache.clear();
+        assert_eq!(cache.stats().invalidations, 2);
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -0,0 +1,472 @@
+//! Interactive first-run setup of a repository, via `bors init`
+//!
+//! The wizard asks for the repository, credentials and the few settings which differ the most
+//! between repositories, checks that the token can access the repository and then writes a
+//! complete config file. Along the way it can create bors' webhook and labels and align the
+//! repository's merge settings with how bors lands PRs, so that `bors serve` works right away.
+
+use crate::{
+    config::{Config, Labels},
+    event_processor::REQUIRED_WEBHOOK_EVENTS,
+    secret::Secret,
+    Result,
+};
+use anyhow::{anyhow, Context};
+use github::client::{Client, CreateHookRequest, EditRepositoryRequest};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct InitOptions {
+    #[structopt(long)]
+    /// Overwrite the config file if it already exists
+    force: bool,
+
+    #[structopt(long)]
+    /// Don't contact Github, only write the config file
+    offline: bool,
+}
+
+/// How PRs are combined with their base branch when they land
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LandStrategy {
+    Rebase,
+    MergeCommit,
+}
+
+impl LandStrategy {
+    fn as_str(self) -> &'static str {
+        match self {
+            LandStrategy::Rebase => "rebase",
+            LandStrategy::MergeCommit => "merge-commit",
+        }
+    }
+}
+
+/// Everything the wizard asked for
+#[derive(Debug)]
+struct Answers {
+    owner: String,
+    name: String,
+    /// Token, or a reference to it, as written to the config
+    api_token: String,
+    webhook_secret: Option<String>,
+    webhook_url: Option<String>,
+    ssh_key_file: String,
+    git_user: String,
+    git_email: String,
+    strategy: LandStrategy,
+    require_review: bool,
+    checks: Vec<String>,
+    labels: Vec<(&'static str, String)>,
+}
+
+pub async fn run_init(path: &Path, options: &InitOptions) -> Result<()> {
+    if path.exists() && !options.force {
+        return Err(anyhow!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        ));
+    }
+
+    let stdin = io::stdin();
+    let mut prompt = Prompt::new(stdin.lock(), io::stdout());
+    let answers = ask(&mut prompt)?;
+
+    if !options.offline {
+        setup_github(&mut prompt, &answers).await?;
+    }
+
+    fs::write(path, render_config(&answers))
+        .with_context(|| format!("writing {}", path.display()))?;
+    Config::from_file(path)
+        .with_context(|| format!("{} was written but isn't valid", path.display()))?;
+
+    println!(
+        "\nWrote {}. Start bors with `bors --config {} serve`",
+        path.display(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn ask<R: BufRead, W: Write>(prompt: &mut Prompt<R, W>) -> Result<Answers> {
+    let (owner, name) = loop {
+        let repo = prompt.ask("Repository, as owner/name", None)?;
+        match repo.split_once('/') {
+            Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => {
+                break (owner.to_owned(), name.to_owned())
+            }
+            _ => prompt.say("Expected a repository of the form owner/name")?,
+        }
+    };
+
+    prompt.say(
+        "Secrets can be given inline or as a reference: env:VAR, file:/path or \
+        keyring:service/account",
+    )?;
+    let api_token = loop {
+        let token = prompt.ask("Github API token", Some("env:BORS_GITHUB_TOKEN"))?;
+        match Secret::resolve(&token) {
+            Ok(_) => break token,
+            Err(e) => prompt.say(&format!("{:#}", e))?,
+        }
+    };
+    let webhook_secret = loop {
+        let secret = prompt.ask_optional("Webhook secret (empty for none)")?;
+        match secret.as_deref().map(Secret::resolve).transpose() {
+            Ok(_) => break secret,
+            Err(e) => prompt.say(&format!("{:#}", e))?,
+        }
+    };
+    let webhook_url = prompt
+        .ask_optional("URL Github delivers webhooks to, e.g. https://bors.example.com/github")?;
+
+    // bors doesn't expand `~`, so the default is spelled out
+    let default_ssh_key = std::env::var("HOME")
+        .map(|home| format!("{}/.ssh/id_ed25519", home))
+        .unwrap_or_else(|_| "id_ed25519".to_owned());
+    let ssh_key_file = prompt.ask("SSH key used to push", Some(&default_ssh_key))?;
+    let git_user = prompt.ask("Name of the commits bors creates", Some("bors"))?;
+    let git_email = prompt.ask(
+        "Email of the commits bors creates",
+        Some("bors@example.com"),
+    )?;
+
+    let strategy = match prompt
+        .choose(
+            "Land PRs by",
+            &["rebase", "merge-commit"],
+            LandStrategy::Rebase.as_str(),
+        )?
+        .as_str()
+    {
+        "merge-commit" => LandStrategy::MergeCommit,
+        _ => LandStrategy::Rebase,
+    };
+    let require_review = prompt.confirm("Require an approving review to land", true)?;
+    let checks = prompt
+        .ask_optional("Checks which must pass before landing, comma separated")?
+        .map(|checks| {
+            checks
+                .split(',')
+                .map(str::trim)
+                .filter(|check| !check.is_empty())
+                .map(ToOwned::to_owned)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let defaults = Labels::default();
+    let mut labels = Vec::new();
+    if prompt.confirm("Customize the names of bors' labels", false)? {
+        for (key, default) in labels_by_key(&defaults) {
+            let label = prompt.ask(&format!("Label for {}", key), Some(default))?;
+            if label != default {
+                labels.push((key, label));
+            }
+        }
+    }
+
+    Ok(Answers {
+        owner,
+        name,
+        api_token,
+        webhook_secret,
+        webhook_url,
+        ssh_key_file,
+        git_user,
+        git_email,
+        strategy,
+        require_review,
+        checks,
+        labels,
+    })
+}
+
+/// Each of `labels` along with its key in the `[repo.labels]` section
+fn labels_by_key(labels: &Labels) -> [(&'static str, &str); 5] {
+    [
+        ("squash", labels.squash()),
+        ("high-priority", labels.high_priority()),
+        ("low-priority", labels.low_priority()),
+        ("canary", labels.canary()),
+        ("merge-commit", labels.merge_commit()),
+    ]
+}
+
+/// Check that the token can access the repository, then offer to create the webhook and labels
+/// and to align the repository's merge settings
+async fn setup_github<R: BufRead, W: Write>(
+    prompt: &mut Prompt<R, W>,
+    answers: &Answers,
+) -> Result<()> {
+    let (owner, name) = (answers.owner.as_str(), answers.name.as_str());
+    let token = Secret::resolve(&answers.api_token)?;
+    let client = Client::builder()
+        .github_api_token(token.expose())
+        .user_agent("bors")
+        .build()?;
+
+    let repo = client
+        .repos()
+        .get(owner, name)
+        .await
+        .with_context(|| format!("unable to access {}/{} with the token", owner, name))?
+        .into_inner();
+    prompt.say(&format!(
+        "The token can access {} (default branch '{}')",
+        repo.full_name, repo.default_branch
+    ))?;
+
+    // PRs merged outside of bors, e.g. with the merge button, should have the same history as
+    // those it lands
+    let merge_commit = answers.strategy == LandStrategy::MergeCommit;
+    let aligned = repo.allow_merge_commit == Some(merge_commit)
+        && repo.allow_rebase_merge == Some(!merge_commit)
+        && repo.allow_squash_merge == Some(false);
+    if !aligned
+        && prompt.confirm(
+            &format!(
+                "Only allow merging PRs by {} on Github, like bors lands them",
+                answers.strategy.as_str()
+            ),
+            false,
+        )?
+    {
+        let request = EditRepositoryRequest {
+            allow_merge_commit: Some(merge_commit),
+            allow_rebase_merge: Some(!merge_commit),
+            allow_squash_merge: Some(false),
+            ..Default::default()
+        };
+        client.repos().edit(owner, name, &request).await?;
+        prompt.say("Updated the repository's merge settings")?;
+    }
+
+    if let Some(url) = &answers.webhook_url {
+        let hooks = client
+            .repos()
+            .list_hooks(owner, name, Default::default())
+            .await?
+            .into_inner();
+        if hooks.iter().any(|hook| hook.config.url == *url) {
+            prompt.say(&format!("A webhook delivering to {} already exists", url))?;
+        } else if prompt.confirm(&format!("Create a webhook delivering to {}", url), true)? {
+            let secret = answers
+                .webhook_secret
+                .as_deref()
+                .map(Secret::resolve)
+                .transpose()?;
+            let request = CreateHookRequest::new(
+                url,
+                secret.as_ref().map(Secret::expose),
+                REQUIRED_WEBHOOK_EVENTS,
+            );
+            client.repos().create_hook(owner, name, &request).await?;
+            prompt.say("Created the webhook")?;
+        }
+    }
+
+    if prompt.confirm(
+        "Create bors' labels now (otherwise they're created on startup)",
+        true,
+    )? {
+        let defaults = Labels::default();
+        for (key, default) in labels_by_key(&defaults) {
+            let label = answers
+                .labels
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map_or(default, |(_, label)| label.as_str());
+            if client.issues().get_label(owner, name, label).await.is_err() {
+                client
+                    .issues()
+                    .create_label(owner, name, label, "D0D8D8", None)
+                    .await?;
+                prompt.say(&format!("Created label '{}'", label))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A toml string literal of `s`
+fn quote(s: &str) -> String {
+    toml::Value::String(s.to_owned()).to_string()
+}
+
+fn render_config(answers: &Answers) -> String {
+    let mut config = String::new();
+    let mut line = |l: String| {
+        config.push_str(&l);
+        config.push('\n');
+    };
+
+    line("[github]".to_owned());
+    line(format!("github-api-token = {}", quote(&answers.api_token)));
+    if let Some(secret) = &answers.webhook_secret {
+        line(format!("webhook-secret = {}", quote(secret)));
+    }
+    if let Some(url) = &answers.webhook_url {
+        line(format!("webhook-url = {}", quote(url)));
+    }
+    line(String::new());
+
+    line("[git]".to_owned());
+    line(format!("ssh-key-file = {}", quote(&answers.ssh_key_file)));
+    line(format!("user = {}", quote(&answers.git_user)));
+    line(format!("email = {}", quote(&answers.git_email)));
+    line(String::new());
+
+    line("[[repo]]".to_owned());
+    line(format!("owner = {}", quote(&answers.owner)));
+    line(format!("name = {}", quote(&answers.name)));
+    line(format!("require-review = {}", answers.require_review));
+    line(format!(
+        "merge-commits = {}",
+        answers.strategy == LandStrategy::MergeCommit
+    ));
+    line(format!(
+        "checks = [{}]",
+        answers
+            .checks
+            .iter()
+            .map(|check| quote(check))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    if !answers.labels.is_empty() {
+        line(String::new());
+        line("[repo.labels]".to_owned());
+        for (key, label) in &answers.labels {
+            line(format!("{} = {}", key, quote(label)));
+        }
+    }
+
+    config
+}
+
+/// Questions asked on `output`, with their answers read from `input`
+struct Prompt<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    fn say(&mut self, message: &str) -> Result<()> {
+        writeln!(self.output, "{}", message)?;
+        Ok(())
+    }
+
+    /// The trimmed answer to `question`, or `None` if it was left empty
+    fn ask_optional(&mut self, question: &str) -> Result<Option<String>> {
+        write!(self.output, "{}: ", question)?;
+        self.output.flush()?;
+
+        let mut answer = String::new();
+        if self.input.read_line(&mut answer)? == 0 {
+            return Err(anyhow!("input ended before '{}' was answered", question));
+        }
+        let answer = answer.trim();
+        Ok(Some(answer.to_owned()).filter(|a| !a.is_empty()))
+    }
+
+    /// The answer to `question`, which is asked again until it's answered unless it has a default
+    fn ask(&mut self, question: &str, default: Option<&str>) -> Result<String> {
+        loop {
+            let question = match default {
+                Some(default) => format!("{} [{}]", question, default),
+                None => question.to_owned(),
+            };
+            match (self.ask_optional(&question)?, default) {
+                (Some(answer), _) => return Ok(answer),
+                (None, Some(default)) => return Ok(default.to_owned()),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn confirm(&mut self, question: &str, default: bool) -> Result<bool> {
+        let question = format!("{} ({})", question, if default { "Y/n" } else { "y/N" });
+        loop {
+            match self.ask_optional(&question)?.as_deref() {
+                None => return Ok(default),
+                Some("y") | Some("Y") | Some("yes") => return Ok(true),
+                Some("n") | Some("N") | Some("no") => return Ok(false),
+                Some(_) => self.say("Please answer yes or no")?,
+            }
+        }
+    }
+
+    fn choose(&mut self, question: &str, choices: &[&str], default: &str) -> Result<String> {
+        let question = format!("{} ({})", question, choices.join("/"));
+        loop {
+            let answer = self.ask(&question, Some(default))?;
+            if choices.contains(&answer.as_str()) {
+                return Ok(answer);
+            }
+            self.say(&format!("Please answer one of: {}", choices.join(", ")))?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ask, render_config, LandStrategy, Prompt};
+    use crate::config::Config;
+
+    #[test]
+    fn wizard_writes_a_valid_config() {
+        let input = "\
+not-a-repo
+bors-rs/bors
+hunter2
+
+https://bors.example.com/github
+/etc/bors/id_ed25519
+
+
+merge-commit
+n
+ci, lint
+y
+
+bors-urgent
+
+
+
+";
+        let mut output = Vec::new();
+        let answers = ask(&mut Prompt::new(input.as_bytes(), &mut output)).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Expected a repository of the form owner/name"));
+
+        assert_eq!(answers.strategy, LandStrategy::MergeCommit);
+        assert_eq!(answers.webhook_secret, None);
+        assert_eq!(
+            answers.labels,
+            [("high-priority", "bors-urgent".to_owned())]
+        );
+
+        let config: Config = toml::from_str(&render_config(&answers)).unwrap();
+        assert_eq!(config.github.github_api_token.unwrap().expose(), "hunter2");
+        assert_eq!(config.git.user, "bors");
+        let repo = &config.repo[0];
+        assert_eq!(repo.repo().to_string(), "bors-rs/bors");
+        assert!(!repo.require_review());
+        assert!(repo.merge_commits());
+        assert_eq!(repo.checks().collect::<Vec<_>>(), ["ci", "lint"]);
+        assert_eq!(repo.labels().high_priority(), "bors-urgent");
+        assert_eq!(repo.labels().squash(), "bors-squash");
+    }
+}
@@ -0,0 +1,360 @@
+//! CI insights, derived from how long the checks on merge commits take and how often they fail
+//!
+//! Every completed check on a merge commit being tested is recorded in the repo's store. From
+//! those records bors reports the slowest checks, the flakiest checks, i.e. those which failed and
+//! then passed on a later attempt to land the same PR head, and how checks trend over time.
+//!
+//! How far each PR was behind its base when it landed is recorded too, so that teams can judge
+//! how stale PRs get while waiting to land.
+
+use github::{DateTime, Oid};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+/// Maximum number of check runs remembered, the oldest are dropped first
+pub const MAX_CHECK_RUNS: usize = 2000;
+
+/// Maximum number of landed PRs whose merge skew is remembered, the oldest are dropped first
+pub const MAX_MERGE_SKEWS: usize = 1000;
+
+/// Number of checks listed as the slowest and flakiest
+const TOP_CHECKS: usize = 10;
+
+/// Number of days, up to and including today, covered by the trend
+const TREND_DAYS: u64 = 30;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A completed check on a merge commit
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct CheckRunRecord {
+    pub name: String,
+    pub pr: u64,
+    /// The PR's head when it was tested, so that re-tests of the same changes can be found
+    pub head_oid: Oid,
+    pub merge_oid: Oid,
+    pub started_at: DateTime,
+    pub finished_at: DateTime,
+    pub passed: bool,
+}
+
+impl CheckRunRecord {
+    pub fn duration(&self) -> Duration {
+        self.finished_at
+            .duration_since(&self.started_at)
+            .unwrap_or_default()
+    }
+
+    fn day(&self) -> u64 {
+        self.finished_at.timestamp().max(0) as u64 / SECONDS_PER_DAY
+    }
+}
+
+/// How far a PR was behind its base when it landed
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct MergeSkewRecord {
+    pub pr: u64,
+    pub landed_at: DateTime,
+    /// Number of commits on the base which the PR's head didn't contain
+    pub commits_behind: usize,
+    /// How long before landing the oldest of those commits was committed
+    pub behind_seconds: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckDuration {
+    pub name: String,
+    pub runs: usize,
+    pub average: Duration,
+    pub max: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckFlakiness {
+    pub name: String,
+    pub runs: usize,
+    /// Number of failures which were followed by the check passing on the same PR head
+    pub flakes: usize,
+}
+
+impl CheckFlakiness {
+    pub fn rate(&self) -> f64 {
+        self.flakes as f64 / self.runs as f64
+    }
+}
+
+/// The checks which completed on one day
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrendPoint {
+    /// Days since the epoch
+    pub day: u64,
+    pub runs: usize,
+    pub failures: usize,
+    pub average: Duration,
+}
+
+impl TrendPoint {
+    /// The day formatted as `YYYY-MM-DD`
+    pub fn date(&self) -> String {
+        DateTime::from_timestamp((self.day * SECONDS_PER_DAY) as i64)
+            .map(|date| date.to_string()[..10].to_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// How far PRs were behind their base when they landed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeSkewSummary {
+    pub landed: usize,
+    /// Number of the landed PRs which were behind their base
+    pub behind: usize,
+    pub average_commits_behind: f64,
+    pub max_commits_behind: usize,
+    /// Average and longest time the PRs which were behind had been behind for
+    pub average_behind: Duration,
+    pub max_behind: Duration,
+}
+
+impl MergeSkewSummary {
+    pub fn new<'a, I>(skews: I) -> Self
+    where
+        I: IntoIterator<Item = &'a MergeSkewRecord>,
+    {
+        let skews = skews.into_iter().collect::<Vec<_>>();
+        let behind = skews
+            .iter()
+            .filter(|skew| skew.commits_behind > 0)
+            .collect::<Vec<_>>();
+        let ages = || {
+            behind
+                .iter()
+                .map(|skew| Duration::from_secs(skew.behind_seconds))
+        };
+
+        Self {
+            landed: skews.len(),
+            behind: behind.len(),
+            average_commits_behind: if skews.is_empty() {
+                0.0
+            } else {
+                skews.iter().map(|skew| skew.commits_behind).sum::<usize>() as f64
+                    / skews.len() as f64
+            },
+            max_commits_behind: skews
+                .iter()
+                .map(|skew| skew.commits_behind)
+                .max()
+                .unwrap_or(0),
+            average_behind: average(ages()),
+            max_behind: ages().max().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CiInsights {
+    pub total_runs: usize,
+    /// Checks with the longest average duration, slowest first
+    pub slowest: Vec<CheckDuration>,
+    /// Checks which have flaked at least once, by decreasing rate of flaking
+    pub flakiest: Vec<CheckFlakiness>,
+    /// One point for each day of the last `TREND_DAYS` with any checks, oldest first
+    pub trend: Vec<TrendPoint>,
+    pub merge_skew: MergeSkewSummary,
+}
+
+impl CiInsights {
+    pub fn new<'a, I>(runs: I, now: DateTime) -> Self
+    where
+        I: IntoIterator<Item = &'a CheckRunRecord>,
+    {
+        let mut runs = runs.into_iter().collect::<Vec<_>>();
+        runs.sort_by_key(|run| run.finished_at);
+
+        let mut by_name: BTreeMap<&str, Vec<&CheckRunRecord>> = BTreeMap::new();
+        for run in &runs {
+            by_name.entry(&run.name).or_default().push(run);
+        }
+
+        let mut slowest = by_name
+            .iter()
+            .map(|(name, runs)| CheckDuration {
+                name: (*name).to_owned(),
+                runs: runs.len(),
+                average: average(runs.iter().map(|run| run.duration())),
+                max: runs.iter().map(|run| run.duration()).max().unwrap(),
+            })
+            .collect::<Vec<_>>();
+        slowest.sort_by_key(|check| Reverse(check.average));
+        slowest.truncate(TOP_CHECKS);
+
+        let mut flakiest = by_name
+            .iter()
+            .map(|(name, runs)| CheckFlakiness {
+                name: (*name).to_owned(),
+                runs: runs.len(),
+                flakes: count_flakes(runs),
+            })
+            .filter(|flakiness| flakiness.flakes > 0)
+            .collect::<Vec<_>>();
+        flakiest.sort_by(|a, b| b.rate().partial_cmp(&a.rate()).unwrap());
+        flakiest.truncate(TOP_CHECKS);
+
+        let today = now.timestamp().max(0) as u64 / SECONDS_PER_DAY;
+        let mut by_day: BTreeMap<u64, Vec<&CheckRunRecord>> = BTreeMap::new();
+        for run in &runs {
+            let day = run.day();
+            if day + TREND_DAYS > today && day <= today {
+                by_day.entry(day).or_default().push(run);
+            }
+        }
+        let trend = by_day
+            .into_iter()
+            .map(|(day, runs)| TrendPoint {
+                day,
+                runs: runs.len(),
+                failures: runs.iter().filter(|run| !run.passed).count(),
+                average: average(runs.iter().map(|run| run.duration())),
+            })
+            .collect();
+
+        Self {
+            total_runs: runs.len(),
+            slowest,
+            flakiest,
+            trend,
+            merge_skew: MergeSkewSummary::default(),
+        }
+    }
+
+    /// Include how far the PRs in `skews` were behind their base when they landed
+    pub fn with_merge_skews<'a, I>(self, skews: I) -> Self
+    where
+        I: IntoIterator<Item = &'a MergeSkewRecord>,
+    {
+        Self {
+            merge_skew: MergeSkewSummary::new(skews),
+            ..self
+        }
+    }
+}
+
+fn average(durations: impl Iterator<Item = Duration>) -> Duration {
+    let (total, count) = durations.fold((Duration::default(), 0), |(total, count), duration| {
+        (total + duration, count + 1)
+    });
+    if count == 0 {
+        Duration::default()
+    } else {
+        total / count
+    }
+}
+
+/// Count the failures in `runs`, which are all of the same check and in the order they finished,
+/// that were followed by the check passing on the same PR head
+fn count_flakes(runs: &[&CheckRunRecord]) -> usize {
+    let mut pending_failures: HashMap<(u64, &Oid), usize> = HashMap::new();
+    let mut flakes = 0;
+    for run in runs {
+        let key = (run.pr, &run.head_oid);
+        if run.passed {
+            flakes += pending_failures.remove(&key).unwrap_or(0);
+        } else {
+            *pending_failures.entry(key).or_default() += 1;
+        }
+    }
+    flakes
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CheckRunRecord, CiInsights, MergeSkewRecord, MergeSkewSummary, SECONDS_PER_DAY};
+    use github::{DateTime, Oid};
+    use std::time::Duration;
+
+    fn run(
+        name: &str,
+        pr: u64,
+        head: &str,
+        start: i64,
+        seconds: i64,
+        passed: bool,
+    ) -> CheckRunRecord {
+        CheckRunRecord {
+            name: name.to_owned(),
+            pr,
+            head_oid: Oid::from_str(head),
+            merge_oid: Oid::from_str("0000000000000000000000000000000000000000"),
+            started_at: DateTime::from_timestamp(start).unwrap(),
+            finished_at: DateTime::from_timestamp(start + seconds).unwrap(),
+            passed,
+        }
+    }
+
+    #[test]
+    fn insights() {
+        const HEAD_A: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        const HEAD_B: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+        let day = SECONDS_PER_DAY as i64;
+        let runs = vec![
+            // `test` fails on #1, then passes when it's re-tested without changes
+            run("test", 1, HEAD_A, 100 * day, 600, false),
+            run("test", 1, HEAD_A, 100 * day + 1000, 400, true),
+            // #2 is updated after `test` fails, so that failure isn't a flake
+            run("test", 2, HEAD_A, 101 * day, 500, false),
+            run("test", 2, HEAD_B, 101 * day + 1000, 500, true),
+            run("lint", 1, HEAD_A, 100 * day, 60, true),
+            run("lint", 2, HEAD_B, 101 * day, 120, true),
+            // Too old to be part of the trend
+            run("lint", 3, HEAD_A, 0, 90, true),
+        ];
+
+        let insights = CiInsights::new(&runs, DateTime::from_timestamp(101 * day + 5000).unwrap());
+        assert_eq!(insights.total_runs, 7);
+
+        assert_eq!(insights.slowest[0].name, "test");
+        assert_eq!(insights.slowest[0].average, Duration::from_secs(500));
+        assert_eq!(insights.slowest[0].max, Duration::from_secs(600));
+        assert_eq!(insights.slowest[1].name, "lint");
+        assert_eq!(insights.slowest[1].average, Duration::from_secs(90));
+
+        assert_eq!(insights.flakiest.len(), 1);
+        assert_eq!(insights.flakiest[0].name, "test");
+        assert_eq!(insights.flakiest[0].flakes, 1);
+        assert_eq!(insights.flakiest[0].rate(), 0.25);
+
+        assert_eq!(insights.trend.len(), 2);
+        assert_eq!(insights.trend[0].date(), "1970-04-11");
+        assert_eq!(insights.trend[0].runs, 3);
+        assert_eq!(insights.trend[0].failures, 1);
+        assert_eq!(insights.trend[1].date(), "1970-04-12");
+        assert_eq!(insights.trend[1].failures, 1);
+    }
+
+    #[test]
+    fn merge_skew() {
+        let skew = |pr, commits_behind, behind_seconds| MergeSkewRecord {
+            pr,
+            landed_at: DateTime::from_timestamp(1000).unwrap(),
+            commits_behind,
+            behind_seconds,
+        };
+        let skews = vec![skew(1, 0, 0), skew(2, 3, 600), skew(3, 9, 1800)];
+
+        let summary = MergeSkewSummary::new(&skews);
+        assert_eq!(summary.landed, 3);
+        assert_eq!(summary.behind, 2);
+        assert_eq!(summary.average_commits_behind, 4.0);
+        assert_eq!(summary.max_commits_behind, 9);
+        assert_eq!(summary.average_behind, Duration::from_secs(1200));
+        assert_eq!(summary.max_behind, Duration::from_secs(1800));
+
+        assert_eq!(MergeSkewSummary::new(&[]), MergeSkewSummary::default());
+    }
+}
@@ -0,0 +1,36 @@
+//! Labeling of PRs based on the files they change
+//!
+//! Policies keyed on labels, e.g. the priority of a PR, can then follow the parts of the repo a PR
+//! touches without anyone having to label it by hand. Labels are only ever added: one which no
+//! longer matches after a push may have been applied on purpose, so it's left for people to remove.
+
+use crate::{config::RepoConfig, graphql::GithubClient, state::PullRequestState, Result};
+use log::info;
+
+/// Apply the repo's `path-labels` matching the files changed by `pull`
+pub async fn label_by_paths(
+    config: &RepoConfig,
+    github: &GithubClient,
+    pull: &mut PullRequestState,
+) -> Result<()> {
+    if !config.has_path_labels() {
+        return Ok(());
+    }
+
+    let files = pull.fetch_changed_files(config, github).await?;
+    let labels = config
+        .labels_for_paths(files.iter().map(String::as_str))
+        .into_iter()
+        .filter(|label| !pull.has_label(label))
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    for label in labels {
+        info!(
+            "pr #{} labeled '{}' by its changed paths",
+            pull.number, label
+        );
+        pull.add_label(config, github, &label).await?;
+    }
+
+    Ok(())
+}
@@ -1,14 +1,40 @@
+mod activity;
+pub mod api;
+mod audit;
+mod bisect;
 mod command;
 mod config;
+mod conflicts;
+mod cooldown;
 mod event_processor;
-mod git;
+pub mod git;
+#[cfg(test)]
+mod golden;
 mod graphql;
+mod init;
+mod insights;
+mod labeler;
 mod project_board;
+mod protection;
 mod queue;
+mod queue_hooks;
+mod secret;
 mod server;
 mod service;
+mod simulate;
+mod sla;
+mod snapshot;
 mod state;
+mod store;
+mod summary;
+mod teams;
+mod templates;
+mod verify_webhook;
 
 pub use anyhow::{Error, Result};
 pub use config::Config;
+pub use init::{run_init, InitOptions};
 pub use service::{run_serve, ServeOptions};
+pub use simulate::{run_simulate, SimulateOptions};
+pub use snapshot::{run_export_state, run_import_state, ExportStateOptions, ImportStateOptions};
+pub use verify_webhook::{run_verify_webhook, VerifyWebhookOptions};
@@ -1,4 +1,8 @@
-use bors::{run_serve, Config, Result, ServeOptions};
+use bors::{
+    run_export_state, run_import_state, run_init, run_serve, run_simulate, run_verify_webhook,
+    Config, ExportStateOptions, ImportStateOptions, InitOptions, Result, ServeOptions,
+    SimulateOptions, VerifyWebhookOptions,
+};
 use log::info;
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -15,9 +19,29 @@ struct Options {
 
 #[derive(StructOpt)]
 enum Command {
+    #[structopt(name = "init")]
+    /// Interactively set up a repository and write the config file
+    Init(InitOptions),
+
     #[structopt(name = "serve")]
     /// Run the server
     Serve(ServeOptions),
+
+    #[structopt(name = "export-state")]
+    /// Export a repository's state from a running server
+    ExportState(ExportStateOptions),
+
+    #[structopt(name = "import-state")]
+    /// Import a repository's state, read from stdin, into a running server
+    ImportState(ImportStateOptions),
+
+    #[structopt(name = "simulate")]
+    /// Simulate processing the merge queue to estimate throughput and wait times
+    Simulate(SimulateOptions),
+
+    #[structopt(name = "verify-webhook")]
+    /// Check a saved webhook delivery the way the server would, reporting each step
+    VerifyWebhook(VerifyWebhookOptions),
 }
 
 #[tokio::main]
@@ -27,11 +51,22 @@ async fn main() -> Result<()> {
     // set up logging, allowing info level logging by default
     env_logger::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    info!("bors starting");
-
-    let config = Config::from_file(&opts.config)?;
-
     match &opts.command {
-        Command::Serve(options) => run_serve(config, options).await,
+        Command::Init(options) => run_init(&opts.config, options).await,
+        Command::Serve(options) => {
+            info!("bors starting");
+            let config = Config::from_file(&opts.config)?;
+            run_serve(config, options).await
+        }
+        Command::ExportState(options) => run_export_state(options).await,
+        Command::ImportState(options) => run_import_state(options).await,
+        Command::Simulate(options) => {
+            let config = Config::from_file(&opts.config)?;
+            run_simulate(&config, options)
+        }
+        Command::VerifyWebhook(options) => {
+            let config = Config::from_file(&opts.config)?;
+            run_verify_webhook(&config, options)
+        }
     }
 }
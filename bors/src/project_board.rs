@@ -1,6 +1,7 @@
 use crate::{
     config::RepoConfig,
     graphql::GithubClient,
+    queue::PullRequests,
     state::{PullRequestState, Status},
     Result,
 };
@@ -8,13 +9,13 @@ use github::{
     client::{ListProjectCardsOptions, PaginationOptions},
     Project, ProjectCard, ProjectColumn,
 };
-use std::collections::HashMap;
 
 const PROJECT_BOARD_NAME: &str = "bors";
 const REVIEW_COLUMN_NAME: &str = "In Review";
 const QUEUED_COLUMN_NAME: &str = "Queued";
 const TESTING_COLUMN_NAME: &str = "Testing";
 const CANARY_COLUMN_NAME: &str = "Canary";
+const FAILED_COLUMN_NAME: &str = "Failed";
 
 #[derive(Debug)]
 pub struct ProjectBoard {
@@ -23,6 +24,7 @@ pub struct ProjectBoard {
     queued_column: ProjectColumn,
     testing_column: ProjectColumn,
     canary_column: ProjectColumn,
+    failed_column: ProjectColumn,
 }
 
 impl ProjectBoard {
@@ -41,6 +43,7 @@ impl ProjectBoard {
                 Status::Queued(_) => self.queued_column.id,
                 Status::Testing { .. } => self.testing_column.id,
                 Status::Canary { .. } => self.canary_column.id,
+                Status::Failed { .. } => self.failed_column.id,
             };
             Self::move_card_to_column(github, card_id, column_id).await?;
         }
@@ -86,11 +89,11 @@ impl ProjectBoard {
     pub async fn synchronize_or_init(
         github: &GithubClient,
         config: &RepoConfig,
-        open_pulls: &mut HashMap<u64, PullRequestState>,
+        open_pulls: &mut PullRequests,
     ) -> Result<Self> {
         let board = Self::create_or_get_project_board(github, config).await?;
 
-        let (review_column, queued_column, testing_column, canary_column) =
+        let (review_column, queued_column, testing_column, canary_column, failed_column) =
             Self::create_or_get_columns(github, board.id).await?;
 
         Self::init_project_cards(
@@ -100,6 +103,7 @@ impl ProjectBoard {
             queued_column.id,
             testing_column.id,
             canary_column.id,
+            failed_column.id,
         )
         .await?;
 
@@ -109,6 +113,7 @@ impl ProjectBoard {
             queued_column,
             testing_column,
             canary_column,
+            failed_column,
         })
     }
 
@@ -150,11 +155,13 @@ impl ProjectBoard {
         github::ProjectColumn,
         github::ProjectColumn,
         github::ProjectColumn,
+        github::ProjectColumn,
     )> {
         let mut review_column = None;
         let mut queued_column = None;
         let mut testing_column = None;
         let mut canary_column = None;
+        let mut failed_column = None;
 
         for column in github
             .projects()
@@ -167,6 +174,7 @@ impl ProjectBoard {
                 QUEUED_COLUMN_NAME => queued_column = Some(column),
                 TESTING_COLUMN_NAME => testing_column = Some(column),
                 CANARY_COLUMN_NAME => canary_column = Some(column),
+                FAILED_COLUMN_NAME => failed_column = Some(column),
                 // Delete columns which don't match
                 _ => {
                     github.projects().delete_column(column.id).await?;
@@ -187,7 +195,17 @@ impl ProjectBoard {
             Self::unwrap_or_create_column(canary_column, CANARY_COLUMN_NAME, project_id, github)
                 .await?;
 
-        Ok((review_column, queued_column, testing_column, canary_column))
+        let failed_column =
+            Self::unwrap_or_create_column(failed_column, FAILED_COLUMN_NAME, project_id, github)
+                .await?;
+
+        Ok((
+            review_column,
+            queued_column,
+            testing_column,
+            canary_column,
+            failed_column,
+        ))
     }
 
     async fn unwrap_or_create_column(
@@ -211,11 +229,12 @@ impl ProjectBoard {
 
     async fn init_project_cards(
         github: &GithubClient,
-        open_pulls: &mut HashMap<u64, PullRequestState>,
+        open_pulls: &mut PullRequests,
         review_column_id: u64,
         queued_column_id: u64,
         testing_column_id: u64,
         canary_column_id: u64,
+        failed_column_id: u64,
     ) -> Result<()> {
         Self::assign_or_delete_cards_in_column(github, open_pulls, review_column_id, None).await?;
         Self::assign_or_delete_cards_in_column(
@@ -239,9 +258,16 @@ impl ProjectBoard {
             Some(review_column_id),
         )
         .await?;
+        Self::assign_or_delete_cards_in_column(
+            github,
+            open_pulls,
+            failed_column_id,
+            Some(review_column_id),
+        )
+        .await?;
 
         // Create cards for remaining PRs
-        for (_n, pull) in open_pulls.iter_mut() {
+        for mut pull in open_pulls.values_mut() {
             if pull.project_card_id.is_none() {
                 let request = github::client::CreateProjectCardRequest {
                     note: None,
@@ -291,13 +317,13 @@ impl ProjectBoard {
 
     async fn assign_or_delete_cards_in_column(
         github: &GithubClient,
-        open_pulls: &mut HashMap<u64, PullRequestState>,
+        open_pulls: &mut PullRequests,
         column_id: u64,
         dst_column: Option<u64>,
     ) -> Result<()> {
-        for card in Self::list_cards(&github, column_id).await? {
+        for card in Self::list_cards(github, column_id).await? {
             match card.issue_number().and_then(|n| open_pulls.get_mut(&n)) {
-                Some(pull) => {
+                Some(mut pull) => {
                     pull.project_card_id = Some(card.id);
                     if let Some(dst_column) = dst_column {
                         Self::move_card_to_column(github, card.id, dst_column).await?;
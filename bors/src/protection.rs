@@ -0,0 +1,232 @@
+//! Detection of branch protection settings which keep bors from testing or landing PRs
+//!
+//! While synchronizing, bors fetches the protection of each branch it pushes to: the base branches
+//! of the open PRs, which it lands them on, and its test branches, which it force-pushes merge
+//! commits to. Settings which would make those pushes fail are logged and shown on the repo's page
+//! along with a suggestion of how to resolve them.
+
+use crate::{config::RepoConfig, event_processor::BORS_CONTEXT};
+use github::{BranchProtection, ProtectionSetting};
+use serde::Serialize;
+use std::fmt;
+
+/// How bors uses a branch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BranchRole {
+    /// PRs are landed on the branch by pushing their tested merge commit to it
+    Base,
+    /// Merge commits are force-pushed to the branch to be tested
+    Test,
+}
+
+/// A protection setting of a branch which conflicts with how bors uses it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProtectionConflict {
+    pub branch: String,
+    pub problem: String,
+    pub suggestion: String,
+}
+
+impl ProtectionConflict {
+    fn new(branch: &str, problem: String, suggestion: String) -> Self {
+        Self {
+            branch: branch.to_owned(),
+            problem,
+            suggestion,
+        }
+    }
+}
+
+impl fmt::Display for ProtectionConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' {}. {}", self.branch, self.problem, self.suggestion)
+    }
+}
+
+/// The settings of `protection` which conflict with bors using `branch` as `role`. `user` is the
+/// user bors pushes as and `is_admin` whether it administers the repository
+pub fn conflicts(
+    config: &RepoConfig,
+    branch: &str,
+    role: BranchRole,
+    protection: &BranchProtection,
+    user: &str,
+    is_admin: bool,
+) -> Vec<ProtectionConflict> {
+    let mut conflicts = Vec::new();
+
+    if let Some(restrictions) = &protection.restrictions {
+        // Membership of the allowed teams and apps can't be checked, so bors is given the benefit
+        // of the doubt when there are any
+        if restrictions.teams.is_empty()
+            && restrictions.apps.is_empty()
+            && !restrictions.users.iter().any(|u| u.login == user)
+        {
+            conflicts.push(ProtectionConflict::new(
+                branch,
+                format!("restricts who can push to it and doesn't allow '{}'", user),
+                format!("Add '{}' to the users allowed to push to it", user),
+            ));
+        }
+    }
+
+    let admins_exempt = is_admin && !ProtectionSetting::is_enabled(&protection.enforce_admins);
+    if protection.required_pull_request_reviews.is_some() && !admins_exempt {
+        conflicts.push(ProtectionConflict::new(
+            branch,
+            "requires changes to be made through reviewed pull requests, rejecting bors' pushes"
+                .to_owned(),
+            format!(
+                "Make '{}' an admin of the repository and don't include administrators in the \
+                protection, or stop requiring reviews",
+                user
+            ),
+        ));
+    }
+
+    if ProtectionSetting::is_enabled(&protection.required_signatures) {
+        conflicts.push(ProtectionConflict::new(
+            branch,
+            "requires signed commits but the commits bors creates aren't signed".to_owned(),
+            "Stop requiring signed commits".to_owned(),
+        ));
+    }
+
+    match role {
+        BranchRole::Base => {
+            let unsatisfiable = protection
+                .required_contexts()
+                // bors can always satisfy the status it sets itself
+                .filter(|context| {
+                    *context != BORS_CONTEXT && !config.required_checks().any(|c| c == *context)
+                })
+                .collect::<Vec<_>>();
+            if !unsatisfiable.is_empty() {
+                conflicts.push(ProtectionConflict::new(
+                    branch,
+                    format!(
+                        "requires checks which bors doesn't wait for before landing: {}",
+                        unsatisfiable.join(", ")
+                    ),
+                    "Add them to `checks` in bors' config or stop requiring them".to_owned(),
+                ));
+            }
+
+            if config.merge_commits()
+                && ProtectionSetting::is_enabled(&protection.required_linear_history)
+            {
+                conflicts.push(ProtectionConflict::new(
+                    branch,
+                    "requires a linear history but bors lands PRs with merge commits".to_owned(),
+                    "Set `merge-commits = false` in bors' config or stop requiring a linear \
+                    history"
+                        .to_owned(),
+                ));
+            }
+        }
+        BranchRole::Test => {
+            if !ProtectionSetting::is_enabled(&protection.allow_force_pushes) {
+                conflicts.push(ProtectionConflict::new(
+                    branch,
+                    "doesn't allow force pushes but bors force-pushes each merge commit it tests"
+                        .to_owned(),
+                    "Remove its protection, or allow force pushes".to_owned(),
+                ));
+            }
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod test {
+    use super::{conflicts, BranchRole};
+    use crate::config::RepoConfig;
+    use github::BranchProtection;
+
+    fn protection(json: &str) -> BranchProtection {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn base_branch_conflicts() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+            merge-commits = true
+            checks = ["ci"]
+            "#,
+        )
+        .unwrap();
+        let protection = protection(
+            r#"{
+                "url": "",
+                "required_status_checks": {
+                    "strict": true,
+                    "contexts": ["ci", "bors", "lint"]
+                },
+                "required_linear_history": { "enabled": true }
+            }"#,
+        );
+
+        let found = conflicts(
+            &config,
+            "main",
+            BranchRole::Base,
+            &protection,
+            "bors",
+            false,
+        );
+        assert_eq!(found.len(), 2);
+        assert!(found[0].problem.ends_with(": lint"));
+        assert!(found[1].problem.contains("linear history"));
+
+        // Force pushes only matter on the branches bors tests on
+        let found = conflicts(
+            &config,
+            "auto",
+            BranchRole::Test,
+            &protection,
+            "bors",
+            false,
+        );
+        assert_eq!(found.len(), 1);
+        assert!(found[0].problem.contains("force pushes"));
+    }
+
+    #[test]
+    fn reviews_block_pushes_unless_admins_are_exempt() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+            "#,
+        )
+        .unwrap();
+        let json = |enforce_admins: bool| {
+            format!(
+                r#"{{
+                    "url": "",
+                    "required_pull_request_reviews": {{ "required_approving_review_count": 1 }},
+                    "enforce_admins": {{ "enabled": {} }}
+                }}"#,
+                enforce_admins
+            )
+        };
+
+        let exempt = protection(&json(false));
+        assert!(conflicts(&config, "main", BranchRole::Base, &exempt, "bors", true).is_empty());
+        assert_eq!(
+            conflicts(&config, "main", BranchRole::Base, &exempt, "bors", false).len(),
+            1
+        );
+
+        let enforced = protection(&json(true));
+        assert_eq!(
+            conflicts(&config, "main", BranchRole::Base, &enforced, "bors", true).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! The open PRs of a repo, indexed for the lookups made on every event
+//!
+//! The merge queue needs the next PR to land and CI results need the PR whose merge commit they
+//! are for, on every webhook. Rather than sorting or scanning every open PR each time, the queued
+//! PRs are kept sorted in a `BTreeSet` and the merge commits being tested are mapped to their PR.
+//! Both are updated as PRs change: PRs are only handed out mutably through a `PullRequestMut`,
+//! which re-indexes its PR once it's dropped.
+
+use super::QueueEntry;
+use crate::{config::Labels, state::PullRequestState};
+use github::Oid;
+use std::{
+    collections::{hash_map, BTreeSet, HashMap},
+    ops,
+    sync::Mutex,
+};
+
+/// A queued PR's place in the queue: hotfixes first, then in the order of their `QueueEntry`
+type QueueKey = (bool, QueueEntry);
+
+#[derive(Debug, Default)]
+struct Indices {
+    /// The queued PRs, in the order they are landed
+    queue: BTreeSet<QueueKey>,
+    /// PR being tested on each merge commit, including canaries
+    merge_oids: HashMap<Oid, u64>,
+    /// How each PR is currently indexed, so that its stale entries can be removed when it changes
+    indexed: HashMap<u64, Indexed>,
+    /// Position of each PR in the explicit order set via `/queue move`
+    order: HashMap<u64, usize>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Indexed {
+    queue: Option<QueueKey>,
+    merge_oid: Option<Oid>,
+}
+
+impl Indices {
+    fn insert(&mut self, pull: &PullRequestState, labels: &Labels) {
+        let queue = pull.status.is_queued().then(|| {
+            let entry = pull
+                .to_queue_entry(labels)
+                .with_position(self.order.get(&pull.number).copied());
+            (!pull.hotfix, entry)
+        });
+        let merge_oid = pull.status.merge_oid().cloned();
+        let indexed = Indexed { queue, merge_oid };
+        // Most changes to PRs don't move them in the queue
+        if self.indexed.get(&pull.number) == Some(&indexed) {
+            return;
+        }
+
+        self.remove(pull.number);
+        if let Some(key) = &indexed.queue {
+            self.queue.insert(key.clone());
+        }
+        if let Some(oid) = &indexed.merge_oid {
+            self.merge_oids.insert(oid.clone(), pull.number);
+        }
+        self.indexed.insert(pull.number, indexed);
+    }
+
+    fn remove(&mut self, number: u64) {
+        if let Some(indexed) = self.indexed.remove(&number) {
+            if let Some(key) = &indexed.queue {
+                self.queue.remove(key);
+            }
+            if let Some(oid) = &indexed.merge_oid {
+                // Another PR may have since been indexed under the same merge commit
+                if self.merge_oids.get(oid) == Some(&number) {
+                    self.merge_oids.remove(oid);
+                }
+            }
+        }
+    }
+}
+
+/// The open PRs of a repo, keyed by number
+#[derive(Debug, Default)]
+pub struct PullRequests {
+    pulls: HashMap<u64, PullRequestState>,
+    /// Labels setting the priority of PRs, which is part of their place in the queue
+    labels: Labels,
+    indices: Mutex<Indices>,
+}
+
+impl Clone for PullRequests {
+    fn clone(&self) -> Self {
+        let mut pulls = Self::new(&self.labels);
+        pulls.set_order(&self.order());
+        pulls.extend(self.pulls.values().cloned());
+        pulls
+    }
+}
+
+impl PullRequests {
+    pub fn new(labels: &Labels) -> Self {
+        Self {
+            labels: labels.clone(),
+            ..Self::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pulls.len()
+    }
+
+    pub fn contains_key(&self, number: &u64) -> bool {
+        self.pulls.contains_key(number)
+    }
+
+    pub fn get(&self, number: &u64) -> Option<&PullRequestState> {
+        self.pulls.get(number)
+    }
+
+    pub fn get_mut(&mut self, number: &u64) -> Option<PullRequestMut<'_>> {
+        let indices = &self.indices;
+        let labels = &self.labels;
+        self.pulls
+            .get_mut(number)
+            .map(|pull| PullRequestMut::new(pull, indices, labels))
+    }
+
+    /// Add a PR, returning the PR with the same number it replaced, if any
+    pub fn insert(&mut self, pull: PullRequestState) -> Option<PullRequestState> {
+        self.indices.get_mut().unwrap().insert(&pull, &self.labels);
+        self.pulls.insert(pull.number, pull)
+    }
+
+    pub fn remove(&mut self, number: &u64) -> Option<PullRequestState> {
+        self.indices.get_mut().unwrap().remove(*number);
+        self.pulls.remove(number)
+    }
+
+    pub fn clear(&mut self) {
+        self.pulls.clear();
+        let indices = self.indices.get_mut().unwrap();
+        indices.queue.clear();
+        indices.merge_oids.clear();
+        indices.indexed.clear();
+    }
+
+    pub fn values(&self) -> hash_map::Values<'_, u64, PullRequestState> {
+        self.pulls.values()
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = PullRequestMut<'_>> {
+        let indices = &self.indices;
+        let labels = &self.labels;
+        self.pulls
+            .values_mut()
+            .map(move |pull| PullRequestMut::new(pull, indices, labels))
+    }
+
+    /// The queued PRs, in every lane, in the order they are landed
+    pub fn queued(&self) -> impl Iterator<Item = &PullRequestState> {
+        self.indices
+            .lock()
+            .unwrap()
+            .queue
+            .iter()
+            .map(|(_, entry)| &self.pulls[&entry.number])
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// The PR whose merge commit, or canary merge commit, is `oid`
+    pub fn number_from_merge_oid(&self, oid: &Oid) -> Option<u64> {
+        self.indices.lock().unwrap().merge_oids.get(oid).copied()
+    }
+
+    /// The entry `pull` is sorted by, respecting the explicit order of queued PRs
+    pub fn queue_entry(&self, pull: &PullRequestState) -> QueueEntry {
+        let position = self
+            .indices
+            .lock()
+            .unwrap()
+            .order
+            .get(&pull.number)
+            .copied();
+        pull.to_queue_entry(&self.labels).with_position(position)
+    }
+
+    /// Set the explicit order of queued PRs, which takes precedence over their priority
+    pub fn set_order(&mut self, order: &[u64]) {
+        let indices = self.indices.get_mut().unwrap();
+        let order = order
+            .iter()
+            .enumerate()
+            .map(|(position, number)| (*number, position))
+            .collect::<HashMap<_, _>>();
+        if order == indices.order {
+            return;
+        }
+        indices.order = order;
+
+        // Only the place of queued PRs depends on the order
+        let queued = indices
+            .queue
+            .iter()
+            .map(|(_, entry)| entry.number)
+            .collect::<Vec<_>>();
+        for number in queued {
+            indices.insert(&self.pulls[&number], &self.labels);
+        }
+    }
+
+    fn order(&self) -> Vec<u64> {
+        let indices = self.indices.lock().unwrap();
+        let mut order = indices.order.iter().collect::<Vec<_>>();
+        order.sort_unstable_by_key(|(_, position)| **position);
+        order.into_iter().map(|(number, _)| *number).collect()
+    }
+}
+
+impl ops::Index<&u64> for PullRequests {
+    type Output = PullRequestState;
+
+    fn index(&self, number: &u64) -> &PullRequestState {
+        &self.pulls[number]
+    }
+}
+
+impl Extend<PullRequestState> for PullRequests {
+    fn extend<T: IntoIterator<Item = PullRequestState>>(&mut self, pulls: T) {
+        for pull in pulls {
+            self.insert(pull);
+        }
+    }
+}
+
+/// A PR borrowed mutably from `PullRequests`, which is re-indexed once the borrow ends
+#[derive(Debug)]
+pub struct PullRequestMut<'a> {
+    pull: &'a mut PullRequestState,
+    indices: &'a Mutex<Indices>,
+    labels: &'a Labels,
+}
+
+impl<'a> PullRequestMut<'a> {
+    fn new(
+        pull: &'a mut PullRequestState,
+        indices: &'a Mutex<Indices>,
+        labels: &'a Labels,
+    ) -> Self {
+        Self {
+            pull,
+            indices,
+            labels,
+        }
+    }
+}
+
+impl ops::Deref for PullRequestMut<'_> {
+    type Target = PullRequestState;
+
+    fn deref(&self) -> &PullRequestState {
+        self.pull
+    }
+}
+
+impl ops::DerefMut for PullRequestMut<'_> {
+    fn deref_mut(&mut self) -> &mut PullRequestState {
+        self.pull
+    }
+}
+
+impl Drop for PullRequestMut<'_> {
+    fn drop(&mut self) {
+        self.indices.lock().unwrap().insert(self.pull, self.labels);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PullRequests;
+    use crate::{
+        config::Labels,
+        state::{PullRequestState, Status},
+    };
+    use github::{
+        test_fixtures::{EventPayload, PullRequestEventBuilder},
+        Oid,
+    };
+
+    fn pull(number: u64, labels: &[&str]) -> PullRequestState {
+        let event = PullRequestEventBuilder::new("opened")
+            .number(number)
+            .labels(labels)
+            .build();
+        let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+        pull.status = Status::queued();
+        pull
+    }
+
+    fn queued(pulls: &PullRequests) -> Vec<u64> {
+        pulls.queued().map(|p| p.number).collect()
+    }
+
+    #[test]
+    fn indices_follow_changes() {
+        let mut pulls = PullRequests::new(&Labels::default());
+        pulls.extend([
+            pull(1, &[]),
+            pull(2, &["bors-high-priority"]),
+            pull(3, &["bors-low-priority"]),
+            pull(4, &[]),
+        ]);
+        assert_eq!(queued(&pulls), [2, 1, 4, 3]);
+
+        pulls.get_mut(&4).unwrap().hotfix = true;
+        pulls.set_order(&[3]);
+        assert_eq!(queued(&pulls), [4, 3, 2, 1]);
+
+        let oid = Oid::from_str("abc");
+        pulls.get_mut(&4).unwrap().status = Status::testing(oid.clone());
+        assert_eq!(queued(&pulls), [3, 2, 1]);
+        assert_eq!(pulls.number_from_merge_oid(&oid), Some(4));
+
+        for mut pull in pulls.values_mut() {
+            pull.status = Status::InReview;
+        }
+        assert!(queued(&pulls).is_empty());
+        assert_eq!(pulls.number_from_merge_oid(&oid), None);
+
+        pulls.get_mut(&1).unwrap().status = Status::queued();
+        let cloned = pulls.clone();
+        pulls.remove(&1);
+        assert!(queued(&pulls).is_empty());
+        assert_eq!(queued(&cloned), [1]);
+    }
+}
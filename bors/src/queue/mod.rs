@@ -0,0 +1,2780 @@
+mod index;
+mod state_machine;
+
+pub use index::{PullRequestMut, PullRequests};
+use state_machine::{QueueEvent, QueueState};
+
+use crate::{
+    config::{CanaryHeadChecks, Lane, RepoConfig},
+    git::{GitBackend, MergeOutcome, Squash},
+    graphql::GithubClient,
+    insights::MergeSkewRecord,
+    project_board::ProjectBoard,
+    queue_hooks::{QueueHookEvent, QueueHookPayload, QueueHooks},
+    state::{
+        allowed_failures, AllowedFailure, Priority, PullRequestState, SquashMode, Status,
+        StatusType, TestResult, TestSuiteResult,
+    },
+    templates, Result,
+};
+use github::{DateTime, Oid};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Branch that canary merges are pushed to for testing
+pub const CANARY_BRANCH: &str = "canary";
+
+/// Number of recent test runs remembered by the `MergeQueue`
+const MAX_TEST_RUN_HISTORY: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+pub struct QueueEntry {
+    status: StatusType,
+
+    /// Position of the PR in the order set via `/queue move`, PRs without a position come after
+    /// every PR with one
+    position: usize,
+
+    /// Indicates the priority of the PR
+    priority: Priority,
+
+    timestamp: Option<Instant>,
+
+    number: u64,
+}
+
+impl QueueEntry {
+    pub fn new(
+        number: u64,
+        status: StatusType,
+        priority: Priority,
+        timestamp: Option<Instant>,
+    ) -> Self {
+        Self {
+            number,
+            status,
+            position: usize::MAX,
+            timestamp,
+            priority,
+        }
+    }
+
+    pub fn with_position(self, position: Option<usize>) -> Self {
+        Self {
+            position: position.unwrap_or(usize::MAX),
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MergeQueue {
+    /// State of the head of the queue, the PR that is currently being landed
+    #[serde(rename = "head")]
+    state: QueueState,
+
+    /// PR which was returned to the queue while being tested, e.g. when preempted by a hotfix or
+    /// when its batch failed, and is landed next
+    #[serde(default)]
+    parked: Option<u64>,
+
+    /// PRs tested along with the head of the queue, rebased on top of it in this order. They stay
+    /// queued while they're tested and land along with the head
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    batch: Vec<BatchedPull>,
+
+    /// PRs of the last batch which failed, one of which broke it. They're tested in ever smaller
+    /// batches, in this order, until it's found
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    suspects: Vec<u64>,
+
+    /// Outcomes of the most recent test runs at the head of the queue, oldest first
+    #[serde(skip)]
+    history: VecDeque<TestRun>,
+
+    /// The lane this queue lands PRs of, or `None` for PRs whose files don't all belong to one
+    /// lane
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lane: Option<String>,
+
+    /// Queues of the configured lanes, keyed by name. Lanes land their PRs concurrently with each
+    /// other, but never alongside a PR from this queue since it may touch any part of the repo
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    lanes: BTreeMap<String, MergeQueue>,
+
+    /// Notified of the PRs this queue lands
+    #[serde(skip)]
+    hooks: QueueHooks,
+
+    /// How far the PRs landed since the last call to `take_merge_skews` were behind their base
+    #[serde(skip)]
+    merge_skews: Vec<MergeSkewRecord>,
+}
+
+/// A PR tested along with the head of the queue
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct BatchedPull {
+    number: u64,
+    /// The PR's head when it was added to the batch. The batch is broken up if it's pushed to
+    head_oid: Oid,
+}
+
+/// The outcome of testing a PR at the head of the queue
+#[derive(Clone, Debug)]
+pub struct TestRun {
+    pub number: u64,
+    pub passed: bool,
+    pub duration: Duration,
+}
+
+impl MergeQueue {
+    pub fn new() -> Self {
+        Self {
+            state: QueueState::Idle,
+            parked: None,
+            batch: Vec::new(),
+            suspects: Vec::new(),
+            history: VecDeque::new(),
+            lane: None,
+            lanes: BTreeMap::new(),
+            hooks: QueueHooks::default(),
+            merge_skews: Vec::new(),
+        }
+    }
+
+    fn for_lane(lane: &str) -> Self {
+        Self {
+            lane: Some(lane.to_owned()),
+            ..Self::new()
+        }
+    }
+
+    /// Set the hooks notified of the PRs landed by this queue and every lane
+    pub fn set_hooks(&mut self, hooks: &QueueHooks) {
+        self.hooks = hooks.clone();
+        for lane in self.lanes.values_mut() {
+            lane.set_hooks(hooks);
+        }
+    }
+
+    /// The PR that is currently being landed outside of any lane, if any
+    pub fn head(&self) -> Option<u64> {
+        self.state.pull()
+    }
+
+    /// Indicates if no PR is being landed, in any lane
+    pub fn is_idle(&self) -> bool {
+        self.head().is_none() && self.lanes.values().all(MergeQueue::is_idle)
+    }
+
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &TestRun> {
+        self.history.iter()
+    }
+
+    pub(crate) fn record_test_run(&mut self, number: u64, passed: bool, duration: Duration) {
+        if self.history.len() == MAX_TEST_RUN_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(TestRun {
+            number,
+            passed,
+            duration,
+        });
+    }
+
+    /// Resume the lands this queue and its lanes were performing when `saved` was saved, before
+    /// bors restarted. A land whose PR is no longer being tested, e.g. since it was pushed to in
+    /// the meantime, is abandoned and the PR's queue moves on
+    pub fn restore(&mut self, mut saved: MergeQueue, pulls: &PullRequests) {
+        saved.reset_unless_testing(pulls);
+        saved.set_hooks(&self.hooks);
+        *self = saved;
+    }
+
+    fn reset_unless_testing(&mut self, pulls: &PullRequests) {
+        let testing = match self.state {
+            QueueState::Idle => true,
+            QueueState::Testing(head) => pulls.get(&head).is_some_and(|p| p.status.is_testing()),
+            _ => false,
+        };
+        if !testing {
+            self.transition(QueueEvent::Reset)
+                .expect("the queue can always be reset");
+        }
+        for lane in self.lanes.values_mut() {
+            lane.reset_unless_testing(pulls);
+        }
+    }
+
+    /// Take the merge skews of the PRs landed by this queue and every lane since the last call
+    pub fn take_merge_skews(&mut self) -> Vec<MergeSkewRecord> {
+        let mut skews = std::mem::take(&mut self.merge_skews);
+        for lane in self.lanes.values_mut() {
+            skews.extend(lane.take_merge_skews());
+        }
+        skews
+    }
+
+    pub fn reset(&mut self) {
+        self.state = self
+            .state
+            .transition(QueueEvent::Reset)
+            .expect("the queue can always be reset");
+        for lane in self.lanes.values_mut() {
+            lane.reset();
+        }
+    }
+
+    /// Indicates if `pull` is landed by this queue
+    fn is_in_lane(&self, pull: &PullRequestState) -> bool {
+        pull.lane == self.lane
+    }
+
+    /// Determine the files changed by each queued PR, and from them its lane
+    async fn refresh_changed_files(
+        config: &RepoConfig,
+        github: &GithubClient,
+        pulls: &mut PullRequests,
+    ) -> Result<()> {
+        for mut pull in pulls.values_mut() {
+            if !pull.status.is_queued() || pull.lane_head.as_ref() == Some(&pull.head_ref_oid) {
+                continue;
+            }
+
+            let files = pull.fetch_changed_files(config, github).await?;
+            pull.lane = config
+                .lane_for_paths(files.iter().map(String::as_str))
+                .map(|lane| lane.name().to_owned());
+            pull.lane_head = Some(pull.head_ref_oid.clone());
+            pull.changed_files = files;
+            if pull.lane.is_some() {
+                info!("pr #{} is in lane {:?}", pull.number, pull.lane);
+            }
+        }
+
+        // Lanes may have been removed from the config since PRs were assigned to them
+        for mut pull in pulls.values_mut() {
+            if pull.status.is_queued()
+                && pull
+                    .lane
+                    .as_deref()
+                    .is_some_and(|lane| config.lane(lane).is_none())
+            {
+                pull.lane = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn transition(&mut self, event: QueueEvent) -> Result<()> {
+        let next = self.state.transition(event)?;
+        if next != self.state {
+            info!("Merge queue: {:?} -> {:?} ({:?})", self.state, next, event);
+        }
+        if next == QueueState::Idle {
+            self.batch.clear();
+        }
+        self.state = next;
+        Ok(())
+    }
+
+    /// Measure how far `pull` is behind its base as it's about to land. The skew is only reported,
+    /// so failing to measure it doesn't hold up the land
+    fn measure_merge_skew(
+        repo: &mut dyn GitBackend,
+        pull: &PullRequestState,
+    ) -> Option<MergeSkewRecord> {
+        let skew = match repo.fetch_merge_skew(&pull.base_ref_name, &pull.head_ref_oid) {
+            Ok(skew) => skew,
+            Err(e) => {
+                warn!(
+                    "unable to measure how far pr #{} is behind '{}': {:#}",
+                    pull.number, pull.base_ref_name, e
+                );
+                return None;
+            }
+        };
+
+        let landed_at = DateTime::now();
+        let behind_seconds = skew
+            .behind_since
+            .and_then(|since| landed_at.duration_since(&since))
+            .map_or(0, |behind| behind.as_secs());
+        info!(
+            "pr #{} is {} commits behind '{}', for {}s",
+            pull.number, skew.commits_behind, pull.base_ref_name, behind_seconds
+        );
+
+        Some(MergeSkewRecord {
+            pr: pull.number,
+            landed_at,
+            commits_behind: skew.commits_behind,
+            behind_seconds,
+        })
+    }
+
+    /// Land `head` by pushing its tested merge commit to its base, along with the PRs in `batch`
+    /// which were rebased underneath that commit
+    #[allow(clippy::too_many_arguments)]
+    async fn land_pr(
+        head: u64,
+        batch: &[u64],
+        lane: Option<&Lane>,
+        skews: &[MergeSkewRecord],
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<QueueEvent> {
+        let mut pull = pulls.get_mut(&head).expect("PR should exist");
+        let (merge_oid, allowed_failures) = match &pull.status {
+            Status::Testing {
+                merge_oid,
+                test_results,
+                ..
+            } => (merge_oid.clone(), allowed_failures(test_results, config)),
+            // XXX Fix this
+            _ => unreachable!(),
+        };
+
+        // Other lanes may have landed since the merge commit was created, so it's rebuilt on top
+        // of their changes and tested again rather than landing something that was never tested
+        if let Some(lane) = lane {
+            if !repo.fetch_and_check_up_to_date(&pull.base_ref_name, &merge_oid)? {
+                return match Self::rebuild_merge(lane, config, repo, &pull, &merge_oid)? {
+                    Some(merge_oid) => {
+                        Self::create_pending_status(config, github, &pull).await?;
+                        pull.last_merge_oid = Some(merge_oid.clone());
+                        pull.update_status(
+                            Status::testing(merge_oid),
+                            config,
+                            github,
+                            project_board,
+                        )
+                        .await?;
+                        Ok(QueueEvent::MergeRebuilt)
+                    }
+                    None => {
+                        let error = format!(
+                            "unable to rebuild the merge commit on top of changes landed \
+                            since it was tested in lane '{}'",
+                            lane.name()
+                        );
+                        Self::land_failed(&mut pull, &error, config, github, project_board).await
+                    }
+                };
+            }
+        }
+        let merge_oid = &merge_oid;
+
+        // Attempt to update the PR in-place
+        if let Some(head_repo) = pull.head_repo.as_ref() {
+            // Before 'merging' the PR into the base ref we first update the PR with the rebased
+            // commits that are to be imminently merged using the `maintainer_can_modify` feature.
+            // This is done so that when the commits are finally pushed to the base ref that Github
+            // will properly mark the PR as being 'merged'.
+            // A fast-forwarded PR's head already is the commit being landed
+            if config.maintainer_mode() && merge_oid != &pull.head_ref_oid {
+                if repo
+                    .push_to_remote(
+                        head_repo,
+                        &pull.head_ref_name,
+                        &pull.head_ref_oid,
+                        merge_oid,
+                    )
+                    .is_err()
+                {
+                    info!(
+                        "unable to update pr #{} in-place. maintainer_can_modify: {}",
+                        pull.number, pull.maintainer_can_modify
+                    );
+
+                    let comment =
+                        templates::render(config, "update-in-place-failed", liquid::object!({}))?;
+
+                    pull.post_comment(config, github, &comment).await?;
+                } else {
+                    // TODO we probably shouldn't spin waiting here. It might be better to wait till we
+                    // get a webhook back from Github that the PR was updated
+                    let r = format!("refs/pull/{}/head", pull.number);
+                    for i in 0..15 {
+                        info!(
+                            "Waiting for Github to update its ref '{}': attempt {}",
+                            r, i
+                        );
+
+                        // Delay a few seconds to try and let Github properly update its references
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+                        let github = github
+                            .pulls()
+                            .get(config.owner(), config.name(), pull.number)
+                            .await
+                            .map(|p| p.map(|p| p.head.sha).into_inner());
+                        let git = repo.fetch_ref(&r);
+
+                        match (git, github) {
+                            (Ok(git), Ok(github)) => {
+                                if merge_oid == &git && merge_oid == &github {
+                                    info!("Github's ref '{}' has been updated", r);
+                                    break;
+                                }
+                            }
+                            (git, github) => {
+                                info!("Github's ref's haven't updated yet.\nExpected: '{}'\nActual: git '{:?}' github '{:?}'", merge_oid, git, github);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Finally 'merge' the PR by updating the 'base_ref' with `merge_oid`
+        if let Err(e) = github
+            .git()
+            .update_ref(
+                config.owner(),
+                config.name(),
+                &format!("heads/{}", pull.base_ref_name),
+                merge_oid,
+                false,
+            )
+            .await
+        {
+            let error = format!("{:#?}", e);
+            return Self::land_failed(&mut pull, &error, config, github, project_board).await;
+        }
+        drop(pull);
+
+        for &number in std::iter::once(&head).chain(batch) {
+            let skew = skews.iter().find(|skew| skew.pr == number);
+            Self::complete_land(
+                number,
+                skew,
+                &allowed_failures,
+                config,
+                github,
+                project_board,
+                pulls,
+            )
+            .await?;
+        }
+
+        Ok(QueueEvent::Landed)
+    }
+
+    /// Notify those following a PR which landed that it did, and stop tracking it
+    async fn complete_land(
+        number: u64,
+        skew: Option<&MergeSkewRecord>,
+        allowed_failures: &[AllowedFailure],
+        config: &RepoConfig,
+        github: &GithubClient,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<()> {
+        let mut pull = match pulls.get_mut(&number) {
+            Some(pull) => pull,
+            None => return Ok(()),
+        };
+
+        let issues = if config.require_linked_issue() {
+            pull.linked_issues()
+        } else {
+            Vec::new()
+        };
+        if !pull.notify.is_empty() || !allowed_failures.is_empty() || !issues.is_empty() {
+            let comment = templates::render(
+                config,
+                "landed",
+                liquid::object!({
+                    "base": pull.base_ref_name,
+                    "commits_behind": skew.map_or(0, |skew| skew.commits_behind),
+                    "behind_hours": skew.map_or(0, |skew| skew.behind_seconds / 3600),
+                    "allowed_failures": allowed_failures,
+                    "issues": issues,
+                    "notify": pull.notify,
+                }),
+            )?;
+            pull.post_comment(config, github, &comment).await?;
+        }
+
+        pull.update_check_run(config, github, true).await;
+
+        if let Some(board) = project_board {
+            board.delete_card(github, &mut pull).await?;
+        }
+
+        // Actually remove the PR
+        drop(pull);
+        pulls.remove(&number);
+
+        Ok(())
+    }
+
+    async fn land_failed(
+        pull: &mut PullRequestState,
+        error: &str,
+        config: &RepoConfig,
+        github: &GithubClient,
+        project_board: Option<&ProjectBoard>,
+    ) -> Result<QueueEvent> {
+        pull.update_status(Status::failed(error, None), config, github, project_board)
+            .await?;
+
+        let comment = templates::render(
+            config,
+            "merge-failed",
+            liquid::object!({
+                "base": pull.base_ref_name,
+                "error": error,
+                "notify": pull.notify,
+            }),
+        )?;
+        pull.notify.clear();
+
+        pull.post_comment(config, github, &comment).await?;
+
+        Ok(QueueEvent::LandFailed)
+    }
+
+    /// Recreate the tested merge commit of a PR landed through `lane` on the current tip of its
+    /// base and push it to the lane's test branch, returning `None` if it no longer applies
+    /// cleanly
+    fn rebuild_merge(
+        lane: &Lane,
+        config: &RepoConfig,
+        repo: &mut dyn GitBackend,
+        pull: &PullRequestState,
+        merge_oid: &Oid,
+    ) -> Result<Option<Oid>> {
+        info!(
+            "'{}' moved since pr #{} was tested, rebuilding its merge commit",
+            pull.base_ref_name, pull.number
+        );
+
+        let branch = lane.test_branch();
+        let outcome = if pull.lands_with_merge_commit(config) {
+            repo.fetch_and_merge(
+                &pull.base_ref_name,
+                &pull.head_ref_oid,
+                &branch,
+                pull.number,
+                &pull.title,
+            )?
+        } else {
+            // The tested commits are already rebased and squashed, so they're replayed as is
+            repo.fetch_and_rebase(
+                &pull.base_ref_name,
+                merge_oid,
+                &branch,
+                pull.number,
+                &Squash::None,
+            )?
+        };
+
+        match outcome {
+            MergeOutcome::Merged(merge_oid) => {
+                repo.push_branch(&branch)?;
+                Ok(Some(merge_oid))
+            }
+            MergeOutcome::Conflict { .. } | MergeOutcome::Empty => Ok(None),
+        }
+    }
+
+    pub async fn process_queue(
+        &mut self,
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<()> {
+        Self::refresh_changed_files(config, github, pulls).await?;
+        self.lanes
+            .retain(|name, queue| config.lane(name).is_some() || !queue.is_idle());
+
+        // PRs outside of any lane wait for the lanes to finish landing what they've started, and
+        // are landed before the lanes start anything new
+        let may_start = self.lanes.values().all(MergeQueue::is_idle);
+        self.advance_or_abort(may_start, config, github, repo, project_board, pulls)
+            .await?;
+
+        for lane in config.lanes() {
+            let may_start = self.head().is_none() && !pulls.queued().any(|p| self.is_in_lane(p));
+            let hooks = self.hooks.clone();
+            let queue = self
+                .lanes
+                .entry(lane.name().to_owned())
+                .or_insert_with(|| MergeQueue::for_lane(lane.name()));
+            queue.hooks = hooks;
+            queue
+                .advance_or_abort(may_start, config, github, repo, project_board, pulls)
+                .await?;
+        }
+
+        self.process_canaries(config, github, repo, project_board, pulls)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn advance_or_abort(
+        &mut self,
+        may_start: bool,
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<()> {
+        // Ensure that only ever 1 PR is in "Testing" at a time
+        assert!(
+            pulls
+                .values()
+                .filter(|p| p.status.is_testing() && self.is_in_lane(p))
+                .count()
+                <= 1
+        );
+
+        if let Err(e) = self
+            .advance(may_start, config, github, repo, project_board, pulls)
+            .await
+        {
+            self.abort(pulls)?;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Drive the head of the queue through its states until it's waiting on something external,
+    /// either for the PR being tested to finish its tests or for a PR to be queued. A new PR is
+    /// only started if `may_start` is set
+    async fn advance(
+        &mut self,
+        may_start: bool,
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<()> {
+        let lane = self.lane.as_deref().and_then(|name| config.lane(name));
+
+        loop {
+            let event = match self.state {
+                QueueState::Idle if !may_start => return Ok(()),
+                QueueState::Idle => match self.next_queued(pulls) {
+                    Some(number) => {
+                        if self.parked == Some(number) {
+                            self.parked = None;
+                        }
+                        QueueEvent::Started(number)
+                    }
+                    None => return Ok(()),
+                },
+                QueueState::Preparing(head) => {
+                    let batch = self.batch_for(head, config, pulls);
+                    if batch.is_empty() {
+                        Self::prepare(head, lane, config, github, repo, project_board, pulls)
+                            .await?
+                    } else {
+                        self.prepare_batch(head, batch, config, github, repo, project_board, pulls)
+                            .await?
+                    }
+                }
+                QueueState::Testing(head) => {
+                    match self
+                        .process_head(head, config, github, project_board, pulls)
+                        .await?
+                    {
+                        Some(event) => event,
+                        // Only a PR whose tests are still running makes way for a hotfix, one
+                        // which already passed lands first
+                        None if self.should_park(head, pulls) => {
+                            self.park(head, config, github, project_board, pulls)
+                                .await?
+                        }
+                        None => return Ok(()),
+                    }
+                }
+                QueueState::Landing(head) => {
+                    let batch = self.batch.iter().map(|b| b.number).collect::<Vec<_>>();
+
+                    // Landing removes the PRs, so their pending events are handed over first and
+                    // the events for landing them are prepared while they still exist
+                    let mut landed = Vec::new();
+                    let mut skews = Vec::new();
+                    for number in std::iter::once(&head).chain(&batch) {
+                        if let Some(mut pull) = pulls.get_mut(number) {
+                            for payload in pull.queue_events.drain(..) {
+                                self.hooks.emit(payload);
+                            }
+                            landed.push(QueueHookPayload::new(
+                                QueueHookEvent::Landed,
+                                config,
+                                &pull,
+                            ));
+                        }
+                        skews.extend(
+                            pulls
+                                .get(number)
+                                .and_then(|pull| Self::measure_merge_skew(repo, pull)),
+                        );
+                    }
+                    let event = Self::land_pr(
+                        head,
+                        &batch,
+                        lane,
+                        &skews,
+                        config,
+                        github,
+                        repo,
+                        project_board,
+                        pulls,
+                    )
+                    .await?;
+                    if let QueueEvent::Landed = event {
+                        for payload in landed {
+                            self.hooks.emit(payload);
+                        }
+                        self.merge_skews.extend(skews);
+                    }
+                    event
+                }
+                QueueState::Done(_) | QueueState::Failed(_) => QueueEvent::Finished,
+            };
+
+            self.transition(event)?;
+        }
+    }
+
+    /// Return to a state from which the queue can be processed again after an error interrupted
+    /// the current operation
+    fn abort(&mut self, pulls: &PullRequests) -> Result<()> {
+        let event = match self.state {
+            // The PR's tests were started before the error, so carry on as if preparing succeeded
+            QueueState::Preparing(head)
+                if pulls.get(&head).map(|p| p.status.is_testing()) == Some(true) =>
+            {
+                QueueEvent::MergeCreated
+            }
+            _ => QueueEvent::Aborted,
+        };
+
+        self.transition(event)
+    }
+
+    async fn process_canaries(
+        &self,
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<()> {
+        for mut pull in pulls.values_mut().filter(|p| p.status.is_canary()) {
+            let pull = &mut *pull;
+            let (merge_oid, test_suite_result, allowed_failures) = match &pull.status {
+                Status::Canary {
+                    merge_oid,
+                    tests_started_at,
+                    test_results,
+                } => {
+                    let test_suite_result =
+                        TestSuiteResult::canary(*tests_started_at, test_results, config);
+                    (
+                        merge_oid,
+                        test_suite_result,
+                        allowed_failures(test_results, config),
+                    )
+                }
+                _ => continue,
+            };
+
+            if let TestSuiteResult::TimedOut = test_suite_result {
+                Self::cancel_timed_out_canary(pull, config, github).await?;
+            } else {
+                Self::update_github_based_on_test_suite_results(
+                    pull,
+                    &test_suite_result,
+                    merge_oid,
+                    config,
+                    github,
+                )
+                .await?;
+            }
+
+            match test_suite_result {
+                TestSuiteResult::Failed { .. } | TestSuiteResult::TimedOut => {
+                    pull.update_status(Status::InReview, config, github, project_board)
+                        .await?;
+                }
+
+                TestSuiteResult::Passed => {
+                    pull.update_status(Status::InReview, config, github, project_board)
+                        .await?;
+                    let comment = templates::render(
+                        config,
+                        "canary-successful",
+                        liquid::object!({ "allowed_failures": allowed_failures }),
+                    )?;
+                    pull.post_comment(config, github, &comment).await?;
+                }
+
+                TestSuiteResult::Pending => continue,
+            }
+
+            // The canary has finished so clear the label that may have triggered it
+            pull.canary_requester = None;
+            pull.remove_label(config, github, config.labels().canary())
+                .await?;
+        }
+
+        for mut pull in pulls.values_mut().filter(|p| p.canary_requested) {
+            let pull = &mut *pull;
+            if config.canary_head_checks() != CanaryHeadChecks::Ignore {
+                match TestSuiteResult::head(&pull.head_test_results, config) {
+                    TestSuiteResult::Failed { name, result } => {
+                        info!(
+                            "Canary of PR #{} canceled, check '{}' failed on its head",
+                            pull.number, name
+                        );
+                        pull.clear_canary_request();
+                        let comment = templates::render(
+                            config,
+                            "canary-head-failed",
+                            liquid::object!({
+                                "requester": pull.canary_requester.take(),
+                                "name": name,
+                                "url": result.details_url,
+                            }),
+                        )?;
+                        pull.post_comment(config, github, &comment).await?;
+                        pull.remove_label(config, github, config.labels().canary())
+                            .await?;
+                        continue;
+                    }
+                    TestSuiteResult::Pending
+                        if config.canary_head_checks() == CanaryHeadChecks::Passed =>
+                    {
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            pull.clear_canary_request();
+
+            if let Some(merge_oid) =
+                Self::create_merge_and_update_github(config, github, repo, pull, &[CANARY_BRANCH])
+                    .await?
+            {
+                pull.update_status(Status::canary(merge_oid), config, github, project_board)
+                    .await?;
+            } else {
+                pull.remove_label(config, github, config.labels().canary())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Report a canary which didn't complete within the canary timeout, e.g. because CI is wedged,
+    /// to the user that requested it
+    async fn cancel_timed_out_canary(
+        pull: &PullRequestState,
+        config: &RepoConfig,
+        github: &GithubClient,
+    ) -> Result<()> {
+        info!("Canary of PR #{} timed-out", pull.number);
+
+        github
+            .repos()
+            .create_status(
+                config.owner(),
+                config.name(),
+                &pull.head_ref_oid.to_string(),
+                &github::client::CreateStatusRequest {
+                    state: github::StatusEventState::Error,
+                    target_url: None,
+                    description: Some("Canary timed-out"),
+                    context: "bors",
+                },
+            )
+            .await?;
+
+        let comment = templates::render(
+            config,
+            "canary-timed-out",
+            liquid::object!({
+                "requester": pull.canary_requester,
+                "minutes": config.canary_timeout().as_secs() / 60,
+            }),
+        )?;
+        pull.post_comment(config, github, &comment).await?;
+
+        Ok(())
+    }
+
+    /// Check the test results of the PR being tested, returning how the queue should transition
+    /// once they're complete
+    async fn process_head(
+        &mut self,
+        head: u64,
+        config: &RepoConfig,
+        github: &GithubClient,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<Option<QueueEvent>> {
+        // A PR tested along with the head was pushed to, dequeued or closed, so what's being tested
+        // is no longer what would land
+        let left = self.batch.iter().find(|batched| {
+            !pulls
+                .get(&batched.number)
+                .is_some_and(|p| p.status.is_queued() && p.head_ref_oid == batched.head_oid)
+        });
+        if let Some(left) = left {
+            info!(
+                "pr #{} left the batch of pr #{}, re-testing",
+                left.number, head
+            );
+            if let Some(mut pull) = pulls.get_mut(&head).filter(|p| p.status.is_testing()) {
+                pull.update_status(Status::queued(), config, github, project_board)
+                    .await?;
+                self.parked = Some(head);
+            }
+            return Ok(Some(QueueEvent::Removed));
+        }
+
+        // The PR that was being tested was closed for some reason
+        let mut pull = match pulls.get_mut(&head) {
+            Some(pull) => pull,
+            None => return Ok(Some(QueueEvent::Removed)),
+        };
+
+        // The PR that was being tested had its state changed from `Status::Testing`, e.g. if the
+        // land was canceled.
+        let (merge_oid, tests_started_at, test_suite_result) = match &pull.status {
+            Status::Testing {
+                merge_oid,
+                tests_started_at,
+                test_results,
+            } => {
+                let test_suite_result = match self.lane.as_deref().and_then(|l| config.lane(l)) {
+                    Some(lane) => {
+                        TestSuiteResult::lane(*tests_started_at, test_results, lane, config)
+                    }
+                    None => TestSuiteResult::new(*tests_started_at, test_results, config),
+                };
+                (merge_oid, *tests_started_at, test_suite_result)
+            }
+            _ => return Ok(Some(QueueEvent::Removed)),
+        };
+
+        // The failure of a batch isn't attributed to any of its PRs until it's been bisected
+        let batch_failed = !self.batch.is_empty()
+            && matches!(
+                test_suite_result,
+                TestSuiteResult::Failed { .. } | TestSuiteResult::TimedOut
+            );
+        if !batch_failed {
+            Self::update_github_based_on_test_suite_results(
+                &pull,
+                &test_suite_result,
+                merge_oid,
+                config,
+                github,
+            )
+            .await?;
+        }
+
+        let event = match test_suite_result {
+            TestSuiteResult::Failed { name, result } if batch_failed => {
+                self.record_test_run(head, false, tests_started_at.elapsed());
+                pull.update_status(Status::queued(), config, github, project_board)
+                    .await?;
+                self.bisect_batch(head, Some((name, result.details_url)), config, github)
+                    .await?;
+                QueueEvent::BatchFailed
+            }
+            TestSuiteResult::TimedOut if batch_failed => {
+                self.record_test_run(head, false, tests_started_at.elapsed());
+                pull.update_status(Status::queued(), config, github, project_board)
+                    .await?;
+                self.bisect_batch(head, None, config, github).await?;
+                QueueEvent::BatchFailed
+            }
+
+            TestSuiteResult::Failed { .. } | TestSuiteResult::TimedOut => {
+                self.record_test_run(head, false, tests_started_at.elapsed());
+
+                // A suspect which fails on its own is what broke the batch being bisected
+                if self.suspects.first() == Some(&head) {
+                    let comment = templates::render(config, "batch-culprit", liquid::object!({}))?;
+                    pull.post_comment(config, github, &comment).await?;
+                }
+
+                // Remove the PR from the Queue
+                let status = match test_suite_result {
+                    TestSuiteResult::Failed { name, .. } => {
+                        Status::failed(format!("check '{}' failed", name), Some(name))
+                    }
+                    _ => Status::failed("tests timed out", None),
+                };
+                pull.update_status(status, config, github, project_board)
+                    .await?;
+                pull.notify.clear();
+                QueueEvent::TestsFailed
+            }
+
+            TestSuiteResult::Passed => {
+                self.record_test_run(head, true, tests_started_at.elapsed());
+                QueueEvent::TestsPassed
+            }
+
+            TestSuiteResult::Pending => return Ok(None),
+        };
+
+        Ok(Some(event))
+    }
+
+    /// Return the PRs of the batch which failed to the queue, to be tested in ever smaller batches
+    /// until the one which broke it is found. `failed` is the check which failed along with its
+    /// details url, if the tests didn't time out. The PRs are only told about the failure of the
+    /// first batch, not of every smaller batch which fails while bisecting it. Suspects which
+    /// weren't part of a failed smaller batch are cleared and silently rejoin normal batching
+    async fn bisect_batch(
+        &mut self,
+        head: u64,
+        failed: Option<(String, String)>,
+        config: &RepoConfig,
+        github: &GithubClient,
+    ) -> Result<()> {
+        let bisecting = self.suspects.first() == Some(&head);
+        self.suspects = std::iter::once(head)
+            .chain(self.batch.iter().map(|b| b.number))
+            .collect();
+        self.parked = Some(head);
+        info!("Batch {:?} failed, bisecting", self.suspects);
+        if bisecting {
+            return Ok(());
+        }
+
+        let (name, url) = failed.unzip();
+        let comment = templates::render(
+            config,
+            "batch-failed",
+            liquid::object!({ "batch": self.suspects, "name": name, "url": url }),
+        )?;
+        for &number in &self.suspects {
+            github
+                .issues()
+                .create_comment(config.owner(), config.name(), number, &comment)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn update_github_based_on_test_suite_results(
+        pull: &PullRequestState,
+        test_suite_result: &TestSuiteResult,
+        merge_oid: &Oid,
+        config: &RepoConfig,
+        github: &GithubClient,
+    ) -> Result<()> {
+        match test_suite_result {
+            TestSuiteResult::Failed { name, result } => {
+                // Create github status/check
+                github
+                    .repos()
+                    .create_status(
+                        config.owner(),
+                        config.name(),
+                        &pull.head_ref_oid.to_string(),
+                        &github::client::CreateStatusRequest {
+                            state: github::StatusEventState::Failure,
+                            target_url: Some(&result.details_url),
+                            description: None,
+                            context: "bors",
+                        },
+                    )
+                    .await?;
+
+                // Report the Error
+                let comment = templates::render(
+                    config,
+                    "test-failed",
+                    liquid::object!({
+                        "name": name,
+                        "url": result.details_url,
+                        "source": result.source.to_string(),
+                        "notify": pull.notify,
+                    }),
+                )?;
+                pull.post_comment(config, github, &comment).await?;
+            }
+            TestSuiteResult::Passed => {
+                // Create github status/check on the merge commit
+                github
+                    .repos()
+                    .create_status(
+                        config.owner(),
+                        config.name(),
+                        &merge_oid.to_string(),
+                        &github::client::CreateStatusRequest {
+                            state: github::StatusEventState::Success,
+                            target_url: None,
+                            description: None,
+                            context: "bors",
+                        },
+                    )
+                    .await?;
+            }
+
+            TestSuiteResult::TimedOut => {
+                info!("PR #{} timed-out", pull.number);
+
+                github
+                    .repos()
+                    .create_status(
+                        config.owner(),
+                        config.name(),
+                        &pull.head_ref_oid.to_string(),
+                        &github::client::CreateStatusRequest {
+                            state: github::StatusEventState::Failure,
+                            target_url: None,
+                            description: Some("Timed-out"),
+                            context: "bors",
+                        },
+                    )
+                    .await?;
+
+                // Report the Error
+                let comment = templates::render(
+                    config,
+                    "tests-timed-out",
+                    liquid::object!({ "notify": pull.notify }),
+                )?;
+                pull.post_comment(config, github, &comment).await?;
+            }
+            TestSuiteResult::Pending => {}
+        }
+
+        Ok(())
+    }
+
+    /// The queued PR which should be landed next: hotfixes first, then the PR they preempted,
+    /// then the rest of the queue in order
+    fn next_queued(&self, pulls: &PullRequests) -> Option<u64> {
+        let next = pulls.queued().find(|p| self.is_in_lane(p))?;
+        let parked = self
+            .parked
+            .filter(|n| pulls.get(n).is_some_and(|p| p.status.is_queued()))
+            .filter(|n| self.is_in_lane(&pulls[n]));
+        match parked {
+            Some(parked) if !next.hotfix => Some(parked),
+            _ => Some(next.number),
+        }
+    }
+
+    /// The queued hotfixes, which come first in the queue
+    fn queued_hotfixes<'a>(
+        &'a self,
+        pulls: &'a PullRequests,
+    ) -> impl Iterator<Item = &'a PullRequestState> {
+        pulls
+            .queued()
+            .take_while(|p| p.hotfix)
+            .filter(move |p| self.is_in_lane(p))
+    }
+
+    /// Indicates if the PR being tested should make way for a queued hotfix
+    fn should_park(&self, head: u64, pulls: &PullRequests) -> bool {
+        let is_hotfix = |number| pulls.get(&number).map(|p| p.hotfix) == Some(true);
+        !is_hotfix(head) && self.queued_hotfixes(pulls).next().is_some()
+    }
+
+    /// Return the PR being tested to the queue, to be landed once the hotfix preempting it has
+    /// landed. A new merge commit is created when it resumes since the hotfix moves its base
+    async fn park(
+        &mut self,
+        head: u64,
+        config: &RepoConfig,
+        github: &GithubClient,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<QueueEvent> {
+        let hotfix = self
+            .queued_hotfixes(pulls)
+            .map(|p| p.number)
+            .min()
+            .expect("a hotfix should be queued");
+        let mut pull = match pulls.get_mut(&head) {
+            Some(pull) if pull.status.is_testing() => pull,
+            _ => return Ok(QueueEvent::Removed),
+        };
+        info!("Parking pr #{} for hotfix #{}", head, hotfix);
+
+        pull.update_status(Status::queued(), config, github, project_board)
+            .await?;
+        self.parked = Some(head);
+
+        let comment =
+            templates::render(config, "land-parked", liquid::object!({ "hotfix": hotfix }))?;
+        github
+            .issues()
+            .create_comment(config.owner(), config.name(), head, &comment)
+            .await?;
+
+        Ok(QueueEvent::Parked)
+    }
+
+    /// Create a merge commit for the PR and push it to the test branches, or to the test branch of
+    /// its lane
+    async fn prepare(
+        head: u64,
+        lane: Option<&Lane>,
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<QueueEvent> {
+        let mut pull = pulls.get_mut(&head).expect("PR should exist");
+
+        // The approval may have expired while the PR was waiting in the queue
+        if config.require_review() && pull.approval_expired(config) {
+            info!("pr #{}'s approval has expired, dequeuing", pull.number);
+            pull.update_status(Status::InReview, config, github, project_board)
+                .await?;
+            pull.notify.clear();
+
+            let comment = templates::render(
+                config,
+                "approval-expired",
+                liquid::object!({
+                    "sender": None::<String>,
+                    "days": config.approval_max_age().unwrap_or_default().as_secs() / (24 * 60 * 60),
+                }),
+            )?;
+            pull.post_comment(config, github, &comment).await?;
+
+            return Ok(QueueEvent::MergeFailed);
+        }
+
+        let lane_branch = lane.map(Lane::test_branch);
+        let test_branches = match &lane_branch {
+            Some(branch) => vec![branch.as_str()],
+            None => config
+                .test_branches()
+                .map(|branch| branch.name())
+                .collect::<Vec<_>>(),
+        };
+
+        let resumed_merge = if std::mem::take(&mut pull.resume) {
+            Self::resume_merge_and_update_github(config, github, repo, &pull, &test_branches)
+                .await?
+        } else {
+            None
+        };
+        let merge_oid = match resumed_merge {
+            Some(merge_oid) => Some(merge_oid),
+            None => {
+                if let Some(test_results) =
+                    Self::up_to_date_test_results(config, github, repo, &pull).await?
+                {
+                    // The PR's own checks already tested exactly what would land, so the base is
+                    // fast-forwarded to its head without testing it again
+                    info!(
+                        "pr #{} is up to date with '{}' and its checks passed, fast-forwarding",
+                        pull.number, pull.base_ref_name
+                    );
+                    let merge_oid = pull.head_ref_oid.clone();
+                    pull.last_merge_oid = Some(merge_oid.clone());
+                    let status = Status::Testing {
+                        merge_oid,
+                        tests_started_at: Instant::now(),
+                        test_results,
+                    };
+                    pull.update_status(status, config, github, project_board)
+                        .await?;
+                    return Ok(QueueEvent::MergeCreated);
+                }
+
+                Self::create_merge_and_update_github(config, github, repo, &pull, &test_branches)
+                    .await?
+            }
+        };
+
+        if let Some(merge_oid) = merge_oid {
+            pull.last_merge_oid = Some(merge_oid.clone());
+            pull.update_status(Status::testing(merge_oid), config, github, project_board)
+                .await?;
+            Ok(QueueEvent::MergeCreated)
+        } else {
+            pull.update_status(
+                Status::failed("merge conflict", None),
+                config,
+                github,
+                project_board,
+            )
+            .await?;
+            pull.notify.clear();
+            Ok(QueueEvent::MergeFailed)
+        }
+    }
+
+    /// The queued PRs which should be tested along with `head`, empty if it should be tested on
+    /// its own. PRs which change any of the same files as a PR of the batch are left out of it.
+    /// While the PRs of a failed batch are being bisected, only the first half of those that are
+    /// left is batched
+    fn batch_for(
+        &mut self,
+        head: u64,
+        config: &RepoConfig,
+        pulls: &PullRequests,
+    ) -> Vec<BatchedPull> {
+        self.suspects
+            .retain(|n| pulls.get(n).is_some_and(|p| p.status.is_queued()));
+
+        // Lanes rebuild a PR's merge commit when landing it, and updating PRs in-place or landing
+        // them with a merge commit needs a commit of each PR's own
+        let batchable = |pull: &PullRequestState| {
+            !pull.hotfix
+                && !pull.resume
+                && !pull.lands_with_merge_commit(config)
+                && !pull.has_label(config.labels().no_rollup())
+                && !(config.require_review() && pull.approval_expired(config))
+        };
+        let leader = &pulls[&head];
+        if config.batch_size() < 2
+            || self.lane.is_some()
+            || config.maintainer_mode()
+            || !batchable(leader)
+        {
+            return Vec::new();
+        }
+
+        let candidates = if self.suspects.is_empty() {
+            pulls
+                .queued()
+                .filter(|p| self.is_in_lane(p))
+                .collect::<Vec<_>>()
+        } else if self.suspects[0] == head {
+            let half = self.suspects.len().div_ceil(2);
+            self.suspects[..half].iter().map(|n| &pulls[n]).collect()
+        } else {
+            return Vec::new();
+        };
+
+        let mut members = vec![leader];
+        let mut batch = Vec::new();
+        for pull in candidates {
+            if members.len() == config.batch_size() {
+                break;
+            }
+            if pull.number == head || pull.base_ref_name != leader.base_ref_name || !batchable(pull)
+            {
+                continue;
+            }
+
+            // A PR changing the same files as one already in the batch is the likeliest to break
+            // it, so rather than risking a bisection it's left to be tested after them
+            if let Some(member) = members.iter().find(|member| member.overlaps(pull)) {
+                info!(
+                    "pr #{} overlaps with pr #{}, leaving it out of the batch",
+                    pull.number, member.number
+                );
+                continue;
+            }
+
+            members.push(pull);
+            batch.push(BatchedPull {
+                number: pull.number,
+                head_oid: pull.head_ref_oid.clone(),
+            });
+        }
+        batch
+    }
+
+    /// Rebase `head` and the PRs of `batch` on top of each other and push the result to the test
+    /// branches. PRs of the batch which don't apply are left out of it, to fail on their own once
+    /// the queue reaches them
+    #[allow(clippy::too_many_arguments)]
+    async fn prepare_batch(
+        &mut self,
+        head: u64,
+        batch: Vec<BatchedPull>,
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        project_board: Option<&ProjectBoard>,
+        pulls: &mut PullRequests,
+    ) -> Result<QueueEvent> {
+        let branches = config
+            .test_branches()
+            .map(|branch| branch.name())
+            .collect::<Vec<_>>();
+        let branch = branches
+            .first()
+            .expect("at least one test branch is required");
+
+        info!("Creating merge for pr #{} and its batch", head);
+        let leader = &pulls[&head];
+        let squash = Self::squash(config, repo, leader)?;
+        let outcome = repo.fetch_and_rebase(
+            &leader.base_ref_name,
+            &leader.head_ref_oid,
+            branch,
+            head,
+            &squash,
+        )?;
+        let mut tip = match outcome {
+            MergeOutcome::Merged(merge_oid) => merge_oid,
+            // Preparing the head on its own reports why it can't be landed
+            MergeOutcome::Conflict { .. } | MergeOutcome::Empty => {
+                return Self::prepare(head, None, config, github, repo, project_board, pulls).await;
+            }
+        };
+
+        let mut included = Vec::new();
+        for batched in batch {
+            let pull = &pulls[&batched.number];
+            let squash = Self::squash(config, repo, pull)?;
+            let outcome = repo.fetch_and_rebase_onto(
+                &pull.base_ref_name,
+                &tip,
+                &pull.head_ref_oid,
+                branch,
+                pull.number,
+                &squash,
+            )?;
+            match outcome {
+                MergeOutcome::Merged(merge_oid) => {
+                    tip = merge_oid;
+                    included.push(batched);
+                }
+                MergeOutcome::Conflict { .. } | MergeOutcome::Empty => {
+                    info!(
+                        "leaving pr #{} out of the batch of pr #{}",
+                        pull.number, head
+                    );
+                }
+            }
+        }
+
+        // A PR which was left out may have been rebased last, so the tip is pushed rather than
+        // the working branch
+        for branch in &branches {
+            repo.push_oid_to_branch(&tip, branch)?;
+            info!("pushed '{}' branch", branch);
+        }
+
+        for number in std::iter::once(head).chain(included.iter().map(|b| b.number)) {
+            Self::create_pending_status(config, github, &pulls[&number]).await?;
+        }
+        info!(
+            "Testing pr #{} batched with {:?}",
+            head,
+            included.iter().map(|b| b.number).collect::<Vec<_>>()
+        );
+        self.batch = included;
+
+        // The merge commit contains the other PRs of the batch, so unlike when the PR is tested on
+        // its own it isn't remembered for resuming the land
+        let mut pull = pulls.get_mut(&head).expect("PR should exist");
+        pull.update_status(Status::testing(tip), config, github, project_board)
+            .await?;
+        Ok(QueueEvent::MergeCreated)
+    }
+
+    /// The results of the checks on the PR's head commit if it can be landed by fast-forwarding its
+    /// base to it, that is if it's already built on the tip of its base and its checks have passed
+    async fn up_to_date_test_results(
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        pull: &PullRequestState,
+    ) -> Result<Option<HashMap<String, TestResult>>> {
+        // Merge commits and squashing both require creating new commits
+        if !config.fast_forward_when_up_to_date()
+            || pull.lands_with_merge_commit(config)
+            || pull.has_label(config.labels().squash())
+        {
+            return Ok(None);
+        }
+
+        if !repo.fetch_and_check_up_to_date(&pull.base_ref_name, &pull.head_ref_oid)? {
+            return Ok(None);
+        }
+
+        let test_results = pull.fetch_head_test_results(config, github).await?;
+        match TestSuiteResult::new(Instant::now(), &test_results, config) {
+            TestSuiteResult::Passed => Ok(Some(test_results)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Push the merge commit from the PR's previous land back to the test branches so that the
+    /// exact same commit is re-tested. Returns `None` if the commit is no longer built on the tip
+    /// of the PR's base branch or can no longer be pushed, in which case a new merge should be
+    /// created instead
+    async fn resume_merge_and_update_github(
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        pull: &PullRequestState,
+        branches: &[&str],
+    ) -> Result<Option<Oid>> {
+        let merge_oid = match &pull.last_merge_oid {
+            Some(merge_oid) => merge_oid,
+            None => return Ok(None),
+        };
+        info!("Resuming merge {} for pr #{}", merge_oid, pull.number);
+
+        // Landing the commit fast-forwards the base branch to it, which is only possible if the
+        // base branch hasn't moved since the commit was created
+        match repo.fetch_and_check_up_to_date(&pull.base_ref_name, merge_oid) {
+            Ok(true) => {}
+            Ok(false) => {
+                info!(
+                    "'{}' has moved since merge {} for pr #{} was created, creating a new merge \
+                    instead",
+                    pull.base_ref_name, merge_oid, pull.number
+                );
+                return Ok(None);
+            }
+            Err(e) => {
+                warn!(
+                    "unable to check merge {} for pr #{} is up to date, creating a new merge \
+                    instead: {:#}",
+                    merge_oid, pull.number, e
+                );
+                return Ok(None);
+            }
+        }
+
+        for branch in branches {
+            if let Err(e) = repo.push_oid_to_branch(merge_oid, branch) {
+                warn!(
+                    "unable to push merge {} for pr #{}, creating a new merge instead: {:#}",
+                    merge_oid, pull.number, e
+                );
+                return Ok(None);
+            }
+            info!("pushed '{}' branch", branch);
+        }
+
+        Self::create_pending_status(config, github, pull).await?;
+
+        Ok(Some(merge_oid.clone()))
+    }
+
+    async fn create_pending_status(
+        config: &RepoConfig,
+        github: &GithubClient,
+        pull: &PullRequestState,
+    ) -> Result<()> {
+        github
+            .repos()
+            .create_status(
+                config.owner(),
+                config.name(),
+                &pull.head_ref_oid.to_string(),
+                &github::client::CreateStatusRequest {
+                    state: github::StatusEventState::Pending,
+                    target_url: None,
+                    description: None,
+                    context: "bors",
+                },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// How the PR's commits are combined when it's rebased, rendering the message of the squashed
+    /// commit from the messages of its commits if its squash mode calls for one
+    fn squash(
+        config: &RepoConfig,
+        repo: &mut dyn GitBackend,
+        pull: &PullRequestState,
+    ) -> Result<Squash> {
+        if !pull.has_label(config.labels().squash()) {
+            return Ok(Squash::None);
+        }
+
+        let template = match pull.squash_mode {
+            SquashMode::KeepFirst => return Ok(Squash::KeepFirst),
+            SquashMode::Concat => "squash-concat",
+            SquashMode::Template => "squash-message",
+        };
+        let commits = repo.fetch_commit_messages(&pull.base_ref_name, &pull.head_ref_oid)?;
+        let message = templates::render_commit_message(
+            config,
+            template,
+            liquid::object!({
+                "number": pull.number,
+                "title": pull.title,
+                "body": pull.body,
+                "commits": commits,
+            }),
+        )?;
+
+        Ok(Squash::Message(message))
+    }
+
+    async fn create_merge_and_update_github(
+        config: &RepoConfig,
+        github: &GithubClient,
+        repo: &mut dyn GitBackend,
+        pull: &PullRequestState,
+        branches: &[&str],
+    ) -> Result<Option<Oid>> {
+        info!("Creating merge for pr #{}", pull.number);
+
+        // The first branch is used as the working branch for the rebase
+        let (branch, other_branches) = branches
+            .split_first()
+            .expect("at least one test branch is required");
+
+        // Attempt to rebase the PR onto 'base_ref' (or create a merge commit if configured to do
+        // so) and push to the test branches for testing
+        let merge_commit = pull.lands_with_merge_commit(config);
+        let outcome = if merge_commit {
+            repo.fetch_and_merge(
+                &pull.base_ref_name,
+                &pull.head_ref_oid,
+                branch,
+                pull.number,
+                &pull.title,
+            )?
+        } else {
+            let squash = Self::squash(config, repo, pull)?;
+            repo.fetch_and_rebase(
+                &pull.base_ref_name,
+                &pull.head_ref_oid,
+                branch,
+                pull.number,
+                &squash,
+            )?
+        };
+
+        let (description, comment) = match outcome {
+            MergeOutcome::Merged(merge_oid) => {
+                repo.push_branch(branch)?;
+                info!("pushed '{}' branch", branch);
+
+                for other in other_branches {
+                    repo.push_oid_to_branch(&merge_oid, other)?;
+                    info!("pushed '{}' branch", other);
+                }
+
+                Self::create_pending_status(config, github, pull).await?;
+
+                return Ok(Some(merge_oid));
+            }
+            MergeOutcome::Conflict { files } => (
+                "Merge Conflict",
+                templates::render(
+                    config,
+                    "merge-conflict",
+                    liquid::object!({
+                        "base": pull.base_ref_name,
+                        "number": pull.number,
+                        "head_ref": pull.head_ref_name,
+                        "files": files,
+                        "merge_commit": merge_commit,
+                        "notify": pull.notify,
+                    }),
+                )?,
+            ),
+            MergeOutcome::Empty => (
+                "No Changes",
+                templates::render(
+                    config,
+                    "nothing-to-land",
+                    liquid::object!({ "base": pull.base_ref_name, "notify": pull.notify }),
+                )?,
+            ),
+        };
+
+        github
+            .repos()
+            .create_status(
+                config.owner(),
+                config.name(),
+                &pull.head_ref_oid.to_string(),
+                &github::client::CreateStatusRequest {
+                    state: github::StatusEventState::Error,
+                    target_url: None,
+                    description: Some(description),
+                    context: "bors",
+                },
+            )
+            .await?;
+
+        pull.post_comment(config, github, &comment).await?;
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        git::mock::{GitOperation, MockGitBackend},
+        graphql::fake::FakeGithub,
+        state::BuildResultSource,
+    };
+    use github::test_fixtures::{CheckRunEventBuilder, EventPayload, PullRequestEventBuilder};
+    use hyper::{Method, StatusCode};
+    use serde_json::json;
+
+    fn repo_config(extra: &str) -> RepoConfig {
+        toml::from_str(&format!("owner = \"bors-rs\"\nname = \"bors\"\n{}", extra)).unwrap()
+    }
+
+    fn head_sha(number: u64) -> String {
+        format!("a{:039x}", number)
+    }
+
+    /// The queued PRs `numbers`, each with a head of its own
+    fn queued_pulls(config: &RepoConfig, numbers: &[u64]) -> PullRequests {
+        let mut pulls = PullRequests::new(config.labels());
+        pulls.extend(numbers.iter().map(|&number| {
+            let event = PullRequestEventBuilder::new("opened")
+                .number(number)
+                .head(&format!("pr-{}", number), &head_sha(number))
+                .build();
+            let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+            pull.status = Status::queued();
+            pull
+        }));
+        pulls
+    }
+
+    /// Report the result of `check` on the merge commit PR `number` is being tested with
+    fn report(pulls: &mut PullRequests, number: u64, check: &str, passed: bool) {
+        let mut pull = pulls.get_mut(&number).unwrap();
+        match &mut pull.status {
+            Status::Testing { test_results, .. } => {
+                test_results.insert(
+                    check.to_owned(),
+                    TestResult {
+                        passed,
+                        details_url: format!("https://ci.example.com/{}", number),
+                        source: BuildResultSource::CommitStatus {
+                            context: check.to_owned(),
+                        },
+                    },
+                );
+            }
+            status => panic!("pr #{} isn't being tested: {:?}", number, status),
+        }
+    }
+
+    fn merge_oid(pulls: &PullRequests, number: u64) -> Oid {
+        match &pulls[&number].status {
+            Status::Testing { merge_oid, .. } => merge_oid.clone(),
+            status => panic!("pr #{} isn't being tested: {:?}", number, status),
+        }
+    }
+
+    #[test]
+    fn priority_sort() {
+        let mut entries = vec![
+            QueueEntry::new(1, StatusType::InReview, Priority::Normal, None),
+            QueueEntry::new(10, StatusType::InReview, Priority::High, None),
+        ];
+
+        entries.sort();
+
+        let expected = vec![
+            QueueEntry::new(10, StatusType::InReview, Priority::High, None),
+            QueueEntry::new(1, StatusType::InReview, Priority::Normal, None),
+        ];
+        assert_eq!(entries, expected);
+
+        let mut entries = vec![
+            QueueEntry::new(10, StatusType::InReview, Priority::Normal, None),
+            QueueEntry::new(1, StatusType::InReview, Priority::Normal, None),
+        ];
+
+        entries.sort();
+
+        let expected = vec![
+            QueueEntry::new(1, StatusType::InReview, Priority::Normal, None),
+            QueueEntry::new(10, StatusType::InReview, Priority::Normal, None),
+        ];
+        assert_eq!(entries, expected);
+
+        let mut entries = vec![
+            QueueEntry::new(1, StatusType::InReview, Priority::Low, None),
+            QueueEntry::new(10, StatusType::InReview, Priority::Normal, None),
+        ];
+
+        entries.sort();
+
+        let expected = vec![
+            QueueEntry::new(10, StatusType::InReview, Priority::Normal, None),
+            QueueEntry::new(1, StatusType::InReview, Priority::Low, None),
+        ];
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn explicit_position_overrides_priority() {
+        let mut entries = [
+            QueueEntry::new(1, StatusType::Queued, Priority::High, None),
+            QueueEntry::new(2, StatusType::Queued, Priority::Low, None).with_position(Some(1)),
+            QueueEntry::new(3, StatusType::Queued, Priority::Normal, None).with_position(Some(0)),
+            QueueEntry::new(4, StatusType::Testing, Priority::Low, None),
+        ];
+
+        entries.sort();
+
+        let numbers = entries.iter().map(|e| e.number).collect::<Vec<_>>();
+        assert_eq!(numbers, [4, 3, 2, 1]);
+    }
+
+    fn batched(
+        queue: &mut MergeQueue,
+        head: u64,
+        config: &RepoConfig,
+        pulls: &PullRequests,
+    ) -> Vec<u64> {
+        queue
+            .batch_for(head, config, pulls)
+            .into_iter()
+            .map(|batched| batched.number)
+            .collect()
+    }
+
+    #[test]
+    fn failed_batches_are_bisected() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+            batch-size = 4
+            "#,
+        )
+        .unwrap();
+        let mut pulls = PullRequests::new(config.labels());
+        pulls.extend((1..=5).map(|number| {
+            let event = PullRequestEventBuilder::new("opened")
+                .number(number)
+                .build();
+            let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+            pull.status = Status::queued();
+            pull
+        }));
+
+        let mut queue = MergeQueue::new();
+        assert_eq!(batched(&mut queue, 1, &config, &pulls), [2, 3, 4]);
+
+        // The batch of PRs 1 to 4 failed, so its first half is tested next
+        queue.suspects = vec![1, 2, 3, 4];
+        assert_eq!(batched(&mut queue, 1, &config, &pulls), [2]);
+
+        // PRs 1 and 2 passed and landed, leaving 3 and 4 to be tested on their own
+        pulls.remove(&1);
+        pulls.remove(&2);
+        assert!(batched(&mut queue, 3, &config, &pulls).is_empty());
+        assert_eq!(queue.suspects, [3, 4]);
+
+        // Other PRs aren't batched until the bisection is over
+        assert!(batched(&mut queue, 5, &config, &pulls).is_empty());
+    }
+
+    #[tokio::test]
+    async fn bisection_comments_once_per_batch() {
+        let config = repo_config("checks = [\"ci\"]\nbatch-size = 4");
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = queued_pulls(&config, &[1, 2, 3, 4]);
+        let mut queue = MergeQueue::new();
+
+        // The whole batch fails, then PRs 1 and 2, then PR 1 on its own
+        for _ in 0..3 {
+            for _ in 0..2 {
+                queue
+                    .process_queue(&config, &client, &mut git, None, &mut pulls)
+                    .await
+                    .unwrap();
+            }
+            report(&mut pulls, 1, "ci", false);
+        }
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(pulls[&1].status.is_failed());
+
+        for number in 2..=4 {
+            let comments = github.comments("bors-rs", "bors", number);
+            assert_eq!(comments.len(), 1, "{:?}", comments);
+            assert!(comments[0].contains("#1, #2, #3, #4"), "{}", comments[0]);
+        }
+        let comments = github.comments("bors-rs", "bors", 1);
+        let batch_failures = comments
+            .iter()
+            .filter(|comment| comment.contains("Tests failed on the batch"))
+            .count();
+        assert_eq!(batch_failures, 1, "{:?}", comments);
+        assert!(
+            comments.last().unwrap().contains("broke it"),
+            "{:?}",
+            comments
+        );
+    }
+
+    #[test]
+    fn overlapping_prs_are_not_batched() {
+        let config = repo_config("batch-size = 3");
+        let mut pulls = queued_pulls(&config, &[1, 2, 3, 4]);
+        for (number, files) in [
+            (1, &["src/lib.rs"][..]),
+            (2, &["README.md", "src/lib.rs"]),
+            (3, &["README.md"]),
+            (4, &["docs/index.md"]),
+        ] {
+            pulls.get_mut(&number).unwrap().changed_files =
+                files.iter().map(|&file| file.to_owned()).collect();
+        }
+
+        // #2 changes the same file as #1, so it's tested after the batch which #3 can still join
+        let mut queue = MergeQueue::new();
+        assert_eq!(batched(&mut queue, 1, &config, &pulls), [3, 4]);
+    }
+
+    /// `queue` as it's loaded after being saved before a restart
+    fn saved(queue: &MergeQueue) -> MergeQueue {
+        serde_json::from_value(serde_json::to_value(queue).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn restore_keeps_the_testing_head() {
+        let config = repo_config(r#"checks = ["ci"]"#);
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = queued_pulls(&config, &[1]);
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_eq!(queue.head(), Some(1));
+
+        let mut restored = MergeQueue::new();
+        restored.restore(saved(&queue), &pulls);
+        assert_eq!(restored.head(), Some(1));
+
+        // #1 was pushed to while bors was down, so its saved status wasn't restored
+        pulls.get_mut(&1).unwrap().status = Status::InReview;
+        let mut restored = MergeQueue::new();
+        restored.restore(saved(&queue), &pulls);
+        assert!(restored.is_idle());
+    }
+
+    #[test]
+    fn restore_resets_unless_testing() {
+        let config = repo_config("");
+        let mut pulls = queued_pulls(&config, &[1]);
+        pulls.get_mut(&1).unwrap().status = Status::testing(Oid::from_str(head_sha(2)));
+
+        // bors stopped while still preparing #1's merge commit
+        let mut queue = MergeQueue::new();
+        queue.transition(QueueEvent::Started(1)).unwrap();
+        assert_eq!(queue.head(), Some(1));
+
+        let mut restored = MergeQueue::new();
+        restored.restore(saved(&queue), &pulls);
+        assert!(restored.is_idle());
+    }
+
+    #[tokio::test]
+    async fn lands_queued_pr() {
+        let config = repo_config(r#"checks = ["ci"]"#);
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = queued_pulls(&config, &[1]);
+        let base = pulls[&1].base_ref_name.clone();
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_eq!(queue.head(), Some(1));
+        let merge_oid = merge_oid(&pulls, 1);
+        assert_eq!(
+            git.operations(),
+            [
+                GitOperation::Rebase {
+                    base_ref: base.clone(),
+                    head_oid: Oid::from_str(head_sha(1)),
+                    branch: "auto".into(),
+                    squash: Squash::None,
+                },
+                GitOperation::PushBranch("auto".into()),
+            ]
+        );
+        assert_eq!(
+            github.statuses("bors-rs", "bors", &head_sha(1)),
+            ["pending"]
+        );
+
+        // Nothing happens until the tests complete
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_eq!(queue.head(), Some(1));
+
+        report(&mut pulls, 1, "ci", true);
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(queue.is_idle());
+        assert!(pulls.get(&1).is_none());
+        assert_eq!(
+            github.statuses("bors-rs", "bors", &merge_oid.to_string()),
+            ["success"]
+        );
+        assert_eq!(
+            github.bodies(
+                Method::PATCH,
+                &format!("repos/bors-rs/bors/git/refs/heads/{}", base)
+            ),
+            [json!({ "sha": merge_oid.to_string(), "force": false })]
+        );
+    }
+
+    /// Respond to requests for the checks on PR `number`'s head with a `ci` check run, which is
+    /// still running if `conclusion` is `None`
+    fn respond_head_check(github: &FakeGithub, number: u64, conclusion: Option<&str>) {
+        let head = head_sha(number);
+        let status = if conclusion.is_some() {
+            "completed"
+        } else {
+            "in_progress"
+        };
+        let check_run = CheckRunEventBuilder::new("completed")
+            .name("ci")
+            .head_sha(&head)
+            .status(status)
+            .conclusion(conclusion)
+            .payload()["check_run"]
+            .clone();
+        github.respond(
+            Method::GET,
+            &format!("repos/bors-rs/bors/commits/{}/check-runs", head),
+            StatusCode::OK,
+            json!({ "total_count": 1, "check_runs": [check_run] }),
+        );
+        github.respond(
+            Method::GET,
+            &format!("repos/bors-rs/bors/commits/{}/status", head),
+            StatusCode::OK,
+            json!({
+                "state": "pending",
+                "name": "bors",
+                "sha": head,
+                "total_count": 0,
+                "statuses": [],
+                "commit_url": "",
+                "repository_url": "",
+            }),
+        );
+    }
+
+    /// Queue PR #1, which is up to date with its base, and process the queue until it lands
+    async fn fast_forward(config: &RepoConfig, github: &FakeGithub) -> MockGitBackend {
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        git.set_up_to_date(Oid::from_str(head_sha(1)));
+        let mut pulls = queued_pulls(config, &[1]);
+        let mut queue = MergeQueue::new();
+
+        for _ in 0..2 {
+            queue
+                .process_queue(config, &client, &mut git, None, &mut pulls)
+                .await
+                .unwrap();
+        }
+        assert!(queue.is_idle());
+        assert!(pulls.get(&1).is_none());
+        git
+    }
+
+    #[tokio::test]
+    async fn up_to_date_pr_is_fast_forwarded() {
+        let config = repo_config("checks = [\"ci\"]\nfast-forward-when-up-to-date = true");
+        let github = FakeGithub::start();
+        respond_head_check(&github, 1, Some("success"));
+        let base = queued_pulls(&config, &[1])[&1].base_ref_name.clone();
+
+        let git = fast_forward(&config, &github).await;
+        assert_eq!(
+            git.operations(),
+            [GitOperation::CheckUpToDate {
+                base_ref: base.clone(),
+                head_oid: Oid::from_str(head_sha(1)),
+            }]
+        );
+        assert_eq!(
+            github.bodies(
+                Method::PATCH,
+                &format!("repos/bors-rs/bors/git/refs/heads/{}", base)
+            ),
+            [json!({ "sha": head_sha(1), "force": false })]
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_or_pending_head_checks_fall_back_to_a_merge() {
+        let config = repo_config("checks = [\"ci\"]\nfast-forward-when-up-to-date = true");
+        for conclusion in [Some("failure"), None].iter() {
+            let github = FakeGithub::start();
+            let client = github.client();
+            respond_head_check(&github, 1, *conclusion);
+            let mut git = MockGitBackend::new("bors");
+            git.set_up_to_date(Oid::from_str(head_sha(1)));
+            let mut pulls = queued_pulls(&config, &[1]);
+            let base = pulls[&1].base_ref_name.clone();
+            let mut queue = MergeQueue::new();
+
+            queue
+                .process_queue(&config, &client, &mut git, None, &mut pulls)
+                .await
+                .unwrap();
+            assert_ne!(merge_oid(&pulls, 1), Oid::from_str(head_sha(1)));
+            assert_eq!(
+                git.operations(),
+                [
+                    GitOperation::CheckUpToDate {
+                        base_ref: base.clone(),
+                        head_oid: Oid::from_str(head_sha(1)),
+                    },
+                    GitOperation::Rebase {
+                        base_ref: base.clone(),
+                        head_oid: Oid::from_str(head_sha(1)),
+                        branch: "auto".into(),
+                        squash: Squash::None,
+                    },
+                    GitOperation::PushBranch("auto".into()),
+                ],
+                "{:?}",
+                conclusion
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn squash_and_merge_commit_prs_are_not_fast_forwarded() {
+        let config = repo_config("checks = [\"ci\"]\nfast-forward-when-up-to-date = true");
+        for merge_commit in [false, true].iter() {
+            let github = FakeGithub::start();
+            let client = github.client();
+            respond_head_check(&github, 1, Some("success"));
+            let mut git = MockGitBackend::new("bors");
+            git.set_up_to_date(Oid::from_str(head_sha(1)));
+            let mut pulls = queued_pulls(&config, &[1]);
+            let mut pull = pulls.get_mut(&1).unwrap();
+            if *merge_commit {
+                pull.rebase = Some(false);
+            } else {
+                pull.labels.insert(config.labels().squash().to_owned());
+            }
+            drop(pull);
+            let mut queue = MergeQueue::new();
+
+            queue
+                .process_queue(&config, &client, &mut git, None, &mut pulls)
+                .await
+                .unwrap();
+            assert_ne!(merge_oid(&pulls, 1), Oid::from_str(head_sha(1)));
+            let operations = git.operations();
+            assert!(
+                !operations
+                    .iter()
+                    .any(|op| matches!(op, GitOperation::CheckUpToDate { .. })),
+                "{:?}",
+                operations
+            );
+            assert_eq!(
+                operations.last(),
+                Some(&GitOperation::PushBranch("auto".into()))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn maintainer_mode_doesnt_push_fast_forwarded_head() {
+        let config = repo_config(
+            "checks = [\"ci\"]\nfast-forward-when-up-to-date = true\nmaintainer-mode = true",
+        );
+        let github = FakeGithub::start();
+        respond_head_check(&github, 1, Some("success"));
+        assert!(queued_pulls(&config, &[1])[&1].head_repo.is_some());
+
+        let git = fast_forward(&config, &github).await;
+        assert!(!git
+            .operations()
+            .iter()
+            .any(|op| matches!(op, GitOperation::PushToRemote { .. })));
+    }
+
+    #[tokio::test]
+    async fn conflicting_pr_fails_to_land() {
+        let config = repo_config("");
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        git.push_outcome(MergeOutcome::Conflict {
+            files: vec!["README.md".into()],
+        });
+        let mut pulls = queued_pulls(&config, &[1, 2]);
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+
+        // The queue moved on to the next PR, which lands since no checks are required
+        assert!(pulls[&1].status.is_failed());
+        assert!(pulls.get(&2).is_none());
+        assert!(queue.is_idle());
+        assert_eq!(github.statuses("bors-rs", "bors", &head_sha(1)), ["error"]);
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0].contains("README.md"), "{}", comments[0]);
+
+        // Only the merge commit of the PR which landed was pushed
+        let pushes = git
+            .operations()
+            .into_iter()
+            .filter(|op| matches!(op, GitOperation::PushBranch(_)))
+            .count();
+        assert_eq!(pushes, 1);
+    }
+
+    /// Queue PR #1 to resume testing the merge commit of its previous land
+    fn resumed_pulls(config: &RepoConfig, merge_oid: &Oid) -> PullRequests {
+        let mut pulls = queued_pulls(config, &[1]);
+        let mut pull = pulls.get_mut(&1).unwrap();
+        pull.last_merge_oid = Some(merge_oid.clone());
+        pull.resume = true;
+        drop(pull);
+        pulls
+    }
+
+    #[tokio::test]
+    async fn resume_retests_previous_merge() {
+        let config = repo_config(r#"checks = ["ci"]"#);
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let previous = Oid::from_str("b".repeat(40));
+        git.set_up_to_date(previous.clone());
+        let mut pulls = resumed_pulls(&config, &previous);
+        let base = pulls[&1].base_ref_name.clone();
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_eq!(merge_oid(&pulls, 1), previous);
+        assert!(!pulls[&1].resume);
+        assert_eq!(
+            git.operations(),
+            [
+                GitOperation::CheckUpToDate {
+                    base_ref: base,
+                    head_oid: previous.clone(),
+                },
+                GitOperation::PushOid {
+                    oid: previous,
+                    branch: "auto".into(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_creates_new_merge_once_base_moved() {
+        let config = repo_config(r#"checks = ["ci"]"#);
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        // The base branch moved since the previous merge was created, so it isn't up to date
+        let previous = Oid::from_str("b".repeat(40));
+        let mut pulls = resumed_pulls(&config, &previous);
+        let base = pulls[&1].base_ref_name.clone();
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_ne!(merge_oid(&pulls, 1), previous);
+        assert_eq!(pulls[&1].last_merge_oid, Some(merge_oid(&pulls, 1)));
+        assert_eq!(
+            git.operations(),
+            [
+                GitOperation::CheckUpToDate {
+                    base_ref: base.clone(),
+                    head_oid: previous,
+                },
+                GitOperation::Rebase {
+                    base_ref: base,
+                    head_oid: Oid::from_str(head_sha(1)),
+                    branch: "auto".into(),
+                    squash: Squash::None,
+                },
+                GitOperation::PushBranch("auto".into()),
+            ]
+        );
+    }
+
+    /// Queue the hotfix #2 while PR #1 is being tested
+    async fn queue_hotfix_while_testing(
+        config: &RepoConfig,
+        client: &GithubClient,
+        git: &mut MockGitBackend,
+        queue: &mut MergeQueue,
+    ) -> PullRequests {
+        let mut pulls = queued_pulls(config, &[1]);
+        queue
+            .process_queue(config, client, git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_eq!(queue.head(), Some(1));
+
+        let mut hotfix = queued_pulls(config, &[2]).remove(&2).unwrap();
+        hotfix.hotfix = true;
+        pulls.insert(hotfix);
+        pulls
+    }
+
+    #[tokio::test]
+    async fn hotfix_parks_pr_being_tested() {
+        let config = repo_config(r#"checks = ["ci"]"#);
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut queue = MergeQueue::new();
+        let mut pulls = queue_hotfix_while_testing(&config, &client, &mut git, &mut queue).await;
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_eq!(queue.head(), Some(2));
+        assert!(pulls[&1].status.is_queued());
+        assert_eq!(queue.parked, Some(1));
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0].contains("#2"), "{}", comments[0]);
+    }
+
+    #[tokio::test]
+    async fn passed_pr_lands_before_hotfix() {
+        let config = repo_config(r#"checks = ["ci"]"#);
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut queue = MergeQueue::new();
+        let mut pulls = queue_hotfix_while_testing(&config, &client, &mut git, &mut queue).await;
+        let merge_oid = merge_oid(&pulls, 1);
+
+        report(&mut pulls, 1, "ci", true);
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(pulls.get(&1).is_none());
+        assert_eq!(queue.head(), Some(2));
+        assert_eq!(queue.parked, None);
+        assert_eq!(
+            github.statuses("bors-rs", "bors", &merge_oid.to_string()),
+            ["success"]
+        );
+    }
+
+    const LANES: &str = r#"
+        checks = ["ci"]
+
+        [[lanes]]
+        name = "docs"
+        paths = ["docs/*"]
+
+        [[lanes]]
+        name = "github"
+        paths = ["github/*"]
+    "#;
+
+    /// The queued PRs `numbers`, each in the given lane
+    fn laned_pulls(config: &RepoConfig, numbers: &[(u64, Option<&str>)]) -> PullRequests {
+        let mut pulls = PullRequests::new(config.labels());
+        for &(number, lane) in numbers {
+            let mut pull = queued_pulls(config, &[number]).remove(&number).unwrap();
+            pull.lane = lane.map(str::to_owned);
+            pull.lane_head = Some(pull.head_ref_oid.clone());
+            pulls.insert(pull);
+        }
+        pulls
+    }
+
+    fn pushed_branches(git: &MockGitBackend) -> Vec<String> {
+        git.operations()
+            .into_iter()
+            .filter_map(|op| match op {
+                GitOperation::PushBranch(branch) => Some(branch),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn lanes_land_concurrently() {
+        let config = repo_config(LANES);
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = laned_pulls(
+            &config,
+            &[(1, Some("docs")), (2, Some("github")), (3, Some("docs"))],
+        );
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+
+        // Each lane tests one PR at a time, on its own test branch
+        assert_eq!(queue.head(), None);
+        assert_eq!(queue.lanes["docs"].head(), Some(1));
+        assert_eq!(queue.lanes["github"].head(), Some(2));
+        assert!(pulls[&3].status.is_queued());
+        assert_eq!(pushed_branches(&git), ["auto-docs", "auto-github"]);
+
+        // PR #1 is landed as tested since nothing else landed in the meantime
+        let merge_oid = merge_oid(&pulls, 1);
+        git.set_up_to_date(merge_oid.clone());
+        report(&mut pulls, 1, "ci", true);
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(pulls.get(&1).is_none());
+        assert_eq!(queue.lanes["docs"].head(), Some(3));
+        assert_eq!(queue.lanes["github"].head(), Some(2));
+        assert_eq!(
+            github.bodies(
+                Method::PATCH,
+                &format!(
+                    "repos/bors-rs/bors/git/refs/heads/{}",
+                    pulls[&2].base_ref_name
+                )
+            ),
+            [json!({ "sha": merge_oid.to_string(), "force": false })]
+        );
+    }
+
+    #[tokio::test]
+    async fn laneless_prs_wait_for_lanes() {
+        let config = repo_config(LANES);
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = laned_pulls(&config, &[(1, Some("docs"))]);
+        let mut queue = MergeQueue::new();
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_eq!(queue.lanes["docs"].head(), Some(1));
+
+        // A PR outside of any lane waits for the lanes to go idle, and holds back the lanes'
+        // other PRs until it has landed
+        pulls.extend(
+            laned_pulls(&config, &[(2, None), (3, Some("github"))])
+                .values()
+                .cloned(),
+        );
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_eq!(queue.head(), None);
+        assert!(queue.lanes["github"].is_idle());
+
+        git.set_up_to_date(merge_oid(&pulls, 1));
+        report(&mut pulls, 1, "ci", true);
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(pulls.get(&1).is_none());
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert_eq!(queue.head(), Some(2));
+        assert!(queue.lanes["github"].is_idle());
+        assert!(pulls[&3].status.is_queued());
+    }
+
+    #[tokio::test]
+    async fn lane_retests_merge_rebuilt_on_moved_base() {
+        let config = repo_config(LANES);
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = laned_pulls(&config, &[(1, Some("docs"))]);
+        let base = pulls[&1].base_ref_name.clone();
+        let landed = format!("repos/bors-rs/bors/git/refs/heads/{}", base);
+        let mut queue = MergeQueue::new();
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        let tested = merge_oid(&pulls, 1);
+
+        // Another lane landed while the PR was tested, so the tested commits are replayed on
+        // top of it and tested again instead of landing
+        report(&mut pulls, 1, "ci", true);
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        let rebuilt = merge_oid(&pulls, 1);
+        assert_ne!(rebuilt, tested);
+        assert_eq!(queue.lanes["docs"].head(), Some(1));
+        assert!(github.bodies(Method::PATCH, &landed).is_empty());
+        assert!(git.operations().contains(&GitOperation::Rebase {
+            base_ref: base,
+            head_oid: tested,
+            branch: "auto-docs".into(),
+            squash: Squash::None,
+        }));
+        assert_eq!(pushed_branches(&git), ["auto-docs", "auto-docs"]);
+        assert_eq!(
+            github.statuses("bors-rs", "bors", &head_sha(1)),
+            ["pending", "pending"]
+        );
+
+        git.set_up_to_date(rebuilt.clone());
+        report(&mut pulls, 1, "ci", true);
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(pulls.get(&1).is_none());
+        assert_eq!(
+            github.bodies(Method::PATCH, &landed),
+            [json!({ "sha": rebuilt.to_string(), "force": false })]
+        );
+    }
+
+    #[tokio::test]
+    async fn timed_out_canary_is_canceled() {
+        let config = repo_config("checks = [\"ci\"]\ncanary-timeout-seconds = 60");
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = queued_pulls(&config, &[1]);
+        let mut pull = pulls.get_mut(&1).unwrap();
+        pull.status = Status::Canary {
+            merge_oid: Oid::from_str("c".repeat(40)),
+            tests_started_at: Instant::now() - Duration::from_secs(120),
+            test_results: HashMap::new(),
+        };
+        pull.canary_requester = Some("alice".to_owned());
+        pull.labels.insert(config.labels().canary().to_owned());
+        drop(pull);
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        let pull = &pulls[&1];
+        assert!(pull.status.is_in_review());
+        assert!(pull.canary_requester.is_none());
+        assert!(!pull.has_label(config.labels().canary()));
+        assert_eq!(github.statuses("bors-rs", "bors", &head_sha(1)), ["error"]);
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(
+            comments[0].starts_with("@alice :hourglass:"),
+            "{}",
+            comments[0]
+        );
+        assert!(git.operations().is_empty());
+
+        // The canary branch is free for the next canary
+        pulls.get_mut(&1).unwrap().canary_requested = true;
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(pulls[&1].status.is_canary());
+        assert_eq!(
+            git.operations().last(),
+            Some(&GitOperation::PushBranch(CANARY_BRANCH.into()))
+        );
+    }
+
+    /// PR #1 in review, with a canary requested by `alice` while check `ci` on its head has
+    /// `passed`, or is still pending
+    fn canary_requested(config: &RepoConfig, passed: Option<bool>) -> PullRequests {
+        let mut pulls = queued_pulls(config, &[1]);
+        let mut pull = pulls.get_mut(&1).unwrap();
+        pull.status = Status::InReview;
+        pull.canary_requested = true;
+        pull.canary_requester = Some("alice".to_owned());
+        pull.labels.insert(config.labels().canary().to_owned());
+        if let Some(passed) = passed {
+            pull.head_test_results.insert(
+                "ci".to_owned(),
+                TestResult {
+                    passed,
+                    details_url: "https://ci.example.com/1".to_owned(),
+                    source: BuildResultSource::CommitStatus {
+                        context: "ci".to_owned(),
+                    },
+                },
+            );
+        }
+        drop(pull);
+        pulls
+    }
+
+    #[tokio::test]
+    async fn canary_waits_for_head_checks() {
+        let config = repo_config("checks = [\"ci\"]\ncanary-head-checks = \"passed\"");
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = canary_requested(&config, None);
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(pulls[&1].status.is_in_review());
+        assert!(pulls[&1].canary_requested);
+        assert!(git.operations().is_empty());
+
+        pulls = canary_requested(&config, Some(true));
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(pulls[&1].status.is_canary());
+        assert_eq!(
+            git.operations().last(),
+            Some(&GitOperation::PushBranch(CANARY_BRANCH.into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn canary_is_canceled_by_failed_head_check() {
+        let config = repo_config("checks = [\"ci\"]\ncanary-head-checks = \"no-failures\"");
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = canary_requested(&config, Some(false));
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        let pull = &pulls[&1];
+        assert!(pull.status.is_in_review());
+        assert!(!pull.canary_requested);
+        assert!(pull.canary_requester.is_none());
+        assert!(!pull.has_label(config.labels().canary()));
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(
+            comments[0].starts_with("@alice :exclamation: Canary canceled since check [ci]"),
+            "{}",
+            comments[0]
+        );
+        assert!(git.operations().is_empty());
+    }
+
+    #[tokio::test]
+    async fn canary_ignoring_head_checks_starts_immediately() {
+        let config = repo_config("checks = [\"ci\"]\ncanary-head-checks = \"ignore\"");
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = canary_requested(&config, None);
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(pulls[&1].status.is_canary());
+        assert!(!pulls[&1].canary_requested);
+        assert_eq!(
+            git.operations().last(),
+            Some(&GitOperation::PushBranch(CANARY_BRANCH.into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_approval_is_dequeued() {
+        let config = repo_config("require-review = true\napproval-max-age-days = 2");
+        let github = FakeGithub::start();
+        let client = github.client();
+        let mut git = MockGitBackend::new("bors");
+        let mut pulls = queued_pulls(&config, &[1]);
+        let mut pull = pulls.get_mut(&1).unwrap();
+        pull.approved = true;
+        pull.approved_at = Some(github::DateTime::from(
+            std::time::SystemTime::now() - Duration::from_secs(3 * 24 * 60 * 60),
+        ));
+        drop(pull);
+        let mut queue = MergeQueue::new();
+
+        queue
+            .process_queue(&config, &client, &mut git, None, &mut pulls)
+            .await
+            .unwrap();
+        assert!(queue.is_idle());
+        assert!(matches!(pulls[&1].status, Status::InReview));
+        assert!(git.operations().is_empty());
+        let comments = github.comments("bors-rs", "bors", 1);
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0].starts_with(":hourglass:"), "{}", comments[0]);
+    }
+}
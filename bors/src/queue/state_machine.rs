@@ -0,0 +1,309 @@
+//! The states that the head of the merge queue moves through while landing a PR
+//!
+//! ```text
+//!                  Started             MergeCreated           TestsPassed          Landed
+//!         Idle ------------> Preparing ------------> Testing ------------> Landing -------> Done
+//!          ^                     |                      |                     |               |
+//!          |                     | MergeFailed          | TestsFailed         | LandFailed    |
+//!          |                     v                      v                     v               |
+//!          |                   Failed <-----------------+---------------------+               |
+//!          |                     |                                                            |
+//!          +---------------------+------------------------------------------------------------+
+//!                                                  Finished
+//! ```
+//!
+//! A PR that is closed or canceled while being tested is `Removed`, returning the queue to `Idle`.
+//! Likewise a PR being tested is `Parked`, and re-queued, when a hotfix preempts it. A lane's PR
+//! whose base moved while it was tested has its merge commit rebuilt and goes back to `Testing`.
+//! If an error interrupts `Preparing` or `Landing`, the queue is `Aborted` back to a state from
+//! which it can be retried.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueState {
+    /// No PR is being landed
+    Idle,
+    /// A merge commit is being created for the PR and pushed for testing
+    Preparing(u64),
+    /// The PR's merge commit is being tested
+    Testing(u64),
+    /// The PR's merge commit passed testing and is being pushed to its base branch
+    Landing(u64),
+    /// The PR landed
+    Done(u64),
+    /// The PR couldn't be landed and has been removed from the queue
+    Failed(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueEvent {
+    /// The PR was taken from the front of the queue
+    Started(u64),
+    MergeCreated,
+    /// A merge commit couldn't be created, e.g. due to a merge conflict
+    MergeFailed,
+    TestsPassed,
+    /// Tests failed or timed-out
+    TestsFailed,
+    /// Tests of a batch of PRs failed or timed-out, returning them to the queue to be tested in
+    /// smaller batches
+    BatchFailed,
+    Landed,
+    LandFailed,
+    /// The base of a lane's PR moved while it was tested, so its merge commit was rebuilt on top
+    /// of it and is tested again
+    MergeRebuilt,
+    /// The PR being tested was closed, its land was canceled or a PR tested along with it left
+    /// its batch
+    Removed,
+    /// The PR being tested was returned to the queue to make way for a hotfix
+    Parked,
+    /// The outcome of the PR has been handled
+    Finished,
+    /// An error interrupted the current operation
+    Aborted,
+    /// The queue was reset, e.g. when re-synchronizing with Github
+    Reset,
+}
+
+#[derive(Debug)]
+pub struct InvalidTransition {
+    pub state: QueueState,
+    pub event: QueueEvent,
+}
+
+impl fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid merge queue transition: {:?} in state {:?}",
+            self.event, self.state
+        )
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+impl QueueState {
+    /// The PR at the head of the queue, if any
+    pub fn pull(&self) -> Option<u64> {
+        match *self {
+            QueueState::Idle => None,
+            QueueState::Preparing(n)
+            | QueueState::Testing(n)
+            | QueueState::Landing(n)
+            | QueueState::Done(n)
+            | QueueState::Failed(n) => Some(n),
+        }
+    }
+
+    pub fn transition(self, event: QueueEvent) -> Result<Self, InvalidTransition> {
+        use QueueEvent::*;
+        use QueueState::*;
+
+        let next = match (self, event) {
+            (_, Reset) => Idle,
+
+            (Idle, Started(n)) => Preparing(n),
+
+            (Preparing(n), MergeCreated) => Testing(n),
+            (Preparing(n), MergeFailed) => Failed(n),
+            // The PR is still queued so it will be prepared again
+            (Preparing(_), Aborted) => Idle,
+
+            (Testing(n), TestsPassed) => Landing(n),
+            (Testing(n), TestsFailed) => Failed(n),
+            (Testing(_), BatchFailed) => Idle,
+            (Testing(_), Removed) | (Testing(_), Parked) => Idle,
+            // Test results are re-evaluated the next time the queue is processed
+            (Testing(n), Aborted) => Testing(n),
+
+            (Landing(n), Landed) => Done(n),
+            (Landing(n), LandFailed) => Failed(n),
+            (Landing(n), MergeRebuilt) => Testing(n),
+            // The tests have already passed so landing will be retried
+            (Landing(n), Aborted) => Testing(n),
+
+            (Done(_), Finished) | (Failed(_), Finished) => Idle,
+            (Done(_), Aborted) | (Failed(_), Aborted) => Idle,
+
+            (Idle, Aborted) => Idle,
+
+            (state, event) => return Err(InvalidTransition { state, event }),
+        };
+
+        Ok(next)
+    }
+}
+
+/// Only `Idle` and `Testing` persist between processing the queue, so the state is serialized as
+/// the PR being tested to remain compatible with snapshots taken before the state machine existed
+impl Serialize for QueueState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            QueueState::Preparing(n) | QueueState::Testing(n) | QueueState::Landing(n) => Some(*n),
+            QueueState::Idle | QueueState::Done(_) | QueueState::Failed(_) => None,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for QueueState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<u64>::deserialize(deserializer)? {
+            Some(n) => QueueState::Testing(n),
+            None => QueueState::Idle,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{QueueEvent, QueueEvent::*, QueueState, QueueState::*};
+    use github::test_fixtures::Rng;
+
+    fn run(events: &[QueueEvent]) -> QueueState {
+        events
+            .iter()
+            .try_fold(Idle, |state, event| state.transition(*event))
+            .unwrap()
+    }
+
+    #[test]
+    fn land() {
+        assert_eq!(run(&[Started(1)]), Preparing(1));
+        assert_eq!(run(&[Started(1), MergeCreated]), Testing(1));
+        assert_eq!(run(&[Started(1), MergeCreated, TestsPassed]), Landing(1));
+        assert_eq!(
+            run(&[Started(1), MergeCreated, TestsPassed, Landed]),
+            Done(1)
+        );
+        assert_eq!(
+            run(&[Started(1), MergeCreated, TestsPassed, Landed, Finished]),
+            Idle
+        );
+        assert_eq!(
+            run(&[Started(1), MergeCreated, TestsPassed, MergeRebuilt]),
+            Testing(1)
+        );
+    }
+
+    #[test]
+    fn failures() {
+        assert_eq!(run(&[Started(1), MergeFailed]), Failed(1));
+        assert_eq!(run(&[Started(1), MergeCreated, TestsFailed]), Failed(1));
+        assert_eq!(
+            run(&[Started(1), MergeCreated, TestsPassed, LandFailed]),
+            Failed(1)
+        );
+        assert_eq!(run(&[Started(1), MergeFailed, Finished]), Idle);
+        assert_eq!(run(&[Started(1), MergeCreated, Removed]), Idle);
+        assert_eq!(run(&[Started(1), MergeCreated, Parked]), Idle);
+    }
+
+    #[test]
+    fn aborts() {
+        assert_eq!(run(&[Aborted]), Idle);
+        assert_eq!(run(&[Started(1), Aborted]), Idle);
+        assert_eq!(run(&[Started(1), MergeCreated, Aborted]), Testing(1));
+        assert_eq!(
+            run(&[Started(1), MergeCreated, TestsPassed, Aborted]),
+            Testing(1)
+        );
+        assert_eq!(run(&[Started(1), MergeFailed, Aborted]), Idle);
+    }
+
+    #[test]
+    fn invalid_transitions() {
+        assert!(Idle.transition(MergeCreated).is_err());
+        assert!(Idle.transition(Landed).is_err());
+        assert!(Preparing(1).transition(Started(2)).is_err());
+        assert!(Testing(1).transition(Started(2)).is_err());
+        assert!(Testing(1).transition(Landed).is_err());
+        assert!(Landing(1).transition(Removed).is_err());
+        assert!(Done(1).transition(Started(2)).is_err());
+    }
+
+    #[test]
+    fn serialization() {
+        for (state, json) in [
+            (Idle, "null"),
+            (Testing(3), "3"),
+            (Done(3), "null"),
+            (Failed(3), "null"),
+        ] {
+            assert_eq!(serde_json::to_string(&state).unwrap(), json);
+        }
+        assert_eq!(serde_json::from_str::<QueueState>("3").unwrap(), Testing(3));
+        assert_eq!(serde_json::from_str::<QueueState>("null").unwrap(), Idle);
+    }
+
+    fn random_event(rng: &mut Rng) -> QueueEvent {
+        const EVENTS: &[QueueEvent] = &[
+            MergeCreated,
+            MergeFailed,
+            TestsPassed,
+            TestsFailed,
+            BatchFailed,
+            Landed,
+            LandFailed,
+            MergeRebuilt,
+            Removed,
+            Parked,
+            Finished,
+            Aborted,
+            Reset,
+        ];
+
+        match rng.below(EVENTS.len() + 1) {
+            0 => Started(1 + rng.below(3) as u64),
+            n => EVENTS[n - 1],
+        }
+    }
+
+    #[test]
+    fn random_event_orderings() {
+        for seed in 1..=500 {
+            let mut rng = Rng::new(seed);
+            let mut state = Idle;
+
+            for _ in 0..64 {
+                let event = random_event(&mut rng);
+                let next = match state.transition(event) {
+                    Ok(next) => next,
+                    Err(e) => {
+                        // Invalid transitions are rejected without changing the state
+                        assert_eq!(e.state, state, "seed {}", seed);
+                        continue;
+                    }
+                };
+
+                // A PR stays at the head of the queue until it is done with, and a new PR can
+                // only be started once the queue is idle
+                if let (Some(before), Some(after)) = (state.pull(), next.pull()) {
+                    assert_eq!(before, after, "seed {}", seed);
+                }
+                if let Started(n) = event {
+                    assert_eq!((state, next), (Idle, Preparing(n)), "seed {}", seed);
+                }
+
+                // A PR is only tested once its merge commit is created and can only land once its
+                // tests have passed
+                let valid = match next {
+                    Testing(_) => matches!(state, Preparing(_) | Testing(_) | Landing(_)),
+                    Landing(_) => matches!(state, Testing(_)),
+                    Done(_) => matches!(state, Landing(_)),
+                    _ => true,
+                };
+                assert!(valid, "seed {}: {:?} -> {:?}", seed, state, next);
+
+                state = next;
+            }
+
+            // The queue can always be returned to idle
+            assert_eq!(state.transition(Reset).unwrap(), Idle);
+        }
+    }
+}
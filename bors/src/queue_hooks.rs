@@ -0,0 +1,278 @@
+//! Outbound webhooks notifying external systems of the lifecycle of PRs in the merge queue
+//!
+//! Each of a repo's `queue-hooks` is sent a json `POST` when a PR is queued, starts testing,
+//! lands, fails or is removed from the queue, so that e.g. deployment pipelines can react without
+//! polling the status API. The event is named by the `X-Bors-Event` header and, when the hook has
+//! a secret, the body is signed like Github signs its webhooks, as an HMAC-SHA256 hexdigest in the
+//! `X-Bors-Signature-256` header.
+//!
+//! Changes of a PR's status are recorded on the PR as they happen and sent once the event
+//! processor is done with the request which caused them. Deliveries are made in order by a task of
+//! their own so that a slow or unreachable receiver never holds up the queue.
+
+use crate::{
+    config::{GithubConfig, QueueHook, RepoConfig},
+    state::{PullRequestState, Status},
+    Result,
+};
+use futures::{channel::mpsc, StreamExt};
+use github::{
+    signature::{Algorithm, Signature},
+    DateTime,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The header naming the event a delivery is for
+pub const EVENT_HEADER: &str = "X-Bors-Event";
+
+/// The header carrying the HMAC-SHA256 signature of a delivery's body
+pub const SIGNATURE_256_HEADER: &str = "X-Bors-Signature-256";
+
+/// Number of times a delivery is attempted before it's dropped
+const DELIVERY_ATTEMPTS: u32 = 3;
+
+/// Time waited before retrying a failed delivery, doubled after each attempt
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueHookEvent {
+    Queued,
+    TestingStarted,
+    Landed,
+    Failed,
+    /// The PR left the queue without landing, e.g. because its land was canceled, it was updated
+    /// or it was closed
+    Cancelled,
+}
+
+impl QueueHookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QueueHookEvent::Queued => "queued",
+            QueueHookEvent::TestingStarted => "testing_started",
+            QueueHookEvent::Landed => "landed",
+            QueueHookEvent::Failed => "failed",
+            QueueHookEvent::Cancelled => "cancelled",
+        }
+    }
+
+    /// The event a PR's status changing from `from` to `to` amounts to, if any. Landing isn't a
+    /// status, it's reported by the merge queue itself
+    pub fn of_transition(from: &Status, to: &Status) -> Option<Self> {
+        let was_in_queue = from.is_queued() || from.is_testing();
+        match to {
+            // A PR being tested is returned to the queue when a hotfix preempts it
+            Status::Queued(_) if !was_in_queue => Some(QueueHookEvent::Queued),
+            Status::Testing { .. } if !from.is_testing() => Some(QueueHookEvent::TestingStarted),
+            Status::Failed { .. } if was_in_queue => Some(QueueHookEvent::Failed),
+            Status::InReview if was_in_queue => Some(QueueHookEvent::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// The body of a delivery
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QueueHookPayload {
+    pub event: QueueHookEvent,
+    /// The repo, as `owner/name`
+    pub repository: String,
+    pub number: u64,
+    pub title: String,
+    pub head_sha: String,
+    pub base_ref: String,
+    /// The merge commit being tested or landed
+    pub merge_sha: Option<String>,
+    /// Why the PR failed or was removed from the queue, when known
+    pub reason: Option<String>,
+    pub timestamp: DateTime,
+}
+
+impl QueueHookPayload {
+    pub fn new(event: QueueHookEvent, config: &RepoConfig, pull: &PullRequestState) -> Self {
+        let (merge_sha, reason) = match &pull.status {
+            Status::Testing { merge_oid, .. } => (Some(merge_oid.to_string()), None),
+            Status::Failed { reason, .. } => (None, Some(reason.clone())),
+            _ => (None, None),
+        };
+
+        Self {
+            event,
+            repository: config.repo().to_string(),
+            number: pull.number,
+            title: pull.title.clone(),
+            head_sha: pull.head_ref_oid.to_string(),
+            base_ref: pull.base_ref_name.clone(),
+            merge_sha,
+            reason,
+            timestamp: DateTime::now(),
+        }
+    }
+
+    pub fn with_reason<R: Into<String>>(self, reason: R) -> Self {
+        Self {
+            reason: Some(reason.into()),
+            ..self
+        }
+    }
+}
+
+/// Handle to the task delivering a repo's queue events. Events are dropped if the repo has no
+/// hooks configured
+#[derive(Clone, Debug, Default)]
+pub struct QueueHooks {
+    sender: Option<mpsc::UnboundedSender<QueueHookPayload>>,
+}
+
+impl QueueHooks {
+    /// Start the task delivering events to the hooks configured for the repo, if there are any
+    pub fn spawn(config: &RepoConfig, github_config: &GithubConfig) -> Result<Self> {
+        if config.queue_hooks().is_empty() {
+            return Ok(Self::default());
+        }
+
+        let client = github_config
+            .http_client_builder()?
+            .timeout(DELIVERY_TIMEOUT)
+            .build()?;
+        let (tx, rx) = mpsc::unbounded();
+        tokio::spawn(deliver(client, config.queue_hooks().to_vec(), rx));
+
+        Ok(Self { sender: Some(tx) })
+    }
+
+    pub fn emit(&self, payload: QueueHookPayload) {
+        if let Some(sender) = &self.sender {
+            if sender.unbounded_send(payload).is_err() {
+                warn!("queue hooks are no longer being delivered");
+            }
+        }
+    }
+}
+
+async fn deliver(
+    client: reqwest::Client,
+    hooks: Vec<QueueHook>,
+    mut payloads: mpsc::UnboundedReceiver<QueueHookPayload>,
+) {
+    while let Some(payload) = payloads.next().await {
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("unable to serialize queue event: {}", e);
+                continue;
+            }
+        };
+
+        for hook in hooks.iter().filter(|hook| hook.wants(payload.event)) {
+            let mut delay = RETRY_DELAY;
+            for attempt in 1..=DELIVERY_ATTEMPTS {
+                match deliver_once(&client, hook, payload.event, &body).await {
+                    Ok(()) => {
+                        info!(
+                            "{} - delivered '{}' event of pr #{} to {}",
+                            payload.repository,
+                            payload.event.as_str(),
+                            payload.number,
+                            hook.url()
+                        );
+                        break;
+                    }
+                    Err(e) => {
+                        warn!(
+                            "{} - delivering '{}' event of pr #{} to {} failed (attempt {}/{}): {:#}",
+                            payload.repository,
+                            payload.event.as_str(),
+                            payload.number,
+                            hook.url(),
+                            attempt,
+                            DELIVERY_ATTEMPTS,
+                            e
+                        );
+                        if attempt < DELIVERY_ATTEMPTS {
+                            tokio::time::sleep(delay).await;
+                            delay *= 2;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn deliver_once(
+    client: &reqwest::Client,
+    hook: &QueueHook,
+    event: QueueHookEvent,
+    body: &[u8],
+) -> Result<()> {
+    let mut request = client
+        .post(hook.url())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(reqwest::header::USER_AGENT, "bors")
+        .header(EVENT_HEADER, event.as_str());
+    if let Some(secret) = hook.secret() {
+        let signature = Signature::new(Algorithm::Sha256, secret.as_bytes(), body);
+        request = request.header(SIGNATURE_256_HEADER, signature.to_string());
+    }
+
+    request
+        .body(body.to_vec())
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::QueueHookEvent;
+    use crate::state::Status;
+    use github::Oid;
+
+    #[test]
+    fn transitions() {
+        let testing = || Status::testing(Oid::from_str("abc"));
+
+        assert_eq!(
+            QueueHookEvent::of_transition(&Status::InReview, &Status::queued()),
+            Some(QueueHookEvent::Queued)
+        );
+        assert_eq!(
+            QueueHookEvent::of_transition(&Status::queued(), &testing()),
+            Some(QueueHookEvent::TestingStarted)
+        );
+        assert_eq!(
+            QueueHookEvent::of_transition(&testing(), &Status::failed("merge conflict", None)),
+            Some(QueueHookEvent::Failed)
+        );
+        assert_eq!(
+            QueueHookEvent::of_transition(&Status::queued(), &Status::InReview),
+            Some(QueueHookEvent::Cancelled)
+        );
+
+        // Parked to make way for a hotfix
+        assert_eq!(
+            QueueHookEvent::of_transition(&testing(), &Status::queued()),
+            None
+        );
+        // A failure expiring
+        assert_eq!(
+            QueueHookEvent::of_transition(
+                &Status::failed("merge conflict", None),
+                &Status::InReview
+            ),
+            None
+        );
+        // Canaries aren't part of the queue
+        assert_eq!(
+            QueueHookEvent::of_transition(&Status::canary(Oid::from_str("abc")), &Status::InReview),
+            None
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Secrets referenced from the config
+//!
+//! A secret is either given inline or as a reference which is resolved when the config is loaded:
+//!
+//! - `env:VAR` reads the environment variable `VAR`
+//! - `file:/path/to/secret` reads the file, ignoring trailing whitespace
+//! - `keyring:service/account` looks the secret up in the OS keyring, via `security` on macOS and
+//!   `secret-tool` elsewhere
+//!
+//! Resolved secrets are never revealed by `Debug` output or when the config is serialized.
+
+use crate::Result;
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{convert::TryFrom, fmt, fs, process::Command};
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Secret(String);
+
+impl Secret {
+    /// A secret with the literal value `secret`
+    pub fn new<S: Into<String>>(secret: S) -> Self {
+        Self(secret.into())
+    }
+
+    /// Resolve `reference` to the secret it refers to
+    pub fn resolve(reference: &str) -> Result<Self> {
+        let secret = if let Some(var) = reference.strip_prefix("env:") {
+            std::env::var(var).with_context(|| format!("unable to read secret from ${}", var))?
+        } else if let Some(path) = reference.strip_prefix("file:") {
+            fs::read_to_string(path)
+                .with_context(|| format!("unable to read secret from '{}'", path))?
+                .trim_end()
+                .to_owned()
+        } else if let Some(entry) = reference.strip_prefix("keyring:") {
+            let (service, account) = entry.split_once('/').ok_or_else(|| {
+                anyhow!("keyring secrets are referenced as 'keyring:service/account'")
+            })?;
+            keyring_lookup(service, account)
+                .with_context(|| format!("unable to read secret '{}' from the keyring", entry))?
+        } else {
+            reference.to_owned()
+        };
+
+        Ok(Self(secret))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Secret {
+    type Error = anyhow::Error;
+
+    fn try_from(reference: String) -> Result<Self> {
+        Self::resolve(&reference)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn keyring_command(service: &str, account: &str) -> Command {
+    let mut command = Command::new("security");
+    command.args(["find-generic-password", "-s", service, "-a", account, "-w"]);
+    command
+}
+
+#[cfg(not(target_os = "macos"))]
+fn keyring_command(service: &str, account: &str) -> Command {
+    let mut command = Command::new("secret-tool");
+    command.args(["lookup", "service", service, "account", account]);
+    command
+}
+
+fn keyring_lookup(service: &str, account: &str) -> Result<String> {
+    let output = keyring_command(service, account).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "keyring lookup failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8(output.stdout)?.trim_end().to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolve() {
+        assert_eq!(Secret::resolve("hunter2").unwrap().expose(), "hunter2");
+
+        std::env::set_var("BORS_TEST_SECRET", "from-env");
+        assert_eq!(
+            Secret::resolve("env:BORS_TEST_SECRET").unwrap().expose(),
+            "from-env"
+        );
+        assert!(Secret::resolve("env:BORS_TEST_MISSING_SECRET").is_err());
+
+        let path = std::env::temp_dir().join(format!("bors-test-secret-{}", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+        let secret = Secret::resolve(&format!("file:{}", path.display())).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(secret.expose(), "from-file");
+
+        assert!(Secret::resolve("keyring:no-account").is_err());
+        assert_eq!(format!("{:?}", secret), REDACTED);
+    }
+}
@@ -0,0 +1,14 @@
+//! Authentication of requests to the routes which change bors' state
+
+use crate::secret::Secret;
+use github::signature::constant_time_eq;
+use hyper::{header::AUTHORIZATION, HeaderMap};
+
+/// Whether `headers` carry `secret` as a bearer token, compared in constant time so that the
+/// secret can't be guessed byte by byte
+pub(super) fn has_bearer_token(headers: &HeaderMap, secret: &Secret) -> bool {
+    let expected = format!("Bearer {}", secret.expose());
+    headers
+        .get(AUTHORIZATION)
+        .is_some_and(|value| constant_time_eq(value.as_bytes(), expected.as_bytes()))
+}
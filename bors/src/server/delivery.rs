@@ -0,0 +1,208 @@
+//! Parsing of the headers and body of a webhook delivery into a `Webhook`
+//!
+//! Each step is exposed on its own so that `verify-webhook` can report on every one of them,
+//! rather than just the first to fail, while checking a delivery exactly the way the server does.
+//! Deliveries relayed by smee are turned back into headers and go through the same steps.
+//!
+//! Deliveries are deduped in memory and, when several instances ingest webhooks, also against a
+//! directory shared between them.
+
+use crate::Result;
+use anyhow::{anyhow, Context};
+use github::{
+    EventType, Webhook, DELIVERY_ID_HEADER, EVENT_TYPE_HEADER, SIGNATURE_256_HEADER,
+    SIGNATURE_HEADER,
+};
+use hyper::{
+    header::{HeaderValue, CONTENT_TYPE},
+    HeaderMap,
+};
+use log::warn;
+use std::{
+    collections::{HashSet, VecDeque},
+    fs, io,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime},
+};
+
+/// Number of delivery ids remembered to recognize deliveries which were already handled
+const RECENT_DELIVERIES: usize = 1000;
+
+/// How long a delivery recorded in a shared deliveries directory is remembered. Github only
+/// redelivers on request, so this just has to outlast the deliveries someone might redeliver
+const SHARED_DELIVERY_RETENTION: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+/// Check that the delivery carries a json payload, the only kind Github is configured to send
+pub(crate) fn check_content_type(headers: &HeaderMap) -> Result<()> {
+    match headers.get(CONTENT_TYPE).map(HeaderValue::to_str) {
+        Some(Ok("application/json")) => Ok(()),
+        Some(Ok(content_type)) => Err(anyhow!("unknown content type '{}'", content_type)),
+        _ => Err(anyhow!("unknown content type")),
+    }
+}
+
+pub(crate) fn event_type(headers: &HeaderMap) -> Result<EventType> {
+    headers
+        .get(EVENT_TYPE_HEADER)
+        .and_then(|h| HeaderValue::to_str(h).ok())
+        .and_then(|s| s.parse::<EventType>().ok())
+        .ok_or_else(|| anyhow!("missing valid X-GitHub-Event header"))
+}
+
+pub(crate) fn delivery_id(headers: &HeaderMap) -> Result<String> {
+    headers
+        .get(DELIVERY_ID_HEADER)
+        .and_then(|h| HeaderValue::to_str(h).ok())
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| anyhow!("missing valid X-GitHub-Delivery header"))
+}
+
+/// Every value of the signature headers, so that duplicates are rejected rather than all but one
+/// being ignored
+pub(crate) fn signatures(headers: &HeaderMap) -> Result<Vec<String>> {
+    [SIGNATURE_HEADER, SIGNATURE_256_HEADER]
+        .iter()
+        .flat_map(|header| headers.get_all(*header))
+        .map(|h| HeaderValue::to_str(h).map(ToOwned::to_owned))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| anyhow!("invalid signature header"))
+}
+
+/// Build the webhook delivered with `headers` and `body`. Its signature still has to be checked
+pub(crate) fn webhook_from_parts(headers: &HeaderMap, body: Vec<u8>) -> Result<Webhook> {
+    check_content_type(headers)?;
+
+    Ok(Webhook {
+        event_type: event_type(headers)?,
+        delivery_id: delivery_id(headers)?,
+        signatures: signatures(headers)?,
+        body,
+    })
+}
+
+/// The ids of the most recently handled deliveries, so that a delivery received more than once,
+/// e.g. redelivered by Github or relayed by smee as well as delivered to the webhook endpoint, is
+/// only handled once
+#[derive(Debug, Default)]
+pub(crate) struct RecentDeliveries {
+    /// Ids from the least to the most recent
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+}
+
+impl RecentDeliveries {
+    /// Record `delivery_id` as handled, returning `false` if it already was. The least recent id
+    /// is forgotten once `RECENT_DELIVERIES` are remembered
+    pub(crate) fn insert(&mut self, delivery_id: &str) -> bool {
+        if self.ids.contains(delivery_id) {
+            return false;
+        }
+
+        if self.order.len() == RECENT_DELIVERIES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.order.push_back(delivery_id.to_owned());
+        self.ids.insert(delivery_id.to_owned());
+        true
+    }
+
+    /// Forget `delivery_id`, e.g. because it couldn't be handled and should be accepted again
+    /// once redelivered
+    pub(crate) fn remove(&mut self, delivery_id: &str) {
+        if self.ids.remove(delivery_id) {
+            self.order.retain(|id| id != delivery_id);
+        }
+    }
+
+    /// The ids remembered, from the least to the most recent
+    pub(crate) fn ids(&self) -> impl Iterator<Item = &str> {
+        self.order.iter().map(String::as_str)
+    }
+}
+
+/// Deliveries handled by any of the instances sharing `dir`, so that a delivery is only handled
+/// once when webhooks are ingested by several instances behind a load balancer. Each delivery is
+/// recorded as an empty file named after its id, created atomically so that only one instance
+/// wins when the same delivery reaches two of them at once
+#[derive(Debug)]
+pub(crate) struct SharedDeliveries {
+    dir: PathBuf,
+    inserted: AtomicUsize,
+}
+
+impl SharedDeliveries {
+    /// Deliveries recorded in `dir`, which must already exist
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            inserted: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record `delivery_id` as handled, returning `false` if it already was by any instance.
+    /// Deliveries older than `SHARED_DELIVERY_RETENTION` are pruned every `RECENT_DELIVERIES`
+    /// inserts
+    pub(crate) fn insert(&self, delivery_id: &str) -> Result<bool> {
+        let path = self.path(delivery_id)?;
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(false),
+            Err(e) => {
+                return Err(e).with_context(|| format!("recording delivery in {}", path.display()))
+            }
+        }
+
+        if self.inserted.fetch_add(1, Ordering::Relaxed) % RECENT_DELIVERIES == 0 {
+            if let Err(e) = self.prune(SystemTime::now()) {
+                warn!("Unable to prune {}: {:#}", self.dir.display(), e);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Forget `delivery_id`, so that any instance accepts it again once redelivered
+    pub(crate) fn remove(&self, delivery_id: &str) -> Result<()> {
+        match fs::remove_file(self.path(delivery_id)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove the deliveries recorded more than `SHARED_DELIVERY_RETENTION` before `now`
+    fn prune(&self, now: SystemTime) -> Result<()> {
+        // Other instances may be pruning the same deliveries, so those which vanish are skipped
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            let recorded = match entry.metadata().and_then(|m| m.modified()) {
+                Ok(recorded) => recorded,
+                Err(_) => continue,
+            };
+            if now
+                .duration_since(recorded)
+                .is_ok_and(|age| age > SHARED_DELIVERY_RETENTION)
+            {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+        Ok(())
+    }
+
+    /// The file recording `delivery_id`. Ids are GUIDs, anything else is rejected rather than
+    /// being allowed to name a path outside of `dir`
+    fn path(&self, delivery_id: &str) -> Result<PathBuf> {
+        if delivery_id.is_empty()
+            || !delivery_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(anyhow!("invalid delivery id '{}'", delivery_id));
+        }
+        Ok(self.dir.join(delivery_id))
+    }
+}
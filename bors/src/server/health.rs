@@ -0,0 +1,247 @@
+//! Health and readiness checks, e.g. for Kubernetes liveness and readiness probes
+//!
+//! `/healthz` reports whether bors is alive, i.e. whether every installation's event processor is
+//! still running, while `/readyz` additionally checks everything bors depends on in order to
+//! process webhooks. Both respond with a json report, and `503 Service Unavailable` if any check
+//! failed.
+
+use super::Installation;
+use crate::{event_processor::SyncStatus, git, graphql::GithubClient, store};
+use futures::future::join_all;
+use hyper::{header::CONTENT_TYPE, Body, Response, StatusCode};
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+/// How long to wait for Github to respond before considering it unreachable
+const GITHUB_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Readiness fails once fewer than this many core API requests remain before the rate limit resets
+const MIN_RATE_LIMIT_REMAINING: usize = 50;
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, result: Result<Option<String>, String>) -> Self {
+        match result {
+            Ok(detail) => Self {
+                name,
+                ok: true,
+                detail,
+            },
+            Err(detail) => Self {
+                name,
+                ok: false,
+                detail: Some(detail),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct InstallationReport {
+    repo: String,
+    ok: bool,
+    checks: Vec<Check>,
+}
+
+impl InstallationReport {
+    fn new(installation: &Installation, checks: Vec<Check>) -> Self {
+        Self {
+            repo: installation.config().repo().to_string(),
+            ok: checks.iter().all(|c| c.ok),
+            checks,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    ok: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checks: Vec<Check>,
+    installations: Vec<InstallationReport>,
+}
+
+impl HealthReport {
+    fn new(checks: Vec<Check>, installations: Vec<InstallationReport>) -> Self {
+        Self {
+            ok: checks.iter().all(|c| c.ok) && installations.iter().all(|i| i.ok),
+            checks,
+            installations,
+        }
+    }
+
+    pub fn into_response(self) -> Response<Body> {
+        let status = if self.ok {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+
+        Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_string_pretty(&self).unwrap()))
+            .unwrap()
+    }
+}
+
+fn event_processor_check(installation: &Installation) -> Check {
+    Check::new(
+        "event-processor",
+        if installation.event_processor().is_closed() {
+            Err("stopped".to_owned())
+        } else {
+            Ok(None)
+        },
+    )
+}
+
+/// Check that every installation's event processor is still running
+pub fn liveness(installations: &[Installation]) -> HealthReport {
+    let installations = installations
+        .iter()
+        .map(|i| InstallationReport::new(i, vec![event_processor_check(i)]))
+        .collect();
+
+    HealthReport::new(Vec::new(), installations)
+}
+
+/// Check that Github is reachable and that every installation is able to process webhooks
+pub async fn readiness(github: &GithubClient, installations: &[Installation]) -> HealthReport {
+    let checks = vec![github_check(github).await];
+
+    let installations = join_all(installations.iter().map(|installation| async move {
+        let mut checks = vec![
+            event_processor_check(installation),
+            Check::new(
+                "api-token",
+                if installation.auth_health().is_healthy() {
+                    Ok(None)
+                } else {
+                    Err("expired or revoked".to_owned())
+                },
+            ),
+            sync_check(installation.sync_status()),
+        ];
+        checks.extend(disk_checks(installation).await);
+
+        InstallationReport::new(installation, checks)
+    }))
+    .await;
+
+    HealthReport::new(checks, installations)
+}
+
+/// Check that the installation has synchronized with Github and that the state it last
+/// synchronized isn't stale, i.e. that synchronizing it again hasn't failed since
+fn sync_check(status: &SyncStatus) -> Check {
+    let result = match (status.last_error(), status.last_synced()) {
+        (Some(error), _) => Err(format!("synchronizing with Github failed: {}", error)),
+        (None, Some(last_synced)) => Ok(Some(format!(
+            "{}s ago",
+            SystemTime::now()
+                .duration_since(last_synced)
+                .unwrap_or_default()
+                .as_secs()
+        ))),
+        (None, None) => Err("not yet synchronized with Github".to_owned()),
+    };
+
+    Check::new("synchronized", result)
+}
+
+/// Check the installation's store and git checkout, which run `git` and touch the disk so are
+/// done on a blocking thread
+async fn disk_checks(installation: &Installation) -> Vec<Check> {
+    let config = installation.config().clone();
+    let result = tokio::task::spawn_blocking(move || {
+        (store::check_writable(&config), git::check_checkout(&config))
+    })
+    .await;
+
+    let (store, checkout) = match result {
+        Ok(results) => results,
+        Err(e) => {
+            let error = format!("check panicked: {}", e);
+            return vec![
+                Check::new("store", Err(error.clone())),
+                Check::new("git-checkout", Err(error)),
+            ];
+        }
+    };
+
+    vec![
+        Check::new(
+            "store",
+            store.map(|()| None).map_err(|e| format!("{:#}", e)),
+        ),
+        Check::new(
+            "git-checkout",
+            checkout.map(|()| None).map_err(|e| format!("{:#}", e)),
+        ),
+    ]
+}
+
+async fn github_check(github: &GithubClient) -> Check {
+    // Querying the rate limit doesn't count against it
+    let result = match tokio::time::timeout(GITHUB_TIMEOUT, github.rate_limit().get()).await {
+        Ok(Ok(response)) => {
+            let core = response.core();
+            if core.remaining < MIN_RATE_LIMIT_REMAINING {
+                Err(format!(
+                    "{}/{} API requests remaining until {}",
+                    core.remaining, core.limit, core.reset
+                ))
+            } else {
+                Ok(Some(format!(
+                    "{}/{} API requests remaining",
+                    core.remaining, core.limit
+                )))
+            }
+        }
+        Ok(Err(e)) => Err(format!("{:#}", e)),
+        Err(_) => Err("timed out".to_owned()),
+    };
+
+    Check::new("github", result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::sync_check;
+    use crate::event_processor::SyncStatus;
+    use anyhow::anyhow;
+
+    #[test]
+    fn stale_sync_fails_readiness() {
+        let status = SyncStatus::default();
+        let check = sync_check(&status);
+        assert!(!check.ok);
+        assert_eq!(
+            check.detail.as_deref(),
+            Some("not yet synchronized with Github")
+        );
+
+        status.record_synced();
+        assert!(sync_check(&status).ok);
+
+        status.record_failed(&anyhow!("502 Bad Gateway"));
+        let check = sync_check(&status);
+        assert!(!check.ok);
+        assert_eq!(
+            check.detail.as_deref(),
+            Some("synchronizing with Github failed: 502 Bad Gateway")
+        );
+        assert!(status.last_synced().is_some());
+
+        status.record_synced();
+        assert!(sync_check(&status).ok);
+    }
+}
@@ -0,0 +1,275 @@
+//! Rendering of the HTML pages served by bors
+//!
+//! Pages show text controlled by users, e.g. PR titles and branch names, so every string passed to
+//! a page's template is HTML-escaped before it's rendered. Pages are always rendered through
+//! `render` so that this can't be forgotten for a new page or value.
+//!
+//! The templates of the pages are embedded in bors, but each can be overridden by a file of the
+//! same name in the configured `templates-dir`, e.g. to customize branding. Those files are
+//! reloaded whenever they change, so tweaks show up without restarting bors.
+
+use crate::Result;
+use liquid::{
+    model::{Value, ValueView},
+    Object,
+};
+use log::warn;
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+pub const INDEX_HTML: &str = "index.html";
+pub const REPO_HTML: &str = "repo.html";
+pub const CI_INSIGHTS_HTML: &str = "ci-insights.html";
+pub const CONFLICTS_HTML: &str = "conflicts.html";
+
+/// The templates of the pages, keyed by their file name
+const EMBEDDED: &[(&str, &str)] = &[
+    (INDEX_HTML, include_str!("../../html/index.html")),
+    (REPO_HTML, include_str!("../../html/repo.html")),
+    (
+        CI_INSIGHTS_HTML,
+        include_str!("../../html/ci-insights.html"),
+    ),
+    (CONFLICTS_HTML, include_str!("../../html/conflicts.html")),
+];
+
+/// A template loaded from disk, along with the modification time and length of the file it was
+/// loaded from, used to notice when it changes
+#[derive(Debug)]
+struct Loaded {
+    modified: SystemTime,
+    len: u64,
+    template: String,
+}
+
+/// The templates of the pages served by bors
+#[derive(Clone, Debug, Default)]
+pub struct Pages {
+    dir: Option<PathBuf>,
+    loaded: Arc<Mutex<HashMap<&'static str, Loaded>>>,
+}
+
+impl Pages {
+    /// Use the templates in `dir`, if provided, in place of the embedded ones
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            loaded: Default::default(),
+        }
+    }
+
+    /// Render the page `name`, e.g. `REPO_HTML`. If its template from the templates directory
+    /// can't be rendered, the embedded one is used instead
+    pub fn render(&self, name: &'static str, data: Object) -> Result<String> {
+        let embedded = embedded(name);
+        match self.load(name) {
+            Some(template) => match render(&template, data.clone()) {
+                Ok(page) => Ok(page),
+                Err(e) => {
+                    warn!("unable to render custom template '{}': {:#}", name, e);
+                    render(embedded, data)
+                }
+            },
+            None => render(embedded, data),
+        }
+    }
+
+    /// The template `name` from the templates directory, reloaded if the file changed since it was
+    /// last loaded, or `None` if there isn't one
+    fn load(&self, name: &'static str) -> Option<String> {
+        let path = self.dir.as_ref()?.join(name);
+        let mut loaded = self.loaded.lock().unwrap();
+
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => {
+                loaded.remove(name);
+                return None;
+            }
+        };
+        let modified = metadata.modified().ok()?;
+        match loaded.get(name) {
+            Some(l) if l.modified == modified && l.len == metadata.len() => {
+                return Some(l.template.clone())
+            }
+            _ => {}
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(template) => {
+                loaded.insert(
+                    name,
+                    Loaded {
+                        modified,
+                        len: metadata.len(),
+                        template: template.clone(),
+                    },
+                );
+                Some(template)
+            }
+            Err(e) => {
+                warn!("unable to read template {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+fn embedded(name: &str) -> &'static str {
+    EMBEDDED
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, template)| *template)
+        .unwrap_or_else(|| panic!("no embedded template '{}'", name))
+}
+
+/// Render the page `template` with `data`, escaping all of the strings in `data`
+pub fn render(template: &str, data: Object) -> Result<String> {
+    let template = liquid::ParserBuilder::with_stdlib()
+        .build()?
+        .parse(template)?;
+    let data = data
+        .into_iter()
+        .map(|(key, value)| (key, escape_value(value)))
+        .collect::<Object>();
+
+    Ok(template.render(&data)?)
+}
+
+fn escape_value(value: Value) -> Value {
+    match value {
+        Value::Scalar(scalar) if scalar.type_name() == "string" => {
+            Value::scalar(escape(&scalar.into_string()))
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(escape_value).collect()),
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, escape_value(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Escape the characters with a special meaning in HTML, in both text and quoted attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::{embedded, render, Pages, INDEX_HTML, REPO_HTML};
+
+    const HOSTILE: &str = r#"<script>alert("pwned")</script><img src=x onerror='alert(1)'>"#;
+
+    #[test]
+    fn hostile_titles_are_escaped() {
+        let data = liquid::object!({
+            "repo": { "owner": "bors-rs", "name": "\"><b>bors</b>" },
+            "total": 1,
+            "pull_requests": [{
+                "number": 7,
+                "title": HOSTILE,
+                "status": "queued",
+                "mergeable": "yes",
+                "head_ref": "octocat:<i>branch</i>",
+                "approved": "yes",
+                "maintainer_can_modify": "no",
+                "priority": "normal",
+            }],
+            "failed": [{
+                "number": 8,
+                "title": "<u>failed</u>",
+                "reason": "<s>merge conflict</s>",
+                "check": nil,
+                "failed_at": "2026-10-15 12:00:00 UTC",
+            }],
+            "sla_breach": "<marquee>slow</marquee>",
+            "protection_conflicts": [{
+                "branch": "<em>main</em>",
+                "problem": "requires signed commits",
+                "suggestion": "Stop requiring signed commits",
+            }],
+            "activity": {
+                "open_pulls": "▁█",
+                "queue_length": "▁▁",
+                "landed": "█▁",
+                "days": [{
+                    "date": "2026-10-15",
+                    "open_pulls": 1,
+                    "queue_length": 0,
+                    "landed": 2,
+                }],
+            },
+        });
+
+        let page = render(embedded(REPO_HTML), data).unwrap();
+        assert!(!page.contains("<script>alert"));
+        assert!(!page.contains("<img"));
+        assert!(!page.contains("<b>bors</b>"));
+        assert!(!page.contains("<i>branch</i>"));
+        assert!(!page.contains("<marquee>"));
+        assert!(!page.contains("<em>main</em>"));
+        assert!(!page.contains("<u>failed</u>"));
+        assert!(!page.contains("<s>merge conflict</s>"));
+        assert!(page.contains(
+            "&lt;script&gt;alert(&quot;pwned&quot;)&lt;/script&gt;&lt;img src=x onerror=&#x27;alert(1)&#x27;&gt;"
+        ));
+    }
+
+    #[test]
+    fn hostile_repo_names_are_escaped() {
+        let data = liquid::object!({
+            "request_count": 1,
+            "repos": [{ "owner": "bors-rs", "name": HOSTILE }],
+        });
+
+        let page = render(embedded(INDEX_HTML), data).unwrap();
+        assert!(!page.contains("<script>alert"));
+        assert!(page.contains("repos/bors-rs/&lt;script&gt;"));
+    }
+
+    #[test]
+    fn custom_templates_are_reloaded() {
+        let dir = std::env::temp_dir().join(format!("bors-pages-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(INDEX_HTML);
+        let _ = std::fs::remove_file(&path);
+
+        let pages = Pages::new(Some(dir.clone()));
+        let data = || liquid::object!({ "request_count": 3, "repos": [] });
+        assert!(pages.render(INDEX_HTML, data()).unwrap().contains("<html>"));
+
+        std::fs::write(&path, "Acme bors: {{ request_count }}").unwrap();
+        assert_eq!(pages.render(INDEX_HTML, data()).unwrap(), "Acme bors: 3");
+
+        std::fs::write(&path, "Acme merge bot: {{ request_count }}").unwrap();
+        assert_eq!(
+            pages.render(INDEX_HTML, data()).unwrap(),
+            "Acme merge bot: 3"
+        );
+
+        // A broken template falls back to the embedded one
+        std::fs::write(&path, "{% if %}").unwrap();
+        assert!(pages.render(INDEX_HTML, data()).unwrap().contains("<html>"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+//! Forwarding of webhook deliveries from ingest replicas to the leader
+//!
+//! Webhook ingestion can be scaled horizontally, and the webhook endpoint kept available while an
+//! instance restarts, by running any number of replicas with `serve --forward-to <leader>` behind
+//! a load balancer. A replica checks the signature of each delivery and dedupes it against the
+//! `deliveries-dir` shared with the other replicas, then forwards it unchanged to the `/ingest`
+//! route of the leader. Only the leader runs the event processors, so the merge queue of each repo
+//! still has a single writer.
+
+use crate::{config::GithubConfig, secret::Secret, Result};
+use anyhow::anyhow;
+use github::{
+    Webhook, DELIVERY_ID_HEADER, EVENT_TYPE_HEADER, SIGNATURE_256_HEADER, SIGNATURE_HEADER,
+};
+use hyper::{
+    header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    HeaderMap,
+};
+use log::warn;
+use std::time::Duration;
+
+/// Route of the leader which replicas forward deliveries to
+pub(crate) const INGEST_PATH: &str = "/ingest";
+
+/// Number of times forwarding a delivery is attempted before giving up on it
+const FORWARD_ATTEMPTS: usize = 3;
+
+/// How long to wait before attempting to forward a delivery again
+const FORWARD_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The leader deliveries are forwarded to
+#[derive(Clone, Debug)]
+pub(super) struct Leader {
+    /// URL of the leader's `/ingest` route
+    url: String,
+    client: reqwest::Client,
+    /// The leader's `ingest-secret`, authenticating forwarded deliveries
+    secret: Secret,
+}
+
+impl Leader {
+    /// Forward deliveries to the `/ingest` route of the bors instance at `url`, authenticated with
+    /// the `ingest-secret` of `config`
+    pub(super) fn new(url: &str, config: &GithubConfig) -> Result<Self> {
+        let secret = config
+            .ingest_secret
+            .clone()
+            .ok_or_else(|| anyhow!("forwarding deliveries requires an ingest-secret"))?;
+
+        Ok(Self {
+            url: format!("{}{}", url.trim_end_matches('/'), INGEST_PATH),
+            client: config.http_client_builder()?.build()?,
+            secret,
+        })
+    }
+
+    /// Forward `webhook` to the leader, retrying a couple of times in case the leader is briefly
+    /// unavailable, e.g. while restarting
+    pub(super) async fn forward(&self, webhook: &Webhook) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            match self.send(webhook).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < FORWARD_ATTEMPTS => {
+                    warn!(
+                        "Delivery {}: forwarding to {} failed, retrying: {:#}",
+                        webhook.delivery_id, self.url, e
+                    );
+                    tokio::time::sleep(FORWARD_RETRY_DELAY).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn send(&self, webhook: &Webhook) -> Result<()> {
+        let response = self
+            .client
+            .post(&self.url)
+            .headers(forwarded_headers(webhook, &self.secret)?)
+            .body(webhook.body.clone())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("leader responded with {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// The headers `webhook` was delivered with, which the leader parses and checks exactly like those
+/// of a delivery made by Github, along with the `secret` authenticating the replica
+pub(super) fn forwarded_headers(webhook: &Webhook, secret: &Secret) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(
+        EVENT_TYPE_HEADER,
+        HeaderValue::from_static(webhook.event_type.as_str()),
+    );
+    headers.insert(
+        DELIVERY_ID_HEADER,
+        HeaderValue::from_str(&webhook.delivery_id)?,
+    );
+    for signature in &webhook.signatures {
+        let header = if signature.starts_with("sha256=") {
+            SIGNATURE_256_HEADER
+        } else {
+            SIGNATURE_HEADER
+        };
+        headers.append(header, HeaderValue::from_str(signature)?);
+    }
+
+    let mut authorization = HeaderValue::from_str(&format!("Bearer {}", secret.expose()))?;
+    authorization.set_sensitive(true);
+    headers.insert(AUTHORIZATION, authorization);
+
+    Ok(headers)
+}
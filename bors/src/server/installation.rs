@@ -1,25 +1,67 @@
 use crate::{
+    activity::{self, ActivitySnapshot},
+    api::{ActivityDto, CiInsightsDto, ConflictsDto, RepoStatusDto},
     config::RepoConfig,
-    event_processor::EventProcessorSender,
+    conflicts,
+    event_processor::{EventProcessorSender, SyncStatus},
+    graphql::{AuthHealth, GraphqlHealth, ReviewDecisionCacheStats},
+    insights::CiInsights,
+    sla::SlaStatus,
+    snapshot::StateSnapshot,
     state::{Priority, PullRequestState},
 };
-use github::Event;
+use github::{client::ReadOnlySwitch, DateTime, Event};
 use serde::Serialize;
 
 #[derive(Debug)]
 pub struct Installation {
     config: RepoConfig,
     event_processor: EventProcessorSender,
+    graphql_health: GraphqlHealth,
+    auth_health: AuthHealth,
+    sync_status: SyncStatus,
+    sla_status: SlaStatus,
+    read_only: ReadOnlySwitch,
 }
 
 impl Installation {
-    pub fn new(config: RepoConfig, event_processor: EventProcessorSender) -> Self {
+    pub fn new(
+        config: RepoConfig,
+        event_processor: EventProcessorSender,
+        graphql_health: GraphqlHealth,
+        auth_health: AuthHealth,
+        sync_status: SyncStatus,
+        sla_status: SlaStatus,
+        read_only: ReadOnlySwitch,
+    ) -> Self {
         Self {
             config,
             event_processor,
+            graphql_health,
+            auth_health,
+            sync_status,
+            sla_status,
+            read_only,
         }
     }
 
+    /// The switch disabling writes to Github for just this repo
+    pub fn read_only(&self) -> &ReadOnlySwitch {
+        &self.read_only
+    }
+
+    pub fn graphql_health(&self) -> &GraphqlHealth {
+        &self.graphql_health
+    }
+
+    pub fn auth_health(&self) -> &AuthHealth {
+        &self.auth_health
+    }
+
+    pub fn sync_status(&self) -> &SyncStatus {
+        &self.sync_status
+    }
+
     pub fn config(&self) -> &RepoConfig {
         &self.config
     }
@@ -32,7 +74,11 @@ impl Installation {
         self.config.name()
     }
 
-    #[allow(dead_code)]
+    /// Follow the repo to `owner/name` after it was renamed or transferred on Github
+    pub fn rename(&mut self, owner: &str, name: &str) {
+        self.config.rename(owner, name);
+    }
+
     pub fn event_processor(&self) -> &EventProcessorSender {
         &self.event_processor
     }
@@ -45,34 +91,159 @@ impl Installation {
     }
 
     pub async fn state(&self) -> Vec<PullRequestState> {
-        let (_queue, pulls) = self.event_processor.get_state().await.unwrap();
+        let (_, pulls) = self.event_processor.get_state().await.unwrap();
+
+        let mut sorted = pulls.values().cloned().collect::<Vec<_>>();
+        sorted.sort_unstable_by_key(|p| pulls.queue_entry(p));
+        sorted
+    }
 
-        let mut pulls = pulls.into_iter().map(|(_, v)| v).collect::<Vec<_>>();
-        pulls.sort_unstable_by_key(|p| p.to_queue_entry(self.config()));
-        pulls
+    pub async fn export_state(&self) -> StateSnapshot {
+        self.event_processor.export_state().await.unwrap()
+    }
+
+    pub async fn import_state(&self, snapshot: StateSnapshot) {
+        self.event_processor.import_state(snapshot).await.unwrap();
+    }
+
+    pub async fn resume_land(&self, number: u64) -> bool {
+        self.event_processor.resume_land(number).await.unwrap()
     }
 
     pub async fn sync(&self) {
         self.event_processor.sync().await.unwrap();
     }
 
+    pub async fn sync_pull(&self, number: u64) -> bool {
+        self.event_processor.sync_pull(number).await.unwrap()
+    }
+
+    pub async fn process_queue(&self) {
+        self.event_processor.process_queue().await.unwrap();
+    }
+
+    pub async fn api_status(&self) -> RepoStatusDto {
+        RepoStatusDto::new(
+            self.config(),
+            self.auth_health.is_healthy(),
+            self.sla_status.breach().map(|breach| breach.to_string()),
+            &self.state().await,
+        )
+    }
+
+    pub async fn ci_insights(&self) -> CiInsightsDto {
+        let runs = self.event_processor.check_runs().await.unwrap();
+        let skews = self.event_processor.merge_skews().await.unwrap();
+        let insights = CiInsights::new(&runs, DateTime::now()).with_merge_skews(&skews);
+        CiInsightsDto::new(self.config(), &insights)
+    }
+
+    pub async fn conflicts(&self) -> ConflictsDto {
+        let clusters = conflicts::conflict_clusters(&self.state().await);
+        ConflictsDto::new(self.config(), &clusters)
+    }
+
+    pub async fn review_decision_cache_stats(&self) -> ReviewDecisionCacheStats {
+        self.event_processor
+            .review_decision_cache_stats()
+            .await
+            .unwrap()
+    }
+
+    pub async fn activity(&self) -> ActivityDto {
+        let activity = self.event_processor.activity().await.unwrap();
+        ActivityDto::new(self.config(), &activity)
+    }
+
     pub async fn repo_liquid_object(&self) -> liquid::Object {
+        let snapshots = self.event_processor.activity().await.unwrap();
+        let recent = &snapshots[snapshots.len().saturating_sub(RECENT_ACTIVITY_DAYS)..];
         let pull_requests = self.state().await;
+        let failed = pull_requests
+            .iter()
+            .filter_map(LiquidFailure::from_pull_request_state)
+            .collect::<Vec<_>>();
         let pull_requests = pull_requests
             .into_iter()
             .map(|p| LiquidPullRequest::from_pull_request_state(p, self.config()))
             .collect::<Vec<_>>();
+        let protection_conflicts = self.event_processor.protection_conflicts().await.unwrap();
 
         let object = liquid::object!({
             "repo": self.config().repo(),
             "total": pull_requests.len(),
             "pull_requests": pull_requests,
+            "failed": failed,
+            "sla_breach": self.sla_status.breach().map(|breach| breach.to_string()),
+            "protection_conflicts": protection_conflicts,
+            "activity": {
+                "open_pulls": activity::sparkline(recent.iter().map(|s| s.open_pulls)),
+                "queue_length": activity::sparkline(recent.iter().map(|s| s.queue_length)),
+                "landed": activity::sparkline(recent.iter().map(|s| s.landed)),
+                "days": recent
+                    .iter()
+                    .rev()
+                    .map(LiquidActivity::from_snapshot)
+                    .collect::<Vec<_>>(),
+            },
         });
 
         object
     }
 }
 
+/// Number of days of activity shown on the repo page
+const RECENT_ACTIVITY_DAYS: usize = 14;
+
+// Type used for Liquid templating
+#[derive(Debug, Serialize)]
+struct LiquidActivity {
+    date: String,
+    open_pulls: usize,
+    queue_length: usize,
+    landed: usize,
+}
+
+impl LiquidActivity {
+    fn from_snapshot(snapshot: &ActivitySnapshot) -> Self {
+        Self {
+            date: snapshot.date(),
+            open_pulls: snapshot.open_pulls,
+            queue_length: snapshot.queue_length,
+            landed: snapshot.landed,
+        }
+    }
+}
+
+// Type used for Liquid templating
+#[derive(Debug, Serialize)]
+struct LiquidFailure {
+    number: u64,
+    title: String,
+    reason: String,
+    check: Option<String>,
+    failed_at: String,
+}
+
+impl LiquidFailure {
+    fn from_pull_request_state(pr: &PullRequestState) -> Option<Self> {
+        match &pr.status {
+            crate::state::Status::Failed {
+                reason,
+                check,
+                failed_at,
+            } => Some(Self {
+                number: pr.number,
+                title: pr.title.clone(),
+                reason: reason.clone(),
+                check: check.clone(),
+                failed_at: failed_at.to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
 // Type used for Liquid templating
 #[derive(Debug, Serialize)]
 struct LiquidPullRequest {
@@ -82,6 +253,7 @@ struct LiquidPullRequest {
     mergeable: &'static str,
     head_ref: String,
     approved: &'static str,
+    review_requests: Vec<String>,
     maintainer_can_modify: &'static str,
     priority: Priority,
 }
@@ -89,6 +261,7 @@ struct LiquidPullRequest {
 impl LiquidPullRequest {
     fn from_pull_request_state(pr: PullRequestState, config: &RepoConfig) -> Self {
         let priority = pr.priority(config);
+        let review_requests = pr.review_requests(config);
 
         use crate::state::Status;
         let status = match pr.status {
@@ -96,10 +269,17 @@ impl LiquidPullRequest {
             Status::Queued(_) => "queued",
             Status::Testing { .. } => "testing",
             Status::Canary { .. } => "canary",
+            Status::Failed { .. } => "failed",
         };
 
         let mergeable = if pr.mergeable { "yes" } else { "no" };
-        let approved = if pr.approved { "yes" } else { "no" };
+        let approved = if pr.approval_expired(config) {
+            "expired"
+        } else if pr.approved {
+            "yes"
+        } else {
+            "no"
+        };
         let maintainer_can_modify = if pr.maintainer_can_modify {
             "yes"
         } else {
@@ -118,6 +298,7 @@ impl LiquidPullRequest {
             status,
             mergeable,
             approved,
+            review_requests,
             maintainer_can_modify,
             head_ref,
             priority,
@@ -1,50 +1,151 @@
+mod auth;
+pub(crate) mod delivery;
+mod health;
+mod html;
+mod ingest;
 mod installation;
 mod smee_client;
+mod webhook_allowlist;
 
 #[cfg(test)]
 mod test;
 
 pub use self::{installation::Installation, smee_client::SmeeClient};
 
-use crate::{config::GithubConfig, Error, Result};
-use anyhow::anyhow;
-use futures::future::{self, TryFutureExt};
-use github::{EventType, Webhook, DELIVERY_ID_HEADER, EVENT_TYPE_HEADER, SIGNATURE_HEADER};
+use self::{
+    delivery::{RecentDeliveries, SharedDeliveries},
+    html::{Pages, CI_INSIGHTS_HTML, CONFLICTS_HTML, INDEX_HTML, REPO_HTML},
+    ingest::{Leader, INGEST_PATH},
+    webhook_allowlist::WebhookAllowlist,
+};
+
+use crate::{
+    api,
+    config::{GithubConfig, OrgConfig, RepoConfig},
+    graphql::GithubClient,
+    snapshot::StateSnapshot,
+    Error, Result,
+};
+use futures::{
+    channel::mpsc,
+    future::{self, TryFutureExt},
+};
+use github::{client::ReadOnlySwitch, Webhook};
 use hyper::{
     body,
-    header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE},
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
     server::conn::AddrStream,
     service::{make_service_fn, service_fn},
     Body, Method, Request, Response, Server as HyperServer, StatusCode,
 };
 use log::{error, info, trace, warn};
 use std::{
+    collections::{HashMap, HashSet},
     net::SocketAddr,
     sync::{
         atomic::{AtomicUsize, Ordering},
-        Arc,
+        Arc, Mutex,
     },
 };
 use tokio::sync::RwLock;
 
-const INDEX_HTML: &str = include_str!("../../html/index.html");
-const REPO_HTML: &str = include_str!("../../html/repo.html");
-
 #[derive(Clone, Debug)]
 pub struct Server {
     config: GithubConfig,
     counter: Arc<AtomicUsize>,
     /// Installations which contain various services
     installations: Arc<RwLock<Vec<Installation>>>,
+    /// Address ranges webhooks are accepted from, if verifying the source of webhooks
+    webhook_allowlist: Option<WebhookAllowlist>,
+    /// Client used for checking that Github is reachable
+    github: Arc<GithubClient>,
+    /// Organizations with an org-level webhook delivering events to bors
+    orgs: Arc<Vec<OrgConfig>>,
+    /// Used to request that an installation be created for a repo matching an org's
+    /// `auto-install` patterns
+    installer: Option<mpsc::UnboundedSender<RepoConfig>>,
+    /// Repos, as `owner/name`, which an installation has been requested for
+    auto_installed: Arc<Mutex<HashSet<String>>>,
+    /// Servers of the tenants whose requests are routed under `/tenants/<name>/`
+    tenants: Arc<HashMap<String, Server>>,
+    /// Switch disabling writes to Github for every repo
+    read_only: ReadOnlySwitch,
+    /// Templates of the HTML pages
+    pages: Pages,
+    /// Deliveries which were already handled, whether received over HTTP or smee
+    recent_deliveries: Arc<Mutex<RecentDeliveries>>,
+    /// Deliveries which were already handled by any of the instances sharing the `deliveries-dir`
+    shared_deliveries: Option<Arc<SharedDeliveries>>,
+    /// The instance deliveries are forwarded to rather than handled, when running as an ingest
+    /// replica
+    leader: Option<Leader>,
 }
 
 impl Server {
-    pub fn new(config: GithubConfig) -> Self {
-        Self {
+    pub fn new(config: GithubConfig) -> Result<Self> {
+        let webhook_allowlist = if config.verify_webhook_source {
+            Some(WebhookAllowlist::default())
+        } else {
+            None
+        };
+
+        let read_only = ReadOnlySwitch::new(config.read_only);
+        let github = Arc::new(GithubClient::new(
+            &config,
+            std::slice::from_ref(&read_only),
+        )?);
+        let pages = Pages::new(config.templates_dir.clone());
+        let shared_deliveries = config
+            .deliveries_dir
+            .clone()
+            .map(|dir| Arc::new(SharedDeliveries::new(dir)));
+
+        Ok(Self {
             config,
             counter: Arc::new(AtomicUsize::new(0)),
             installations: Arc::new(RwLock::new(Vec::new())),
-        }
+            webhook_allowlist,
+            github,
+            orgs: Arc::new(Vec::new()),
+            installer: None,
+            auto_installed: Arc::new(Mutex::new(HashSet::new())),
+            tenants: Arc::new(HashMap::new()),
+            read_only,
+            pages,
+            recent_deliveries: Arc::new(Mutex::new(RecentDeliveries::default())),
+            shared_deliveries,
+            leader: None,
+        })
+    }
+
+    /// The switch disabling writes to Github for every repo served by this server
+    pub fn read_only(&self) -> &ReadOnlySwitch {
+        &self.read_only
+    }
+
+    /// Route requests under `/tenants/<name>/` to the server of the tenant `name`
+    pub fn with_tenants(mut self, tenants: HashMap<String, Server>) -> Self {
+        self.tenants = Arc::new(tenants);
+        self
+    }
+
+    /// Route events from the org-level webhooks of `orgs`, sending the configuration of each repo
+    /// which should be automatically installed to `installer`
+    pub fn with_orgs(
+        mut self,
+        orgs: Vec<OrgConfig>,
+        installer: mpsc::UnboundedSender<RepoConfig>,
+    ) -> Self {
+        self.orgs = Arc::new(orgs);
+        self.installer = Some(installer);
+        self
+    }
+
+    /// Forward deliveries to the `/ingest` route of the bors instance at `leader` once their
+    /// signature is checked and they're deduped, instead of handling them
+    pub fn forwarding_to(mut self, leader: &str) -> Result<Self> {
+        self.leader = Some(Leader::new(leader, &self.config)?);
+        Ok(self)
     }
 
     pub async fn add_installation(&mut self, installation: Installation) {
@@ -52,18 +153,26 @@ impl Server {
     }
 
     pub async fn start(self, addr: SocketAddr) -> Result<()> {
+        for server in std::iter::once(&self).chain(self.tenants.values()) {
+            if let Some(webhook_allowlist) = &server.webhook_allowlist {
+                let github = GithubClient::new(&server.config, &[])?;
+                tokio::spawn(webhook_allowlist.clone().start(github));
+            }
+        }
+
         // The closure inside `make_service_fn` is run for each connection,
         // creating a 'service' to handle requests for that specific connection.
-        let make_service = make_service_fn(|_socket: &AddrStream| {
+        let make_service = make_service_fn(|socket: &AddrStream| {
             // While the state was moved into the make_service closure,
             // we need to clone it here because this closure is called
             // once for every connection.
             let server = self.clone();
+            let remote_addr = socket.remote_addr();
 
             // This is the `Service` that will handle the connection.
             future::ok::<_, Error>(service_fn(move |request| {
                 let server = server.clone();
-                server.serve(request)
+                server.serve(request, remote_addr)
             }))
         });
 
@@ -77,9 +186,78 @@ impl Server {
         Ok(())
     }
 
-    async fn serve(mut self, request: Request<Body>) -> Result<Response<Body>> {
-        self.counter.fetch_add(1, Ordering::AcqRel);
-        self.route_http_request(request).await
+    async fn serve(
+        self,
+        request: Request<Body>,
+        remote_addr: SocketAddr,
+    ) -> Result<Response<Body>> {
+        let (mut server, request) = match self.resolve_tenant(request)? {
+            Some(resolved) => resolved,
+            None => {
+                return Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty())?)
+            }
+        };
+
+        server.counter.fetch_add(1, Ordering::AcqRel);
+
+        if request.method() == Method::POST
+            && request.uri().path() == "/github"
+            && !server.is_allowed_webhook_source(&request, remote_addr)
+        {
+            return Ok(Response::builder()
+                .status(StatusCode::FORBIDDEN)
+                .body(Body::empty())?);
+        }
+
+        server.route_http_request(request).await
+    }
+
+    /// Find the server which handles `request`, stripping the `/tenants/<name>` prefix from the
+    /// path of requests for a tenant. Returns `None` for requests for an unknown tenant
+    fn resolve_tenant(self, request: Request<Body>) -> Result<Option<(Server, Request<Body>)>> {
+        let rest = match request.uri().path().strip_prefix("/tenants/") {
+            Some(rest) => rest,
+            None => return Ok(Some((self, request))),
+        };
+
+        let (name, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+        let tenant = match self.tenants.get(name) {
+            Some(tenant) => tenant.clone(),
+            None => return Ok(None),
+        };
+
+        let path_and_query = match request.uri().query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.to_owned(),
+        };
+        let (mut parts, body) = request.into_parts();
+        parts.uri = path_and_query.parse()?;
+
+        Ok(Some((tenant, Request::from_parts(parts, body))))
+    }
+
+    fn is_allowed_webhook_source(&self, request: &Request<Body>, remote_addr: SocketAddr) -> bool {
+        let webhook_allowlist = match &self.webhook_allowlist {
+            Some(webhook_allowlist) => webhook_allowlist,
+            None => return true,
+        };
+
+        match webhook_allowlist::client_addr(request, remote_addr, self.config.behind_proxy) {
+            Some(addr) if webhook_allowlist.contains(addr) => true,
+            Some(addr) => {
+                warn!("rejecting webhook from {}, not a Github address", addr);
+                false
+            }
+            None => {
+                warn!("rejecting webhook with missing or invalid X-Forwarded-For header");
+                false
+            }
+        }
     }
 
     async fn route_http_request(&mut self, request: Request<Body>) -> Result<Response<Body>> {
@@ -87,12 +265,6 @@ impl Server {
             (&Method::GET, "/") => {
                 let count = self.counter.load(Ordering::Relaxed);
 
-                let template = liquid::ParserBuilder::with_stdlib()
-                    .build()
-                    .unwrap()
-                    .parse(INDEX_HTML)
-                    .unwrap();
-
                 let repos = self
                     .installations
                     .read()
@@ -104,27 +276,107 @@ impl Server {
                     "request_count": count,
                     "repos": repos,
                 });
-                let output = template.render(&data).unwrap();
+                let output = self.pages.render(INDEX_HTML, data)?;
 
                 let response = Response::new(Body::from(output));
                 Ok(response)
             }
+            (&Method::GET, "/healthz") => {
+                Ok(health::liveness(&self.installations.read().await).into_response())
+            }
+            (&Method::GET, "/readyz") => Ok(health::readiness(
+                &self.github,
+                &self.installations.read().await,
+            )
+            .await
+            .into_response()),
             (&Method::GET, "/github") => Ok(Response::builder()
                 .status(StatusCode::METHOD_NOT_ALLOWED)
                 .body(Body::empty())?),
             (&Method::POST, "/github") => self.route_github(request).await,
+            (&Method::POST, INGEST_PATH) => self.route_ingest(request).await,
+            (method, "/read-only") => {
+                let method = method.clone();
+                if method != Method::GET {
+                    if let Some(rejection) = self.reject_unless_admin(&request)? {
+                        return Ok(rejection);
+                    }
+                }
+                let installations = self.installations.read().await;
+                route_read_only(&method, &self.read_only, installations.iter()).await
+            }
             (_, route) if route == "/repos" || route.starts_with("/repos/") => {
                 self.route_repos(request).await
             }
+            (&Method::GET, route) if route.starts_with("/api/") => self.route_api(route).await,
             _ => Ok(Response::builder()
                 .status(StatusCode::NOT_FOUND)
                 .body(Body::empty())?),
         }
     }
 
+    async fn route_api(&self, route: &str) -> Result<Response<Body>> {
+        let prefix = format!("/api/v{}/repos", api::VERSION);
+        let repo = match route.strip_prefix(&prefix) {
+            Some(repo) => repo.trim_matches('/'),
+            None => {
+                return Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty())?)
+            }
+        };
+
+        let (repo, ci_insights) = match repo.strip_suffix("/ci-insights") {
+            Some(repo) => (repo, true),
+            None => (repo, false),
+        };
+        let (repo, activity) = match repo.strip_suffix("/activity") {
+            Some(repo) => (repo, true),
+            None => (repo, false),
+        };
+        let (repo, conflicts) = match repo.strip_suffix("/conflicts") {
+            Some(repo) => (repo, true),
+            None => (repo, false),
+        };
+
+        let installations = self.installations.read().await;
+        let body = if repo.is_empty() {
+            let mut repos = Vec::new();
+            for installation in installations.iter() {
+                repos.push(installation.api_status().await);
+            }
+            serde_json::to_string(&repos)?
+        } else {
+            match installations
+                .iter()
+                .find(|i| format!("{}/{}", i.owner(), i.name()) == repo)
+            {
+                Some(installation) if ci_insights => {
+                    serde_json::to_string(&installation.ci_insights().await)?
+                }
+                Some(installation) if activity => {
+                    serde_json::to_string(&installation.activity().await)?
+                }
+                Some(installation) if conflicts => {
+                    serde_json::to_string(&installation.conflicts().await)?
+                }
+                Some(installation) => serde_json::to_string(&installation.api_status().await)?,
+                None => {
+                    return Ok(Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::empty())?)
+                }
+            }
+        };
+
+        Ok(Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))?)
+    }
+
     // XXX Really rough code for dumping internal state
     async fn route_repos(&mut self, request: Request<Body>) -> Result<Response<Body>> {
-        let path = request.uri().path();
+        let path = request.uri().path().to_owned();
 
         if path == "/repos" || path == "/repos/" {
             let mut body = String::new();
@@ -132,9 +384,14 @@ impl Server {
 
             for installation in self.installations.read().await.iter() {
                 body.push_str(&format!(
-                    "{}/{}\n",
+                    "{}/{}{}\n",
                     installation.owner(),
-                    installation.name()
+                    installation.name(),
+                    if installation.auth_health().is_healthy() {
+                        ""
+                    } else {
+                        " (unhealthy: API token expired)"
+                    }
                 ));
             }
 
@@ -148,29 +405,161 @@ impl Server {
                 repo = installation.name()
             );
 
-            if path == &route[..route.len() - 1] || path == route {
-                let template = liquid::ParserBuilder::with_stdlib()
-                    .build()
-                    .unwrap()
-                    .parse(REPO_HTML)
-                    .unwrap();
+            if path == route[..route.len() - 1] || path == route {
+                let body = self
+                    .pages
+                    .render(REPO_HTML, installation.repo_liquid_object().await)?;
 
-                let body = template
-                    .render(&installation.repo_liquid_object().await)
-                    .unwrap();
+                return Ok(Response::new(Body::from(body)));
+            } else if path.starts_with(&route) && path.ends_with("/ci-insights") {
+                let insights = installation.ci_insights().await;
+                let skew = &insights.merge_skew;
+                let hours = |seconds: u64| format!("{:.1}", seconds as f64 / 3600.0);
+                let data = liquid::object!({
+                    "repo": installation.config().repo(),
+                    "total_runs": insights.total_runs,
+                    "slowest": liquid::model::to_value(&insights.slowest)?,
+                    "flakiest": insights
+                        .flakiest
+                        .iter()
+                        .map(|check| liquid::object!({
+                            "name": check.name,
+                            "runs": check.runs,
+                            "flakes": check.flakes,
+                            "flake_percent": format!("{:.1}", check.flake_rate * 100.0),
+                        }))
+                        .collect::<Vec<_>>(),
+                    "trend": liquid::model::to_value(&insights.trend)?,
+                    "merge_skew": liquid::object!({
+                        "landed": skew.landed,
+                        "behind": skew.behind,
+                        "average_commits_behind": format!("{:.1}", skew.average_commits_behind),
+                        "max_commits_behind": skew.max_commits_behind,
+                        "average_behind_hours": hours(skew.average_behind_seconds),
+                        "max_behind_hours": hours(skew.max_behind_seconds),
+                    }),
+                });
+                let body = self.pages.render(CI_INSIGHTS_HTML, data)?;
 
                 return Ok(Response::new(Body::from(body)));
-            } else if path.starts_with(&route) && path.ends_with("/debug") {
-                let body = format!(
-                    "{}/{}\n\nConfig:\n{:#?}\n\nState:\n{:#?}",
-                    installation.owner(),
-                    installation.name(),
-                    installation.config(),
-                    installation.state().await,
-                );
+            } else if path.starts_with(&route) && path.ends_with("/conflicts") {
+                let conflicts = installation.conflicts().await;
+                let data = liquid::object!({
+                    "repo": installation.config().repo(),
+                    "clusters": liquid::model::to_value(&conflicts.clusters)?,
+                });
+                let body = self.pages.render(CONFLICTS_HTML, data)?;
 
                 return Ok(Response::new(Body::from(body)));
+            } else if path.starts_with(&route) && path.ends_with("/debug") {
+                let graphql_health = installation.graphql_health();
+                let body = serde_json::to_string_pretty(&serde_json::json!({
+                    "repo": installation.config().repo(),
+                    "api_token_valid": installation.auth_health().is_healthy(),
+                    "graphql": {
+                        "healthy": graphql_health.is_healthy(),
+                        "consecutive_failures": graphql_health.consecutive_failures(),
+                        "review_decision_cache": installation.review_decision_cache_stats().await,
+                    },
+                    "config": installation.config(),
+                    "state": installation.export_state().await,
+                }))?;
+
+                return Ok(Response::builder()
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))?);
+            } else if path.starts_with(&route) && path.ends_with("/state") {
+                return match *request.method() {
+                    Method::GET => {
+                        let body = serde_json::to_string(&installation.export_state().await)?;
+                        Ok(Response::builder()
+                            .header(CONTENT_TYPE, "application/json")
+                            .body(Body::from(body))?)
+                    }
+                    Method::POST => {
+                        if let Some(rejection) = self.reject_unless_admin(&request)? {
+                            return Ok(rejection);
+                        }
+                        let body = body::to_bytes(request.into_body()).await?;
+                        match StateSnapshot::from_json(&body, installation.config().repo()) {
+                            Ok(snapshot) => {
+                                // So that Github's redeliveries of them aren't handled again here
+                                for delivery_id in &snapshot.deliveries {
+                                    self.record_delivery(delivery_id)?;
+                                }
+                                installation.import_state(snapshot).await;
+                                Ok(Response::new(Body::from("Imported State!")))
+                            }
+                            Err(e) => Ok(Response::builder()
+                                .status(StatusCode::BAD_REQUEST)
+                                .body(Body::from(format!("{:#}", e)))?),
+                        }
+                    }
+                    _ => Ok(Response::builder()
+                        .status(StatusCode::METHOD_NOT_ALLOWED)
+                        .body(Body::empty())?),
+                };
+            } else if let Some(number) = path
+                .strip_prefix(&route)
+                .and_then(|p| p.strip_prefix("resume/"))
+                .and_then(|n| n.parse::<u64>().ok())
+            {
+                if request.method() != Method::POST {
+                    return Ok(Response::builder()
+                        .status(StatusCode::METHOD_NOT_ALLOWED)
+                        .body(Body::empty())?);
+                }
+                if let Some(rejection) = self.reject_unless_admin(&request)? {
+                    return Ok(rejection);
+                }
+
+                return if installation.resume_land(number).await {
+                    Ok(Response::new(Body::from(format!(
+                        "Resuming land of #{}!",
+                        number
+                    ))))
+                } else {
+                    Ok(Response::builder()
+                        .status(StatusCode::CONFLICT)
+                        .body(Body::from(format!("#{} has no land to resume", number)))?)
+                };
+            } else if let Some(number) = path
+                .strip_prefix(&route)
+                .and_then(|p| p.strip_prefix("sync/"))
+                .and_then(|n| n.parse::<u64>().ok())
+            {
+                if request.method() != Method::POST {
+                    return Ok(Response::builder()
+                        .status(StatusCode::METHOD_NOT_ALLOWED)
+                        .body(Body::empty())?);
+                }
+                if let Some(rejection) = self.reject_unless_admin(&request)? {
+                    return Ok(rejection);
+                }
+
+                return if installation.sync_pull(number).await {
+                    Ok(Response::new(Body::from(format!("Synced #{}!", number))))
+                } else {
+                    Ok(Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::from(format!("#{} isn't an open PR", number)))?)
+                };
+            } else if path.starts_with(&route) && path.ends_with("/read-only") {
+                if request.method() != Method::GET {
+                    if let Some(rejection) = self.reject_unless_admin(&request)? {
+                        return Ok(rejection);
+                    }
+                }
+                return route_read_only(
+                    request.method(),
+                    installation.read_only(),
+                    std::iter::once(installation),
+                )
+                .await;
             } else if path.starts_with(&route) && path.ends_with("/sync") {
+                if let Some(rejection) = self.reject_unless_admin(&request)? {
+                    return Ok(rejection);
+                }
                 installation.sync().await;
                 return Ok(Response::new(Body::from("Syncing Pull Requests!")));
             }
@@ -181,10 +570,73 @@ impl Server {
             .body(Body::empty())?)
     }
 
+    /// The response rejecting `request`, which changes bors' state, unless it carries the
+    /// `admin-secret` as a bearer token. Such routes are disabled when no secret is configured
+    fn reject_unless_admin(&self, request: &Request<Body>) -> Result<Option<Response<Body>>> {
+        let authorized = match &self.config.admin_secret {
+            Some(secret) => auth::has_bearer_token(request.headers(), secret),
+            None => {
+                return Ok(Some(
+                    Response::builder()
+                        .status(StatusCode::FORBIDDEN)
+                        .body(Body::from(
+                            "This route requires an admin-secret to be configured",
+                        ))?,
+                ))
+            }
+        };
+        if authorized {
+            return Ok(None);
+        }
+
+        warn!(
+            "rejecting {} {} without a valid admin-secret",
+            request.method(),
+            request.uri().path()
+        );
+        Ok(Some(
+            Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(Body::empty())?,
+        ))
+    }
+
     async fn route_github(&mut self, request: Request<Body>) -> Result<Response<Body>> {
         assert_eq!(request.method(), &Method::POST);
         assert_eq!(request.uri().path(), "/github");
 
+        self.route_delivery(request, false).await
+    }
+
+    /// Accept deliveries forwarded by ingest replicas, which already checked and deduped them. Not
+    /// subject to `verify-webhook-source`, replicas authenticate with the `ingest-secret` instead
+    async fn route_ingest(&mut self, request: Request<Body>) -> Result<Response<Body>> {
+        let authorized = match &self.config.ingest_secret {
+            // Replicas only forward deliveries, they never accept forwarded ones
+            Some(secret) if self.leader.is_none() => {
+                auth::has_bearer_token(request.headers(), secret)
+            }
+            _ => {
+                return Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty())?)
+            }
+        };
+        if !authorized {
+            warn!("rejecting forwarded delivery with a missing or invalid ingest-secret");
+            return Ok(Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(Body::empty())?);
+        }
+
+        self.route_delivery(request, true).await
+    }
+
+    async fn route_delivery(
+        &mut self,
+        request: Request<Body>,
+        forwarded: bool,
+    ) -> Result<Response<Body>> {
         let webhook = match webhook_from_request(request).await {
             Ok(webhook) => webhook,
             Err(e) => {
@@ -195,7 +647,11 @@ impl Server {
             }
         };
 
-        self.handle_webhook(webhook).await?;
+        if forwarded {
+            self.handle_forwarded_webhook(webhook).await?;
+        } else {
+            self.handle_webhook(webhook).await?;
+        }
 
         Ok(Response::builder()
             .status(StatusCode::OK)
@@ -204,6 +660,8 @@ impl Server {
             .body(Body::from("OK"))?)
     }
 
+    /// Handle a webhook, whether delivered to `/github` or relayed by smee, or forward it to the
+    /// leader when running as an ingest replica
     //TODO maybe insert into database here
     pub(super) async fn handle_webhook(&mut self, webhook: Webhook) -> Result<()> {
         trace!("Handling Webhook: {}", webhook.delivery_id);
@@ -212,6 +670,80 @@ impl Server {
             return Ok(());
         }
 
+        // Only checked once the signature is, so that a forged delivery can't shadow a genuine one
+        if !self.record_delivery(&webhook.delivery_id)? {
+            info!(
+                "Delivery {} was already handled; skipping",
+                webhook.delivery_id
+            );
+            return Ok(());
+        }
+
+        if let Some(leader) = &self.leader {
+            if let Err(e) = leader.forward(&webhook).await {
+                // So that the delivery is accepted again once redelivered
+                self.forget_delivery(&webhook.delivery_id);
+                return Err(e.context(format!("forwarding delivery {}", webhook.delivery_id)));
+            }
+            return Ok(());
+        }
+
+        self.dispatch_webhook(&webhook).await;
+        Ok(())
+    }
+
+    /// Handle a webhook forwarded by an ingest replica. Its signature is checked again, but it's
+    /// only deduped against the deliveries this instance handled, as the replica recorded it in the
+    /// shared `deliveries-dir` already. That still catches a replica forwarding it again after
+    /// losing the response
+    async fn handle_forwarded_webhook(&mut self, webhook: Webhook) -> Result<()> {
+        trace!("Handling forwarded Webhook: {}", webhook.delivery_id);
+        if !webhook.check_signature(self.config.webhook_secret().map(str::as_bytes)) {
+            warn!("Signature check FAILED! Skipping Event.");
+            return Ok(());
+        }
+
+        if !self
+            .recent_deliveries
+            .lock()
+            .unwrap()
+            .insert(&webhook.delivery_id)
+        {
+            info!(
+                "Delivery {} was already handled; skipping",
+                webhook.delivery_id
+            );
+            return Ok(());
+        }
+
+        self.dispatch_webhook(&webhook).await;
+        Ok(())
+    }
+
+    /// Record `delivery_id` as handled, returning `false` if it already was by this instance or,
+    /// with a `deliveries-dir`, by any of the instances sharing it
+    fn record_delivery(&self, delivery_id: &str) -> Result<bool> {
+        if !self.recent_deliveries.lock().unwrap().insert(delivery_id) {
+            return Ok(false);
+        }
+
+        match &self.shared_deliveries {
+            Some(shared) => shared.insert(delivery_id),
+            None => Ok(true),
+        }
+    }
+
+    fn forget_delivery(&self, delivery_id: &str) {
+        self.recent_deliveries.lock().unwrap().remove(delivery_id);
+        if let Some(shared) = &self.shared_deliveries {
+            if let Err(e) = shared.remove(delivery_id) {
+                error!("Unable to forget delivery {}: {:#}", delivery_id, e);
+            }
+        }
+    }
+
+    /// Route a checked and deduped webhook to the installations of its repo or org
+    async fn dispatch_webhook(&self, webhook: &Webhook) {
         // Convert the webhook to an event so that we can get out the installation information
         let event = match webhook.to_event() {
             Ok(webhook) => webhook,
@@ -227,67 +759,129 @@ impl Server {
                         .unwrap_err(),
                     pretty_json,
                 );
-                return Ok(());
+                return;
             }
         };
 
-        // XXX Right now we only handle Webhook installations for Repositories
+        if let github::Event::Repository(e) = &event {
+            if e.is_renamed() || e.is_transferred() {
+                self.follow_moved_repository(e).await;
+            }
+        }
+
         let installations = self.installations.read().await;
-        if let Some(installation) = event.repository().and_then(|repository| {
-            installations
+        if let Some(repository) = event.repository() {
+            match installations
                 .iter()
                 .find(|i| i.owner() == repository.owner.login && i.name() == repository.name)
-        }) {
-            installation
-                .handle_webhook(&event, &webhook.delivery_id)
-                .await;
+            {
+                Some(installation) => {
+                    installation
+                        .handle_webhook(&event, &webhook.delivery_id)
+                        .await
+                }
+                None => self.auto_install(&repository.owner.login, &repository.name),
+            }
+        } else if let Some(org) = event.organization() {
+            // Org-level events are handled by every installation in the org
+            for installation in installations.iter().filter(|i| i.owner() == org.login) {
+                installation
+                    .handle_webhook(&event, &webhook.delivery_id)
+                    .await;
+            }
         }
+    }
 
-        Ok(())
+    /// Route the events of a renamed or transferred repo to its installation under the repo's new
+    /// owner and name. The installation's event processor moves its state when it receives the
+    /// event
+    async fn follow_moved_repository(&self, event: &github::RepositoryEvent) {
+        let mut installations = self.installations.write().await;
+        if let Some(installation) = installations
+            .iter_mut()
+            .find(|i| i.owner() == event.previous_owner() && i.name() == event.previous_name())
+        {
+            info!(
+                "{}/{} - Repository moved to {}",
+                installation.owner(),
+                installation.name(),
+                event.repository.full_name
+            );
+            installation.rename(&event.repository.owner.login, &event.repository.name);
+        }
+    }
+
+    /// Request an installation for a repo which isn't managed by bors yet, if it matches the
+    /// `auto-install` patterns of its org. The event itself is dropped, the new installation
+    /// picks up the repo's current state when it synchronizes on startup
+    fn auto_install(&self, owner: &str, name: &str) {
+        let (org, installer) = match (
+            self.orgs.iter().find(|org| org.owner() == owner),
+            &self.installer,
+        ) {
+            (Some(org), Some(installer)) if org.should_auto_install(name) => (org, installer),
+            _ => return,
+        };
+
+        // Events for the repo keep arriving while its installation is starting up
+        if !self
+            .auto_installed
+            .lock()
+            .unwrap()
+            .insert(format!("{}/{}", owner, name))
+        {
+            return;
+        }
+
+        match org.repo_config(name) {
+            Ok(config) => {
+                info!("{}/{} - Automatically installing", owner, name);
+                if installer.unbounded_send(config).is_err() {
+                    error!("{}/{} - Unable to request installation", owner, name);
+                }
+            }
+            Err(e) => error!("{}/{} - Invalid configuration: {:#}", owner, name, e),
+        }
     }
 }
 
-async fn webhook_from_request(request: Request<Body>) -> Result<Webhook> {
-    // Webhooks from github should only contain json payloads
-    match request.headers().get(CONTENT_TYPE).map(HeaderValue::to_str) {
-        Some(Ok("application/json")) => {}
-        _ => return Err(anyhow!("unknown content type")),
+/// Report the state of the read-only `switch` on `GET`, turn it on with `POST` or off with
+/// `DELETE`, which callers must only allow with the admin-secret. Once turned off the queues of
+/// the `affected` installations are processed again
+async fn route_read_only<'a>(
+    method: &Method,
+    switch: &ReadOnlySwitch,
+    affected: impl Iterator<Item = &'a Installation>,
+) -> Result<Response<Body>> {
+    match *method {
+        Method::GET => {}
+        Method::POST => {
+            warn!("read-only mode enabled through the API");
+            switch.set(true);
+        }
+        Method::DELETE => {
+            info!("read-only mode disabled through the API");
+            switch.set(false);
+            for installation in affected {
+                installation.process_queue().await;
+            }
+        }
+        _ => {
+            return Ok(Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .body(Body::empty())?)
+        }
     }
 
-    let event_type = match request
-        .headers()
-        .get(EVENT_TYPE_HEADER)
-        .and_then(|h| HeaderValue::to_str(h).ok())
-        .and_then(|s| s.parse::<EventType>().ok())
-    {
-        Some(event) => event,
-        _ => return Err(anyhow!("missing valid X-GitHub-Event header")),
-    };
-
-    let delivery_id = match request
-        .headers()
-        .get(DELIVERY_ID_HEADER)
-        .and_then(|h| HeaderValue::to_str(h).ok())
-    {
-        Some(guid) => guid.to_owned(),
-        _ => return Err(anyhow!("missing valid X-GitHub-Delivery header")),
-    };
-
-    let signature = match request
-        .headers()
-        .get(SIGNATURE_HEADER)
-        .and_then(|h| HeaderValue::to_str(h).ok())
-    {
-        Some(signature) => Some(signature.to_owned()),
-        _ => None,
-    };
-
-    let body = body::to_bytes(request.into_body()).await?.to_vec();
-
-    Ok(Webhook {
-        event_type,
-        delivery_id,
-        signature,
-        body,
-    })
+    Ok(Response::new(Body::from(if switch.is_on() {
+        "read-only: on\n"
+    } else {
+        "read-only: off\n"
+    })))
+}
+
+async fn webhook_from_request(request: Request<Body>) -> Result<Webhook> {
+    let (parts, body) = request.into_parts();
+    let body = body::to_bytes(body).await?.to_vec();
+    delivery::webhook_from_parts(&parts.headers, body)
 }
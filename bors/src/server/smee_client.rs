@@ -1,12 +1,16 @@
-use super::Server;
+use super::{delivery, Server};
 use crate::Result;
+use anyhow::anyhow;
 use bytes::{Buf, BytesMut};
-use github::{EventType, Webhook};
-use log::{debug, info, trace, warn};
-use reqwest::{Client, Response};
-use serde::Deserialize;
+use github::Webhook;
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap,
+};
+use log::{debug, error, info, trace, warn};
+use reqwest::Response;
 use serde_json::value::RawValue;
-use std::{borrow::Cow, str};
+use std::{borrow::Cow, collections::HashMap, str};
 
 pub struct SmeeClient {
     uri: String,
@@ -37,7 +41,7 @@ impl SmeeClient {
     async fn run(&mut self) -> Result<()> {
         info!("Starting SmeeClient with {}", self.uri);
 
-        let client = Client::new();
+        let client = self.server.config.http_client_builder()?.build()?;
         let mut response = client
             .get(&self.uri)
             .header("Accept", "text/event-stream")
@@ -70,16 +74,31 @@ enum SmeeEvent {
     Message(Webhook),
 }
 
-#[derive(Debug, Deserialize)]
-struct SmeeMessage<'a> {
-    #[serde(rename = "x-github-event")]
-    event_type: EventType,
-    #[serde(borrow, rename = "body")]
-    event: &'a RawValue,
-    #[serde(rename = "x-github-delivery")]
-    delivery_id: String,
-    #[serde(rename = "x-hub-signature")]
-    signature: Option<String>,
+/// Build the webhook relayed by smee in `data`, a json object of the headers the delivery was
+/// made with along with its `body`. The headers are checked exactly like those of a delivery made
+/// to the webhook endpoint
+pub(super) fn webhook_from_smee_message(data: &str) -> Result<Webhook> {
+    let message: HashMap<String, &RawValue> = serde_json::from_str(data)?;
+    let body = message
+        .get("body")
+        .ok_or_else(|| anyhow!("smee message without a body"))?
+        .get()
+        .as_bytes()
+        .to_owned();
+
+    // Besides the headers, messages carry the `body`, `query` and `timestamp` of the delivery,
+    // none of which are strings
+    let mut headers = HeaderMap::new();
+    for (name, value) in &message {
+        let value = serde_json::from_str::<String>(value.get())
+            .ok()
+            .and_then(|value| HeaderValue::from_str(&value).ok());
+        if let (Ok(name), Some(value)) = (HeaderName::from_bytes(name.as_bytes()), value) {
+            headers.append(name, value);
+        }
+    }
+
+    delivery::webhook_from_parts(&headers, body)
 }
 
 struct ServerSentEvent<'a> {
@@ -133,16 +152,14 @@ impl<'b> SmeeEventParser<'b> {
         let event = match (server_sent_event.event, server_sent_event.data) {
             (Some("ready"), Some(_)) => SmeeEvent::Ready,
             (Some("ping"), Some(_)) => SmeeEvent::Ping,
-            (None, Some(data)) => {
-                let smee_msg: SmeeMessage = serde_json::from_str(&data)?;
-                let webhook = Webhook {
-                    event_type: smee_msg.event_type,
-                    delivery_id: smee_msg.delivery_id,
-                    signature: smee_msg.signature,
-                    body: smee_msg.event.get().as_bytes().to_owned(),
-                };
-                SmeeEvent::Message(webhook)
-            }
+            (None, Some(data)) => match webhook_from_smee_message(&data) {
+                Ok(webhook) => SmeeEvent::Message(webhook),
+                // Dropped like a delivery to the webhook endpoint would be, without restarting
+                Err(e) => {
+                    error!("parsing smee payload: {:#?}", e);
+                    return Ok(None);
+                }
+            },
             _ => return Ok(None),
         };
 
@@ -184,7 +201,7 @@ impl<'b> SmeeEventParser<'b> {
                 "data" => {
                     if let Some(value) = value {
                         if let Some(data) = data.as_mut() {
-                            data.to_mut().push_str("\n");
+                            data.to_mut().push('\n');
                             data.to_mut().push_str(value);
                         } else {
                             data = Some(Cow::Borrowed(value));
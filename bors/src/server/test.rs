@@ -1,22 +1,50 @@
-use super::Server;
-use crate::config::GithubConfig;
-use hyper::{Body, Method, Request, StatusCode, Uri, Version};
+use super::{
+    auth,
+    delivery::{self, RecentDeliveries, SharedDeliveries},
+    ingest,
+    smee_client::webhook_from_smee_message,
+    webhook_from_request, Server,
+};
+use crate::{config::GithubConfig, secret::Secret};
+use github::test_fixtures::{EventPayload, PullRequestEventBuilder};
+use hyper::{Body, HeaderMap, Method, Request, StatusCode, Uri, Version};
+use std::sync::Arc;
+
+// Build the `Request<Body>` Github would deliver for the provided payload
+fn webhook_request<P: EventPayload>(payload: &P) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/github")
+        .header("content-type", "application/json")
+        .header("User-Agent", "GitHub-Hookshot/f1003bc")
+        .header("X-GitHub-Delivery", "bbb-11-22-3")
+        .header("X-GitHub-Event", P::EVENT_TYPE.as_str())
+        .body(Body::from(payload.to_json()))
+        .unwrap()
+}
 
 #[tokio::test]
 async fn pull_request_event() {
     static PAYLOAD: &str = include_str!("../../test-input/pull-request-event-payload");
     let request = request_from_raw_http(PAYLOAD);
 
-    let mut service = Server::new(GithubConfig {
-        github_api_token: "".to_string(),
-        webhook_secret: None,
-    });
+    let mut service = Server::new(github_config()).unwrap();
 
     let resp = service.route_github(request).await.unwrap();
     assert_eq!(resp.status(), StatusCode::OK);
     println!("{:?}", resp);
 }
 
+#[tokio::test]
+async fn built_pull_request_event() {
+    let request = webhook_request(&PullRequestEventBuilder::new("opened"));
+
+    let mut service = Server::new(github_config()).unwrap();
+
+    let resp = service.route_github(request).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
 // Super quick and dirty parsing of raw http into a `Request<Body>` type.
 // This assumes that the content is JSON
 fn request_from_raw_http(raw: &'static str) -> Request<Body> {
@@ -52,3 +80,258 @@ fn parse_method_line(line: &str) -> (Method, Uri, Version) {
 
     (method, uri, version)
 }
+
+#[tokio::test]
+async fn smee_deliveries_are_parsed_like_http_ones() {
+    let payload = PullRequestEventBuilder::new("opened");
+    let http = webhook_from_request(webhook_request(&payload))
+        .await
+        .unwrap();
+
+    // smee relays the headers lowercased alongside the payload
+    let data = format!(
+        r#"{{
+            "content-type": "application/json",
+            "user-agent": "GitHub-Hookshot/f1003bc",
+            "x-github-delivery": "bbb-11-22-3",
+            "x-github-event": "pull_request",
+            "x-hub-signature-256": "sha256=abc",
+            "query": {{}},
+            "timestamp": 1602849600000,
+            "body": {}
+        }}"#,
+        String::from_utf8(payload.to_json()).unwrap()
+    );
+    let smee = webhook_from_smee_message(&data).unwrap();
+
+    assert_eq!(smee.event_type, http.event_type);
+    assert_eq!(smee.delivery_id, http.delivery_id);
+    assert_eq!(smee.body, http.body);
+    assert_eq!(smee.signatures, vec!["sha256=abc"]);
+
+    // The same headers are required of both
+    let data = data.replace(r#""content-type": "application/json","#, "");
+    assert!(webhook_from_smee_message(&data).is_err());
+}
+
+#[test]
+fn recent_deliveries() {
+    let mut recent = RecentDeliveries::default();
+    assert!(recent.insert("0"));
+    assert!(!recent.insert("0"));
+
+    for id in 1..1000 {
+        assert!(recent.insert(&id.to_string()));
+    }
+    assert!(!recent.insert("0"));
+
+    // The least recent delivery is forgotten to make room
+    assert!(recent.insert("1000"));
+    assert!(recent.insert("0"));
+    assert!(!recent.insert("999"));
+
+    recent.remove("999");
+    assert!(recent.insert("999"));
+}
+
+#[test]
+fn shared_deliveries() {
+    let dir = std::env::temp_dir().join(format!("bors-deliveries-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // Instances sharing the directory only handle a delivery once between them
+    let a = SharedDeliveries::new(dir.clone());
+    let b = SharedDeliveries::new(dir.clone());
+    assert!(a.insert("bbb-11-22-3").unwrap());
+    assert!(!b.insert("bbb-11-22-3").unwrap());
+    assert!(!a.insert("bbb-11-22-3").unwrap());
+
+    b.remove("bbb-11-22-3").unwrap();
+    assert!(b.insert("bbb-11-22-3").unwrap());
+
+    // Ids can't name files outside of the directory
+    assert!(a.insert("../bbb").is_err());
+    assert!(a.insert("").is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn forwarded_deliveries_are_parsed_like_http_ones() {
+    let payload = PullRequestEventBuilder::new("opened");
+    let mut request = webhook_request(&payload);
+    for (header, signature) in &[
+        ("X-Hub-Signature", "sha1=abc"),
+        ("X-Hub-Signature-256", "sha256=def"),
+    ] {
+        request
+            .headers_mut()
+            .append(*header, signature.parse().unwrap());
+    }
+    let http = webhook_from_request(request).await.unwrap();
+
+    let secret = Secret::new("ingest");
+    let headers = ingest::forwarded_headers(&http, &secret).unwrap();
+    let forwarded = delivery::webhook_from_parts(&headers, http.body.clone()).unwrap();
+
+    assert_eq!(forwarded.event_type, http.event_type);
+    assert_eq!(forwarded.delivery_id, http.delivery_id);
+    assert_eq!(forwarded.body, http.body);
+    assert_eq!(forwarded.signatures, http.signatures);
+
+    assert!(auth::has_bearer_token(&headers, &secret));
+    assert!(!auth::has_bearer_token(&headers, &Secret::new("other")));
+    assert!(!auth::has_bearer_token(&HeaderMap::new(), &secret));
+}
+
+#[tokio::test]
+async fn ingest_route() {
+    let ingest_request = |authorization: Option<&str>| {
+        let mut request = webhook_request(&PullRequestEventBuilder::new("opened"));
+        *request.uri_mut() = "/ingest".parse().unwrap();
+        if let Some(authorization) = authorization {
+            request
+                .headers_mut()
+                .insert("Authorization", authorization.parse().unwrap());
+        }
+        request
+    };
+
+    // Disabled without an ingest-secret
+    let mut server = Server::new(github_config()).unwrap();
+    let resp = server
+        .route_http_request(ingest_request(Some("Bearer ingest")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let mut server = Server::new(GithubConfig {
+        ingest_secret: Some(Secret::new("ingest")),
+        ..github_config()
+    })
+    .unwrap();
+    for authorization in &[None, Some("Bearer other")] {
+        let resp = server
+            .route_http_request(ingest_request(*authorization))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    let resp = server
+        .route_http_request(ingest_request(Some("Bearer ingest")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Replicas don't accept forwarded deliveries themselves
+    let mut replica = server.forwarding_to("http://leader:3000").unwrap();
+    let resp = replica
+        .route_http_request(ingest_request(Some("Bearer ingest")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn read_only_requires_admin_secret() {
+    let read_only_request = |method: Method, authorization: Option<&str>| {
+        let mut request = Request::builder().method(method).uri("/read-only");
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+        request.body(Body::empty()).unwrap()
+    };
+
+    // Switching is disabled without an admin-secret, while its state can still be read
+    let mut server = Server::new(github_config()).unwrap();
+    let resp = server
+        .route_http_request(read_only_request(Method::POST, None))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert!(!server.read_only.is_on());
+
+    let mut server = Server::new(GithubConfig {
+        admin_secret: Some(Secret::new("admin")),
+        ..github_config()
+    })
+    .unwrap();
+    for (method, authorization) in [
+        (Method::POST, None),
+        (Method::POST, Some("Bearer other")),
+        (Method::DELETE, None),
+    ] {
+        let resp = server
+            .route_http_request(read_only_request(method, authorization))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    assert!(!server.read_only.is_on());
+
+    let resp = server
+        .route_http_request(read_only_request(Method::POST, Some("Bearer admin")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(server.read_only.is_on());
+    let resp = server
+        .route_http_request(read_only_request(Method::GET, None))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+fn github_config() -> GithubConfig {
+    GithubConfig {
+        github_api_token: Some(Secret::new("")),
+        app: None,
+        webhook_secret: None,
+        webhook_url: None,
+        fix_webhook_events: false,
+        verify_webhook_source: false,
+        behind_proxy: false,
+        graphql_batching: false,
+        graphql_persisted_queries: false,
+        read_only: false,
+        templates_dir: None,
+        proxy: None,
+        no_proxy: None,
+        ca_bundle: None,
+        danger_accept_invalid_certs: false,
+        deliveries_dir: None,
+        admin_secret: None,
+        ingest_secret: None,
+    }
+}
+
+#[tokio::test]
+async fn tenant_routing() {
+    let tenants = vec![("acme".to_owned(), Server::new(github_config()).unwrap())]
+        .into_iter()
+        .collect();
+    let server = Server::new(github_config()).unwrap().with_tenants(tenants);
+
+    let request = Request::get("/tenants/acme/repos?x=1")
+        .body(Body::empty())
+        .unwrap();
+    let (tenant, request) = server.clone().resolve_tenant(request).unwrap().unwrap();
+    assert!(Arc::ptr_eq(
+        &tenant.counter,
+        &server.tenants["acme"].counter
+    ));
+    assert_eq!(request.uri(), "/repos?x=1");
+
+    let request = Request::get("/tenants/acme").body(Body::empty()).unwrap();
+    let (_, request) = server.clone().resolve_tenant(request).unwrap().unwrap();
+    assert_eq!(request.uri(), "/");
+
+    let request = Request::get("/tenants/other/github")
+        .body(Body::empty())
+        .unwrap();
+    assert!(server.clone().resolve_tenant(request).unwrap().is_none());
+
+    let request = Request::get("/github").body(Body::empty()).unwrap();
+    let (untenanted, _) = server.clone().resolve_tenant(request).unwrap().unwrap();
+    assert!(Arc::ptr_eq(&untenanted.counter, &server.counter));
+}
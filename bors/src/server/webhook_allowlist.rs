@@ -0,0 +1,177 @@
+//! Verification that webhooks are delivered from Github's published address ranges
+//!
+//! This is defense-in-depth on top of webhook signatures: Github publishes the address ranges it
+//! delivers webhooks from via its meta API, which are periodically refreshed and used to reject
+//! deliveries from anywhere else.
+
+use crate::{graphql::GithubClient, Result};
+use anyhow::anyhow;
+use hyper::{header::HeaderMap, Body, Request};
+use log::{info, warn};
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// How often the address ranges are refreshed from the meta API
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+
+/// A range of addresses in CIDR notation, e.g. `192.30.252.0/22`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u32,
+}
+
+impl Cidr {
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.network, addr.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len).unwrap_or(0);
+                u32::from(network) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len).unwrap_or(0);
+                u128::from(network) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (network, prefix_len) = match s.split_once('/') {
+            Some((network, prefix_len)) => (network.parse::<IpAddr>()?, Some(prefix_len.parse()?)),
+            None => (s.parse::<IpAddr>()?, None),
+        };
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len.unwrap_or(max_len);
+
+        if prefix_len > max_len {
+            return Err(anyhow!("invalid prefix length in '{}'", s));
+        }
+
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+}
+
+/// The address ranges webhooks are accepted from
+///
+/// Until the ranges have been loaded every delivery is rejected.
+#[derive(Clone, Debug, Default)]
+pub struct WebhookAllowlist {
+    ranges: Arc<RwLock<Vec<Cidr>>>,
+}
+
+impl WebhookAllowlist {
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        self.ranges
+            .read()
+            .unwrap()
+            .iter()
+            .any(|range| range.contains(addr))
+    }
+
+    pub async fn refresh(&self, github: &GithubClient) -> Result<()> {
+        let meta = github.meta().get().await?;
+        let ranges = meta
+            .hooks
+            .iter()
+            .map(|range| range.parse())
+            .collect::<Result<Vec<Cidr>>>()?;
+
+        info!("loaded {} webhook address ranges", ranges.len());
+        *self.ranges.write().unwrap() = ranges;
+        Ok(())
+    }
+
+    /// Periodically refresh the address ranges, keeping the previous ranges if a refresh fails
+    pub async fn start(self, github: GithubClient) -> Result<()> {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.refresh(&github).await {
+                warn!("unable to refresh webhook address ranges: {:#}", e);
+            }
+        }
+    }
+}
+
+/// The address of the client which made `request`
+///
+/// When behind a proxy the address is taken from the last entry of the `X-Forwarded-For` header,
+/// which is the one appended by the proxy itself and so can't be spoofed by the client.
+pub fn client_addr(
+    request: &Request<Body>,
+    remote_addr: SocketAddr,
+    behind_proxy: bool,
+) -> Option<IpAddr> {
+    if behind_proxy {
+        forwarded_for(request.headers())
+    } else {
+        Some(remote_addr.ip())
+    }
+}
+
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get_all(FORWARDED_FOR_HEADER)
+        .iter()
+        .next_back()?
+        .to_str()
+        .ok()?
+        .rsplit(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{client_addr, Cidr};
+    use hyper::{Body, Request};
+    use std::net::{IpAddr, SocketAddr};
+
+    #[test]
+    fn cidr() {
+        let range: Cidr = "192.30.252.0/22".parse().unwrap();
+        assert!(range.contains("192.30.255.1".parse().unwrap()));
+        assert!(range.contains("::ffff:192.30.252.7".parse().unwrap()));
+        assert!(!range.contains("192.30.248.1".parse().unwrap()));
+
+        let range: Cidr = "2606:50c0::/32".parse().unwrap();
+        assert!(range.contains("2606:50c0:8000::153".parse().unwrap()));
+        assert!(!range.contains("2606:50c1::1".parse().unwrap()));
+
+        let any: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains("10.0.0.1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn forwarded_for() {
+        let remote: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let request = Request::builder()
+            .header("X-Forwarded-For", "1.2.3.4, 140.82.115.10")
+            .body(Body::empty())
+            .unwrap();
+
+        let github: IpAddr = "140.82.115.10".parse().unwrap();
+        assert_eq!(client_addr(&request, remote, true), Some(github));
+        assert_eq!(client_addr(&request, remote, false), Some(remote.ip()));
+
+        let request = Request::builder().body(Body::empty()).unwrap();
+        assert_eq!(client_addr(&request, remote, true), None);
+    }
+}
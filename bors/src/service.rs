@@ -4,8 +4,25 @@ use crate::{
     server::{Installation, Server, SmeeClient},
     Config, Result,
 };
-use futures::future::try_join_all;
+use futures::{channel::mpsc, future::try_join_all, Future, StreamExt};
+use log::error;
+use std::{collections::HashMap, time::Duration};
 use structopt::StructOpt;
+use tokio::time::Instant;
+
+/// How often each repository's queue is checked against its SLA, if one is configured
+const SLA_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often PRs are considered for auto-land, so that they're queued once its window opens even
+/// without webhook activity
+const AUTO_LAND_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How often each repository's activity is sampled into its daily snapshot
+const ACTIVITY_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the queue is processed while nothing else happens, returning PRs whose land failed
+/// more than `failed-status-hours` ago to review
+const FAILED_STATUS_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
 
 #[derive(StructOpt)]
 pub struct ServeOptions {
@@ -15,11 +32,66 @@ pub struct ServeOptions {
     #[structopt(long)]
     /// smee.io URL
     smee: Option<String>,
+
+    #[structopt(long)]
+    /// Start with writes to Github disabled for every repo, also enabled by setting
+    /// BORS_READ_ONLY=1
+    read_only: bool,
+
+    #[structopt(long, value_name = "URL")]
+    /// Run as an ingest replica, checking and deduping webhook deliveries before forwarding them
+    /// to the bors instance at URL rather than processing them. Requires `ingest-secret` to be
+    /// configured, along with a `deliveries-dir` shared with the other replicas
+    forward_to: Option<String>,
+}
+
+impl ServeOptions {
+    fn read_only(&self) -> bool {
+        self.read_only || std::env::var("BORS_READ_ONLY").is_ok_and(|v| v == "1" || v == "true")
+    }
 }
 
 pub async fn run_serve(config: Config, options: &ServeOptions) -> Result<()> {
+    if let Some(leader) = &options.forward_to {
+        return run_ingest_replica(config, leader, options).await;
+    }
+
     let mut tasks = Vec::new();
-    let server = Server::new(config.github.clone());
+    let (installer, auto_installs) = mpsc::unbounded();
+    let mut tenants = HashMap::new();
+    for tenant in config.tenant {
+        let (installer, auto_installs) = mpsc::unbounded();
+        let server = Server::new(tenant.github.clone())?.with_orgs(tenant.org, installer);
+        if options.read_only() {
+            server.read_only().set(true);
+        }
+        tenants.insert(tenant.name.clone(), server.clone());
+
+        // A tenant's repos failing to start up mustn't take down the other tenants
+        let name = tenant.name;
+        for repo in tenant.repo {
+            let (server, github, git) = (server.clone(), tenant.github.clone(), tenant.git.clone());
+            let name = name.clone();
+            tokio::spawn(async move {
+                let repo_name = repo.repo().to_string();
+                if let Err(e) = start_event_processor(server, repo, github, git).await {
+                    error!("{}: {} - Unable to start: {:#}", name, repo_name, e);
+                }
+            });
+        }
+        tokio::spawn(start_auto_installs(
+            server,
+            auto_installs,
+            tenant.github,
+            tenant.git,
+        ));
+    }
+    let server = Server::new(config.github.clone())?
+        .with_orgs(config.org.clone(), installer)
+        .with_tenants(tenants);
+    if options.read_only() {
+        server.read_only().set(true);
+    }
 
     // Start up the server and optionally a smee client
     let addr = ([0, 0, 0, 0], options.port).into();
@@ -32,7 +104,9 @@ pub async fn run_serve(config: Config, options: &ServeOptions) -> Result<()> {
     }
 
     // Start up all of the configured repos
-    let Config { repo, github, git } = config;
+    let Config {
+        repo, github, git, ..
+    } = config;
     for repo in repo {
         let github = github.clone();
         let git = git.clone();
@@ -42,11 +116,69 @@ pub async fn run_serve(config: Config, options: &ServeOptions) -> Result<()> {
         )));
     }
 
+    tasks.push(tokio::spawn(start_auto_installs(
+        server,
+        auto_installs,
+        github,
+        git,
+    )));
+
     // Join all of the spawned tasks
     try_join_all(tasks).await?;
     Ok(())
 }
 
+/// Serve the webhook endpoint, forwarding deliveries to `leader` instead of processing them. No
+/// repos are started, so that the leader remains the only instance writing to their queues
+async fn run_ingest_replica(config: Config, leader: &str, options: &ServeOptions) -> Result<()> {
+    let leader = leader.trim_end_matches('/');
+    let mut tenants = HashMap::new();
+    for tenant in config.tenant {
+        let url = format!("{}/tenants/{}", leader, tenant.name);
+        tenants.insert(
+            tenant.name,
+            Server::new(tenant.github)?.forwarding_to(&url)?,
+        );
+    }
+    let server = Server::new(config.github)?
+        .forwarding_to(leader)?
+        .with_tenants(tenants);
+
+    let mut tasks = Vec::new();
+    let addr = ([0, 0, 0, 0], options.port).into();
+    tasks.push(tokio::spawn(server.clone().start(addr)));
+
+    if let Some(smee_uri) = &options.smee {
+        let smee_client = SmeeClient::with_uri(smee_uri.clone(), server);
+        tasks.push(tokio::spawn(smee_client.start()));
+    }
+
+    try_join_all(tasks).await?;
+    Ok(())
+}
+
+/// Start up repos automatically installed through their org's webhook as they're discovered
+async fn start_auto_installs(
+    server: Server,
+    mut auto_installs: mpsc::UnboundedReceiver<RepoConfig>,
+    github: GithubConfig,
+    git: GitConfig,
+) -> Result<()> {
+    while let Some(repo) = auto_installs.next().await {
+        let (server, github, git) = (server.clone(), github.clone(), git.clone());
+        tokio::spawn(async move {
+            let repo_name = repo.repo().to_string();
+            if let Err(e) = start_event_processor(server, repo, github, git).await {
+                error!(
+                    "{} - Unable to start automatic installation: {:#}",
+                    repo_name, e
+                );
+            }
+        });
+    }
+    Ok(())
+}
+
 async fn start_event_processor(
     mut server: Server,
     repo: RepoConfig,
@@ -54,13 +186,111 @@ async fn start_event_processor(
     git: GitConfig,
 ) -> Result<()> {
     let repo_clone = repo.clone();
-    let (tx, event_processor) =
-        tokio::task::spawn_blocking(move || EventProcessor::new(repo_clone, &github, &git))
-            .await??;
+    let git_clone = git.clone();
+    let read_only = server.read_only().clone();
+    let (tx, event_processor) = tokio::task::spawn_blocking(move || {
+        EventProcessor::new(repo_clone, &github, &git_clone, read_only)
+    })
+    .await??;
+    let graphql_health = event_processor.graphql_health();
+    let auth_health = event_processor.auth_health();
+    let sync_status = event_processor.sync_status();
+    let sla_status = event_processor.sla_status();
+    let read_only = event_processor.read_only();
     tokio::spawn(event_processor.start());
 
-    let installation = Installation::new(repo, tx);
+    if let Some(hours) = git.maintenance_interval_hours {
+        let tx = tx.clone();
+        tokio::spawn(run_periodically(
+            Duration::from_secs(hours * 60 * 60),
+            move || {
+                let tx = tx.clone();
+                async move { tx.git_maintenance().await }
+            },
+        ));
+    }
+
+    if let Some(minutes) = git.prefetch_interval_minutes {
+        let tx = tx.clone();
+        tokio::spawn(run_periodically(
+            Duration::from_secs(minutes * 60),
+            move || {
+                let tx = tx.clone();
+                async move { tx.prefetch().await }
+            },
+        ));
+    }
+
+    if repo.sla().max_queue_wait().is_some() {
+        let tx = tx.clone();
+        tokio::spawn(run_periodically(SLA_CHECK_INTERVAL, move || {
+            let tx = tx.clone();
+            async move { tx.check_sla().await }
+        }));
+    }
+
+    // Keeps the elapsed time of the summary statuses up to date while nothing else happens
+    if repo.summary_status() {
+        let tx = tx.clone();
+        tokio::spawn(run_periodically(
+            repo.summary_status_interval(),
+            move || {
+                let tx = tx.clone();
+                async move { tx.process_queue().await }
+            },
+        ));
+    }
+
+    if repo.auto_land().is_enabled() {
+        let tx = tx.clone();
+        tokio::spawn(run_periodically(AUTO_LAND_INTERVAL, move || {
+            let tx = tx.clone();
+            async move { tx.auto_land().await }
+        }));
+    }
+
+    {
+        let tx = tx.clone();
+        tokio::spawn(run_periodically(FAILED_STATUS_CHECK_INTERVAL, move || {
+            let tx = tx.clone();
+            async move { tx.process_queue().await }
+        }));
+    }
+
+    {
+        let tx = tx.clone();
+        tokio::spawn(run_periodically(ACTIVITY_SNAPSHOT_INTERVAL, move || {
+            let tx = tx.clone();
+            async move { tx.snapshot_activity().await }
+        }));
+    }
+
+    let installation = Installation::new(
+        repo,
+        tx,
+        graphql_health,
+        auth_health,
+        sync_status,
+        sla_status,
+        read_only,
+    );
     server.add_installation(installation).await;
 
     Ok(())
 }
+
+/// Periodically send a request to an event processor, e.g. to run git maintenance on its checkout
+async fn run_periodically<F, Fut>(period: Duration, send: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<(), mpsc::SendError>>,
+{
+    let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        if send().await.is_err() {
+            // The event processor has shut down
+            return;
+        }
+    }
+}
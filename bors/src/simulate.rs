@@ -0,0 +1,371 @@
+//! Offline simulation of the merge queue
+//!
+//! Simulating how a set of PRs would make their way through the queue, using the same ordering
+//! and timeout policies as the real queue, gives a rough idea of the throughput and wait times to
+//! expect when tuning a repository's configuration.
+
+use crate::{
+    config::{Config, RepoConfig},
+    queue::QueueEntry,
+    snapshot::StateSnapshot,
+    state::{Priority, Repo, Status, StatusType},
+    Result,
+};
+use anyhow::anyhow;
+use std::{
+    fmt,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct SimulateOptions {
+    #[structopt(long)]
+    /// Repository whose configuration to use, in the form 'owner/name'. Defaults to the first
+    /// configured repository
+    repo: Option<Repo>,
+
+    #[structopt(long, parse(from_os_str))]
+    /// Simulate the PRs queued in a snapshot produced by `export-state` instead of synthesizing
+    /// PRs
+    snapshot: Option<PathBuf>,
+
+    #[structopt(long, default_value = "20")]
+    /// Number of PRs to synthesize
+    pulls: usize,
+
+    #[structopt(long, default_value = "0.1")]
+    /// Fraction of synthesized PRs which are high priority
+    high_priority: f64,
+
+    #[structopt(long, default_value = "0.1")]
+    /// Fraction of synthesized PRs which are low priority
+    low_priority: f64,
+
+    #[structopt(long, default_value = "0")]
+    /// Minutes between synthesized PRs being queued, 0 queues them all at once
+    arrival_minutes: u64,
+
+    #[structopt(long, default_value = "30")]
+    /// Average number of minutes CI takes to run
+    ci_minutes: u64,
+
+    #[structopt(long, default_value = "0.25")]
+    /// Maximum fraction by which a CI run's duration varies from the average
+    ci_jitter: f64,
+
+    #[structopt(long, default_value = "0.1")]
+    /// Fraction of CI runs which fail
+    failure_rate: f64,
+
+    #[structopt(long)]
+    /// Seed used to generate PRs and CI results, defaults to the current time
+    seed: Option<u64>,
+}
+
+/// A PR as seen by the simulator
+#[derive(Debug)]
+struct SimulatedPull {
+    number: u64,
+    priority: Priority,
+    queued_at: Instant,
+    ci_duration: Duration,
+    passes: bool,
+}
+
+#[derive(Debug)]
+enum Outcome {
+    Landed,
+    Failed,
+    TimedOut,
+}
+
+#[derive(Debug)]
+struct SimulatedRun {
+    priority: Priority,
+    wait: Duration,
+    outcome: Outcome,
+}
+
+/// A small xorshift generator so that simulations are reproducible given a seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Scramble the seed since xorshift produces poor initial values from small seeds, and
+        // gets stuck at 0
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    /// A number in the range [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+pub fn run_simulate(config: &Config, options: &SimulateOptions) -> Result<()> {
+    let repo_config = match &options.repo {
+        Some(repo) => config.repo.iter().find(|r| r.repo() == repo),
+        None => config.repo.first(),
+    }
+    .ok_or_else(|| anyhow!("no matching repository configured"))?;
+
+    let seed = options.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    });
+    let mut rng = Rng::new(seed);
+    let start = Instant::now();
+
+    let pulls = match &options.snapshot {
+        Some(path) => load_pulls(path, repo_config, options, &mut rng)?,
+        None => synthesize_pulls(start, options, &mut rng),
+    };
+
+    println!(
+        "Simulating {} PRs for {} (seed {})\n",
+        pulls.len(),
+        repo_config.repo(),
+        seed
+    );
+    println!("{}", simulate(start, pulls, repo_config.timeout()));
+
+    Ok(())
+}
+
+fn ci_run(options: &SimulateOptions, rng: &mut Rng) -> (Duration, bool) {
+    let jitter = 1.0 + options.ci_jitter * (2.0 * rng.next_f64() - 1.0);
+    let duration = Duration::from_secs(options.ci_minutes * 60).mul_f64(jitter.max(0.0));
+    let passes = rng.next_f64() >= options.failure_rate;
+
+    (duration, passes)
+}
+
+fn synthesize_pulls(
+    start: Instant,
+    options: &SimulateOptions,
+    rng: &mut Rng,
+) -> Vec<SimulatedPull> {
+    (0..options.pulls)
+        .map(|i| {
+            let roll = rng.next_f64();
+            let priority = if roll < options.high_priority {
+                Priority::High
+            } else if roll < options.high_priority + options.low_priority {
+                Priority::Low
+            } else {
+                Priority::Normal
+            };
+            let (ci_duration, passes) = ci_run(options, rng);
+
+            SimulatedPull {
+                number: i as u64 + 1,
+                priority,
+                queued_at: start + Duration::from_secs(options.arrival_minutes * 60) * i as u32,
+                ci_duration,
+                passes,
+            }
+        })
+        .collect()
+}
+
+/// Load the PRs which are queued or being tested from a state snapshot
+fn load_pulls(
+    path: &std::path::Path,
+    config: &RepoConfig,
+    options: &SimulateOptions,
+    rng: &mut Rng,
+) -> Result<Vec<SimulatedPull>> {
+    let json = std::fs::read(path)?;
+    let snapshot = StateSnapshot::from_json(&json, config.repo())?;
+
+    Ok(snapshot
+        .pulls
+        .iter()
+        .filter_map(|pull| {
+            let queued_at = match &pull.status {
+                Status::Queued(queued_at) => *queued_at,
+                Status::Testing {
+                    tests_started_at, ..
+                } => *tests_started_at,
+                Status::InReview | Status::Failed { .. } | Status::Canary { .. } => return None,
+            };
+            let (ci_duration, passes) = ci_run(options, rng);
+
+            Some(SimulatedPull {
+                number: pull.number,
+                priority: pull.priority(config),
+                queued_at,
+                ci_duration,
+                passes,
+            })
+        })
+        .collect())
+}
+
+/// Run `pulls` through the queue one at a time, in the same order the real queue would test them
+fn simulate(start: Instant, mut pulls: Vec<SimulatedPull>, timeout: Duration) -> SimulationReport {
+    let mut now = start;
+    let mut runs = Vec::new();
+
+    while !pulls.is_empty() {
+        // If nothing has been queued yet skip ahead to the next arrival
+        let next_arrival = pulls.iter().map(|p| p.queued_at).min().unwrap();
+        now = now.max(next_arrival);
+
+        let (idx, _) = pulls
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.queued_at <= now)
+            .min_by_key(|(_, p)| {
+                QueueEntry::new(p.number, StatusType::Queued, p.priority, Some(p.queued_at))
+            })
+            .unwrap();
+        let pull = pulls.swap_remove(idx);
+
+        let wait = now.saturating_duration_since(pull.queued_at.max(start));
+        let outcome = if pull.ci_duration > timeout {
+            now += timeout;
+            Outcome::TimedOut
+        } else {
+            now += pull.ci_duration;
+            if pull.passes {
+                Outcome::Landed
+            } else {
+                Outcome::Failed
+            }
+        };
+
+        runs.push(SimulatedRun {
+            priority: pull.priority,
+            wait,
+            outcome,
+        });
+    }
+
+    SimulationReport {
+        elapsed: now.saturating_duration_since(start),
+        runs,
+    }
+}
+
+#[derive(Debug)]
+struct SimulationReport {
+    elapsed: Duration,
+    runs: Vec<SimulatedRun>,
+}
+
+impl SimulationReport {
+    fn landed(&self) -> usize {
+        self.count(|o| matches!(o, Outcome::Landed))
+    }
+
+    fn count(&self, f: impl Fn(&Outcome) -> bool) -> usize {
+        self.runs.iter().filter(|r| f(&r.outcome)).count()
+    }
+
+    /// PRs landed per hour of simulated time
+    fn throughput(&self) -> f64 {
+        let hours = self.elapsed.as_secs_f64() / 3600.0;
+        if hours > 0.0 {
+            self.landed() as f64 / hours
+        } else {
+            0.0
+        }
+    }
+}
+
+fn minutes(duration: Duration) -> String {
+    format!("{:.1}m", duration.as_secs_f64() / 60.0)
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Total time:  {}", minutes(self.elapsed))?;
+        writeln!(f, "Landed:      {}", self.landed())?;
+        writeln!(
+            f,
+            "Failed:      {}",
+            self.count(|o| matches!(o, Outcome::Failed))
+        )?;
+        writeln!(
+            f,
+            "Timed out:   {}",
+            self.count(|o| matches!(o, Outcome::TimedOut))
+        )?;
+        writeln!(f, "Throughput:  {:.2} PRs/hour", self.throughput())?;
+        writeln!(f)?;
+        writeln!(f, "Wait time until testing started:")?;
+        writeln!(f, "| Priority | PRs | Mean | p50 | p90 | Max |")?;
+        writeln!(f, "|----------|-----|------|-----|-----|-----|")?;
+
+        for (name, priority) in [
+            ("all", None),
+            ("high", Some(Priority::High)),
+            ("normal", Some(Priority::Normal)),
+            ("low", Some(Priority::Low)),
+        ] {
+            let mut waits = self
+                .runs
+                .iter()
+                .filter(|r| priority.map(|p| p == r.priority).unwrap_or(true))
+                .map(|r| r.wait)
+                .collect::<Vec<_>>();
+            if waits.is_empty() {
+                continue;
+            }
+            waits.sort_unstable();
+
+            let percentile = |p: usize| waits[(waits.len() - 1) * p / 100];
+            let mean = waits.iter().sum::<Duration>() / waits.len() as u32;
+            writeln!(
+                f,
+                "| {} | {} | {} | {} | {} | {} |",
+                name,
+                waits.len(),
+                minutes(mean),
+                minutes(percentile(50)),
+                minutes(percentile(90)),
+                minutes(percentile(100)),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn high_priority_jumps_the_queue() {
+        let start = Instant::now();
+        let pull = |number, priority, passes| SimulatedPull {
+            number,
+            priority,
+            queued_at: start,
+            ci_duration: Duration::from_secs(600),
+            passes,
+        };
+        let pulls = vec![
+            pull(1, Priority::Normal, true),
+            pull(2, Priority::Low, true),
+            pull(3, Priority::High, false),
+        ];
+
+        let report = simulate(start, pulls, Duration::from_secs(3600));
+
+        assert_eq!(report.elapsed, Duration::from_secs(1800));
+        assert_eq!(report.landed(), 2);
+        let order = report.runs.iter().map(|r| r.priority).collect::<Vec<_>>();
+        assert_eq!(order, [Priority::High, Priority::Normal, Priority::Low]);
+        assert_eq!(report.runs[2].wait, Duration::from_secs(1200));
+    }
+}
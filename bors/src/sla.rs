@@ -0,0 +1,204 @@
+//! Monitoring of the merge queue against the service levels configured in `[repo.sla]`
+
+use crate::{
+    config::RepoConfig,
+    queue::{MergeQueue, PullRequests},
+    state::Status,
+};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Number of consecutive failures at the head of the queue considered to be blocking it
+const REPEATED_FAILURE_THRESHOLD: usize = 2;
+
+/// The most likely reason that PRs are waiting longer than expected
+#[derive(Clone, Debug, PartialEq)]
+pub enum SuspectedCause {
+    /// Github rejected the API token so the queue can't make progress
+    ExpiredToken,
+    /// PRs are queued but none is being tested
+    Stalled,
+    /// The most recent test runs at the head of the queue, of these PRs, have all failed
+    RepeatedFailures(Vec<u64>),
+    /// Test runs take long enough that only a few PRs can land within the SLA
+    LongCi(Duration),
+    /// More PRs are queued than can be landed within the SLA
+    Backlog(usize),
+}
+
+impl fmt::Display for SuspectedCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuspectedCause::ExpiredToken => write!(f, "Github is rejecting the API token"),
+            SuspectedCause::Stalled => write!(f, "the queue is not testing any PR"),
+            SuspectedCause::RepeatedFailures(numbers) => {
+                let numbers = numbers
+                    .iter()
+                    .map(|n| format!("#{}", n))
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "the last {} test runs at the head failed ({})",
+                    numbers.len(),
+                    numbers.join(", ")
+                )
+            }
+            SuspectedCause::LongCi(average) => write!(
+                f,
+                "test runs are taking {} minutes on average",
+                average.as_secs() / 60
+            ),
+            SuspectedCause::Backlog(queued) => write!(f, "{} PRs are queued", queued),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SlaBreach {
+    /// The PR which has been waiting the longest
+    pub number: u64,
+    pub waited: Duration,
+    pub cause: SuspectedCause,
+}
+
+impl fmt::Display for SlaBreach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} has been queued for {} minutes, likely because {}",
+            self.number,
+            self.waited.as_secs() / 60,
+            self.cause
+        )
+    }
+}
+
+/// The SLA breach, if any, found by the most recent check of a repository's queue
+#[derive(Clone, Debug, Default)]
+pub struct SlaStatus {
+    breach: Arc<Mutex<Option<SlaBreach>>>,
+}
+
+impl SlaStatus {
+    pub fn breach(&self) -> Option<SlaBreach> {
+        self.breach.lock().unwrap().clone()
+    }
+
+    /// Record the result of a check, returning the previously recorded breach
+    pub(crate) fn record(&self, breach: Option<SlaBreach>) -> Option<SlaBreach> {
+        std::mem::replace(&mut *self.breach.lock().unwrap(), breach)
+    }
+}
+
+/// Check if any queued PR has waited longer than the configured SLA allows
+pub fn check(
+    config: &RepoConfig,
+    merge_queue: &MergeQueue,
+    pulls: &PullRequests,
+    auth_healthy: bool,
+) -> Option<SlaBreach> {
+    let max_wait = config.sla().max_queue_wait()?;
+
+    let queued = pulls
+        .values()
+        .filter_map(|pull| match pull.status {
+            Status::Queued(queued_at) => Some((pull.number, queued_at.elapsed())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let (number, waited) = queued.iter().max_by_key(|(_, waited)| *waited).copied()?;
+    if waited <= max_wait {
+        return None;
+    }
+
+    Some(SlaBreach {
+        number,
+        waited,
+        cause: suspected_cause(merge_queue, queued.len(), max_wait, auth_healthy),
+    })
+}
+
+fn suspected_cause(
+    merge_queue: &MergeQueue,
+    queued: usize,
+    max_wait: Duration,
+    auth_healthy: bool,
+) -> SuspectedCause {
+    if !auth_healthy {
+        return SuspectedCause::ExpiredToken;
+    }
+
+    if merge_queue.is_idle() {
+        return SuspectedCause::Stalled;
+    }
+
+    let failures = merge_queue
+        .history()
+        .rev()
+        .take_while(|run| !run.passed)
+        .map(|run| run.number)
+        .collect::<Vec<_>>();
+    if failures.len() >= REPEATED_FAILURE_THRESHOLD {
+        return SuspectedCause::RepeatedFailures(failures);
+    }
+
+    let runs = merge_queue.history().count() as u32;
+    if runs > 0 {
+        let average = merge_queue
+            .history()
+            .map(|run| run.duration)
+            .sum::<Duration>()
+            / runs;
+        // Fewer than 4 PRs can land within the SLA
+        if average * 4 > max_wait {
+            return SuspectedCause::LongCi(average);
+        }
+    }
+
+    SuspectedCause::Backlog(queued)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{suspected_cause, SuspectedCause};
+    use crate::queue::MergeQueue;
+    use std::time::Duration;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    #[test]
+    fn suspected_causes() {
+        let queue = MergeQueue::new();
+        assert_eq!(
+            suspected_cause(&queue, 3, 6 * HOUR, false),
+            SuspectedCause::ExpiredToken
+        );
+        assert_eq!(
+            suspected_cause(&queue, 3, 6 * HOUR, true),
+            SuspectedCause::Stalled
+        );
+
+        // Start testing #1
+        let mut queue: MergeQueue = serde_json::from_str(r#"{ "head": 1 }"#).unwrap();
+        assert_eq!(
+            suspected_cause(&queue, 3, 6 * HOUR, true),
+            SuspectedCause::Backlog(3)
+        );
+
+        queue.record_test_run(1, true, 2 * HOUR);
+        assert_eq!(
+            suspected_cause(&queue, 3, 6 * HOUR, true),
+            SuspectedCause::LongCi(2 * HOUR)
+        );
+
+        queue.record_test_run(2, false, HOUR);
+        queue.record_test_run(3, false, HOUR);
+        assert_eq!(
+            suspected_cause(&queue, 3, 6 * HOUR, true),
+            SuspectedCause::RepeatedFailures(vec![3, 2])
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Export and import of a repository's in-memory state
+//!
+//! A snapshot captures everything bors knows about a repository which can't be recovered by
+//! re-synchronizing with GitHub (e.g. queue positions and in-flight test runs) so that an
+//! operator can move bors to a new host or restore from a backup.
+//!
+//! It also carries the repo's audit log, which replaces the one on the new host when imported, and
+//! the ids of the webhook deliveries recently handled for the repo, so that the new host doesn't
+//! handle them again when Github redelivers them.
+
+use crate::{
+    audit::AuditEntry,
+    queue::MergeQueue,
+    secret::Secret,
+    state::{PullRequestState, Repo},
+    store::RepoData,
+    Result,
+};
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use structopt::StructOpt;
+
+/// Version of the snapshot format, bumped whenever an incompatible change is made
+///
+/// - 2: test results record the check run or commit status they came from
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StateSnapshot {
+    pub version: u32,
+    pub repo: Repo,
+    pub merge_queue: MergeQueue,
+    pub pulls: Vec<PullRequestState>,
+    /// Data from the persistent store, e.g. ignored users
+    #[serde(default)]
+    pub data: RepoData,
+    /// The entries of the audit log, oldest first
+    #[serde(default)]
+    pub audit_log: Vec<AuditEntry>,
+    /// Ids of the webhook deliveries recently handled for the repo, oldest first
+    #[serde(default)]
+    pub deliveries: Vec<String>,
+}
+
+impl StateSnapshot {
+    pub fn new(
+        repo: Repo,
+        merge_queue: MergeQueue,
+        mut pulls: Vec<PullRequestState>,
+        data: RepoData,
+        audit_log: Vec<AuditEntry>,
+        deliveries: Vec<String>,
+    ) -> Self {
+        pulls.sort_unstable_by_key(|p| p.number);
+
+        Self {
+            version: SNAPSHOT_VERSION,
+            repo,
+            merge_queue,
+            pulls,
+            data,
+            audit_log,
+            deliveries,
+        }
+    }
+
+    /// Parse a snapshot, verifying that it is compatible and intended for `repo`
+    pub fn from_json(json: &[u8], repo: &Repo) -> Result<Self> {
+        let snapshot: Self = serde_json::from_slice(json).context("invalid state snapshot")?;
+
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(anyhow!(
+                "unsupported snapshot version {}, expected {}",
+                snapshot.version,
+                SNAPSHOT_VERSION
+            ));
+        }
+
+        if &snapshot.repo != repo {
+            return Err(anyhow!(
+                "snapshot is for '{}', not '{}'",
+                snapshot.repo,
+                repo
+            ));
+        }
+
+        Ok(snapshot)
+    }
+}
+
+#[derive(StructOpt)]
+pub struct ExportStateOptions {
+    #[structopt(long)]
+    /// Repository to export, in the form 'owner/name'
+    repo: Repo,
+
+    #[structopt(long, default_value = "http://localhost:3000")]
+    /// Address of the running bors server
+    server: String,
+}
+
+#[derive(StructOpt)]
+pub struct ImportStateOptions {
+    #[structopt(long)]
+    /// Repository to import into, in the form 'owner/name'
+    repo: Repo,
+
+    #[structopt(long, default_value = "http://localhost:3000")]
+    /// Address of the running bors server
+    server: String,
+
+    #[structopt(long, parse(try_from_str = Secret::resolve))]
+    /// The server's `admin-secret`, given inline or as an `env:VAR` or `file:/path` reference
+    admin_secret: Secret,
+}
+
+fn state_url(server: &str, repo: &Repo) -> String {
+    format!(
+        "{}/repos/{}/{}/state",
+        server.trim_end_matches('/'),
+        repo.owner(),
+        repo.name()
+    )
+}
+
+/// Fetch the state of a repository from a running server and write it to stdout
+pub async fn run_export_state(options: &ExportStateOptions) -> Result<()> {
+    let response = reqwest::get(state_url(&options.server, &options.repo))
+        .await?
+        .error_for_status()?;
+    let body = response.bytes().await?;
+
+    // Round-trip through the snapshot type to validate what the server returned
+    let snapshot = StateSnapshot::from_json(&body, &options.repo)?;
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    serde_json::to_writer_pretty(&mut stdout, &snapshot)?;
+    writeln!(stdout)?;
+
+    Ok(())
+}
+
+/// Read a snapshot from stdin and load it into a running server
+pub async fn run_import_state(options: &ImportStateOptions) -> Result<()> {
+    let mut body = Vec::new();
+    std::io::stdin().read_to_end(&mut body)?;
+
+    // Validate locally first to give a better error message
+    StateSnapshot::from_json(&body, &options.repo)?;
+
+    let response = reqwest::Client::new()
+        .post(state_url(&options.server, &options.repo))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .bearer_auth(options.admin_secret.expose())
+        .body(body)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        return Err(anyhow!("import failed ({}): {}", status, message));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::StateSnapshot;
+    use crate::{
+        audit::AuditEntry,
+        golden::assert_golden,
+        queue::MergeQueue,
+        state::{PullRequestState, Repo, Status},
+    };
+    use github::Oid;
+
+    #[test]
+    fn round_trip() {
+        let repo = Repo::new("bors-rs", "bors");
+        let snapshot = StateSnapshot::new(
+            repo.clone(),
+            MergeQueue::new(),
+            Vec::new(),
+            Default::default(),
+            vec![AuditEntry::new("alice", "land", Some(1), "")],
+            vec!["72d3162e-cc78-11e3-81ab-4c9367dc0958".to_owned()],
+        );
+        let json = serde_json::to_vec(&snapshot).unwrap();
+
+        let parsed = StateSnapshot::from_json(&json, &repo).unwrap();
+        assert_eq!(parsed.repo, repo);
+        assert!(parsed.pulls.is_empty());
+        assert_eq!(parsed.audit_log.len(), 1);
+        assert_eq!(parsed.audit_log[0].actor, "alice");
+        assert_eq!(parsed.deliveries, snapshot.deliveries);
+
+        assert!(StateSnapshot::from_json(&json, &Repo::new("bors-rs", "other")).is_err());
+    }
+
+    #[test]
+    fn serialized_snapshot() {
+        let pull = PullRequestState {
+            number: 7,
+            id: 4242,
+            author: Some("octocat".to_owned()),
+            title: "Fix the frobnicator".to_owned(),
+            body: "Closes #6".to_owned(),
+            head_ref_oid: Oid::from_str("6dcb09b5b57875f334f61aebed695e2e4193db5e"),
+            head_ref_name: "frobnicator".to_owned(),
+            head_repo: Some(Repo::new("octocat", "bors")),
+            base_ref_name: "main".to_owned(),
+            base_ref_oid: Oid::from_str("c5b97d5ae6c19d5c5df71a34c7fbeeda2479ccbc"),
+            state: github::PullRequestState::Open,
+            is_draft: false,
+            locked: false,
+            approved_by: vec!["reviewer".to_owned()].into_iter().collect(),
+            approved: true,
+            approved_at: None,
+            reviews: vec![("reviewer".to_owned(), github::ReviewState::Approved)]
+                .into_iter()
+                .collect(),
+            requested_reviewers: Default::default(),
+            requested_teams: vec!["maintainers".to_owned()].into_iter().collect(),
+            maintainer_can_modify: true,
+            mergeable: true,
+            labels: vec!["bors-squash".to_owned()].into_iter().collect(),
+            status: Status::InReview,
+            project_card_id: None,
+            canary_requested: false,
+            canary_requester: None,
+            notify: vec!["bors-rs/maintainers".to_owned()],
+            last_merge_oid: None,
+            resume: false,
+            merge_when_green: false,
+            hotfix: false,
+            lane: None,
+            lane_head: None,
+            changed_files: Vec::new(),
+            blocked_since: None,
+            check_run: None,
+            queue_events: Vec::new(),
+            head_test_results: Default::default(),
+            squash_mode: Default::default(),
+            rebase: None,
+        };
+
+        let mut data = crate::store::RepoData::default();
+        data.ignored_users.insert("spammer".to_owned());
+
+        let mut entry = AuditEntry::new("alice", "ignore-user", Some(7), "spammer");
+        entry.timestamp = "2020-10-16T12:00:00Z".parse().unwrap();
+
+        let snapshot = StateSnapshot::new(
+            Repo::new("bors-rs", "bors"),
+            MergeQueue::new(),
+            vec![pull],
+            data,
+            vec![entry],
+            vec!["72d3162e-cc78-11e3-81ab-4c9367dc0958".to_owned()],
+        );
+        assert_golden("state-snapshot.json", &snapshot);
+    }
+}
@@ -1,16 +1,28 @@
 use crate::{
-    config::RepoConfig, graphql::GithubClient, project_board::ProjectBoard, queue::QueueEntry,
-    Result,
+    config::{CanaryHeadChecks, Labels, Lane, RepoConfig},
+    event_processor::BORS_CONTEXT,
+    graphql::GithubClient,
+    project_board::ProjectBoard,
+    queue::QueueEntry,
+    queue_hooks::{QueueHookEvent, QueueHookPayload},
+    templates, Result,
 };
-use github::Oid;
+use github::{
+    client::{
+        CheckRunOutputRequest, CreateCheckRunRequest, PaginationOptions, UpdateCheckRunRequest,
+    },
+    CheckStatus, Conclusion, DateTime, Oid, ReviewState,
+};
+use log::warn;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     str::FromStr,
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PullRequestState {
     pub number: u64,
     pub id: u64,
@@ -29,8 +41,24 @@ pub struct PullRequestState {
 
     pub state: github::PullRequestState,
     pub is_draft: bool,
+    /// Indicates if the PR's conversation is locked, in which case bors' comments are shown in its
+    /// `bors` check run instead
+    #[serde(default)]
+    pub locked: bool,
     pub approved_by: HashSet<String>,
     pub approved: bool,
+    /// When the PR's most recent approving review was submitted
+    #[serde(default)]
+    pub approved_at: Option<DateTime>,
+    /// The latest approving or blocking review of each reviewer
+    #[serde(default)]
+    pub reviews: HashMap<String, ReviewState>,
+    /// Users whose review was requested and who haven't reviewed the PR since
+    #[serde(default)]
+    pub requested_reviewers: BTreeSet<String>,
+    /// Slugs of the teams whose review was requested and which haven't reviewed the PR since
+    #[serde(default)]
+    pub requested_teams: BTreeSet<String>,
     pub maintainer_can_modify: bool, // Use to enable 'rebase' merging and having github know a PR has been merged
     pub mergeable: bool,
     pub labels: HashSet<String>,
@@ -39,12 +67,102 @@ pub struct PullRequestState {
     pub project_card_id: Option<u64>,
 
     pub canary_requested: bool,
+
+    /// User who requested the PR's current or pending canary, notified if it times out
+    #[serde(default)]
+    pub canary_requester: Option<String>,
+
+    /// Users and teams (without the leading '@') to mention once the PR's land completes
+    #[serde(default)]
+    pub notify: Vec<String>,
+
+    /// How the PR's commits are combined when it's labeled to be squashed
+    #[serde(default)]
+    pub squash_mode: SquashMode,
+
+    /// Whether the PR is landed by rebasing it rather than with a merge commit, when chosen with
+    /// `/land rebase+` or `/land rebase-`
+    #[serde(default)]
+    pub rebase: Option<bool>,
+
+    /// The merge commit created the last time the PR was tested for landing. Cleared when the PR
+    /// is updated with new commits
+    #[serde(default)]
+    pub last_merge_oid: Option<Oid>,
+
+    /// Indicates that the next land should re-test `last_merge_oid` instead of creating a new
+    /// merge commit
+    #[serde(default)]
+    pub resume: bool,
+
+    /// Indicates that the PR should be merged directly, bypassing the merge queue, once the checks
+    /// on its own head commit have passed
+    #[serde(default)]
+    pub merge_when_green: bool,
+
+    /// Results of the checks on the PR's head commit, tracked while `merge_when_green` is set or
+    /// while a requested canary waits on them
+    #[serde(default)]
+    pub head_test_results: HashMap<String, TestResult>,
+
+    /// Indicates that the PR is a hotfix, which preempts the PR being tested and is landed before
+    /// any other queued PR
+    #[serde(default)]
+    pub hotfix: bool,
+
+    /// The lane the PR is landed through, or `None` if its files don't all belong to one lane
+    #[serde(default)]
+    pub lane: Option<String>,
+
+    /// The head commit `lane` and `changed_files` were determined for
+    #[serde(default)]
+    pub lane_head: Option<Oid>,
+
+    /// Paths of the files changed by the PR, including the previous paths of renamed files, in
+    /// sorted order. Only tracked while the PR is queued
+    #[serde(default)]
+    pub changed_files: Vec<String>,
+
+    /// When the PR was first found blocked from landing while queued. Cleared once it's no longer
+    /// blocked or leaves the queue
+    #[serde(default)]
+    pub blocked_since: Option<DateTime>,
+
+    /// The `bors` check run created on a head commit of the PR with `check-run` enabled, as
+    /// (head commit, check run id). Only kept up to date while the commit is the PR's head
+    #[serde(default)]
+    pub check_run: Option<(Oid, u64)>,
+
+    /// Queue events of the PR which haven't been handed to the queue hooks yet
+    #[serde(skip)]
+    pub queue_events: Vec<QueueHookPayload>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TestResult {
     pub passed: bool,
     pub details_url: String,
+    pub source: BuildResultSource,
+}
+
+/// Where a build result originated from
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BuildResultSource {
+    /// A check run (or workflow run) reported through the Checks API
+    CheckRun { suite: u64, app: String },
+    /// A commit status reported through the Statuses API
+    CommitStatus { context: String },
+}
+
+impl std::fmt::Display for BuildResultSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildResultSource::CheckRun { app, .. } => write!(f, "check run from `{}`", app),
+            BuildResultSource::CommitStatus { context } => {
+                write!(f, "commit status `{}`", context)
+            }
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialOrd, PartialEq, Ord, Eq)]
@@ -52,27 +170,35 @@ pub enum StatusType {
     Testing,
     Canary,
     Queued,
+    Failed,
     InReview,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum Status {
     InReview,
-    Queued(std::time::Instant),
+    Queued(#[serde(with = "serde_instant")] Instant),
     Testing {
         merge_oid: Oid,
-        tests_started_at: std::time::Instant,
+        #[serde(with = "serde_instant")]
+        tests_started_at: Instant,
         test_results: HashMap<String, TestResult>,
     },
     Canary {
         merge_oid: Oid,
-        tests_started_at: std::time::Instant,
+        #[serde(with = "serde_instant")]
+        tests_started_at: Instant,
         test_results: HashMap<String, TestResult>,
     },
-    // Failed {
-    //     merge_oid: Oid,
-    //     test_results: HashMap<String, TestResult>,
-    // },
+    /// The PR's last land failed. It is returned to review once it's pushed to or after
+    /// `failed-status-hours`
+    Failed {
+        reason: String,
+        /// Name of the check that failed, if the land failed because of one
+        #[serde(default)]
+        check: Option<String>,
+        failed_at: DateTime,
+    },
     // Success {
     //     merge_oid: Oid,
     //     test_results: HashMap<String, TestResult>,
@@ -92,14 +218,32 @@ impl Status {
         matches!(self, Status::Canary { .. })
     }
 
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Status::Failed { .. })
+    }
+
+    /// Whether the PR is waiting on its author or reviewers rather than on bors, which is also the
+    /// case once its land has failed
+    pub fn is_in_review(&self) -> bool {
+        matches!(self, Status::InReview | Status::Failed { .. })
+    }
+
+    /// The merge commit being tested, if any
+    pub fn merge_oid(&self) -> Option<&Oid> {
+        match self {
+            Status::Testing { merge_oid, .. } | Status::Canary { merge_oid, .. } => Some(merge_oid),
+            Status::InReview | Status::Failed { .. } | Status::Queued(_) => None,
+        }
+    }
+
     pub fn queued() -> Status {
-        Status::Queued(std::time::Instant::now())
+        Status::Queued(Instant::now())
     }
 
     pub fn testing(merge_oid: Oid) -> Status {
         Status::Testing {
             merge_oid,
-            tests_started_at: std::time::Instant::now(),
+            tests_started_at: Instant::now(),
             test_results: HashMap::new(),
         }
     }
@@ -107,14 +251,23 @@ impl Status {
     pub fn canary(merge_oid: Oid) -> Status {
         Status::Canary {
             merge_oid,
-            tests_started_at: std::time::Instant::now(),
+            tests_started_at: Instant::now(),
             test_results: HashMap::new(),
         }
     }
 
+    pub fn failed(reason: impl Into<String>, check: Option<String>) -> Status {
+        Status::Failed {
+            reason: reason.into(),
+            check,
+            failed_at: DateTime::now(),
+        }
+    }
+
     pub fn status_type(&self) -> StatusType {
         match self {
             Status::InReview => StatusType::InReview,
+            Status::Failed { .. } => StatusType::Failed,
             Status::Queued(_) => StatusType::Queued,
             Status::Testing { .. } => StatusType::Testing,
             Status::Canary { .. } => StatusType::Canary,
@@ -122,6 +275,44 @@ impl Status {
     }
 }
 
+/// `Instant`s are only meaningful within the process that created them, so they are serialized as
+/// wall-clock time and converted back relative to the current time when deserialized.
+mod serde_instant {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::{Duration, Instant, SystemTime};
+
+    pub fn serialize<S: Serializer>(instant: &Instant, serializer: S) -> Result<S::Ok, S::Error> {
+        let system_time = SystemTime::now() - instant.elapsed();
+        system_time.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Instant, D::Error> {
+        let system_time = SystemTime::deserialize(deserializer)?;
+        let elapsed = system_time.elapsed().unwrap_or_default();
+        Ok(saturating_sub(Instant::now(), elapsed))
+    }
+
+    /// `now - elapsed`, or the oldest `Instant` that can be represented if that's earlier, e.g.
+    /// on platforms where `Instant`s can't be earlier than the host's boot. Restored timestamps
+    /// then stay older than any taken since, rather than collapsing to now
+    pub(super) fn saturating_sub(now: Instant, elapsed: Duration) -> Instant {
+        if let Some(instant) = now.checked_sub(elapsed) {
+            return instant;
+        }
+
+        let (mut representable, mut unrepresentable) = (Duration::ZERO, elapsed);
+        while unrepresentable - representable > Duration::from_millis(1) {
+            let mid = representable + (unrepresentable - representable) / 2;
+            if now.checked_sub(mid).is_some() {
+                representable = mid;
+            } else {
+                unrepresentable = mid;
+            }
+        }
+        now - representable
+    }
+}
+
 impl PullRequestState {
     pub fn from_pull_request(pull: &github::PullRequest) -> Self {
         let state = match pull.state {
@@ -131,7 +322,7 @@ impl PullRequestState {
 
         let labels = pull.labels.iter().map(|l| l.name.clone()).collect();
 
-        Self {
+        let mut state = Self {
             number: pull.number,
             id: pull.id,
             author: Some(pull.user.login.clone()),
@@ -144,15 +335,146 @@ impl PullRequestState {
             base_ref_oid: pull.base.sha.clone(),
             state,
             is_draft: pull.draft.unwrap_or(false),
+            locked: pull.locked,
             approved_by: HashSet::new(),
             approved: false,
+            approved_at: None,
+            reviews: HashMap::new(),
+            requested_reviewers: BTreeSet::new(),
+            requested_teams: BTreeSet::new(),
             maintainer_can_modify: pull.maintainer_can_modify.unwrap_or(false),
             mergeable: pull.mergeable.unwrap_or(false),
             labels,
             status: Status::InReview,
             project_card_id: None,
             canary_requested: false,
+            canary_requester: None,
+            notify: Vec::new(),
+            last_merge_oid: None,
+            resume: false,
+            merge_when_green: false,
+            hotfix: false,
+            lane: None,
+            lane_head: None,
+            changed_files: Vec::new(),
+            blocked_since: None,
+            check_run: None,
+            queue_events: Vec::new(),
+            head_test_results: HashMap::new(),
+            squash_mode: SquashMode::default(),
+            rebase: None,
+        };
+        state.update_review_requests(pull);
+        state
+    }
+
+    /// Check if the PR's previous land can be resumed by re-testing its last merge commit. A land
+    /// which failed isn't resumable, re-testing the same merge commit would fail it again
+    pub fn can_resume_land(&self) -> bool {
+        matches!(self.status, Status::InReview) && self.last_merge_oid.is_some()
+    }
+
+    /// Time left until the PR's most recent approval is older than `approval-max-age-days`, or
+    /// `None` if approvals don't expire
+    pub fn approval_remaining(&self, config: &RepoConfig) -> Option<Duration> {
+        let max_age = config.approval_max_age()?;
+        let age = match &self.approved_at {
+            Some(approved_at) => approved_at.elapsed(),
+            // An approval from an unknown time can't be shown to be recent enough
+            None => max_age,
+        };
+        Some(max_age.saturating_sub(age))
+    }
+
+    /// Indicates if the PR was approved, but its most recent approval is too old to count
+    pub fn approval_expired(&self, config: &RepoConfig) -> bool {
+        self.approved && self.approval_remaining(config) == Some(Duration::ZERO)
+    }
+
+    /// Indicates if the PR has an approval which satisfies `require-review`
+    pub fn has_valid_approval(&self, config: &RepoConfig) -> bool {
+        self.approved && !self.approval_expired(config)
+    }
+
+    /// Why the PR can't land as it is, e.g. because it was approved when queued but no longer is,
+    /// or `None` if nothing blocks it
+    pub fn blocked_reason(&self, config: &RepoConfig) -> Option<String> {
+        if config.require_review() && !self.has_valid_approval(config) {
+            return Some("it's missing approvals".to_owned());
         }
+
+        let changes_requested_by = self.changes_requested_by();
+        if config.strict_changes_requested() && !changes_requested_by.is_empty() {
+            return Some(format!(
+                "changes were requested by @{}",
+                changes_requested_by.join(", @")
+            ));
+        }
+
+        let review_requests = self.review_requests(config);
+        if config.block_on_review_requests() && !review_requests.is_empty() {
+            return Some(format!(
+                "reviews are still requested from @{}",
+                review_requests.join(", @")
+            ));
+        }
+
+        config
+            .blocking_labels()
+            .find(|label| self.has_label(label))
+            .map(|label| format!("it's labeled `{}`", label))
+    }
+
+    /// Record `state` as the latest review of `reviewer`
+    pub fn record_review(&mut self, reviewer: &str, state: ReviewState) {
+        match state {
+            ReviewState::Approved | ReviewState::ChangesRequested => {
+                self.reviews.insert(reviewer.to_owned(), state);
+            }
+            ReviewState::Dismissed => {
+                self.reviews.remove(reviewer);
+            }
+            ReviewState::Commented => {}
+        }
+    }
+
+    /// Replace the outstanding review requests with those of `pull`, Github's view of the PR
+    pub fn update_review_requests(&mut self, pull: &github::PullRequest) {
+        self.requested_reviewers = pull
+            .requested_reviewers
+            .iter()
+            .map(|u| u.login.clone())
+            .collect();
+        self.requested_teams = pull
+            .requested_teams
+            .iter()
+            .map(|t| t.slug.clone())
+            .collect();
+    }
+
+    /// Users and teams (as `owner/slug`) whose review is still requested, without the leading '@'
+    pub fn review_requests(&self, config: &RepoConfig) -> Vec<String> {
+        self.requested_reviewers
+            .iter()
+            .cloned()
+            .chain(
+                self.requested_teams
+                    .iter()
+                    .map(|slug| format!("{}/{}", config.owner(), slug)),
+            )
+            .collect()
+    }
+
+    /// Reviewers whose latest review requested changes
+    pub fn changes_requested_by(&self) -> Vec<&str> {
+        let mut reviewers = self
+            .reviews
+            .iter()
+            .filter(|(_, state)| **state == ReviewState::ChangesRequested)
+            .map(|(reviewer, _)| reviewer.as_str())
+            .collect::<Vec<_>>();
+        reviewers.sort_unstable();
+        reviewers
     }
 
     /// Check if either the PR is marked as being draft or if the PR title seems to indicate that
@@ -164,6 +486,20 @@ impl PullRequestState {
                 .any(|s| self.title.starts_with(s))
     }
 
+    /// Items of the markdown checklist in the PR's body which haven't been checked
+    pub fn unchecked_checklist_items(&self) -> Vec<&str> {
+        checklist_items(&self.body)
+            .filter(|(checked, _)| !checked)
+            .map(|(_, item)| item)
+            .collect()
+    }
+
+    /// Numbers of the issues the PR's body says it closes, e.g. with `Fixes #12`, in the order
+    /// they're referenced
+    pub fn linked_issues(&self) -> Vec<u64> {
+        closing_issue_references(&self.body)
+    }
+
     // Update the Head Oid of a PR and kick it out of the queue if the Oid doesn't match the
     // currently being tested 'merge_oid'
     pub async fn update_head(
@@ -173,6 +509,25 @@ impl PullRequestState {
         github: &GithubClient,
         project_board: Option<&ProjectBoard>,
     ) -> Result<()> {
+        if self.head_ref_oid != oid {
+            self.last_merge_oid = None;
+            // A canary waiting on the checks of the previous head waits on those of the new one
+            if self.canary_requested {
+                self.head_test_results.clear();
+            }
+
+            if self.merge_when_green {
+                let msg = templates::render(
+                    config,
+                    "merge-when-green-canceled-by-update",
+                    liquid::object!({}),
+                )?;
+
+                self.post_comment(config, github, &msg).await?;
+
+                self.stop_merge_when_green();
+            }
+        }
         self.head_ref_oid = oid.clone();
 
         match &self.status {
@@ -183,18 +538,10 @@ impl PullRequestState {
             Status::InReview => {}
             _ => {
                 if let Status::Testing { .. } | Status::Queued(_) = &self.status {
-                    let msg = ":exclamation: Land has been canceled due to this PR being updated with new commits. \
-                    Please issue another Land command if you want to requeue this PR.";
-
-                    github
-                        .issues()
-                        .create_comment(
-                            config.repo().owner(),
-                            config.repo().name(),
-                            self.number,
-                            msg,
-                        )
-                        .await?;
+                    let msg =
+                        templates::render(config, "land-canceled-by-update", liquid::object!({}))?;
+
+                    self.post_comment(config, github, &msg).await?;
                 }
 
                 self.update_status(Status::InReview, config, github, project_board)
@@ -234,18 +581,9 @@ impl PullRequestState {
             )
         {
             let msg =
-                ":exclamation: Land has been canceled due to this PR's base ref being changed. \
-                Please issue another Land command if you want to requeue this PR.";
+                templates::render(config, "land-canceled-by-base-change", liquid::object!({}))?;
 
-            github
-                .issues()
-                .create_comment(
-                    config.repo().owner(),
-                    config.repo().name(),
-                    self.number,
-                    msg,
-                )
-                .await?;
+            self.post_comment(config, github, &msg).await?;
 
             self.update_status(Status::InReview, config, github, project_board)
                 .await?;
@@ -257,19 +595,199 @@ impl PullRequestState {
     pub async fn update_status(
         &mut self,
         status: Status,
-        _config: &RepoConfig,
+        config: &RepoConfig,
         github: &GithubClient,
         project_board: Option<&ProjectBoard>,
     ) -> Result<()> {
+        // A resume or hotfix only applies to the land it was requested for, and a PR that is
+        // returned to review, e.g. by canceling its land, should no longer be merged once its
+        // checks pass
+        if status.is_in_review() {
+            self.resume = false;
+            self.hotfix = false;
+            self.stop_merge_when_green();
+        }
+        if !status.is_queued() {
+            self.blocked_since = None;
+        }
+        let event = QueueHookEvent::of_transition(&self.status, &status);
         self.status = status;
+        if let Some(event) = event {
+            self.queue_events
+                .push(QueueHookPayload::new(event, config, self));
+        }
 
         if let Some(board) = project_board {
-            board.move_pr_to_status_column(github, &self).await?;
+            board.move_pr_to_status_column(github, self).await?;
         }
 
+        self.update_check_run(config, github, false).await;
+
         Ok(())
     }
 
+    /// Create the `bors` check run on `head_sha`, a head commit of the PR which Github requested a
+    /// check suite for, showing what bors is doing with the PR
+    pub async fn create_check_run(
+        &mut self,
+        config: &RepoConfig,
+        github: &GithubClient,
+        head_sha: &Oid,
+    ) -> Result<()> {
+        let (status, conclusion, title, summary) = self.check_run_output(false);
+        let head_sha_string = head_sha.to_string();
+        let request = CreateCheckRunRequest {
+            name: BORS_CONTEXT,
+            head_sha: &head_sha_string,
+            details_url: None,
+            external_id: None,
+            status: Some(status),
+            started_at: None,
+            conclusion,
+            completed_at: None,
+            output: Some(CheckRunOutputRequest {
+                title,
+                summary: &summary,
+                text: None,
+            }),
+        };
+        let check_run = github
+            .checks()
+            .create_check_run(config.owner(), config.name(), &request)
+            .await?
+            .into_inner();
+        self.check_run = Some((head_sha.clone(), check_run.id));
+
+        Ok(())
+    }
+
+    /// Bring the PR's `bors` check run in line with its status, or mark it as landed. Failing to
+    /// update it, e.g. because bors isn't authenticated as the app which created it, isn't fatal
+    pub async fn update_check_run(&self, config: &RepoConfig, github: &GithubClient, landed: bool) {
+        self.update_check_run_output(config, github, landed, None)
+            .await;
+    }
+
+    /// Update the PR's `bors` check run, showing `text` as its details. Returns false if the PR's
+    /// head has no check run or it couldn't be updated
+    async fn update_check_run_output(
+        &self,
+        config: &RepoConfig,
+        github: &GithubClient,
+        landed: bool,
+        text: Option<&str>,
+    ) -> bool {
+        let id = match &self.check_run {
+            Some((head_sha, id)) if *head_sha == self.head_ref_oid => *id,
+            _ => return false,
+        };
+
+        let (status, conclusion, title, summary) = self.check_run_output(landed);
+        let request = UpdateCheckRunRequest {
+            status: Some(status),
+            conclusion,
+            output: Some(CheckRunOutputRequest {
+                title,
+                summary: &summary,
+                text,
+            }),
+            ..Default::default()
+        };
+        if let Err(e) = github
+            .checks()
+            .update_check_run(config.owner(), config.name(), id, &request)
+            .await
+        {
+            warn!(
+                "unable to update the bors check run of pr #{}: {}",
+                self.number, e
+            );
+            return false;
+        }
+
+        true
+    }
+
+    /// Comment `body` on the PR. While the PR's conversation is locked the comment is shown in the
+    /// PR's `bors` check run instead, so that it isn't lost
+    pub async fn post_comment(
+        &self,
+        config: &RepoConfig,
+        github: &GithubClient,
+        body: &str,
+    ) -> Result<()> {
+        if !self.locked {
+            github
+                .issues()
+                .create_comment(config.owner(), config.name(), self.number, body)
+                .await?;
+            return Ok(());
+        }
+
+        if self
+            .update_check_run_output(config, github, false, Some(body))
+            .await
+        {
+            warn!(
+                "pr #{}: conversation is locked, the comment is shown in the bors check run",
+                self.number
+            );
+        } else {
+            warn!(
+                "pr #{}: conversation is locked and there's no bors check run, dropping the \
+                comment: {}",
+                self.number, body
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The status, conclusion, title and summary of the PR's `bors` check run
+    fn check_run_output(
+        &self,
+        landed: bool,
+    ) -> (CheckStatus, Option<Conclusion>, &'static str, String) {
+        if landed {
+            return (
+                CheckStatus::Completed,
+                Some(Conclusion::Success),
+                "Landed",
+                format!("Landed on `{}`", self.base_ref_name),
+            );
+        }
+
+        match &self.status {
+            Status::InReview | Status::Canary { .. } => (
+                CheckStatus::Queued,
+                None,
+                "Waiting for /land",
+                "Comment `/land` to queue this PR for landing".to_owned(),
+            ),
+            Status::Queued(_) => (
+                CheckStatus::Queued,
+                None,
+                "Queued for landing",
+                format!("Queued for landing on `{}`", self.base_ref_name),
+            ),
+            Status::Testing { merge_oid, .. } => (
+                CheckStatus::InProgress,
+                None,
+                "Testing",
+                format!(
+                    "Testing merge commit {} for landing on `{}`",
+                    merge_oid, self.base_ref_name
+                ),
+            ),
+            Status::Failed { reason, .. } => (
+                CheckStatus::Completed,
+                Some(Conclusion::Failure),
+                "Land failed",
+                reason.clone(),
+            ),
+        }
+    }
+
     pub async fn add_label(
         &mut self,
         config: &RepoConfig,
@@ -293,10 +811,81 @@ impl PullRequestState {
         self.labels.contains(label)
     }
 
+    /// Whether the PR is landed with a merge commit rather than by rebasing it. A choice made with
+    /// `/land rebase+` or `/land rebase-` takes precedence over the PR's merge commit label, which
+    /// takes precedence over the repo's `merge-commits`
+    pub fn lands_with_merge_commit(&self, config: &RepoConfig) -> bool {
+        match self.rebase {
+            Some(rebase) => !rebase,
+            None => self.has_label(config.labels().merge_commit()) || config.merge_commits(),
+        }
+    }
+
+    /// Fetch the paths of the files changed by the PR, including the old paths of renamed files,
+    /// sorted and without duplicates
+    pub async fn fetch_changed_files(
+        &self,
+        config: &RepoConfig,
+        github: &GithubClient,
+    ) -> Result<Vec<String>> {
+        let mut files = Vec::new();
+        for file in self.fetch_files(config, github).await? {
+            files.extend(file.previous_filename);
+            files.push(file.filename);
+        }
+
+        files.sort_unstable();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Fetch the files changed by the PR, along with the number of lines changed in each
+    pub async fn fetch_files(
+        &self,
+        config: &RepoConfig,
+        github: &GithubClient,
+    ) -> Result<Vec<github::CommitFile>> {
+        let mut files = Vec::new();
+        let mut page = None;
+        loop {
+            let response = github
+                .pulls()
+                .list_files(
+                    config.owner(),
+                    config.name(),
+                    self.number,
+                    Some(PaginationOptions {
+                        page,
+                        per_page: Some(100),
+                    }),
+                )
+                .await?;
+            page = response.pagination().next_page;
+            files.extend(response.into_inner());
+            if page.is_none() {
+                break;
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Indicates if this PR and `other` change any of the same files, in which case landing one
+    /// may break the other even if they don't conflict
+    pub fn overlaps(&self, other: &PullRequestState) -> bool {
+        self.changed_files
+            .iter()
+            .any(|path| other.changed_files.binary_search(path).is_ok())
+    }
+
     pub fn priority(&self, config: &RepoConfig) -> Priority {
-        if self.has_label(config.labels().high_priority()) {
+        self.priority_from_labels(config.labels())
+    }
+
+    fn priority_from_labels(&self, labels: &Labels) -> Priority {
+        if self.has_label(labels.high_priority()) {
             Priority::High
-        } else if self.has_label(config.labels().low_priority()) {
+        } else if self.has_label(labels.low_priority()) {
             Priority::Low
         } else {
             Priority::Normal
@@ -325,6 +914,7 @@ impl PullRequestState {
         build_name: &str,
         details_url: &str,
         conclusion: github::Conclusion,
+        source: BuildResultSource,
     ) {
         if let Status::Testing {
             ref mut test_results,
@@ -340,14 +930,265 @@ impl PullRequestState {
                 TestResult {
                     details_url: details_url.to_owned(),
                     passed: matches!(conclusion, github::Conclusion::Success),
+                    source,
                 },
             );
         }
     }
 
-    pub fn to_queue_entry(&self, config: &RepoConfig) -> QueueEntry {
+    /// Indicates if the results of the checks on the PR's head commit are being tracked
+    pub fn tracks_head_checks(&self, config: &RepoConfig) -> bool {
+        self.merge_when_green
+            || (self.canary_requested && config.canary_head_checks() != CanaryHeadChecks::Ignore)
+    }
+
+    /// Request a canary of the PR, started once the checks on its head commit allow it
+    pub async fn request_canary(
+        &mut self,
+        requester: &str,
+        config: &RepoConfig,
+        github: &GithubClient,
+    ) -> Result<()> {
+        self.canary_requested = true;
+        self.canary_requester = Some(requester.to_owned());
+
+        // Checks may have completed before the canary was requested, so seed the results with
+        // those reported so far
+        if config.canary_head_checks() != CanaryHeadChecks::Ignore {
+            let results = self.fetch_head_test_results(config, github).await?;
+            self.head_test_results.extend(results);
+        }
+        Ok(())
+    }
+
+    /// Stop waiting on a requested canary, once it's started or been canceled
+    pub fn clear_canary_request(&mut self) {
+        self.canary_requested = false;
+        if !self.merge_when_green {
+            self.head_test_results.clear();
+        }
+    }
+
+    /// Record the result of a check on the PR's head commit, if waiting to merge it or to canary
+    /// it once its checks pass
+    pub fn add_head_build_result(
+        &mut self,
+        build_name: &str,
+        details_url: &str,
+        conclusion: github::Conclusion,
+        source: BuildResultSource,
+        config: &RepoConfig,
+    ) {
+        if self.tracks_head_checks(config) {
+            self.head_test_results.insert(
+                build_name.to_owned(),
+                TestResult {
+                    details_url: details_url.to_owned(),
+                    passed: matches!(conclusion, github::Conclusion::Success),
+                    source,
+                },
+            );
+        }
+    }
+
+    /// Fetch the results of the trusted checks reported so far on the PR's head commit
+    pub async fn fetch_head_test_results(
+        &self,
+        config: &RepoConfig,
+        github: &GithubClient,
+    ) -> Result<HashMap<String, TestResult>> {
+        Self::fetch_test_results(&self.head_ref_oid, config, github).await
+    }
+
+    /// Fetch the results of the trusted checks reported so far on `oid`
+    async fn fetch_test_results(
+        oid: &Oid,
+        config: &RepoConfig,
+        github: &GithubClient,
+    ) -> Result<HashMap<String, TestResult>> {
+        let oid = oid.to_string();
+        let mut check_runs = Vec::new();
+        let mut page = None;
+        loop {
+            let options = PaginationOptions {
+                page,
+                per_page: Some(100),
+            };
+            let (pagination, _, list) = github
+                .checks()
+                .list_check_runs_for_ref(config.owner(), config.name(), &oid, options)
+                .await?
+                .into_parts();
+            check_runs.extend(list.check_runs);
+
+            page = pagination.next_page;
+            if page.is_none() {
+                break;
+            }
+        }
+
+        let mut statuses = Vec::new();
+        let mut page = None;
+        loop {
+            let options = PaginationOptions {
+                page,
+                per_page: Some(100),
+            };
+            let (pagination, _, combined) = github
+                .repos()
+                .get_combined_status(config.owner(), config.name(), &oid, options)
+                .await?
+                .into_parts();
+            statuses.extend(combined.statuses);
+
+            page = pagination.next_page;
+            if page.is_none() {
+                break;
+            }
+        }
+
+        let mut results = HashMap::new();
+        for check_run in check_runs {
+            let conclusion = match (check_run.status, check_run.conclusion) {
+                (github::CheckStatus::Completed, Some(conclusion)) => conclusion,
+                _ => continue,
+            };
+            let source = BuildResultSource::CheckRun {
+                suite: check_run.check_suite.id,
+                app: check_run.app.slug.clone(),
+            };
+            if config.is_trusted_build_result(&check_run.name, &source) {
+                results.insert(
+                    check_run.name,
+                    TestResult {
+                        details_url: check_run.details_url,
+                        passed: matches!(conclusion, github::Conclusion::Success),
+                        source,
+                    },
+                );
+            }
+        }
+        for status in statuses {
+            let passed = match status.state {
+                github::StatusEventState::Pending => continue,
+                github::StatusEventState::Success => true,
+                github::StatusEventState::Failure | github::StatusEventState::Error => false,
+            };
+            let source = BuildResultSource::CommitStatus {
+                context: status.context.clone(),
+            };
+            if config.is_trusted_build_result(&status.context, &source) {
+                results.insert(
+                    status.context,
+                    TestResult {
+                        details_url: status.target_url.unwrap_or_default(),
+                        passed,
+                        source,
+                    },
+                );
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Bring the PR up to date with Github: its description, labels, reviews and head commit, as
+    /// well as the results of the checks bors is waiting on, in case a webhook was missed. bors' own
+    /// state, e.g. the PR's place in the queue, is kept unless the head commit changed
+    pub async fn refresh(
+        &mut self,
+        config: &RepoConfig,
+        github: &GithubClient,
+        project_board: Option<&ProjectBoard>,
+    ) -> Result<()> {
+        let fresh = github
+            .pull(config.owner(), config.name(), self.number)
+            .await?;
+
+        self.update_head(fresh.head_ref_oid.clone(), config, github, project_board)
+            .await?;
+        self.author = fresh.author;
+        self.title = fresh.title;
+        self.body = fresh.body;
+        self.head_ref_name = fresh.head_ref_name;
+        self.head_repo = fresh.head_repo;
+        self.base_ref_name = fresh.base_ref_name;
+        self.base_ref_oid = fresh.base_ref_oid;
+        self.state = fresh.state;
+        self.is_draft = fresh.is_draft;
+        self.locked = fresh.locked;
+        self.approved = fresh.approved;
+        self.approved_at = fresh.approved_at;
+        self.reviews = fresh.reviews;
+        self.requested_reviewers = fresh.requested_reviewers;
+        self.requested_teams = fresh.requested_teams;
+        self.maintainer_can_modify = fresh.maintainer_can_modify;
+        self.mergeable = fresh.mergeable;
+        self.labels = fresh.labels;
+
+        if self.tracks_head_checks(config) {
+            self.head_test_results = self.fetch_head_test_results(config, github).await?;
+        }
+        if let Status::Testing { merge_oid, .. } | Status::Canary { merge_oid, .. } = &self.status {
+            let results = Self::fetch_test_results(merge_oid, config, github).await?;
+            if let Status::Testing { test_results, .. } | Status::Canary { test_results, .. } =
+                &mut self.status
+            {
+                for (name, result) in results {
+                    test_results.entry(name).or_insert(result);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Carry over the files changed by the PR, and the lane they put it in, from `previous`, the
+    /// PR as it was before being fetched from Github again, as long as its head hasn't moved since
+    /// they were fetched
+    pub fn keep_changed_files(&mut self, previous: PullRequestState) {
+        if previous.lane_head.as_ref() == Some(&self.head_ref_oid) {
+            self.lane = previous.lane;
+            self.lane_head = previous.lane_head;
+            self.changed_files = previous.changed_files;
+        }
+    }
+
+    /// Carry over bors' own state of the PR, e.g. its place in the queue or the merge commit being
+    /// tested, from `saved`, the PR as it was before bors restarted. Nothing is carried over if the
+    /// PR's head or base changed in the meantime, returning `false`
+    pub fn restore_queue_state(&mut self, saved: PullRequestState) -> bool {
+        if saved.head_ref_oid != self.head_ref_oid || saved.base_ref_name != self.base_ref_name {
+            return false;
+        }
+
+        self.status = saved.status;
+        self.canary_requested = saved.canary_requested;
+        self.canary_requester = saved.canary_requester;
+        self.notify = saved.notify;
+        self.squash_mode = saved.squash_mode;
+        self.rebase = saved.rebase;
+        self.last_merge_oid = saved.last_merge_oid;
+        self.resume = saved.resume;
+        self.merge_when_green = saved.merge_when_green;
+        self.head_test_results = saved.head_test_results;
+        self.hotfix = saved.hotfix;
+        self.lane = saved.lane;
+        self.lane_head = saved.lane_head;
+        self.changed_files = saved.changed_files;
+        self.blocked_since = saved.blocked_since;
+        self.check_run = saved.check_run;
+        true
+    }
+
+    pub fn stop_merge_when_green(&mut self) {
+        self.merge_when_green = false;
+        self.head_test_results.clear();
+    }
+
+    pub fn to_queue_entry(&self, labels: &Labels) -> QueueEntry {
         let timestamp = match &self.status {
-            Status::InReview => None,
+            Status::InReview | Status::Failed { .. } => None,
             Status::Queued(timestamp) => Some(*timestamp),
             Status::Testing {
                 tests_started_at, ..
@@ -360,7 +1201,7 @@ impl PullRequestState {
         QueueEntry::new(
             self.number,
             self.status.status_type(),
-            self.priority(config),
+            self.priority_from_labels(labels),
             timestamp,
         )
     }
@@ -374,30 +1215,133 @@ pub enum TestSuiteResult {
 }
 
 impl TestSuiteResult {
+    /// Result of testing a PR for landing, which requires the checks from every test branch
     pub fn new(
         tests_started_at: std::time::Instant,
         test_results: &HashMap<String, TestResult>,
         config: &RepoConfig,
     ) -> Self {
-        // Check if there were any test failures from configured checks
-        if let Some((name, result)) = config
-            .checks()
-            .filter_map(|name| test_results.get(name).map(|result| (name, result)))
+        Self::from_checks(
+            tests_started_at,
+            test_results,
+            config.required_checks(),
+            Some(config.timeout()),
+            config,
+        )
+    }
+
+    /// Result of testing a PR landed through `lane`, which is only pushed to the lane's test
+    /// branch and so requires the repository-wide checks and those of the lane
+    pub fn lane(
+        tests_started_at: std::time::Instant,
+        test_results: &HashMap<String, TestResult>,
+        lane: &Lane,
+        config: &RepoConfig,
+    ) -> Self {
+        Self::from_checks(
+            tests_started_at,
+            test_results,
+            config.checks().chain(lane.checks()),
+            Some(config.timeout()),
+            config,
+        )
+    }
+
+    /// Result of the checks on a PR's own head commit, which only requires the repository-wide
+    /// checks. The PR's own CI isn't started by bors so it never times out
+    pub fn head(test_results: &HashMap<String, TestResult>, config: &RepoConfig) -> Self {
+        Self::from_checks(
+            std::time::Instant::now(),
+            test_results,
+            config.checks(),
+            None,
+            config,
+        )
+    }
+
+    /// Result of the checks on a PR's own head commit before it's merged directly. As with
+    /// [`head`](Self::head) the repository-wide checks are required, but when none are configured
+    /// every check reported on the head has to pass instead, and the PR isn't merged until at least
+    /// one has been reported
+    pub fn merge_when_green(
+        test_results: &HashMap<String, TestResult>,
+        config: &RepoConfig,
+    ) -> Self {
+        if config.checks().next().is_some() {
+            return Self::head(test_results, config);
+        }
+
+        let trusted = test_results
+            .iter()
+            .filter(|(name, result)| config.is_trusted_build_result(name, &result.source))
+            .collect::<Vec<_>>();
+        match trusted
+            .iter()
+            .find(|(name, result)| !result.passed && !config.is_allowed_failure(name))
+        {
+            Some((name, result)) => TestSuiteResult::Failed {
+                name: (*name).to_owned(),
+                result: (*result).to_owned(),
+            },
+            None if trusted.is_empty() => TestSuiteResult::Pending,
+            None => TestSuiteResult::Passed,
+        }
+    }
+
+    /// Result of a canary run, which is only pushed to a single branch and so only requires the
+    /// repository-wide checks. Canaries have their own timeout so that a wedged canary is
+    /// canceled independently of the land timeout
+    pub fn canary(
+        tests_started_at: std::time::Instant,
+        test_results: &HashMap<String, TestResult>,
+        config: &RepoConfig,
+    ) -> Self {
+        Self::from_checks(
+            tests_started_at,
+            test_results,
+            config.checks(),
+            Some(config.canary_timeout()),
+            config,
+        )
+    }
+
+    fn from_checks<'a>(
+        tests_started_at: std::time::Instant,
+        test_results: &HashMap<String, TestResult>,
+        checks: impl Iterator<Item = &'a str> + Clone,
+        timeout: Option<std::time::Duration>,
+        config: &RepoConfig,
+    ) -> Self {
+        // A result only counts towards a check if its source is trusted to report it
+        let result_of = |name: &str| {
+            test_results
+                .get(name)
+                .filter(|result| config.is_trusted_build_result(name, &result.source))
+        };
+
+        // Check if there were any test failures from configured checks, other than those which
+        // are allowed to fail
+        if let Some((name, result)) = checks
+            .clone()
+            .filter(|name| !config.is_allowed_failure(name))
+            .filter_map(|name| result_of(name).map(|result| (name, result)))
             .find(|(_name, result)| !result.passed)
         {
             TestSuiteResult::Failed {
                 name: name.to_owned(),
                 result: result.to_owned(),
             }
-        // Check if all tests have completed and passed
-        } else if config
-            .checks()
-            .map(|name| test_results.get(name))
-            .all(|result| result.map(|r| r.passed).unwrap_or(false))
+        // Check if all tests have completed and passed, or are allowed to fail
+        } else if checks
+            .map(|name| (name, result_of(name)))
+            .all(|(name, result)| match result {
+                Some(result) => result.passed || config.is_allowed_failure(name),
+                None => false,
+            })
         {
             TestSuiteResult::Passed
         // Check if the test has timed-out
-        } else if tests_started_at.elapsed() >= config.timeout() {
+        } else if timeout.is_some_and(|timeout| tests_started_at.elapsed() >= timeout) {
             TestSuiteResult::TimedOut
         } else {
             TestSuiteResult::Pending
@@ -405,7 +1349,31 @@ impl TestSuiteResult {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialOrd, PartialEq, Ord, Eq, Serialize)]
+/// A check which failed but is allowed to fail, used when reporting the outcome of a land
+#[derive(Debug, Serialize)]
+pub struct AllowedFailure {
+    pub name: String,
+    pub url: String,
+}
+
+/// The checks which failed but are allowed to fail, sorted by name
+pub fn allowed_failures(
+    test_results: &HashMap<String, TestResult>,
+    config: &RepoConfig,
+) -> Vec<AllowedFailure> {
+    let mut failures = test_results
+        .iter()
+        .filter(|(name, result)| !result.passed && config.is_allowed_failure(name))
+        .map(|(name, result)| AllowedFailure {
+            name: name.to_owned(),
+            url: result.details_url.clone(),
+        })
+        .collect::<Vec<_>>();
+    failures.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    failures
+}
+
+#[derive(Clone, Copy, Debug, PartialOrd, PartialEq, Ord, Eq, Deserialize, Serialize)]
 pub enum Priority {
     High,
     Normal,
@@ -428,6 +1396,35 @@ impl FromStr for Priority {
     }
 }
 
+/// How the commits of a squashed PR are combined, chosen with `/land squash=<mode>`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SquashMode {
+    /// Keep the message of the PR's first commit
+    #[default]
+    KeepFirst,
+    /// Concatenate the messages of all of the PR's commits, with the `squash-concat` template
+    Concat,
+    /// Render the message from the PR's title and description, with the `squash-message` template
+    Template,
+}
+
+#[derive(Error, Debug)]
+#[error("invalid squash mode")]
+pub struct ParseSquashModeError;
+
+impl FromStr for SquashMode {
+    type Err = ParseSquashModeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep-first" => Ok(SquashMode::KeepFirst),
+            "concat" => Ok(SquashMode::Concat),
+            "template" => Ok(SquashMode::Template),
+            _ => Err(ParseSquashModeError),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct Repo {
     owner: String,
@@ -465,3 +1462,562 @@ impl Repo {
         format!("https://github.com/{}/{}.git", self.owner, self.name)
     }
 }
+
+impl std::fmt::Display for Repo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.owner, self.name)
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("invalid repository, expected 'owner/name'")]
+pub struct ParseRepoError;
+
+impl FromStr for Repo {
+    type Err = ParseRepoError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => {
+                Ok(Repo::new(owner, name))
+            }
+            _ => Err(ParseRepoError),
+        }
+    }
+}
+
+/// The trimmed lines of the markdown `body` which are rendered as text, i.e. which aren't in code
+/// blocks or HTML comments
+fn text_lines(body: &str) -> impl Iterator<Item = &str> {
+    let mut in_code_block = false;
+    let mut in_comment = false;
+
+    body.lines().filter_map(move |line| {
+        let line = line.trim();
+
+        if line.starts_with("```") {
+            in_code_block = !in_code_block;
+            return None;
+        }
+        if line.starts_with("<!--") {
+            in_comment = true;
+        }
+        if in_comment {
+            if line.contains("-->") {
+                in_comment = false;
+            }
+            return None;
+        }
+        if in_code_block {
+            return None;
+        }
+
+        Some(line)
+    })
+}
+
+/// Parse the items of markdown task lists (e.g. `- [x] Added tests`) in `body`, ignoring any
+/// which appear in code blocks or HTML comments
+fn checklist_items(body: &str) -> impl Iterator<Item = (bool, &str)> {
+    text_lines(body).filter_map(|line| {
+        let item = line
+            .strip_prefix("- ")
+            .or_else(|| line.strip_prefix("* "))
+            .or_else(|| line.strip_prefix("+ "))?
+            .trim_start();
+        let (checked, text) = if let Some(text) = item.strip_prefix("[ ]") {
+            (false, text)
+        } else if let Some(text) = item
+            .strip_prefix("[x]")
+            .or_else(|| item.strip_prefix("[X]"))
+        {
+            (true, text)
+        } else {
+            return None;
+        };
+
+        Some((checked, text.trim()))
+    })
+}
+
+/// Keywords which link a PR to an issue it closes
+/// https://docs.github.com/en/github/managing-your-work-on-github/linking-a-pull-request-to-an-issue
+const CLOSING_KEYWORDS: &[&str] = &[
+    "close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved",
+];
+
+/// Parse the references to issues in the same repository, e.g. `Closes #12`, which are preceded by
+/// a closing keyword in `body`, ignoring any which appear in code blocks or HTML comments
+fn closing_issue_references(body: &str) -> Vec<u64> {
+    let mut issues = Vec::new();
+    for line in text_lines(body) {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        for pair in words.windows(2) {
+            let keyword = pair[0]
+                .trim_start_matches(|c: char| !c.is_alphanumeric())
+                .trim_end_matches(':')
+                .to_lowercase();
+            if !CLOSING_KEYWORDS.contains(&keyword.as_str()) {
+                continue;
+            }
+
+            let number = pair[1]
+                .strip_prefix('#')
+                .map(|n| n.trim_end_matches(|c: char| !c.is_ascii_digit()))
+                .and_then(|n| n.parse::<u64>().ok());
+            if let Some(number) = number {
+                if !issues.contains(&number) {
+                    issues.push(number);
+                }
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        checklist_items, closing_issue_references, BuildResultSource, PullRequestState, Status,
+        StatusType, TestResult, TestSuiteResult,
+    };
+    use crate::config::RepoConfig;
+    use github::{
+        test_fixtures::{EventPayload, PullRequestEventBuilder},
+        CheckStatus, Conclusion, DateTime, Oid, ReviewState,
+    };
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant, SystemTime},
+    };
+
+    #[test]
+    fn checklist() {
+        let body = "\
+Description
+
+- [x] Added tests
+* [ ] Updated docs
+- not a task
+<!--
+- [ ] Example item
+-->
+```
+- [ ] Inside a code block
+```
+  + [X] Indented
+";
+
+        let items = checklist_items(body).collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            [
+                (true, "Added tests"),
+                (false, "Updated docs"),
+                (true, "Indented")
+            ]
+        );
+    }
+
+    #[test]
+    fn linked_issues() {
+        let body = "\
+Fixes #12, and closes: #7.
+This also (resolves #12) again, mentions #3 and fixes nothing
+<!-- Fixes #99 -->
+```
+Closes #100
+```
+CLOSED #8
+";
+
+        assert_eq!(closing_issue_references(body), [12, 7, 8]);
+    }
+
+    #[test]
+    fn allowed_failures() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+            checks = ["ci", "fuzz"]
+            allowed-failures = ["fuzz"]
+            "#,
+        )
+        .unwrap();
+        let result = |passed| TestResult {
+            passed,
+            details_url: String::new(),
+            source: BuildResultSource::CommitStatus {
+                context: String::new(),
+            },
+        };
+
+        let mut test_results = HashMap::new();
+        test_results.insert("fuzz".to_owned(), result(false));
+        assert!(matches!(
+            TestSuiteResult::new(Instant::now(), &test_results, &config),
+            TestSuiteResult::Pending
+        ));
+
+        test_results.insert("ci".to_owned(), result(true));
+        assert!(matches!(
+            TestSuiteResult::new(Instant::now(), &test_results, &config),
+            TestSuiteResult::Passed
+        ));
+        assert_eq!(super::allowed_failures(&test_results, &config).len(), 1);
+
+        test_results.insert("ci".to_owned(), result(false));
+        assert!(matches!(
+            TestSuiteResult::new(Instant::now(), &test_results, &config),
+            TestSuiteResult::Failed { .. }
+        ));
+    }
+
+    #[test]
+    fn untrusted_results_dont_count_towards_checks() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+            checks = ["ci"]
+            trusted-check-apps = ["github-actions"]
+            "#,
+        )
+        .unwrap();
+        let result = |passed, app: &str| TestResult {
+            passed,
+            details_url: String::new(),
+            source: BuildResultSource::CheckRun {
+                suite: 1,
+                app: app.to_owned(),
+            },
+        };
+        let suite = |result| {
+            let test_results = vec![("ci".to_owned(), result)].into_iter().collect();
+            (
+                TestSuiteResult::new(Instant::now(), &test_results, &config),
+                TestSuiteResult::head(&test_results, &config),
+            )
+        };
+
+        assert!(matches!(
+            suite(result(true, "github-actions")),
+            (TestSuiteResult::Passed, TestSuiteResult::Passed)
+        ));
+        assert!(matches!(
+            suite(result(false, "github-actions")),
+            (
+                TestSuiteResult::Failed { .. },
+                TestSuiteResult::Failed { .. }
+            )
+        ));
+
+        // A check of the same name from another app neither passes nor fails the suite
+        assert!(matches!(
+            suite(result(true, "impostor")),
+            (TestSuiteResult::Pending, TestSuiteResult::Pending)
+        ));
+        assert!(matches!(
+            suite(result(false, "impostor")),
+            (TestSuiteResult::Pending, TestSuiteResult::Pending)
+        ));
+    }
+
+    #[test]
+    fn changed_files_are_kept_until_pushed_to() {
+        let event = PullRequestEventBuilder::new("opened").build();
+        let mut previous = PullRequestState::from_pull_request(&event.pull_request);
+        previous.lane = Some("docs".to_owned());
+        previous.lane_head = Some(previous.head_ref_oid.clone());
+        previous.changed_files = vec!["docs/README.md".to_owned()];
+
+        let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+        pull.keep_changed_files(previous.clone());
+        assert_eq!(pull.lane.as_deref(), Some("docs"));
+        assert_eq!(pull.lane_head, previous.lane_head);
+        assert_eq!(pull.changed_files, previous.changed_files);
+
+        let mut pushed = PullRequestState::from_pull_request(&event.pull_request);
+        pushed.head_ref_oid = Oid::from_str(format!("b{:039x}", 1));
+        pushed.keep_changed_files(previous);
+        assert_eq!(pushed.lane, None);
+        assert_eq!(pushed.lane_head, None);
+        assert!(pushed.changed_files.is_empty());
+    }
+
+    #[test]
+    fn queue_state_is_restored_unless_updated() {
+        let event = PullRequestEventBuilder::new("opened").build();
+        let mut saved = PullRequestState::from_pull_request(&event.pull_request);
+        saved.status = Status::testing(Oid::from_str(format!("c{:039x}", 1)));
+        saved.notify = vec!["octocat".to_owned()];
+
+        let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+        assert!(pull.restore_queue_state(saved.clone()));
+        assert!(pull.status.is_testing());
+        assert_eq!(pull.notify, saved.notify);
+
+        // Pushed to while bors was down
+        let mut pushed = PullRequestState::from_pull_request(&event.pull_request);
+        pushed.head_ref_oid = Oid::from_str(format!("b{:039x}", 1));
+        assert!(!pushed.restore_queue_state(saved.clone()));
+        assert!(pushed.status.is_in_review());
+        assert!(pushed.notify.is_empty());
+
+        // Retargeted while bors was down
+        let mut retargeted = PullRequestState::from_pull_request(&event.pull_request);
+        retargeted.base_ref_name = "release".to_owned();
+        assert!(!retargeted.restore_queue_state(saved));
+        assert!(retargeted.status.is_in_review());
+    }
+
+    #[test]
+    fn approvals_expire() {
+        let config: RepoConfig =
+            toml::from_str("owner = \"bors-rs\"\nname = \"bors\"\napproval-max-age-days = 2")
+                .unwrap();
+        let days_ago = |days: u64| {
+            Some(DateTime::from(
+                SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60),
+            ))
+        };
+        let mut pull = PullRequestState::from_pull_request(
+            &PullRequestEventBuilder::new("opened").build().pull_request,
+        );
+        pull.approved = true;
+
+        pull.approved_at = days_ago(1);
+        let remaining = pull.approval_remaining(&config).unwrap();
+        assert!(
+            remaining > Duration::from_secs(23 * 60 * 60),
+            "{:?}",
+            remaining
+        );
+        assert!(
+            remaining <= Duration::from_secs(24 * 60 * 60),
+            "{:?}",
+            remaining
+        );
+        assert!(!pull.approval_expired(&config));
+        assert!(pull.has_valid_approval(&config));
+
+        pull.approved_at = days_ago(3);
+        assert_eq!(pull.approval_remaining(&config), Some(Duration::ZERO));
+        assert!(pull.approval_expired(&config));
+        assert!(!pull.has_valid_approval(&config));
+
+        // An approval from an unknown time can't be shown to be recent enough
+        pull.approved_at = None;
+        assert!(pull.approval_expired(&config));
+
+        // Without a maximum age approvals never expire
+        let config: RepoConfig = toml::from_str("owner = \"bors-rs\"\nname = \"bors\"").unwrap();
+        assert_eq!(pull.approval_remaining(&config), None);
+        assert!(pull.has_valid_approval(&config));
+
+        // A PR which isn't approved has nothing to expire
+        pull.approved = false;
+        assert!(!pull.approval_expired(&config));
+        assert!(!pull.has_valid_approval(&config));
+    }
+
+    #[test]
+    fn merge_when_green_needs_a_result() {
+        let config: RepoConfig = toml::from_str("owner = \"bors-rs\"\nname = \"bors\"").unwrap();
+        let result = |passed| TestResult {
+            passed,
+            details_url: String::new(),
+            source: BuildResultSource::CommitStatus {
+                context: String::new(),
+            },
+        };
+
+        // Without any configured checks nothing is known about the head until a check reports
+        let mut test_results = HashMap::new();
+        assert!(matches!(
+            TestSuiteResult::merge_when_green(&test_results, &config),
+            TestSuiteResult::Pending
+        ));
+
+        test_results.insert("ci".to_owned(), result(true));
+        assert!(matches!(
+            TestSuiteResult::merge_when_green(&test_results, &config),
+            TestSuiteResult::Passed
+        ));
+
+        test_results.insert("lint".to_owned(), result(false));
+        assert!(matches!(
+            TestSuiteResult::merge_when_green(&test_results, &config),
+            TestSuiteResult::Failed { name, .. } if name == "lint"
+        ));
+
+        // Configured checks are required as usual
+        let config: RepoConfig =
+            toml::from_str("owner = \"bors-rs\"\nname = \"bors\"\nchecks = [\"ci\"]").unwrap();
+        assert!(matches!(
+            TestSuiteResult::merge_when_green(&HashMap::new(), &config),
+            TestSuiteResult::Pending
+        ));
+        assert!(matches!(
+            TestSuiteResult::merge_when_green(&test_results, &config),
+            TestSuiteResult::Passed
+        ));
+    }
+
+    #[test]
+    fn failed_status() {
+        let status = Status::failed("check 'ci' failed", Some("ci".to_owned()));
+        assert!(status.is_failed());
+        assert!(status.is_in_review());
+        assert_eq!(status.status_type(), StatusType::Failed);
+
+        let json = serde_json::to_string(&status).unwrap();
+        match serde_json::from_str(&json).unwrap() {
+            Status::Failed { reason, check, .. } => {
+                assert_eq!(reason, "check 'ci' failed");
+                assert_eq!(check.as_deref(), Some("ci"));
+            }
+            status => panic!("unexpected status {:?}", status),
+        }
+    }
+
+    #[test]
+    fn restored_timestamps_keep_their_order() {
+        let queued_at = |ago: Duration| {
+            let json = serde_json::json!({ "Queued": SystemTime::now() - ago });
+            match serde_json::from_value(json).unwrap() {
+                Status::Queued(instant) => instant,
+                status => panic!("unexpected status {:?}", status),
+            }
+        };
+
+        // Older than the process, and likely the host's uptime
+        let old = queued_at(Duration::from_secs(365 * 24 * 60 * 60));
+        let recent = queued_at(Duration::from_secs(60));
+        assert!(old < recent);
+        assert!(recent.elapsed() >= Duration::from_secs(59));
+
+        // Saving and restoring again doesn't move the timestamps
+        let round_trip = |instant: Instant| {
+            let json = serde_json::to_string(&Status::Queued(instant)).unwrap();
+            match serde_json::from_str(&json).unwrap() {
+                Status::Queued(restored) => restored,
+                status => panic!("unexpected status {:?}", status),
+            }
+        };
+        let restored = round_trip(old);
+        assert!(restored < recent);
+        let drift = if restored > old {
+            restored - old
+        } else {
+            old - restored
+        };
+        assert!(drift < Duration::from_secs(1));
+
+        let now = Instant::now();
+        assert_eq!(
+            super::serde_instant::saturating_sub(now, Duration::from_secs(1)),
+            now - Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn blocked_reason() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+            require-review = true
+            strict-changes-requested = true
+            block-on-review-requests = true
+            blocking-labels = ["do-not-merge"]
+            "#,
+        )
+        .unwrap();
+        let event = PullRequestEventBuilder::new("opened").build();
+        let mut pull = super::PullRequestState::from_pull_request(&event.pull_request);
+
+        assert_eq!(
+            pull.blocked_reason(&config).as_deref(),
+            Some("it's missing approvals")
+        );
+
+        pull.approved = true;
+        assert_eq!(pull.blocked_reason(&config), None);
+
+        pull.requested_reviewers.insert("octocat".to_owned());
+        pull.requested_teams.insert("maintainers".to_owned());
+        assert_eq!(
+            pull.blocked_reason(&config).as_deref(),
+            Some("reviews are still requested from @octocat, @bors-rs/maintainers")
+        );
+
+        pull.requested_reviewers.clear();
+        pull.requested_teams.clear();
+        pull.labels.insert("do-not-merge".to_owned());
+        assert_eq!(
+            pull.blocked_reason(&config).as_deref(),
+            Some("it's labeled `do-not-merge`")
+        );
+
+        pull.record_review("octocat", ReviewState::ChangesRequested);
+        assert_eq!(
+            pull.blocked_reason(&config).as_deref(),
+            Some("changes were requested by @octocat")
+        );
+    }
+
+    #[test]
+    fn lands_with_merge_commit() {
+        let config = |merge_commits: bool| -> RepoConfig {
+            toml::from_str(&format!(
+                "owner = \"bors-rs\"\nname = \"bors\"\nmerge-commits = {}",
+                merge_commits
+            ))
+            .unwrap()
+        };
+        let event = PullRequestEventBuilder::new("opened").build();
+        let mut pull = super::PullRequestState::from_pull_request(&event.pull_request);
+
+        assert!(!pull.lands_with_merge_commit(&config(false)));
+        assert!(pull.lands_with_merge_commit(&config(true)));
+
+        let label = config(false).labels().merge_commit().to_owned();
+        pull.labels.insert(label);
+        assert!(pull.lands_with_merge_commit(&config(false)));
+
+        // `/land rebase+` and `rebase-` override both the label and the repo's default
+        pull.rebase = Some(true);
+        assert!(!pull.lands_with_merge_commit(&config(true)));
+        pull.labels.clear();
+        pull.rebase = Some(false);
+        assert!(pull.lands_with_merge_commit(&config(false)));
+    }
+
+    #[test]
+    fn check_run_output() {
+        let event = PullRequestEventBuilder::new("opened").build();
+        let mut pull = super::PullRequestState::from_pull_request(&event.pull_request);
+
+        let (status, conclusion, title, _) = pull.check_run_output(false);
+        assert!(matches!(status, CheckStatus::Queued));
+        assert!(conclusion.is_none());
+        assert_eq!(title, "Waiting for /land");
+
+        pull.status = Status::testing(Oid::from_str("abc"));
+        let (status, _, title, summary) = pull.check_run_output(false);
+        assert!(matches!(status, CheckStatus::InProgress));
+        assert_eq!(title, "Testing");
+        assert!(summary.starts_with("Testing merge commit abc"));
+
+        pull.status = Status::failed("merge conflict", None);
+        let (status, conclusion, _, summary) = pull.check_run_output(false);
+        assert!(matches!(status, CheckStatus::Completed));
+        assert!(matches!(conclusion, Some(Conclusion::Failure)));
+        assert_eq!(summary, "merge conflict");
+
+        let (_, conclusion, title, _) = pull.check_run_output(true);
+        assert!(matches!(conclusion, Some(Conclusion::Success)));
+        assert_eq!(title, "Landed");
+    }
+}
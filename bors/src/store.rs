@@ -0,0 +1,455 @@
+//! Persistent per-repository storage
+//!
+//! Data which bors needs to remember across restarts, and which can't be recovered from Github,
+//! is stored as json in `state/<owner>/<name>.json`, under `tenants/<tenant>/` for repos belonging
+//! to a tenant. The state of the merge queue and of each open PR is kept separately in
+//! `state/<owner>/<name>.queue.json`, since it's rewritten after every event. Writes go to a
+//! temporary file which is then renamed over the original so that a crash never leaves a partially
+//! written file behind.
+
+use crate::{
+    activity::{ActivitySnapshot, MAX_ACTIVITY_SNAPSHOTS},
+    config::RepoConfig,
+    insights::{CheckRunRecord, MergeSkewRecord, MAX_CHECK_RUNS, MAX_MERGE_SKEWS},
+    queue::MergeQueue,
+    state::PullRequestState,
+    Result,
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const STATE_DIR: &str = "state";
+
+/// Maximum number of executed commands remembered for replay protection
+const MAX_PROCESSED_COMMANDS: usize = 1000;
+
+/// The state directory for the repo `config` pertains to, created if it doesn't already exist
+pub fn repo_state_dir(config: &RepoConfig) -> Result<PathBuf> {
+    let mut directory = config.tenant_directory()?;
+    directory.push(STATE_DIR);
+    directory.push(config.owner());
+    fs::create_dir_all(&directory)
+        .with_context(|| format!("creating state directory {}", directory.display()))?;
+    Ok(directory)
+}
+
+/// Check that the state directory for the repo `config` pertains to is writable
+pub fn check_writable(config: &RepoConfig) -> Result<()> {
+    let probe = repo_state_dir(config)?.join(format!(".{}.probe", config.name()));
+    fs::write(&probe, b"")
+        .and_then(|()| fs::remove_file(&probe))
+        .with_context(|| format!("writing to {}", probe.display()))
+}
+
+/// Move the file at `from` to `to`, if there is one
+pub fn relocate_file(from: &Path, to: &Path) -> Result<()> {
+    if from != to && from.exists() {
+        fs::rename(from, to)
+            .with_context(|| format!("moving {} to {}", from.display(), to.display()))?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RepoData {
+    /// Users whose commands are ignored, added via `/ignore`
+    #[serde(default)]
+    pub ignored_users: BTreeSet<String>,
+
+    /// The users in `ignored_users` which were added because their org blocked them, so that
+    /// they're only removed again when that org unblocks them
+    #[serde(default)]
+    pub org_blocked_users: BTreeSet<String>,
+
+    /// The most recently executed commands, as (comment node id, command fingerprint) pairs, so
+    /// that editing a comment never executes the same command twice
+    #[serde(default)]
+    pub processed_commands: VecDeque<(String, u64)>,
+
+    /// Number of PRs queued by auto-land on the most recent day it queued any, as (days since the
+    /// epoch, count), so that the daily limit survives restarts
+    #[serde(default)]
+    pub auto_landed: (u64, u32),
+
+    /// The most recently completed checks on merge commits, oldest first, from which CI insights
+    /// are derived
+    #[serde(default)]
+    pub check_runs: VecDeque<CheckRunRecord>,
+
+    /// How far the most recently landed PRs were behind their base, oldest first
+    #[serde(default)]
+    pub merge_skews: VecDeque<MergeSkewRecord>,
+
+    /// Explicit order of the queued PRs, set via `/queue move`. PRs queued since are landed after
+    /// these, in order of priority
+    #[serde(default)]
+    pub queue_order: Vec<u64>,
+
+    /// PRs `/requeue-failed` is waiting to re-queue, in the order they're re-queued
+    #[serde(default)]
+    pub requeue_pending: VecDeque<u64>,
+
+    /// PRs merged on the most recent day any were, as (days since the epoch, numbers), from which
+    /// the day's land rate is counted
+    #[serde(default)]
+    pub landed: (u64, BTreeSet<u64>),
+
+    /// Daily snapshots of the repo's activity, oldest first
+    #[serde(default)]
+    pub activity: VecDeque<ActivitySnapshot>,
+
+    /// Preferences set by users via `/prefs`, keyed by their login
+    #[serde(default)]
+    pub user_preferences: BTreeMap<String, UserPreferences>,
+}
+
+/// A user's preferences for their interactions with bors in a repo
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct UserPreferences {
+    /// Whether PRs the user lands without `squash+` or `squash-` are squashed, or the PR's labels
+    /// decide if `None`
+    #[serde(default)]
+    pub squash: Option<bool>,
+
+    /// Whether the user can be mentioned via `/land notify=`, `true` if `None`
+    #[serde(default)]
+    pub notifications: Option<bool>,
+}
+
+impl UserPreferences {
+    pub fn wants_notifications(&self) -> bool {
+        self.notifications.unwrap_or(true)
+    }
+}
+
+impl RepoData {
+    pub fn is_command_processed(&self, comment: &str, fingerprint: u64) -> bool {
+        self.processed_commands
+            .iter()
+            .any(|(c, f)| c == comment && *f == fingerprint)
+    }
+
+    /// Number of PRs queued by auto-land on `day`, counted in days since the epoch
+    pub fn auto_landed_on(&self, day: u64) -> u32 {
+        if self.auto_landed.0 == day {
+            self.auto_landed.1
+        } else {
+            0
+        }
+    }
+
+    pub fn record_auto_landed(&mut self, day: u64) {
+        self.auto_landed = (day, self.auto_landed_on(day) + 1);
+    }
+
+    /// Number of PRs merged on `day`, counted in days since the epoch
+    pub fn landed_on(&self, day: u64) -> usize {
+        if self.landed.0 == day {
+            self.landed.1.len()
+        } else {
+            0
+        }
+    }
+
+    pub fn record_landed(&mut self, day: u64, number: u64) {
+        if self.landed.0 != day {
+            self.landed = (day, BTreeSet::new());
+        }
+        self.landed.1.insert(number);
+    }
+
+    /// Record a snapshot of the repo's activity, replacing any earlier one of the same day
+    pub fn record_activity(&mut self, snapshot: ActivitySnapshot) {
+        match self.activity.back_mut() {
+            Some(last) if last.day == snapshot.day => *last = snapshot,
+            _ => {
+                if self.activity.len() >= MAX_ACTIVITY_SNAPSHOTS {
+                    self.activity.pop_front();
+                }
+                self.activity.push_back(snapshot);
+            }
+        }
+    }
+
+    /// Record a completed check, unless the same result was already recorded, e.g. when Github
+    /// redelivers a webhook
+    pub fn record_check_run(&mut self, run: CheckRunRecord) {
+        if self.check_runs.iter().any(|r| {
+            r.name == run.name && r.merge_oid == run.merge_oid && r.started_at == run.started_at
+        }) {
+            return;
+        }
+        if self.check_runs.len() >= MAX_CHECK_RUNS {
+            self.check_runs.pop_front();
+        }
+        self.check_runs.push_back(run);
+    }
+
+    /// The preferences of `user`, or the defaults if they never set any
+    pub fn preferences(&self, user: &str) -> UserPreferences {
+        self.user_preferences.get(user).cloned().unwrap_or_default()
+    }
+
+    pub fn record_merge_skew(&mut self, skew: MergeSkewRecord) {
+        if self.merge_skews.len() >= MAX_MERGE_SKEWS {
+            self.merge_skews.pop_front();
+        }
+        self.merge_skews.push_back(skew);
+    }
+
+    pub fn record_processed_command(&mut self, comment: &str, fingerprint: u64) {
+        if self.processed_commands.len() >= MAX_PROCESSED_COMMANDS {
+            self.processed_commands.pop_front();
+        }
+        self.processed_commands
+            .push_back((comment.to_owned(), fingerprint));
+    }
+}
+
+#[derive(Debug)]
+pub struct Store {
+    path: PathBuf,
+    data: RepoData,
+}
+
+impl Store {
+    pub fn open(config: &RepoConfig) -> Result<Self> {
+        let path = repo_state_dir(config)?.join(format!("{}.json", config.name()));
+        Self::open_path(path)
+    }
+
+    /// Open the data stored at `path`
+    pub(crate) fn open_path(path: PathBuf) -> Result<Self> {
+        let data = if path.exists() {
+            let contents = fs::read(&path)?;
+            serde_json::from_slice(&contents)
+                .with_context(|| format!("parsing {}", path.display()))?
+        } else {
+            RepoData::default()
+        };
+
+        Ok(Self { path, data })
+    }
+
+    /// Move the stored data to the location for the repo `config` pertains to, after the repo was
+    /// renamed or transferred
+    pub fn relocate(&mut self, config: &RepoConfig) -> Result<()> {
+        let path = repo_state_dir(config)?.join(format!("{}.json", config.name()));
+        relocate_file(&self.path, &path)?;
+        self.path = path;
+        Ok(())
+    }
+
+    pub fn data(&self) -> &RepoData {
+        &self.data
+    }
+
+    /// Apply `f` to the stored data and persist the result
+    pub fn update<F: FnOnce(&mut RepoData)>(&mut self, f: F) -> Result<()> {
+        f(&mut self.data);
+        Self::write(&self.path, &self.data)
+    }
+
+    /// Replace all of the stored data, e.g. when importing a state snapshot
+    pub fn replace(&mut self, data: RepoData) -> Result<()> {
+        self.update(|d| *d = data)
+    }
+
+    fn write(path: &Path, data: &RepoData) -> Result<()> {
+        write_json(path, data)
+    }
+}
+
+fn write_json<T: Serialize>(path: &Path, data: &T) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(data)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// The merge queue and the open PRs as they were last saved
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct QueueData {
+    pub merge_queue: MergeQueue,
+    pub pulls: Vec<PullRequestState>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct QueueDataRef<'a> {
+    merge_queue: &'a MergeQueue,
+    pulls: Vec<&'a PullRequestState>,
+}
+
+/// Persists the merge queue and the open PRs, so that the PRs which were queued or being tested
+/// aren't dropped when bors restarts
+#[derive(Debug)]
+pub struct QueueStore {
+    path: PathBuf,
+}
+
+impl QueueStore {
+    pub fn open(config: &RepoConfig) -> Result<Self> {
+        let path = repo_state_dir(config)?.join(format!("{}.queue.json", config.name()));
+        Ok(Self::open_path(path))
+    }
+
+    /// Persist the queue state at `path`
+    pub(crate) fn open_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The last saved queue state, if any
+    pub fn load(&self) -> Result<Option<QueueData>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read(&self.path)?;
+        let data = serde_json::from_slice(&contents)
+            .with_context(|| format!("parsing {}", self.path.display()))?;
+        Ok(Some(data))
+    }
+
+    pub fn save<'a>(
+        &self,
+        merge_queue: &MergeQueue,
+        pulls: impl Iterator<Item = &'a PullRequestState>,
+    ) -> Result<()> {
+        let mut pulls = pulls.collect::<Vec<_>>();
+        pulls.sort_unstable_by_key(|p| p.number);
+        write_json(&self.path, &QueueDataRef { merge_queue, pulls })
+    }
+
+    /// Move the saved state to the location for the repo `config` pertains to, after the repo was
+    /// renamed or transferred
+    pub fn relocate(&mut self, config: &RepoConfig) -> Result<()> {
+        let path = repo_state_dir(config)?.join(format!("{}.queue.json", config.name()));
+        relocate_file(&self.path, &path)?;
+        self.path = path;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{QueueStore, RepoData, Store, UserPreferences, MAX_PROCESSED_COMMANDS};
+    use crate::{
+        activity::ActivitySnapshot,
+        queue::MergeQueue,
+        state::{PullRequestState, Status},
+    };
+    use github::test_fixtures::PullRequestEventBuilder;
+
+    #[test]
+    fn persists_across_opens() {
+        let path =
+            std::env::temp_dir().join(format!("bors-store-test-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut store = Store::open_path(path.clone()).unwrap();
+        assert!(store.data().ignored_users.is_empty());
+        store
+            .update(|d| {
+                d.ignored_users.insert("spammer".to_owned());
+            })
+            .unwrap();
+
+        let store = Store::open_path(path.clone()).unwrap();
+        assert!(store.data().ignored_users.contains("spammer"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn queue_round_trips() {
+        let dir =
+            std::env::temp_dir().join(format!("bors-queue-store-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = QueueStore::open_path(dir.join("queue.json"));
+        assert!(store.load().unwrap().is_none());
+
+        let pulls = [2, 1].map(|number| {
+            let event = PullRequestEventBuilder::new("opened")
+                .number(number)
+                .build();
+            let mut pull = PullRequestState::from_pull_request(&event.pull_request);
+            pull.status = Status::queued();
+            pull
+        });
+        store.save(&MergeQueue::new(), pulls.iter()).unwrap();
+
+        let data = store.load().unwrap().unwrap();
+        assert!(data.merge_queue.is_idle());
+        let numbers = data.pulls.iter().map(|p| p.number).collect::<Vec<_>>();
+        assert_eq!(numbers, [1, 2]);
+        assert!(data.pulls.iter().all(|p| p.status.is_queued()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn processed_commands_are_bounded() {
+        let mut data = RepoData::default();
+        for i in 0..=MAX_PROCESSED_COMMANDS as u64 {
+            data.record_processed_command("MDEyOklzc3VlQ29tbWVudDE=", i);
+        }
+
+        assert_eq!(data.processed_commands.len(), MAX_PROCESSED_COMMANDS);
+        assert!(!data.is_command_processed("MDEyOklzc3VlQ29tbWVudDE=", 0));
+        assert!(data.is_command_processed("MDEyOklzc3VlQ29tbWVudDE=", 1));
+        assert!(!data.is_command_processed("other", 1));
+    }
+
+    #[test]
+    fn activity_keeps_the_latest_snapshot_of_each_day() {
+        let snapshot = |day, landed| ActivitySnapshot {
+            day,
+            open_pulls: 4,
+            queue_length: 2,
+            landed,
+        };
+
+        let mut data = RepoData::default();
+        data.record_landed(10, 1);
+        data.record_landed(10, 1);
+        data.record_landed(10, 2);
+        assert_eq!(data.landed_on(10), 2);
+        assert_eq!(data.landed_on(11), 0);
+
+        data.record_activity(snapshot(10, 1));
+        data.record_activity(snapshot(10, data.landed_on(10)));
+        data.record_activity(snapshot(11, 0));
+        assert_eq!(data.activity.len(), 2);
+        assert_eq!(data.activity[0].landed, 2);
+        assert_eq!(data.activity[1].day, 11);
+    }
+
+    #[test]
+    fn preferences_default_for_unknown_users() {
+        let mut data = RepoData::default();
+        data.user_preferences.insert(
+            "octocat".to_owned(),
+            UserPreferences {
+                squash: Some(true),
+                notifications: Some(false),
+            },
+        );
+
+        let preferences = data.preferences("octocat");
+        assert_eq!(preferences.squash, Some(true));
+        assert!(!preferences.wants_notifications());
+
+        let preferences = data.preferences("hubot");
+        assert_eq!(preferences, UserPreferences::default());
+        assert!(preferences.wants_notifications());
+    }
+}
@@ -0,0 +1,251 @@
+//! The summary status published on the head commit of each PR, describing what bors is doing with
+//! it so users can follow its progress from the PR's checks
+
+use crate::{
+    config::RepoConfig,
+    graphql::GithubClient,
+    queue::PullRequests,
+    state::{PullRequestState, Status},
+    Result,
+};
+use github::{Oid, StatusEventState};
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Context of the summary status, distinct from the `bors` status so that requiring the latter in
+/// branch protection keeps working
+pub const SUMMARY_CONTEXT: &str = "bors/summary";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    InReview,
+    MergeConflict,
+    Queued,
+    Testing,
+    Canary,
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Summary {
+    phase: Phase,
+    state: StatusEventState,
+    description: String,
+}
+
+impl Summary {
+    fn new(phase: Phase, state: StatusEventState, description: String) -> Self {
+        Self {
+            phase,
+            state,
+            description,
+        }
+    }
+
+    /// Summary of `pull`, `queued` being the numbers of the queued PRs in the order they land
+    fn of(pull: &PullRequestState, queued: &[u64]) -> Self {
+        match &pull.status {
+            Status::Queued(_) => {
+                let description = match queued.iter().position(|n| *n == pull.number) {
+                    Some(idx) => format!("queued {} of {}", ordinal(idx + 1), queued.len()),
+                    None => "queued".to_owned(),
+                };
+                Self::new(Phase::Queued, StatusEventState::Pending, description)
+            }
+            Status::Testing {
+                tests_started_at, ..
+            } => Self::new(
+                Phase::Testing,
+                StatusEventState::Pending,
+                format!("testing ({} elapsed)", elapsed(tests_started_at.elapsed())),
+            ),
+            Status::Canary {
+                tests_started_at, ..
+            } => Self::new(
+                Phase::Canary,
+                StatusEventState::Pending,
+                format!("canary ({} elapsed)", elapsed(tests_started_at.elapsed())),
+            ),
+            // The reason for a failed merge can be too long for a status description
+            Status::Failed { check, .. } => Self::new(
+                Phase::Failed,
+                StatusEventState::Failure,
+                match check {
+                    Some(check) => format!("land failed: check '{}' failed", check),
+                    None => "land failed".to_owned(),
+                },
+            ),
+            Status::InReview if !pull.mergeable => Self::new(
+                Phase::MergeConflict,
+                StatusEventState::Failure,
+                "merge conflict".to_owned(),
+            ),
+            Status::InReview => {
+                let description = if pull.is_draft {
+                    "draft"
+                } else if pull.approved {
+                    "approved, not queued"
+                } else {
+                    "awaiting review"
+                };
+                Self::new(
+                    Phase::InReview,
+                    StatusEventState::Success,
+                    description.to_owned(),
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Published {
+    head: Oid,
+    summary: Summary,
+    at: Instant,
+}
+
+/// Tracks the summary status last published for each PR, so that it's only updated when it
+/// changes
+#[derive(Debug, Default)]
+pub struct SummaryStatuses {
+    published: HashMap<u64, Published>,
+}
+
+impl SummaryStatuses {
+    /// Publish the summary status of every PR whose summary changed. A change of phase is
+    /// published right away, while updates within the same phase, e.g. of the elapsed time or the
+    /// position in the queue, are published at most once per `summary_status_interval`
+    pub async fn update(
+        &mut self,
+        config: &RepoConfig,
+        github: &GithubClient,
+        pulls: &PullRequests,
+        queued: &[u64],
+    ) {
+        if !config.summary_status() {
+            return;
+        }
+
+        self.published
+            .retain(|number, _| pulls.contains_key(number));
+
+        let now = Instant::now();
+        for pull in pulls.values() {
+            let summary = Summary::of(pull, queued);
+            if !self.should_publish(pull, &summary, now, config.summary_status_interval()) {
+                continue;
+            }
+
+            info!(
+                "Updating summary status of pr #{}: {}",
+                pull.number, summary.description
+            );
+            if let Err(e) = publish(config, github, &pull.head_ref_oid, &summary).await {
+                warn!(
+                    "Unable to update summary status of pr #{}: {:?}",
+                    pull.number, e
+                );
+                continue;
+            }
+
+            self.published.insert(
+                pull.number,
+                Published {
+                    head: pull.head_ref_oid.clone(),
+                    summary,
+                    at: now,
+                },
+            );
+        }
+    }
+
+    fn should_publish(
+        &self,
+        pull: &PullRequestState,
+        summary: &Summary,
+        now: Instant,
+        interval: Duration,
+    ) -> bool {
+        match self.published.get(&pull.number) {
+            // PRs bors hasn't done anything with yet are left alone, unless they can't be merged
+            None => summary.phase != Phase::InReview,
+            Some(published) if published.head != pull.head_ref_oid => true,
+            Some(published) if published.summary.phase != summary.phase => true,
+            Some(published) => {
+                published.summary != *summary && now.duration_since(published.at) >= interval
+            }
+        }
+    }
+}
+
+async fn publish(
+    config: &RepoConfig,
+    github: &GithubClient,
+    oid: &Oid,
+    summary: &Summary,
+) -> Result<()> {
+    github
+        .repos()
+        .create_status(
+            config.owner(),
+            config.name(),
+            &oid.to_string(),
+            &github::client::CreateStatusRequest {
+                state: summary.state,
+                target_url: None,
+                description: Some(&summary.description),
+                context: SUMMARY_CONTEXT,
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+fn elapsed(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{elapsed, ordinal};
+    use std::time::Duration;
+
+    #[test]
+    fn descriptions() {
+        let ordinals = [1, 2, 3, 4, 11, 12, 13, 21, 22, 101, 111]
+            .iter()
+            .map(|n| ordinal(*n))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ordinals,
+            [
+                "1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "101st",
+                "111th"
+            ]
+        );
+
+        assert_eq!(elapsed(Duration::from_secs(59)), "0m");
+        assert_eq!(elapsed(Duration::from_secs(12 * 60 + 5)), "12m");
+        assert_eq!(elapsed(Duration::from_secs(65 * 60)), "1h 5m");
+    }
+}
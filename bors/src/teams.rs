@@ -0,0 +1,103 @@
+//! Expansion of `@org/team` mentions into the members of the team
+//!
+//! Commands which take users, e.g. `/land notify=`, also accept teams of the repository's
+//! organization. Their members are listed through the teams API and remembered for a while, so
+//! that a busy queue doesn't list the same team over and over.
+
+use crate::{graphql::GithubClient, Result};
+use futures::stream::TryStreamExt;
+use log::warn;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// How long the members of a team are remembered before they're listed again
+const TEAM_MEMBERS_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Default)]
+pub struct TeamMembers {
+    /// Logins of the members of each `(org, team)`, along with when they were listed
+    cache: HashMap<(String, String), (Instant, Vec<String>)>,
+}
+
+impl TeamMembers {
+    /// Replace each team among `mentions` by its members, without duplicates. A team whose
+    /// members can't be listed, e.g. because bors can't see it, is kept as it is so that Github
+    /// still notifies it
+    pub async fn expand(&mut self, github: &GithubClient, mentions: &[String]) -> Vec<String> {
+        let mut expanded = Vec::new();
+        for mention in mentions {
+            let members = match team_of(mention) {
+                Some((org, team)) => match self.members(github, org, team).await {
+                    Ok(members) => members.to_vec(),
+                    Err(e) => {
+                        warn!("unable to list the members of team '{}': {}", mention, e);
+                        vec![mention.clone()]
+                    }
+                },
+                None => vec![mention.clone()],
+            };
+
+            for member in members {
+                if !expanded.contains(&member) {
+                    expanded.push(member);
+                }
+            }
+        }
+
+        expanded
+    }
+
+    async fn members(&mut self, github: &GithubClient, org: &str, team: &str) -> Result<&[String]> {
+        let key = (org.to_owned(), team.to_owned());
+        let fresh = self
+            .cache
+            .get(&key)
+            .is_some_and(|(listed_at, _)| listed_at.elapsed() < TEAM_MEMBERS_TTL);
+
+        if !fresh {
+            let teams = github.teams();
+            let members = teams
+                .members(org, team)
+                .map_ok(|user| user.login)
+                .try_collect::<Vec<_>>()
+                .await?;
+            self.cache.insert(key.clone(), (Instant::now(), members));
+        }
+
+        Ok(&self.cache[&key].1)
+    }
+}
+
+/// The organization and slug of the team `mention` refers to, if it's of the form `org/team`
+fn team_of(mention: &str) -> Option<(&str, &str)> {
+    let mention = mention.trim_start_matches('@');
+    match mention.split_once('/') {
+        Some((org, team)) if !org.is_empty() && !team.is_empty() && !team.contains('/') => {
+            Some((org, team))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::team_of;
+
+    #[test]
+    fn team_mentions() {
+        assert_eq!(
+            team_of("bors-rs/maintainers"),
+            Some(("bors-rs", "maintainers"))
+        );
+        assert_eq!(
+            team_of("@bors-rs/maintainers"),
+            Some(("bors-rs", "maintainers"))
+        );
+        assert_eq!(team_of("octocat"), None);
+        assert_eq!(team_of("bors-rs/"), None);
+        assert_eq!(team_of("/maintainers"), None);
+        assert_eq!(team_of("a/b/c"), None);
+    }
+}
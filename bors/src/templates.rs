@@ -0,0 +1,789 @@
+//! Templates for the comments bors posts on PRs
+//!
+//! Every user-facing comment, as well as the message of a PR squashed with `squash=concat` or
+//! `squash=template`, is rendered from a [liquid](https://shopify.github.io/liquid/) template. The built-in templates for the configured locale can be overridden per repository,
+//! and an optional footer is appended to every comment. In addition to the variables listed for
+//! each template, `repo` (in the form 'owner/name') is available to all templates.
+//!
+//! Every comment ends with a hidden [`CommentMarker`], an HTML comment holding json which names the
+//! template it was rendered from, so that bors and external tooling can recognize bors' comments
+//! in a PR's thread without matching their text, which may be overridden or translated.
+
+use crate::{
+    config::{Comments, RepoConfig},
+    Result,
+};
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Start of the hidden marker at the end of each comment
+const MARKER_PREFIX: &str = "<!-- bors:";
+
+/// A built-in template: its name, the variables it is rendered with and its source
+type Template = (&'static str, &'static [&'static str], &'static str);
+
+/// Built-in templates for each supported locale
+const LOCALES: &[(&str, &[Template])] = &[("en", EN)];
+
+const EN: &[Template] = &[
+    (
+        "insufficient-privileges",
+        &["sender", "reason"],
+        "@{{ sender }}: :key: Insufficient privileges: {{ reason }}",
+    ),
+    ("help", &["help"], "{{ help }}"),
+    ("invalid-command", &["help"], ":exclamation: Invalid command\n\n{{ help }}"),
+    (
+        "closed-pr",
+        &["sender"],
+        "@{{ sender }} :exclamation: Unable to run the provided command on a closed PR",
+    ),
+    (
+        "ignore-user",
+        &["sender", "user"],
+        "@{{ sender }} :mute: Commands from @{{ user }} will now be ignored",
+    ),
+    (
+        "unignore-user",
+        &["sender", "user"],
+        "@{{ sender }} :loud_sound: Commands from @{{ user }} will no longer be ignored",
+    ),
+    (
+        "user-preferences",
+        &["sender", "squash", "notifications"],
+        "@{{ sender }} :gear: Your preferences for this repository:\n\
+        - squash: `{{ squash }}`\n\
+        - notifications: `{{ notifications }}`",
+    ),
+    (
+        "draft-pr",
+        &["sender"],
+        ":clipboard: Looks like this PR is still in progress, unable to queue for landing",
+    ),
+    (
+        "nothing-to-resume",
+        &["sender"],
+        "@{{ sender }} :exclamation: There is no previous land to resume, the PR must not be \
+        queued and can't have been updated since it was last tested. Use `/land` to start a new land",
+    ),
+    (
+        "land-canceled-by-update",
+        &[],
+        ":exclamation: Land has been canceled due to this PR being updated with new commits. \
+        Please issue another Land command if you want to requeue this PR.",
+    ),
+    (
+        "land-canceled-by-base-change",
+        &[],
+        ":exclamation: Land has been canceled due to this PR's base ref being changed. Please \
+        issue another Land command if you want to requeue this PR.",
+    ),
+    (
+        "merge-when-green-canceled-by-update",
+        &[],
+        ":exclamation: Merging once checks pass has been canceled due to this PR being updated \
+        with new commits. Please issue another Land command if you want to merge this PR.",
+    ),
+    (
+        "land-parked",
+        &["hotfix"],
+        ":parking: Testing of this PR was interrupted to land the hotfix #{{ hotfix }}, it will be \
+        re-tested once the hotfix has landed",
+    ),
+    (
+        "merge-when-green",
+        &["sender"],
+        ":hourglass: This PR will be merged once the checks on its head commit pass",
+    ),
+    (
+        "missing-approvals",
+        &["sender"],
+        "@{{ sender }} :exclamation: This PR is still missing approvals, unable to queue for landing",
+    ),
+    (
+        "changes-requested",
+        &["sender", "reviewers"],
+        "@{{ sender }} :no_entry: Changes were requested by \
+        {% for reviewer in reviewers %}{% unless forloop.first %}, {% endunless %}@{{ reviewer }}{% endfor %}, \
+        unable to queue for landing until they approve or their review is dismissed",
+    ),
+    (
+        "review-requested",
+        &["sender", "reviewers"],
+        "@{{ sender }} :no_entry: Reviews are still requested from \
+        {% for reviewer in reviewers %}{% unless forloop.first %}, {% endunless %}@{{ reviewer }}{% endfor %}, \
+        unable to queue for landing until they review the PR or the requests are removed",
+    ),
+    (
+        "approval-expired",
+        &["sender", "days"],
+        "{% if sender %}@{{ sender }} {% endif %}:hourglass: This PR's most recent approval is more \
+        than {{ days }} days old, it needs to be approved again before it can land",
+    ),
+    (
+        "queued",
+        &["approval_hours_remaining"],
+        ":calendar: Queued for landing. The approval remains valid for another \
+        {{ approval_hours_remaining }} hours, the PR will need to be approved again if it hasn't \
+        landed by then",
+    ),
+    (
+        "unchecked-checklist",
+        &["sender", "items"],
+        "@{{ sender }} :ballot_box_with_check: Every item in this PR's checklist must be checked \
+        before it can be queued for landing. Unchecked items:
+{%- for item in items %}
+- {{ item }}
+{%- endfor %}",
+    ),
+    // `invalid`: numbers of the linked issues which aren't open issues
+    (
+        "missing-linked-issue",
+        &["sender", "invalid"],
+        "@{{ sender }} :link: This PR must link an open issue it closes before it can be queued \
+        for landing, e.g. by adding `Fixes #<issue>` to its description.
+{%- if invalid.size > 0 %}
+
+These linked issues aren't open issues:{% for number in invalid %} #{{ number }}{% endfor %}
+{%- endif %}",
+    ),
+    (
+        "checklist-dequeued",
+        &["items"],
+        ":ballot_box_with_check: This PR has been removed from the merge queue since items in its \
+        checklist are no longer checked:
+{%- for item in items %}
+- {{ item }}
+{%- endfor %}",
+    ),
+    (
+        "blocked-dequeued",
+        &["reason", "minutes"],
+        ":hourglass: This PR has been removed from the merge queue since {{ reason }}, and has been \
+        for more than {{ minutes }} minutes. Please issue another Land command once it's unblocked",
+    ),
+    (
+        "already-queued",
+        &["sender"],
+        "@{{ sender }} :bulb: This PR is already queued for landing",
+    ),
+    (
+        "land-during-canary",
+        &["sender"],
+        "@{{ sender }} :bulb: This PR is currently being canaried, cancel the canary before queuing for landing",
+    ),
+    (
+        "canary-while-queued",
+        &["sender"],
+        "@{{ sender }} :bulb: This PR is currently queued for landing, cancel first if you want to canary the landing",
+    ),
+    ("already-canary", &["sender"], "This PR is already being canaried"),
+    (
+        "canary-waiting-for-checks",
+        &["sender"],
+        ":hourglass: The canary will start once the checks on this PR's head commit pass",
+    ),
+    (
+        "canary-head-failed",
+        &["requester", "name", "url"],
+        "{% if requester %}@{{ requester }} {% endif %}:exclamation: Canary canceled since check \
+        [{{ name }}]({{ url }}) failed on this PR's head commit",
+    ),
+    (
+        "canary-timed-out",
+        &["requester", "minutes"],
+        "{% if requester %}@{{ requester }} {% endif %}:hourglass: Canary canceled after it didn't \
+        complete within {{ minutes }} minutes",
+    ),
+    (
+        "canary-successful",
+        &["allowed_failures"],
+        ":sunny: Canary successful
+{%- if allowed_failures.size > 0 %}
+
+:warning: These checks failed but are allowed to fail:
+{%- for check in allowed_failures %}
+- [{{ check.name }}]({{ check.url }})
+{%- endfor %}
+{%- endif %}",
+    ),
+    (
+        "invalid-cherry-pick-target",
+        &["sender", "target"],
+        "@{{ sender }} :exclamation: '{{ target }}' is an invalid branch target for cherry-picking",
+    ),
+    (
+        "cherry-pick-failed",
+        &["sender", "url", "target", "base_oid", "head_oid"],
+        "@{{ sender }} :exclamation: cherry-pick failed, possibly due to conflicts. \
+        You can perform the cherry-pick yourself by running the following commands:
+```
+git fetch {{ url }} {{ target }} {{ head_oid }}
+git checkout {{ target }}
+git cherry-pick {{ base_oid }}..{{ head_oid }}
+```",
+    ),
+    (
+        "invalid-land-base",
+        &["sender", "base"],
+        "@{{ sender }} :exclamation: '{{ base }}' is an invalid base branch to land onto",
+    ),
+    (
+        "retarget-while-queued",
+        &["sender", "base"],
+        "@{{ sender }} :exclamation: this PR can't be retargeted onto `{{ base }}` while it's \
+        queued, run `/cancel` first",
+    ),
+    (
+        "retarget-failed",
+        &["sender", "base", "files"],
+        "@{{ sender }} :lock: this PR doesn't apply to `{{ base }}` so it hasn't been retargeted
+{%- if files.size > 0 %}
+
+Conflicting files:
+{%- for file in files %}
+- `{{ file }}`
+{%- endfor %}
+{%- else %}
+
+Its changes are already present in `{{ base }}`.
+{%- endif %}",
+    ),
+    (
+        "cherry-pick-opened",
+        &["sender", "number", "target"],
+        "@{{ sender }} :cherries: Opened PR #{{ number }} to cherry-pick these changes into {{ target }}",
+    ),
+    (
+        "invalid-bisect",
+        &["sender", "reason"],
+        "@{{ sender }} :exclamation: Unable to bisect: {{ reason }}",
+    ),
+    (
+        "invalid-queue-move",
+        &["sender", "reason"],
+        "@{{ sender }} :exclamation: Unable to reorder the queue: {{ reason }}",
+    ),
+    (
+        "queue-moved",
+        &["sender", "number", "position", "total"],
+        "@{{ sender }} :arrow_up_down: #{{ number }} is now {{ position }} of {{ total }} in the \
+        queue",
+    ),
+    (
+        "review-exempted",
+        &["sender", "policy"],
+        "@{{ sender }} :scroll: This PR is small enough to land without review under the \
+        `{{ policy }}` policy",
+    ),
+    (
+        "requeue-failed",
+        &["sender", "numbers", "batch_size"],
+        "@{{ sender }} :repeat: Re-queuing {{ numbers | size }} PRs whose land failed, in the order \
+        they failed with at most {{ batch_size }} waiting in the queue at a time: \
+        {% for number in numbers %}#{{ number }}{% unless forloop.last %}, {% endunless %}{% endfor %}",
+    ),
+    (
+        "nothing-to-requeue",
+        &["sender"],
+        "@{{ sender }} :exclamation: There are no failed PRs to re-queue",
+    ),
+    (
+        "pr-refreshed",
+        &["sender"],
+        "@{{ sender }} :arrows_counterclockwise: Refreshed this PR's state from Github",
+    ),
+    (
+        "bisect-started",
+        &["sender", "good", "bad", "branch"],
+        "@{{ sender }} :mag: Bisecting `{{ good }}..{{ bad }}`, candidate commits are tested on the \
+        `{{ branch }}` branch",
+    ),
+    (
+        "bisect-culprit",
+        &["requester", "commit", "pr", "steps"],
+        "@{{ requester }} :mag: After testing {{ steps }} commit(s), the first bad commit is \
+        {{ commit }}{% if pr %}, landed by #{{ pr }}{% endif %}",
+    ),
+    (
+        "bisect-inconclusive",
+        &["requester", "commits"],
+        "@{{ requester }} :mag: Some commits couldn't be tested, the first bad commit is one of: \
+        {{ commits | join: \", \" }}",
+    ),
+    (
+        "bisect-failed",
+        &["requester", "error"],
+        "@{{ requester }} :exclamation: Bisect failed:\n```\n{{ error }}\n```",
+    ),
+    (
+        "enable-maintainer-edits",
+        &[],
+        ":exclamation: before this PR can be merged please make sure that you enable \
+        [\"Allow edits from maintainers\"]\
+        (https://help.github.com/en/github/collaborating-with-issues-and-pull-requests/allowing-changes-to-a-pull-request-branch-created-from-a-fork).\n\n\
+        This is needed for tooling to be able to update this PR in-place so that Github can \
+        properly recognize and mark it as merged once its merged into the upstream branch",
+    ),
+    (
+        "update-in-place-failed",
+        &[],
+        ":exclamation: failed to update PR in-place. When this PR is merged Github will improperly mark it as \"Closed\" instead of \"Merged\".\n\
+        In the future make sure that that [\"Allow edits from maintainers\"]\
+        (https://help.github.com/en/github/collaborating-with-issues-and-pull-requests/allowing-changes-to-a-pull-request-branch-created-from-a-fork) \
+        is enabled, allowing Bors to update the PR in-place before merging and convince Github to mark the PR as \"Merged\".",
+    ),
+    (
+        "merge-failed",
+        &["base", "error", "notify"],
+        "Error occured while trying to merge into {{ base }}:\n```\n{{ error }}\n```
+{%- if notify.size > 0 %}
+
+cc{% for user in notify %} @{{ user }}{% endfor %}
+{%- endif %}",
+    ),
+    // `allowed_failures`: each with a name and url
+    (
+        "landed",
+        &["base", "commits_behind", "behind_hours", "allowed_failures", "issues", "notify"],
+        ":sunny: Landed in `{{ base }}`
+{%- if commits_behind > 0 %}
+
+It was {{ commits_behind }} commits behind `{{ base }}`, the oldest of which was committed \
+{{ behind_hours }} hours earlier
+{%- endif %}
+{%- if issues.size > 0 %}
+
+Linked issues:{% for number in issues %} #{{ number }}{% endfor %}
+{%- endif %}
+{%- if allowed_failures.size > 0 %}
+
+:warning: These checks failed but are allowed to fail:
+{%- for check in allowed_failures %}
+- [{{ check.name }}]({{ check.url }})
+{%- endfor %}
+{%- endif %}
+{%- if notify.size > 0 %}
+
+cc{% for user in notify %} @{{ user }}{% endfor %}
+{%- endif %}",
+    ),
+    (
+        "test-failed",
+        &["name", "url", "source", "notify"],
+        ":broken_heart: Test Failed - [{{ name }}]({{ url }}) ({{ source }})
+{%- if notify.size > 0 %}
+
+cc{% for user in notify %} @{{ user }}{% endfor %}
+{%- endif %}",
+    ),
+    (
+        "batch-failed",
+        &["batch", "name", "url"],
+        ":broken_heart: Tests failed on the batch of #{{ batch | join: \", #\" }}\
+        {% if name %} - [{{ name }}]({{ url }}){% endif %}. These PRs will be tested in smaller \
+        batches to find the one which broke it",
+    ),
+    (
+        "batch-culprit",
+        &[],
+        ":mag: Bisecting the failed batch found this PR to be the one which broke it",
+    ),
+    (
+        "tests-timed-out",
+        &["notify"],
+        ":boom: Tests timed-out
+{%- if notify.size > 0 %}
+
+cc{% for user in notify %} @{{ user }}{% endfor %}
+{%- endif %}",
+    ),
+    (
+        "nothing-to-land",
+        &["base", "notify"],
+        ":lock: Nothing to land, the changes in this PR are already present in `{{ base }}`
+{%- if notify.size > 0 %}
+
+cc{% for user in notify %} @{{ user }}{% endfor %}
+{%- endif %}",
+    ),
+    (
+        "merge-conflict",
+        &["base", "number", "head_ref", "files", "merge_commit", "notify"],
+        ":lock: Merge Conflict with `{{ base }}`
+{%- if files.size > 0 %}
+
+Conflicting files:
+{%- for file in files %}
+- `{{ file }}`
+{%- endfor %}
+{%- endif %}
+
+To resolve the conflict locally run the following commands:
+```
+git fetch origin pull/{{ number }}/head:pr-{{ number }}
+git checkout pr-{{ number }}
+git fetch origin {{ base }}
+{%- if merge_commit %}
+git merge origin/{{ base }}
+# resolve the conflicts, then
+git add <files> && git commit
+git push <your-remote> pr-{{ number }}:{{ head_ref }}
+{%- else %}
+git rebase origin/{{ base }}
+# resolve the conflicts, then
+git add <files> && git rebase --continue
+git push --force-with-lease <your-remote> pr-{{ number }}:{{ head_ref }}
+{%- endif %}
+```
+{%- if notify.size > 0 %}
+
+cc{% for user in notify %} @{{ user }}{% endfor %}
+{%- endif %}",
+    ),
+    // Commit message of a PR landed with `squash=concat`
+    // `commits`: the message of each commit, oldest first
+    (
+        "squash-concat",
+        &["number", "title", "body", "commits"],
+        "{% for commit in commits %}{% unless forloop.first %}\n\n{% endunless %}{{ commit }}{% endfor %}",
+    ),
+    // Commit message of a PR landed with `squash=template`
+    // `commits`: the message of each commit, oldest first
+    (
+        "squash-message",
+        &["number", "title", "body", "commits"],
+        "{{ title }} (#{{ number }})
+{%- if body != \"\" %}
+
+{{ body }}
+{%- endif %}",
+    ),
+];
+
+/// Machine-readable description of a comment posted by bors, embedded at its end
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CommentMarker {
+    /// Version of the marker's format
+    pub version: u32,
+    /// Name of the template the comment was rendered from
+    pub template: String,
+    /// The repo, as `owner/name`
+    pub repo: String,
+}
+
+impl CommentMarker {
+    const VERSION: u32 = 1;
+
+    fn new(config: &RepoConfig, template: &str) -> Self {
+        Self {
+            version: Self::VERSION,
+            template: template.to_owned(),
+            repo: config.repo().to_string(),
+        }
+    }
+
+    /// The marker of `comment`, or `None` if it wasn't posted by bors
+    pub fn parse(comment: &str) -> Option<Self> {
+        let start = comment.rfind(MARKER_PREFIX)? + MARKER_PREFIX.len();
+        let end = start + comment[start..].find("-->")?;
+        serde_json::from_str(comment[start..end].trim()).ok()
+    }
+
+    fn to_html_comment(&self) -> Result<String> {
+        Ok(format!(
+            "{} {} -->",
+            MARKER_PREFIX,
+            serde_json::to_string(self)?
+        ))
+    }
+}
+
+fn builtin_templates(locale: &str) -> Option<&'static [Template]> {
+    LOCALES
+        .iter()
+        .find(|(l, _)| *l == locale)
+        .map(|(_, templates)| *templates)
+}
+
+fn parse(source: &str) -> Result<liquid::Template> {
+    Ok(liquid::ParserBuilder::with_stdlib()
+        .build()?
+        .parse(source)?)
+}
+
+/// Check that the configured locale is supported, that every override replaces a known template
+/// and that every template and the footer render with the variables they are given. Rendering
+/// fails on a variable which isn't defined, so a typo in an override is reported here rather than
+/// when bors first posts the comment
+pub fn validate(comments: &Comments) -> Result<()> {
+    let builtin = builtin_templates(comments.locale()).ok_or_else(|| {
+        anyhow!(
+            "unsupported locale '{}', expected one of: {}",
+            comments.locale(),
+            LOCALES
+                .iter()
+                .map(|(l, _)| *l)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })?;
+
+    for (name, _) in comments.templates() {
+        if !builtin.iter().any(|(n, _, _)| *n == name) {
+            return Err(anyhow!("unknown comment template '{}'", name));
+        }
+    }
+
+    for (name, variables, source) in builtin {
+        let source = comments.template(name).unwrap_or(source);
+        parse(source)
+            .and_then(|template| Ok(template.render(&sample_globals(variables))?))
+            .with_context(|| format!("invalid comment template '{}'", name))?;
+    }
+
+    if let Some(footer) = comments.footer() {
+        parse(footer)
+            .and_then(|footer| Ok(footer.render(&sample_globals(&[]))?))
+            .context("invalid comment footer")?;
+    }
+
+    Ok(())
+}
+
+/// Values of the same shape as those `variables` are rendered with, as well as `repo`
+fn sample_globals(variables: &[&str]) -> liquid::Object {
+    let samples = liquid::object!({
+        "allowed_failures": [{ "name": "ci", "url": "https://ci.example.com/1" }],
+        "approval_hours_remaining": 12,
+        "batch": [1, 2],
+        "batch_size": 2,
+        "behind_hours": 3,
+        "commits": ["Fix frobnicating"],
+        "commits_behind": 2,
+        "days": 7,
+        "files": ["README.md"],
+        "hotfix": 1,
+        "invalid": [1],
+        "issues": [1],
+        "items": ["Add a test"],
+        "merge_commit": false,
+        "minutes": 30,
+        "notify": ["octocat"],
+        "number": 1,
+        "numbers": [1, 2],
+        "position": 1,
+        "pr": 1,
+        "reviewers": ["octocat"],
+        "steps": 3,
+        "total": 2,
+    });
+
+    let mut globals = liquid::Object::new();
+    for variable in variables {
+        let value = samples
+            .get(*variable)
+            .cloned()
+            .unwrap_or_else(|| liquid::model::Value::scalar("sample"));
+        globals.insert(variable.to_string().into(), value);
+    }
+    globals.insert("repo".into(), liquid::model::Value::scalar("owner/name"));
+    globals
+}
+
+/// Render the comment template `name`, including the configured footer and the comment's marker
+pub fn render(config: &RepoConfig, name: &str, globals: liquid::Object) -> Result<String> {
+    render_inner(config, name, globals, true)
+}
+
+/// Render the commit message template `name`, which unlike a comment doesn't get the footer or a
+/// marker
+pub fn render_commit_message(
+    config: &RepoConfig,
+    name: &str,
+    globals: liquid::Object,
+) -> Result<String> {
+    render_inner(config, name, globals, false)
+}
+
+fn render_inner(
+    config: &RepoConfig,
+    name: &str,
+    mut globals: liquid::Object,
+    is_comment: bool,
+) -> Result<String> {
+    let comments = config.comments();
+    let source = match comments.template(name) {
+        Some(source) => source,
+        None => builtin_templates(comments.locale())
+            .and_then(|templates| templates.iter().find(|(n, _, _)| *n == name))
+            .map(|(_, _, source)| *source)
+            .ok_or_else(|| anyhow!("unknown comment template '{}'", name))?,
+    };
+
+    globals.insert(
+        "repo".into(),
+        liquid::model::Value::scalar(config.repo().to_string()),
+    );
+
+    let mut comment = parse(source)?.render(&globals)?;
+
+    if let Some(footer) = comments.footer().filter(|_| is_comment) {
+        comment.push_str("\n\n");
+        comment.push_str(&parse(footer)?.render(&globals)?);
+    }
+
+    if is_comment {
+        comment.push_str("\n\n");
+        comment.push_str(&CommentMarker::new(config, name).to_html_comment()?);
+    }
+
+    Ok(comment)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builtin_templates_render() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+
+            [comments]
+            footer = "Docs for {{ repo }}"
+
+            [comments.templates]
+            canary-successful = ":tada:"
+            "#,
+        )
+        .unwrap();
+        validate(config.comments()).unwrap();
+
+        assert_eq!(
+            render(
+                &config,
+                "canary-successful",
+                liquid::object!({ "allowed_failures": [] })
+            )
+            .unwrap(),
+            ":tada:\n\nDocs for bors-rs/bors\n\n\
+            <!-- bors: {\"version\":1,\"template\":\"canary-successful\",\"repo\":\"bors-rs/bors\"} -->"
+        );
+
+        let comment = render(
+            &config,
+            "merge-conflict",
+            liquid::object!({
+                "base": "main",
+                "number": 7,
+                "head_ref": "feature",
+                "files": ["README.md"],
+                "merge_commit": false,
+                "notify": ["octocat", "bors-rs/core"],
+            }),
+        )
+        .unwrap();
+        assert!(comment.starts_with(
+            ":lock: Merge Conflict with `main`\n\nConflicting files:\n- `README.md`\n\n"
+        ));
+        assert!(comment.contains("\ngit rebase origin/main\n"));
+        assert!(comment.contains("```\n\ncc @octocat @bors-rs/core\n\nDocs for"));
+    }
+
+    #[test]
+    fn validate_renders_templates() {
+        let config = |templates: &str| -> RepoConfig {
+            toml::from_str(&format!(
+                "owner = \"bors-rs\"\nname = \"bors\"\n{}",
+                templates
+            ))
+            .unwrap()
+        };
+
+        // Every built-in template renders with the variables it declares
+        validate(config("").comments()).unwrap();
+
+        validate(
+            config("[comments.templates]\nqueued = \"Valid for {{ approval_hours_remaining }}h\"")
+                .comments(),
+        )
+        .unwrap();
+        let error = validate(
+            config("[comments.templates]\nqueued = \"Queued by {{ sender }}\"").comments(),
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", error).contains("invalid comment template 'queued'"));
+
+        validate(config("[comments]\nfooter = \"Docs for {{ repo }}\"").comments()).unwrap();
+        assert!(validate(config("[comments]\nfooter = \"Ask {{ sender }}\"").comments()).is_err());
+    }
+
+    #[test]
+    fn squash_messages_have_no_footer() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+
+            [comments]
+            footer = "Docs for {{ repo }}"
+            "#,
+        )
+        .unwrap();
+        let globals = || {
+            liquid::object!({
+                "number": 7,
+                "title": "Fix the frobnicator",
+                "body": "It was broken",
+                "commits": ["Fix frobnicating\n\nDetails", "Add a test"],
+            })
+        };
+
+        assert_eq!(
+            render_commit_message(&config, "squash-concat", globals()).unwrap(),
+            "Fix frobnicating\n\nDetails\n\nAdd a test"
+        );
+        assert_eq!(
+            render_commit_message(&config, "squash-message", globals()).unwrap(),
+            "Fix the frobnicator (#7)\n\nIt was broken"
+        );
+    }
+
+    #[test]
+    fn comment_markers() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            owner = "bors-rs"
+            name = "bors"
+
+            [comments.templates]
+            land-parked = "Parked for #{{ hotfix }}, see <!-- bors: not a marker -->"
+            "#,
+        )
+        .unwrap();
+
+        let comment = render(&config, "land-parked", liquid::object!({ "hotfix": 3 })).unwrap();
+        assert_eq!(
+            CommentMarker::parse(&comment),
+            Some(CommentMarker {
+                version: 1,
+                template: "land-parked".to_owned(),
+                repo: "bors-rs/bors".to_owned(),
+            })
+        );
+        assert_eq!(CommentMarker::parse("Parked for #3, by a human"), None);
+
+        let message = render_commit_message(
+            &config,
+            "squash-concat",
+            liquid::object!({ "commits": ["Fix frobnicating"] }),
+        )
+        .unwrap();
+        assert_eq!(CommentMarker::parse(&message), None);
+    }
+}
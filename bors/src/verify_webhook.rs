@@ -0,0 +1,429 @@
+//! Offline verification of a webhook delivery, to troubleshoot bors ignoring a repo's webhooks
+//!
+//! A saved payload and its headers are put through the same steps the server takes when a
+//! webhook is delivered: the headers are parsed, the signature is checked against the configured
+//! secret, the payload is deserialized and the event is routed to a repository. Every step is
+//! reported, along with the JSON path of the field which couldn't be deserialized, if any.
+
+use crate::{
+    config::{Config, GithubConfig, OrgConfig, RepoConfig},
+    server::delivery,
+    Result,
+};
+use anyhow::{anyhow, Context};
+use github::{
+    signature::{self, Algorithm, Signature, SignatureError},
+    Event,
+};
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap,
+};
+use std::{fmt, fs, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct VerifyWebhookOptions {
+    #[structopt(parse(from_os_str))]
+    /// File containing the payload, byte for byte as it was delivered
+    payload: PathBuf,
+
+    #[structopt(short = "H", long = "header")]
+    /// Header the payload was delivered with, as 'Name: value'. May be repeated
+    headers: Vec<String>,
+
+    #[structopt(long = "headers", parse(from_os_str))]
+    /// File containing the headers the payload was delivered with, one 'Name: value' per line
+    headers_file: Option<PathBuf>,
+
+    #[structopt(long)]
+    /// Verify the delivery against the configuration of this tenant
+    tenant: Option<String>,
+}
+
+pub fn run_verify_webhook(config: &Config, options: &VerifyWebhookOptions) -> Result<()> {
+    let body = fs::read(&options.payload)
+        .with_context(|| format!("reading {}", options.payload.display()))?;
+
+    let mut lines = options.headers.clone();
+    if let Some(path) = &options.headers_file {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        lines.extend(contents.lines().map(ToOwned::to_owned));
+    }
+    let headers = parse_headers(lines.iter().map(String::as_str))?;
+
+    let report = match &options.tenant {
+        Some(name) => {
+            let tenant = config
+                .tenant
+                .iter()
+                .find(|tenant| &tenant.name == name)
+                .ok_or_else(|| anyhow!("no tenant '{}' configured", name))?;
+            verify(&tenant.github, &tenant.repo, &tenant.org, &headers, &body)
+        }
+        None => verify(&config.github, &config.repo, &config.org, &headers, &body),
+    };
+
+    println!(
+        "Verifying {} ({} bytes)\n",
+        options.payload.display(),
+        body.len()
+    );
+    print!("{}", report);
+
+    if report.passed() {
+        Ok(())
+    } else {
+        Err(anyhow!("bors would ignore this delivery"))
+    }
+}
+
+/// Parse `Name: value` header lines, skipping those without a colon such as blank lines or the
+/// request line
+fn parse_headers<'a>(lines: impl Iterator<Item = &'a str>) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .with_context(|| format!("invalid header name in '{}'", line))?;
+            let value = HeaderValue::from_str(value.trim())
+                .with_context(|| format!("invalid header value in '{}'", line))?;
+            headers.append(name, value);
+        }
+    }
+    Ok(headers)
+}
+
+#[derive(Debug)]
+struct Step {
+    name: &'static str,
+    outcome: Result<String, String>,
+}
+
+/// The outcome of each step of handling a webhook delivery
+#[derive(Debug, Default)]
+pub struct Report {
+    steps: Vec<Step>,
+}
+
+impl Report {
+    fn record(&mut self, name: &'static str, outcome: Result<String, String>) {
+        self.steps.push(Step { name, outcome });
+    }
+
+    /// Indicates if the delivery would be handled by the server
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.outcome.is_ok())
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            match &step.outcome {
+                Ok(detail) => writeln!(f, "  ok    {}: {}", step.name, detail)?,
+                Err(detail) => writeln!(f, "  FAIL  {}: {}", step.name, detail)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Put the delivery of `body` with `headers` through every step the server takes
+fn verify(
+    github: &GithubConfig,
+    repos: &[RepoConfig],
+    orgs: &[OrgConfig],
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Report {
+    let mut report = Report::default();
+
+    report.record(
+        "content type",
+        delivery::check_content_type(headers)
+            .map(|()| "application/json".to_owned())
+            .map_err(|e| format!("{:#}", e)),
+    );
+
+    let event_type = delivery::event_type(headers);
+    report.record(
+        "event type",
+        event_type
+            .as_ref()
+            .map(ToString::to_string)
+            .map_err(|e| format!("{:#}", e)),
+    );
+
+    report.record(
+        "delivery id",
+        delivery::delivery_id(headers).map_err(|e| format!("{:#}", e)),
+    );
+
+    report.record("signature", check_signature(github, headers, body));
+
+    let event = match event_type {
+        Ok(event_type) => match Event::from_json(event_type, body) {
+            Ok(event) => {
+                report.record("payload", Ok(format!("valid {} event", event_type)));
+                Some(event)
+            }
+            Err(e) => {
+                report.record("payload", Err(describe_payload_error(&e, body)));
+                None
+            }
+        },
+        Err(_) => {
+            report.record(
+                "payload",
+                Err("not deserialized, the event type is unknown".into()),
+            );
+            None
+        }
+    };
+
+    if let Some(event) = event {
+        report.record("routing", route(&event, repos, orgs));
+    }
+
+    report
+}
+
+fn check_signature(
+    github: &GithubConfig,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<String, String> {
+    let secret = match github.webhook_secret() {
+        Some(secret) => secret.as_bytes(),
+        None => return Ok("not checked, no webhook secret is configured".to_owned()),
+    };
+    let signatures = delivery::signatures(headers).map_err(|e| format!("{:#}", e))?;
+
+    match signature::verify(secret, body, signatures.iter().map(String::as_str)) {
+        Ok(()) => Ok(format!(
+            "{} matches the configured secret",
+            signatures.join(", ")
+        )),
+        Err(SignatureError::Mismatch(algorithm)) => Err(format!(
+            "{} signature doesn't match, the configured secret signs this payload as {}. Either \
+             the secrets differ or the payload wasn't saved byte for byte as delivered",
+            algorithm,
+            Signature::new(algorithm, secret, body)
+        )),
+        Err(SignatureError::Missing) => Err(format!(
+            "no signature, but a webhook secret is configured. Expected e.g. {}",
+            Signature::new(Algorithm::Sha256, secret, body)
+        )),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn describe_payload_error(error: &std::io::Error, body: &[u8]) -> String {
+    match error
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<serde_json::Error>())
+    {
+        Some(e) if e.line() > 0 => format!(
+            "{} at {} (line {}, column {})",
+            // The position is reported separately, along with the path
+            e.to_string()
+                .rsplit_once(" at line ")
+                .map_or_else(|| e.to_string(), |(message, _)| message.to_owned()),
+            json_path_at(body, e.line(), e.column()),
+            e.line(),
+            e.column()
+        ),
+        _ => error.to_string(),
+    }
+}
+
+/// Which installation the server would hand the event to
+fn route(event: &Event, repos: &[RepoConfig], orgs: &[OrgConfig]) -> Result<String, String> {
+    if let Some(repository) = event.repository() {
+        let (owner, name) = (&repository.owner.login, &repository.name);
+        if repos.iter().any(|r| r.owner() == owner && r.name() == name) {
+            Ok(format!("handled by {}/{}", owner, name))
+        } else if orgs
+            .iter()
+            .any(|org| org.owner() == owner && org.should_auto_install(name))
+        {
+            Ok(format!(
+                "{}/{} isn't configured, but is installed automatically through its org",
+                owner, name
+            ))
+        } else {
+            Err(format!("{}/{} isn't a configured repository", owner, name))
+        }
+    } else if let Some(org) = event.organization() {
+        let count = repos.iter().filter(|r| r.owner() == org.login).count();
+        if count > 0 {
+            Ok(format!(
+                "handled by {} repositories in {}",
+                count, org.login
+            ))
+        } else {
+            Err(format!("no repositories in {} are configured", org.login))
+        }
+    } else {
+        Err("the event doesn't belong to a repository or organization".to_owned())
+    }
+}
+
+#[derive(Debug)]
+enum Frame {
+    /// An object, and the key of the member being parsed, if any
+    Object(Option<String>),
+    /// An array, and the index of the element being parsed
+    Array(usize),
+}
+
+/// The JSON path, e.g. `$.pull_request.labels[2]`, of the value being parsed at `line` and
+/// `column` of `json`, as reported by `serde_json`'s errors
+fn json_path_at(json: &[u8], line: usize, column: usize) -> String {
+    let mut stack: Vec<Frame> = Vec::new();
+    let (mut current_line, mut current_column) = (1, 0);
+    let (mut in_string, mut escaped) = (false, false);
+    let mut string = Vec::new();
+    // Set while the next string in the innermost object is a key
+    let mut expecting_key = false;
+
+    for &byte in json {
+        if current_line > line || (current_line == line && current_column >= column) {
+            break;
+        }
+        if byte == b'\n' {
+            current_line += 1;
+            current_column = 0;
+        } else {
+            current_column += 1;
+        }
+
+        if in_string {
+            if escaped {
+                escaped = false;
+                string.push(byte);
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+                if expecting_key {
+                    if let Some(Frame::Object(key)) = stack.last_mut() {
+                        *key = Some(String::from_utf8_lossy(&string).into_owned());
+                    }
+                    expecting_key = false;
+                }
+            } else {
+                string.push(byte);
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => {
+                in_string = true;
+                string.clear();
+            }
+            b'{' => {
+                stack.push(Frame::Object(None));
+                expecting_key = true;
+            }
+            b'[' => {
+                stack.push(Frame::Array(0));
+                expecting_key = false;
+            }
+            b'}' | b']' => {
+                stack.pop();
+                expecting_key = false;
+            }
+            b',' => match stack.last_mut() {
+                Some(Frame::Array(index)) => *index += 1,
+                Some(Frame::Object(_)) => expecting_key = true,
+                None => {}
+            },
+            _ => {}
+        }
+    }
+
+    let mut path = "$".to_owned();
+    for frame in &stack {
+        match frame {
+            Frame::Object(Some(key)) => {
+                path.push('.');
+                path.push_str(key);
+            }
+            Frame::Object(None) => {}
+            Frame::Array(index) => path.push_str(&format!("[{}]", index)),
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod test {
+    use super::{json_path_at, parse_headers, verify};
+    use crate::config::GithubConfig;
+    use github::{
+        signature::{Algorithm, Signature},
+        test_fixtures::{EventPayload, PullRequestEventBuilder},
+    };
+    use serde::Deserialize;
+
+    fn github_config(secret: &str) -> GithubConfig {
+        toml::from_str(&format!(
+            "github-api-token = \"\"\nwebhook-secret = \"{}\"",
+            secret
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn json_paths() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Label {
+            name: String,
+        }
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Payload {
+            labels: Vec<Label>,
+        }
+
+        let json = br#"{"action": "opened", "labels": [{"name": "a"}, {"name": 7}]}"#;
+        let error = serde_json::from_slice::<Payload>(json).unwrap_err();
+        assert_eq!(
+            json_path_at(json, error.line(), error.column()),
+            "$.labels[1].name"
+        );
+        assert_eq!(json_path_at(json, 1, 0), "$");
+    }
+
+    #[test]
+    fn reports_each_step() {
+        let payload = PullRequestEventBuilder::new("opened").to_json();
+        let signature = Signature::new(Algorithm::Sha256, b"secret", &payload);
+        let lines = [
+            "POST /github HTTP/1.1".to_owned(),
+            "Content-Type: application/json".to_owned(),
+            "X-GitHub-Event: pull_request".to_owned(),
+            "X-GitHub-Delivery: 72d3162e-cc78-11e3-81ab-4c9367dc0958".to_owned(),
+            format!("X-Hub-Signature-256: {}", signature),
+        ];
+        let headers = parse_headers(lines.iter().map(String::as_str)).unwrap();
+
+        let report = verify(&github_config("secret"), &[], &[], &headers, &payload);
+        let output = report.to_string();
+        assert!(output.contains("ok    signature: sha256="), "{}", output);
+        assert!(output.contains("ok    payload: valid pull_request event"));
+        // No repos are configured
+        assert!(output.contains("FAIL  routing:"));
+        assert!(!report.passed());
+
+        let report = verify(&github_config("other"), &[], &[], &headers, &payload);
+        assert!(report
+            .to_string()
+            .contains("FAIL  signature: sha256 signature doesn't match"));
+    }
+}
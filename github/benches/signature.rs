@@ -0,0 +1,42 @@
+//! Times webhook signature verification for both algorithms and a range of body sizes
+//!
+//! Run with `cargo bench -p github --bench signature`
+
+use github::signature::{verify, Algorithm, Signature};
+use std::time::{Duration, Instant};
+
+const KEY: &[u8] = b"webhook secret";
+const BODY_SIZES: &[usize] = &[256, 4 * 1024, 64 * 1024, 1024 * 1024];
+
+/// Run `f` until at least this long has passed, to average out noise
+const MEASUREMENT_TIME: Duration = Duration::from_millis(500);
+
+fn time<F: FnMut()>(mut f: F) -> (Duration, u32) {
+    let start = Instant::now();
+    let mut iterations = 0;
+    while start.elapsed() < MEASUREMENT_TIME {
+        f();
+        iterations += 1;
+    }
+    (start.elapsed() / iterations, iterations)
+}
+
+fn main() {
+    for algorithm in [Algorithm::Sha1, Algorithm::Sha256] {
+        for &size in BODY_SIZES {
+            let body = vec![b'x'; size];
+            let valid = Signature::new(algorithm, KEY, &body).to_string();
+            let forged = Signature::new(algorithm, b"not the secret", &body).to_string();
+
+            for (name, value) in [("valid", &valid), ("forged", &forged)] {
+                let (per_iteration, iterations) = time(|| {
+                    let _ = std::hint::black_box(verify(KEY, &body, [value.as_str()]));
+                });
+                println!(
+                    "{:>6} {:>8} bytes {:>6}: {:>12?}/iter ({} iterations)",
+                    algorithm, size, name, per_iteration, iterations
+                );
+            }
+        }
+    }
+}
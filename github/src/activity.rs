@@ -0,0 +1,87 @@
+use super::{DateTime, License, Repository, User};
+use serde::{Deserialize, Serialize};
+
+/// A user who starred a repository, along with when they did so
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Stargazer {
+    pub starred_at: DateTime,
+    pub user: User,
+}
+
+/// A repository starred by a user, along with when they did so
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StarredRepository {
+    pub starred_at: DateTime,
+    pub repo: Repository,
+}
+
+/// The authenticated user's notification settings for a repository they watch
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Subscription {
+    pub subscribed: bool,
+    pub ignored: bool,
+    pub reason: Option<String>,
+    pub created_at: DateTime,
+    pub url: String,
+    pub repository_url: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SubscriptionRequest {
+    /// Receive notifications from the repository
+    pub subscribed: bool,
+    /// Block all notifications from the repository
+    pub ignored: bool,
+}
+
+/// Community health metrics of a repository, based on the presence of the files that Github
+/// recommends, e.g. a README, license and code of conduct
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CommunityProfile {
+    pub health_percentage: u32,
+    pub description: Option<String>,
+    pub documentation: Option<String>,
+    pub files: CommunityFiles,
+    pub updated_at: Option<DateTime>,
+    pub content_reports_enabled: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CommunityFiles {
+    pub code_of_conduct: Option<CodeOfConduct>,
+    pub code_of_conduct_file: Option<CommunityFile>,
+    pub contributing: Option<CommunityFile>,
+    pub issue_template: Option<CommunityFile>,
+    pub pull_request_template: Option<CommunityFile>,
+    pub license: Option<License>,
+    pub readme: Option<CommunityFile>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CommunityFile {
+    pub url: String,
+    pub html_url: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CodeOfConduct {
+    pub key: String,
+    pub name: String,
+    pub url: String,
+    pub html_url: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::CommunityProfile;
+
+    #[test]
+    fn community_profile() {
+        const COMMUNITY_PROFILE_JSON: &str = include_str!("../test-input/community-profile.json");
+        let profile: CommunityProfile = serde_json::from_str(COMMUNITY_PROFILE_JSON).unwrap();
+        assert_eq!(profile.health_percentage, 71);
+        assert!(profile.files.readme.is_some());
+        assert!(profile.files.issue_template.is_none());
+        assert_eq!(profile.files.license.unwrap().spdx_id, "MIT");
+    }
+}
@@ -1,8 +1,8 @@
 use super::{DateTime, EventType, NodeId, Oid, User};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Annotation {
     pub path: String,
     pub start_line: u64,
@@ -15,14 +15,14 @@ pub struct Annotation {
     pub raw_details: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Image {
     pub alt: String,
     pub image_url: String,
     pub caption: Option<String>,
 }
 
-#[derive(Clone, Copy, Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Conclusion {
     Success,
@@ -34,7 +34,7 @@ pub enum Conclusion {
     Skipped,
 }
 
-#[derive(Clone, Copy, Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CheckStatus {
     Queued,
@@ -42,7 +42,7 @@ pub enum CheckStatus {
     Completed,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CheckOutput {
     pub title: Option<String>,
     pub summary: Option<String>,
@@ -54,31 +54,31 @@ pub struct CheckOutput {
 }
 
 // Maybe rename these?
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CheckPullRequest {
-    url: String,
-    id: u64,
-    number: u64,
-    head: CheckBranch,
-    base: CheckBranch,
+    pub url: String,
+    pub id: u64,
+    pub number: u64,
+    pub head: CheckBranch,
+    pub base: CheckBranch,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CheckBranch {
     #[serde(rename = "ref")]
-    git_ref: String,
-    sha: Oid,
-    repo: CheckRepo,
+    pub git_ref: String,
+    pub sha: Oid,
+    pub repo: CheckRepo,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CheckRepo {
-    id: u64,
-    url: String,
-    name: String,
+    pub id: u64,
+    pub url: String,
+    pub name: String,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CheckRun {
     pub id: u64,
     pub head_sha: Oid,
@@ -98,7 +98,26 @@ pub struct CheckRun {
     pub pull_requests: Vec<CheckPullRequest>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+/// A check run as returned when it's created or updated, which only includes a summary of some of
+/// the fields of a `CheckRun`, e.g. of its check suite
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CheckRunSummary {
+    pub id: u64,
+    pub head_sha: Oid,
+    pub name: String,
+    pub html_url: Option<String>,
+    pub status: CheckStatus,
+    pub conclusion: Option<Conclusion>,
+}
+
+/// A page of the check runs for a git reference
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CheckRunList {
+    pub total_count: u64,
+    pub check_runs: Vec<CheckRun>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct App {
     pub id: u64,
     pub slug: String,
@@ -114,7 +133,7 @@ pub struct App {
     pub events: Vec<EventType>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CheckSuite {
     pub id: u64,
     pub node_id: NodeId,
@@ -135,14 +154,25 @@ pub struct CheckSuite {
 
 #[cfg(test)]
 mod test {
-    use super::{CheckRun, CheckSuite};
+    use super::{CheckRun, CheckRunList, CheckSuite};
+
+    const CHECK_RUN_JSON: &str = include_str!("../test-input/check-run.json");
 
     #[test]
     fn check_run() {
-        const CHECK_RUN_JSON: &str = include_str!("../test-input/check-run.json");
         let _: CheckRun = serde_json::from_str(CHECK_RUN_JSON).unwrap();
     }
 
+    #[test]
+    fn check_run_list() {
+        let json = format!(
+            r#"{{ "total_count": 1, "check_runs": [{}] }}"#,
+            CHECK_RUN_JSON
+        );
+        let list: CheckRunList = serde_json::from_str(&json).unwrap();
+        assert_eq!(list.check_runs.len(), 1);
+    }
+
     #[test]
     fn check_suite() {
         const CHECK_SUITE_JSON: &str = include_str!("../test-input/check-suite.json");
@@ -0,0 +1,261 @@
+use reqwest::header::HeaderValue;
+
+/// A preview of the Github API, opted into by the media type it's requested with
+///
+/// The previews which this client doesn't use itself are only available with the matching
+/// `preview-*` cargo feature, or all of them with `all-previews`.
+///
+/// https://developer.github.com/v3/previews/#api-previews
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Preview {
+    /// https://developer.github.com/changes/2020-01-10-revoke-installation-token/
+    #[cfg(feature = "preview-apps")]
+    RevokeToken,
+
+    /// https://developer.github.com/changes/2014-12-09-new-attributes-for-stars-api/
+    Starring,
+
+    /// https://help.github.com/enterprise/2.4/admin/guides/migrations/exporting-the-github-com-organization-s-repositories/
+    #[cfg(feature = "preview-enterprise")]
+    Migrations,
+
+    /// https://developer.github.com/changes/2016-04-06-deployment-and-deployment-status-enhancements/
+    #[cfg(feature = "preview-deployments")]
+    DeploymentStatus,
+
+    /// https://developer.github.com/changes/2018-10-16-deployments-environments-states-and-auto-inactive-updates/
+    #[cfg(feature = "preview-deployments")]
+    ExpandDeploymentStatus,
+
+    /// https://developer.github.com/changes/2016-05-12-reactions-api-preview/
+    Reactions,
+
+    /// https://developer.github.com/changes/2016-05-23-timeline-preview-api/
+    #[cfg(feature = "preview-timeline")]
+    Timeline,
+
+    /// https://developer.github.com/changes/2016-09-14-projects-api/
+    Projects,
+
+    /// https://developer.github.com/changes/2016-09-14-Integrations-Early-Access/
+    Integration,
+
+    /// https://developer.github.com/changes/2017-01-05-commit-search-api/
+    #[cfg(feature = "preview-commits")]
+    CommitSearch,
+
+    /// https://developer.github.com/changes/2017-02-28-user-blocking-apis-and-webhook/
+    #[cfg(feature = "preview-repos")]
+    BlockUsers,
+
+    /// https://developer.github.com/changes/2017-02-09-community-health/
+    RepositoryCommunityHealthMetrics,
+
+    /// https://developer.github.com/changes/2017-05-23-coc-api/
+    #[cfg(feature = "preview-repos")]
+    CodesOfConduct,
+
+    /// https://developer.github.com/changes/2017-07-17-update-topics-on-repositories/
+    #[cfg(feature = "preview-repos")]
+    Topics,
+
+    /// https://developer.github.com/changes/2018-03-16-protected-branches-required-approving-reviews/
+    #[cfg(feature = "preview-branch-protection")]
+    RequiredApprovingReviews,
+
+    /// https://developer.github.com/changes/2018-01-10-lock-reason-api-preview/
+    LockReason,
+
+    /// https://developer.github.com/changes/2018-05-07-new-checks-api-public-beta/
+    CheckRuns,
+
+    /// https://developer.github.com/enterprise/2.13/v3/repos/pre_receive_hooks/
+    #[cfg(feature = "preview-enterprise")]
+    PreReceiveHooks,
+
+    /// https://developer.github.com/changes/2018-02-22-protected-branches-required-signatures/
+    #[cfg(feature = "preview-branch-protection")]
+    Signature,
+
+    /// https://developer.github.com/changes/2018-09-05-project-card-events/
+    ProjectCardDetails,
+
+    /// https://developer.github.com/changes/2018-12-18-interactions-preview/
+    #[cfg(feature = "preview-repos")]
+    InteractionRestrictions,
+
+    /// https://developer.github.com/changes/2019-02-14-draft-pull-requests/
+    Draft,
+
+    /// https://developer.github.com/changes/2019-03-14-enabling-disabling-pages/
+    #[cfg(feature = "preview-repos")]
+    EnablePagesApi,
+
+    /// https://developer.github.com/changes/2019-04-24-vulnerability-alerts/
+    #[cfg(feature = "preview-security")]
+    RequiredVulnerabilityAlerts,
+
+    /// https://developer.github.com/changes/2019-06-04-automated-security-fixes/
+    #[cfg(feature = "preview-security")]
+    RequiredAutomatedSecurityFixes,
+
+    /// https://developer.github.com/changes/2019-05-29-update-branch-api/
+    UpdatePullRequestBranch,
+
+    /// https://developer.github.com/changes/2019-04-11-pulls-branches-for-commit/
+    #[cfg(feature = "preview-commits")]
+    ListPullsOrBranchesForCommit,
+
+    /// https://developer.github.com/v3/previews/#repository-creation-permissions
+    #[cfg(feature = "preview-enterprise")]
+    MemberAllowedRepoCreationType,
+
+    /// https://developer.github.com/v3/previews/#create-and-use-repository-templates
+    #[cfg(feature = "preview-repos")]
+    RepositoryTemplate,
+
+    /// https://developer.github.com/changes/2019-10-03-multi-line-comments/
+    MultiLineComments,
+
+    /// https://developer.github.com/changes/2019-11-05-deprecated-passwords-and-authorizations-api/
+    #[cfg(feature = "preview-apps")]
+    OAuthApp,
+}
+
+impl Preview {
+    pub fn media_type(self) -> &'static str {
+        match self {
+            #[cfg(feature = "preview-apps")]
+            Preview::RevokeToken => "application/vnd.github.gambit-preview+json",
+            Preview::Starring => "application/vnd.github.v3.star+json",
+            #[cfg(feature = "preview-enterprise")]
+            Preview::Migrations => "application/vnd.github.wyandotte-preview+json",
+            #[cfg(feature = "preview-deployments")]
+            Preview::DeploymentStatus => "application/vnd.github.ant-man-preview+json",
+            #[cfg(feature = "preview-deployments")]
+            Preview::ExpandDeploymentStatus => "application/vnd.github.flash-preview+json",
+            Preview::Reactions => "application/vnd.github.squirrel-girl-preview",
+            #[cfg(feature = "preview-timeline")]
+            Preview::Timeline => "application/vnd.github.mockingbird-preview+json",
+            Preview::Projects => "application/vnd.github.inertia-preview+json",
+            Preview::Integration => "application/vnd.github.machine-man-preview+json",
+            #[cfg(feature = "preview-commits")]
+            Preview::CommitSearch => "application/vnd.github.cloak-preview+json",
+            #[cfg(feature = "preview-repos")]
+            Preview::BlockUsers => "application/vnd.github.giant-sentry-fist-preview+json",
+            Preview::RepositoryCommunityHealthMetrics => {
+                "application/vnd.github.black-panther-preview+json"
+            }
+            #[cfg(feature = "preview-repos")]
+            Preview::CodesOfConduct => "application/vnd.github.scarlet-witch-preview+json",
+            #[cfg(feature = "preview-repos")]
+            Preview::Topics => "application/vnd.github.mercy-preview+json",
+            #[cfg(feature = "preview-branch-protection")]
+            Preview::RequiredApprovingReviews => "application/vnd.github.luke-cage-preview+json",
+            Preview::LockReason => "application/vnd.github.sailor-v-preview+json",
+            Preview::CheckRuns => "application/vnd.github.antiope-preview+json",
+            #[cfg(feature = "preview-enterprise")]
+            Preview::PreReceiveHooks => "application/vnd.github.eye-scream-preview",
+            #[cfg(feature = "preview-branch-protection")]
+            Preview::Signature => "application/vnd.github.zzzax-preview+json",
+            Preview::ProjectCardDetails => "application/vnd.github.starfox-preview+json",
+            #[cfg(feature = "preview-repos")]
+            Preview::InteractionRestrictions => "application/vnd.github.sombra-preview+json",
+            Preview::Draft => "application/vnd.github.shadow-cat-preview+json",
+            #[cfg(feature = "preview-repos")]
+            Preview::EnablePagesApi => "application/vnd.github.switcheroo-preview+json",
+            #[cfg(feature = "preview-security")]
+            Preview::RequiredVulnerabilityAlerts => "application/vnd.github.dorian-preview+json",
+            #[cfg(feature = "preview-security")]
+            Preview::RequiredAutomatedSecurityFixes => "application/vnd.github.london-preview+json",
+            Preview::UpdatePullRequestBranch => "application/vnd.github.lydian-preview+json",
+            #[cfg(feature = "preview-commits")]
+            Preview::ListPullsOrBranchesForCommit => "application/vnd.github.groot-preview+json",
+            #[cfg(feature = "preview-enterprise")]
+            Preview::MemberAllowedRepoCreationType => "application/vnd.github.surtur-preview+json",
+            #[cfg(feature = "preview-repos")]
+            Preview::RepositoryTemplate => "application/vnd.github.baptiste-preview+json",
+            Preview::MultiLineComments => "application/vnd.github.comfort-fade-preview+json",
+            #[cfg(feature = "preview-apps")]
+            Preview::OAuthApp => "application/vnd.github.doctor-strange-preview+json",
+        }
+    }
+}
+
+/// The media types a request accepts, sent together in a single `Accept` header
+///
+/// Github only looks at the first `Accept` header of a request, so every preview a request needs
+/// has to be listed in the same one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Accepts {
+    media_types: Vec<&'static str>,
+}
+
+impl Accepts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn preview(self, preview: Preview) -> Self {
+        self.media_type(preview.media_type())
+    }
+
+    pub fn media_type(mut self, media_type: &'static str) -> Self {
+        if !self.media_types.contains(&media_type) {
+            self.media_types.push(media_type);
+        }
+        self
+    }
+
+    /// Add all of the media types of `other` which aren't already accepted
+    pub fn merge(self, other: Accepts) -> Self {
+        other
+            .media_types
+            .into_iter()
+            .fold(self, |accepts, media_type| accepts.media_type(media_type))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.media_types.is_empty()
+    }
+
+    pub fn media_types(&self) -> &[&'static str] {
+        &self.media_types
+    }
+
+    /// The value of the `Accept` header, or `None` if no media type was added
+    pub fn header_value(&self) -> Option<HeaderValue> {
+        if self.is_empty() {
+            return None;
+        }
+        // Media types are all static ASCII strings, so they are valid header values
+        HeaderValue::from_str(&self.media_types.join(", ")).ok()
+    }
+}
+
+impl From<Preview> for Accepts {
+    fn from(preview: Preview) -> Self {
+        Accepts::new().preview(preview)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Accepts, Preview};
+
+    #[test]
+    fn previews_are_merged_into_one_header() {
+        let accepts = Accepts::new()
+            .preview(Preview::LockReason)
+            .preview(Preview::Draft)
+            .merge(Accepts::from(Preview::LockReason));
+
+        assert_eq!(
+            accepts.header_value().unwrap(),
+            "application/vnd.github.sailor-v-preview+json, \
+             application/vnd.github.shadow-cat-preview+json"
+        );
+        assert_eq!(Accepts::new().header_value(), None);
+    }
+}
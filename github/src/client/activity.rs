@@ -0,0 +1,212 @@
+use crate::{
+    client::{paginate, Client, PaginationOptions, Preview, Response, Result},
+    Repository, Stargazer, StarredRepository, Subscription, SubscriptionRequest, User,
+};
+use futures::stream::Stream;
+
+/// `ActivityClient` handles communication with the activity related methods of the GitHub API,
+/// i.e. starring and watching repositories.
+///
+/// GitHub API docs: https://developer.github.com/v3/activity/
+pub struct ActivityClient<'a> {
+    inner: &'a Client,
+}
+
+impl<'a> ActivityClient<'a> {
+    pub(super) fn new(client: &'a Client) -> Self {
+        Self { inner: client }
+    }
+
+    /// List the users who have starred a repository, along with when they starred it
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/starring/#list-stargazers
+    pub async fn list_stargazers(
+        &self,
+        owner: &str,
+        repo: &str,
+        options: Option<PaginationOptions>,
+    ) -> Result<Response<Vec<Stargazer>>> {
+        let url = format!("repos/{}/{}/stargazers", owner, repo);
+        let response = self
+            .inner
+            .get(&url)
+            // The star preview includes when each user starred the repository
+            .preview(Preview::Starring)
+            .query(&options)
+            .send()
+            .await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Stream every user who has starred a repository, fetching further pages as needed
+    pub fn stargazers<'b>(
+        &'b self,
+        owner: &'b str,
+        repo: &'b str,
+    ) -> impl Stream<Item = Result<Stargazer>> + 'b {
+        paginate(Some(100), move |options| {
+            self.list_stargazers(owner, repo, Some(options))
+        })
+    }
+
+    /// List the repositories starred by `user`, or by the authenticated user if `None`, along
+    /// with when they were starred
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/starring/#list-repositories-starred-by-a-user
+    pub async fn list_starred(
+        &self,
+        user: Option<&str>,
+        options: Option<PaginationOptions>,
+    ) -> Result<Response<Vec<StarredRepository>>> {
+        let url = match user {
+            Some(user) => format!("users/{}/starred", user),
+            None => "user/starred".to_owned(),
+        };
+        let response = self
+            .inner
+            .get(&url)
+            .preview(Preview::Starring)
+            .query(&options)
+            .send()
+            .await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Stream every repository starred by `user`, or by the authenticated user if `None`
+    pub fn starred<'b>(
+        &'b self,
+        user: Option<&'b str>,
+    ) -> impl Stream<Item = Result<StarredRepository>> + 'b {
+        paginate(Some(100), move |options| {
+            self.list_starred(user, Some(options))
+        })
+    }
+
+    /// Check if a repository is starred by the authenticated user
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/starring/#check-if-a-repository-is-starred-by-the-authenticated-user
+    pub async fn is_starred(&self, owner: &str, repo: &str) -> Result<Response<bool>> {
+        let url = format!("user/starred/{}/{}", owner, repo);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.boolean(response).await
+    }
+
+    /// Star a repository for the authenticated user
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/starring/#star-a-repository-for-the-authenticated-user
+    pub async fn star(&self, owner: &str, repo: &str) -> Result<Response<()>> {
+        let url = format!("user/starred/{}/{}", owner, repo);
+        let response = self.inner.put(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+
+    /// Unstar a repository for the authenticated user
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/starring/#unstar-a-repository-for-the-authenticated-user
+    pub async fn unstar(&self, owner: &str, repo: &str) -> Result<Response<()>> {
+        let url = format!("user/starred/{}/{}", owner, repo);
+        let response = self.inner.delete(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+
+    /// List the users watching a repository
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/watching/#list-watchers
+    pub async fn list_watchers(
+        &self,
+        owner: &str,
+        repo: &str,
+        options: Option<PaginationOptions>,
+    ) -> Result<Response<Vec<User>>> {
+        let url = format!("repos/{}/{}/subscribers", owner, repo);
+        let response = self.inner.get(&url).query(&options).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Stream every user watching a repository, fetching further pages as needed
+    pub fn watchers<'b>(
+        &'b self,
+        owner: &'b str,
+        repo: &'b str,
+    ) -> impl Stream<Item = Result<User>> + 'b {
+        paginate(Some(100), move |options| {
+            self.list_watchers(owner, repo, Some(options))
+        })
+    }
+
+    /// List the repositories watched by `user`, or by the authenticated user if `None`
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/watching/#list-repositories-watched-by-a-user
+    pub async fn list_watched(
+        &self,
+        user: Option<&str>,
+        options: Option<PaginationOptions>,
+    ) -> Result<Response<Vec<Repository>>> {
+        let url = match user {
+            Some(user) => format!("users/{}/subscriptions", user),
+            None => "user/subscriptions".to_owned(),
+        };
+        let response = self.inner.get(&url).query(&options).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Stream every repository watched by `user`, or by the authenticated user if `None`
+    pub fn watched<'b>(
+        &'b self,
+        user: Option<&'b str>,
+    ) -> impl Stream<Item = Result<Repository>> + 'b {
+        paginate(Some(100), move |options| {
+            self.list_watched(user, Some(options))
+        })
+    }
+
+    /// Get the authenticated user's subscription to a repository
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/watching/#get-a-repository-subscription
+    pub async fn get_repository_subscription(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Response<Subscription>> {
+        let url = format!("repos/{}/{}/subscription", owner, repo);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Watch, or ignore, a repository as the authenticated user
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/watching/#set-a-repository-subscription
+    pub async fn set_repository_subscription(
+        &self,
+        owner: &str,
+        repo: &str,
+        request: SubscriptionRequest,
+    ) -> Result<Response<Subscription>> {
+        let url = format!("repos/{}/{}/subscription", owner, repo);
+        let response = self.inner.put(&url).json(&request).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Stop watching a repository as the authenticated user
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/activity/watching/#delete-a-repository-subscription
+    pub async fn delete_repository_subscription(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Response<()>> {
+        let url = format!("repos/{}/{}/subscription", owner, repo);
+        let response = self.inner.delete(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{
+    client::{Client, Response, Result},
+    DateTime, InstallationToken,
+};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Tokens expiring within this long are refreshed rather than handed out
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// How long an app's JSON web tokens are valid for. Github rejects tokens valid for longer than
+/// 10 minutes
+const JWT_LIFETIME: Duration = Duration::from_secs(9 * 60);
+
+/// How far back an app's JSON web tokens are issued, in case Github's clock is behind
+const JWT_BACKDATE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default, Serialize)]
+pub struct CreateInstallationTokenRequest<'a> {
+    /// Names of the repositories, all owned by the installation's account, the token is scoped
+    /// to. The token can access every repository of the installation if empty
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub repositories: &'a [&'a str],
+    /// Permissions of the token, a subset of the installation's. The token has all of the
+    /// installation's permissions if `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<&'a HashMap<String, String>>,
+}
+
+/// `AppsClient` handles communication with the Github App related methods of the GitHub API.
+///
+/// GitHub API docs: https://developer.github.com/v3/apps/
+pub struct AppsClient<'a> {
+    inner: &'a Client,
+}
+
+impl<'a> AppsClient<'a> {
+    pub(super) fn new(client: &'a Client) -> Self {
+        Self { inner: client }
+    }
+
+    /// Create a token to access the API as an installation of an app. Authenticated with `jwt`,
+    /// the app's JSON web token, rather than the client's own token. Creating a token doesn't
+    /// write to any repository, so it isn't refused while the client is read-only.
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/apps/#create-an-installation-access-token-for-an-app
+    pub async fn create_installation_token(
+        &self,
+        jwt: &str,
+        installation_id: u64,
+        request: &CreateInstallationTokenRequest<'_>,
+    ) -> Result<Response<InstallationToken>> {
+        let url = format!("app/installations/{}/access_tokens", installation_id);
+        let response = self
+            .inner
+            .post(&url)
+            .bearer_auth(jwt)
+            .json(request)
+            .send()
+            .await?;
+
+        self.inner.json(response).await
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JwtClaims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+/// Authentication as an installation of a Github App. The installation's access tokens are
+/// created with a JSON web token signed by the app's private key, and kept in an
+/// [`InstallationTokenCache`] until shortly before they expire.
+pub(super) struct AppAuth {
+    app_id: u64,
+    installation_id: u64,
+    key: EncodingKey,
+    /// Names of the repositories the tokens are scoped to, every repository of the installation
+    /// if empty
+    repositories: Vec<String>,
+    tokens: InstallationTokenCache,
+    /// Held while a token is looked up, so concurrent requests don't each create one
+    refresh: futures::lock::Mutex<()>,
+}
+
+impl AppAuth {
+    /// Authenticate as `installation_id` of `app_id`, with `private_key` the app's PEM encoded RSA
+    /// private key, using tokens scoped to `repositories` which are kept in `tokens`
+    pub(super) fn new(
+        app_id: u64,
+        private_key: &[u8],
+        installation_id: u64,
+        repositories: Vec<String>,
+        tokens: InstallationTokenCache,
+    ) -> Result<Self> {
+        let key = EncodingKey::from_rsa_pem(private_key)
+            .map_err(|e| format!("invalid private key of app {}: {}", app_id, e))?;
+        Ok(Self {
+            app_id,
+            installation_id,
+            key,
+            repositories,
+            tokens,
+            refresh: futures::lock::Mutex::new(()),
+        })
+    }
+
+    pub(super) fn app_id(&self) -> u64 {
+        self.app_id
+    }
+
+    pub(super) fn installation_id(&self) -> u64 {
+        self.installation_id
+    }
+
+    /// A JSON web token identifying the app, valid from shortly before `now`
+    fn jwt(&self, now: DateTime) -> Result<String> {
+        let claims = JwtClaims {
+            iat: now.timestamp() - JWT_BACKDATE.as_secs() as i64,
+            exp: now.timestamp() + JWT_LIFETIME.as_secs() as i64,
+            iss: self.app_id.to_string(),
+        };
+        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key)
+            .map_err(|e| format!("unable to sign a token of app {}: {}", self.app_id, e).into())
+    }
+
+    /// The installation's current access token, creating a new one through `client` if there is
+    /// none yet or it's about to expire
+    pub(super) async fn token(&self, client: &Client) -> Result<String> {
+        let _refresh = self.refresh.lock().await;
+        let repositories = self
+            .repositories
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        self.tokens
+            .token_with(
+                client,
+                || self.jwt(DateTime::now()),
+                self.installation_id,
+                &repositories,
+            )
+            .await
+    }
+}
+
+type TokenKey = (u64, BTreeSet<String>);
+
+/// Installation tokens, keyed by the installation and the set of repositories they are scoped
+/// to, which are reused until shortly before they expire. Clones share the same tokens.
+#[derive(Clone, Debug, Default)]
+pub struct InstallationTokenCache {
+    tokens: Arc<Mutex<HashMap<TokenKey, InstallationToken>>>,
+}
+
+impl InstallationTokenCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token of `installation_id` scoped to only `repositories`, creating one with `jwt` if no
+    /// cached token is valid for long enough
+    pub async fn token(
+        &self,
+        client: &Client,
+        jwt: &str,
+        installation_id: u64,
+        repositories: &[&str],
+    ) -> Result<String> {
+        self.token_with(client, || Ok(jwt.to_owned()), installation_id, repositories)
+            .await
+    }
+
+    /// Like [`InstallationTokenCache::token`], only signing the app's JSON web token with `jwt`
+    /// when a new token has to be created
+    pub(super) async fn token_with<F>(
+        &self,
+        client: &Client,
+        jwt: F,
+        installation_id: u64,
+        repositories: &[&str],
+    ) -> Result<String>
+    where
+        F: FnOnce() -> Result<String>,
+    {
+        let key = token_key(installation_id, repositories);
+        if let Some(token) = self.cached(&key, DateTime::now()) {
+            return Ok(token);
+        }
+
+        let repositories = key.1.iter().map(String::as_str).collect::<Vec<_>>();
+        let request = CreateInstallationTokenRequest {
+            repositories: &repositories,
+            permissions: None,
+        };
+        let token = client
+            .apps()
+            .create_installation_token(&jwt()?, installation_id, &request)
+            .await?
+            .into_inner();
+
+        let secret = token.token.clone();
+        self.tokens.lock().unwrap().insert(key, token);
+        Ok(secret)
+    }
+
+    /// Forget every token of `installation_id`, e.g. after it was uninstalled or its
+    /// permissions changed
+    pub fn invalidate(&self, installation_id: u64) {
+        self.tokens
+            .lock()
+            .unwrap()
+            .retain(|(id, _), _| *id != installation_id);
+    }
+
+    fn cached(&self, key: &TokenKey, now: DateTime) -> Option<String> {
+        let mut tokens = self.tokens.lock().unwrap();
+        // Drop every expiring token while at it, so tokens of repos no longer operated on don't
+        // accumulate
+        tokens.retain(|_, token| is_fresh(token, now));
+        tokens.get(key).map(|token| token.token.clone())
+    }
+}
+
+fn token_key(installation_id: u64, repositories: &[&str]) -> TokenKey {
+    let repositories = repositories
+        .iter()
+        .map(|repo| repo.to_lowercase())
+        .collect();
+    (installation_id, repositories)
+}
+
+fn is_fresh(token: &InstallationToken, now: DateTime) -> bool {
+    now.checked_add(TOKEN_EXPIRY_MARGIN)
+        .is_some_and(|deadline| token.expires_at > deadline)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{token_key, InstallationTokenCache};
+    use crate::{DateTime, InstallationToken};
+
+    fn token(token: &str, expires_at: i64) -> InstallationToken {
+        InstallationToken {
+            token: token.to_owned(),
+            expires_at: DateTime::from_timestamp(expires_at).unwrap(),
+            permissions: Default::default(),
+            repository_selection: Some("selected".to_owned()),
+            repositories: None,
+        }
+    }
+
+    #[test]
+    fn tokens_are_cached_per_repository_set() {
+        let cache = InstallationTokenCache::new();
+        {
+            let mut tokens = cache.tokens.lock().unwrap();
+            tokens.insert(token_key(1, &["bors", "other"]), token("both", 3600));
+            tokens.insert(token_key(1, &["bors"]), token("expiring", 60));
+            tokens.insert(token_key(2, &["bors"]), token("other-installation", 3600));
+        }
+        let now = DateTime::from_timestamp(0).unwrap();
+
+        // The order and case of the repositories don't matter
+        assert_eq!(
+            cache.cached(&token_key(1, &["Other", "bors", "bors"]), now),
+            Some("both".to_owned())
+        );
+        // Tokens about to expire aren't handed out
+        assert_eq!(cache.cached(&token_key(1, &["bors"]), now), None);
+        assert_eq!(cache.cached(&token_key(1, &["other"]), now), None);
+
+        cache.invalidate(1);
+        assert_eq!(cache.cached(&token_key(1, &["bors", "other"]), now), None);
+        assert_eq!(
+            cache.cached(&token_key(2, &["bors"]), now),
+            Some("other-installation".to_owned())
+        );
+    }
+}
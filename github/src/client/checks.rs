@@ -0,0 +1,119 @@
+use crate::{
+    client::{Client, PaginationOptions, Response, Result},
+    CheckRunList, CheckRunSummary, CheckStatus, Conclusion, DateTime,
+};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct CreateCheckRunRequest<'a> {
+    pub name: &'a str,
+    pub head_sha: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<&'a str>,
+    /// Defaults to `queued`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<CheckStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime>,
+    /// Required when `status` is `completed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conclusion: Option<Conclusion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<CheckRunOutputRequest<'a>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct UpdateCheckRunRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<CheckStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime>,
+    /// Setting a conclusion completes the check run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conclusion: Option<Conclusion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<CheckRunOutputRequest<'a>>,
+}
+
+/// What's shown for a check run in the Checks UI
+#[derive(Debug, Serialize)]
+pub struct CheckRunOutputRequest<'a> {
+    pub title: &'a str,
+    /// Markdown summary of the check run
+    pub summary: &'a str,
+    /// Markdown details of the check run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<&'a str>,
+}
+
+/// `ChecksClient` handles communication with the checks related methods of the GitHub API.
+///
+/// GitHub API docs: https://developer.github.com/v3/checks/
+pub struct ChecksClient<'a> {
+    inner: &'a Client,
+}
+
+impl<'a> ChecksClient<'a> {
+    pub(super) fn new(client: &'a Client) -> Self {
+        Self { inner: client }
+    }
+
+    /// List check runs for a commit SHA, branch name, or tag name
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/checks/runs/#list-check-runs-for-a-git-reference
+    pub async fn list_check_runs_for_ref(
+        &self,
+        owner: &str,
+        repo: &str,
+        ref_name: &str,
+        options: PaginationOptions,
+    ) -> Result<Response<CheckRunList>> {
+        let url = format!("repos/{}/{}/commits/{}/check-runs", owner, repo, ref_name);
+        let response = self.inner.get(&url).query(&options).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Create a check run for a commit. Only Github Apps can create check runs
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/checks/runs#create-a-check-run
+    pub async fn create_check_run(
+        &self,
+        owner: &str,
+        repo: &str,
+        request: &CreateCheckRunRequest<'_>,
+    ) -> Result<Response<CheckRunSummary>> {
+        let url = format!("repos/{}/{}/check-runs", owner, repo);
+        let response = self.inner.post(&url).json(request).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Update a check run created by the same Github App
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/checks/runs#update-a-check-run
+    pub async fn update_check_run(
+        &self,
+        owner: &str,
+        repo: &str,
+        check_run_id: u64,
+        request: &UpdateCheckRunRequest<'_>,
+    ) -> Result<Response<CheckRunSummary>> {
+        let url = format!("repos/{}/{}/check-runs/{}", owner, repo, check_run_id);
+        let response = self.inner.patch(&url).json(request).send().await?;
+
+        self.inner.json(response).await
+    }
+}
@@ -29,9 +29,40 @@ pub enum Error {
     #[error("AbuseLimit")]
     AbuseLimit,
 
+    /// The API token was rejected, either because it has expired, been revoked, or is invalid
+    #[error("AuthExpired: bad credentials")]
+    AuthExpired,
+
+    /// The request would have written to Github while the client is read-only
+    #[error("ReadOnly: writes to Github are disabled")]
+    ReadOnly,
+
     #[cfg(feature = "graphql")]
     #[error("GraphqlError: {0:?}")]
     GraphqlError(Vec<graphql_client::Error>),
+
+    /// Errors reported for a raw GraphQL query
+    #[cfg(feature = "graphql")]
+    #[error("GraphqlError: {0}")]
+    Graphql(Box<crate::client::GraphqlErrors>),
+}
+
+impl Error {
+    /// Indicates if the request failed because the API token is no longer valid
+    pub fn is_auth_expired(&self) -> bool {
+        matches!(self, Error::AuthExpired)
+    }
+
+    /// Indicates if Github responded with `404 Not Found`, e.g. because the requested resource
+    /// doesn't exist or isn't visible to the authenticated user
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Error::GithubClientError(status, _) if *status == reqwest::StatusCode::NOT_FOUND)
+    }
+
+    /// Indicates if the request was refused because the client is read-only
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, Error::ReadOnly)
+    }
 }
 
 impl From<&'static str> for Error {
@@ -86,6 +117,6 @@ mod test {
             "message": "Validation Failed"
         }"#;
 
-        let _e: GithubClientError = serde_json::from_str(&json).unwrap();
+        let _e: GithubClientError = serde_json::from_str(json).unwrap();
     }
 }
@@ -1,6 +1,19 @@
-use crate::client::{Client, Error, Response, Result};
+use crate::{
+    client::{Client, Error, Response, Result},
+    DateTime,
+};
 use graphql_client::{QueryBody, Response as GraphqlResponse};
-use serde::{de::DeserializeOwned, Serialize};
+use log::{debug, info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{fmt, sync::atomic::Ordering};
+
+/// Error reported when the endpoint doesn't know about a persisted query's hash yet
+const PERSISTED_QUERY_NOT_FOUND: &str = "PersistedQueryNotFound";
+
+/// Error reported when the endpoint doesn't support persisted queries at all
+const PERSISTED_QUERY_NOT_SUPPORTED: &str = "PersistedQueryNotSupported";
 
 /// `GraphqlClient` handles communication with the GitHub's GraphQL API.
 ///
@@ -9,28 +22,460 @@ pub struct GraphqlClient<'a> {
     inner: &'a Client,
 }
 
+/// Body of a GraphQL request, optionally referencing a persisted query by its hash
+#[derive(Debug, Serialize)]
+struct Request<'q, V> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<&'static str>,
+    variables: &'q V,
+    #[serde(rename = "operationName")]
+    operation_name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<Extensions>,
+}
+
+#[derive(Debug, Serialize)]
+struct Extensions {
+    #[serde(rename = "persistedQuery")]
+    persisted_query: PersistedQuery,
+}
+
+#[derive(Debug, Serialize)]
+struct PersistedQuery {
+    version: u8,
+    #[serde(rename = "sha256Hash")]
+    sha256_hash: String,
+}
+
+impl<'q, V> Request<'q, V> {
+    fn new(query: &'q QueryBody<V>) -> Self {
+        Self {
+            query: Some(query.query),
+            variables: &query.variables,
+            operation_name: query.operation_name,
+            extensions: None,
+        }
+    }
+
+    fn persisted(query: &'q QueryBody<V>) -> Self {
+        Self {
+            query: None,
+            extensions: Some(Extensions {
+                persisted_query: PersistedQuery {
+                    version: 1,
+                    sha256_hash: hex::encode(Sha256::digest(query.query.as_bytes())),
+                },
+            }),
+            ..Self::new(query)
+        }
+    }
+
+    /// Include the full query text, registering its hash with the endpoint
+    fn with_query(self, query: &'static str) -> Self {
+        Self {
+            query: Some(query),
+            ..self
+        }
+    }
+}
+
+/// Body of a raw GraphQL request
+#[derive(Debug, Serialize)]
+struct RawRequest<'q, V> {
+    query: &'q str,
+    variables: &'q V,
+}
+
+/// Body of the response to a raw GraphQL request
+#[derive(Debug, Deserialize)]
+struct RawBody {
+    data: Option<Value>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+/// The result of a raw GraphQL query
+#[derive(Clone, Debug)]
+pub struct RawResponse {
+    /// The `data` of the response, as selected by the query
+    pub data: Value,
+    /// What the query cost, if it selected the `rateLimit` field
+    pub cost: Option<QueryCost>,
+}
+
+/// The cost of a query and the state of the GraphQL rate limit after it, reported when a query
+/// selects e.g. `rateLimit { cost limit nodeCount remaining resetAt }`
+///
+/// GitHub API docs: https://docs.github.com/en/graphql/overview/resource-limitations#returning-the-rate-limit-status
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryCost {
+    pub cost: u64,
+    pub limit: u64,
+    #[serde(default)]
+    pub node_count: u64,
+    pub remaining: u64,
+    pub reset_at: DateTime,
+}
+
+/// Category of a GraphQL error, as reported by Github in its `type` field
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[non_exhaustive]
+pub enum GraphqlErrorType {
+    NotFound,
+    Forbidden,
+    RateLimited,
+    MaxNodeLimitExceeded,
+    InsufficientScopes,
+    Unprocessable,
+    Internal,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GraphqlErrorLocation {
+    pub line: u64,
+    pub column: u64,
+}
+
+/// An error reported by Github's GraphQL API
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct GraphqlError {
+    pub message: String,
+    /// Category of the error, missing for e.g. errors parsing or validating the query
+    #[serde(rename = "type")]
+    pub error_type: Option<GraphqlErrorType>,
+    /// Path of the field the error occurred at, made of field names and list indices
+    #[serde(default)]
+    pub path: Vec<Value>,
+    #[serde(default)]
+    pub locations: Vec<GraphqlErrorLocation>,
+    pub extensions: Option<Value>,
+}
+
+/// The errors a raw GraphQL query failed with, along with the partial data and the cost of the
+/// query, if any were returned alongside them
+#[derive(Clone, Debug)]
+pub struct GraphqlErrors {
+    pub errors: Vec<GraphqlError>,
+    pub data: Option<Value>,
+    pub cost: Option<QueryCost>,
+}
+
+impl GraphqlErrors {
+    /// Indicates if the query was refused because the GraphQL rate limit was exceeded
+    pub fn is_rate_limited(&self) -> bool {
+        self.has_error_type(&GraphqlErrorType::RateLimited)
+    }
+
+    pub fn has_error_type(&self, error_type: &GraphqlErrorType) -> bool {
+        self.errors
+            .iter()
+            .any(|e| e.error_type.as_ref() == Some(error_type))
+    }
+}
+
+impl fmt::Display for GraphqlErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            match &error.error_type {
+                Some(error_type) => write!(f, "{:?}: {}", error_type, error.message)?,
+                None => f.write_str(&error.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<'a> GraphqlClient<'a> {
     pub(super) fn new(client: &'a Client) -> Self {
         Self { inner: client }
     }
 
+    /// Perform a query given as plain text against GitHub's GraphQL Endpoint, for queries the
+    /// typed client doesn't cover yet. The `data` of the response is returned as is, and errors
+    /// are reported as `Error::Graphql`.
+    ///
+    /// Selecting `rateLimit { cost limit nodeCount remaining resetAt }` in the query reports its
+    /// cost in `RawResponse::cost`.
+    pub async fn raw<V: Serialize>(
+        &self,
+        query: &str,
+        variables: &V,
+    ) -> Result<Response<RawResponse>> {
+        if self.inner.is_read_only() && is_mutation_text(query) {
+            info!("read-only: refusing raw mutation");
+            return Err(Error::ReadOnly);
+        }
+
+        let request = RawRequest { query, variables };
+        let response = self.inner.post("graphql").json(&request).send().await?;
+        into_raw_result(self.inner.json::<RawBody>(response).await?)
+    }
+
     /// Perform a Query against GitHub's GraphQL Endpoint
     pub async fn query<V: Serialize, R: DeserializeOwned>(
         &self,
         query: &QueryBody<V>,
     ) -> Result<Response<R>> {
-        let response = self.inner.post("graphql").json(query).send().await?;
-        let (pagination, rate_limit, response) = self
+        if self.inner.is_read_only() && is_mutation(query) {
+            info!("read-only: refusing mutation {}", query.operation_name);
+            return Err(Error::ReadOnly);
+        }
+
+        if !self.persisted_queries() {
+            return self.send(&Request::new(query)).await;
+        }
+
+        match self.send(&Request::persisted(query)).await {
+            Err(Error::GraphqlError(errors)) if has_error(&errors, PERSISTED_QUERY_NOT_FOUND) => {
+                debug!("registering persisted query {}", query.operation_name);
+                self.send(&Request::persisted(query).with_query(query.query))
+                    .await
+            }
+            Err(Error::GraphqlError(errors))
+                if has_error(&errors, PERSISTED_QUERY_NOT_SUPPORTED) =>
+            {
+                self.disable_persisted_queries();
+                self.send(&Request::new(query)).await
+            }
+            // Endpoints without support for persisted queries don't know where to find them
+            Err(e) if e.is_not_found() => {
+                self.disable_persisted_queries();
+                self.send(&Request::new(query)).await
+            }
+            result => result,
+        }
+    }
+
+    /// Perform several independent Queries against GitHub's GraphQL Endpoint, returning their
+    /// results in the same order.
+    ///
+    /// If batching is enabled the queries are sent as a single request, otherwise, or if the
+    /// endpoint rejects the batch, they're sent one at a time.
+    pub async fn batch<V: Serialize, R: DeserializeOwned>(
+        &self,
+        queries: &[QueryBody<V>],
+    ) -> Vec<Result<Response<R>>> {
+        // Mutations are sent individually while read-only so that they're refused
+        let refuse_mutations = self.inner.is_read_only() && queries.iter().any(is_mutation);
+        if queries.len() > 1
+            && !refuse_mutations
+            && self.inner.graphql_batching.load(Ordering::Relaxed)
+        {
+            match self.send_batch(queries).await {
+                Ok(results) => return results,
+                Err(e @ Error::GithubClientError(..)) | Err(e @ Error::Message(_)) => {
+                    warn!("GraphQL batch rejected, disabling batching: {}", e);
+                    self.inner.graphql_batching.store(false, Ordering::Relaxed);
+                }
+                Err(e) => warn!("GraphQL batch failed: {}", e),
+            }
+        }
+
+        let mut results = Vec::with_capacity(queries.len());
+        for query in queries {
+            results.push(self.query(query).await);
+        }
+        results
+    }
+
+    async fn send_batch<V: Serialize, R: DeserializeOwned>(
+        &self,
+        queries: &[QueryBody<V>],
+    ) -> Result<Vec<Result<Response<R>>>> {
+        // Each query carries its full text so that a batch never needs to be retried to register
+        // a persisted query
+        let persisted = self.persisted_queries();
+        let body = queries
+            .iter()
+            .map(|query| {
+                if persisted {
+                    Request::persisted(query).with_query(query.query)
+                } else {
+                    Request::new(query)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let response = self.inner.post("graphql").json(&body).send().await?;
+        let (pagination, rate_limit, responses) = self
             .inner
-            .json::<GraphqlResponse<R>>(response)
+            .json::<Vec<GraphqlResponse<R>>>(response)
             .await?
             .into_parts();
 
-        match (response.data, response.errors) {
-            (Some(data), None) => Ok(Response::new(pagination, rate_limit, data)),
-            (_, Some(errors)) => Err(Error::GraphqlError(errors)),
-            // GraphQL endpoints should always retern something
-            (None, None) => unreachable!(),
+        if responses.len() != queries.len() {
+            return Err(Error::Message(
+                format!(
+                    "GraphQL batch of {} queries returned {} responses",
+                    queries.len(),
+                    responses.len()
+                )
+                .into(),
+            ));
         }
+
+        Ok(responses
+            .into_iter()
+            .map(|response| {
+                into_result(Response::new(
+                    pagination.clone(),
+                    rate_limit.clone(),
+                    response,
+                ))
+            })
+            .collect())
+    }
+
+    async fn send<V: Serialize, R: DeserializeOwned>(
+        &self,
+        request: &Request<'_, V>,
+    ) -> Result<Response<R>> {
+        let response = self.inner.post("graphql").json(request).send().await?;
+        into_result(self.inner.json::<GraphqlResponse<R>>(response).await?)
+    }
+
+    fn persisted_queries(&self) -> bool {
+        self.inner.graphql_persisted_queries.load(Ordering::Relaxed)
+    }
+
+    fn disable_persisted_queries(&self) {
+        warn!("GraphQL endpoint doesn't support persisted queries, disabling them");
+        self.inner
+            .graphql_persisted_queries
+            .store(false, Ordering::Relaxed);
+    }
+}
+
+fn into_result<R>(response: Response<GraphqlResponse<R>>) -> Result<Response<R>> {
+    let (pagination, rate_limit, response) = response.into_parts();
+
+    match (response.data, response.errors) {
+        (Some(data), None) => Ok(Response::new(pagination, rate_limit, data)),
+        (_, Some(errors)) => Err(Error::GraphqlError(errors)),
+        // GraphQL endpoints should always retern something
+        (None, None) => unreachable!(),
+    }
+}
+
+fn into_raw_result(response: Response<RawBody>) -> Result<Response<RawResponse>> {
+    let (pagination, rate_limit, body) = response.into_parts();
+
+    let cost = body
+        .data
+        .as_ref()
+        .and_then(|data| data.get("rateLimit"))
+        .and_then(|cost| serde_json::from_value(cost.clone()).ok());
+
+    if !body.errors.is_empty() {
+        return Err(Error::Graphql(Box::new(GraphqlErrors {
+            errors: body.errors,
+            data: body.data,
+            cost,
+        })));
+    }
+
+    let data = body.data.unwrap_or(Value::Null);
+    Ok(Response::new(
+        pagination,
+        rate_limit,
+        RawResponse { data, cost },
+    ))
+}
+
+fn is_mutation<V>(query: &QueryBody<V>) -> bool {
+    is_mutation_text(query.query)
+}
+
+fn is_mutation_text(query: &str) -> bool {
+    query.trim_start().starts_with("mutation")
+}
+
+fn has_error(errors: &[graphql_client::Error], message: &str) -> bool {
+    errors.iter().any(|e| e.message == message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn persisted_request() {
+        let query = QueryBody {
+            variables: (),
+            query: "query Viewer { viewer { login } }",
+            operation_name: "Viewer",
+        };
+
+        let request = serde_json::to_value(Request::persisted(&query)).unwrap();
+        assert!(request.get("query").is_none());
+        assert_eq!(
+            request["extensions"]["persistedQuery"]["sha256Hash"],
+            "9cfe9cf37d218bb81c6690144fd3f204b301d434faa053ed54e6bb5dd9466365"
+        );
+
+        let request =
+            serde_json::to_value(Request::persisted(&query).with_query(query.query)).unwrap();
+        assert_eq!(request["query"], query.query);
+        assert_eq!(request["extensions"]["persistedQuery"]["version"], 1);
+    }
+
+    #[test]
+    fn raw_responses() {
+        let response = |body: Value| {
+            into_raw_result(Response::new(
+                Default::default(),
+                Default::default(),
+                serde_json::from_value(body).unwrap(),
+            ))
+        };
+
+        let ok = response(serde_json::json!({
+            "data": {
+                "viewer": { "login": "bors" },
+                "rateLimit": {
+                    "cost": 1,
+                    "limit": 5000,
+                    "nodeCount": 0,
+                    "remaining": 4999,
+                    "resetAt": "2020-01-01T00:00:00Z",
+                },
+            },
+        }))
+        .unwrap()
+        .into_inner();
+        assert_eq!(ok.data["viewer"]["login"], "bors");
+        assert_eq!(ok.cost.unwrap().remaining, 4999);
+
+        let errors = match response(serde_json::json!({
+            "data": { "repository": null },
+            "errors": [{
+                "type": "NOT_FOUND",
+                "path": ["repository"],
+                "locations": [{ "line": 1, "column": 3 }],
+                "message": "Could not resolve to a Repository with the name 'bors-rs/nope'.",
+            }, {
+                "type": "SOMETHING_NEW",
+                "message": "Something new",
+            }],
+        })) {
+            Err(Error::Graphql(errors)) => errors,
+            other => panic!("expected graphql errors, got {:?}", other),
+        };
+        assert!(errors.has_error_type(&GraphqlErrorType::NotFound));
+        assert!(!errors.is_rate_limited());
+        assert_eq!(errors.errors[1].error_type, Some(GraphqlErrorType::Unknown));
+        assert_eq!(errors.data.as_ref().unwrap()["repository"], Value::Null);
+        assert_eq!(
+            errors.to_string(),
+            "NotFound: Could not resolve to a Repository with the name 'bors-rs/nope'.; \
+             Unknown: Something new"
+        );
     }
 }
@@ -1,10 +1,8 @@
 use crate::{
     client::{
-        Client, PaginationOptions, Response, Result, SortDirection, SortPages, StateFilter,
-        MEDIA_TYPE_INTEGRATION_PREVIEW, MEDIA_TYPE_LOCK_REASON_PREVIEW,
-        MEDIA_TYPE_REACTIONS_PREVIEW,
+        Client, PaginationOptions, Preview, Response, Result, SortDirection, SortPages, StateFilter,
     },
-    Comment, DateTime, Issue, Label, State, User,
+    Comment, DateTime, Issue, IssueEventRecord, Label, State, User,
 };
 use serde::Serialize;
 
@@ -16,6 +14,7 @@ pub struct ListIssuesOptions {
     /// * mentioned: Issues mentioning you
     /// * subscribed: Issues you're subscribed to updates for
     /// * all: All issues the authenticated user can see, regardless of participation or creation
+    ///
     /// Default: assigned
     pub filter: ListIssuesFilter,
 
@@ -38,9 +37,10 @@ pub struct ListIssuesOptions {
     pub pagination_options: PaginationOptions,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ListIssuesFilter {
+    #[default]
     Assigned,
     Created,
     Mentioned,
@@ -48,12 +48,6 @@ pub enum ListIssuesFilter {
     All,
 }
 
-impl Default for ListIssuesFilter {
-    fn default() -> Self {
-        ListIssuesFilter::Assigned
-    }
-}
-
 #[derive(Debug, Default, Serialize)]
 pub struct ListIssuesForRepoOptions {
     // If an integer is passed, it should refer to a milestone by its number field. If the string *
@@ -180,9 +174,9 @@ impl<'a> IssuesClient<'a> {
             .inner
             .get(url)
             // For the 'performed_via_github_app' object in the response
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_INTEGRATION_PREVIEW)
+            .preview(Preview::Integration)
             // For the 'reactions' object in an Issue
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .query(&options)
             .send()
             .await?;
@@ -242,9 +236,9 @@ impl<'a> IssuesClient<'a> {
             .inner
             .get(&url)
             // For the 'performed_via_github_app' object in the response
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_INTEGRATION_PREVIEW)
+            .preview(Preview::Integration)
             // For the 'reactions' object in an Issue
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .send()
             .await?;
 
@@ -306,7 +300,7 @@ impl<'a> IssuesClient<'a> {
         if let Some(lock_reason) = lock_reason {
             request_builder = request_builder
                 // For the supplying a lock reason
-                .header(reqwest::header::ACCEPT, MEDIA_TYPE_LOCK_REASON_PREVIEW)
+                .preview(Preview::LockReason)
                 .json(&LockRequest { lock_reason });
         }
 
@@ -422,7 +416,7 @@ impl<'a> IssuesClient<'a> {
             .inner
             .get(&url)
             // For the 'reactions' object in an Issue
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .query(&options)
             .send()
             .await?;
@@ -444,7 +438,7 @@ impl<'a> IssuesClient<'a> {
             .inner
             .get(&url)
             // For the 'reactions' object in an Issue
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .query(&options)
             .send()
             .await?;
@@ -466,9 +460,9 @@ impl<'a> IssuesClient<'a> {
             .inner
             .get(&url)
             // For the 'performed_via_github_app' object
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_INTEGRATION_PREVIEW)
+            .preview(Preview::Integration)
             // For the 'reactions' object
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .send()
             .await?;
 
@@ -748,6 +742,25 @@ impl<'a> IssuesClient<'a> {
         self.inner.json(response).await
     }
 
+    // Events Endpoint
+    // https://developer.github.com/v3/issues/events/
+
+    /// List the events of an issue, oldest first
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/issues/events/#list-events-for-an-issue
+    pub async fn list_events(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        options: Option<PaginationOptions>,
+    ) -> Result<Response<Vec<IssueEventRecord>>> {
+        let url = format!("repos/{}/{}/issues/{}/events", owner, repo, issue_number);
+        let response = self.inner.get(&url).query(&options).send().await?;
+
+        self.inner.json(response).await
+    }
+
     // TODO
     // Milestone Endpoint
     // https://developer.github.com/v3/issues/milestones/
@@ -0,0 +1,25 @@
+use crate::{
+    client::{Client, Response, Result},
+    Meta,
+};
+
+/// `MetaClient` handles communication with the meta related methods of the GitHub API.
+///
+/// GitHub API docs: https://developer.github.com/v3/meta/
+pub struct MetaClient<'a> {
+    inner: &'a Client,
+}
+
+impl<'a> MetaClient<'a> {
+    pub(super) fn new(client: &'a Client) -> Self {
+        Self { inner: client }
+    }
+
+    /// Get information about Github, including the IP address ranges its services use
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/meta/#meta
+    pub async fn get(&self) -> Result<Response<Meta>> {
+        let response = self.inner.get("meta").send().await?;
+        self.inner.json(response).await
+    }
+}
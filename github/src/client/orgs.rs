@@ -0,0 +1,153 @@
+use crate::{
+    client::{
+        Client, CreateHookRequest, EditHookRequest, ListHookDeliveriesOptions, PaginationOptions,
+        Response, Result,
+    },
+    Hook, HookDelivery, HookDeliveryDetails,
+};
+
+/// `OrgsClient` handles communication with the organization related methods of the GitHub API.
+///
+/// GitHub API docs: https://developer.github.com/v3/orgs/
+pub struct OrgsClient<'a> {
+    inner: &'a Client,
+}
+
+impl<'a> OrgsClient<'a> {
+    pub(super) fn new(client: &'a Client) -> Self {
+        Self { inner: client }
+    }
+
+    /// Check if `username` is a member of `org`. Only public members are visible when the
+    /// authenticated user isn't a member of `org` themselves
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/members#check-organization-membership-for-a-user
+    pub async fn is_member(&self, org: &str, username: &str) -> Result<Response<bool>> {
+        let url = format!("orgs/{}/members/{}", org, username);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.boolean(response).await
+    }
+
+    /// List organization webhooks
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/webhooks#list-organization-webhooks
+    pub async fn list_hooks(
+        &self,
+        org: &str,
+        options: PaginationOptions,
+    ) -> Result<Response<Vec<Hook>>> {
+        let url = format!("orgs/{}/hooks", org);
+        let response = self.inner.get(&url).query(&options).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Get an organization webhook
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/webhooks#get-an-organization-webhook
+    pub async fn get_hook(&self, org: &str, hook_id: u64) -> Result<Response<Hook>> {
+        let url = format!("orgs/{}/hooks/{}", org, hook_id);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Create an organization webhook
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/webhooks#create-an-organization-webhook
+    pub async fn create_hook(
+        &self,
+        org: &str,
+        request: &CreateHookRequest<'_>,
+    ) -> Result<Response<Hook>> {
+        let url = format!("orgs/{}/hooks", org);
+        let response = self.inner.post(&url).json(request).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Update an organization webhook. `add_events` and `remove_events` aren't supported for
+    /// organization webhooks, use `events` instead
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/webhooks#update-an-organization-webhook
+    pub async fn edit_hook(
+        &self,
+        org: &str,
+        hook_id: u64,
+        request: &EditHookRequest<'_>,
+    ) -> Result<Response<Hook>> {
+        let url = format!("orgs/{}/hooks/{}", org, hook_id);
+        let response = self.inner.patch(&url).json(request).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Delete an organization webhook
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/webhooks#delete-an-organization-webhook
+    pub async fn delete_hook(&self, org: &str, hook_id: u64) -> Result<Response<()>> {
+        let url = format!("orgs/{}/hooks/{}", org, hook_id);
+        let response = self.inner.delete(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+
+    /// Trigger a `ping` event to be sent to the webhook
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/webhooks#ping-an-organization-webhook
+    pub async fn ping_hook(&self, org: &str, hook_id: u64) -> Result<Response<()>> {
+        let url = format!("orgs/{}/hooks/{}/pings", org, hook_id);
+        let response = self.inner.post(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+
+    /// List the recent deliveries of an organization webhook, most recent first
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/webhooks#list-deliveries-for-an-organization-webhook
+    pub async fn list_hook_deliveries(
+        &self,
+        org: &str,
+        hook_id: u64,
+        options: &ListHookDeliveriesOptions,
+    ) -> Result<Response<Vec<HookDelivery>>> {
+        let url = format!("orgs/{}/hooks/{}/deliveries", org, hook_id);
+        let response = self.inner.get(&url).query(options).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Get a delivery of an organization webhook, including its request and response
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/webhooks#get-a-webhook-delivery-for-an-organization-webhook
+    pub async fn get_hook_delivery(
+        &self,
+        org: &str,
+        hook_id: u64,
+        delivery_id: u64,
+    ) -> Result<Response<HookDeliveryDetails>> {
+        let url = format!("orgs/{}/hooks/{}/deliveries/{}", org, hook_id, delivery_id);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Redeliver a delivery of an organization webhook
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/orgs/webhooks#redeliver-a-delivery-for-an-organization-webhook
+    pub async fn redeliver_hook_delivery(
+        &self,
+        org: &str,
+        hook_id: u64,
+        delivery_id: u64,
+    ) -> Result<Response<()>> {
+        let url = format!(
+            "orgs/{}/hooks/{}/deliveries/{}/attempts",
+            org, hook_id, delivery_id
+        );
+        let response = self.inner.post(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+}
@@ -1,9 +1,13 @@
-use crate::client::HEADER_LINK;
+use crate::client::{Response, Result, HEADER_LINK};
+use futures::{
+    future::Future,
+    stream::{self, Stream, TryStreamExt},
+};
 use serde::Serialize;
 use url::Url;
 
 /// Represents `Pagination` information from a Github API request
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct Pagination {
     pub next_page: Option<usize>,
     pub prev_page: Option<usize>,
@@ -42,11 +46,13 @@ impl Pagination {
                 continue;
             };
 
-            // and then pull out the page number
-            let page = if let Some(page) =
-                url.query_pairs()
-                    .find_map(|(k, v)| if k == "page" { Some(v) } else { None })
-            {
+            // and then pull out the page number, or the cursor of cursor-based pagination
+            let (page, is_cursor) = if let Some(page) =
+                url.query_pairs().find_map(|(k, v)| match k.as_ref() {
+                    "page" => Some((v, false)),
+                    "cursor" => Some((v, true)),
+                    _ => None,
+                }) {
                 page
             } else {
                 continue;
@@ -55,7 +61,7 @@ impl Pagination {
             for rel in &segments[1..] {
                 match rel.trim() {
                     "rel=\"next\"" => {
-                        if let Ok(n) = page.parse() {
+                        if let (false, Ok(n)) = (is_cursor, page.parse()) {
                             pagination.next_page = Some(n);
                         } else {
                             pagination.next_page_token = Some(page.clone().into_owned());
@@ -85,57 +91,88 @@ pub struct PaginationOptions {
     pub per_page: Option<usize>,
 }
 
+/// Stream the items of a paginated listing, with `fetch` requesting each page once every item of
+/// the previous one has been consumed
+///
+/// ```no_run
+/// # async fn stars(client: &github::Client) -> github::client::Result<()> {
+/// use futures::stream::TryStreamExt;
+///
+/// let activity = client.activity();
+/// let stargazers = github::client::paginate(Some(100), |options| {
+///     activity.list_stargazers("bors-rs", "bors", Some(options))
+/// });
+/// let stargazers = stargazers.try_collect::<Vec<_>>().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn paginate<'a, T, F, Fut>(
+    per_page: Option<usize>,
+    mut fetch: F,
+) -> impl Stream<Item = Result<T>> + 'a
+where
+    T: 'a,
+    F: FnMut(PaginationOptions) -> Fut + 'a,
+    Fut: Future<Output = Result<Response<Vec<T>>>> + 'a,
+{
+    stream::try_unfold(Some(1), move |page: Option<usize>| {
+        let request = page.map(|page| {
+            fetch(PaginationOptions {
+                page: Some(page),
+                per_page,
+            })
+        });
+        async move {
+            let response = match request {
+                Some(request) => request.await?,
+                None => return Ok(None),
+            };
+            let next_page = response.pagination().next_page;
+            let items = stream::iter(response.into_inner().into_iter().map(Ok));
+            Result::<_>::Ok(Some((items, next_page)))
+        }
+    })
+    .try_flatten()
+}
+
 #[derive(Debug, Default, Serialize)]
 pub struct PaginationCursorOptions {
     pub page: Option<String>,
     pub per_page: Option<usize>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum StateFilter {
+    #[default]
     Open,
     Closed,
     All,
 }
 
-impl Default for StateFilter {
-    fn default() -> Self {
-        StateFilter::Open
-    }
-}
-
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SortPages {
+    #[default]
     Created,
     Updated,
     Comments,
 }
 
-impl Default for SortPages {
-    fn default() -> Self {
-        SortPages::Created
-    }
-}
-
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub enum SortDirection {
     #[serde(rename = "asc")]
     Ascending,
+    #[default]
     #[serde(rename = "desc")]
     Descending,
 }
 
-impl Default for SortDirection {
-    fn default() -> Self {
-        SortDirection::Descending
-    }
-}
-
 #[cfg(test)]
 mod test {
-    use super::{Pagination, HEADER_LINK};
+    use super::{paginate, Pagination, HEADER_LINK};
+    use crate::client::{Rate, Response};
+    use futures::stream::TryStreamExt;
     use reqwest::header::HeaderMap;
 
     #[test]
@@ -147,5 +184,39 @@ mod test {
         let p = Pagination::from_headers(&headers);
         assert_eq!(p.next_page, Some(3));
         assert_eq!(p.last_page, Some(50));
+
+        let mut headers = HeaderMap::new();
+        let link = r#"<https://api.github.com/repos/bors-rs/bors/hooks/1/deliveries?cursor=v1_12077215967&per_page=30>; rel="next""#;
+        headers.insert(HEADER_LINK, link.parse().unwrap());
+
+        let p = Pagination::from_headers(&headers);
+        assert_eq!(p.next_page, None);
+        assert_eq!(p.next_page_token.as_deref(), Some("v1_12077215967"));
+    }
+
+    #[tokio::test]
+    async fn paginated_stream() {
+        let mut requested = Vec::new();
+        let items = paginate(Some(2), |options| {
+            let page = options.page.unwrap();
+            requested.push(page);
+            async move {
+                let pagination = Pagination {
+                    next_page: Some(page + 1).filter(|&next| next <= 3),
+                    ..Pagination::default()
+                };
+                Ok(Response::new(
+                    pagination,
+                    Rate::default(),
+                    vec![page * 10, page * 10 + 1],
+                ))
+            }
+        })
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+        assert_eq!(items, [10, 11, 20, 21, 30, 31]);
+        assert_eq!(requested, [1, 2, 3]);
     }
 }
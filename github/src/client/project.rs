@@ -1,5 +1,5 @@
 use crate::{
-    client::{Client, PaginationOptions, Response, Result, MEDIA_TYPE_PROJECTS_PREVIEW},
+    client::{Client, PaginationOptions, Preview, Response, Result},
     Project, ProjectCard, ProjectColumn,
 };
 use serde::Serialize;
@@ -92,7 +92,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .get(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .query(&options)
             .send()
             .await?;
@@ -111,7 +111,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .get(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .query(&options)
             .send()
             .await?;
@@ -130,7 +130,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .get(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .query(&options)
             .send()
             .await?;
@@ -145,7 +145,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .get(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .send()
             .await?;
 
@@ -166,7 +166,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .post(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(&request)
             .send()
             .await?;
@@ -187,7 +187,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .post(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(&request)
             .send()
             .await?;
@@ -208,7 +208,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .post(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(&request)
             .send()
             .await?;
@@ -227,7 +227,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .patch(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(request)
             .send()
             .await?;
@@ -242,7 +242,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .delete(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .send()
             .await?;
 
@@ -263,7 +263,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .get(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .query(&options)
             .send()
             .await?;
@@ -278,7 +278,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .get(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .send()
             .await?;
 
@@ -296,7 +296,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .post(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(&request)
             .send()
             .await?;
@@ -315,7 +315,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .patch(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(request)
             .send()
             .await?;
@@ -330,7 +330,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .delete(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .send()
             .await?;
 
@@ -348,7 +348,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .post(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(request)
             .send()
             .await?;
@@ -373,7 +373,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .get(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .query(&options)
             .send()
             .await?;
@@ -388,7 +388,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .get(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .send()
             .await?;
 
@@ -412,7 +412,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .post(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(&request)
             .send()
             .await?;
@@ -437,7 +437,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .patch(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(&request)
             .send()
             .await?;
@@ -452,7 +452,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .delete(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .send()
             .await?;
 
@@ -477,7 +477,7 @@ impl<'a> ProjectClient<'a> {
             .inner
             .post(&url)
             // For the enabling projects endpoint
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_PROJECTS_PREVIEW)
+            .preview(Preview::Projects)
             .json(&request)
             .send()
             .await?;
@@ -1,11 +1,8 @@
 use crate::{
     client::{
-        Client, PaginationOptions, Response, Result, SortDirection, SortPages, StateFilter,
-        MEDIA_TYPE_DRAFT_PREVIEW, MEDIA_TYPE_LOCK_REASON_PREVIEW,
-        MEDIA_TYPE_MULTI_LINE_COMMENTS_PREVIEW, MEDIA_TYPE_REACTIONS_PREVIEW,
-        MEDIA_TYPE_UPDATE_PULL_REQUEST_BRANCH_PREVIEW,
+        Client, PaginationOptions, Preview, Response, Result, SortDirection, SortPages, StateFilter,
     },
-    DateTime, PullRequest, Review, ReviewComment, Team, User,
+    CommitFile, DateTime, PullRequest, Review, ReviewComment, Team, User,
 };
 use serde::{Deserialize, Serialize};
 
@@ -105,20 +102,15 @@ pub struct MergePullRequest {
     pub sha: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum MergeMethod {
+    #[default]
     Merge,
     Squash,
     Rebase,
 }
 
-impl Default for MergeMethod {
-    fn default() -> Self {
-        MergeMethod::Merge
-    }
-}
-
 #[derive(Debug, Default, Deserialize)]
 pub struct MergePullRequestResponse {
     pub sha: String,
@@ -244,9 +236,9 @@ impl<'a> PullsClient<'a> {
             .inner
             .get(&url)
             // For the 'lock_reason' object
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_LOCK_REASON_PREVIEW)
+            .preview(Preview::LockReason)
             // For the 'draft' parameter
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_DRAFT_PREVIEW)
+            .preview(Preview::Draft)
             .query(&options)
             .send()
             .await?;
@@ -268,9 +260,9 @@ impl<'a> PullsClient<'a> {
             .inner
             .get(&url)
             // For the 'lock_reason' object
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_LOCK_REASON_PREVIEW)
+            .preview(Preview::LockReason)
             // For the 'draft' parameter
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_DRAFT_PREVIEW)
+            .preview(Preview::Draft)
             .send()
             .await?;
 
@@ -291,7 +283,7 @@ impl<'a> PullsClient<'a> {
             .inner
             .post(&url)
             // For the 'draft' parameter
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_DRAFT_PREVIEW)
+            .preview(Preview::Draft)
             .json(&pull_request)
             .send()
             .await?;
@@ -314,9 +306,9 @@ impl<'a> PullsClient<'a> {
             .inner
             .post(&url)
             // For the 'lock_reason' object
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_LOCK_REASON_PREVIEW)
+            .preview(Preview::LockReason)
             // For the 'draft' parameter
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_DRAFT_PREVIEW)
+            .preview(Preview::Draft)
             .json(&pull_request)
             .send()
             .await?;
@@ -346,10 +338,7 @@ impl<'a> PullsClient<'a> {
             .inner
             .post(&url)
             // Enable this preview endpoint
-            .header(
-                reqwest::header::ACCEPT,
-                MEDIA_TYPE_UPDATE_PULL_REQUEST_BRANCH_PREVIEW,
-            )
+            .preview(Preview::UpdatePullRequestBranch)
             .json(&request)
             .send()
             .await?;
@@ -376,7 +365,6 @@ impl<'a> PullsClient<'a> {
         self.inner.empty(response).await
     }
 
-    //TODO add CommitFile type
     /// List files on a pull request
     ///
     /// GitHub API docs: https://developer.github.com/v3/pulls/#list-pull-requests-files
@@ -386,13 +374,11 @@ impl<'a> PullsClient<'a> {
         repo: &str,
         pull_number: u64,
         options: Option<PaginationOptions>,
-        //) -> Result<Response<Vec<CommitFile>>> {
-    ) -> Result<Response<()>> {
+    ) -> Result<Response<Vec<CommitFile>>> {
         let url = format!("repos/{}/{}/pulls/{}/files", owner, repo, pull_number);
         let response = self.inner.get(&url).query(&options).send().await?;
 
-        //self.inner.json(response).await
-        self.inner.empty(response).await
+        self.inner.json(response).await
     }
 
     /// Check if a pull request has been merged
@@ -615,12 +601,9 @@ impl<'a> PullsClient<'a> {
             .inner
             .get(&url)
             // For the multi line comments
-            .header(
-                reqwest::header::ACCEPT,
-                MEDIA_TYPE_MULTI_LINE_COMMENTS_PREVIEW,
-            )
+            .preview(Preview::MultiLineComments)
             // For the 'reactions' reaction summary object
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .query(&options)
             .send()
             .await?;
@@ -642,12 +625,9 @@ impl<'a> PullsClient<'a> {
             .inner
             .get(&url)
             // For the multi line comments
-            .header(
-                reqwest::header::ACCEPT,
-                MEDIA_TYPE_MULTI_LINE_COMMENTS_PREVIEW,
-            )
+            .preview(Preview::MultiLineComments)
             // For the 'reactions' reaction summary object
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .query(&options)
             .send()
             .await?;
@@ -669,12 +649,9 @@ impl<'a> PullsClient<'a> {
             .inner
             .get(&url)
             // For the multi line comments
-            .header(
-                reqwest::header::ACCEPT,
-                MEDIA_TYPE_MULTI_LINE_COMMENTS_PREVIEW,
-            )
+            .preview(Preview::MultiLineComments)
             // For the 'reactions' reaction summary object
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .send()
             .await?;
 
@@ -696,10 +673,7 @@ impl<'a> PullsClient<'a> {
             .inner
             .post(&url)
             // For the multi line comments
-            .header(
-                reqwest::header::ACCEPT,
-                MEDIA_TYPE_MULTI_LINE_COMMENTS_PREVIEW,
-            )
+            .preview(Preview::MultiLineComments)
             .json(&review_request)
             .send()
             .await?;
@@ -732,10 +706,7 @@ impl<'a> PullsClient<'a> {
             .inner
             .post(&url)
             // For the multi line comments
-            .header(
-                reqwest::header::ACCEPT,
-                MEDIA_TYPE_MULTI_LINE_COMMENTS_PREVIEW,
-            )
+            .preview(Preview::MultiLineComments)
             .json(&request)
             .send()
             .await?;
@@ -764,10 +735,7 @@ impl<'a> PullsClient<'a> {
             .inner
             .patch(&url)
             // For the multi line comments
-            .header(
-                reqwest::header::ACCEPT,
-                MEDIA_TYPE_MULTI_LINE_COMMENTS_PREVIEW,
-            )
+            .preview(Preview::MultiLineComments)
             .json(&request)
             .send()
             .await?;
@@ -1,9 +1,9 @@
 use crate::client::{
     Client, Response, Result, HEADER_RATE_LIMIT, HEADER_RATE_REMAINING, HEADER_RATE_RESET,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Rate {
     pub limit: usize,
     pub remaining: usize,
@@ -69,6 +69,20 @@ pub struct RateLimits {
     integration_manifest: Rate,
 }
 
+impl RateLimits {
+    pub fn core(&self) -> &Rate {
+        &self.core
+    }
+
+    pub fn search(&self) -> &Rate {
+        &self.search
+    }
+
+    pub fn graphql(&self) -> &Rate {
+        &self.graphql
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct RateLimitResponse {
     resources: RateLimits,
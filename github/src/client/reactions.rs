@@ -1,5 +1,5 @@
 use crate::{
-    client::{Client, PaginationOptions, Response, Result, MEDIA_TYPE_REACTIONS_PREVIEW},
+    client::{Client, PaginationOptions, Preview, Response, Result},
     Reaction, ReactionType,
 };
 use serde::Serialize;
@@ -90,7 +90,7 @@ impl<'a> ReactionsClient<'a> {
             .inner
             .get(url)
             // TODO: remove custom Accept headers when APIs fully launch.
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .query(&options)
             .send()
             .await?;
@@ -109,7 +109,7 @@ impl<'a> ReactionsClient<'a> {
             .inner
             .post(url)
             // TODO: remove custom Accept headers when APIs fully launch.
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .json(&request)
             .send()
             .await?;
@@ -122,7 +122,7 @@ impl<'a> ReactionsClient<'a> {
             .inner
             .delete(url)
             // TODO: remove custom Accept headers when APIs fully launch.
-            .header(reqwest::header::ACCEPT, MEDIA_TYPE_REACTIONS_PREVIEW)
+            .preview(Preview::Reactions)
             .send()
             .await?;
 
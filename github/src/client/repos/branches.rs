@@ -0,0 +1,25 @@
+use super::RepositoryClient;
+use crate::{
+    client::{Response, Result},
+    BranchProtection,
+};
+
+// Implementation for the branches endpoint
+// https://docs.github.com/en/rest/branches
+impl RepositoryClient<'_> {
+    /// Get the protection of a branch. Fails with a `404 Not Found` error if the branch isn't
+    /// protected
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/branches/branch-protection#get-branch-protection
+    pub async fn get_branch_protection(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+    ) -> Result<Response<BranchProtection>> {
+        let url = format!("repos/{}/{}/branches/{}/protection", owner, repo, branch);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.json(response).await
+    }
+}
@@ -0,0 +1,26 @@
+use super::RepositoryClient;
+use crate::{
+    client::{PaginationOptions, Response, Result},
+    PullRequest,
+};
+
+// Implementation for the commits enpoint
+// https://docs.github.com/en/rest/commits/commits
+impl RepositoryClient<'_> {
+    /// List the pull requests associated with a commit, i.e. the merged pull request which
+    /// introduced it into the default branch or the open pull requests containing it
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/commits/commits#list-pull-requests-associated-with-a-commit
+    pub async fn list_pull_requests_associated_with_commit(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+        options: PaginationOptions,
+    ) -> Result<Response<Vec<PullRequest>>> {
+        let url = format!("repos/{}/{}/commits/{}/pulls", owner, repo, sha);
+        let response = self.inner.get(&url).query(&options).send().await?;
+
+        self.inner.json(response).await
+    }
+}
@@ -0,0 +1,30 @@
+use super::RepositoryClient;
+use crate::{
+    client::{Preview, Response, Result},
+    CommunityProfile,
+};
+
+// Implementation from the community endpoint
+// https://developer.github.com/v3/repos/community/
+impl RepositoryClient<'_> {
+    /// Get the community profile metrics of a repository, i.e. its health percentage and which of
+    /// the recommended community files it has
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/repos/community/#get-community-profile-metrics
+    pub async fn get_community_profile_metrics(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Response<CommunityProfile>> {
+        let url = format!("repos/{}/{}/community/profile", owner, repo);
+        let response = self
+            .inner
+            .get(&url)
+            // TODO: remove custom Accept headers when APIs fully launch.
+            .preview(Preview::RepositoryCommunityHealthMetrics)
+            .send()
+            .await?;
+
+        self.inner.json(response).await
+    }
+}
@@ -0,0 +1,202 @@
+use super::RepositoryClient;
+use crate::{
+    client::{PaginationOptions, Response, Result},
+    EventType, Hook, HookDelivery, HookDeliveryDetails,
+};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct CreateHookRequest<'a> {
+    /// Must be `web`
+    pub name: &'a str,
+    pub config: HookConfigRequest<'a>,
+    pub events: &'a [EventType],
+    pub active: bool,
+}
+
+impl<'a> CreateHookRequest<'a> {
+    /// An active webhook delivering json payloads for `events` to `url`
+    pub fn new(url: &'a str, secret: Option<&'a str>, events: &'a [EventType]) -> Self {
+        Self {
+            name: "web",
+            config: HookConfigRequest {
+                url: Some(url),
+                content_type: Some("json"),
+                secret,
+                insecure_ssl: None,
+            },
+            events,
+            active: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct HookConfigRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<&'a str>,
+    /// Either `json` or `form`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<&'a str>,
+    /// `0` to verify the SSL certificate of the host for `url`, `1` to not verify it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insecure_ssl: Option<&'a str>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct EditHookRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<HookConfigRequest<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<&'a [EventType]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub add_events: Option<&'a [EventType]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remove_events: Option<&'a [EventType]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+}
+
+/// Deliveries are paginated with a cursor, the next of which is provided as
+/// `Pagination::next_page_token`
+#[derive(Debug, Default, Serialize)]
+pub struct ListHookDeliveriesOptions {
+    pub per_page: Option<usize>,
+    pub cursor: Option<String>,
+}
+
+// Implementation for the webhooks endpoint
+// https://developer.github.com/v3/repos/hooks/
+impl RepositoryClient<'_> {
+    /// List repository webhooks
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/repos/hooks/#list-repository-webhooks
+    pub async fn list_hooks(
+        &self,
+        owner: &str,
+        repo: &str,
+        options: PaginationOptions,
+    ) -> Result<Response<Vec<Hook>>> {
+        let url = format!("repos/{}/{}/hooks", owner, repo);
+        let response = self.inner.get(&url).query(&options).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Get a repository webhook
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/webhooks/repos#get-a-repository-webhook
+    pub async fn get_hook(&self, owner: &str, repo: &str, hook_id: u64) -> Result<Response<Hook>> {
+        let url = format!("repos/{}/{}/hooks/{}", owner, repo, hook_id);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Create a repository webhook
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/webhooks/repos#create-a-repository-webhook
+    pub async fn create_hook(
+        &self,
+        owner: &str,
+        repo: &str,
+        request: &CreateHookRequest<'_>,
+    ) -> Result<Response<Hook>> {
+        let url = format!("repos/{}/{}/hooks", owner, repo);
+        let response = self.inner.post(&url).json(request).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Update a repository webhook
+    ///
+    /// GitHub API docs: https://developer.github.com/v3/repos/hooks/#update-a-repository-webhook
+    pub async fn edit_hook(
+        &self,
+        owner: &str,
+        repo: &str,
+        hook_id: u64,
+        request: &EditHookRequest<'_>,
+    ) -> Result<Response<Hook>> {
+        let url = format!("repos/{}/{}/hooks/{}", owner, repo, hook_id);
+        let response = self.inner.patch(&url).json(request).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Delete a repository webhook
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/webhooks/repos#delete-a-repository-webhook
+    pub async fn delete_hook(&self, owner: &str, repo: &str, hook_id: u64) -> Result<Response<()>> {
+        let url = format!("repos/{}/{}/hooks/{}", owner, repo, hook_id);
+        let response = self.inner.delete(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+
+    /// Trigger a `ping` event to be sent to the webhook
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/webhooks/repos#ping-a-repository-webhook
+    pub async fn ping_hook(&self, owner: &str, repo: &str, hook_id: u64) -> Result<Response<()>> {
+        let url = format!("repos/{}/{}/hooks/{}/pings", owner, repo, hook_id);
+        let response = self.inner.post(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+
+    /// List the recent deliveries of a repository webhook, most recent first
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/webhooks/repo-deliveries#list-deliveries-for-a-repository-webhook
+    pub async fn list_hook_deliveries(
+        &self,
+        owner: &str,
+        repo: &str,
+        hook_id: u64,
+        options: &ListHookDeliveriesOptions,
+    ) -> Result<Response<Vec<HookDelivery>>> {
+        let url = format!("repos/{}/{}/hooks/{}/deliveries", owner, repo, hook_id);
+        let response = self.inner.get(&url).query(options).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Get a delivery of a repository webhook, including its request and response
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/webhooks/repo-deliveries#get-a-delivery-for-a-repository-webhook
+    pub async fn get_hook_delivery(
+        &self,
+        owner: &str,
+        repo: &str,
+        hook_id: u64,
+        delivery_id: u64,
+    ) -> Result<Response<HookDeliveryDetails>> {
+        let url = format!(
+            "repos/{}/{}/hooks/{}/deliveries/{}",
+            owner, repo, hook_id, delivery_id
+        );
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Redeliver a delivery of a repository webhook, e.g. one that was missed while bors was down
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/webhooks/repo-deliveries#redeliver-a-delivery-for-a-repository-webhook
+    pub async fn redeliver_hook_delivery(
+        &self,
+        owner: &str,
+        repo: &str,
+        hook_id: u64,
+        delivery_id: u64,
+    ) -> Result<Response<()>> {
+        let url = format!(
+            "repos/{}/{}/hooks/{}/deliveries/{}/attempts",
+            owner, repo, hook_id, delivery_id
+        );
+        let response = self.inner.post(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+}
@@ -1,9 +1,16 @@
 use crate::client::Client;
 
+mod branches;
 mod collaborators;
+mod commits;
+mod community;
+mod hooks;
+mod settings;
 mod status;
 
 pub use collaborators::ListCollaboratorsOptions;
+pub use hooks::{CreateHookRequest, EditHookRequest, HookConfigRequest, ListHookDeliveriesOptions};
+pub use settings::EditRepositoryRequest;
 pub use status::{CombinedStatus, CreateStatusRequest, RepoStatus};
 
 /// `RepositoryClient` handles communication with the Repository related methods of the GitHub API.
@@ -0,0 +1,111 @@
+use super::RepositoryClient;
+use crate::{
+    client::{Response, Result},
+    Repository, TagProtection,
+};
+use serde::Serialize;
+
+/// Settings of a repository to update, those which are `None` are left as they are
+#[derive(Debug, Default, Serialize)]
+pub struct EditRepositoryRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_merge_commit: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_squash_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_rebase_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_auto_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_update_branch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_branch_on_merge: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateTagProtectionRequest<'a> {
+    pattern: &'a str,
+}
+
+// Implementation for the repository and tag protection endpoints
+// https://docs.github.com/en/rest/repos/repos
+// https://docs.github.com/en/rest/repos/tags
+impl RepositoryClient<'_> {
+    /// Get a repository. Its merge settings are only included when authenticated as a user with
+    /// admin access to it
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/repos/repos#get-a-repository
+    pub async fn get(&self, owner: &str, repo: &str) -> Result<Response<Repository>> {
+        let url = format!("repos/{}/{}", owner, repo);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Update the settings of a repository, e.g. which merge methods are allowed
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/repos/repos#update-a-repository
+    pub async fn edit(
+        &self,
+        owner: &str,
+        repo: &str,
+        request: &EditRepositoryRequest<'_>,
+    ) -> Result<Response<Repository>> {
+        let url = format!("repos/{}/{}", owner, repo);
+        let response = self.inner.patch(&url).json(request).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// List the tag protections of a repository
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/repos/tags#list-tag-protection-states-for-a-repository
+    pub async fn list_tag_protections(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Response<Vec<TagProtection>>> {
+        let url = format!("repos/{}/{}/tags/protection", owner, repo);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Protect the tags matching `pattern`
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/repos/tags#create-a-tag-protection-state-for-a-repository
+    pub async fn create_tag_protection(
+        &self,
+        owner: &str,
+        repo: &str,
+        pattern: &str,
+    ) -> Result<Response<TagProtection>> {
+        let url = format!("repos/{}/{}/tags/protection", owner, repo);
+        let request = CreateTagProtectionRequest { pattern };
+        let response = self.inner.post(&url).json(&request).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Delete a tag protection
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/repos/tags#delete-a-tag-protection-state-for-a-repository
+    pub async fn delete_tag_protection(
+        &self,
+        owner: &str,
+        repo: &str,
+        tag_protection_id: u64,
+    ) -> Result<Response<()>> {
+        let url = format!(
+            "repos/{}/{}/tags/protection/{}",
+            owner, repo, tag_protection_id
+        );
+        let response = self.inner.delete(&url).send().await?;
+
+        self.inner.empty(response).await
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    client::{paginate, Client, PaginationOptions, Response, Result},
+    Team, User,
+};
+use futures::stream::Stream;
+
+/// `TeamsClient` handles communication with the teams related methods of the GitHub API.
+///
+/// GitHub API docs: https://docs.github.com/en/rest/teams
+pub struct TeamsClient<'a> {
+    inner: &'a Client,
+}
+
+impl<'a> TeamsClient<'a> {
+    pub(super) fn new(client: &'a Client) -> Self {
+        Self { inner: client }
+    }
+
+    /// Get a team of `org` by its slug
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/teams/teams#get-a-team-by-name
+    pub async fn get_by_slug(&self, org: &str, team_slug: &str) -> Result<Response<Team>> {
+        let url = format!("orgs/{}/teams/{}", org, team_slug);
+        let response = self.inner.get(&url).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// List the members of a team, including those of its child teams
+    ///
+    /// GitHub API docs: https://docs.github.com/en/rest/teams/members#list-team-members
+    pub async fn list_members(
+        &self,
+        org: &str,
+        team_slug: &str,
+        options: Option<PaginationOptions>,
+    ) -> Result<Response<Vec<User>>> {
+        let url = format!("orgs/{}/teams/{}/members", org, team_slug);
+        let response = self.inner.get(&url).query(&options).send().await?;
+
+        self.inner.json(response).await
+    }
+
+    /// Stream every member of a team, fetching further pages as needed
+    pub fn members<'b>(
+        &'b self,
+        org: &'b str,
+        team_slug: &'b str,
+    ) -> impl Stream<Item = Result<User>> + 'b {
+        paginate(Some(100), move |options| {
+            self.list_members(org, team_slug, Some(options))
+        })
+    }
+}
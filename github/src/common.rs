@@ -1,7 +1,12 @@
 use serde::{de, ser, Deserialize, Serialize};
-use std::fmt;
+use std::{
+    convert::TryFrom,
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct NodeId(String);
 
 impl NodeId {
@@ -10,7 +15,7 @@ impl NodeId {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub struct Oid(String);
 
 impl Oid {
@@ -26,16 +31,120 @@ impl fmt::Display for Oid {
     }
 }
 
-#[derive(Clone, Debug)]
+/// A point in time reported by Github, in UTC
+///
+/// Github reports times in several formats depending on the API and payload: RFC 3339 strings,
+/// with or without fractional seconds and with either a `Z` or a numeric offset, strings of the
+/// form `2020-08-28 09:19:55 UTC`, and seconds since the unix epoch. All of them are accepted when
+/// deserializing. Times are serialized as RFC 3339 strings, or as seconds since the epoch for
+/// formats which aren't human readable, and always deserialize back to the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DateTime(chrono::DateTime<chrono::Utc>);
 
+impl DateTime {
+    pub fn now() -> Self {
+        DateTime(chrono::Utc::now())
+    }
+
+    /// The time `secs` seconds after the unix epoch, if representable
+    pub fn from_timestamp(secs: i64) -> Option<Self> {
+        use chrono::{offset::LocalResult, TimeZone};
+
+        match chrono::Utc.timestamp_opt(secs, 0) {
+            LocalResult::Single(datetime) => Some(DateTime(datetime)),
+            _ => None,
+        }
+    }
+
+    /// Seconds since the unix epoch
+    pub fn timestamp(&self) -> i64 {
+        self.0.timestamp()
+    }
+
+    /// Time from `earlier` until this time, or `None` if `earlier` is later than this time
+    pub fn duration_since(&self, earlier: &DateTime) -> Option<Duration> {
+        (self.0 - earlier.0).to_std().ok()
+    }
+
+    /// Time since this time, which is zero if it's in the future
+    pub fn elapsed(&self) -> Duration {
+        Self::now().duration_since(self).unwrap_or_default()
+    }
+
+    /// The time `duration` after this time, if representable
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let duration = chrono::Duration::from_std(duration).ok()?;
+        self.0.checked_add_signed(duration).map(DateTime)
+    }
+
+    /// The time `duration` before this time, if representable
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        let duration = chrono::Duration::from_std(duration).ok()?;
+        self.0.checked_sub_signed(duration).map(DateTime)
+    }
+}
+
+impl From<chrono::DateTime<chrono::Utc>> for DateTime {
+    fn from(datetime: chrono::DateTime<chrono::Utc>) -> Self {
+        DateTime(datetime)
+    }
+}
+
+impl From<DateTime> for chrono::DateTime<chrono::Utc> {
+    fn from(datetime: DateTime) -> Self {
+        datetime.0
+    }
+}
+
+impl From<SystemTime> for DateTime {
+    fn from(time: SystemTime) -> Self {
+        DateTime(time.into())
+    }
+}
+
+impl From<DateTime> for SystemTime {
+    fn from(datetime: DateTime) -> Self {
+        datetime.0.into()
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = chrono::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Formats other than RFC 3339 which Github has been seen to use
+        const FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S %z", "%Y/%m/%d %H:%M:%S %z"];
+
+        let rfc3339_error = match chrono::DateTime::parse_from_rfc3339(s) {
+            Ok(datetime) => return Ok(DateTime(datetime.into())),
+            Err(e) => e,
+        };
+        if let Some(s) = s.strip_suffix(" UTC") {
+            if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
+                return Ok(DateTime(datetime.and_utc()));
+            }
+        }
+        FORMATS
+            .iter()
+            .find_map(|format| chrono::DateTime::parse_from_str(s, format).ok())
+            .map(|datetime| DateTime(datetime.into()))
+            .ok_or(rfc3339_error)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+    }
+}
+
 impl Serialize for DateTime {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: ser::Serializer,
     {
         if serializer.is_human_readable() {
-            serializer.serialize_str(&self.0.to_rfc3339())
+            serializer.collect_str(self)
         } else {
             serializer.serialize_i64(self.0.timestamp())
         }
@@ -60,35 +169,105 @@ impl<'de> Deserialize<'de> for DateTime {
             where
                 E: de::Error,
             {
-                Ok(DateTime(
-                    v.parse().map_err(|e| E::custom(format!("{}", e)))?,
-                ))
+                v.parse()
+                    .map_err(|e| E::custom(format!("'{}' is not a legal date time: {}", v, e)))
             }
 
             fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
             where
                 E: de::Error,
             {
-                use chrono::{offset::LocalResult, TimeZone};
-
-                match chrono::Utc.timestamp_opt(v, 0) {
-                    LocalResult::Single(datetime) => Ok(DateTime(datetime)),
-                    _ => Err(E::custom(format!("'{}' is not a legal timestamp", v))),
-                }
+                DateTime::from_timestamp(v)
+                    .ok_or_else(|| E::custom(format!("'{}' is not a legal timestamp", v)))
             }
 
             fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
             where
                 E: de::Error,
             {
-                self.visit_i64(v as i64)
+                let v = i64::try_from(v)
+                    .map_err(|_| E::custom(format!("'{}' is not a legal timestamp", v)))?;
+                self.visit_i64(v)
             }
         }
 
         if deserializer.is_human_readable() {
             deserializer.deserialize_any(DateTimeVisitor)
         } else {
-            deserializer.deserialize_u64(DateTimeVisitor)
+            deserializer.deserialize_i64(DateTimeVisitor)
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::DateTime;
+    use std::time::Duration;
+
+    #[test]
+    fn date_time_formats() {
+        let expected = DateTime::from_timestamp(1_598_606_395).unwrap();
+        for json in &[
+            r#""2020-08-28T09:19:55Z""#,
+            r#""2020-08-28T09:19:55.000Z""#,
+            r#""2020-08-28T11:19:55+02:00""#,
+            r#""2020-08-28T02:19:55-07:00""#,
+            r#""2020-08-28 09:19:55 UTC""#,
+            r#""2020/08/28 02:19:55 -0700""#,
+            "1598606395",
+        ] {
+            let datetime: DateTime = serde_json::from_str(json).unwrap();
+            assert_eq!(datetime, expected, "parsing {}", json);
+        }
+
+        assert!(serde_json::from_str::<DateTime>(r#""yesterday""#).is_err());
+        assert!(serde_json::from_str::<DateTime>("18446744073709551615").is_err());
+    }
+
+    #[test]
+    fn date_time_round_trip() {
+        // Cover a spread of times, including sub-second ones, with a simple deterministic
+        // generator rather than a fuzzer
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..1000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+
+            let secs = (seed % 8_000_000_000) as i64 - 1_000_000_000;
+            let nanos = if seed & 1 == 0 {
+                0
+            } else {
+                (seed >> 32) % 1_000_000_000
+            };
+            let datetime = DateTime::from_timestamp(secs)
+                .unwrap()
+                .checked_add(Duration::from_nanos(nanos))
+                .unwrap();
+
+            let json = serde_json::to_string(&datetime).unwrap();
+            assert_eq!(
+                serde_json::from_str::<DateTime>(&json).unwrap(),
+                datetime,
+                "round-tripping {}",
+                json
+            );
+            assert_eq!(datetime.to_string().parse::<DateTime>().unwrap(), datetime);
+        }
+    }
+
+    #[test]
+    fn date_time_arithmetic() {
+        let earlier = DateTime::from_timestamp(1_598_606_395).unwrap();
+        let later = earlier.checked_add(Duration::from_secs(90)).unwrap();
+
+        assert!(earlier < later);
+        assert_eq!(
+            later.duration_since(&earlier),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(earlier.duration_since(&later), None);
+        assert_eq!(later.checked_sub(Duration::from_secs(90)), Some(earlier));
+        assert!(earlier.elapsed() > Duration::from_secs(60 * 60 * 24 * 365));
+    }
+}
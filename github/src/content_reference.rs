@@ -0,0 +1,14 @@
+use super::NodeId;
+use serde::{Deserialize, Serialize};
+
+/// A link to a domain registered by a GitHub App, posted in an issue or pull request, which the
+/// app can attach content to
+///
+/// GitHub API docs: https://developer.github.com/v3/apps/installations/#create-a-content-attachment
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ContentReference {
+    pub id: u64,
+    pub node_id: NodeId,
+    /// The URL, or part of it, which was referenced
+    pub reference: String,
+}
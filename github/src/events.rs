@@ -1,12 +1,13 @@
 use super::{
-    CheckRun, CheckSuite, Comment, Commit, DateTime, Hook, Issue, Key, Label, Milestone, Oid,
-    Project, ProjectCard, ProjectColumn, PullRequest, Pusher, Repository, Review, ReviewComment,
-    Team, User, Workflow, WorkflowRun,
+    CheckRun, CheckSuite, Comment, Commit, ContentReference, DateTime, Hook, Issue, Key, Label,
+    MarketplacePurchase, Milestone, Oid, Organization, Package, Project, ProjectCard,
+    ProjectColumn, PullRequest, Pusher, Repository, Review, ReviewComment, Team, User, Workflow,
+    WorkflowRun,
 };
 use serde::{de, Deserialize, Serialize};
 use std::{io, str::FromStr};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum EventType {
     CheckRun,
     CheckSuite,
@@ -67,6 +68,73 @@ pub enum EventType {
     Unknown,
 }
 
+impl EventType {
+    /// The name of the event as used in webhook configuration and the `X-GitHub-Event` header
+    pub fn as_str(&self) -> &'static str {
+        use EventType::*;
+
+        match self {
+            CheckRun => "check_run",
+            CheckSuite => "check_suite",
+            CommitComment => "commit_comment",
+            ContentReference => "content_reference",
+            Create => "create",
+            Delete => "delete",
+            DeployKey => "deploy_key",
+            Deployment => "deployment",
+            DeploymentStatus => "deployment_status",
+            Fork => "fork",
+            GithubAppAuthorization => "github_app_authorization",
+            Gollum => "gollum",
+            Installation => "installation",
+            InstallationRepositories => "installation_repositories",
+            IssueComment => "issue_comment",
+            Issues => "issues",
+            Label => "label",
+            MarketplacePurchase => "marketplace_purchase",
+            Member => "member",
+            Membership => "membership",
+            Meta => "meta",
+            Milestone => "milestone",
+            Organization => "organization",
+            OrgBlock => "org_block",
+            Package => "package",
+            PageBuild => "page_build",
+            Ping => "ping",
+            ProjectCard => "project_card",
+            ProjectColumn => "project_column",
+            Project => "project",
+            Public => "public",
+            PullRequest => "pull_request",
+            PullRequestReview => "pull_request_review",
+            PullRequestReviewComment => "pull_request_review_comment",
+            Push => "push",
+            RegistryPackage => "registry_package",
+            Release => "release",
+            RepositoryDispatch => "repository_dispatch",
+            Repository => "repository",
+            RepositoryImport => "repository_import",
+            RepositoryVulnerabilityAlert => "repository_vulnerability_alert",
+            SecurityAdvisory => "security_advisory",
+            Sponsorship => "sponsorship",
+            Star => "star",
+            Status => "status",
+            Team => "team",
+            TeamAdd => "team_add",
+            Watch => "watch",
+            WorkflowRun => "workflow_run",
+            Wildcard => "*",
+            Unknown => "unknown",
+        }
+    }
+}
+
+impl std::fmt::Display for EventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl FromStr for EventType {
     type Err = std::convert::Infallible;
 
@@ -131,6 +199,15 @@ impl FromStr for EventType {
     }
 }
 
+impl Serialize for EventType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 impl<'de> Deserialize<'de> for EventType {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -179,6 +256,7 @@ pub enum Event {
     PullRequestReview(PullRequestReviewEvent),
     PullRequestReviewComment(PullRequestReviewCommentEvent),
     Push(PushEvent),
+    RegistryPackage(RegistryPackageEvent),
     Release(ReleaseEvent),
     Repository(RepositoryEvent),
     RepositoryDispatch(RepositoryDispatchEvent),
@@ -240,7 +318,7 @@ impl Event {
                 Event::PullRequestReviewComment(serde_json::from_slice(json)?)
             }
             EventType::Push => Event::Push(serde_json::from_slice(json)?),
-            EventType::RegistryPackage => unimplemented!(), // This is a deprecated type
+            EventType::RegistryPackage => Event::RegistryPackage(serde_json::from_slice(json)?),
             EventType::Release => Event::Release(serde_json::from_slice(json)?),
             EventType::Repository => Event::Repository(serde_json::from_slice(json)?),
             EventType::RepositoryDispatch => {
@@ -261,9 +339,7 @@ impl Event {
             // TODO have an error type if we try to De a wildcard event payload since they don't
             // exist
             EventType::Wildcard => unimplemented!(),
-            EventType::Unknown => {
-                return Err(io::Error::new(io::ErrorKind::Other, "Unknown EventType"))
-            }
+            EventType::Unknown => return Err(io::Error::other("Unknown EventType")),
         };
 
         Ok(event)
@@ -306,6 +382,7 @@ impl Event {
             Event::PullRequestReview(_) => EventType::PullRequestReview,
             Event::PullRequestReviewComment(_) => EventType::PullRequestReviewComment,
             Event::Push(_) => EventType::Push,
+            Event::RegistryPackage(_) => EventType::RegistryPackage,
             Event::Release(_) => EventType::Release,
             Event::Repository(_) => EventType::Repository,
             Event::RepositoryDispatch(_) => EventType::RepositoryDispatch,
@@ -339,7 +416,6 @@ impl Event {
             | Event::Issues(IssueEvent { repository, .. })
             | Event::Label(LabelEvent { repository, .. })
             | Event::Member(MemberEvent { repository, .. })
-            | Event::Membership(MembershipEvent { repository, .. })
             | Event::Meta(MetaEvent { repository, .. })
             | Event::Milestone(MilestoneEvent { repository, .. })
             | Event::Package(PackageEvent { repository, .. })
@@ -355,6 +431,7 @@ impl Event {
                 repository, ..
             })
             | Event::Push(PushEvent { repository, .. })
+            | Event::RegistryPackage(RegistryPackageEvent { repository, .. })
             | Event::Release(ReleaseEvent { repository, .. })
             | Event::Repository(RepositoryEvent { repository, .. })
             | Event::RepositoryDispatch(RepositoryDispatchEvent { repository, .. })
@@ -367,11 +444,12 @@ impl Event {
             | Event::Status(StatusEvent { repository, .. })
             | Event::TeamAdd(TeamAddEvent { repository, .. })
             | Event::Watch(WatchEvent { repository, .. })
-            | Event::WorkflowRun(WorkflowRunEvent { repository, .. }) => Some(&repository),
+            | Event::WorkflowRun(WorkflowRunEvent { repository, .. }) => Some(repository),
 
             Event::Installation(_)
             | Event::InstallationRepositories(_)
             | Event::MarketplacePurchase(_)
+            | Event::Membership(_)
             | Event::Organization(_)
             | Event::OrgBlock(_)
             | Event::GithubAppAuthorization(_)
@@ -380,10 +458,21 @@ impl Event {
             | Event::Team(_) => None,
         }
     }
+
+    /// The organization of events which are only delivered to organization webhooks
+    pub fn organization(&self) -> Option<&Organization> {
+        match &self {
+            Event::Membership(MembershipEvent { organization, .. })
+            | Event::Organization(OrganizationEvent { organization, .. })
+            | Event::OrgBlock(OrgBlockEvent { organization, .. })
+            | Event::Team(TeamEvent { organization, .. }) => Some(organization),
+            _ => None,
+        }
+    }
 }
 
 /// The Action performed by a `CheckRunEvent`
-#[derive(Clone, Copy, Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CheckRunEventAction {
     Created,
@@ -392,15 +481,21 @@ pub enum CheckRunEventAction {
     RequestedAction,
 }
 
+impl CheckRunEventAction {
+    pub fn is_rerequested(&self) -> bool {
+        matches!(self, CheckRunEventAction::Rerequested)
+    }
+}
+
 /// `RequestedAction` is included in a `CheckRunEvent` when a user has invoked an action,
 /// i.e. when the `CheckRunEventAction` type is `RequestedAction`.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RequestedAction {
     pub identifier: String,
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#checkrunevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CheckRunEvent {
     pub action: CheckRunEventAction,
     pub check_run: CheckRun,
@@ -412,7 +507,7 @@ pub struct CheckRunEvent {
 }
 
 /// The Action performed by a `CheckSuiteEvent`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CheckSuiteEventAction {
     Completed,
@@ -420,8 +515,18 @@ pub enum CheckSuiteEventAction {
     Rerequested,
 }
 
+impl CheckSuiteEventAction {
+    pub fn is_requested(&self) -> bool {
+        matches!(self, CheckSuiteEventAction::Requested)
+    }
+
+    pub fn is_rerequested(&self) -> bool {
+        matches!(self, CheckSuiteEventAction::Rerequested)
+    }
+}
+
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#checksuiteevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CheckSuiteEvent {
     pub action: CheckSuiteEventAction,
     pub check_suite: CheckSuite,
@@ -432,7 +537,7 @@ pub struct CheckSuiteEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#commitcommentevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CommitCommentEvent {
     pub comment: Comment,
 
@@ -442,10 +547,10 @@ pub struct CommitCommentEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#contentreferenceevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ContentReferenceEvent {
     pub action: String,
-    // pub content_reference: ContentReference, //TODO add type
+    pub content_reference: ContentReference,
 
     // Populated by Webhook events
     pub repository: Repository,
@@ -453,7 +558,7 @@ pub struct ContentReferenceEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#createevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CreateEvent {
     /// The object that was created. Possible values are: "repository", "branch", "tag"
     pub ref_type: String,
@@ -469,7 +574,7 @@ pub struct CreateEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#deleteevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeleteEvent {
     /// The object that was created. Possible values are: "branch", "tag"
     pub ref_type: String,
@@ -483,7 +588,7 @@ pub struct DeleteEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#deploykeyevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeployKeyEvent {
     /// The action performed. Possible values are: "created" or "deleted"
     pub action: String,
@@ -495,7 +600,7 @@ pub struct DeployKeyEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#deploymentevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeploymentEvent {
     /// The action performed. Possible values are: "created"
     pub action: String,
@@ -507,7 +612,7 @@ pub struct DeploymentEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#deploymentstatusevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeploymentStatusEvent {
     /// The action performed. Possible values are: "created"
     pub action: String,
@@ -520,7 +625,7 @@ pub struct DeploymentStatusEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#forkevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ForkEvent {
     // The newly created fork
     pub forkee: Repository,
@@ -533,7 +638,7 @@ pub struct ForkEvent {
 /// An event triggered when a user's authorization for a GitHub Application is revoked
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#githubappauthorizationevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct GithubAppAuthorizationEvent {
     /// The action performed. Possible values are: "revoked"
     pub action: String,
@@ -543,18 +648,18 @@ pub struct GithubAppAuthorizationEvent {
 }
 
 // Page represents a single Wiki page.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Page {
-    page_name: String,
-    title: String,
-    summary: Option<String>,
-    action: String,
-    sha: Oid,
-    html_url: String,
+    pub page_name: String,
+    pub title: String,
+    pub summary: Option<String>,
+    pub action: String,
+    pub sha: Oid,
+    pub html_url: String,
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#gollumevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct GollumEvent {
     pub pages: Vec<Page>,
 
@@ -564,7 +669,7 @@ pub struct GollumEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#installationevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct InstallationEvent {
     /// The action performed. Possible values are: "created", "deleted", "new_permissions_accepted"
     pub action: String,
@@ -573,7 +678,7 @@ pub struct InstallationEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#installationrepositoriesevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct InstallationRepositoriesEvent {
     /// The action performed. Possible values are: "added", "removed"
     pub action: String,
@@ -586,19 +691,19 @@ pub struct InstallationRepositoriesEvent {
     pub sender: User,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OldContents {
     pub from: String,
 }
 
 /// The representation of an edit made on an issue, pull request, or comment
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct EditChange {
     pub title: Option<OldContents>,
     pub body: Option<OldContents>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum IssueCommentEventAction {
     Created,
@@ -608,16 +713,16 @@ pub enum IssueCommentEventAction {
 
 impl IssueCommentEventAction {
     pub fn is_created(&self) -> bool {
-        if let IssueCommentEventAction::Created = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, IssueCommentEventAction::Created)
+    }
+
+    pub fn is_edited(&self) -> bool {
+        matches!(self, IssueCommentEventAction::Edited)
     }
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#issuecommentevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct IssueCommentEvent {
     pub action: IssueCommentEventAction,
     pub changes: Option<EditChange>, // If action is Edited
@@ -629,7 +734,7 @@ pub struct IssueCommentEvent {
     pub sender: User,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum IssueEventAction {
     Opened,
@@ -651,7 +756,7 @@ pub enum IssueEventAction {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#issuesevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct IssueEvent {
     pub action: IssueEventAction,
     pub changes: Option<EditChange>, // If action is Edited
@@ -665,14 +770,14 @@ pub struct IssueEvent {
 }
 
 /// The representation of an edit made on a label
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct LabelChange {
     pub name: Option<OldContents>,
     pub color: Option<OldContents>,
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#labelevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct LabelEvent {
     /// The action that was performed. Can be created, edited, or deleted
     pub action: String,
@@ -685,11 +790,13 @@ pub struct LabelEvent {
 }
 
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#marketplacepurchaseevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MarketplacePurchaseEvent {
     /// The action performed. Can be purchased, cancelled, pending_change, pending_change_cancelled, or changed
     pub action: String,
-    //pub marketplace_purchase: MarketplacePurchase, //TODO add type
+    pub marketplace_purchase: MarketplacePurchase,
+    /// The plan before the change, if action is changed or pending_change
+    pub previous_marketplace_purchase: Option<MarketplacePurchase>,
     pub effective_date: DateTime,
 
     // Populated by Webhook events
@@ -700,7 +807,7 @@ pub struct MarketplacePurchaseEvent {
 /// The Webhook event name is "member".
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#memberevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MemberEvent {
     /// The action that was performed. Can be "added", "removed", or "edited"
     pub action: String,
@@ -715,8 +822,11 @@ pub struct MemberEvent {
 /// Triggered when a user is added or removed from a team.
 /// The Webhook event name is "membership".
 ///
+/// Membership events are only delivered to organization webhooks, so they carry the
+/// `organization` they happened in rather than a `repository`.
+///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#membershipevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MembershipEvent {
     /// The action that was performed. Can be "added", "removed"
     pub action: String,
@@ -725,7 +835,7 @@ pub struct MembershipEvent {
     pub team: Team,
 
     // Populated by Webhook events
-    pub repository: Repository,
+    pub organization: Organization,
     pub sender: User,
 }
 
@@ -734,7 +844,7 @@ pub struct MembershipEvent {
 /// Therefore, it must be selected for each hook that you'd like to receive meta events for.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#metaevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MetaEvent {
     /// The action that was performed. Can be "deleted"
     pub action: String,
@@ -749,7 +859,7 @@ pub struct MetaEvent {
 /// MilestoneEvent is triggered when a milestone is created, closed, opened, edited, or deleted.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#milestoneevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MilestoneEvent {
     /// Action is the action that was performed. Possible values are:
     /// "created", "closed", "opened", "edited", "deleted"
@@ -767,14 +877,14 @@ pub struct MilestoneEvent {
 /// Events of this type are not visible in timelines. These events are only used to trigger organization hooks.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#organizationevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OrganizationEvent {
     // Action is the action that was performed.
     // Possible values are: "deleted", "renamed", "member_added", "member_removed", or "member_invited".
     pub action: String,
     //pub invitation: //TODO add type
     //pub membership: //TODO add type
-    //pub organization: //TODO add type
+    pub organization: Organization,
     pub sender: User,
 }
 
@@ -782,34 +892,50 @@ pub struct OrganizationEvent {
 /// The Webhook event name is "org_block".
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#orgblockevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OrgBlockEvent {
     // Action is the action that was performed.
     // Possible values are: "blocked" or "unblocked"
     pub action: String,
     pub blocked_user: User,
-    //pub organization: Organization //TODO add type
+    pub organization: Organization,
     pub sender: User,
 }
 
 /// Triggered when a package version is published or updated in GitHub Packages.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#packageevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PackageEvent {
     // Action is the action that was performed.
     // Possible values are: "published" or "updated"
     pub action: String,
-    //pub package: Package, //TODO add type
+    pub package: Package,
+    pub repository: Repository,
+    /// Present when the package belongs to an organization
+    pub organization: Option<Organization>,
+    pub sender: User,
+}
+
+/// Triggered when a package is published or updated in GitHub Packages. Deprecated in favor of
+/// `PackageEvent`, which has the same payload
+///
+/// GitHub API docs: https://developer.github.com/v3/activity/events/types/#registrypackageevent
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegistryPackageEvent {
+    // Action is the action that was performed.
+    // Possible values are: "published" or "updated"
+    pub action: String,
+    pub registry_package: Package,
     pub repository: Repository,
-    //pub organization: Organization //TODO add type
+    pub organization: Option<Organization>,
     pub sender: User,
 }
 
 /// Represents an attempted build of a GitHub Pages site, whether successful or not
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#pagebuildevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PageBuildEvent {
     //pub build: PagesBuild, //TODO add type
     pub id: u64,
@@ -822,7 +948,7 @@ pub struct PageBuildEvent {
 /// PingEvent is triggered when a Webhook is added to GitHub.
 ///
 /// GitHub API docs: https://developer.github.com/webhooks/#ping-event
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PingEvent {
     pub zen: String,
     pub hook_id: u64,
@@ -834,7 +960,7 @@ pub struct PingEvent {
 /// Triggered when a project card is created, edited, moved, converted to an issue, or deleted
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#projectcardevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ProjectCardEvent {
     /// The action performed on the project card. Can be created, edited, moved, converted, or deleted
     pub action: String,
@@ -852,7 +978,7 @@ pub struct ProjectCardEvent {
 /// Triggered when a project column is created, updated, moved, or deleted
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#projectcolumnevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ProjectColumnEvent {
     /// The action that was performed on the project column. Can be one of created, edited, moved or deleted
     pub action: String,
@@ -870,7 +996,7 @@ pub struct ProjectColumnEvent {
 /// Triggered when a project is created, updated, closed, reopened, or deleted
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#projectevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ProjectEvent {
     /// The action that was performed on the project. Can be one of created, edited, closed, reopened, or deleted
     pub action: String,
@@ -886,13 +1012,13 @@ pub struct ProjectEvent {
 /// "Without a doubt: the best GitHub event."
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#publicevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PublicEvent {
     pub repository: Repository,
     pub sender: User,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 /// The action that was performed. Can be one of assigned, unassigned, review_requested,
 /// review_request_removed, labeled, unlabeled, opened, edited, closed, ready_for_review, locked,
@@ -923,7 +1049,7 @@ pub enum PullRequestEventAction {
 /// is requested or removed.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#pullrequestevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PullRequestEvent {
     pub action: PullRequestEventAction,
     pub number: u64,
@@ -945,7 +1071,7 @@ pub struct PullRequestEvent {
     pub sender: User,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PullRequestReviewEventAction {
     Submitted,
@@ -955,11 +1081,7 @@ pub enum PullRequestReviewEventAction {
 
 impl PullRequestReviewEventAction {
     pub fn is_submitted(&self) -> bool {
-        if let PullRequestReviewEventAction::Submitted = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, PullRequestReviewEventAction::Submitted)
     }
 }
 
@@ -967,7 +1089,7 @@ impl PullRequestReviewEventAction {
 /// or the review is dismissed
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#pullrequestreviewevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PullRequestReviewEvent {
     pub action: PullRequestReviewEventAction,
     pub review: Review,
@@ -979,7 +1101,7 @@ pub struct PullRequestReviewEvent {
     pub sender: User,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PullRequestReviewCommentEventAction {
     Created,
@@ -989,11 +1111,11 @@ pub enum PullRequestReviewCommentEventAction {
 
 impl PullRequestReviewCommentEventAction {
     pub fn is_created(&self) -> bool {
-        if let PullRequestReviewCommentEventAction::Created = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, PullRequestReviewCommentEventAction::Created)
+    }
+
+    pub fn is_edited(&self) -> bool {
+        matches!(self, PullRequestReviewCommentEventAction::Edited)
     }
 }
 
@@ -1001,12 +1123,12 @@ impl PullRequestReviewCommentEventAction {
 /// the Files Changed tab)
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#pullrequestreviewcommentevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PullRequestReviewCommentEvent {
     pub action: PullRequestReviewCommentEventAction,
     pub comment: ReviewComment,
     pub pull_request: PullRequest,
-    //pub changes: // TODO add type
+    pub changes: Option<EditChange>, // If action is Edited
 
     // Populated by Webhook events
     pub repository: Repository,
@@ -1017,7 +1139,7 @@ pub struct PullRequestReviewCommentEvent {
 /// trigger webhook push events.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#pushevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PushEvent {
     #[serde(rename = "ref")]
     pub git_ref: String,
@@ -1037,10 +1159,17 @@ pub struct PushEvent {
     pub sender: User,
 }
 
+impl PushEvent {
+    /// The name of the branch that was pushed to, or `None` if a tag was pushed
+    pub fn branch(&self) -> Option<&str> {
+        self.git_ref.strip_prefix("refs/heads/")
+    }
+}
+
 /// Triggered when a release is published, unpublished, created, edited, deleted, or prereleased
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#releaseevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ReleaseEvent {
     /// The action that was performed. Currently, can be published, unpublished, created, edited,
     /// deleted, or prereleased
@@ -1057,7 +1186,7 @@ pub struct ReleaseEvent {
 /// endpoint.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#repositorydispatchevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RepositoryDispatchEvent {
     pub action: String,
     pub branch: String,
@@ -1075,11 +1204,12 @@ pub struct RepositoryDispatchEvent {
 /// deleted
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#repositoryevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RepositoryEvent {
     /// The action that was performed. This can be one of created, deleted (organization hooks
     /// only), archived, unarchived, edited, renamed, transferred, publicized, or privatized
     pub action: String,
+    pub changes: Option<RepositoryChanges>, // If action is renamed or transferred
 
     // Populated by Webhook events
     pub repository: Repository,
@@ -1088,12 +1218,72 @@ pub struct RepositoryEvent {
     //pub installation: Installation, //TODO add type
 }
 
+impl RepositoryEvent {
+    pub fn is_renamed(&self) -> bool {
+        self.action == "renamed"
+    }
+
+    pub fn is_transferred(&self) -> bool {
+        self.action == "transferred"
+    }
+
+    /// The repository's owner before it was transferred, or its current owner otherwise
+    pub fn previous_owner(&self) -> &str {
+        self.changes
+            .as_ref()
+            .and_then(|changes| changes.owner.as_ref())
+            .and_then(|owner| {
+                owner
+                    .from
+                    .user
+                    .as_ref()
+                    .map(|user| &user.login)
+                    .or_else(|| owner.from.organization.as_ref().map(|org| &org.login))
+            })
+            .unwrap_or(&self.repository.owner.login)
+    }
+
+    /// The repository's name before it was renamed, or its current name otherwise
+    pub fn previous_name(&self) -> &str {
+        self.changes
+            .as_ref()
+            .and_then(|changes| changes.repository.as_ref())
+            .and_then(|repository| repository.name.as_ref())
+            .map(|name| name.from.as_str())
+            .unwrap_or(&self.repository.name)
+    }
+}
+
+/// The representation of a repository being renamed or transferred
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RepositoryChanges {
+    pub repository: Option<RepositoryNameChange>,
+    pub owner: Option<RepositoryOwnerChange>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RepositoryNameChange {
+    pub name: Option<OldContents>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RepositoryOwnerChange {
+    pub from: PreviousOwner,
+}
+
+/// The previous owner of a transferred repository, either a user or an organization
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PreviousOwner {
+    pub user: Option<User>,
+    pub organization: Option<Organization>,
+}
+
 /// Triggered when a successful, cancelled, or failed repository import finishes for a GitHub
 /// organization or a personal repository. To receive this event for a personal repository, you
 /// must create an empty repository prior to the import.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#repositoryimportevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RepositoryImportEvent {
     /// The final state of the import. This can be one of success, cancelled, or failure
     pub status: String,
@@ -1105,7 +1295,7 @@ pub struct RepositoryImportEvent {
     //pub installation: Installation, //TODO add type
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Alert {
     pub id: u64,
     pub affected_range: String,
@@ -1118,7 +1308,7 @@ pub struct Alert {
 /// Triggered when a security alert is created, dismissed, or resolved.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#repositoryvulnerabilityalertevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RepositoryVulnerabilityAlertEvent {
     /// The action that was performed. This can be one of create, dismiss, or resolve.
     pub action: String,
@@ -1135,7 +1325,7 @@ pub struct RepositoryVulnerabilityAlertEvent {
 /// provides information about security-related vulnerabilities in software on GitHub.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#securityadvisoryevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SecurityAdvisoryEvent {
     /// The action that was performed. The action can be one of published, updated, or performed
     /// for all new events
@@ -1147,7 +1337,7 @@ pub struct SecurityAdvisoryEvent {
 /// pending_cancellation, or pending_tier_change
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#sponsorshipevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SponsorshipEvent {
     /// The action that was performed. This can be one of created, cancelled, edited, tier_changed,
     /// pending_cancellation, or pending_tier_change
@@ -1159,7 +1349,7 @@ pub struct SponsorshipEvent {
 /// Triggered when a star is added or removed from a repository.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#starevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct StarEvent {
     /// The action performed. Can be created or deleted.
     pub action: String,
@@ -1172,7 +1362,7 @@ pub struct StarEvent {
     //pub installation: Installation, //TODO add type
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum StatusEventState {
     Pending,
@@ -1184,7 +1374,7 @@ pub enum StatusEventState {
 /// Triggered when the status of a Git commit changes
 ///
 /// GitHub: API docs: https://developer.github.com/v3/activity/events/types/#statusevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct StatusEvent {
     pub sha: Oid,
     pub state: StatusEventState,
@@ -1201,7 +1391,7 @@ pub struct StatusEvent {
 /// removed_from_repository
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#teamevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TeamEvent {
     /// The action that was performed. Can be one of created, deleted, edited, added_to_repository,
     /// or removed_from_repository.
@@ -1212,14 +1402,14 @@ pub struct TeamEvent {
 
     // Populated by Webhook events
     pub sender: User,
-    //pub organization: Organization, //TODO add type
+    pub organization: Organization,
     //pub installation: Installation, //TODO add type
 }
 
 /// Triggered when a repository is added to a team
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#teamaddevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TeamAddEvent {
     pub team: Team,
     pub repository: Repository,
@@ -1247,7 +1437,7 @@ pub struct TeamAddEvent {
 /// Triggered when someone stars a repository. This event is not related to watching a repository.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#watchevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct WatchEvent {
     /// The action that was performed. Currently, can only be started
     pub action: String,
@@ -1259,7 +1449,7 @@ pub struct WatchEvent {
     //pub installation: Installation, //TODO add type
 }
 
-#[derive(Clone, Copy, Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum WorkflowRunAction {
     Requested,
@@ -1269,7 +1459,7 @@ pub enum WorkflowRunAction {
 /// Triggered when someone stars a repository. This event is not related to watching a repository.
 ///
 /// GitHub API docs: https://developer.github.com/v3/activity/events/types/#watchevent
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct WorkflowRunEvent {
     /// The action that was performed.
     pub action: WorkflowRunAction,
@@ -1285,14 +1475,75 @@ pub struct WorkflowRunEvent {
 #[cfg(test)]
 mod test {
     use super::{
-        CheckRunEvent, CheckSuiteEvent, IssueCommentEvent, IssueEvent,
-        PullRequestReviewCommentEvent, PullRequestReviewEvent, PushEvent, StatusEvent,
+        CheckRunEvent, CheckSuiteEvent, Event, EventType, IssueCommentEvent, IssueEvent,
+        PullRequestReviewCommentEvent, PullRequestReviewEvent, PushEvent, RepositoryEvent,
+        StatusEvent,
     };
+    use crate::test_fixtures::{
+        CheckRunEventBuilder, EventPayload, IssueCommentEventBuilder, PullRequestEventBuilder,
+        StatusEventBuilder,
+    };
+    use crate::Oid;
+
+    #[test]
+    fn event_type_round_trip() {
+        for s in &["pull_request", "check_run", "issue_comment", "*"] {
+            let event_type: EventType = s.parse().unwrap();
+            assert_eq!(event_type.as_str(), *s);
+            assert_eq!(
+                serde_json::to_string(&event_type).unwrap(),
+                format!("\"{}\"", s)
+            );
+        }
+    }
+
+    /// Serializing a payload and deserializing it again must give back the same payload
+    fn assert_round_trips<T: serde::de::DeserializeOwned + serde::Serialize>(json: &str) {
+        let payload: T = serde_json::from_str(json).unwrap();
+        let serialized = serde_json::to_value(&payload).unwrap();
+        let payload: T = serde_json::from_value(serialized.clone())
+            .unwrap_or_else(|e| panic!("{}: {}", std::any::type_name::<T>(), e));
+        assert_eq!(serde_json::to_value(&payload).unwrap(), serialized);
+    }
+
+    #[test]
+    fn events_round_trip() {
+        assert_round_trips::<PushEvent>(include_str!("../test-input/push-event.json"));
+        assert_round_trips::<IssueEvent>(include_str!("../test-input/issue-event.json"));
+        assert_round_trips::<CheckSuiteEvent>(include_str!("../test-input/check-suite-event.json"));
+        assert_round_trips::<PullRequestReviewEvent>(include_str!(
+            "../test-input/pull-request-review-event.json"
+        ));
+        assert_round_trips::<PullRequestReviewCommentEvent>(include_str!(
+            "../test-input/pull-request-review-comment-event.json"
+        ));
+        assert_round_trips::<super::PullRequestEvent>(include_str!(
+            "../test-input/pull-request-event.json"
+        ));
+        assert_round_trips::<Vec<super::IssueCommentEvent>>(include_str!(
+            "../test-input/issue-comment-event.json"
+        ));
+        assert_round_trips::<super::StatusEvent>(include_str!("../test-input/status-event.json"));
+        assert_round_trips::<super::CheckRunEvent>(include_str!(
+            "../test-input/check-run-event.json"
+        ));
+        assert_round_trips::<super::MarketplacePurchaseEvent>(include_str!(
+            "../test-input/marketplace-purchase-event.json"
+        ));
+        assert_round_trips::<super::PackageEvent>(include_str!("../test-input/package-event.json"));
+        assert_round_trips::<super::OrgBlockEvent>(include_str!(
+            "../test-input/org-block-event.json"
+        ));
+        assert_round_trips::<super::MembershipEvent>(include_str!(
+            "../test-input/membership-event.json"
+        ));
+    }
 
     #[test]
     fn push_event() {
         const PUSH_JSON: &str = include_str!("../test-input/push-event.json");
-        let _push: PushEvent = serde_json::from_str(PUSH_JSON).unwrap();
+        let push: PushEvent = serde_json::from_str(PUSH_JSON).unwrap();
+        assert_eq!(push.branch(), Some("master"));
     }
 
     #[test]
@@ -1301,6 +1552,18 @@ mod test {
         let _: Vec<IssueCommentEvent> = serde_json::from_str(JSON).unwrap();
     }
 
+    #[test]
+    fn issue_comment_event_builder() {
+        let event = IssueCommentEventBuilder::new("created").build_event();
+        assert!(matches!(event, Event::IssueComment(_)));
+    }
+
+    #[test]
+    fn pull_request_event_builder() {
+        let event = PullRequestEventBuilder::new("opened").build_event();
+        assert!(matches!(event, Event::PullRequest(_)));
+    }
+
     #[test]
     fn issue_event() {
         const JSON: &str = include_str!("../test-input/issue-event.json");
@@ -1313,18 +1576,90 @@ mod test {
         let _: StatusEvent = serde_json::from_str(JSON).unwrap();
     }
 
+    #[test]
+    fn status_event_builder() {
+        let event = StatusEventBuilder::new("ci", "success").build_event();
+        assert!(matches!(event, Event::Status(_)));
+    }
+
     #[test]
     fn check_run_event() {
         const JSON: &str = include_str!("../test-input/check-run-event.json");
         let _: CheckRunEvent = serde_json::from_str(JSON).unwrap();
     }
 
+    #[test]
+    fn check_run_event_builder() {
+        let event = CheckRunEventBuilder::new("completed").build_event();
+        assert!(matches!(event, Event::CheckRun(_)));
+    }
+
     #[test]
     fn check_suite_event() {
         const JSON: &str = include_str!("../test-input/check-suite-event.json");
         let _: CheckSuiteEvent = serde_json::from_str(JSON).unwrap();
     }
 
+    #[test]
+    fn org_block_event() {
+        const JSON: &[u8] = include_bytes!("../test-input/org-block-event.json");
+        let event = Event::from_json(EventType::OrgBlock, JSON).unwrap();
+        assert!(event.repository().is_none());
+        assert_eq!(event.organization().unwrap().login, "bors-rs");
+        match event {
+            Event::OrgBlock(e) => assert_eq!(e.blocked_user.login, "spammer"),
+            _ => panic!("expected an org_block event"),
+        }
+    }
+
+    #[test]
+    fn membership_event() {
+        const JSON: &[u8] = include_bytes!("../test-input/membership-event.json");
+        let event = Event::from_json(EventType::Membership, JSON).unwrap();
+        assert!(event.repository().is_none());
+        assert_eq!(event.organization().unwrap().login, "bors-rs");
+        match event {
+            Event::Membership(e) => assert_eq!(e.team.slug, "reviewers"),
+            _ => panic!("expected a membership event"),
+        }
+    }
+
+    #[test]
+    fn repository_event() {
+        const REPO_JSON: &str = include_str!("../test-input/repo.json");
+        let repository: serde_json::Value = serde_json::from_str(REPO_JSON).unwrap();
+        let owner = repository["owner"]["login"].as_str().unwrap().to_owned();
+        let name = repository["name"].as_str().unwrap().to_owned();
+
+        let renamed: RepositoryEvent = serde_json::from_value(serde_json::json!({
+            "action": "renamed",
+            "changes": { "repository": { "name": { "from": "old-name" } } },
+            "repository": repository,
+            "sender": repository["owner"],
+        }))
+        .unwrap();
+        assert!(renamed.is_renamed());
+        assert_eq!(renamed.previous_owner(), owner);
+        assert_eq!(renamed.previous_name(), "old-name");
+
+        let transferred: RepositoryEvent = serde_json::from_value(serde_json::json!({
+            "action": "transferred",
+            "changes": { "owner": { "from": { "organization": {
+                "login": "old-org",
+                "id": 1,
+                "node_id": "MDEyOk9yZ2FuaXphdGlvbjE=",
+                "url": "https://api.github.com/orgs/old-org",
+                "description": null,
+            } } } },
+            "repository": repository,
+            "sender": repository["owner"],
+        }))
+        .unwrap();
+        assert!(transferred.is_transferred());
+        assert_eq!(transferred.previous_owner(), "old-org");
+        assert_eq!(transferred.previous_name(), name);
+    }
+
     #[test]
     fn pull_request_review() {
         const JSON: &str = include_str!("../test-input/pull-request-review-event.json");
@@ -1336,4 +1671,64 @@ mod test {
         const JSON: &str = include_str!("../test-input/pull-request-review-comment-event.json");
         let _: PullRequestReviewCommentEvent = serde_json::from_str(JSON).unwrap();
     }
+
+    #[test]
+    fn content_reference_event() {
+        const JSON: &str = include_str!("../test-input/content-reference-event.json");
+        let event = Event::from_json(EventType::ContentReference, JSON.as_bytes()).unwrap();
+        let event = match event {
+            Event::ContentReference(event) => event,
+            _ => panic!("expected a content_reference event"),
+        };
+        assert_eq!(event.content_reference.id, 17);
+        assert_eq!(event.content_reference.reference, "errors.ai");
+    }
+
+    #[test]
+    fn marketplace_purchase_event() {
+        const JSON: &str = include_str!("../test-input/marketplace-purchase-event.json");
+        let event = Event::from_json(EventType::MarketplacePurchase, JSON.as_bytes()).unwrap();
+        let event = match event {
+            Event::MarketplacePurchase(event) => event,
+            _ => panic!("expected a marketplace_purchase event"),
+        };
+        let purchase = event.marketplace_purchase;
+        assert_eq!(purchase.account.login, "username");
+        assert_eq!(purchase.plan.price_model, "per-unit");
+        assert_eq!(purchase.plan.bullets.len(), 2);
+        assert!(purchase.free_trial_ends_on.is_none());
+        assert_eq!(event.previous_marketplace_purchase.unwrap().unit_count, 5);
+    }
+
+    #[test]
+    fn package_events() {
+        const JSON: &str = include_str!("../test-input/package-event.json");
+        let event = Event::from_json(EventType::Package, JSON.as_bytes()).unwrap();
+        let event = match event {
+            Event::Package(event) => event,
+            _ => panic!("expected a package event"),
+        };
+        assert_eq!(event.package.package_type, "npm");
+        assert_eq!(event.package.registry.unwrap().registry_type, "npm");
+        let version = event.package.package_version.unwrap();
+        assert_eq!(version.version, "1.0.0");
+        assert_eq!(
+            version.target_oid,
+            Some(Oid::from_str("a9d6b97ab38ac2b73f9c3b36ab7a6ae4e8be4b8a"))
+        );
+        assert_eq!(version.package_files[0].size, 1003);
+        assert!(event.organization.is_none());
+
+        // The deprecated registry_package event has the same payload under a different key
+        let mut payload: serde_json::Value = serde_json::from_str(JSON).unwrap();
+        let package = payload.as_object_mut().unwrap().remove("package").unwrap();
+        payload["registry_package"] = package;
+        let event = Event::from_json(
+            EventType::RegistryPackage,
+            &serde_json::to_vec(&payload).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(event.event_type(), EventType::RegistryPackage);
+        assert!(event.repository().is_some());
+    }
 }
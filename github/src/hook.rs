@@ -1,45 +1,120 @@
 use super::{DateTime, EventType};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Hook {
     #[serde(rename = "type")]
-    hook_type: String,
-    id: u64,
-    name: String,
-    active: bool,
-    events: Vec<EventType>,
-    config: HookConfig,
-    updated_at: DateTime,
-    created_at: DateTime,
-    url: String,
-    test_url: String,
-    ping_url: String,
-    last_response: HookResponse,
-}
-
-#[derive(Clone, Debug, Deserialize)]
+    pub hook_type: String,
+    pub id: u64,
+    pub name: String,
+    pub active: bool,
+    pub events: Vec<EventType>,
+    pub config: HookConfig,
+    pub updated_at: DateTime,
+    pub created_at: DateTime,
+    pub url: String,
+    /// Only present on repository webhooks
+    pub test_url: Option<String>,
+    pub ping_url: String,
+    pub deliveries_url: Option<String>,
+    /// Only present on repository webhooks
+    pub last_response: Option<HookResponse>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct HookConfig {
-    content_type: String,
-    insecure_ssl: String,
-    secret: Option<String>,
-    url: String,
+    pub content_type: String,
+    pub insecure_ssl: String,
+    pub secret: Option<String>,
+    pub url: String,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct HookResponse {
-    code: Option<String>,
-    status: String,
-    message: Option<String>,
+    pub code: Option<String>,
+    pub status: String,
+    pub message: Option<String>,
+}
+
+/// A delivery of a webhook event, as listed by the hook deliveries endpoints
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HookDelivery {
+    pub id: u64,
+    /// The `X-GitHub-Delivery` header of the delivery
+    pub guid: String,
+    pub delivered_at: DateTime,
+    /// Indicates if this delivery is a redelivery of an earlier one
+    pub redelivery: bool,
+    /// Time, in seconds, the delivery took
+    pub duration: f64,
+    /// Description of the outcome, e.g. `OK` or `Invalid HTTP Response: 503`
+    pub status: String,
+    /// HTTP status code of the response, `0` if the delivery failed to connect
+    pub status_code: u16,
+    pub event: String,
+    pub action: Option<String>,
+    pub installation_id: Option<u64>,
+    pub repository_id: Option<u64>,
+}
+
+impl HookDelivery {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status_code)
+    }
+}
+
+/// A single delivery of a webhook event, including the request and response
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HookDeliveryDetails {
+    #[serde(flatten)]
+    pub delivery: HookDelivery,
+    pub request: HookDeliveryRequest,
+    pub response: HookDeliveryResponse,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HookDeliveryRequest {
+    pub headers: Option<HashMap<String, String>>,
+    pub payload: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HookDeliveryResponse {
+    pub headers: Option<HashMap<String, String>>,
+    pub payload: Option<String>,
 }
 
 #[cfg(test)]
 mod test {
-    use super::Hook;
+    use super::{Hook, HookDelivery};
 
     #[test]
     fn hook() {
         const JSON: &str = include_str!("../test-input/hook.json");
         let _: Hook = serde_json::from_str(JSON).unwrap();
     }
+
+    #[test]
+    fn hook_delivery() {
+        const JSON: &str = r#"
+            {
+                "id": 12345678,
+                "guid": "0b989ba4-242f-11e5-81e1-c7b6966d2516",
+                "delivered_at": "2019-06-03T00:57:16Z",
+                "redelivery": false,
+                "duration": 0.27,
+                "status": "Invalid HTTP Response: 503",
+                "status_code": 503,
+                "event": "issues",
+                "action": "opened",
+                "installation_id": null,
+                "repository_id": 1296269
+            }
+        "#;
+
+        let delivery: HookDelivery = serde_json::from_str(JSON).unwrap();
+        assert!(!delivery.is_success());
+        assert_eq!(delivery.guid, "0b989ba4-242f-11e5-81e1-c7b6966d2516");
+    }
 }
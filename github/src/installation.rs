@@ -0,0 +1,28 @@
+use crate::{DateTime, Repository};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+/// A token to access the API as an installation of a Github App
+#[derive(Clone, Deserialize, Serialize)]
+pub struct InstallationToken {
+    pub token: String,
+    pub expires_at: DateTime,
+    #[serde(default)]
+    pub permissions: HashMap<String, String>,
+    /// Either "all" or "selected"
+    pub repository_selection: Option<String>,
+    /// The repositories the token is scoped to, if it was requested for specific repositories
+    pub repositories: Option<Vec<Repository>>,
+}
+
+impl fmt::Debug for InstallationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstallationToken")
+            .field("token", &"<redacted>")
+            .field("expires_at", &self.expires_at)
+            .field("permissions", &self.permissions)
+            .field("repository_selection", &self.repository_selection)
+            .field("repositories", &self.repositories)
+            .finish()
+    }
+}
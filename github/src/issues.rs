@@ -1,7 +1,7 @@
 use super::{DateTime, NodeId, ReactionSummary, Repository, User};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Issue {
     pub id: u64,
     pub node_id: NodeId,
@@ -44,7 +44,7 @@ pub enum State {
     Closed,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PullRequestRef {
     pub url: String,
     pub html_url: String,
@@ -52,7 +52,7 @@ pub struct PullRequestRef {
     pub patch_url: String,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Milestone {
     pub url: String,
     pub html_url: String,
@@ -69,10 +69,11 @@ pub struct Milestone {
     pub created_at: DateTime,
     pub updated_at: DateTime,
     pub closed_at: Option<DateTime>,
-    pub due_on: DateTime,
+    /// Milestones don't need to have a due date
+    pub due_on: Option<DateTime>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Comment {
     pub id: u64,
     pub node_id: NodeId,
@@ -91,7 +92,7 @@ impl Comment {
     }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Label {
     pub id: u64,
     pub node_id: NodeId,
@@ -101,3 +102,134 @@ pub struct Label {
     pub color: String,
     pub default: bool,
 }
+
+/// An entry of an issue's history, e.g. a label being added, as listed by the issue events API.
+/// Not to be confused with `IssueEvent`, the webhook payload
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IssueEventRecord {
+    pub id: u64,
+    pub node_id: NodeId,
+    pub url: String,
+    /// The user who caused the event, missing if their account was deleted
+    pub actor: Option<User>,
+    /// The kind of event, e.g. "labeled", "unlabeled", "closed" or "renamed"
+    pub event: String,
+    pub commit_id: Option<String>,
+    pub commit_url: Option<String>,
+    pub created_at: DateTime,
+    /// The label added or removed by "labeled" and "unlabeled" events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<IssueEventLabel>,
+}
+
+/// The label of an `IssueEventRecord`, as it was named when the event occurred
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IssueEventLabel {
+    pub name: String,
+    pub color: String,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{IssueEventRecord, Milestone};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn issue_event_record_round_trip() {
+        let json = json!({
+            "id": 1,
+            "node_id": "MDEwOklzc3VlRXZlbnQx",
+            "url": "https://api.github.com/repos/octocat/Hello-World/issues/events/1",
+            "actor": {
+                "login": "octocat",
+                "id": 1,
+                "node_id": "MDQ6VXNlcjE=",
+                "avatar_url": "https://github.com/images/error/octocat_happy.gif",
+                "gravatar_id": "",
+                "url": "https://api.github.com/users/octocat",
+                "html_url": "https://github.com/octocat",
+                "followers_url": "https://api.github.com/users/octocat/followers",
+                "following_url": "https://api.github.com/users/octocat/following{/other_user}",
+                "gists_url": "https://api.github.com/users/octocat/gists{/gist_id}",
+                "starred_url": "https://api.github.com/users/octocat/starred{/owner}{/repo}",
+                "subscriptions_url": "https://api.github.com/users/octocat/subscriptions",
+                "organizations_url": "https://api.github.com/users/octocat/orgs",
+                "repos_url": "https://api.github.com/users/octocat/repos",
+                "events_url": "https://api.github.com/users/octocat/events{/privacy}",
+                "received_events_url": "https://api.github.com/users/octocat/received_events",
+                "type": "User",
+                "site_admin": false
+            },
+            "event": "labeled",
+            "commit_id": null,
+            "commit_url": null,
+            "created_at": "2011-04-14T16:00:49Z",
+            "label": {
+                "name": "bors-high-priority",
+                "color": "d73a4a"
+            }
+        });
+
+        let event: IssueEventRecord = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&event).unwrap(), json);
+
+        // Events other than labeling don't have a label, and the actor may have been deleted
+        let mut json = json;
+        json["event"] = "closed".into();
+        json["actor"] = Value::Null;
+        json.as_object_mut().unwrap().remove("label");
+        let event: IssueEventRecord = serde_json::from_value(json).unwrap();
+        assert!(event.actor.is_none());
+        assert!(event.label.is_none());
+    }
+
+    #[test]
+    fn milestone_round_trip() {
+        let json = json!({
+            "url": "https://api.github.com/repos/octocat/Hello-World/milestones/1",
+            "html_url": "https://github.com/octocat/Hello-World/milestones/v1.0",
+            "labels_url": "https://api.github.com/repos/octocat/Hello-World/milestones/1/labels",
+            "id": 1002604,
+            "node_id": "MDk6TWlsZXN0b25lMTAwMjYwNA==",
+            "number": 1,
+            "state": "open",
+            "title": "v1.0",
+            "description": "Tracking milestone for version 1.0",
+            "creator": {
+                "login": "octocat",
+                "id": 1,
+                "node_id": "MDQ6VXNlcjE=",
+                "avatar_url": "https://github.com/images/error/octocat_happy.gif",
+                "gravatar_id": "",
+                "url": "https://api.github.com/users/octocat",
+                "html_url": "https://github.com/octocat",
+                "followers_url": "https://api.github.com/users/octocat/followers",
+                "following_url": "https://api.github.com/users/octocat/following{/other_user}",
+                "gists_url": "https://api.github.com/users/octocat/gists{/gist_id}",
+                "starred_url": "https://api.github.com/users/octocat/starred{/owner}{/repo}",
+                "subscriptions_url": "https://api.github.com/users/octocat/subscriptions",
+                "organizations_url": "https://api.github.com/users/octocat/orgs",
+                "repos_url": "https://api.github.com/users/octocat/repos",
+                "events_url": "https://api.github.com/users/octocat/events{/privacy}",
+                "received_events_url": "https://api.github.com/users/octocat/received_events",
+                "type": "User",
+                "site_admin": false
+            },
+            "open_issues": 4,
+            "closed_issues": 8,
+            "created_at": "2011-04-10T20:09:31Z",
+            "updated_at": "2014-03-03T18:58:10Z",
+            "closed_at": null,
+            "due_on": "2012-10-09T23:39:01Z"
+        });
+
+        let milestone: Milestone = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&milestone).unwrap(), json);
+
+        // Milestones without a due date
+        let mut json = json;
+        json["due_on"] = Value::Null;
+        let milestone: Milestone = serde_json::from_value(json).unwrap();
+        assert!(milestone.due_on.is_none());
+    }
+}
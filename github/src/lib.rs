@@ -1,33 +1,99 @@
 //! Types for Github's v3 API and webhooks
 //! https://developer.github.com/webhooks/
 //! https://developer.github.com/v3/
+//!
+//! The crate is split into cargo features so that consumers only build what they use:
+//!
+//! - `types` (default): the types of the API's resources and of webhook events
+//! - `webhook` (default): parsing and signature verification of webhook deliveries
+//! - `client`: a client for the REST API, built on `reqwest`
+//! - `graphql`: support for GraphQL queries in the client
 
+#[cfg(feature = "types")]
+mod activity;
+#[cfg(feature = "types")]
 mod check;
-pub mod client; //TODO Maybe hide with a feature?
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "types")]
 mod common;
+#[cfg(feature = "types")]
+mod content_reference;
+#[cfg(feature = "types")]
 mod events;
+#[cfg(feature = "types")]
 mod hook;
+#[cfg(feature = "types")]
+mod installation;
+#[cfg(feature = "types")]
 mod issues;
+#[cfg(feature = "types")]
 mod license;
+#[cfg(feature = "types")]
+mod marketplace;
+#[cfg(feature = "types")]
+mod meta;
+#[cfg(feature = "types")]
+mod package;
+#[cfg(feature = "types")]
 mod project;
+#[cfg(feature = "types")]
+mod protection;
+#[cfg(feature = "types")]
 mod pull_request;
+#[cfg(feature = "types")]
 mod reactions;
+#[cfg(feature = "types")]
 mod repo;
+pub mod signature;
+#[cfg(all(feature = "types", any(test, feature = "test-fixtures")))]
+pub mod test_fixtures;
+#[cfg(feature = "types")]
 mod user;
+#[cfg(feature = "webhook")]
 mod webhook;
+#[cfg(feature = "types")]
 mod workflow;
 
+#[cfg(feature = "types")]
+pub use activity::*;
+#[cfg(feature = "types")]
 pub use check::*;
+#[cfg(feature = "client")]
 pub use client::Client;
+#[cfg(feature = "types")]
 pub use common::*;
+#[cfg(feature = "types")]
+pub use content_reference::*;
+#[cfg(feature = "types")]
 pub use events::*;
+#[cfg(feature = "types")]
 pub use hook::*;
+#[cfg(feature = "types")]
+pub use installation::*;
+#[cfg(feature = "types")]
 pub use issues::*;
+#[cfg(feature = "types")]
 pub use license::*;
+#[cfg(feature = "types")]
+pub use marketplace::*;
+#[cfg(feature = "types")]
+pub use meta::*;
+#[cfg(feature = "types")]
+pub use package::*;
+#[cfg(feature = "types")]
 pub use project::*;
+#[cfg(feature = "types")]
+pub use protection::*;
+#[cfg(feature = "types")]
 pub use pull_request::*;
+#[cfg(feature = "types")]
 pub use reactions::*;
+#[cfg(feature = "types")]
 pub use repo::*;
+#[cfg(feature = "types")]
 pub use user::*;
+#[cfg(feature = "webhook")]
 pub use webhook::*;
+#[cfg(feature = "types")]
 pub use workflow::*;
@@ -1,7 +1,7 @@
 use super::NodeId;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct License {
     pub key: String,
     pub name: String,
@@ -20,7 +20,7 @@ pub struct License {
     pub featured: Option<bool>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RepositoryLicense {
     pub name: String,
     pub path: String,
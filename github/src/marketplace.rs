@@ -0,0 +1,45 @@
+use super::{DateTime, NodeId};
+use serde::{Deserialize, Serialize};
+
+/// An account's purchase of a GitHub Marketplace plan
+///
+/// GitHub API docs: https://developer.github.com/v3/apps/marketplace/
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MarketplacePurchase {
+    pub account: MarketplaceAccount,
+    /// Either "monthly" or "yearly"
+    pub billing_cycle: String,
+    /// The number of units, e.g. seats, purchased for per-unit plans
+    pub unit_count: u64,
+    pub on_free_trial: bool,
+    pub free_trial_ends_on: Option<DateTime>,
+    pub next_billing_date: Option<DateTime>,
+    pub plan: MarketplacePlan,
+}
+
+/// The user or organization which purchased a plan
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MarketplaceAccount {
+    pub id: u64,
+    pub node_id: NodeId,
+    pub login: String,
+    /// Either "User" or "Organization"
+    #[serde(rename = "type")]
+    pub account_type: String,
+    pub organization_billing_email: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MarketplacePlan {
+    pub id: u64,
+    pub name: String,
+    pub description: String,
+    pub monthly_price_in_cents: u64,
+    pub yearly_price_in_cents: u64,
+    /// One of "free", "flat-rate" or "per-unit"
+    pub price_model: String,
+    pub has_free_trial: bool,
+    pub unit_name: Option<String>,
+    #[serde(default)]
+    pub bullets: Vec<String>,
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Information about Github, including the IP address ranges its services use
+///
+/// Address ranges are in CIDR notation, e.g. `192.30.252.0/22`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Meta {
+    pub verifiable_password_authentication: bool,
+    #[serde(default)]
+    pub ssh_key_fingerprints: HashMap<String, String>,
+    /// Addresses webhooks are delivered from
+    #[serde(default)]
+    pub hooks: Vec<String>,
+    #[serde(default)]
+    pub web: Vec<String>,
+    #[serde(default)]
+    pub api: Vec<String>,
+    #[serde(default)]
+    pub git: Vec<String>,
+    #[serde(default)]
+    pub packages: Vec<String>,
+    #[serde(default)]
+    pub pages: Vec<String>,
+    #[serde(default)]
+    pub importer: Vec<String>,
+    #[serde(default)]
+    pub actions: Vec<String>,
+    #[serde(default)]
+    pub dependabot: Vec<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::Meta;
+
+    #[test]
+    fn meta() {
+        const JSON: &str = include_str!("../test-input/meta.json");
+        let meta: Meta = serde_json::from_str(JSON).unwrap();
+        assert_eq!(meta.hooks[0], "192.30.252.0/22");
+    }
+}
@@ -0,0 +1,69 @@
+use super::{DateTime, Oid, User};
+use serde::{Deserialize, Serialize};
+
+/// A package published to GitHub Packages
+///
+/// GitHub API docs: https://developer.github.com/v3/activity/events/types/#packageevent
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Package {
+    pub id: u64,
+    pub name: String,
+    /// The type of package, e.g. "npm", "maven", "rubygems", "docker" or "nuget"
+    pub package_type: String,
+    pub html_url: String,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+    pub owner: User,
+    /// The version which was published or updated, present in webhook payloads
+    pub package_version: Option<PackageVersion>,
+    pub registry: Option<PackageRegistry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PackageVersion {
+    pub id: u64,
+    pub version: String,
+    pub summary: Option<String>,
+    pub body: Option<String>,
+    pub body_html: Option<String>,
+    pub manifest: Option<String>,
+    pub html_url: String,
+    pub tag_name: Option<String>,
+    pub target_commitish: Option<String>,
+    pub target_oid: Option<Oid>,
+    pub draft: Option<bool>,
+    pub prerelease: Option<bool>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+    #[serde(default)]
+    pub package_files: Vec<PackageFile>,
+    pub author: Option<User>,
+    pub installation_command: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PackageFile {
+    pub id: u64,
+    pub name: String,
+    pub download_url: String,
+    pub sha256: Option<String>,
+    pub sha1: Option<String>,
+    pub md5: Option<String>,
+    pub content_type: String,
+    /// The state of the upload, e.g. "uploaded"
+    pub state: String,
+    pub size: u64,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+/// The registry a package was published to
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PackageRegistry {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub registry_type: String,
+    pub url: String,
+    pub about_url: String,
+    pub vendor: String,
+}
@@ -1,7 +1,7 @@
 use super::{DateTime, NodeId, User};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Project {
     pub id: u64,
     pub node_id: NodeId,
@@ -18,7 +18,7 @@ pub struct Project {
     pub creator: User,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ProjectCard {
     pub id: u64,
     pub node_id: NodeId,
@@ -44,7 +44,7 @@ pub struct ProjectCard {
 
 impl ProjectCard {
     pub fn column_id(&self) -> Option<u64> {
-        self.column_url.split('/').last()?.parse().ok()
+        self.column_url.split('/').next_back()?.parse().ok()
     }
 
     pub fn issue_number(&self) -> Option<u64> {
@@ -53,14 +53,14 @@ impl ProjectCard {
         }
 
         if let Some(url) = &self.content_url {
-            url.split('/').last()?.parse().ok()
+            url.split('/').next_back()?.parse().ok()
         } else {
             None
         }
     }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ProjectColumn {
     pub id: u64,
     pub node_id: NodeId,
@@ -0,0 +1,105 @@
+use super::{App, Team, User};
+use serde::{Deserialize, Serialize};
+
+/// The protection of a branch
+///
+/// Each setting is `None` when it isn't enabled for the branch.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BranchProtection {
+    pub url: String,
+    pub required_status_checks: Option<RequiredStatusChecks>,
+    pub enforce_admins: Option<ProtectionSetting>,
+    pub required_pull_request_reviews: Option<RequiredPullRequestReviews>,
+    /// Users, teams and apps allowed to push to the branch. Anyone with write access can push to
+    /// it if `None`
+    pub restrictions: Option<BranchRestrictions>,
+    pub required_linear_history: Option<ProtectionSetting>,
+    pub allow_force_pushes: Option<ProtectionSetting>,
+    pub allow_deletions: Option<ProtectionSetting>,
+    pub required_signatures: Option<ProtectionSetting>,
+    pub required_conversation_resolution: Option<ProtectionSetting>,
+}
+
+impl BranchProtection {
+    /// The contexts of the checks and statuses which must pass before the branch can be updated
+    pub fn required_contexts(&self) -> impl Iterator<Item = &str> {
+        let checks = self.required_status_checks.iter();
+        checks
+            .clone()
+            .flat_map(|c| c.contexts.iter().map(String::as_str))
+            .chain(checks.flat_map(|c| c.checks.iter().map(|check| check.context.as_str())))
+    }
+}
+
+/// A protection setting which is either enabled or not
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProtectionSetting {
+    pub enabled: bool,
+}
+
+impl ProtectionSetting {
+    /// Indicates if `setting` is present and enabled
+    pub fn is_enabled(setting: &Option<ProtectionSetting>) -> bool {
+        setting.as_ref().is_some_and(|s| s.enabled)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RequiredStatusChecks {
+    /// Requires the branch being merged to be up to date with the protected branch
+    pub strict: bool,
+    pub contexts: Vec<String>,
+    #[serde(default)]
+    pub checks: Vec<RequiredCheck>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RequiredCheck {
+    pub context: String,
+    /// The app which must set the check, or any app if `None`
+    pub app_id: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RequiredPullRequestReviews {
+    #[serde(default)]
+    pub dismiss_stale_reviews: bool,
+    #[serde(default)]
+    pub require_code_owner_reviews: bool,
+    #[serde(default)]
+    pub required_approving_review_count: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BranchRestrictions {
+    pub users: Vec<User>,
+    pub teams: Vec<Team>,
+    #[serde(default)]
+    pub apps: Vec<App>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BranchProtection, ProtectionSetting};
+
+    #[test]
+    fn branch_protection() {
+        const JSON: &str = include_str!("../test-input/branch-protection.json");
+        let protection: BranchProtection = serde_json::from_str(JSON).unwrap();
+
+        assert_eq!(
+            protection.required_contexts().collect::<Vec<_>>(),
+            vec!["continuous-integration/travis-ci", "ci/build"]
+        );
+        assert!(ProtectionSetting::is_enabled(
+            &protection.required_linear_history
+        ));
+        assert!(!ProtectionSetting::is_enabled(
+            &protection.allow_force_pushes
+        ));
+        assert!(!ProtectionSetting::is_enabled(
+            &protection.required_signatures
+        ));
+        assert_eq!(protection.restrictions.unwrap().users[0].login, "octocat");
+    }
+}
@@ -1,9 +1,9 @@
 use super::{
     DateTime, Label, Milestone, NodeId, Oid, ReactionSummary, Repository, State, Team, User,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CommitRef {
     pub label: String,
     #[serde(rename = "ref")]
@@ -13,7 +13,7 @@ pub struct CommitRef {
     pub repo: Option<Repository>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PullRequestState {
     Open,
@@ -21,7 +21,7 @@ pub enum PullRequestState {
     Merged,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PullRequest {
     pub url: String,
     pub id: u64,
@@ -71,7 +71,7 @@ pub struct PullRequest {
     pub changed_files: Option<u64>,
 }
 
-#[derive(Clone, Copy, Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ReviewState {
     Approved,
@@ -80,7 +80,7 @@ pub enum ReviewState {
     Dismissed,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Review {
     pub id: u64,
     pub node_id: NodeId,
@@ -101,7 +101,7 @@ impl Review {
     }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ReviewComment {
     pub url: String,
     pub id: u64,
@@ -137,6 +137,23 @@ impl ReviewComment {
     }
 }
 
+/// A file changed by a commit or pull request
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CommitFile {
+    pub sha: Option<String>,
+    pub filename: String,
+    pub status: String,
+    pub additions: u64,
+    pub deletions: u64,
+    pub changes: u64,
+    pub blob_url: Option<String>,
+    pub raw_url: Option<String>,
+    pub contents_url: Option<String>,
+    pub patch: Option<String>,
+    /// The file's path before it was renamed
+    pub previous_filename: Option<String>,
+}
+
 #[cfg(test)]
 mod test {
     use super::PullRequest;
@@ -1,7 +1,7 @@
 use super::{DateTime, License, NodeId, Oid, User};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Repository {
     pub id: u64,
     pub node_id: NodeId,
@@ -76,18 +76,33 @@ pub struct Repository {
     pub open_issues: u64,
     pub watchers: u64,
     pub default_branch: String,
+    // Merge settings, which are only included for users with admin access and not in webhook
+    // payloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_merge_commit: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_squash_merge: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_rebase_merge: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_auto_merge: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_update_branch: Option<bool>,
+    /// Delete the head branches of PRs once they're merged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete_branch_on_merge: Option<bool>,
     // parent: Option<Box<Repository>>,
     // source: Option<Box<Repository>>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Author {
     pub name: String,
     pub email: String,
     pub username: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Commit {
     pub id: Oid,
     pub tree_id: Oid,
@@ -105,13 +120,42 @@ pub struct Commit {
     pub modified: Vec<String>,
 }
 
+/// A protection of the tags matching a pattern, which only users with admin or maintain access
+/// can create or delete
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TagProtection {
+    pub id: u64,
+    /// Glob pattern of the protected tags, e.g. `v*`
+    pub pattern: String,
+    pub created_at: Option<DateTime>,
+    pub updated_at: Option<DateTime>,
+    pub enabled: Option<bool>,
+}
+
 #[cfg(test)]
 mod test {
-    use super::Repository;
+    use super::{Repository, TagProtection};
 
     #[test]
     fn repo() {
         const REPO_JSON: &str = include_str!("../test-input/repo.json");
-        let _repo: Repository = serde_json::from_str(REPO_JSON).unwrap();
+        let repo: Repository = serde_json::from_str(REPO_JSON).unwrap();
+        assert_eq!(repo.allow_squash_merge, Some(true));
+        assert_eq!(repo.allow_rebase_merge, Some(false));
+        assert_eq!(repo.delete_branch_on_merge, Some(true));
+        assert_eq!(repo.allow_auto_merge, None);
+    }
+
+    #[test]
+    fn tag_protection() {
+        const JSON: &str = r#"[{
+            "id": 2,
+            "pattern": "v1.*",
+            "created_at": "2022-03-21T19:39:26Z",
+            "updated_at": "2022-03-21T19:39:26Z"
+        }]"#;
+        let protections: Vec<TagProtection> = serde_json::from_str(JSON).unwrap();
+        assert_eq!(protections[0].pattern, "v1.*");
+        assert_eq!(protections[0].id, 2);
     }
 }
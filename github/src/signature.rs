@@ -0,0 +1,328 @@
+//! Verification of the signatures Github sends along with webhooks
+//!
+//! Github signs the body of every webhook delivery with the webhook's secret, as an HMAC-SHA1
+//! hexdigest in the `X-Hub-Signature` header and an HMAC-SHA256 hexdigest in the
+//! `X-Hub-Signature-256` header. A delivery is only accepted if every signature it carries is
+//! well formed and matches, there is at most one signature per algorithm, and digests are
+//! compared in constant time so their timing doesn't leak how much of a forged signature is right.
+//! HMAC-SHA256 is computed and compared by the `hmac` and `sha2` crates.
+//!
+//! Github API docs: https://docs.github.com/en/developers/webhooks-and-events/webhooks/securing-your-webhooks
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+use thiserror::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Hash function a webhook's body is signed with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+}
+
+impl Algorithm {
+    /// The prefix of signatures made with this algorithm, e.g. `sha256` in `sha256=<hexdigest>`
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+        }
+    }
+
+    /// Length, in bytes, of this algorithm's digests
+    pub fn digest_len(self) -> usize {
+        match self {
+            Algorithm::Sha1 => 20,
+            Algorithm::Sha256 => 32,
+        }
+    }
+
+    fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::Sha1 => hmacsha1::hmac_sha1(key, data).to_vec(),
+            Algorithm::Sha256 => hmac_sha256(key, data).to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.prefix())
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("no signature")]
+    Missing,
+    #[error("malformed signature `{0}`")]
+    Malformed(String),
+    #[error("more than one {0} signature")]
+    Ambiguous(Algorithm),
+    #[error("{0} signature doesn't match")]
+    Mismatch(Algorithm),
+}
+
+/// A parsed signature header value, e.g. `sha256=<hexdigest>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub algorithm: Algorithm,
+    pub digest: Vec<u8>,
+}
+
+impl Signature {
+    /// Sign `body` with `key`
+    pub fn new(algorithm: Algorithm, key: &[u8], body: &[u8]) -> Self {
+        Self {
+            algorithm,
+            digest: algorithm.hmac(key, body),
+        }
+    }
+
+    /// Parse a signature header value. Anything but a known algorithm followed by a hexdigest of
+    /// exactly the algorithm's length is rejected
+    pub fn parse(value: &str) -> Result<Self, SignatureError> {
+        let malformed = || SignatureError::Malformed(value.to_owned());
+
+        let (prefix, hexdigest) = value.split_once('=').ok_or_else(malformed)?;
+        let algorithm = [Algorithm::Sha1, Algorithm::Sha256]
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.prefix() == prefix)
+            .ok_or_else(malformed)?;
+        if hexdigest.len() != algorithm.digest_len() * 2 {
+            return Err(malformed());
+        }
+        let digest = hex::decode(hexdigest).map_err(|_| malformed())?;
+
+        Ok(Self { algorithm, digest })
+    }
+
+    /// Check, in constant time, that this is the signature of `body` signed with `key`
+    pub fn verify(&self, key: &[u8], body: &[u8]) -> bool {
+        match self.algorithm {
+            Algorithm::Sha1 => constant_time_eq(&self.algorithm.hmac(key, body), &self.digest),
+            Algorithm::Sha256 => hmac_sha256_mac(key, body)
+                .verify_slice(&self.digest)
+                .is_ok(),
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.algorithm, hex::encode(&self.digest))
+    }
+}
+
+/// Check the signature header values of a webhook delivery against its `body`
+///
+/// Every value, from any of the signature headers, has to be a well formed signature which
+/// matches. Several values for the same algorithm are rejected, even if they agree, as there's no
+/// telling which one Github sent.
+pub fn verify<'a, I>(key: &[u8], body: &[u8], values: I) -> Result<(), SignatureError>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut signatures: Vec<Signature> = Vec::new();
+    for value in values {
+        let signature = Signature::parse(value)?;
+        if signatures
+            .iter()
+            .any(|other| other.algorithm == signature.algorithm)
+        {
+            return Err(SignatureError::Ambiguous(signature.algorithm));
+        }
+        signatures.push(signature);
+    }
+
+    if signatures.is_empty() {
+        return Err(SignatureError::Missing);
+    }
+
+    // Check every signature rather than stopping at the first mismatch, so the time taken doesn't
+    // reveal which one didn't match
+    let mut mismatch = None;
+    for signature in &signatures {
+        if !signature.verify(key, body) {
+            mismatch.get_or_insert(signature.algorithm);
+        }
+    }
+    match mismatch {
+        Some(algorithm) => Err(SignatureError::Mismatch(algorithm)),
+        None => Ok(()),
+    }
+}
+
+/// Compare `a` and `b` in time which only depends on their lengths
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let difference = a.iter().zip(b).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    // Keep the compiler from turning the fold into an early-exit comparison
+    std::hint::black_box(difference) == 0
+}
+
+/// Computes the HMAC-SHA256 of `data` with `key`
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&hmac_sha256_mac(key, data).finalize().into_bytes());
+    digest
+}
+
+fn hmac_sha256_mac(key: &[u8], data: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Block size, in bytes, of both SHA-1 and SHA-256
+    const BLOCK_SIZE: usize = 64;
+
+    #[test]
+    fn hmac_sha256_digest() {
+        // RFC 4231 test cases 1 and 6, the latter with a key longer than a block
+        assert_eq!(
+            hex::encode(hmac_sha256(&[0x0b; 20], b"Hi There")),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hex::encode(hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn github_example() {
+        // From Github's documentation on validating webhook deliveries
+        let signature = Signature::parse(
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+        )
+        .unwrap();
+        assert!(signature.verify(b"It's a Secret to Everybody", b"Hello, World!"));
+    }
+
+    #[test]
+    fn rejects_ambiguous_or_malformed_signatures() {
+        let key = b"secret";
+        let body = b"{}";
+        let sha1 = Signature::new(Algorithm::Sha1, key, body).to_string();
+        let sha256 = Signature::new(Algorithm::Sha256, key, body).to_string();
+        let forged = Signature::new(Algorithm::Sha256, b"guess", body).to_string();
+
+        assert_eq!(verify(key, body, [sha1.as_str(), sha256.as_str()]), Ok(()));
+        assert_eq!(verify(key, body, Vec::new()), Err(SignatureError::Missing));
+        assert_eq!(
+            verify(key, body, [sha256.as_str(), sha256.as_str()]),
+            Err(SignatureError::Ambiguous(Algorithm::Sha256))
+        );
+        assert_eq!(
+            verify(key, body, [sha1.as_str(), forged.as_str()]),
+            Err(SignatureError::Mismatch(Algorithm::Sha256))
+        );
+
+        let truncated = &sha1[..sha1.len() - 2];
+        let uppercase_prefix = sha1.replacen("sha1", "SHA1", 1);
+        let unknown = sha256.replacen("sha256", "sha512", 1);
+        for value in [
+            truncated,
+            &uppercase_prefix,
+            &unknown,
+            "sha1",
+            "sha1=xyz",
+            "",
+        ] {
+            assert_eq!(
+                verify(key, body, [value]),
+                Err(SignatureError::Malformed(value.to_owned())),
+                "{}",
+                value
+            );
+        }
+    }
+
+    /// A xorshift generator, to check properties over many arbitrary inputs reproducibly
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+            let len = self.below(max_len + 1);
+            (0..len).map(|_| self.next() as u8).collect()
+        }
+    }
+
+    #[test]
+    fn signatures_only_verify_the_signed_body() {
+        let mut rng = Rng(0x5eed_cafe_f00d_d00d);
+        for _ in 0..500 {
+            // Keys up to two blocks long, so both short and hashed keys are covered
+            let key = rng.bytes(2 * BLOCK_SIZE);
+            let body = rng.bytes(300);
+            let algorithm = [Algorithm::Sha1, Algorithm::Sha256][rng.below(2)];
+
+            let value = Signature::new(algorithm, &key, &body).to_string();
+            let signature = Signature::parse(&value).unwrap();
+            assert_eq!(signature.algorithm, algorithm);
+            assert!(signature.verify(&key, &body));
+            assert_eq!(verify(&key, &body, [value.as_str()]), Ok(()));
+
+            let mut tampered = body.clone();
+            if tampered.is_empty() {
+                tampered.push(0);
+            } else {
+                let i = rng.below(tampered.len());
+                tampered[i] ^= 1 << rng.below(8);
+            }
+            assert_eq!(
+                verify(&key, &tampered, [value.as_str()]),
+                Err(SignatureError::Mismatch(algorithm))
+            );
+
+            // Not just any longer key: short keys are zero padded, so appending zeros is the same key
+            let mut other_key = key.clone();
+            if other_key.is_empty() {
+                other_key.push(1);
+            } else {
+                let i = rng.below(other_key.len());
+                other_key[i] ^= 1 << rng.below(8);
+            }
+            assert!(!signature.verify(&other_key, &body));
+        }
+    }
+
+    #[test]
+    fn constant_time_eq_agrees_with_eq() {
+        let mut rng = Rng(0x0123_4567_89ab_cdef);
+        for _ in 0..1000 {
+            let a = rng.bytes(8);
+            let b = if rng.below(2) == 0 {
+                a.clone()
+            } else {
+                rng.bytes(8)
+            };
+            assert_eq!(constant_time_eq(&a, &b), a == b);
+        }
+    }
+}
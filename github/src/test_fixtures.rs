@@ -0,0 +1,478 @@
+//! Builders for realistic webhook payloads, for use in tests
+//!
+//! Each builder starts from a payload captured from Github and allows the fields that tests
+//! usually care about to be overridden. Builders can produce either the raw json payload, as it
+//! would be delivered by a webhook, or the parsed event.
+//!
+//! ```
+//! use github::test_fixtures::{EventPayload, PullRequestEventBuilder};
+//!
+//! let event = PullRequestEventBuilder::new("opened")
+//!     .number(7)
+//!     .title("Fix the frobnicator")
+//!     .build();
+//! assert_eq!(event.pull_request.number, 7);
+//! ```
+
+use crate::{CheckRunEvent, Event, EventType, IssueCommentEvent, PullRequestEvent, StatusEvent};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+const PULL_REQUEST_EVENT: &str = include_str!("../test-input/pull-request-event.json");
+const CHECK_RUN_EVENT: &str = include_str!("../test-input/check-run-event.json");
+const ISSUE_COMMENT_EVENTS: &str = include_str!("../test-input/issue-comment-event.json");
+const STATUS_EVENT: &str = include_str!("../test-input/status-event.json");
+
+/// A webhook payload produced by one of the builders in this module
+pub trait EventPayload {
+    /// The type of event, as delivered in the `X-GitHub-Event` header
+    const EVENT_TYPE: EventType;
+
+    /// The event type specific payload
+    type Event: DeserializeOwned;
+
+    fn payload(&self) -> &Value;
+
+    /// The json payload, as delivered by a webhook
+    fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self.payload()).unwrap()
+    }
+
+    /// Parse the payload into its event type specific type
+    fn build(&self) -> Self::Event {
+        serde_json::from_value(self.payload().clone()).unwrap()
+    }
+
+    /// Parse the payload into an `Event`
+    fn build_event(&self) -> Event {
+        Event::from_json(Self::EVENT_TYPE, &self.to_json()).unwrap()
+    }
+}
+
+/// Set the value at `path` within `payload`, panicking if an intermediate object doesn't exist
+fn set<V: Into<Value>>(payload: &mut Value, path: &[&str], value: V) {
+    let (last, path) = path.split_last().unwrap();
+    let mut object = payload;
+    for key in path {
+        object = object
+            .get_mut(*key)
+            .unwrap_or_else(|| panic!("missing key '{}' in payload", key));
+    }
+    object[*last] = value.into();
+}
+
+/// A label named `name`, as it appears in payloads and API responses
+pub fn label(name: &str) -> Value {
+    json!({
+        "id": 1,
+        "node_id": "MDU6TGFiZWwx",
+        "url": format!("https://api.github.com/repos/Codertocat/Hello-World/labels/{}", name),
+        "name": name,
+        "description": null,
+        "color": "ededed",
+        "default": false,
+    })
+}
+
+/// The user `login`, as it appears in payloads and API responses
+pub fn user(login: &str) -> Value {
+    json!({
+        "login": login,
+        "id": 1,
+        "node_id": "MDQ6VXNlcjE=",
+        "avatar_url": "",
+        "gravatar_id": "",
+        "url": format!("https://api.github.com/users/{}", login),
+        "html_url": format!("https://github.com/{}", login),
+        "followers_url": "",
+        "following_url": "",
+        "gists_url": "",
+        "starred_url": "",
+        "subscriptions_url": "",
+        "organizations_url": "",
+        "repos_url": "",
+        "events_url": "",
+        "received_events_url": "",
+        "type": "User",
+        "site_admin": false,
+    })
+}
+
+/// Point `repository` (and anything nested in it) at `owner/name`
+fn set_repository(repository: &mut Value, owner: &str, name: &str) {
+    set(repository, &["name"], name);
+    set(repository, &["full_name"], format!("{}/{}", owner, name));
+    set(repository, &["owner", "login"], owner);
+}
+
+/// Builder for `pull_request` events, by default an open PR #2 in `Codertocat/Hello-World`
+#[derive(Clone, Debug)]
+pub struct PullRequestEventBuilder {
+    payload: Value,
+}
+
+impl PullRequestEventBuilder {
+    pub fn new(action: &str) -> Self {
+        let mut payload: Value = serde_json::from_str(PULL_REQUEST_EVENT).unwrap();
+        set(&mut payload, &["action"], action);
+        Self { payload }
+    }
+
+    pub fn number(mut self, number: u64) -> Self {
+        set(&mut self.payload, &["number"], number);
+        set(&mut self.payload, &["pull_request", "number"], number);
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        set(&mut self.payload, &["pull_request", "title"], title);
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        set(&mut self.payload, &["pull_request", "body"], body);
+        self
+    }
+
+    pub fn author(mut self, login: &str) -> Self {
+        set(&mut self.payload, &["pull_request", "user"], user(login));
+        self
+    }
+
+    pub fn sender(mut self, login: &str) -> Self {
+        set(&mut self.payload, &["sender"], user(login));
+        self
+    }
+
+    pub fn head(mut self, git_ref: &str, sha: &str) -> Self {
+        set(&mut self.payload, &["pull_request", "head", "ref"], git_ref);
+        set(&mut self.payload, &["pull_request", "head", "sha"], sha);
+        self
+    }
+
+    pub fn base(mut self, git_ref: &str, sha: &str) -> Self {
+        set(&mut self.payload, &["pull_request", "base", "ref"], git_ref);
+        set(&mut self.payload, &["pull_request", "base", "sha"], sha);
+        self
+    }
+
+    pub fn draft(mut self, draft: bool) -> Self {
+        set(&mut self.payload, &["pull_request", "draft"], draft);
+        self
+    }
+
+    pub fn merged(mut self, merged: bool) -> Self {
+        set(&mut self.payload, &["pull_request", "merged"], merged);
+        self
+    }
+
+    pub fn maintainer_can_modify(mut self, maintainer_can_modify: bool) -> Self {
+        set(
+            &mut self.payload,
+            &["pull_request", "maintainer_can_modify"],
+            maintainer_can_modify,
+        );
+        self
+    }
+
+    /// Set the labels applied to the PR
+    pub fn labels(mut self, labels: &[&str]) -> Self {
+        let labels = labels.iter().map(|name| label(name)).collect::<Vec<_>>();
+        set(&mut self.payload, &["pull_request", "labels"], labels);
+        self
+    }
+
+    /// Set the users whose review of the PR is still requested
+    pub fn requested_reviewers(mut self, logins: &[&str]) -> Self {
+        let users = logins.iter().map(|login| user(login)).collect::<Vec<_>>();
+        set(
+            &mut self.payload,
+            &["pull_request", "requested_reviewers"],
+            users,
+        );
+        self
+    }
+
+    /// Set the label which was added or removed, for `labeled` and `unlabeled` events
+    pub fn label(mut self, name: &str) -> Self {
+        set(&mut self.payload, &["label"], label(name));
+        self
+    }
+
+    /// Set the repository the PR is opened against
+    pub fn repository(mut self, owner: &str, name: &str) -> Self {
+        set_repository(&mut self.payload["repository"], owner, name);
+        set_repository(
+            &mut self.payload["pull_request"]["base"]["repo"],
+            owner,
+            name,
+        );
+        self
+    }
+}
+
+impl EventPayload for PullRequestEventBuilder {
+    const EVENT_TYPE: EventType = EventType::PullRequest;
+    type Event = PullRequestEvent;
+
+    fn payload(&self) -> &Value {
+        &self.payload
+    }
+}
+
+/// Builder for `check_run` events, by default a completed, successful check run
+#[derive(Clone, Debug)]
+pub struct CheckRunEventBuilder {
+    payload: Value,
+}
+
+impl CheckRunEventBuilder {
+    pub fn new(action: &str) -> Self {
+        let mut payload: Value = serde_json::from_str(CHECK_RUN_EVENT).unwrap();
+        set(&mut payload, &["action"], action);
+        Self { payload }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        set(&mut self.payload, &["check_run", "name"], name);
+        self
+    }
+
+    pub fn head_sha(mut self, sha: &str) -> Self {
+        set(&mut self.payload, &["check_run", "head_sha"], sha);
+        set(
+            &mut self.payload,
+            &["check_run", "check_suite", "head_sha"],
+            sha,
+        );
+        self
+    }
+
+    /// Set the check run's status, e.g. `queued`, `in_progress` or `completed`
+    pub fn status(mut self, status: &str) -> Self {
+        set(&mut self.payload, &["check_run", "status"], status);
+        self
+    }
+
+    /// Set the check run's conclusion, e.g. `success` or `failure`
+    pub fn conclusion(mut self, conclusion: Option<&str>) -> Self {
+        set(&mut self.payload, &["check_run", "conclusion"], conclusion);
+        self
+    }
+
+    pub fn details_url(mut self, url: &str) -> Self {
+        set(&mut self.payload, &["check_run", "details_url"], url);
+        self
+    }
+
+    /// Set the slug of the app which created the check run
+    pub fn app(mut self, slug: &str) -> Self {
+        set(&mut self.payload, &["check_run", "app", "slug"], slug);
+        set(
+            &mut self.payload,
+            &["check_run", "check_suite", "app", "slug"],
+            slug,
+        );
+        self
+    }
+
+    pub fn check_suite_id(mut self, id: u64) -> Self {
+        set(&mut self.payload, &["check_run", "check_suite", "id"], id);
+        self
+    }
+
+    pub fn repository(mut self, owner: &str, name: &str) -> Self {
+        set_repository(&mut self.payload["repository"], owner, name);
+        self
+    }
+}
+
+impl EventPayload for CheckRunEventBuilder {
+    const EVENT_TYPE: EventType = EventType::CheckRun;
+    type Event = CheckRunEvent;
+
+    fn payload(&self) -> &Value {
+        &self.payload
+    }
+}
+
+/// Builder for `issue_comment` events, by default a comment created on a PR
+#[derive(Clone, Debug)]
+pub struct IssueCommentEventBuilder {
+    payload: Value,
+}
+
+impl IssueCommentEventBuilder {
+    pub fn new(action: &str) -> Self {
+        let events: Vec<Value> = serde_json::from_str(ISSUE_COMMENT_EVENTS).unwrap();
+        let mut payload = events.into_iter().next().unwrap();
+        set(&mut payload, &["action"], action);
+        Self { payload }
+    }
+
+    pub fn number(mut self, number: u64) -> Self {
+        set(&mut self.payload, &["issue", "number"], number);
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        set(&mut self.payload, &["comment", "body"], body);
+        self
+    }
+
+    /// Set the user who made the comment
+    pub fn sender(mut self, login: &str) -> Self {
+        set(&mut self.payload, &["sender"], user(login));
+        set(&mut self.payload, &["comment", "user"], user(login));
+        self
+    }
+
+    /// Set the body the comment had before it was edited
+    pub fn previous_body(mut self, body: &str) -> Self {
+        set(
+            &mut self.payload,
+            &["changes"],
+            json!({ "body": { "from": body } }),
+        );
+        self
+    }
+
+    /// Indicate whether the comment was made on a PR, as opposed to an issue
+    pub fn on_pull_request(mut self, on_pull_request: bool) -> Self {
+        let issue = self.payload["issue"].as_object_mut().unwrap();
+        if on_pull_request {
+            issue.insert(
+                "pull_request".to_owned(),
+                json!({
+                    "url": "",
+                    "html_url": "",
+                    "diff_url": "",
+                    "patch_url": "",
+                }),
+            );
+        } else {
+            issue.remove("pull_request");
+        }
+        self
+    }
+
+    pub fn repository(mut self, owner: &str, name: &str) -> Self {
+        set_repository(&mut self.payload["repository"], owner, name);
+        self
+    }
+}
+
+impl EventPayload for IssueCommentEventBuilder {
+    const EVENT_TYPE: EventType = EventType::IssueComment;
+    type Event = IssueCommentEvent;
+
+    fn payload(&self) -> &Value {
+        &self.payload
+    }
+}
+
+/// Builder for `status` events
+#[derive(Clone, Debug)]
+pub struct StatusEventBuilder {
+    payload: Value,
+}
+
+impl StatusEventBuilder {
+    /// Create a status event for `context` with the given `state`, e.g. `success` or `failure`
+    pub fn new(context: &str, state: &str) -> Self {
+        let mut payload: Value = serde_json::from_str(STATUS_EVENT).unwrap();
+        set(&mut payload, &["context"], context);
+        set(&mut payload, &["state"], state);
+        Self { payload }
+    }
+
+    pub fn sha(mut self, sha: &str) -> Self {
+        set(&mut self.payload, &["sha"], sha);
+        self
+    }
+
+    pub fn target_url(mut self, url: Option<&str>) -> Self {
+        set(&mut self.payload, &["target_url"], url);
+        self
+    }
+
+    pub fn repository(mut self, owner: &str, name: &str) -> Self {
+        set_repository(&mut self.payload["repository"], owner, name);
+        self
+    }
+}
+
+impl EventPayload for StatusEventBuilder {
+    const EVENT_TYPE: EventType = EventType::Status;
+    type Event = StatusEvent;
+
+    fn payload(&self) -> &Value {
+        &self.payload
+    }
+}
+
+/// A small xorshift generator, for checking properties over many arbitrary inputs reproducibly
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed since xorshift produces poor initial values from small seeds, and
+        // gets stuck at 0
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in the range [0, n)
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Up to `max_len` arbitrary bytes
+    pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.below(max_len + 1);
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Event, PullRequestEventAction};
+
+    #[test]
+    fn builders() {
+        let event = PullRequestEventBuilder::new("labeled")
+            .number(42)
+            .labels(&["bors-squash"])
+            .label("bors-squash")
+            .repository("bors-rs", "bors")
+            .build();
+        assert!(matches!(event.action, PullRequestEventAction::Labeled));
+        assert_eq!(event.number, 42);
+        assert_eq!(event.pull_request.labels[0].name, "bors-squash");
+        assert_eq!(event.label.unwrap().name, "bors-squash");
+        assert_eq!(event.repository.full_name, "bors-rs/bors");
+
+        let event = CheckRunEventBuilder::new("completed")
+            .name("ci")
+            .conclusion(Some("failure"))
+            .build();
+        assert_eq!(event.check_run.name, "ci");
+
+        let event = IssueCommentEventBuilder::new("created")
+            .body("/land")
+            .on_pull_request(true)
+            .build_event();
+        assert!(matches!(event, Event::IssueComment(e) if e.issue.is_pull_request()));
+
+        let event = StatusEventBuilder::new("ci/jenkins", "success").build();
+        assert_eq!(event.context, "ci/jenkins");
+    }
+}
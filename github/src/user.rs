@@ -1,14 +1,14 @@
 use super::{DateTime, NodeId};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub enum UserType {
     Bot,
     Organization,
     User,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct User {
     pub login: String,
     pub id: u64,
@@ -31,42 +31,58 @@ pub struct User {
     pub site_admin: bool,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Team {
-    id: u64,
-    node_id: NodeId,
-    url: String,
-    html_url: String,
-    name: String,
-    slug: String,
-    description: Option<String>,
-    privacy: String,
-    permission: String,
-    members_url: String,
-    repositories_url: String,
-    parent: Option<Box<Team>>,
+    pub id: u64,
+    pub node_id: NodeId,
+    pub url: String,
+    pub html_url: String,
+    pub name: String,
+    pub slug: String,
+    pub description: Option<String>,
+    pub privacy: String,
+    pub permission: String,
+    pub members_url: String,
+    pub repositories_url: String,
+    pub parent: Option<Box<Team>>,
+}
+
+/// The organization included in the payload of organization-level webhooks
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Organization {
+    pub login: String,
+    pub id: u64,
+    pub node_id: NodeId,
+    pub url: String,
+    pub description: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Pusher {
-    name: String,
-    email: String,
+    pub name: String,
+    /// Missing for pushes by users who keep their email private
+    pub email: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Key {
-    id: u64,
-    key: String,
-    url: String,
-    title: String,
-    read_only: bool,
-    created_at: DateTime,
-    verified: bool,
+    pub id: u64,
+    pub key: String,
+    pub url: String,
+    pub title: String,
+    pub read_only: bool,
+    pub created_at: DateTime,
+    pub verified: bool,
+    /// Login of the user who added the key, if known
+    pub added_by: Option<String>,
+    pub last_used: Option<DateTime>,
+    /// Deploy keys of organizations which don't allow them are disabled
+    pub enabled: Option<bool>,
 }
 
 #[cfg(test)]
 mod test {
-    use super::{User, UserType};
+    use super::{Key, User, UserType};
 
     #[test]
     fn user() {
@@ -95,6 +111,48 @@ mod test {
 
         let user: User = serde_json::from_str(USER_JSON).unwrap();
         assert_eq!(user.user_type, UserType::User);
+        assert_eq!(
+            serde_json::to_value(&user).unwrap(),
+            serde_json::from_str::<serde_json::Value>(USER_JSON).unwrap()
+        );
+    }
+
+    #[test]
+    fn key() {
+        const KEY_JSON: &str = r#"
+            {
+                "id": 1,
+                "key": "ssh-rsa AAA...",
+                "url": "https://api.github.com/repos/octocat/Hello-World/keys/1",
+                "title": "octocat@octomac",
+                "read_only": true,
+                "created_at": "2014-12-10T15:53:42Z",
+                "verified": true,
+                "added_by": "octocat",
+                "last_used": "2022-01-10T15:53:42Z",
+                "enabled": true
+            }
+        "#;
+
+        let key: Key = serde_json::from_str(KEY_JSON).unwrap();
+        assert!(key.read_only);
+        assert_eq!(
+            serde_json::to_value(&key).unwrap(),
+            serde_json::from_str::<serde_json::Value>(KEY_JSON).unwrap()
+        );
+
+        // Keys of older payloads don't report who added them or when they were last used
+        let key: Key = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "key": "ssh-rsa AAA...",
+            "url": "https://api.github.com/repos/octocat/Hello-World/keys/1",
+            "title": "octocat@octomac",
+            "read_only": false,
+            "created_at": "2014-12-10T15:53:42Z",
+            "verified": true,
+        }))
+        .unwrap();
+        assert!(key.last_used.is_none());
     }
 
     #[test]
@@ -1,5 +1,5 @@
-use super::{Event, EventType};
-use log::{trace, warn};
+use super::{signature, Event, EventType};
+use log::warn;
 
 /// The GitHub header key used to pass the event type
 ///
@@ -11,35 +11,40 @@ pub const EVENT_TYPE_HEADER: &str = "X-Github-Event";
 /// Github API docs: https://developer.github.com/webhooks/#delivery-headers
 pub const DELIVERY_ID_HEADER: &str = "X-Github-Delivery";
 
-/// The GitHub header key used to pass the HMAC hexdigest
+/// The GitHub header key used to pass the HMAC-SHA1 hexdigest
 ///
 /// Github API docs: https://developer.github.com/webhooks/#delivery-headers
 pub const SIGNATURE_HEADER: &str = "X-Hub-Signature";
 
+/// The GitHub header key used to pass the HMAC-SHA256 hexdigest
+///
+/// Github API docs: https://developer.github.com/webhooks/#delivery-headers
+pub const SIGNATURE_256_HEADER: &str = "X-Hub-Signature-256";
+
 #[derive(Clone, Debug)]
 pub struct Webhook {
     pub event_type: EventType,
     pub delivery_id: String,
-    pub signature: Option<String>,
+    /// Values of every `SIGNATURE_HEADER` and `SIGNATURE_256_HEADER` the webhook was delivered with
+    pub signatures: Vec<String>,
     pub body: Vec<u8>,
 }
 
 impl Webhook {
     pub fn check_signature(&self, key: Option<&[u8]>) -> bool {
-        match (key, &self.signature) {
-            (Some(key), Some(signature)) if signature.starts_with("sha1=") => {
-                let hash = hex::encode(hmacsha1::hmac_sha1(key, &self.body));
-                let signature = &signature["sha1=".len()..];
-
-                trace!("hash: {}", hash);
-                trace!("sig:  {}", signature);
-                hash == signature
+        match key {
+            Some(key) => {
+                let signatures = self.signatures.iter().map(String::as_str);
+                match signature::verify(key, &self.body, signatures) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        warn!("Delivery {}: {}", self.delivery_id, e);
+                        false
+                    }
+                }
             }
-            // We are expecting a signature and we either recieved it in a different format than
-            // expected or no signature was sent.
-            (Some(_), _) => false,
             // No key or signature to check
-            (None, _) => {
+            None => {
                 warn!("No secret specified; signature ignored");
                 true
             }
@@ -1,7 +1,7 @@
 use crate::{CheckStatus, Conclusion, DateTime, EventType, NodeId, Oid};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Workflow {
     pub id: u64,
     pub node_id: NodeId,
@@ -15,7 +15,7 @@ pub struct Workflow {
     pub badge_url: String,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct WorkflowRun {
     pub id: u64,
     pub name: String,